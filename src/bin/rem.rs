@@ -4,9 +4,14 @@
 //! and dispatches to the appropriate subcommand handler.
 
 use clap::{CommandFactory, Parser};
-use clap_complete::{generate, Shell};
+use clap_complete::{Shell, generate};
 use rempower::cli::{Cli, Commands};
-use rempower::subcommands::dns::perform;
+use rempower::subcommands::{
+    airdrop, appearance, arp, audio, awake, battery, bluetooth, captive, dhcp, disk, display, dmg, dns, find, firewall,
+    hostname, hosts, hotspot, input, ip, keyboard, location, lock, mdns, mtu, net, netaudit, nettraffic, ping, ports,
+    portscan, power, proxy, ramdisk, route, screenshot, share, shred, speedtest, spotlight, ssh, storage, tags,
+    thermals, trace, trash, usb, wallpaper, wifi, wol, xattr,
+};
 use std::error::Error;
 use std::io;
 
@@ -22,12 +27,176 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     match cli.command {
         Commands::Dns(args) => {
-            perform(args)?;
+            dns::perform(*args)?;
+        }
+        Commands::Hosts(args) => {
+            hosts::perform(args)?;
+        }
+        Commands::Wifi(args) => {
+            wifi::perform(args)?;
+        }
+        Commands::Location(args) => {
+            location::perform(args)?;
+        }
+        Commands::Proxy(args) => {
+            proxy::perform(args)?;
+        }
+        Commands::Ip(args) => {
+            ip::perform(args)?;
+        }
+        Commands::Hostname(args) => {
+            hostname::perform(args)?;
+        }
+        Commands::Speedtest(args) => {
+            speedtest::perform(args)?;
+        }
+        Commands::Portscan(args) => {
+            portscan::perform(args)?;
+        }
+        Commands::Ping(args) => {
+            ping::perform(args)?;
+        }
+        Commands::Firewall(args) => {
+            firewall::perform(args)?;
+        }
+        Commands::Arp(args) => {
+            arp::perform(args)?;
+        }
+        Commands::Netstat(args) => {
+            nettraffic::perform(args)?;
+        }
+        Commands::Captive(args) => {
+            captive::perform(args)?;
+        }
+        Commands::Trace(args) => {
+            trace::perform(args)?;
+        }
+        Commands::Ports(args) => {
+            ports::perform(args)?;
+        }
+        Commands::Dhcp(args) => {
+            dhcp::perform(args)?;
+        }
+        Commands::Net(args) => {
+            net::perform(args)?;
+        }
+        Commands::Wol(args) => {
+            wol::perform(args)?;
+        }
+        Commands::Route(args) => {
+            route::perform(args)?;
+        }
+        Commands::Share(args) => {
+            share::perform(args)?;
+        }
+        Commands::Mtu(args) => {
+            mtu::perform(args)?;
+        }
+        Commands::Ssh(args) => {
+            ssh::perform(args)?;
+        }
+        Commands::Mdns(args) => {
+            mdns::perform(args)?;
+        }
+        Commands::Netaudit(args) => {
+            netaudit::perform(args)?;
+        }
+        Commands::Hotspot(args) => {
+            hotspot::perform(args)?;
+        }
+        Commands::Awake(args) => {
+            awake::perform(args)?;
+        }
+        Commands::Battery(args) => {
+            battery::perform(args)?;
+        }
+        Commands::Power(args) => {
+            power::perform(args)?;
+        }
+        Commands::Thermals(args) => {
+            thermals::perform(args)?;
+        }
+        Commands::Appearance(args) => {
+            appearance::perform(args)?;
+        }
+        Commands::Display(args) => {
+            display::perform(args)?;
+        }
+        Commands::Screenshot(args) => {
+            screenshot::perform(args)?;
+        }
+        Commands::Volume(args) => {
+            audio::volume::perform(args)?;
+        }
+        Commands::Audio(args) => {
+            audio::perform(args)?;
+        }
+        Commands::Wallpaper(args) => {
+            wallpaper::perform(args)?;
+        }
+        Commands::Lock(args) => {
+            lock::perform(args)?;
+        }
+        Commands::Screensaver(args) => {
+            lock::screensaver(args)?;
+        }
+        Commands::Bluetooth(args) => {
+            bluetooth::perform(args)?;
+        }
+        Commands::Keyboard(args) => {
+            keyboard::perform(args)?;
+        }
+        Commands::Input(args) => {
+            input::perform(args)?;
+        }
+        Commands::Usb(args) => {
+            usb::perform(args)?;
+        }
+        Commands::Airdrop(args) => {
+            airdrop::perform(args)?;
+        }
+        Commands::DiskUsage(args) => {
+            storage::disk_usage(args)?;
+        }
+        Commands::BigFiles(args) => {
+            storage::bigfiles::perform(args)?;
+        }
+        Commands::Dupes(args) => {
+            storage::dupes::perform(args)?;
+        }
+        Commands::Clean(args) => {
+            storage::clean::perform(args)?;
+        }
+        Commands::Shred(args) => {
+            shred::perform(args)?;
+        }
+        Commands::Trash(args) => {
+            trash::perform(args)?;
+        }
+        Commands::Disk(args) => {
+            disk::perform(args)?;
+        }
+        Commands::Dmg(args) => {
+            dmg::perform(args)?;
+        }
+        Commands::Ramdisk(args) => {
+            ramdisk::perform(args)?;
+        }
+        Commands::Spotlight(args) => {
+            spotlight::perform(args)?;
+        }
+        Commands::Find(args) => {
+            find::perform(args)?;
+        }
+        Commands::Tags(args) => {
+            tags::perform(args)?;
+        }
+        Commands::Xattr(args) => {
+            xattr::perform(args)?;
         }
         Commands::Completions { shell } => {
             generate_completions(shell);
         }
-
     }
 
     Ok(())
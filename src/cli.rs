@@ -3,15 +3,18 @@
 //! This module defines the CLI structure using clap's derive macros.
 //! It includes the root command parser and all subcommand definitions.
 
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Package version from Cargo.toml
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Generates the about text with version information
 fn get_about() -> String {
-    format!("Rust empowered tools v{}", VERSION)
+    format!("Rust empowered tools v{VERSION}")
 }
 
 /// Root CLI structure
@@ -35,8 +38,121 @@ pub struct Cli {
 /// Each variant represents a different tool or utility provided by rempower.
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Switch between public DNS servers and those assigned by the DHCP server
-    Dns(DnsArgs),
+    /// Switch between public, custom and DHCP-assigned DNS servers
+    Dns(Box<DnsArgs>),
+    /// Manage entries of the hosts file in named blocks
+    Hosts(HostsArgs),
+    /// Scan for and manage Wi-Fi networks
+    Wifi(WifiArgs),
+    /// List, switch and manage network locations
+    Location(LocationArgs),
+    /// Configure web, secure web and SOCKS proxies and proxy auto-configuration
+    Proxy(ProxyArgs),
+    /// Show local addresses, default gateways and the public IP address
+    Ip(IpArgs),
+    /// Show or set the computer name, local host name and host name
+    Hostname(HostnameArgs),
+    /// Measure download and upload throughput, latency and responsiveness
+    Speedtest(SpeedtestArgs),
+    /// Scan a host for open TCP ports
+    Portscan(PortscanArgs),
+    /// Ping a host continuously with rolling latency and loss statistics
+    Ping(PingArgs),
+    /// Manage the application firewall
+    Firewall(FirewallArgs),
+    /// List the neighbor table (ARP and NDP cache) with vendors of the devices
+    Arp(ArpArgs),
+    /// Show the traffic of the network interfaces or monitor their bandwidth
+    Netstat(NetstatArgs),
+    /// Detect a captive portal and open it in the browser
+    Captive(CaptiveArgs),
+    /// Trace the route to a host with host names and autonomous systems of the hops
+    Trace(TraceArgs),
+    /// List listening sockets and connections with their processes
+    Ports(PortsArgs),
+    /// Show or renew the DHCP lease of an interface
+    Dhcp(DhcpArgs),
+    /// Diagnose the network connection
+    Net(NetArgs),
+    /// Wake up a host with a Wake-on-LAN magic packet
+    Wol(WolArgs),
+    /// Show the routing table and add or delete static routes
+    Route(RouteArgs),
+    /// Mount and unmount SMB and AFP network shares
+    Share(ShareArgs),
+    /// Show and set the MTU of an interface and discover the path MTU to a host
+    Mtu(MtuArgs),
+    /// Manage SSH host entries and known host keys
+    Ssh(SshArgs),
+    /// Advertise Bonjour services on the local network
+    Mdns(MdnsArgs),
+    /// Show outbound connections grouped by application
+    Netaudit(NetauditArgs),
+    /// Show and switch Internet Sharing
+    Hotspot(HotspotArgs),
+    /// Keep the Mac awake for a time, while a command runs or until Ctrl-C
+    Awake(AwakeArgs),
+    /// Show battery charge, health, cycle count, temperature and charger
+    Battery(BatteryArgs),
+    /// Manage power settings and the sleep and wake schedule
+    Power(PowerArgs),
+    /// Show the thermal pressure, CPU speed limit and fan speeds
+    Thermals(ThermalsArgs),
+    /// Switch between dark and light mode, also at sunset and sunrise
+    Appearance(AppearanceArgs),
+    /// Show connected displays and switch True Tone and automatic brightness
+    Display(DisplayArgs),
+    /// Take screenshots and configure where and how they are saved
+    Screenshot(ScreenshotArgs),
+    /// Show and change the output volume and mute
+    Volume(VolumeArgs),
+    /// List and switch the audio devices
+    Audio(AudioArgs),
+    /// Show and change the desktop picture of each display
+    Wallpaper(WallpaperArgs),
+    /// Lock the screen and set when the password is required
+    Lock(LockArgs),
+    /// Start the screen saver
+    Screensaver(ScreensaverArgs),
+    /// Turn Bluetooth on and off and connect paired devices
+    #[command(name = "bt")]
+    Bluetooth(BluetoothArgs),
+    /// Set the keyboard backlight, key repeat and modifier keys
+    Keyboard(KeyboardArgs),
+    /// Show and change the trackpad and mouse settings
+    Input(InputArgs),
+    /// List the connected USB devices and watch them come and go
+    Usb(UsbArgs),
+    /// Show and switch who can find this Mac with AirDrop
+    Airdrop(AirdropArgs),
+    /// Show what takes up disk space and browse the largest folders
+    #[command(name = "du")]
+    DiskUsage(DiskUsageArgs),
+    /// Find large and old files and select which to move to the Trash
+    #[command(name = "bigfiles")]
+    BigFiles(BigFilesArgs),
+    /// Find duplicate files and replace them with APFS clones or hard links
+    Dupes(DupesArgs),
+    /// Delete caches, logs and build files that are recreated when needed
+    Clean(CleanArgs),
+    /// Overwrite files before deleting them, or purge the purgeable space
+    Shred(ShredArgs),
+    /// List, empty and restore the Trash and move files to it
+    Trash(TrashArgs),
+    /// Show the health of the drives and mount, unmount and eject volumes
+    Disk(DiskArgs),
+    /// Create, attach and detach disk images, optionally encrypted
+    Dmg(DmgArgs),
+    /// Create and destroy RAM disks, optionally created again at every login
+    Ramdisk(RamdiskArgs),
+    /// Show and manage Spotlight indexing and its excluded folders
+    Spotlight(SpotlightArgs),
+    /// Search files with Spotlight using filters like kind:pdf or modified:<7d
+    Find(FindArgs),
+    /// List, add and remove Finder tags of files and find the files with a tag
+    Tags(TagsArgs),
+    /// Show extended attributes, remove the quarantine of downloads and copy attributes
+    Xattr(XattrArgs),
     /// Generate shell completions
     Completions {
         /// The shell to generate completions for
@@ -48,19 +164,3075 @@ pub enum Commands {
 /// Arguments for the DNS subcommand
 ///
 /// Provides options to configure DNS settings on macOS network interfaces.
-/// Only one option can be specified at a time.
+/// Exactly one action option must be specified, unless a DNS subcommand is used instead.
+/// Actions can be restricted to specific network services with `--interface`.
 #[derive(Parser)]
-#[group(required = true, multiple = false)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    group(ArgGroup::new("action").required(true).multiple(false)),
+    group(ArgGroup::new("servers").args(["pub_dns", "set", "profile"]).multiple(false))
+)]
+#[group(skip)]
 pub struct DnsArgs {
-    /// Enable CloudFlare and Google DNS servers
-    #[arg(long = "pub")]
-    pub pub_dns: bool,
+    /// DNS subcommand to execute
+    #[command(subcommand)]
+    pub command: Option<DnsCommands>,
+
+    /// Enable public DNS servers of a provider (CloudFlare and Google by default, see `rem dns providers`)
+    #[arg(
+        long = "pub",
+        group = "action",
+        value_name = "PROVIDER",
+        num_args = 0..=1,
+        default_missing_value = "default"
+    )]
+    pub pub_dns: Option<String>,
+
+    /// Use the given DNS servers (IPv4 or IPv6 addresses)
+    #[arg(long = "set", group = "action", value_name = "SERVER", num_args = 1..)]
+    pub set: Option<Vec<IpAddr>>,
+
+    /// Use the DNS servers of a profile from the configuration file
+    #[arg(long, group = "action", value_name = "NAME")]
+    pub profile: Option<String>,
 
     /// Revert to DNS servers assigned by the DHCP server
-    #[arg(long = "dhcp")]
+    #[arg(long = "dhcp", group = "action")]
     pub dhcp: bool,
 
+    /// Restore the DNS servers that were configured before the first change since the last restore
+    #[arg(long, group = "action")]
+    pub restore: bool,
+
+    /// Set DNS search domains
+    #[arg(long, group = "action", value_name = "DOMAIN", num_args = 1.., value_parser = parse_domain)]
+    pub set_search_domains: Option<Vec<String>>,
+
+    /// Remove manually configured DNS search domains
+    #[arg(long, group = "action")]
+    pub clear_search_domains: bool,
+
     /// List active DNS servers
-    #[arg(short,long)]
+    #[arg(short, long, group = "action")]
+    pub list: bool,
+
+    /// Print the list as JSON
+    #[arg(long, requires = "list", conflicts_with = "yaml")]
+    pub json: bool,
+
+    /// Print the list as YAML
+    #[arg(long, requires = "list")]
+    pub yaml: bool,
+
+    /// Flush the DNS cache
+    #[arg(long, group = "action")]
+    pub flush: bool,
+
+    /// Probe the new DNS servers first and abort if one is unreachable (`warn`: only report them)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "abort",
+        requires = "servers"
+    )]
+    pub check: Option<CheckMode>,
+
+    /// Print the commands that would change the configuration instead of running them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Restrict the operation to the given network service (can be repeated)
+    #[arg(short, long = "interface", value_name = "NAME")]
+    pub interfaces: Vec<String>,
+
+    /// Exclude network services matching a glob (`Tailscale*`) or a regex (`/vpn$/`) (can be repeated)
+    #[arg(short = 'x', long = "exclude", value_name = "PATTERN")]
+    pub excludes: Vec<String>,
+}
+
+/// Available DNS subcommands
+#[derive(Subcommand)]
+pub enum DnsCommands {
+    /// Manage named DNS server profiles
+    Profiles {
+        /// The profile operation to execute
+        #[command(subcommand)]
+        command: ProfilesCommands,
+    },
+    /// Generate an encrypted DNS (DoH/DoT) configuration profile
+    Doh(DohArgs),
+    /// List the built-in public DNS providers
+    Providers,
+    /// Look up DNS records
+    Query(QueryArgs),
+    /// Look up the host names of IP addresses (PTR records)
+    Reverse(ReverseArgs),
+    /// Test which upstream resolvers answer DNS queries and detect VPN DNS leaks
+    Leaktest,
+    /// Test the active resolvers for EDNS, DNSSEC validation, QNAME minimization and DoT
+    Probe,
+    /// Show mDNSResponder cache, resolver and query statistics (requires sudo)
+    CacheStats,
+    /// Switch DNS servers automatically at fixed times of the day
+    Schedule(ScheduleArgs),
+    /// Print the DNS servers and search domains of all network services as JSON
+    Export,
+    /// Apply DNS servers and search domains from a file written by `rem dns export`
+    Import(ImportArgs),
+    /// Apply DNS presets automatically depending on the Wi-Fi network or network location
+    Auto(AutoArgs),
+    /// Send queries for specific domains to dedicated DNS servers via /etc/resolver
+    Split(SplitArgs),
+}
+
+/// Arguments for split DNS management
+///
+/// Each domain is configured by a file in the resolver directory, which macOS uses
+/// to resolve names below that domain with the listed DNS servers.
+#[derive(Parser)]
+pub struct SplitArgs {
+    /// Split DNS operation to execute
+    #[command(subcommand)]
+    pub command: SplitCommands,
+
+    /// Resolver directory to manage
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "/etc/resolver",
+        global = true,
+        hide = true
+    )]
+    pub dir: PathBuf,
+}
+
+/// Split DNS operations
+#[derive(Subcommand)]
+pub enum SplitCommands {
+    /// List all domains with dedicated DNS servers
+    List,
+    /// Resolve a domain with dedicated DNS servers
+    Add {
+        /// Domain whose names are resolved by the servers, e.g. corp.example.com
+        #[arg(value_parser = parse_domain)]
+        domain: String,
+
+        /// IPv4 or IPv6 addresses of the DNS servers
+        #[arg(required = true, value_name = "SERVER")]
+        servers: Vec<IpAddr>,
+
+        /// Port of the DNS servers
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    /// Remove the dedicated DNS servers of a domain
+    Remove {
+        /// Domain to remove
+        #[arg(value_parser = parse_domain)]
+        domain: String,
+
+        /// Also remove resolver files not created by rempower
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Arguments for automatic DNS rules
+///
+/// Without options, the first rule matching the current network is applied once.
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct AutoArgs {
+    /// Rule operation to execute
+    #[command(subcommand)]
+    pub command: Option<AutoCommands>,
+
+    /// Add a rule with this condition: `ssid=<NAME>` or `location=<NAME>` (globs and /regex/ allowed)
+    #[arg(long, value_name = "CONDITION", requires = "preset", conflicts_with_all = ["watch", "dry_run"])]
+    pub when: Option<String>,
+
+    /// DNS preset of the new rule: `pub`, `pub:<PROVIDER>`, `profile:<NAME>` or `dhcp`
+    #[arg(long = "use", value_name = "PRESET", requires = "when")]
+    pub preset: Option<String>,
+
+    /// Keep running and apply the matching rule whenever the network changes
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Seconds between network checks in watch mode
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub interval: u64,
+
+    /// Print the commands that would change the configuration instead of running them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Rule operations
+#[derive(Subcommand)]
+pub enum AutoCommands {
+    /// List all rules in the order they are evaluated
+    List,
+    /// Remove a rule
+    Remove {
+        /// Number of the rule as shown by `rem dns auto list`
+        number: usize,
+    },
+}
+
+/// Arguments for importing a DNS configuration
+#[derive(Parser)]
+pub struct ImportArgs {
+    /// File written by `rem dns export`, or `-` for stdin
+    pub file: PathBuf,
+
+    /// Print the commands that would change the configuration instead of running them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Arguments for scheduling DNS changes
+///
+/// Each schedule entry consists of `rem dns` action options followed by `--at HH:MM`.
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct ScheduleArgs {
+    /// Schedule operation to execute
+    #[command(subcommand)]
+    pub command: Option<ScheduleCommands>,
+
+    /// DNS actions, each followed by the time it runs, e.g. `--pub quad9 --at 09:00 --dhcp --at 18:00`
+    #[arg(
+        required = true,
+        num_args = 1..,
+        allow_hyphen_values = true,
+        trailing_var_arg = true,
+        value_name = "ACTION --at HH:MM"
+    )]
+    pub entries: Vec<String>,
+}
+
+/// Schedule operations
+#[derive(Subcommand)]
+pub enum ScheduleCommands {
+    /// List scheduled DNS changes
+    List,
+    /// Remove scheduled DNS changes
+    Remove {
+        /// Time of the scheduled change to remove (HH:MM)
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        time: Option<String>,
+        /// Remove all scheduled DNS changes
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+/// Arguments for generating encrypted DNS configuration profiles
+#[derive(Parser)]
+pub struct DohArgs {
+    /// Encrypted DNS provider (see `rem dns providers`)
+    #[arg(long, required_unless_present = "remove")]
+    pub provider: Option<String>,
+
+    /// Encrypted DNS protocol
+    #[arg(long, value_enum, default_value_t = DohProtocol::Https)]
+    pub protocol: DohProtocol,
+
+    /// Write the configuration profile to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Open the configuration profile for installation in System Settings
+    #[arg(long)]
+    pub install: bool,
+
+    /// Remove a previously installed encrypted DNS configuration profile
+    #[arg(long, conflicts_with_all = ["provider", "output", "install"])]
+    pub remove: bool,
+}
+
+/// Arguments for DNS lookups
+#[derive(Parser)]
+pub struct QueryArgs {
+    /// Domain name to look up
+    pub name: String,
+
+    /// Record type, e.g. A, AAAA, MX, TXT, NS, CNAME, SOA, SRV, PTR, CAA or TYPE<n>
+    #[arg(short = 't', long = "type", value_name = "TYPE", default_value = "A")]
+    pub record_type: String,
+
+    /// DNS server to query (defaults to the first system DNS server)
+    #[arg(short, long, value_name = "SERVER")]
+    pub server: Option<IpAddr>,
+
+    /// Timeout in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 3000)]
+    pub timeout: u64,
+}
+
+/// Arguments for reverse DNS lookups
+#[derive(Parser)]
+pub struct ReverseArgs {
+    /// IP addresses to look up; read from stdin (one per line) if omitted
+    #[arg(value_name = "ADDRESS")]
+    pub addresses: Vec<IpAddr>,
+
+    /// DNS server to query (defaults to the first system DNS server)
+    #[arg(short, long, value_name = "SERVER")]
+    pub server: Option<IpAddr>,
+
+    /// Timeout in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 3000)]
+    pub timeout: u64,
+
+    /// Number of lookups running in parallel
+    #[arg(short, long, value_name = "N", default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the hosts subcommand
+///
+/// Entries added by rempower are kept in managed blocks named by a tag, so they can
+/// be updated, disabled or removed without touching other entries.
+#[derive(Parser)]
+pub struct HostsArgs {
+    /// Hosts operation to execute
+    #[command(subcommand)]
+    pub command: HostsCommands,
+
+    /// Hosts file to manage
+    #[arg(long, value_name = "PATH", default_value = "/etc/hosts", global = true, hide = true)]
+    pub file: PathBuf,
+}
+
+/// Hosts operations
+#[derive(Subcommand)]
+pub enum HostsCommands {
+    /// List all entries, grouped by managed block
+    List {
+        /// Only list the block with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+    },
+    /// Add an entry to a managed block, replacing existing mappings of its hostnames
+    Add {
+        /// IP address of the entry
+        ip: IpAddr,
+        /// Hostnames mapped to the address
+        #[arg(required = true, value_name = "HOSTNAME", value_parser = parse_domain)]
+        hostnames: Vec<String>,
+        /// Tag of the managed block
+        #[arg(short, long, default_value = "default")]
+        tag: String,
+    },
+    /// Remove hostnames from managed blocks, or a whole block with --tag
+    Remove {
+        /// Hostnames to remove
+        #[arg(value_name = "HOSTNAME", required_unless_present = "tag")]
+        hostnames: Vec<String>,
+        /// Only remove from the block with this tag; removes the whole block if no hostnames are given
+        #[arg(short, long)]
+        tag: Option<String>,
+    },
+    /// Enable the entries of a managed block
+    Enable {
+        /// Tag of the managed block
+        tag: String,
+    },
+    /// Disable the entries of a managed block by commenting them out
+    Disable {
+        /// Tag of the managed block
+        tag: String,
+    },
+}
+
+/// Handling of unreachable DNS servers found by `--check`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CheckMode {
+    /// Abort without changing anything
+    Abort,
+    /// Report unreachable servers and apply the change anyway
+    Warn,
+}
+
+/// Encrypted DNS protocols supported by macOS
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DohProtocol {
+    /// DNS over HTTPS
+    Https,
+    /// DNS over TLS
+    Tls,
+}
+
+/// Operations on named DNS server profiles
+#[derive(Subcommand)]
+pub enum ProfilesCommands {
+    /// List all configured profiles
+    List,
+    /// Add a profile or replace an existing one
+    Add {
+        /// Name of the profile
+        name: String,
+        /// DNS servers of the profile (IPv4 or IPv6 addresses)
+        #[arg(required = true, value_name = "SERVER")]
+        servers: Vec<IpAddr>,
+    },
+    /// Remove a profile
+    Remove {
+        /// Name of the profile
+        name: String,
+    },
+}
+
+/// Arguments for the proxy subcommand
+///
+/// Without an option, the proxy settings are listed. Proxy options can be combined;
+/// proxies that are not given keep their current setting.
+#[derive(Parser)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    group(ArgGroup::new("action").multiple(false))
+)]
+pub struct ProxyArgs {
+    /// Proxy subcommand to execute
+    #[command(subcommand)]
+    pub command: Option<ProxyCommands>,
+
+    /// Proxies to configure
+    #[command(flatten)]
+    pub servers: ProxyServers,
+
+    /// Disable all proxies and proxy auto-configuration
+    #[arg(long, group = "action", conflicts_with_all = ["web", "secure_web", "socks", "pac"])]
+    pub off: bool,
+
+    /// Apply the proxies of a preset from the configuration file, disabling all others
+    #[arg(long, group = "action", value_name = "NAME", conflicts_with_all = ["web", "secure_web", "socks", "pac"])]
+    pub preset: Option<String>,
+
+    /// List the proxy settings (default)
+    #[arg(short, long, group = "action", conflicts_with_all = ["web", "secure_web", "socks", "pac"])]
     pub list: bool,
+
+    /// Print the list as JSON
+    #[arg(long, conflicts_with_all = ["off", "preset", "web", "secure_web", "socks", "pac"])]
+    pub json: bool,
+
+    /// Print the commands that would change the configuration instead of running them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Restrict the operation to the given network service (can be repeated)
+    #[arg(short, long = "interface", value_name = "NAME")]
+    pub interfaces: Vec<String>,
+
+    /// Exclude network services matching a glob (`Tailscale*`) or a regex (`/vpn$/`) (can be repeated)
+    #[arg(short = 'x', long = "exclude", value_name = "PATTERN")]
+    pub excludes: Vec<String>,
+}
+
+/// Proxy servers given on the command line
+#[derive(Args)]
+pub struct ProxyServers {
+    /// Web (HTTP) proxy
+    #[arg(long, value_name = "HOST:PORT", value_parser = parse_proxy_server)]
+    pub web: Option<String>,
+
+    /// Secure web (HTTPS) proxy
+    #[arg(long, value_name = "HOST:PORT", value_parser = parse_proxy_server)]
+    pub secure_web: Option<String>,
+
+    /// SOCKS proxy
+    #[arg(long, value_name = "HOST:PORT", value_parser = parse_proxy_server)]
+    pub socks: Option<String>,
+
+    /// URL of a proxy auto-configuration (PAC) file
+    #[arg(long, value_name = "URL")]
+    pub pac: Option<String>,
+}
+
+/// Available proxy subcommands
+#[derive(Subcommand)]
+pub enum ProxyCommands {
+    /// Manage named proxy presets
+    Presets {
+        /// The preset operation to execute
+        #[command(subcommand)]
+        command: PresetsCommands,
+    },
+}
+
+/// Proxy preset operations
+#[derive(Subcommand)]
+pub enum PresetsCommands {
+    /// List all configured presets
+    List,
+    /// Add a preset or replace an existing one
+    Add {
+        /// Name of the preset
+        name: String,
+
+        /// Proxies of the preset
+        #[command(flatten)]
+        servers: ProxyServers,
+    },
+    /// Remove a preset
+    Remove {
+        /// Name of the preset
+        name: String,
+    },
+}
+
+/// Validates a proxy server given as `HOST:PORT`
+///
+/// IPv6 addresses must be enclosed in brackets, e.g. `[::1]:3128`.
+fn parse_proxy_server(value: &str) -> Result<String, String> {
+    let (host, port) = value
+        .rsplit_once(':')
+        .ok_or_else(|| format!("'{value}' is not a proxy server, expected HOST:PORT"))?;
+    let host = match host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+        Some(address) => address,
+        None if host.contains(':') => return Err(format!("IPv6 address in '{value}' must be enclosed in brackets")),
+        None => host,
+    };
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(format!("'{value}' has no valid host"));
+    }
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(value.to_string()),
+        _ => Err(format!("'{port}' is not a valid port")),
+    }
+}
+
+/// Validates a domain name given on the command line
+///
+/// Accepts names consisting of dot-separated labels of letters, digits and hyphens,
+/// where no label starts or ends with a hyphen. A trailing dot is allowed.
+fn parse_domain(value: &str) -> Result<String, String> {
+    let name = value.strip_suffix('.').unwrap_or(value);
+    let valid = !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(format!("'{value}' is not a valid domain name"))
+    }
+}
+
+/// Arguments for the location subcommand
+///
+/// Without a subcommand, the network locations are listed.
+#[derive(Parser)]
+pub struct LocationArgs {
+    /// Location operation to execute
+    #[command(subcommand)]
+    pub command: Option<LocationCommands>,
+}
+
+/// Network location operations
+#[derive(Subcommand)]
+pub enum LocationCommands {
+    /// List all network locations, marking the active one
+    List,
+    /// Activate a network location
+    Switch {
+        /// Name of the location (case-insensitive)
+        name: String,
+
+        /// DNS preset to apply after switching: pub, pub:<PROVIDER>, profile:<NAME> or dhcp
+        #[arg(long, value_name = "PRESET")]
+        dns: Option<String>,
+    },
+    /// Create a network location with the default network services
+    Create {
+        /// Name of the new location
+        name: String,
+    },
+    /// Delete a network location
+    Delete {
+        /// Name of the location (case-insensitive)
+        name: String,
+    },
+}
+
+/// Arguments for the IP subcommand
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct IpArgs {
+    /// IP operation to execute
+    #[command(subcommand)]
+    pub command: Option<IpCommands>,
+
+    /// Only print the public IP addresses
+    #[arg(long)]
+    pub public_only: bool,
+
+    /// Skip the lookup of the public IP addresses
+    #[arg(long, conflicts_with = "public_only")]
+    pub no_public: bool,
+
+    /// HTTPS endpoint replying with the client address as plain text (overrides the configuration file)
+    #[arg(long, value_name = "URL")]
+    pub endpoint: Option<String>,
+
+    /// Keep running and print the addresses again whenever they change
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Seconds between checks in watch mode
+    #[arg(long, value_name = "SECONDS", default_value_t = 10, requires = "watch")]
+    pub interval: u64,
+
+    /// Print the addresses as JSON (one line per change in watch mode)
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// IP operations
+#[derive(Subcommand)]
+pub enum IpCommands {
+    /// Poll the public IP address and log its changes with timestamps
+    Watch(IpWatchArgs),
+}
+
+/// Arguments for watching the public IP address
+#[derive(Parser)]
+pub struct IpWatchArgs {
+    /// Post a notification when the public address changes
+    #[arg(short, long)]
+    pub notify: bool,
+
+    /// Seconds between checks
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 300, value_parser = clap::value_parser!(u64).range(10..))]
+    pub interval: u64,
+
+    /// Also watch the public IPv6 address, which changes often with privacy addresses
+    #[arg(short = '6', long)]
+    pub ipv6: bool,
+
+    /// HTTPS endpoint replying with the client address as plain text (overrides the configuration file)
+    #[arg(long, value_name = "URL")]
+    pub endpoint: Option<String>,
+
+    /// Install a LaunchAgent running the watcher in the background with these options
+    #[arg(long, conflicts_with = "uninstall")]
+    pub install: bool,
+
+    /// Stop and remove the LaunchAgent
+    #[arg(long, conflicts_with_all = ["notify", "ipv6", "endpoint"])]
+    pub uninstall: bool,
+}
+
+/// Arguments for the hostname subcommand
+///
+/// Without a subcommand, the current names are shown.
+#[derive(Parser)]
+pub struct HostnameArgs {
+    /// Hostname operation to execute
+    #[command(subcommand)]
+    pub command: Option<HostnameCommands>,
+}
+
+/// Hostname operations
+#[derive(Subcommand)]
+pub enum HostnameCommands {
+    /// Show the computer name, local host name and host name
+    Get,
+    /// Set the computer name, local host name and host name to the same name
+    Set {
+        /// New name: letters, digits and hyphens, as required for Bonjour
+        name: String,
+
+        /// Print the commands that would change the names instead of running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Arguments for the speed test subcommand
+#[derive(Parser)]
+pub struct SpeedtestArgs {
+    /// Run the download and upload tests one after the other instead of at the same time
+    #[arg(short, long)]
+    pub sequential: bool,
+
+    /// Network interface to test, e.g. `en0`
+    #[arg(short = 'I', long, value_name = "NAME")]
+    pub interface: Option<String>,
+
+    /// Print the result as a single JSON line, e.g. to log results over time
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the port scan subcommand
+#[derive(Parser)]
+pub struct PortscanArgs {
+    /// Host name or IP address to scan
+    pub host: String,
+
+    /// Ports to scan: single ports and ranges separated by commas, e.g. `22,80,8000-8100`
+    #[arg(short, long, value_name = "PORTS", default_value = "1-1024")]
+    pub ports: String,
+
+    /// Time to wait for a connection per port, e.g. `500ms` or `2s` (plain numbers are milliseconds)
+    #[arg(short, long, value_name = "DURATION", default_value = "500ms", value_parser = parse_duration)]
+    pub timeout: Duration,
+
+    /// Maximum number of connection attempts at the same time
+    #[arg(short, long, value_name = "N", default_value_t = 128, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+/// Arguments for the ping monitor
+#[derive(Parser)]
+pub struct PingArgs {
+    /// Host name or IP address to ping
+    pub host: String,
+
+    /// Time between pings, e.g. `1s` or `500ms` (plain numbers are milliseconds)
+    #[arg(short, long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration)]
+    pub interval: Duration,
+
+    /// Number of recent pings the statistics and the sparkline cover
+    #[arg(short, long, value_name = "N", default_value_t = 60, value_parser = clap::value_parser!(u16).range(2..))]
+    pub window: u16,
+
+    /// Time to wait for each reply (plain numbers are milliseconds)
+    #[arg(short, long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration)]
+    pub timeout: Duration,
+
+    /// Stop after this number of pings and print a summary
+    #[arg(short = 'n', long, value_name = "N")]
+    pub count: Option<u64>,
+
+    /// Post a notification when the packet loss within the window exceeds this percentage
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub alert_loss: Option<u8>,
+}
+
+/// Arguments for the firewall subcommand
+///
+/// Without a subcommand, the firewall status is shown.
+#[derive(Parser)]
+pub struct FirewallArgs {
+    /// Firewall operation to execute
+    #[command(subcommand)]
+    pub command: Option<FirewallCommands>,
+}
+
+/// Application firewall operations
+#[derive(Subcommand)]
+pub enum FirewallCommands {
+    /// Show whether the firewall, stealth mode and blocking of all connections are enabled
+    Status,
+    /// Turn the firewall on
+    On,
+    /// Turn the firewall off
+    Off,
+    /// Turn stealth mode on or off (no replies to pings and connection attempts to closed ports)
+    Stealth {
+        /// New state of stealth mode
+        #[arg(value_enum)]
+        state: Switch,
+    },
+    /// List applications with firewall rules
+    List,
+    /// Allow incoming connections for an application
+    Allow {
+        /// Path or name of the application, e.g. `Slack` or `/usr/local/bin/node`
+        app: String,
+    },
+    /// Block incoming connections for an application
+    Block {
+        /// Path or name of the application, e.g. `Slack` or `/usr/local/bin/node`
+        app: String,
+    },
+}
+
+/// Arguments for the arp subcommand
+///
+/// Lists the hosts of the local network the Mac has recently communicated with.
+#[derive(Parser)]
+pub struct ArpArgs {
+    /// Include IPv6 neighbors from the neighbor discovery cache
+    #[arg(short = '6', long)]
+    pub ipv6: bool,
+
+    /// Keep running and print devices as they appear on the local network
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Seconds between checks in watch mode
+    #[arg(long, value_name = "SECONDS", default_value_t = 5, requires = "watch")]
+    pub interval: u64,
+
+    /// Print the neighbors as JSON (one line per new device in watch mode)
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the netstat subcommand
+///
+/// Without `--watch`, the bytes received and sent since boot are shown.
+#[derive(Parser)]
+pub struct NetstatArgs {
+    /// Keep running and show the receive and transmit rates of the interfaces
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Time between samples in watch mode, e.g. `1s` or `500ms` (plain numbers are milliseconds)
+    #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration, requires = "watch")]
+    pub interval: Duration,
+
+    /// Only show this interface, e.g. `en0` (repeatable)
+    #[arg(short, long = "interface", value_name = "NAME")]
+    pub interfaces: Vec<String>,
+
+    /// Also show loopback, inactive and idle interfaces
+    #[arg(long, conflicts_with = "interfaces")]
+    pub all: bool,
+
+    /// Print the counters as JSON
+    #[arg(long, conflicts_with = "watch")]
+    pub json: bool,
+}
+
+/// Arguments for the captive subcommand
+#[derive(Parser)]
+pub struct CaptiveArgs {
+    /// Open the portal in the default browser if one is detected
+    #[arg(long)]
+    pub open: bool,
+
+    /// Print the probe result as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the trace subcommand
+#[derive(Parser)]
+pub struct TraceArgs {
+    /// Host name or IP address to trace the route to
+    pub host: String,
+
+    /// Trace the route to the IPv6 address of the host
+    #[arg(short = '6', long)]
+    pub ipv6: bool,
+
+    /// Send ICMP echo requests instead of UDP packets
+    #[arg(long)]
+    pub icmp: bool,
+
+    /// Maximum number of hops
+    #[arg(short, long, value_name = "N", default_value_t = 30, value_parser = clap::value_parser!(u8).range(1..))]
+    pub max_hops: u8,
+
+    /// Number of probes per hop
+    #[arg(short, long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=10))]
+    pub queries: u8,
+
+    /// Seconds to wait for the reply to a probe
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..))]
+    pub wait: u8,
+
+    /// Skip the host name and autonomous system lookups
+    #[arg(short, long)]
+    pub numeric: bool,
+
+    /// Print the hops as JSON when the trace is complete
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the ports subcommand
+///
+/// Filters can be combined; a socket must match all of them.
+#[derive(Parser)]
+pub struct PortsArgs {
+    /// Only show sockets with this local or remote port
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// Only show sockets of processes with this ID or a name containing this text
+    #[arg(long, value_name = "PID|NAME")]
+    pub process: Option<String>,
+
+    /// Only show TCP sockets in this state, e.g. `LISTEN`, `ESTABLISHED` or `TIME_WAIT`
+    #[arg(long)]
+    pub state: Option<String>,
+
+    /// Only show listening TCP and unconnected UDP sockets
+    #[arg(short, long, conflicts_with = "state")]
+    pub listen: bool,
+
+    /// Print the sockets as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the DHCP subcommand
+///
+/// Without a subcommand, the lease of the primary interface is shown.
+#[derive(Parser)]
+pub struct DhcpArgs {
+    /// DHCP operation to execute
+    #[command(subcommand)]
+    pub command: Option<DhcpCommands>,
+}
+
+/// DHCP operations
+#[derive(Subcommand)]
+pub enum DhcpCommands {
+    /// Show the DHCP lease with server, router, DNS servers, lease times and options
+    Info {
+        /// Interface to show, e.g. `en0` (default: interface of the default route)
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Print the lease as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Request a new DHCP lease
+    Renew {
+        /// Interface to renew, e.g. `en0` (default: interface of the default route)
+        #[arg(short, long)]
+        interface: Option<String>,
+    },
+}
+
+/// Arguments for the net subcommand
+#[derive(Parser)]
+pub struct NetArgs {
+    /// Network operation to execute
+    #[command(subcommand)]
+    pub command: NetCommands,
+}
+
+/// Network operations
+#[derive(Subcommand)]
+pub enum NetCommands {
+    /// Check interface, gateway, DNS, public IP, latency, MTU and proxies and suggest fixes
+    Doctor(DoctorArgs),
+}
+
+/// Arguments for the network doctor
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// Reset DNS to the servers assigned by DHCP without asking if no nameserver answers
+    #[arg(long)]
+    pub fix: bool,
+}
+
+/// Arguments for the wol subcommand
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct WolArgs {
+    /// Alias operation to execute
+    #[command(subcommand)]
+    pub command: Option<WolCommands>,
+
+    /// MAC address (e.g. `00:11:32:ab:cd:ef`) or alias of the host to wake up
+    #[arg(required = true)]
+    pub target: Option<String>,
+
+    /// Broadcast address of the network of the host (default: stored with the alias, otherwise 255.255.255.255)
+    #[arg(short, long)]
+    pub broadcast: Option<Ipv4Addr>,
+
+    /// UDP port to send the magic packet to
+    #[arg(short, long, default_value_t = 9)]
+    pub port: u16,
+
+    /// Save the MAC address and broadcast address under this alias
+    #[arg(long, value_name = "NAME")]
+    pub alias: Option<String>,
+}
+
+/// Wake-on-LAN alias operations
+#[derive(Subcommand)]
+pub enum WolCommands {
+    /// List all saved hosts
+    List,
+    /// Remove a saved host
+    Remove {
+        /// Alias of the host
+        name: String,
+    },
+}
+
+/// Arguments for the route subcommand
+#[derive(Parser)]
+pub struct RouteArgs {
+    /// Routing operation to execute (default: list)
+    #[command(subcommand)]
+    pub command: Option<RouteCommands>,
+}
+
+/// Routing operations
+#[derive(Subcommand)]
+pub enum RouteCommands {
+    /// Show the routing table
+    List {
+        /// Only show IPv4 routes
+        #[arg(short = '4', long, conflicts_with = "ipv6")]
+        ipv4: bool,
+        /// Only show IPv6 routes
+        #[arg(short = '6', long)]
+        ipv6: bool,
+        /// Also show host routes cloned from other routes (ARP and neighbor discovery entries)
+        #[arg(short, long)]
+        all: bool,
+        /// Print the routes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add a static route (requires sudo, not persistent across reboots)
+    Add {
+        /// Destination network (e.g. `10.0.0.0/8`), host address, or `default`
+        destination: String,
+        /// Gateway to route the destination through
+        #[arg(required_unless_present = "interface")]
+        gateway: Option<IpAddr>,
+        /// Route the destination directly to an interface instead of a gateway, e.g. `utun3`
+        #[arg(short, long, conflicts_with = "gateway")]
+        interface: Option<String>,
+        /// Delete the route again after this many minutes
+        #[arg(short, long, value_name = "MINUTES", value_parser = clap::value_parser!(u32).range(1..))]
+        expire: Option<u32>,
+    },
+    /// Delete a route (requires sudo)
+    Delete {
+        /// Destination network (e.g. `10.0.0.0/8`), host address, or `default`
+        destination: String,
+        /// Gateway of the route, to delete only the route through this gateway
+        gateway: Option<IpAddr>,
+    },
+}
+
+/// Arguments for the share subcommand
+#[derive(Parser)]
+pub struct ShareArgs {
+    /// Share operation to execute
+    #[command(subcommand)]
+    pub command: ShareCommands,
+}
+
+/// Network share operations
+#[derive(Subcommand)]
+pub enum ShareCommands {
+    /// Mount a network share in /Volumes
+    Mount {
+        /// URL of the share (`smb://[USER@]SERVER/VOLUME` or `afp://...`) or name of a favorite
+        share: String,
+
+        /// Use the password stored in the keychain, asking for it and storing it if there is none
+        #[arg(long)]
+        keychain: bool,
+
+        /// Save the share as a favorite under this name
+        #[arg(long, value_name = "NAME")]
+        alias: Option<String>,
+    },
+    /// Unmount a network share
+    Unmount {
+        /// Mount point, URL, volume name or favorite of the share
+        share: String,
+
+        /// Unmount even if files on the share are open
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// List mounted network shares and favorites
+    List,
+    /// Remove a favorite
+    Remove {
+        /// Name of the favorite
+        name: String,
+    },
+}
+
+/// Arguments for the mtu subcommand
+#[derive(Parser)]
+pub struct MtuArgs {
+    /// MTU operation to execute (default: get)
+    #[command(subcommand)]
+    pub command: Option<MtuCommands>,
+}
+
+/// MTU operations
+#[derive(Subcommand)]
+pub enum MtuCommands {
+    /// Show the MTU of an interface
+    Get {
+        /// Interface, e.g. `en0` (default: interface of the default route)
+        #[arg(short, long)]
+        interface: Option<String>,
+    },
+    /// Set the MTU of an interface (requires sudo)
+    Set {
+        /// New MTU in bytes
+        #[arg(value_parser = clap::value_parser!(u32).range(68..=65535))]
+        value: u32,
+        /// Interface, e.g. `en0` (default: interface of the default route)
+        #[arg(short, long)]
+        interface: Option<String>,
+    },
+    /// Find the largest packet that reaches a host unfragmented with don't-fragment pings
+    Discover {
+        /// Host name or IPv4 address
+        host: String,
+        /// Largest packet size to probe (default: MTU of the primary interface)
+        #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u32).range(577..=65535))]
+        max: Option<u32>,
+    },
+}
+
+/// Arguments for the ssh subcommand
+#[derive(Parser)]
+pub struct SshArgs {
+    /// SSH operation to execute
+    #[command(subcommand)]
+    pub command: SshCommands,
+}
+
+/// SSH operations
+#[derive(Subcommand)]
+pub enum SshCommands {
+    /// Manage host entries of ~/.ssh/config
+    Hosts {
+        /// Host operation to execute
+        #[command(subcommand)]
+        command: SshHostsCommands,
+    },
+    /// Manage keys in ~/.ssh/known_hosts
+    Known {
+        /// Known hosts operation to execute
+        #[command(subcommand)]
+        command: SshKnownCommands,
+    },
+}
+
+/// SSH host entry operations
+#[derive(Subcommand)]
+pub enum SshHostsCommands {
+    /// List all host entries
+    List,
+    /// Add a host entry or replace an existing one
+    Add {
+        /// Alias to connect with, e.g. `nas` for `ssh nas`
+        alias: String,
+        /// Real host name or address
+        hostname: String,
+        /// User name to log in with
+        #[arg(short, long)]
+        user: Option<String>,
+        /// Port to connect to
+        #[arg(short, long)]
+        port: Option<u16>,
+        /// Private key file, e.g. `~/.ssh/id_ed25519`
+        #[arg(short, long)]
+        identity: Option<String>,
+        /// Jump host to connect through
+        #[arg(short = 'J', long)]
+        jump: Option<String>,
+        /// Further option as KEY=VALUE, e.g. `ForwardAgent=yes` (repeatable)
+        #[arg(short, long = "option", value_name = "KEY=VALUE")]
+        options: Vec<String>,
+    },
+    /// Remove a host entry
+    Remove {
+        /// Alias of the host
+        alias: String,
+    },
+}
+
+/// Known hosts operations
+#[derive(Subcommand)]
+pub enum SshKnownCommands {
+    /// Remove the stored keys of a host and its addresses, e.g. after it was reinstalled
+    Prune {
+        /// Host name, address or alias of ~/.ssh/config
+        host: String,
+        /// Port of the host (default: port of ~/.ssh/config or 22)
+        #[arg(short, long)]
+        port: Option<u16>,
+    },
+}
+
+/// Arguments for the mdns subcommand
+#[derive(Parser)]
+pub struct MdnsArgs {
+    /// mDNS operation to execute
+    #[command(subcommand)]
+    pub command: MdnsCommands,
+}
+
+/// mDNS operations
+#[derive(Subcommand)]
+pub enum MdnsCommands {
+    /// Advertise a Bonjour service on the local network until stopped with Ctrl-C
+    Advertise(MdnsAdvertiseArgs),
+}
+
+/// Arguments for advertising a Bonjour service
+#[derive(Parser)]
+pub struct MdnsAdvertiseArgs {
+    /// Name of the service (default: computer name)
+    #[arg(short, long)]
+    pub name: Option<String>,
+    /// Service type, e.g. `_http._tcp` or `_ssh._tcp`
+    #[arg(short = 't', long = "type", value_name = "TYPE")]
+    pub service_type: String,
+    /// Port the service listens on
+    #[arg(short, long)]
+    pub port: u16,
+    /// TXT record entry as KEY=VALUE, e.g. `path=/api` (repeatable)
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub txt: Vec<String>,
+    /// Domain to register the service in
+    #[arg(short, long, default_value = "local")]
+    pub domain: String,
+}
+
+/// Arguments for the netaudit subcommand
+#[derive(Parser)]
+pub struct NetauditArgs {
+    /// Flag connections to countries other than these, e.g. `DE,NL,US`
+    #[arg(short, long, value_name = "CODES", value_delimiter = ',')]
+    pub countries: Vec<String>,
+
+    /// Only show applications with this process ID or a name containing this text
+    #[arg(short, long, value_name = "PID|NAME")]
+    pub process: Option<String>,
+
+    /// Do not look up host names and countries
+    #[arg(short, long, conflicts_with = "countries")]
+    pub numeric: bool,
+
+    /// Print the applications and connections as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the hotspot subcommand
+#[derive(Parser)]
+pub struct HotspotArgs {
+    /// Internet Sharing operation to execute
+    #[command(subcommand)]
+    pub command: Option<HotspotCommands>,
+}
+
+/// Internet Sharing operations
+#[derive(Subcommand)]
+pub enum HotspotCommands {
+    /// Show whether Internet Sharing is on and which interface is shared to which
+    Status,
+    /// Turn Internet Sharing on with the interfaces chosen in System Settings
+    On,
+    /// Turn Internet Sharing off
+    Off,
+}
+
+/// Arguments for the awake subcommand
+#[derive(Parser)]
+pub struct AwakeArgs {
+    /// Also keep the display from sleeping
+    #[arg(short, long)]
+    pub display: bool,
+
+    /// Release after this time, e.g. `45m`, `2h` or `1h30m`
+    #[arg(short = 't', long = "for", value_name = "DURATION", value_parser = parse_span)]
+    pub duration: Option<Duration>,
+
+    /// Keep awake while this command runs; takes all remaining arguments
+    #[arg(short, long = "while", value_name = "COMMAND", num_args = 1.., allow_hyphen_values = true)]
+    pub command: Vec<String>,
+}
+
+/// Arguments for the battery subcommand
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct BatteryArgs {
+    /// Battery operation to execute
+    #[command(subcommand)]
+    pub command: Option<BatteryCommands>,
+
+    /// Keep running and print the battery state at an interval
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Seconds between readings in watch mode
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 30, requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+
+    /// Show connected UPS devices and the shutdown thresholds on UPS power instead
+    #[arg(short, long, conflicts_with = "watch")]
+    pub ups: bool,
+
+    /// Shut down when the UPS charge drops to this percentage, 0 to disable
+    #[arg(long, value_name = "PERCENT", requires = "ups", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub halt_level: Option<u8>,
+
+    /// Shut down after this many minutes on UPS power, 0 to disable
+    #[arg(long, value_name = "MINUTES", requires = "ups")]
+    pub halt_after: Option<u32>,
+
+    /// Shut down when the UPS estimates this many minutes remaining, 0 to disable
+    #[arg(long, value_name = "MINUTES", requires = "ups")]
+    pub halt_remain: Option<u32>,
+
+    /// Print the battery state as JSON (one line per reading in watch mode)
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Battery operations
+#[derive(Subcommand)]
+pub enum BatteryCommands {
+    /// Record the battery state to a CSV file and report capacity and cycle trends
+    Log(BatteryLogArgs),
+    /// Watch the charge and post notifications when the battery runs low
+    Notify(BatteryNotifyArgs),
+}
+
+/// Arguments for the battery history log
+///
+/// Without a subcommand or option, one sample is appended to the log.
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct BatteryLogArgs {
+    /// Battery log operation to execute
+    #[command(subcommand)]
+    pub command: Option<BatteryLogCommands>,
+
+    /// Install a LaunchAgent recording a sample at an interval
+    #[arg(long, conflicts_with = "uninstall")]
+    pub install: bool,
+
+    /// Minutes between samples of the LaunchAgent
+    #[arg(short, long, value_name = "MINUTES", default_value_t = 10, requires = "install", value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+
+    /// Stop and remove the LaunchAgent
+    #[arg(long)]
+    pub uninstall: bool,
+}
+
+/// Arguments for the low battery notifications
+#[derive(Parser)]
+pub struct BatteryNotifyArgs {
+    /// Post a notification when the charge drops below this percentage
+    #[arg(short, long, value_name = "PERCENT", default_value_t = 20, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub below: u8,
+
+    /// Post an urgent notification and run the hook when the charge drops below this percentage
+    #[arg(short, long, value_name = "PERCENT", default_value_t = 10, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub critical: u8,
+
+    /// Shell command run at the critical level, with the charge in REM_BATTERY_PERCENT
+    #[arg(long, value_name = "COMMAND")]
+    pub hook: Option<String>,
+
+    /// Seconds between checks
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 60, value_parser = clap::value_parser!(u64).range(10..))]
+    pub interval: u64,
+
+    /// Install a LaunchAgent running the watcher in the background with these options
+    #[arg(long, conflicts_with = "uninstall")]
+    pub install: bool,
+
+    /// Stop and remove the LaunchAgent
+    #[arg(long, conflicts_with = "hook")]
+    pub uninstall: bool,
+}
+
+/// Battery log operations
+#[derive(Subcommand)]
+pub enum BatteryLogCommands {
+    /// Chart the health, capacity and cycle count of the recorded samples by week
+    Report {
+        /// Number of most recent weeks to include
+        #[arg(short, long, default_value_t = 12, value_parser = clap::value_parser!(u64).range(1..))]
+        weeks: u64,
+    },
+}
+
+/// Arguments for the power subcommand
+#[derive(Parser)]
+pub struct PowerArgs {
+    /// Power operation to execute
+    #[command(subcommand)]
+    pub command: PowerCommands,
+}
+
+/// Power operations
+#[derive(Subcommand)]
+pub enum PowerCommands {
+    /// Show and set the repeating sleep and wake schedule
+    Schedule {
+        /// Schedule operation to execute
+        #[command(subcommand)]
+        command: PowerScheduleCommands,
+    },
+    /// Show and switch Low Power Mode
+    Lowpower {
+        /// Low Power Mode operation to execute
+        #[command(subcommand)]
+        command: Option<PowerLowpowerCommands>,
+    },
+    /// Show and set the display, system and disk sleep timers
+    Timers(PowerTimersArgs),
+    /// Show which processes keep the Mac or its display from sleeping
+    Blame(PowerBlameArgs),
+    /// Show the power drawn by CPU, GPU and the whole Mac while it runs
+    Monitor(PowerMonitorArgs),
+    /// Shut down the Mac at a time of the day, after a delay or right after quitting all apps
+    Shutdown(PowerShutdownArgs),
+    /// Restart the Mac at a time of the day, after a delay or right after quitting all apps
+    Restart(PowerShutdownArgs),
+    /// Show the connected power adapter and whether it charges the battery
+    Adapter(PowerAdapterArgs),
+}
+
+/// Power schedule operations
+#[derive(Subcommand)]
+pub enum PowerScheduleCommands {
+    /// Show the repeating and upcoming power events
+    Show,
+    /// Wake or power on the Mac at a time of the day
+    Wake {
+        /// Time of the day (HH:MM)
+        #[arg(value_name = "HH:MM")]
+        time: String,
+        /// Days to wake on: daily, weekdays, weekends, letters of MTWRFSU or e.g. mon,wed,fri
+        #[arg(default_value = "daily")]
+        days: String,
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Print the pmset command instead of running it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Put the Mac to sleep at a time of the day
+    Sleep {
+        /// Time of the day (HH:MM)
+        #[arg(value_name = "HH:MM")]
+        time: String,
+        /// Days to sleep on: daily, weekdays, weekends, letters of MTWRFSU or e.g. mon,wed,fri
+        #[arg(default_value = "daily")]
+        days: String,
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Print the pmset command instead of running it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove all repeating power events
+    Clear {
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Print the pmset command instead of running it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Low Power Mode operations
+#[derive(Subcommand)]
+pub enum PowerLowpowerCommands {
+    /// Show whether Low Power Mode is on while on battery and on the power adapter
+    Status,
+    /// Turn Low Power Mode on
+    On {
+        /// Power sources to change
+        #[command(flatten)]
+        source: PowerSourceArgs,
+    },
+    /// Turn Low Power Mode off
+    Off {
+        /// Power sources to change
+        #[command(flatten)]
+        source: PowerSourceArgs,
+    },
+}
+
+/// Arguments for showing and setting the sleep timers
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct PowerTimersArgs {
+    /// Timer operation to execute
+    #[command(subcommand)]
+    pub command: Option<PowerTimersCommands>,
+
+    /// Minutes of inactivity until the display sleeps, 0 for never
+    #[arg(short, long, value_name = "MINUTES")]
+    pub display: Option<u32>,
+
+    /// Minutes of inactivity until the system sleeps, 0 for never
+    #[arg(short, long, value_name = "MINUTES")]
+    pub sleep: Option<u32>,
+
+    /// Minutes of inactivity until disks spin down, 0 for never
+    #[arg(long, value_name = "MINUTES")]
+    pub disk: Option<u32>,
+
+    /// Power sources to change
+    #[command(flatten)]
+    pub source: PowerSourceArgs,
+}
+
+/// Sleep timer operations
+#[derive(Subcommand)]
+pub enum PowerTimersCommands {
+    /// Show the timers of battery and power adapter side by side
+    Show,
+}
+
+/// Arguments for showing the processes preventing sleep
+#[derive(Parser)]
+pub struct PowerBlameArgs {
+    /// Keep running and print assertions as they are taken and released
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Post a notification when a process starts preventing sleep
+    #[arg(short, long, requires = "watch")]
+    pub notify: bool,
+
+    /// Seconds between checks in watch mode
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 10, requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+}
+
+/// Arguments for monitoring the power consumption
+#[derive(Parser)]
+pub struct PowerMonitorArgs {
+    /// Seconds between samples
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+}
+
+/// Arguments for showing the power adapter
+#[derive(Parser)]
+pub struct PowerAdapterArgs {
+    /// Output as JSON
+    #[arg(short, long)]
+    pub json: bool,
+}
+
+/// Arguments for scheduling a shutdown or restart
+#[derive(Parser)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    group(ArgGroup::new("when").required(true))
+)]
+pub struct PowerShutdownArgs {
+    /// Scheduled shutdown operation to execute
+    #[command(subcommand)]
+    pub command: Option<PowerShutdownCommands>,
+
+    /// Time of the day (HH:MM), tomorrow if it has passed today
+    #[arg(long, value_name = "HH:MM", group = "when")]
+    pub at: Option<String>,
+
+    /// Delay, e.g. `45m`, `2h` or `1h30m`
+    #[arg(long = "in", value_name = "DURATION", value_parser = parse_span, group = "when")]
+    pub delay: Option<Duration>,
+
+    /// Quit all apps, then shut down or restart right away once all of them have quit
+    #[arg(short, long, group = "when")]
+    pub graceful: bool,
+
+    /// Seconds to wait for the apps to quit
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 60, requires = "graceful", value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: u64,
+
+    /// Schedule without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// Scheduled shutdown operations
+#[derive(Subcommand)]
+pub enum PowerShutdownCommands {
+    /// Cancel a scheduled shutdown or restart
+    Cancel,
+}
+
+/// Power sources a power setting is changed for; both if neither is given
+#[derive(Args)]
+pub struct PowerSourceArgs {
+    /// Only change the setting used on battery
+    #[arg(short, long, conflicts_with = "ac")]
+    pub battery: bool,
+
+    /// Only change the setting used on the power adapter
+    #[arg(short = 'c', long)]
+    pub ac: bool,
+}
+
+/// Arguments for the thermals subcommand
+#[derive(Parser)]
+pub struct ThermalsArgs {
+    /// Keep running and print the thermal state at an interval
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Seconds between readings in watch mode
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 2, requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+}
+
+/// Arguments for the appearance subcommand
+///
+/// Without a subcommand, the current appearance is shown.
+#[derive(Parser)]
+pub struct AppearanceArgs {
+    /// Appearance operation to execute
+    #[command(subcommand)]
+    pub command: Option<AppearanceCommands>,
+}
+
+/// Appearance operations
+#[derive(Subcommand)]
+pub enum AppearanceCommands {
+    /// Show the current appearance and the sunset schedule
+    Show,
+    /// Switch to dark mode
+    Dark,
+    /// Switch to light mode
+    Light,
+    /// Switch between dark and light mode
+    Toggle,
+    /// Let macOS switch the appearance automatically
+    Auto,
+    /// Switch to dark mode at sunset and to light mode at sunrise at a location
+    Schedule(AppearanceScheduleArgs),
+}
+
+/// Arguments for the sunset and sunrise schedule
+///
+/// Without options, the schedule is shown.
+#[derive(Parser)]
+pub struct AppearanceScheduleArgs {
+    /// Latitude of the location in degrees, negative south of the equator
+    #[arg(long, value_name = "DEGREES", allow_negative_numbers = true, requires = "longitude")]
+    pub latitude: Option<f64>,
+
+    /// Longitude of the location in degrees, negative west of Greenwich
+    #[arg(long, value_name = "DEGREES", allow_negative_numbers = true, requires = "latitude")]
+    pub longitude: Option<f64>,
+
+    /// Stop and remove the schedule
+    #[arg(long, conflicts_with_all = ["latitude", "longitude"])]
+    pub uninstall: bool,
+
+    /// Switch the appearance if sunset or sunrise has passed since the last run (used by the LaunchAgent)
+    #[arg(long, hide = true, conflicts_with_all = ["latitude", "longitude", "uninstall"])]
+    pub run: bool,
+}
+
+/// Arguments for the display subcommand
+///
+/// Without a subcommand, the state of True Tone and automatic brightness is shown.
+#[derive(Parser)]
+pub struct DisplayArgs {
+    /// Display operation to execute
+    #[command(subcommand)]
+    pub command: Option<DisplayCommands>,
+}
+
+/// Display operations
+#[derive(Subcommand)]
+pub enum DisplayCommands {
+    /// Show whether True Tone and automatic brightness are on
+    Status,
+    /// Show or switch True Tone, which adapts the white point to the ambient light
+    Truetone {
+        /// New state of True Tone
+        #[arg(value_enum)]
+        state: Option<Switch>,
+    },
+    /// Show or switch the automatic brightness adjustment of the built-in display
+    Autobrightness {
+        /// New state of automatic brightness
+        #[arg(value_enum)]
+        state: Option<Switch>,
+    },
+    /// List the connected displays with model, serial, connection, current mode and HDR support
+    Info {
+        /// Also dump the raw EDID of each display
+        #[arg(long)]
+        edid: bool,
+    },
+}
+
+/// Arguments for the screenshot subcommand
+///
+/// Without a subcommand, the screenshot settings are shown.
+#[derive(Parser)]
+pub struct ScreenshotArgs {
+    /// Screenshot operation to execute
+    #[command(subcommand)]
+    pub command: Option<ScreenshotCommands>,
+}
+
+/// Screenshot operations
+#[derive(Subcommand)]
+pub enum ScreenshotCommands {
+    /// Show where and how screenshots are saved
+    Show,
+    /// Change where and how screenshots are saved
+    Config(ScreenshotConfigArgs),
+    /// Take a screenshot of the main display, a display, a window or a region
+    Take(ScreenshotTakeArgs),
+}
+
+/// Arguments for the screenshot settings
+///
+/// At least one setting must be given; unchanged settings keep their value.
+#[derive(Parser)]
+#[command(group(ArgGroup::new("setting").required(true).multiple(true)))]
+pub struct ScreenshotConfigArgs {
+    /// Folder to save screenshots in, created if missing
+    #[arg(short, long, value_name = "DIR", group = "setting")]
+    pub location: Option<PathBuf>,
+
+    /// File format of screenshots
+    #[arg(short, long, value_enum, group = "setting")]
+    pub format: Option<ScreenshotFormat>,
+
+    /// Include the window shadow in window screenshots
+    #[arg(long, group = "setting", conflicts_with = "no_shadow")]
+    pub shadow: bool,
+
+    /// Capture windows without their shadow
+    #[arg(long, group = "setting")]
+    pub no_shadow: bool,
+
+    /// Show the floating thumbnail after taking a screenshot
+    #[arg(long, group = "setting", conflicts_with = "no_thumbnail")]
+    pub thumbnail: bool,
+
+    /// Save screenshots right away without the floating thumbnail
+    #[arg(long, group = "setting")]
+    pub no_thumbnail: bool,
+}
+
+/// Arguments for taking a screenshot
+///
+/// Without an option, the main display is captured and saved with a timestamped
+/// name in the screenshot folder.
+#[derive(Parser)]
+#[command(group(ArgGroup::new("target").multiple(false)))]
+pub struct ScreenshotTakeArgs {
+    /// Click the window to capture
+    #[arg(short, long, group = "target")]
+    pub window: bool,
+
+    /// Drag the region to capture
+    #[arg(short, long, group = "target")]
+    pub region: bool,
+
+    /// Capture display N, 1 being the main display
+    #[arg(short, long, value_name = "N", group = "target", value_parser = clap::value_parser!(u32).range(1..))]
+    pub display: Option<u32>,
+
+    /// Copy the screenshot to the clipboard instead of saving it
+    #[arg(short, long, conflicts_with_all = ["output", "open", "ocr"])]
+    pub clipboard: bool,
+
+    /// Seconds to wait before capturing
+    #[arg(long, value_name = "SECONDS")]
+    pub delay: Option<u32>,
+
+    /// File to save the screenshot to instead of a timestamped one in the screenshot folder
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// File format, instead of the configured one
+    #[arg(short, long, value_enum)]
+    pub format: Option<ScreenshotFormat>,
+
+    /// Open the screenshot in Preview
+    #[arg(long)]
+    pub open: bool,
+
+    /// Print the text recognized in the screenshot
+    #[arg(long)]
+    pub ocr: bool,
+}
+
+/// File format of screenshots
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ScreenshotFormat {
+    /// Lossless PNG, the default of macOS
+    Png,
+    /// Compressed JPEG
+    Jpg,
+    /// Compressed HEIC
+    Heic,
+    /// Lossless TIFF
+    Tiff,
+    /// Vector PDF
+    Pdf,
+}
+
+/// Arguments for the volume subcommand
+///
+/// Without a subcommand, the volume of the default output device is shown.
+#[derive(Parser)]
+pub struct VolumeArgs {
+    /// Volume operation to execute
+    #[command(subcommand)]
+    pub command: Option<VolumeCommands>,
+
+    /// Output device to control instead of the default one, by name
+    #[arg(short, long, value_name = "NAME", global = true)]
+    pub device: Option<String>,
+
+    /// Print the volume as JSON, also after changing it
+    #[arg(short, long, global = true)]
+    pub json: bool,
+}
+
+/// Volume operations
+#[derive(Subcommand)]
+pub enum VolumeCommands {
+    /// Show the volume and whether the device is muted
+    Get,
+    /// Set the volume
+    Set {
+        /// New volume in percent
+        #[arg(value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    /// Turn the volume up
+    Up {
+        /// Percentage points to add
+        #[arg(value_name = "PERCENT", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=100))]
+        step: u8,
+    },
+    /// Turn the volume down
+    Down {
+        /// Percentage points to subtract
+        #[arg(value_name = "PERCENT", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=100))]
+        step: u8,
+    },
+    /// Mute the device
+    Mute,
+    /// Unmute the device
+    Unmute,
+}
+
+/// Arguments for the audio subcommand
+#[derive(Parser)]
+pub struct AudioArgs {
+    /// Audio operation to execute
+    #[command(subcommand)]
+    pub command: AudioCommands,
+}
+
+/// Audio operations
+#[derive(Subcommand)]
+pub enum AudioCommands {
+    /// List and switch the default output device
+    Output {
+        /// Output device operation, `list` by default
+        #[command(subcommand)]
+        command: Option<AudioOutputCommands>,
+    },
+    /// List and switch the default input device, mute microphones and watch their use
+    Input {
+        /// Input device operation, `status` by default
+        #[command(subcommand)]
+        command: Option<AudioInputCommands>,
+    },
+    /// Show and set the left-right balance of an output device
+    Balance {
+        /// Balance operation, `get` by default
+        #[command(subcommand)]
+        command: Option<AudioBalanceCommands>,
+
+        /// Output device instead of the default one, by name
+        #[arg(short, long, value_name = "NAME", global = true)]
+        device: Option<String>,
+    },
+    /// Show and set the sample rate of an output device
+    Rate {
+        /// Sample rate operation, `list` by default
+        #[command(subcommand)]
+        command: Option<AudioRateCommands>,
+
+        /// Output device instead of the default one, by name
+        #[arg(short, long, value_name = "NAME", global = true)]
+        device: Option<String>,
+    },
+}
+
+/// Output device operations
+#[derive(Subcommand)]
+pub enum AudioOutputCommands {
+    /// List the output devices, marking the default one
+    List {
+        /// Print the devices as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Switch the default output device
+    Set {
+        /// Name of the device; a part or the initials of the name are enough if unambiguous
+        name: String,
+    },
+    /// Switch to the next output device, e.g. bound to a hotkey
+    Cycle,
+}
+
+/// Input device operations
+#[derive(Subcommand)]
+pub enum AudioInputCommands {
+    /// Show the default input device, its volume, whether it is muted and in use
+    Status,
+    /// List the input devices, marking the default one
+    List {
+        /// Print the devices as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Switch the default input device
+    Set {
+        /// Name of the device; a part or the initials of the name are enough if unambiguous
+        name: String,
+    },
+    /// Mute all microphones, or the one given
+    Mute {
+        /// Input device to mute instead of all, by name
+        #[arg(short, long, value_name = "NAME")]
+        device: Option<String>,
+    },
+    /// Unmute all microphones, or the one given
+    Unmute {
+        /// Input device to unmute instead of all, by name
+        #[arg(short, long, value_name = "NAME")]
+        device: Option<String>,
+    },
+    /// Mute all microphones if the default one is unmuted, unmute them otherwise
+    Toggle,
+    /// Report when an app starts or stops using a microphone
+    Watch(AudioInputWatchArgs),
+}
+
+/// Arguments for the microphone watcher
+#[derive(Parser)]
+pub struct AudioInputWatchArgs {
+    /// Post a notification when a microphone starts or stops being used
+    #[arg(short, long)]
+    pub notify: bool,
+
+    /// Seconds between checks
+    #[arg(short, long, value_name = "SECONDS", default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+
+    /// Install a LaunchAgent running the watcher in the background with notifications
+    #[arg(long, conflicts_with = "uninstall")]
+    pub install: bool,
+
+    /// Stop and remove the LaunchAgent
+    #[arg(long)]
+    pub uninstall: bool,
+}
+
+/// Balance operations
+#[derive(Subcommand)]
+pub enum AudioBalanceCommands {
+    /// Show the balance
+    Get,
+    /// Set the balance
+    Set {
+        /// Balance from 0 (left) over 0.5 (center) to 1 (right)
+        #[arg(value_parser = parse_balance)]
+        balance: f32,
+    },
+}
+
+/// Sample rate operations
+#[derive(Subcommand)]
+pub enum AudioRateCommands {
+    /// List the sample rates of the device, marking the current one
+    List,
+    /// Set the sample rate
+    Set {
+        /// Sample rate in Hz, e.g. 48000
+        #[arg(value_name = "HZ", value_parser = clap::value_parser!(u32).range(1..))]
+        rate: u32,
+    },
+}
+
+/// Arguments for the wallpaper subcommand
+///
+/// Without a subcommand, the desktop picture of each display is shown.
+#[derive(Parser)]
+pub struct WallpaperArgs {
+    /// Wallpaper operation to execute
+    #[command(subcommand)]
+    pub command: Option<WallpaperCommands>,
+}
+
+/// Wallpaper operations
+#[derive(Subcommand)]
+pub enum WallpaperCommands {
+    /// Show the desktop picture of each display
+    Get,
+    /// Set the desktop picture from an image or a folder of images
+    Set(WallpaperSetArgs),
+}
+
+/// Arguments for setting the desktop picture
+#[derive(Parser)]
+pub struct WallpaperSetArgs {
+    /// Image, or folder to take the next image from
+    pub path: PathBuf,
+
+    /// Display to change, 1 being the main display
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub display: Option<u32>,
+
+    /// Change all displays, each to a different image of a folder
+    #[arg(short, long, conflicts_with = "display")]
+    pub all_displays: bool,
+
+    /// Pick images of a folder at random instead of in order
+    #[arg(short, long)]
+    pub random: bool,
+}
+
+/// Arguments for the lock subcommand
+///
+/// Without a subcommand, the screen is locked right away.
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct LockArgs {
+    /// Lock operation to execute
+    #[command(subcommand)]
+    pub command: Option<LockCommands>,
+
+    /// Also lock the default keychain, so that apps ask for its password again
+    #[arg(short, long)]
+    pub keychain: bool,
+}
+
+/// Lock operations
+#[derive(Subcommand)]
+pub enum LockCommands {
+    /// Show and set when the password is required after sleep or the screen saver
+    Settings(LockSettingsArgs),
+}
+
+/// Arguments for the screen lock settings
+///
+/// Without options, the settings are shown.
+#[derive(Parser)]
+pub struct LockSettingsArgs {
+    /// Require the password `immediately`, after a time like `5s`, `5m` or `1h`, or `never`
+    #[arg(long, value_name = "DELAY", value_parser = parse_password_delay)]
+    pub require_password: Option<PasswordDelay>,
+}
+
+/// Time after sleep or the screen saver until the password is required
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PasswordDelay {
+    /// Right away
+    Immediately,
+    /// After a time
+    After(Duration),
+    /// Never; the screen does not lock
+    Never,
+}
+
+/// Arguments for the screen saver subcommand
+#[derive(Parser)]
+pub struct ScreensaverArgs {
+    /// Screen saver operation to execute
+    #[command(subcommand)]
+    pub command: ScreensaverCommands,
+}
+
+/// Screen saver operations
+#[derive(Subcommand)]
+pub enum ScreensaverCommands {
+    /// Start the screen saver now
+    Start,
+}
+
+/// Arguments for the Bluetooth subcommand
+#[derive(Parser)]
+pub struct BluetoothArgs {
+    /// Bluetooth operation to execute
+    #[command(subcommand)]
+    pub command: Option<BluetoothCommands>,
+}
+
+/// Bluetooth operations
+#[derive(Subcommand)]
+pub enum BluetoothCommands {
+    /// Show whether Bluetooth is on and the controller
+    Status,
+    /// Turn Bluetooth on
+    On,
+    /// Turn Bluetooth off
+    Off,
+    /// Turn Bluetooth on if it is off and off otherwise
+    Toggle,
+    /// List the paired devices and whether they are connected
+    Devices {
+        /// Print the devices as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Connect a paired device
+    Connect {
+        /// Name, part of the name or address of the device
+        device: String,
+    },
+    /// Disconnect a device
+    Disconnect {
+        /// Name, part of the name or address of the device
+        device: String,
+    },
+}
+
+/// Arguments for the keyboard subcommand
+#[derive(Parser)]
+pub struct KeyboardArgs {
+    /// Keyboard operation to execute
+    #[command(subcommand)]
+    pub command: KeyboardCommands,
+}
+
+/// Keyboard operations
+#[derive(Subcommand)]
+pub enum KeyboardCommands {
+    /// Show and set the brightness of the keyboard backlight of laptops
+    Backlight(KeyboardBacklightArgs),
+    /// Show and set how fast a held key repeats
+    Repeat(KeyboardRepeatArgs),
+    /// Show or switch swapping Caps Lock and Control, also after restarts
+    SwapCapsCtrl {
+        /// Swap the keys or restore them
+        #[arg(value_enum)]
+        state: Option<Switch>,
+    },
+}
+
+/// Arguments for the key repeat
+///
+/// Both are given in units of 15 ms. Without options, the settings are shown.
+#[derive(Parser)]
+pub struct KeyboardRepeatArgs {
+    /// Interval between repeats; 2 is the fastest of System Settings
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate: Option<u32>,
+
+    /// Delay until a held key repeats; 15 is the shortest of System Settings
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub delay: Option<u32>,
+}
+
+/// Arguments for the keyboard backlight
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct KeyboardBacklightArgs {
+    /// Backlight operation to execute
+    #[command(subcommand)]
+    pub command: Option<KeyboardBacklightCommands>,
+
+    /// Adjust the brightness to the ambient light automatically
+    #[arg(long, value_name = "STATE")]
+    pub auto: Option<Switch>,
+}
+
+/// Keyboard backlight operations
+#[derive(Subcommand)]
+pub enum KeyboardBacklightCommands {
+    /// Show the brightness and whether it is adjusted automatically
+    Get,
+    /// Set the brightness
+    Set {
+        /// Brightness from 0 to 1, in percent like `50%`, or `off`
+        #[arg(value_parser = parse_brightness)]
+        brightness: f32,
+    },
+}
+
+/// Arguments for the input subcommand
+#[derive(Parser)]
+pub struct InputArgs {
+    /// Input device to configure
+    #[command(subcommand)]
+    pub command: InputCommands,
+}
+
+/// Input devices
+#[derive(Subcommand)]
+pub enum InputCommands {
+    /// Show and change the trackpad settings
+    Trackpad(TrackpadArgs),
+    /// Show and change the mouse settings
+    Mouse(MouseArgs),
+}
+
+/// Arguments for the trackpad settings
+///
+/// Without options, the settings are shown.
+#[derive(Parser)]
+pub struct TrackpadArgs {
+    /// Click by tapping the trackpad
+    #[arg(long, value_name = "STATE")]
+    pub tap_to_click: Option<Switch>,
+
+    /// Tracking speed from 0 (slow) to 3 (fast)
+    #[arg(long, value_name = "SPEED", value_parser = parse_speed)]
+    pub tracking: Option<f32>,
+
+    /// Scroll content in the direction of the finger movement; applies to mice as well
+    #[arg(long, value_name = "STATE")]
+    pub natural_scroll: Option<Switch>,
+}
+
+/// Arguments for the mouse settings
+///
+/// Without options, the settings are shown.
+#[derive(Parser)]
+pub struct MouseArgs {
+    /// Tracking speed from 0 (slow) to 3 (fast)
+    #[arg(long, value_name = "SPEED", value_parser = parse_speed)]
+    pub speed: Option<f32>,
+}
+
+/// Arguments for the USB subcommand
+#[derive(Parser)]
+pub struct UsbArgs {
+    /// USB operation to execute
+    #[command(subcommand)]
+    pub command: UsbCommands,
+}
+
+/// USB operations
+#[derive(Subcommand)]
+pub enum UsbCommands {
+    /// List the connected USB devices as a tree of hubs and ports
+    List(UsbListArgs),
+}
+
+/// Arguments for listing USB devices
+#[derive(Parser)]
+pub struct UsbListArgs {
+    /// Print the devices as JSON
+    #[arg(short, long, conflicts_with = "watch")]
+    pub json: bool,
+
+    /// Keep running and print whenever a device is connected or disconnected
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Seconds between the checks of `--watch`
+    #[arg(short, long, default_value_t = 1, requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+}
+
+/// Arguments for the AirDrop subcommand
+#[derive(Parser)]
+pub struct AirdropArgs {
+    /// AirDrop operation to execute
+    #[command(subcommand)]
+    pub command: Option<AirdropCommands>,
+}
+
+/// AirDrop operations
+#[derive(Subcommand)]
+pub enum AirdropCommands {
+    /// Show who can find this Mac with AirDrop
+    Status,
+    /// Hide this Mac from AirDrop
+    Off,
+    /// Let only contacts find this Mac
+    Contacts,
+    /// Let everyone nearby find this Mac
+    Everyone,
+}
+
+/// Arguments for the disk usage subcommand
+///
+/// Without `--top`, the folders are browsed interactively in the terminal.
+#[derive(Parser)]
+pub struct DiskUsageArgs {
+    /// Folder to analyze
+    #[arg(value_name = "PATH", default_value = ".")]
+    pub path: PathBuf,
+
+    /// Print the N largest items of the folder instead of browsing them
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub top: Option<u32>,
+}
+
+/// Arguments for the bigfiles subcommand
+#[derive(Parser)]
+pub struct BigFilesArgs {
+    /// Folder to search
+    #[arg(value_name = "PATH", default_value = ".")]
+    pub path: PathBuf,
+
+    /// Only files taking at least this much space, e.g. 500MB or 2G
+    #[arg(long, value_name = "SIZE", default_value = "100MB", value_parser = parse_size)]
+    pub min_size: u64,
+
+    /// Only files not modified for this long, e.g. 30d, 2w or 1y
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    pub older_than: Option<Duration>,
+
+    /// Print the N largest files and the groups instead of selecting files
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub top: Option<u32>,
+
+    /// Print the files and the groups instead of selecting files
+    #[arg(short, long)]
+    pub list: bool,
+}
+
+/// Arguments for the dupes subcommand
+///
+/// Without `--clone` or `--hardlink`, the duplicates are only shown.
+#[derive(Parser)]
+pub struct DupesArgs {
+    /// Folder to search
+    #[arg(value_name = "PATH", default_value = ".")]
+    pub path: PathBuf,
+
+    /// Only files of at least this size, e.g. 100k or 1MB
+    #[arg(long, value_name = "SIZE", default_value = "1", value_parser = parse_size)]
+    pub min_size: u64,
+
+    /// Replace the duplicates with APFS clones of the kept file, which share its blocks until one is changed
+    #[arg(long, conflicts_with = "hardlink")]
+    pub clone: bool,
+
+    /// Replace the duplicates with hard links to the kept file, which make them the same file
+    #[arg(long)]
+    pub hardlink: bool,
+
+    /// Link duplicates even if their owner or permissions differ from the kept file
+    #[arg(short, long, requires = "hardlink")]
+    pub force: bool,
+
+    /// Replace without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Print the groups as JSON
+    #[arg(short, long, conflicts_with_all = ["clone", "hardlink"])]
+    pub json: bool,
+}
+
+/// Arguments for the clean subcommand
+///
+/// Without a category, all categories are cleaned.
+#[derive(Parser)]
+pub struct CleanArgs {
+    /// Caches of apps in ~/Library/Caches
+    #[arg(long)]
+    pub caches: bool,
+
+    /// Logs and crash reports in ~/Library/Logs
+    #[arg(long)]
+    pub logs: bool,
+
+    /// Xcode build products, device support files and simulator caches
+    #[arg(long)]
+    pub xcode: bool,
+
+    /// Downloads cached by Homebrew
+    #[arg(long)]
+    pub brew: bool,
+
+    /// Packages cached by npm
+    #[arg(long)]
+    pub npm: bool,
+
+    /// Show the space that can be recovered without deleting anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Delete without asking for confirmation
+    #[arg(short, long, conflicts_with = "dry_run")]
+    pub yes: bool,
+}
+
+/// Arguments for the shred subcommand
+#[derive(Parser)]
+pub struct ShredArgs {
+    /// Files to overwrite and delete
+    #[arg(
+        value_name = "FILE",
+        required_unless_present = "purgeable",
+        conflicts_with = "purgeable"
+    )]
+    pub files: Vec<PathBuf>,
+
+    /// Number of passes with random data
+    #[arg(short = 'n', long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..=35))]
+    pub passes: u32,
+
+    /// Finish with a pass of zeros
+    #[arg(short, long)]
+    pub zero: bool,
+
+    /// Shred the files in folders and remove the folders
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Shred or remove the snapshots without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Remove the local Time Machine snapshots of the startup volume, so its free space matches the available space
+    #[arg(long)]
+    pub purgeable: bool,
+}
+
+/// Arguments for the trash subcommand
+#[derive(Parser)]
+pub struct TrashArgs {
+    /// Trash operation to execute
+    #[command(subcommand)]
+    pub command: Option<TrashCommands>,
+}
+
+/// Trash operations
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    /// List the items in the Trash of every volume with where they came from
+    List,
+    /// Delete the items in the Trash for good
+    Empty {
+        /// Only delete items moved to the Trash longer ago, e.g. 30d, 2w or 1y
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+
+        /// Delete without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Move files to the Trash with Finder, so they can be put back
+    Put {
+        /// Files and folders to move
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Move an item from the Trash back to where it came from
+    Restore {
+        /// Name or part of the name of the item
+        item: String,
+    },
+}
+
+/// Arguments for the disk subcommand
+#[derive(Parser)]
+pub struct DiskArgs {
+    /// Disk operation to execute
+    #[command(subcommand)]
+    pub command: DiskCommands,
+}
+
+/// Disk operations
+#[derive(Subcommand)]
+pub enum DiskCommands {
+    /// Show wear, temperature, errors and a verdict from SMART for every drive
+    Health {
+        /// Print the health as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// List the disks with their partitions, volumes and mount points
+    List {
+        /// Print the disks as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Mount the volumes of a disk or a single volume
+    Mount {
+        /// Disk or volume identifier like disk4 or disk4s1, or a volume name
+        disk: String,
+    },
+    /// Unmount a volume
+    Unmount {
+        /// Volume name or part of it, mount point or identifier like disk4s1
+        volume: String,
+        /// Unmount even if apps have files open on the volume
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Unmount all volumes of a disk and eject it
+    Eject {
+        /// Disk identifier like disk4, or a volume of the disk
+        disk: String,
+        /// Unmount even if apps have files open on the volumes
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// Arguments for the dmg subcommand
+#[derive(Parser)]
+pub struct DmgArgs {
+    /// Disk image operation to execute; lists the attached images if omitted
+    #[command(subcommand)]
+    pub command: Option<DmgCommands>,
+}
+
+/// Disk image operations
+#[derive(Subcommand)]
+pub enum DmgCommands {
+    /// List the attached disk images with their mount points
+    List,
+    /// Create a disk image, empty or with the contents of a folder
+    Create {
+        /// Path of the image; `.dmg` is appended if it has no extension
+        image: PathBuf,
+        /// Folder to copy into the image; the image is compressed and read-only unless a size is given
+        #[arg(long)]
+        from: Option<PathBuf>,
+        /// Size of a writable image, e.g. 500m or 2g
+        #[arg(short, long, value_parser = parse_size)]
+        size: Option<u64>,
+        /// Encrypt the image with AES-256 and a password
+        #[arg(short, long)]
+        encrypted: bool,
+        /// Name of the volume; defaults to the name of the folder or image
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Do not save the password of an encrypted image in the keychain
+        #[arg(long)]
+        no_keychain: bool,
+    },
+    /// Attach a disk image, with the password from the keychain if it is encrypted
+    Attach {
+        /// Path of the image
+        image: PathBuf,
+        /// Save the password in the keychain if it had to be entered
+        #[arg(long)]
+        remember: bool,
+    },
+    /// Detach a disk image
+    Detach {
+        /// Image name or part of it, image path, mount point or disk identifier
+        image: String,
+        /// Detach even if apps have files open on the volume
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// Arguments for the ramdisk subcommand
+#[derive(Parser)]
+pub struct RamdiskArgs {
+    /// RAM disk operation to execute
+    #[command(subcommand)]
+    pub command: Option<RamdiskCommands>,
+}
+
+/// RAM disk operations
+#[derive(Subcommand)]
+pub enum RamdiskCommands {
+    /// List the RAM disks and the ones created at login
+    List,
+    /// Create a RAM disk and mount it in /Volumes
+    Create {
+        /// Size of the disk, e.g. 512m or 4G
+        #[arg(short, long, value_parser = parse_size)]
+        size: u64,
+        /// Name of the volume
+        #[arg(short, long, default_value = "RAM Disk")]
+        name: String,
+        /// Format the disk as Mac OS Extended instead of APFS
+        #[arg(long)]
+        hfs: bool,
+        /// Create the RAM disk again at every login
+        #[arg(long)]
+        at_login: bool,
+    },
+    /// Destroy a RAM disk with its contents and stop creating it at login
+    Destroy {
+        /// Name of the RAM disk or part of it; may be left out if there is only one
+        name: Option<String>,
+        /// Destroy it even if apps have files open on it
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// Arguments for the spotlight subcommand
+#[derive(Parser)]
+pub struct SpotlightArgs {
+    /// Spotlight operation to execute; shows the status if omitted
+    #[command(subcommand)]
+    pub command: Option<SpotlightCommands>,
+}
+
+/// Spotlight operations
+#[derive(Subcommand)]
+pub enum SpotlightCommands {
+    /// Show the indexing state of all volumes and the CPU used by Spotlight
+    Status,
+    /// Turn indexing of a volume on
+    On {
+        /// Mount point or volume name; the startup volume if omitted
+        volume: Option<String>,
+    },
+    /// Turn indexing of a volume off
+    Off {
+        /// Mount point or volume name; the startup volume if omitted
+        volume: Option<String>,
+    },
+    /// Delete the index of a volume and build it again
+    Rebuild {
+        /// Mount point or volume name; the startup volume if omitted
+        volume: Option<String>,
+    },
+    /// List, add and remove folders excluded from Spotlight (Privacy in System Settings)
+    Exclusions {
+        /// Exclusion operation to execute; lists the exclusions if omitted
+        #[command(subcommand)]
+        command: Option<SpotlightExclusionsCommands>,
+    },
+}
+
+/// Spotlight exclusion operations
+#[derive(Subcommand)]
+pub enum SpotlightExclusionsCommands {
+    /// List the excluded folders of all volumes
+    List,
+    /// Exclude folders from Spotlight
+    Add {
+        /// Folders to exclude
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Let Spotlight index excluded folders again
+    Remove {
+        /// Excluded folders
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// Arguments for the find subcommand
+#[derive(Parser)]
+pub struct FindArgs {
+    /// Words and filters: kind:, ext:, name:, modified:, created:, used:, size:, tag:, author:; `-` excludes
+    /// a term, which must then be quoted with the others or follow `--`
+    #[arg(required = true)]
+    pub query: Vec<String>,
+    /// Only search below this folder
+    #[arg(short, long = "in", value_name = "FOLDER")]
+    pub folder: Option<PathBuf>,
+    /// Show at most this many results, most recently modified first
+    #[arg(short = 'n', long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub limit: Option<u32>,
+    /// Print the results as JSON
+    #[arg(short, long)]
+    pub json: bool,
+    /// Run a shell command for every result; `{}` is replaced by the path, which is appended otherwise
+    #[arg(short = 'x', long, value_name = "COMMAND", conflicts_with = "json")]
+    pub exec: Option<String>,
+    /// Print the Spotlight query instead of searching
+    #[arg(long)]
+    pub predicate: bool,
+}
+
+/// Arguments for the tags subcommand
+#[derive(Parser)]
+pub struct TagsArgs {
+    /// Tag operation to execute
+    #[command(subcommand)]
+    pub command: TagsCommands,
+}
+
+/// Finder tag operations
+#[derive(Subcommand)]
+pub enum TagsCommands {
+    /// List the tags of files
+    List {
+        /// Files and folders
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Print the tags as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Add a tag to files
+    Add {
+        /// Name of the tag; Finder's color tags like Red get their color
+        tag: String,
+        /// Files and folders to tag
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Color of the tag, also changing the color of a tag the files have
+        #[arg(short, long, value_enum)]
+        color: Option<TagColor>,
+    },
+    /// Remove a tag from files
+    Remove {
+        /// Name of the tag
+        tag: String,
+        /// Files and folders to remove the tag from
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Find the files with a tag with Spotlight
+    Find {
+        /// Name of the tag
+        tag: String,
+        /// Only search below this folder
+        #[arg(short, long = "in", value_name = "FOLDER")]
+        folder: Option<PathBuf>,
+        /// Print the results as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+}
+
+/// Colors of Finder tags, in the order of their numbers
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TagColor {
+    /// No color
+    None,
+    /// Gray
+    Gray,
+    /// Green
+    Green,
+    /// Purple
+    Purple,
+    /// Blue
+    Blue,
+    /// Yellow
+    Yellow,
+    /// Red
+    Red,
+    /// Orange
+    Orange,
+}
+
+/// Arguments for the xattr subcommand
+#[derive(Parser)]
+pub struct XattrArgs {
+    /// Extended attribute operation to execute
+    #[command(subcommand)]
+    pub command: XattrCommands,
+}
+
+/// Extended attribute operations
+#[derive(Subcommand)]
+pub enum XattrCommands {
+    /// Show the extended attributes of a file and where it was downloaded from
+    Show {
+        /// File or folder
+        path: PathBuf,
+        /// Print the values in hexadecimal instead of decoding them
+        #[arg(long)]
+        hex: bool,
+    },
+    /// Remove the quarantine of files and everything in folders, so they open without a warning
+    StripQuarantine {
+        /// Files, apps and folders
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Copy the extended attributes of a file to another one, replacing attributes with the same name
+    Copy {
+        /// File to copy the attributes of
+        source: PathBuf,
+        /// File to copy the attributes to
+        target: PathBuf,
+        /// Leave out the quarantine
+        #[arg(long)]
+        no_quarantine: bool,
+    },
+}
+
+/// State of a setting that can be switched on and off
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Switch {
+    /// Turn the setting on
+    On,
+    /// Turn the setting off
+    Off,
+}
+
+/// Parses a duration given as milliseconds (`500`, `500ms`) or seconds (`2s`)
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, millis_per_unit) = match value.strip_suffix("ms") {
+        Some(number) => (number, 1),
+        None => match value.strip_suffix('s') {
+            Some(number) => (number, 1000),
+            None => (value, 1),
+        },
+    };
+    let millis = number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(millis_per_unit));
+    match millis {
+        Some(millis) if millis > 0 => Ok(Duration::from_millis(millis)),
+        _ => Err(format!("'{value}' is not a duration, expected e.g. 500ms or 2s")),
+    }
+}
+
+/// Parses a balance between 0 (left) and 1 (right)
+fn parse_balance(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(balance) if (0.0..=1.0).contains(&balance) => Ok(balance),
+        _ => Err(format!(
+            "'{value}' is not a balance, expected a number from 0 (left) to 1 (right)"
+        )),
+    }
+}
+
+/// Parses when the password is required: `immediately`, `never` or a span of time like `5m`
+fn parse_password_delay(value: &str) -> Result<PasswordDelay, String> {
+    match value.trim().to_lowercase().as_str() {
+        "immediately" | "immediate" | "0" => Ok(PasswordDelay::Immediately),
+        "never" | "off" => Ok(PasswordDelay::Never),
+        span => parse_span(span)
+            .map(PasswordDelay::After)
+            .map_err(|_| format!("'{value}' is not a delay, expected immediately, never or e.g. 5s, 5m or 1h")),
+    }
+}
+
+/// Parses an age in days, weeks or years, e.g. `30d`, `2w` or `1y`
+pub fn parse_age(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{value}' is not an age, expected e.g. 30d, 2w or 1y");
+    let value = value.trim();
+    let days_per_unit = match value.chars().last() {
+        Some('d') => 1,
+        Some('w') => 7,
+        Some('y') => 365,
+        _ => return Err(invalid()),
+    };
+    let seconds = value[..value.len() - 1]
+        .parse::<u64>()
+        .ok()
+        .and_then(|count| count.checked_mul(days_per_unit * 86_400));
+    match seconds {
+        Some(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a size in bytes with a decimal unit, e.g. `500m`, `2g`, `500MB` or `1.5TB`
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let invalid = || format!("'{value}' is not a size, expected e.g. 500m, 2g or 1.5TB");
+    let lowercase = value.trim().to_lowercase();
+    let number = lowercase.strip_suffix('b').unwrap_or(&lowercase);
+    let (number, unit) = match number.chars().last() {
+        Some('k') => (&number[..number.len() - 1], 1e3),
+        Some('m') => (&number[..number.len() - 1], 1e6),
+        Some('g') => (&number[..number.len() - 1], 1e9),
+        Some('t') => (&number[..number.len() - 1], 1e12),
+        _ => (number, 1.0),
+    };
+    match number.trim().parse::<f64>() {
+        Ok(count) if count > 0.0 && count.is_finite() => Ok((count * unit).round() as u64),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a brightness from 0 to 1, in percent (`50%`) or `off`
+fn parse_brightness(value: &str) -> Result<f32, String> {
+    let value = value.trim();
+    let brightness = match value.strip_suffix('%') {
+        _ if value.eq_ignore_ascii_case("off") => Ok(0.0),
+        Some(percent) => percent.trim().parse::<f32>().map(|percent| percent / 100.0),
+        None => value.parse::<f32>(),
+    };
+    match brightness {
+        Ok(brightness) if (0.0..=1.0).contains(&brightness) => Ok(brightness),
+        _ => Err(format!("'{value}' is not a brightness, expected e.g. 0.5, 50% or off")),
+    }
+}
+
+/// Parses a tracking speed from 0 to 3, the range of the sliders in System Settings
+fn parse_speed(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(speed) if (0.0..=3.0).contains(&speed) => Ok(speed),
+        _ => Err(format!(
+            "'{value}' is not a speed, expected a number from 0 (slow) to 3 (fast)"
+        )),
+    }
+}
+
+/// Parses a span of time given in hours, minutes and seconds, e.g. `2h`, `1h30m` or `90s`
+fn parse_span(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{value}' is not a duration, expected e.g. 45m, 2h or 1h30m");
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let count: u64 = number.parse().map_err(|_| invalid())?;
+        seconds = count
+            .checked_mul(unit)
+            .and_then(|span| seconds.checked_add(span))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Arguments for the Wi-Fi subcommand
+#[derive(Parser)]
+pub struct WifiArgs {
+    /// Wi-Fi operation to execute
+    #[command(subcommand)]
+    pub command: WifiCommands,
+}
+
+/// Wi-Fi operations
+#[derive(Subcommand)]
+pub enum WifiCommands {
+    /// List visible Wi-Fi networks
+    Scan(WifiScanArgs),
+    /// Join a Wi-Fi network
+    Join {
+        /// Name of the network
+        ssid: String,
+
+        /// Password of the network (visible in the process list, prefer the keychain)
+        #[arg(long, conflicts_with = "keychain")]
+        password: Option<String>,
+
+        /// Require the password stored in the keychain, fail if there is none
+        #[arg(long)]
+        keychain: bool,
+    },
+    /// Turn Wi-Fi on
+    On,
+    /// Turn Wi-Fi off
+    Off,
+    /// Show whether Wi-Fi is on and which network it is connected to
+    Status,
+    /// Disconnect from the current Wi-Fi network
+    Disconnect,
+    /// Manage the list of known (preferred) Wi-Fi networks
+    Known(KnownArgs),
+    /// Continuously chart signal strength, noise, transmit rate and channel of the current network
+    Monitor {
+        /// Milliseconds between samples
+        #[arg(long, value_name = "MS", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(100..))]
+        interval: u64,
+
+        /// Stop after this number of samples and print a summary
+        #[arg(short = 'n', long, value_name = "N")]
+        count: Option<u64>,
+    },
+}
+
+/// Arguments for managing known Wi-Fi networks
+///
+/// Without a subcommand or option, the known networks are listed.
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct KnownArgs {
+    /// Known network operation to execute
+    #[command(subcommand)]
+    pub command: Option<KnownCommands>,
+
+    /// Forget all known networks without encryption
+    #[arg(long)]
+    pub forget_open_networks: bool,
+
+    /// Forget the open networks without asking for confirmation
+    #[arg(short, long, requires = "forget_open_networks")]
+    pub yes: bool,
+
+    /// Print the commands that would forget the open networks instead of running them
+    #[arg(long, requires = "forget_open_networks", conflicts_with = "yes")]
+    pub dry_run: bool,
+}
+
+/// Known Wi-Fi network operations
+#[derive(Subcommand)]
+pub enum KnownCommands {
+    /// List known networks in the order they are joined automatically
+    List,
+    /// Remove a network from the known networks
+    Forget {
+        /// Name of the network
+        ssid: String,
+    },
+    /// Move a network to a position in the known networks list
+    Priority {
+        /// Name of the network
+        ssid: String,
+
+        /// New position, 1 is joined first
+        #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+        position: u32,
+    },
+}
+
+/// Arguments for scanning Wi-Fi networks
+#[derive(Parser)]
+pub struct WifiScanArgs {
+    /// Order of the listed networks
+    #[arg(long, value_enum, default_value_t = ScanOrder::Signal)]
+    pub sort: ScanOrder,
+
+    /// Print the networks as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Sort order of scanned Wi-Fi networks
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ScanOrder {
+    /// Strongest signal first
+    Signal,
+    /// Alphabetically by network name
+    Ssid,
+    /// By channel number
+    Channel,
 }
@@ -0,0 +1,401 @@
+//! User configuration
+//!
+//! Rempower stores its configuration as TOML in `~/.config/rempower/config.toml`
+//! (or `$XDG_CONFIG_HOME/rempower/config.toml` if `XDG_CONFIG_HOME` is set).
+//! A missing configuration file is treated as an empty configuration.
+//!
+//! Saving only updates the table that changed, e.g. `[dns.profiles]`, in the
+//! existing file, so comments, key order and keys unknown to rempower are kept.
+//!
+//! # Example
+//!
+//! ```toml
+//! [dns]
+//! exclude = ["Tailscale*", "/vpn/"]
+//!
+//! [dns.profiles.work]
+//! servers = ["10.0.0.53", "10.0.0.54"]
+//!
+//! [dns.profiles.adblock]
+//! servers = ["94.140.14.14", "94.140.15.15"]
+//!
+//! [[dns.rules]]
+//! when = "ssid=Office*"
+//! use = "profile:work"
+//!
+//! [ip]
+//! public_ip_url = "https://ifconfig.co/ip"
+//!
+//! [proxy.presets.office]
+//! web = "proxy.example.com:3128"
+//! secure_web = "proxy.example.com:3128"
+//!
+//! [wol.hosts.nas]
+//! mac = "00:11:32:ab:cd:ef"
+//! broadcast = "192.168.1.255"
+//!
+//! [share.favorites.media]
+//! url = "smb://alice@nas.local/media"
+//! keychain = true
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+
+/// Name of the configuration file inside the configuration directory
+const CONFIG_FILE: &str = "config.toml";
+
+/// Root of the configuration file
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Settings of the DNS subcommand
+    pub dns: DnsConfig,
+    /// Settings of the proxy subcommand
+    pub proxy: ProxyConfig,
+    /// Settings of the IP subcommand
+    pub ip: IpConfig,
+    /// Settings of the Wake-on-LAN subcommand
+    pub wol: WolConfig,
+    /// Settings of the share subcommand
+    pub share: ShareConfig,
+}
+
+/// Settings of the DNS subcommand
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsConfig {
+    /// Patterns of network interfaces excluded from bulk DNS changes (see `rem dns --exclude`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Named DNS server sets, keyed by profile name
+    pub profiles: BTreeMap<String, DnsProfile>,
+    /// Rules applying DNS presets depending on the network (see `rem dns auto`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<DnsRule>,
+}
+
+/// A named set of DNS servers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsProfile {
+    /// DNS server addresses of the profile
+    pub servers: Vec<IpAddr>,
+}
+
+/// A rule applying a DNS preset when the current network matches a condition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsRule {
+    /// Condition, e.g. `ssid=HomeWiFi` or `location=Office`
+    pub when: String,
+    /// DNS preset, e.g. `pub`, `pub:quad9`, `profile:work` or `dhcp`
+    #[serde(rename = "use")]
+    pub preset: String,
+}
+
+/// Settings of the proxy subcommand
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    /// Named proxy settings, keyed by preset name
+    pub presets: BTreeMap<String, ProxyPreset>,
+}
+
+/// A named set of proxies
+///
+/// Proxies are given as `HOST:PORT`; proxies without a value are disabled when
+/// the preset is applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyPreset {
+    /// Web (HTTP) proxy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web: Option<String>,
+    /// Secure web (HTTPS) proxy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secure_web: Option<String>,
+    /// SOCKS proxy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socks: Option<String>,
+    /// URL of a proxy auto-configuration (PAC) file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pac: Option<String>,
+}
+
+/// Settings of the IP subcommand
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpConfig {
+    /// HTTPS endpoint replying with the client address as plain text (see `rem ip --endpoint`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_ip_url: Option<String>,
+}
+
+/// Settings of the Wake-on-LAN subcommand
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WolConfig {
+    /// Hosts that can be woken by name, keyed by alias
+    pub hosts: BTreeMap<String, WolHost>,
+}
+
+/// A host that can be woken by its alias
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WolHost {
+    /// MAC address of the host, e.g. `00:11:32:ab:cd:ef`
+    pub mac: String,
+    /// Broadcast address of the network of the host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broadcast: Option<Ipv4Addr>,
+}
+
+/// Settings of the share subcommand
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareConfig {
+    /// Network shares that can be mounted by name, keyed by favorite name
+    pub favorites: BTreeMap<String, ShareFavorite>,
+}
+
+/// A network share that can be mounted by its name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareFavorite {
+    /// URL of the share, e.g. `smb://alice@nas.local/media`
+    pub url: String,
+    /// Mount with the password stored in the keychain (see `rem share mount --keychain`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keychain: bool,
+}
+
+impl Config {
+    /// Loads the configuration from the configuration file
+    ///
+    /// Returns the default configuration if the file does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not valid TOML.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = config_file()?;
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content).map_err(|e| format!("Invalid configuration file {}: {e}", path.display()).into())
+    }
+
+    /// Saves a table of the configuration to the configuration file
+    ///
+    /// Only the table at `path`, e.g. `["dns", "profiles"]`, is updated in the
+    /// existing file; the rest of the file is kept as it is. Entries of the table
+    /// whose values did not change keep their formatting and comments.
+    /// Creates the configuration directory if it does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Keys leading to the table that changed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the existing file is not valid TOML, a key of `path`
+    /// is not a table in it, or the configuration cannot be serialized or written.
+    pub fn save(&self, path: &[&str]) -> Result<(), Box<dyn Error>> {
+        let file = config_file()?;
+        let mut document = match fs::read_to_string(&file) {
+            Ok(content) => content
+                .parse::<DocumentMut>()
+                .map_err(|e| format!("Invalid configuration file {}: {e}", file.display()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => DocumentMut::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let updated: DocumentMut = toml::to_string(self)?.parse()?;
+
+        let (key, parents) = path.split_last().ok_or("No configuration table to save")?;
+        let mut table: &mut dyn TableLike = document.as_table_mut();
+        for parent in parents {
+            let mut implicit = Table::new();
+            implicit.set_implicit(true);
+            table = table
+                .entry(parent)
+                .or_insert(Item::Table(implicit))
+                .as_table_like_mut()
+                .ok_or_else(|| format!("'{parent}' in {} is not a table", file.display()))?;
+        }
+        match path.iter().try_fold(updated.as_item(), |item, key| item.get(key)) {
+            Some(new) => match table.get_mut(key) {
+                Some(old) => merge(old, new),
+                None => {
+                    table.insert(key, detached(new));
+                }
+            },
+            None => {
+                table.remove(key);
+            }
+        }
+
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file, document.to_string())?;
+        Ok(())
+    }
+}
+
+/// Updates an item of the configuration file to a newly serialized one
+///
+/// Tables are updated key by key and values are only replaced if they changed,
+/// keeping the comments around them.
+fn merge(old: &mut Item, new: &Item) {
+    if let (Some(old), Some(new)) = (old.as_table_like_mut(), new.as_table_like()) {
+        merge_table(old, new);
+    } else if let (Some(old), Some(new)) = (old.as_array_of_tables_mut(), new.as_array_of_tables()) {
+        merge_tables(old, new);
+    } else if let (Some(old), Some(new)) = (old.as_value_mut(), new.as_value()) {
+        if !same_value(old, new) {
+            let decor = old.decor().clone();
+            *old = new.clone();
+            *old.decor_mut() = decor;
+        }
+    } else {
+        *old = detached(new);
+    }
+}
+
+/// Updates a table key by key, removing the keys the new table lacks
+fn merge_table(old: &mut dyn TableLike, new: &dyn TableLike) {
+    let removed: Vec<String> = old
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !new.contains_key(key))
+        .collect();
+    for key in removed {
+        old.remove(&key);
+    }
+    for (key, item) in new.iter() {
+        match old.get_mut(key) {
+            Some(existing) => merge(existing, item),
+            None => {
+                old.insert(key, detached(item));
+            }
+        }
+    }
+}
+
+/// Updates an array of tables table by table
+fn merge_tables(old: &mut ArrayOfTables, new: &ArrayOfTables) {
+    while old.len() > new.len() {
+        old.remove(old.len() - 1);
+    }
+    for (index, table) in new.iter().enumerate() {
+        match old.get_mut(index) {
+            Some(existing) => merge_table(existing, table),
+            None => {
+                let mut table = table.clone();
+                clear_positions(&mut table);
+                old.push(table);
+            }
+        }
+    }
+}
+
+/// Returns whether two values are equal, regardless of their formatting
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_value(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| same_value(a, b)))
+        }
+        _ => false,
+    }
+}
+
+/// Copies an item of another document
+///
+/// The positions of its tables are cleared, so they are written after the
+/// tables preceding them instead of where they were in the other document.
+fn detached(item: &Item) -> Item {
+    let mut item = item.clone();
+    match &mut item {
+        Item::Table(table) => clear_positions(table),
+        Item::ArrayOfTables(tables) => tables.iter_mut().for_each(clear_positions),
+        Item::None | Item::Value(_) => {}
+    }
+    item
+}
+
+/// Clears the document positions of a table and its nested tables
+fn clear_positions(table: &mut Table) {
+    table.set_position(None);
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(table) => clear_positions(table),
+            Item::ArrayOfTables(tables) => tables.iter_mut().for_each(clear_positions),
+            Item::None | Item::Value(_) => {}
+        }
+    }
+}
+
+/// Returns the rempower configuration directory
+///
+/// Uses `$XDG_CONFIG_HOME/rempower` if set, otherwise `~/.config/rempower`.
+///
+/// # Errors
+///
+/// Returns an error if neither `XDG_CONFIG_HOME` nor `HOME` is set.
+pub fn config_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()?.join(".config"),
+    };
+    Ok(base.join("rempower"))
+}
+
+/// Returns the rempower state directory
+///
+/// State files are written by rempower itself (e.g. backups) and are not meant
+/// to be edited. Uses `$XDG_STATE_HOME/rempower` if set, otherwise `~/.local/state/rempower`.
+///
+/// # Errors
+///
+/// Returns an error if neither `XDG_STATE_HOME` nor `HOME` is set.
+pub fn state_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let base = match env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()?.join(".local").join("state"),
+    };
+    Ok(base.join("rempower"))
+}
+
+/// Returns the path of the configuration file
+///
+/// # Errors
+///
+/// Returns an error if the configuration directory cannot be determined.
+pub fn config_file() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(config_dir()?.join(CONFIG_FILE))
+}
+
+/// Returns the home directory of the current user
+///
+/// # Errors
+///
+/// Returns an error if `HOME` is not set.
+pub fn home_dir() -> Result<PathBuf, Box<dyn Error>> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| "HOME environment variable is not set".into())
+}
@@ -0,0 +1,198 @@
+//! I/O Registry access through IOKit
+//!
+//! Hardware like batteries, displays, USB devices and drives is described by
+//! services in the I/O Registry, which are found by the name of their class.
+//! Their properties are Core Foundation types, read through [`Properties`].
+//! Functions of IOKit that only one command needs are declared by that command.
+
+use core_foundation::array::CFArray;
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef, CFMutableDictionaryRef};
+use core_foundation::number::CFNumber;
+use core_foundation::string::{CFString, CFStringRef};
+use std::error::Error;
+use std::ffi::{CStr, c_char, c_void};
+use std::ptr;
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOServiceMatching(name: *const c_char) -> CFMutableDictionaryRef;
+    fn IOServiceGetMatchingService(main_port: u32, matching: CFMutableDictionaryRef) -> u32;
+    fn IOServiceGetMatchingServices(main_port: u32, matching: CFMutableDictionaryRef, iterator: *mut u32) -> i32;
+    fn IOIteratorNext(iterator: u32) -> u32;
+    fn IORegistryEntryCreateCFProperties(
+        entry: u32,
+        properties: *mut CFMutableDictionaryRef,
+        allocator: *const c_void,
+        options: u32,
+    ) -> i32;
+    fn IORegistryEntryCreateCFProperty(
+        entry: u32,
+        key: CFStringRef,
+        allocator: *const c_void,
+        options: u32,
+    ) -> CFTypeRef;
+    fn IORegistryEntrySearchCFProperty(
+        entry: u32,
+        plane: *const c_char,
+        key: CFStringRef,
+        allocator: *const c_void,
+        options: u32,
+    ) -> CFTypeRef;
+    fn IOObjectRelease(object: u32) -> i32;
+}
+
+/// `kIOMainPortDefault`
+const MAIN_PORT_DEFAULT: u32 = 0;
+
+/// `kIORegistryIterateRecursively`
+const ITERATE_RECURSIVELY: u32 = 1;
+
+/// A service of the I/O Registry, released on drop
+pub struct Service(u32);
+
+impl Service {
+    /// Returns the `io_service_t` of this service for other IOKit functions
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+
+    /// Reads all properties of this service
+    pub fn properties(&self) -> Option<Properties> {
+        let mut properties: CFMutableDictionaryRef = ptr::null_mut();
+        let result = unsafe { IORegistryEntryCreateCFProperties(self.0, &mut properties, ptr::null(), 0) };
+        if result != 0 || properties.is_null() {
+            return None;
+        }
+        Some(Properties(unsafe {
+            CFDictionary::wrap_under_create_rule(properties as CFDictionaryRef)
+        }))
+    }
+
+    /// Reads a property of this service
+    pub fn property(&self, key: &str) -> Option<CFType> {
+        let key = CFString::new(key);
+        let value = unsafe { IORegistryEntryCreateCFProperty(self.0, key.as_concrete_TypeRef(), ptr::null(), 0) };
+        (!value.is_null()).then(|| unsafe { CFType::wrap_under_create_rule(value) })
+    }
+
+    /// Reads a property of this service or the first of its children that has it
+    pub fn search_property(&self, key: &str) -> Option<CFType> {
+        let key = CFString::new(key);
+        let value = unsafe {
+            IORegistryEntrySearchCFProperty(
+                self.0,
+                c"IOService".as_ptr(),
+                key.as_concrete_TypeRef(),
+                ptr::null(),
+                ITERATE_RECURSIVELY,
+            )
+        };
+        (!value.is_null()).then(|| unsafe { CFType::wrap_under_create_rule(value) })
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        unsafe { IOObjectRelease(self.0) };
+    }
+}
+
+/// Returns the first service of a class
+///
+/// # Returns
+///
+/// `None` if no service of the class exists.
+pub fn matching_service(class: &CStr) -> Option<Service> {
+    let matching = unsafe { IOServiceMatching(class.as_ptr()) };
+    if matching.is_null() {
+        return None;
+    }
+    // The matching dictionary is consumed by the call
+    let service = unsafe { IOServiceGetMatchingService(MAIN_PORT_DEFAULT, matching) };
+    (service != 0).then_some(Service(service))
+}
+
+/// Returns all services of a class
+///
+/// # Errors
+///
+/// Returns an error if the I/O Registry cannot be searched.
+pub fn matching_services(class: &CStr) -> Result<Vec<Service>, Box<dyn Error>> {
+    let matching = unsafe { IOServiceMatching(class.as_ptr()) };
+    if matching.is_null() {
+        return Err(format!(
+            "Failed to create the matching dictionary of {}",
+            class.to_string_lossy()
+        )
+        .into());
+    }
+    let mut iterator = 0;
+    // The matching dictionary is consumed by the call
+    let result = unsafe { IOServiceGetMatchingServices(MAIN_PORT_DEFAULT, matching, &mut iterator) };
+    if result != 0 {
+        return Err(format!(
+            "Failed to search the I/O Registry for {} (error {result:#x})",
+            class.to_string_lossy()
+        )
+        .into());
+    }
+
+    let mut services = Vec::new();
+    loop {
+        let service = unsafe { IOIteratorNext(iterator) };
+        if service == 0 {
+            break;
+        }
+        services.push(Service(service));
+    }
+    unsafe { IOObjectRelease(iterator) };
+    Ok(services)
+}
+
+/// A property list dictionary with typed accessors
+pub struct Properties(pub CFDictionary<CFString, CFType>);
+
+impl Properties {
+    /// Returns an integer property
+    pub fn number(&self, key: &str) -> Option<i64> {
+        self.0
+            .find(CFString::new(key))
+            .and_then(|value| value.downcast::<CFNumber>())
+            .and_then(|number| number.to_i64())
+    }
+
+    /// Returns a boolean property
+    pub fn boolean(&self, key: &str) -> Option<bool> {
+        self.0
+            .find(CFString::new(key))
+            .and_then(|value| value.downcast::<CFBoolean>())
+            .map(bool::from)
+    }
+
+    /// Returns a string property
+    pub fn string(&self, key: &str) -> Option<String> {
+        self.0
+            .find(CFString::new(key))
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|string| string.to_string())
+    }
+
+    /// Returns the dictionaries of an array property
+    pub fn dictionaries(&self, key: &str) -> Vec<Properties> {
+        self.0
+            .find(CFString::new(key))
+            .and_then(|value| value.downcast::<CFArray>())
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|item| unsafe { CFType::wrap_under_get_rule(*item) }.downcast::<CFDictionary>())
+                    .map(|dictionary| {
+                        Properties(unsafe { CFDictionary::wrap_under_get_rule(dictionary.as_concrete_TypeRef()) })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,201 @@
+//! LaunchAgents of the current user
+//!
+//! Commands that run in the background (watchers, schedules, login actions)
+//! install a LaunchAgent in `~/Library/LaunchAgents`. This module generates the
+//! property lists and loads and unloads them with `launchctl`.
+//!
+//! # System Commands Used
+//!
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload LaunchAgents
+
+use crate::config;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// When launchd starts the program of a LaunchAgent
+#[derive(Clone, Copy)]
+pub enum Start {
+    /// Once at login
+    AtLogin,
+    /// At login, and again whenever it exits
+    KeepAlive,
+    /// At login and then every given number of seconds
+    Interval(u64),
+    /// Daily at the given hour and minute
+    Daily(u8, u8),
+}
+
+/// Where the output of a LaunchAgent is written
+#[derive(Clone, Copy)]
+pub enum Log<'a> {
+    /// The output is discarded
+    None,
+    /// Only errors are appended to the file
+    Errors(&'a Path),
+    /// Output and errors are appended to the file
+    All(&'a Path),
+}
+
+/// A LaunchAgent running a program with arguments
+pub struct LaunchAgent<'a> {
+    /// Label identifying the LaunchAgent, also the name of its file
+    pub label: &'a str,
+    /// Program and its arguments
+    pub arguments: &'a [String],
+    /// When the program is started
+    pub start: Start,
+    /// Where the output of the program is written
+    pub log: Log<'a>,
+}
+
+impl LaunchAgent<'_> {
+    /// Generates the property list of this LaunchAgent
+    pub fn plist(&self) -> String {
+        let label = xml_escape(self.label);
+        let arguments: String = self
+            .arguments
+            .iter()
+            .map(|arg| format!("\t\t<string>{}</string>\n", xml_escape(arg)))
+            .collect();
+        let start = match self.start {
+            Start::AtLogin => "\t<key>RunAtLoad</key>\n\t<true/>\n".to_string(),
+            Start::KeepAlive => "\t<key>RunAtLoad</key>\n\t<true/>\n\t<key>KeepAlive</key>\n\t<true/>\n".to_string(),
+            Start::Interval(seconds) => {
+                format!(
+                    "\t<key>StartInterval</key>\n\t<integer>{seconds}</integer>\n\t<key>RunAtLoad</key>\n\t<true/>\n"
+                )
+            }
+            Start::Daily(hour, minute) => format!(
+                "\t<key>StartCalendarInterval</key>\n\t<dict>\n\t\t<key>Hour</key>\n\t\t<integer>{hour}</integer>\n\
+                 \t\t<key>Minute</key>\n\t\t<integer>{minute}</integer>\n\t</dict>\n"
+            ),
+        };
+        let log = match self.log {
+            Log::None => String::new(),
+            Log::Errors(path) => format!(
+                "\t<key>StandardErrorPath</key>\n\t<string>{}</string>\n",
+                xml_escape(&path.to_string_lossy())
+            ),
+            Log::All(path) => {
+                let path = xml_escape(&path.to_string_lossy());
+                format!(
+                    "\t<key>StandardOutPath</key>\n\t<string>{path}</string>\n\
+                     \t<key>StandardErrorPath</key>\n\t<string>{path}</string>\n"
+                )
+            }
+        };
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Label</key>
+	<string>{label}</string>
+	<key>ProgramArguments</key>
+	<array>
+{arguments}	</array>
+{start}{log}</dict>
+</plist>
+"#
+        )
+    }
+
+    /// Writes and loads this LaunchAgent
+    ///
+    /// An installed LaunchAgent with the same label is unloaded and replaced.
+    ///
+    /// # Returns
+    ///
+    /// The path of the LaunchAgent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the LaunchAgent cannot be written or loaded.
+    pub fn install(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let dir = agents_dir()?;
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.plist", self.label));
+        if path.exists() {
+            launchctl("unload", &path)?;
+        }
+        fs::write(&path, self.plist())?;
+        launchctl("load", &path)?;
+        Ok(path)
+    }
+}
+
+/// Unloads and removes a LaunchAgent
+///
+/// # Arguments
+///
+/// * `label` - Label of the LaunchAgent
+///
+/// # Returns
+///
+/// False if the LaunchAgent is not installed.
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be unloaded or removed.
+pub fn uninstall(label: &str) -> Result<bool, Box<dyn Error>> {
+    let path = agent_path(label)?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    launchctl("unload", &path)?;
+    fs::remove_file(&path)?;
+    Ok(true)
+}
+
+/// Returns true if a LaunchAgent is installed
+///
+/// # Errors
+///
+/// Returns an error if the home directory cannot be determined.
+pub fn installed(label: &str) -> Result<bool, Box<dyn Error>> {
+    Ok(agent_path(label)?.exists())
+}
+
+/// Returns the path of a LaunchAgent
+///
+/// # Errors
+///
+/// Returns an error if the home directory cannot be determined.
+pub fn agent_path(label: &str) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(agents_dir()?.join(format!("{label}.plist")))
+}
+
+/// Returns the LaunchAgents directory of the current user
+///
+/// # Errors
+///
+/// Returns an error if the home directory cannot be determined.
+pub fn agents_dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(config::home_dir()?.join("Library").join("LaunchAgents"))
+}
+
+/// Loads or unloads a LaunchAgent with `launchctl`
+///
+/// # Errors
+///
+/// Returns an error if `launchctl` fails.
+fn launchctl(operation: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("launchctl").args([operation, "-w"]).arg(path).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to {operation} {}: {}", path.display(), stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Escapes the characters with special meaning in XML
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
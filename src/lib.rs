@@ -6,7 +6,23 @@
 //! # Modules
 //!
 //! - [`cli`] - Command-line interface definitions using clap
+//! - [`config`] - User configuration stored as TOML
+//! - `iokit` - I/O Registry access (macOS only)
+//! - [`launchd`] - LaunchAgents of the current user
+//! - [`notify`] - macOS user notifications
+//! - `objc` - Access to Objective-C frameworks (macOS only)
 //! - [`subcommands`] - Individual tool implementations
+//! - [`util`] - Helpers shared by the commands
+//! - [`xattr`] - Extended attributes of files
 
 pub mod cli;
+pub mod config;
+#[cfg(target_os = "macos")]
+pub mod iokit;
+pub mod launchd;
+pub mod notify;
+#[cfg(target_os = "macos")]
+pub mod objc;
 pub mod subcommands;
+pub mod util;
+pub mod xattr;
@@ -0,0 +1,37 @@
+//! macOS user notifications
+//!
+//! Notifications are posted with `osascript` (`display notification`), which
+//! works without an app bundle. They appear in Notification Center under the
+//! name of the app running the script, e.g. "Script Editor".
+
+use std::error::Error;
+use std::process::Command;
+
+/// Posts a notification
+///
+/// # Arguments
+///
+/// * `title` - Title of the notification
+/// * `message` - Text of the notification
+///
+/// # Errors
+///
+/// Returns an error if `osascript` cannot be run or fails.
+pub fn notify(title: &str, message: &str) -> Result<(), Box<dyn Error>> {
+    let script = format!(
+        "display notification {} with title {}",
+        applescript_string(message),
+        applescript_string(title)
+    );
+    let output = Command::new("osascript").args(["-e", &script]).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to post notification: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Quotes text as an AppleScript string literal
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
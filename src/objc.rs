@@ -0,0 +1,173 @@
+//! Access to Objective-C frameworks through the Objective-C runtime
+//!
+//! Frameworks like CoreWLAN, AppKit, IOBluetooth and CoreBrightness only offer
+//! an Objective-C API. Their classes are used by sending messages with
+//! `objc_msgSend`, cast to the signature of each called method. Only methods
+//! returning nothing, objects, integers, `BOOL`, `float` or `double` are used,
+//! which need no special calling conventions.
+
+use std::error::Error;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::mem;
+use std::ptr;
+
+/// Objective-C object pointer
+pub type Id = *mut c_void;
+
+/// Objective-C method selector
+pub type Sel = *const c_void;
+
+#[link(name = "Foundation", kind = "framework")]
+unsafe extern "C" {}
+
+#[link(name = "objc")]
+unsafe extern "C" {
+    fn objc_getClass(name: *const c_char) -> Id;
+    fn sel_registerName(name: *const c_char) -> Sel;
+    fn objc_msgSend();
+    fn objc_autoreleasePoolPush() -> *mut c_void;
+    fn objc_autoreleasePoolPop(pool: *mut c_void);
+}
+
+/// Autorelease pool draining the objects created by the frameworks when dropped
+pub struct AutoreleasePool(*mut c_void);
+
+impl AutoreleasePool {
+    /// Pushes a new autorelease pool
+    pub fn new() -> Self {
+        AutoreleasePool(unsafe { objc_autoreleasePoolPush() })
+    }
+}
+
+impl Default for AutoreleasePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for AutoreleasePool {
+    fn drop(&mut self) {
+        unsafe { objc_autoreleasePoolPop(self.0) }
+    }
+}
+
+/// Looks up a class; `nil` if it does not exist
+pub fn class(name: &CStr) -> Id {
+    unsafe { objc_getClass(name.as_ptr()) }
+}
+
+/// Returns `objc_msgSend` cast to the signature of the called method
+///
+/// # Safety
+///
+/// `F` must be an `unsafe extern "C" fn` pointer type matching the receiver,
+/// selector and arguments of the method the result is called with.
+pub unsafe fn msg_send<F: Copy>() -> F {
+    assert_eq!(mem::size_of::<F>(), mem::size_of::<unsafe extern "C" fn()>());
+    let function: unsafe extern "C" fn() = objc_msgSend;
+    unsafe { mem::transmute_copy::<unsafe extern "C" fn(), F>(&function) }
+}
+
+/// Registers or looks up a method selector
+pub fn selector(name: &CStr) -> Sel {
+    unsafe { sel_registerName(name.as_ptr()) }
+}
+
+/// Sends a message without arguments returning an object; `nil` receives `nil`
+///
+/// # Safety
+///
+/// `receiver` must be `nil` or an object responding to `name` with an object.
+pub unsafe fn send_id(receiver: Id, name: &CStr) -> Id {
+    if receiver.is_null() {
+        return ptr::null_mut();
+    }
+    unsafe {
+        let send: unsafe extern "C" fn(Id, Sel) -> Id = msg_send();
+        send(receiver, selector(name))
+    }
+}
+
+/// Sends a message without arguments returning an `NSInteger`
+///
+/// # Safety
+///
+/// `receiver` must be `nil` or an object responding to `name` with an integer.
+pub unsafe fn send_integer(receiver: Id, name: &CStr) -> isize {
+    unsafe {
+        let send: unsafe extern "C" fn(Id, Sel) -> isize = msg_send();
+        send(receiver, selector(name))
+    }
+}
+
+/// Sends a message without arguments returning a `BOOL`
+///
+/// # Safety
+///
+/// `receiver` must be `nil` or an object responding to `name` with a `BOOL`.
+pub unsafe fn send_bool(receiver: Id, name: &CStr) -> bool {
+    unsafe {
+        let send: unsafe extern "C" fn(Id, Sel) -> i8 = msg_send();
+        send(receiver, selector(name)) != 0
+    }
+}
+
+/// Returns the elements of an `NSArray`; `nil` has none
+///
+/// # Safety
+///
+/// `array` must be `nil` or an `NSArray`. The elements are only valid as long
+/// as the array is.
+pub unsafe fn objects(array: Id) -> Vec<Id> {
+    if array.is_null() {
+        return Vec::new();
+    }
+    unsafe {
+        let object_at: unsafe extern "C" fn(Id, Sel, usize) -> Id = msg_send();
+        (0..send_integer(array, c"count").max(0) as usize)
+            .map(|index| object_at(array, selector(c"objectAtIndex:"), index))
+            .collect()
+    }
+}
+
+/// Creates an autoreleased `NSString`
+///
+/// # Errors
+///
+/// Returns an error if the text contains a NUL character.
+pub fn ns_string(text: &str) -> Result<Id, Box<dyn Error>> {
+    let text = CString::new(text)?;
+    unsafe {
+        let create: unsafe extern "C" fn(Id, Sel, *const c_char) -> Id = msg_send();
+        Ok(create(
+            class(c"NSString"),
+            selector(c"stringWithUTF8String:"),
+            text.as_ptr(),
+        ))
+    }
+}
+
+/// Converts an `NSString` into a Rust string; `nil` becomes `None`
+///
+/// # Safety
+///
+/// `object` must be `nil` or an `NSString`.
+pub unsafe fn string(object: Id) -> Option<String> {
+    if object.is_null() {
+        return None;
+    }
+    unsafe {
+        let utf8: unsafe extern "C" fn(Id, Sel) -> *const c_char = msg_send();
+        let chars = utf8(object, selector(c"UTF8String"));
+        (!chars.is_null()).then(|| CStr::from_ptr(chars).to_string_lossy().into_owned())
+    }
+}
+
+/// Returns the `localizedDescription` of an `NSError`
+///
+/// # Safety
+///
+/// `error` must be `nil` or an `NSError`.
+pub unsafe fn error_description(error: Id) -> String {
+    unsafe { string(send_id(error, c"localizedDescription")) }.unwrap_or_else(|| "unknown error".to_string())
+}
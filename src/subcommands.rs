@@ -3,4 +3,54 @@
 //! This module contains the implementation of all available subcommands.
 //! Each subcommand is organized in its own module.
 
-pub mod dns;
\ No newline at end of file
+pub mod airdrop;
+pub mod appearance;
+pub mod arp;
+pub mod audio;
+pub mod awake;
+pub mod battery;
+pub mod bluetooth;
+pub mod captive;
+pub mod dhcp;
+pub mod disk;
+pub mod display;
+pub mod dmg;
+pub mod dns;
+pub mod find;
+pub mod firewall;
+pub mod hostname;
+pub mod hosts;
+pub mod hotspot;
+pub mod input;
+pub mod ip;
+pub mod keyboard;
+pub mod location;
+pub mod lock;
+pub mod mdns;
+pub mod mtu;
+pub mod net;
+pub mod netaudit;
+pub mod nettraffic;
+pub mod ping;
+pub mod ports;
+pub mod portscan;
+pub mod power;
+pub mod proxy;
+pub mod ramdisk;
+pub mod route;
+pub mod screenshot;
+pub mod share;
+pub mod shred;
+pub mod speedtest;
+pub mod spotlight;
+pub mod ssh;
+pub mod storage;
+pub mod tags;
+pub mod thermals;
+pub mod trace;
+pub mod trash;
+pub mod usb;
+pub mod wallpaper;
+pub mod wifi;
+pub mod wol;
+pub mod xattr;
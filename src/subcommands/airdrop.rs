@@ -0,0 +1,107 @@
+//! AirDrop discoverability
+//!
+//! Shows and switches who can find this Mac with AirDrop, like the AirDrop
+//! menu of Control Center. The setting is the `DiscoverableMode` preference of
+//! `sharingd`, the daemon behind AirDrop, which only reads it when it starts;
+//! after writing it, `sharingd` is restarted and launchd starts it again.
+//!
+//! Since macOS 13.3, macOS switches Everyone back to Contacts Only after 10
+//! minutes.
+//!
+//! # System Commands Used
+//!
+//! - `defaults read com.apple.sharingd DiscoverableMode` - Read the discoverability
+//! - `defaults write com.apple.sharingd DiscoverableMode` - Set the discoverability
+//! - `killall sharingd` - Make AirDrop apply the discoverability
+
+use crate::cli::{AirdropArgs, AirdropCommands};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// Preference domain of `sharingd`
+const DOMAIN: &str = "com.apple.sharingd";
+
+/// Preference with the discoverability
+const KEY: &str = "DiscoverableMode";
+
+/// Discoverability hiding this Mac
+const OFF: &str = "Off";
+
+/// Discoverability for contacts, the macOS default
+const CONTACTS_ONLY: &str = "Contacts Only";
+
+/// Discoverability for everyone nearby
+const EVERYONE: &str = "Everyone";
+
+/// Performs AirDrop operations
+///
+/// # Arguments
+///
+/// * `args` - AirDrop arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the discoverability cannot be set.
+pub fn perform(args: AirdropArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(AirdropCommands::Status) => {
+            print_status();
+            Ok(())
+        }
+        Some(AirdropCommands::Off) => set(OFF),
+        Some(AirdropCommands::Contacts) => set(CONTACTS_ONLY),
+        Some(AirdropCommands::Everyone) => set(EVERYONE),
+    }
+}
+
+/// Prints who can find this Mac with AirDrop
+fn print_status() {
+    let mode = match discoverable_mode().as_deref() {
+        Some(OFF) => "off".yellow(),
+        Some(EVERYONE) => "everyone".green(),
+        Some(mode) if mode != CONTACTS_ONLY => mode.normal(),
+        _ => "contacts only".green(),
+    };
+    println!("{:<16}{mode}", "AirDrop".bold());
+}
+
+/// Sets who can find this Mac with AirDrop and restarts `sharingd` to apply it
+///
+/// # Arguments
+///
+/// * `mode` - Value of the `DiscoverableMode` preference
+///
+/// # Errors
+///
+/// Returns an error if `defaults` fails.
+fn set(mode: &str) -> Result<(), Box<dyn Error>> {
+    print!("Set AirDrop discoverability to {}", mode.to_lowercase());
+    io::stdout().flush()?;
+    let output = Command::new("defaults")
+        .args(["write", DOMAIN, KEY, "-string", mode])
+        .output()
+        .map_err(|e| format!("Failed to run defaults: {e}"))?;
+    if !output.status.success() {
+        println!();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to write {KEY}: {}", stderr.trim()).into());
+    }
+    // Fails if sharingd is not running, which then reads the preference when it starts
+    let _ = Command::new("killall").arg("sharingd").output();
+    println!("{}", " OK".green());
+    if mode == EVERYONE {
+        println!("macOS switches back to contacts only after 10 minutes");
+    }
+    Ok(())
+}
+
+/// Reads the discoverability; `None` if it was never changed
+fn discoverable_mode() -> Option<String> {
+    let output = Command::new("defaults").args(["read", DOMAIN, KEY]).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,181 @@
+//! Dark and light mode
+//!
+//! Shows and switches the system appearance through the appearance preferences
+//! of System Events, which take effect right away. `auto` turns on the Auto
+//! appearance of System Settings, which macOS switches itself, usually at night
+//! and while the Mac is idle.
+//!
+//! `schedule` switches at the actual sunset and sunrise of a location instead;
+//! see the [`schedule`] module.
+//!
+//! # System Commands Used
+//!
+//! - `osascript` - Read and switch dark mode through System Events
+//! - `defaults read -g AppleInterfaceStyleSwitchesAutomatically` - Read the Auto appearance
+//! - `defaults write -g AppleInterfaceStyleSwitchesAutomatically` - Switch the Auto appearance
+
+pub mod schedule;
+
+use crate::cli::{AppearanceArgs, AppearanceCommands};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// Global preference enabling the Auto appearance
+const AUTO_KEY: &str = "AppleInterfaceStyleSwitchesAutomatically";
+
+/// Performs appearance operations
+///
+/// # Arguments
+///
+/// * `args` - Appearance arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the appearance cannot be read or switched.
+pub fn perform(args: AppearanceArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(AppearanceCommands::Show) => print_status(),
+        Some(AppearanceCommands::Dark) => switch(Some(true)),
+        Some(AppearanceCommands::Light) => switch(Some(false)),
+        Some(AppearanceCommands::Toggle) => switch(None),
+        Some(AppearanceCommands::Auto) => auto(),
+        Some(AppearanceCommands::Schedule(args)) => schedule::perform(args),
+    }
+}
+
+/// Prints the current appearance and how it is switched
+///
+/// # Errors
+///
+/// Returns an error if the appearance cannot be read.
+fn print_status() -> Result<(), Box<dyn Error>> {
+    let mode = if dark_mode()? {
+        "dark".normal()
+    } else {
+        "light".normal()
+    };
+    println!("{:<16}{mode}", "Appearance".bold());
+    let switching = if auto_enabled() {
+        "automatic (macOS)".green()
+    } else if let Some(schedule) = schedule::installed() {
+        format!("at sunset and sunrise ({schedule})").green()
+    } else {
+        "manual".normal()
+    };
+    println!("{:<16}{switching}", "Switching".bold());
+    Ok(())
+}
+
+/// Switches to dark or light mode, or toggles it
+///
+/// Turns off the Auto appearance, which would switch back otherwise.
+///
+/// # Arguments
+///
+/// * `dark` - True for dark mode, false for light mode, `None` to toggle
+///
+/// # Errors
+///
+/// Returns an error if `osascript` fails.
+fn switch(dark: Option<bool>) -> Result<(), Box<dyn Error>> {
+    let dark = match dark {
+        Some(dark) => dark,
+        None => !dark_mode()?,
+    };
+    print!("Switch to {} mode", if dark { "dark" } else { "light" });
+    io::stdout().flush()?;
+    if auto_enabled() {
+        set_auto(false)?;
+    }
+    if let Err(e) = set_dark_mode(dark) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Turns on the Auto appearance
+///
+/// # Errors
+///
+/// Returns an error if the preference cannot be written.
+fn auto() -> Result<(), Box<dyn Error>> {
+    if schedule::installed().is_some() {
+        return Err("The sunset schedule switches the appearance, remove it with \
+                    `rem appearance schedule --uninstall` first"
+            .into());
+    }
+    print!("Switch to automatic appearance");
+    io::stdout().flush()?;
+    set_auto(true)?;
+    println!("{}", " OK".green());
+    println!("macOS applies the change after the next login");
+    Ok(())
+}
+
+/// Returns true if dark mode is on
+///
+/// # Errors
+///
+/// Returns an error if `osascript` fails, e.g. without the Automation permission.
+pub fn dark_mode() -> Result<bool, Box<dyn Error>> {
+    let output = osascript("tell application \"System Events\" to tell appearance preferences to get dark mode")?;
+    Ok(output.trim() == "true")
+}
+
+/// Switches dark mode on or off
+///
+/// # Errors
+///
+/// Returns an error if `osascript` fails.
+pub fn set_dark_mode(dark: bool) -> Result<(), Box<dyn Error>> {
+    osascript(&format!(
+        "tell application \"System Events\" to tell appearance preferences to set dark mode to {dark}"
+    ))?;
+    Ok(())
+}
+
+/// Returns true if the Auto appearance is on
+pub fn auto_enabled() -> bool {
+    Command::new("defaults")
+        .args(["read", "-g", AUTO_KEY])
+        .output()
+        .is_ok_and(|output| output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "1")
+}
+
+/// Switches the Auto appearance on or off
+///
+/// # Errors
+///
+/// Returns an error if `defaults` fails.
+pub fn set_auto(on: bool) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("defaults")
+        .args(["write", "-g", AUTO_KEY, "-bool", if on { "true" } else { "false" }])
+        .output()
+        .map_err(|e| format!("Failed to run defaults: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to write {AUTO_KEY}: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Runs an AppleScript and returns its result
+///
+/// # Errors
+///
+/// Returns an error if `osascript` cannot be run or the script fails.
+fn osascript(script: &str) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .map_err(|e| format!("Failed to run osascript: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to access the appearance: {}", stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -0,0 +1,382 @@
+//! Sunset and sunrise schedule
+//!
+//! A LaunchAgent runs `rem appearance schedule --run` every few minutes and
+//! after login. Each run calculates sunrise and sunset of the day at the
+//! configured location and switches to dark mode after sunset and to light mode
+//! after sunrise. Only the passing of sunset or sunrise switches the
+//! appearance, so switching it by hand in between is kept until the next one.
+//!
+//! The location and the last appearance switched to are kept in
+//! `appearance-schedule` in the rempower state directory. Sunrise and sunset
+//! are calculated with the sunrise equation, which is accurate to a few minutes.
+//!
+//! # System Commands Used
+//!
+//! - `date` - Read the local date, time and time zone offset
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload the LaunchAgent
+
+use super::{auto_enabled, set_auto, set_dark_mode};
+use crate::cli::AppearanceScheduleArgs;
+use crate::config;
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use crate::util::{parse_date, timestamp};
+use colored::Colorize;
+use std::env;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fs;
+use std::process::Command;
+
+/// Label of the LaunchAgent
+const LABEL: &str = "com.github.rfoerthe.rempower.appearance-schedule";
+
+/// Name of the file with the location and the last switch inside the state directory
+const STATE_FILE: &str = "appearance-schedule";
+
+/// Name of the log file of the LaunchAgent inside the state directory
+const LOG_FILE: &str = "appearance-schedule.log";
+
+/// Seconds between runs of the LaunchAgent
+const RUN_INTERVAL: u64 = 300;
+
+/// Days from the Unix epoch to 2000-01-01, the epoch of the sunrise equation
+const J2000_DAYS: i64 = 10_957;
+
+/// Julian date of the Unix epoch
+const UNIX_EPOCH_JULIAN: f64 = 2_440_587.5;
+
+/// Julian date of 2000-01-01 12:00 UTC
+const J2000_JULIAN: f64 = 2_451_545.0;
+
+/// Sun altitude at sunrise and sunset in degrees, corrected for refraction and the solar disc
+const HORIZON_ALTITUDE: f64 = -0.833;
+
+/// Sunrise and sunset of a day
+enum SunTimes {
+    /// Sunrise and sunset in minutes after local midnight
+    Daily { sunrise: i64, sunset: i64 },
+    /// The sun does not set
+    PolarDay,
+    /// The sun does not rise
+    PolarNight,
+}
+
+/// Schedule state
+struct State {
+    /// Latitude in degrees
+    latitude: f64,
+    /// Longitude in degrees
+    longitude: f64,
+    /// Appearance switched to by the last run, true for dark mode
+    dark: Option<bool>,
+}
+
+/// Local date and time
+struct LocalTime {
+    /// Days since the Unix epoch
+    days: i64,
+    /// Minutes after midnight
+    minutes: i64,
+    /// Offset from UTC in minutes
+    offset: i64,
+}
+
+/// Installs, runs, removes or shows the schedule
+///
+/// # Arguments
+///
+/// * `args` - Schedule arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the location is invalid, the state cannot be read or
+/// written, or the LaunchAgent cannot be installed or removed.
+pub fn perform(args: AppearanceScheduleArgs) -> Result<(), Box<dyn Error>> {
+    if args.uninstall {
+        return uninstall();
+    }
+    if args.run {
+        return run();
+    }
+    if let (Some(latitude), Some(longitude)) = (args.latitude, args.longitude) {
+        return install(latitude, longitude);
+    }
+
+    let Some(state) = read_state()? else {
+        println!(
+            "No appearance schedule installed, add one with \
+             `rem appearance schedule --latitude <DEGREES> --longitude <DEGREES>`"
+        );
+        return Ok(());
+    };
+    let now = local_time()?;
+    println!("{:<16}{:.4}, {:.4}", "Location".bold(), state.latitude, state.longitude);
+    match sun_times(&state, &now) {
+        SunTimes::Daily { sunrise, sunset } => {
+            println!("{:<16}{}", "Sunrise".bold(), format_time_of_day(sunrise));
+            println!("{:<16}{}", "Sunset".bold(), format_time_of_day(sunset));
+            let next = if is_day(sunrise, sunset, now.minutes) {
+                format!("dark mode at {}", format_time_of_day(sunset))
+            } else {
+                format!("light mode at {}", format_time_of_day(sunrise))
+            };
+            println!("{:<16}{next}", "Next switch".bold());
+        }
+        SunTimes::PolarDay => println!("{:<16}the sun does not set today", "Sun".bold()),
+        SunTimes::PolarNight => println!("{:<16}the sun does not rise today", "Sun".bold()),
+    }
+    Ok(())
+}
+
+/// Returns the location of the installed schedule, e.g. `52.5200, 13.4050`
+pub fn installed() -> Option<String> {
+    if !launchd::installed(LABEL).ok()? {
+        return None;
+    }
+    let state = read_state().ok()??;
+    Some(format!("{:.4}, {:.4}", state.latitude, state.longitude))
+}
+
+/// Switches the appearance if sunset or sunrise has passed since the last run
+///
+/// # Errors
+///
+/// Returns an error if the schedule is not installed or the appearance cannot be switched.
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut state = read_state()?.ok_or("No appearance schedule installed")?;
+    let now = local_time()?;
+    let dark = match sun_times(&state, &now) {
+        SunTimes::Daily { sunrise, sunset } => !is_day(sunrise, sunset, now.minutes),
+        SunTimes::PolarDay => false,
+        SunTimes::PolarNight => true,
+    };
+    if state.dark == Some(dark) {
+        return Ok(());
+    }
+
+    set_dark_mode(dark)?;
+    println!(
+        "{}  Switched to {} mode",
+        timestamp(),
+        if dark { "dark" } else { "light" }
+    );
+    state.dark = Some(dark);
+    write_state(&state)
+}
+
+/// Installs and loads the LaunchAgent for a location
+///
+/// Turns off the Auto appearance of macOS, which would switch as well.
+///
+/// # Errors
+///
+/// Returns an error if the location is invalid or the LaunchAgent cannot be written or loaded.
+fn install(latitude: f64, longitude: f64) -> Result<(), Box<dyn Error>> {
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err(format!("Invalid latitude {latitude}, expected -90 to 90 degrees").into());
+    }
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err(format!("Invalid longitude {longitude}, expected -180 to 180 degrees").into());
+    }
+    let state = State {
+        latitude,
+        longitude,
+        dark: None,
+    };
+    write_state(&state)?;
+    if auto_enabled() {
+        set_auto(false)?;
+    }
+
+    let program = env::current_exe()?;
+    let arguments = [
+        program.to_string_lossy().to_string(),
+        "appearance".to_string(),
+        "schedule".to_string(),
+        "--run".to_string(),
+    ];
+    LaunchAgent {
+        label: LABEL,
+        arguments: &arguments,
+        start: Start::Interval(RUN_INTERVAL),
+        log: Log::All(&config::state_dir()?.join(LOG_FILE)),
+    }
+    .install()?;
+
+    let today = match sun_times(&state, &local_time()?) {
+        SunTimes::Daily { sunrise, sunset } => format!(
+            "sunrise today at {}, sunset at {}",
+            format_time_of_day(sunrise),
+            format_time_of_day(sunset)
+        ),
+        SunTimes::PolarDay => "the sun does not set today".to_string(),
+        SunTimes::PolarNight => "the sun does not rise today".to_string(),
+    };
+    println!("{} the appearance schedule, {today}", "Installed".green());
+    Ok(())
+}
+
+/// Unloads and removes the LaunchAgent and its state
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be unloaded or removed.
+fn uninstall() -> Result<(), Box<dyn Error>> {
+    if !launchd::uninstall(LABEL)? {
+        println!("The appearance schedule is not installed");
+        return Ok(());
+    }
+    let state = config::state_dir()?.join(STATE_FILE);
+    if state.exists() {
+        fs::remove_file(state)?;
+    }
+    println!("{} the appearance schedule", "Removed".green());
+    Ok(())
+}
+
+/// Calculates sunrise and sunset of the current day with the sunrise equation
+fn sun_times(state: &State, now: &LocalTime) -> SunTimes {
+    let radians = |degrees: f64| degrees * PI / 180.0;
+    let degrees = |radians: f64| radians * 180.0 / PI;
+
+    // Mean solar time at the longitude
+    let mean_time = (now.days - J2000_DAYS) as f64 - state.longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_time).rem_euclid(360.0);
+    let center =
+        1.9148 * radians(anomaly).sin() + 0.02 * radians(2.0 * anomaly).sin() + 0.0003 * radians(3.0 * anomaly).sin();
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+    let transit =
+        J2000_JULIAN + mean_time + 0.0053 * radians(anomaly).sin() - 0.0069 * radians(2.0 * ecliptic_longitude).sin();
+    let declination = (radians(ecliptic_longitude).sin() * radians(23.4397).sin()).asin();
+
+    let latitude = radians(state.latitude);
+    let hour_angle =
+        (radians(HORIZON_ALTITUDE).sin() - latitude.sin() * declination.sin()) / (latitude.cos() * declination.cos());
+    if hour_angle < -1.0 {
+        return SunTimes::PolarDay;
+    }
+    if hour_angle > 1.0 {
+        return SunTimes::PolarNight;
+    }
+    let half_day = degrees(hour_angle.acos()) / 360.0;
+
+    // Julian date to minutes after local midnight
+    let local_minutes = |julian: f64| {
+        let minutes = ((julian - UNIX_EPOCH_JULIAN) * 1440.0).round() as i64 + now.offset;
+        minutes.rem_euclid(1440)
+    };
+    SunTimes::Daily {
+        sunrise: local_minutes(transit - half_day),
+        sunset: local_minutes(transit + half_day),
+    }
+}
+
+/// Returns true if the time lies between sunrise and sunset
+fn is_day(sunrise: i64, sunset: i64, minutes: i64) -> bool {
+    if sunrise <= sunset {
+        (sunrise..sunset).contains(&minutes)
+    } else {
+        // Sunset after local midnight, e.g. with a time zone far from the longitude
+        minutes >= sunrise || minutes < sunset
+    }
+}
+
+/// Reads the local date, time and time zone offset
+///
+/// # Errors
+///
+/// Returns an error if `date` cannot be run or its output is malformed.
+fn local_time() -> Result<LocalTime, Box<dyn Error>> {
+    let output = Command::new("date")
+        .arg("+%Y-%m-%d %H:%M %z")
+        .output()
+        .map_err(|e| format!("Failed to run date: {e}"))?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let malformed = || format!("Unexpected output of date: {}", text.trim());
+
+    let mut fields = text.split_whitespace();
+    let days = fields.next().and_then(parse_date).ok_or_else(malformed)?;
+    let (hour, minute) = fields
+        .next()
+        .and_then(|time| time.split_once(':'))
+        .and_then(|(hour, minute)| Some((hour.parse::<i64>().ok()?, minute.parse::<i64>().ok()?)))
+        .ok_or_else(malformed)?;
+    // Offset as +HHMM or -HHMM
+    let offset = fields
+        .next()
+        .filter(|offset| offset.len() == 5)
+        .and_then(|offset| {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let hours: i64 = offset.get(1..3)?.parse().ok()?;
+            let minutes: i64 = offset.get(3..5)?.parse().ok()?;
+            Some(sign * (hours * 60 + minutes))
+        })
+        .ok_or_else(malformed)?;
+
+    Ok(LocalTime {
+        days,
+        minutes: hour * 60 + minute,
+        offset,
+    })
+}
+
+/// Reads the schedule state
+///
+/// The first line holds latitude and longitude, the second the appearance
+/// switched to by the last run (`dark` or `light`).
+///
+/// # Returns
+///
+/// `None` if no schedule has been installed.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is malformed.
+fn read_state() -> Result<Option<State>, Box<dyn Error>> {
+    let path = config::state_dir()?.join(STATE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)?;
+    let mut lines = content.lines();
+    let (latitude, longitude) = lines
+        .next()
+        .and_then(|line| line.split_once(' '))
+        .and_then(|(latitude, longitude)| Some((latitude.parse().ok()?, longitude.trim().parse().ok()?)))
+        .ok_or_else(|| format!("Malformed {}", path.display()))?;
+    let dark = match lines.next().map(str::trim) {
+        Some("dark") => Some(true),
+        Some("light") => Some(false),
+        _ => None,
+    };
+    Ok(Some(State {
+        latitude,
+        longitude,
+        dark,
+    }))
+}
+
+/// Writes the schedule state
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+fn write_state(state: &State) -> Result<(), Box<dyn Error>> {
+    let dir = config::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let dark = match state.dark {
+        Some(true) => "dark",
+        Some(false) => "light",
+        None => "",
+    };
+    fs::write(
+        dir.join(STATE_FILE),
+        format!("{} {}\n{dark}\n", state.latitude, state.longitude),
+    )?;
+    Ok(())
+}
+
+/// Formats minutes after midnight as `HH:MM`
+fn format_time_of_day(minutes: i64) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
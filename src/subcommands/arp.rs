@@ -0,0 +1,257 @@
+//! Neighbor table subcommand
+//!
+//! Lists the hosts of the local network this Mac has recently exchanged packets
+//! with, as cached by ARP (IPv4) and neighbor discovery (IPv6), together with the
+//! vendor of their network adapter. Vendors are looked up by the first three bytes
+//! of the MAC address in an embedded database of common vendors of home and office
+//! network devices (`arp/oui.txt`). Locally administered addresses, e.g. the
+//! private Wi-Fi addresses of phones and laptops, carry no vendor and are marked
+//! as private.
+//!
+//! With `--watch`, the tables are read again periodically and every device with a
+//! MAC address not seen before is printed as it appears.
+//!
+//! # System Commands Used
+//!
+//! - `arp -an` - Read the ARP cache
+//! - `ndp -an` - Read the neighbor discovery cache
+
+use crate::cli::ArpArgs;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::net::IpAddr;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Embedded OUI database, one `<OUI> <vendor>` pair per line
+const OUI_DATABASE: &str = include_str!("arp/oui.txt");
+
+/// Vendor shown for locally administered MAC addresses
+const PRIVATE_ADDRESS: &str = "(private address)";
+
+/// Entry of the neighbor table
+#[derive(Serialize)]
+struct Neighbor {
+    /// IP address of the neighbor
+    address: IpAddr,
+    /// MAC address with two lowercase hex digits per byte
+    mac: String,
+    /// Device the neighbor is reached through
+    interface: String,
+    /// Vendor of the network adapter, if known
+    vendor: Option<&'static str>,
+}
+
+/// Shows the neighbor table
+///
+/// # Arguments
+///
+/// * `args` - Arp arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the neighbor table cannot be read (except in watch mode).
+pub fn perform(args: ArpArgs) -> Result<(), Box<dyn Error>> {
+    let neighbors = neighbors(args.ipv6)?;
+
+    if !args.watch {
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&neighbors)?);
+        } else {
+            print_table(&neighbors);
+        }
+        return Ok(());
+    }
+
+    let interval = Duration::from_secs(args.interval.max(1));
+    if !args.json {
+        print_table(&neighbors);
+        println!();
+        println!(
+            "Watching for new devices every {} s (Ctrl-C to stop)",
+            interval.as_secs()
+        );
+    }
+    let mut seen: HashSet<String> = neighbors.into_iter().map(|neighbor| neighbor.mac).collect();
+
+    loop {
+        thread::sleep(interval);
+        match self::neighbors(args.ipv6) {
+            Ok(neighbors) => {
+                for neighbor in neighbors {
+                    if !seen.insert(neighbor.mac.clone()) {
+                        continue;
+                    }
+                    if args.json {
+                        println!("{}", serde_json::to_string(&neighbor)?);
+                    } else {
+                        println!(
+                            "{} {} {} on {} ({})",
+                            "New device:".green().bold(),
+                            neighbor.address,
+                            neighbor.mac,
+                            neighbor.interface,
+                            neighbor.vendor.unwrap_or("unknown vendor")
+                        );
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}", format!("Failed to read the neighbor table: {e}").red()),
+        }
+    }
+}
+
+/// Prints the neighbor table with aligned columns
+fn print_table(neighbors: &[Neighbor]) {
+    if neighbors.is_empty() {
+        println!("No neighbors found");
+        return;
+    }
+
+    let width = neighbors
+        .iter()
+        .map(|neighbor| neighbor.address.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("ADDRESS".len());
+    println!("{:<width$}  {:<17}  {:<9}  VENDOR", "ADDRESS", "MAC", "INTERFACE");
+    for neighbor in neighbors {
+        let vendor = match neighbor.vendor {
+            Some(PRIVATE_ADDRESS) => PRIVATE_ADDRESS.yellow(),
+            Some(vendor) => vendor.normal(),
+            None => "-".dimmed(),
+        };
+        println!(
+            "{:<width$}  {:<17}  {:<9}  {vendor}",
+            neighbor.address.to_string(),
+            neighbor.mac,
+            neighbor.interface
+        );
+    }
+}
+
+/// Reads the neighbor table, sorted by address
+///
+/// # Arguments
+///
+/// * `ipv6` - Also read the IPv6 neighbor discovery cache
+///
+/// # Errors
+///
+/// Returns an error if `arp` or `ndp` cannot be run or fails.
+fn neighbors(ipv6: bool) -> Result<Vec<Neighbor>, Box<dyn Error>> {
+    let mut neighbors = parse_arp(&run("arp")?);
+    if ipv6 {
+        neighbors.extend(parse_ndp(&run("ndp")?));
+    }
+    neighbors.sort_by(|a, b| a.address.cmp(&b.address).then_with(|| a.interface.cmp(&b.interface)));
+    Ok(neighbors)
+}
+
+/// Runs `arp -an` or `ndp -an` and returns its output
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be run or exits with a failure.
+fn run(program: &str) -> Result<String, Box<dyn Error>> {
+    let output = Command::new(program)
+        .arg("-an")
+        .output()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("{program} failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the output of `arp -an`
+///
+/// Lines look like `? (192.168.1.1) at 3c:a6:2f:1:2:3 on en0 ifscope [ethernet]`.
+/// Incomplete entries and broadcast or multicast addresses are skipped.
+fn parse_arp(output: &str) -> Vec<Neighbor> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let address = fields.get(1)?.strip_prefix('(')?.strip_suffix(')')?.parse().ok()?;
+            let at = fields.iter().position(|field| *field == "at")?;
+            let on = fields.iter().position(|field| *field == "on")?;
+            neighbor(address, fields.get(at + 1)?, fields.get(on + 1)?)
+        })
+        .collect()
+}
+
+/// Parses the output of `ndp -an`
+///
+/// The columns are neighbor, link layer address, interface, expiry and state.
+/// Permanent entries are addresses of this Mac and are skipped, as are incomplete
+/// entries and multicast addresses.
+fn parse_ndp(output: &str) -> Vec<Neighbor> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3) == Some(&"permanent") {
+                return None;
+            }
+            let address = fields.first()?.split('%').next()?.parse().ok()?;
+            neighbor(address, fields.get(1)?, fields.get(2)?)
+        })
+        .collect()
+}
+
+/// Creates a neighbor from the fields of a table entry
+///
+/// # Returns
+///
+/// `None` if the MAC address is invalid, incomplete, or a broadcast or multicast address.
+fn neighbor(address: IpAddr, mac: &str, interface: &str) -> Option<Neighbor> {
+    let bytes = parse_mac(mac)?;
+    if bytes[0] & 0x01 != 0 {
+        return None;
+    }
+
+    Some(Neighbor {
+        address,
+        mac: bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(":"),
+        interface: interface.to_string(),
+        vendor: vendor(bytes),
+    })
+}
+
+/// Parses a MAC address; `arp` omits leading zeros of the bytes (`0:1c:42:0:0:8`)
+fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let mut bytes = [0; 6];
+    let mut parts = mac.split(':');
+    for byte in &mut bytes {
+        *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+    }
+    parts.next().is_none().then_some(bytes)
+}
+
+/// Looks up the vendor of a MAC address in the embedded OUI database
+///
+/// # Returns
+///
+/// The vendor name, [`PRIVATE_ADDRESS`] for locally administered addresses, or
+/// `None` if the vendor is not in the database.
+fn vendor(mac: [u8; 6]) -> Option<&'static str> {
+    if mac[0] & 0x02 != 0 {
+        return Some(PRIVATE_ADDRESS);
+    }
+
+    let oui = format!("{:02X}{:02X}{:02X}", mac[0], mac[1], mac[2]);
+    OUI_DATABASE
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| line.split_once(' ').filter(|(prefix, _)| *prefix == oui))
+        .map(|(_, vendor)| vendor.trim())
+}
@@ -0,0 +1,319 @@
+//! Audio devices
+//!
+//! Reads and changes the audio devices through CoreAudio, the hardware layer
+//! the Sound settings and the volume keys use as well, so changes take effect
+//! right away and without the delay of AppleScript. `volume` controls the
+//! volume and mute of an output device; see the [`volume`] module.
+//!
+//! `audio output` lists and switches the default output device. Devices are
+//! selected by name, which is matched loosely so that `airpods` or `mbps`
+//! select "AirPods Pro" or "MacBook Pro Speakers". `cycle` switches to the
+//! next device on every invocation, which suits a keyboard shortcut.
+//!
+//! `audio input` does the same for input devices and mutes the microphones;
+//! see the [`input`] module. `audio balance` and `audio rate` set the balance
+//! and sample rate of an output device; see the [`tuning`] module.
+//!
+//! # System Commands Used
+//!
+//! - Devices, default devices, volume, mute, balance, sample rate and use are read and changed with
+//!   `AudioObjectGetPropertyData` and `AudioObjectSetPropertyData` of CoreAudio
+
+#[cfg(target_os = "macos")]
+mod coreaudio;
+pub mod input;
+pub mod tuning;
+pub mod volume;
+
+use crate::cli::{AudioArgs, AudioCommands, AudioOutputCommands};
+use crate::util::match_name;
+use colored::Colorize;
+use serde::Serialize;
+use std::error::Error;
+use std::io::{self, Write};
+
+#[cfg(target_os = "macos")]
+pub use coreaudio::{
+    balance, can_mute, default_device, devices, muted, running, sample_rate, sample_rates, set_balance,
+    set_default_device, set_muted, set_sample_rate, set_volume, volume,
+};
+
+/// Direction of the audio through a device
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Speakers, headphones and other outputs
+    Output,
+    /// Microphones and other inputs
+    Input,
+}
+
+/// A device of the device list
+#[derive(Serialize)]
+struct ListedDevice {
+    /// The device
+    #[serde(flatten)]
+    device: Device,
+    /// True for the default device
+    default: bool,
+}
+
+/// An audio device
+#[derive(Serialize)]
+pub struct Device {
+    /// CoreAudio object id, valid until the device is disconnected
+    #[serde(skip)]
+    pub id: u32,
+    /// Name as shown in the Sound settings
+    pub name: String,
+    /// Unique id that persists across reconnects
+    pub uid: String,
+    /// Connection, e.g. `built-in`, `USB` or `Bluetooth`
+    pub transport: &'static str,
+    /// Number of output channels
+    pub output_channels: u32,
+    /// Number of input channels
+    pub input_channels: u32,
+}
+
+impl Device {
+    /// Returns the number of channels in a direction
+    pub fn channels(&self, direction: Direction) -> u32 {
+        match direction {
+            Direction::Output => self.output_channels,
+            Direction::Input => self.input_channels,
+        }
+    }
+}
+
+/// Performs audio device operations
+///
+/// # Arguments
+///
+/// * `args` - Audio arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read or switched.
+pub fn perform(args: AudioArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        AudioCommands::Output { command } => match command {
+            None => list(Direction::Output, false),
+            Some(AudioOutputCommands::List { json }) => list(Direction::Output, json),
+            Some(AudioOutputCommands::Set { name }) => {
+                let device = select_device(Some(&name), Direction::Output)?;
+                switch(&device, Direction::Output)
+            }
+            Some(AudioOutputCommands::Cycle) => cycle(Direction::Output),
+        },
+        AudioCommands::Input { command } => input::perform(command),
+        AudioCommands::Balance { command, device } => tuning::balance(command, device.as_deref()),
+        AudioCommands::Rate { command, device } => tuning::rate(command, device.as_deref()),
+    }
+}
+
+/// Lists the devices of a direction, marking the default one
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read.
+pub fn list(direction: Direction, json: bool) -> Result<(), Box<dyn Error>> {
+    let default = default_device(direction).ok();
+    let devices: Vec<ListedDevice> = devices_of(direction)?
+        .into_iter()
+        .map(|device| ListedDevice {
+            default: Some(device.id) == default,
+            device,
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&devices)?);
+        return Ok(());
+    }
+    if devices.is_empty() {
+        println!("No {} device", label(direction));
+        return Ok(());
+    }
+    for listed in devices {
+        let device = &listed.device;
+        let channels = device.channels(direction);
+        let plural = if channels == 1 { "" } else { "s" };
+        let details = format!("{}, {channels} channel{plural}", device.transport);
+        if listed.default {
+            println!("{} {} {}", "*".green(), device.name.green().bold(), details.dimmed());
+        } else {
+            println!("  {} {}", device.name, details.dimmed());
+        }
+    }
+    Ok(())
+}
+
+/// Switches to the device after the default one, starting over after the last
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read or switched.
+pub fn cycle(direction: Direction) -> Result<(), Box<dyn Error>> {
+    let mut devices = devices_of(direction)?;
+    if devices.is_empty() {
+        return Err(format!("No {} device", label(direction)).into());
+    }
+    let default = default_device(direction).ok();
+    let next = devices
+        .iter()
+        .position(|device| Some(device.id) == default)
+        .map_or(0, |index| (index + 1) % devices.len());
+    let device = devices.swap_remove(next);
+    switch(&device, direction)
+}
+
+/// Makes a device the default device of a direction
+///
+/// # Errors
+///
+/// Returns an error if switching fails.
+pub fn switch(device: &Device, direction: Direction) -> Result<(), Box<dyn Error>> {
+    print!("Switch {} to {}", label(direction), device.name);
+    io::stdout().flush()?;
+    if let Err(e) = set_default_device(direction, device.id) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Returns a device by name, or the default device
+///
+/// The name is matched loosely against the device names; see [`match_name`].
+///
+/// # Arguments
+///
+/// * `name` - Name or part of the name of the device, `None` for the default device
+/// * `direction` - Direction the device must support
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read, or no device or more than
+/// one device matches the name.
+pub fn select_device(name: Option<&str>, direction: Direction) -> Result<Device, Box<dyn Error>> {
+    let mut devices = devices_of(direction)?;
+
+    let Some(name) = name else {
+        let id = default_device(direction)?;
+        return devices
+            .into_iter()
+            .find(|device| device.id == id)
+            .ok_or_else(|| "No default device".into());
+    };
+
+    let names: Vec<&str> = devices.iter().map(|device| device.name.as_str()).collect();
+    match match_name(&names, name)? {
+        Some(index) => Ok(devices.swap_remove(index)),
+        None => Err(format!("No {} device matches '{name}'", label(direction)).into()),
+    }
+}
+
+/// Returns the devices with channels in a direction
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read.
+pub fn devices_of(direction: Direction) -> Result<Vec<Device>, Box<dyn Error>> {
+    Ok(devices()?
+        .into_iter()
+        .filter(|device| device.channels(direction) > 0)
+        .collect())
+}
+
+/// Returns `output` or `input`
+pub fn label(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Output => "output",
+        Direction::Input => "input",
+    }
+}
+
+/// Reads the audio devices; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn devices() -> Result<Vec<Device>, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads the default device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn default_device(_direction: Direction) -> Result<u32, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Switches the default device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn set_default_device(_direction: Direction, _device: u32) -> Result<(), Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads the volume of a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn volume(_device: u32, _direction: Direction) -> Result<f32, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Sets the volume of a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn set_volume(_device: u32, _direction: Direction, _volume: f32) -> Result<(), Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads whether a device is muted; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn muted(_device: u32, _direction: Direction) -> Result<bool, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads whether a device can be muted; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn can_mute(_device: u32, _direction: Direction) -> bool {
+    false
+}
+
+/// Mutes or unmutes a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn set_muted(_device: u32, _direction: Direction, _muted: bool) -> Result<(), Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads whether any app uses a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn running(_device: u32) -> Result<bool, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads the balance of a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn balance(_device: u32) -> Result<f32, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Sets the balance of a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn set_balance(_device: u32, _balance: f32) -> Result<(), Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads the sample rate of a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn sample_rate(_device: u32) -> Result<f64, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Reads the sample rates of a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn sample_rates(_device: u32) -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
+
+/// Sets the sample rate of a device; requires CoreAudio
+#[cfg(not(target_os = "macos"))]
+pub fn set_sample_rate(_device: u32, _rate: f64) -> Result<(), Box<dyn Error>> {
+    Err("Audio devices can only be controlled on macOS".into())
+}
@@ -0,0 +1,468 @@
+//! Audio devices from CoreAudio
+//!
+//! Every device is an audio object whose properties are addressed by a
+//! selector, a scope (global, output or input) and an element (the main
+//! element or a channel). The volume is the virtual main volume, which macOS
+//! maps onto the channel volumes of the device just like the volume keys do.
+
+use super::{Device, Direction};
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use std::error::Error;
+use std::ffi::c_void;
+use std::mem;
+use std::ptr;
+
+#[link(name = "CoreAudio", kind = "framework")]
+unsafe extern "C" {
+    fn AudioObjectHasProperty(object: u32, address: *const PropertyAddress) -> u8;
+    fn AudioObjectIsPropertySettable(object: u32, address: *const PropertyAddress, settable: *mut u8) -> i32;
+    fn AudioObjectGetPropertyDataSize(
+        object: u32,
+        address: *const PropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        size: *mut u32,
+    ) -> i32;
+    fn AudioObjectGetPropertyData(
+        object: u32,
+        address: *const PropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        size: *mut u32,
+        data: *mut c_void,
+    ) -> i32;
+    fn AudioObjectSetPropertyData(
+        object: u32,
+        address: *const PropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        size: u32,
+        data: *const c_void,
+    ) -> i32;
+}
+
+/// `AudioValueRange`
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct ValueRange {
+    minimum: f64,
+    maximum: f64,
+}
+
+/// `AudioObjectPropertyAddress`
+#[repr(C)]
+struct PropertyAddress {
+    selector: u32,
+    scope: u32,
+    element: u32,
+}
+
+/// `kAudioObjectSystemObject`
+const SYSTEM_OBJECT: u32 = 1;
+
+/// `kAudioObjectPropertyElementMain`
+const ELEMENT_MAIN: u32 = 0;
+
+/// `kAudioObjectPropertyScopeGlobal`
+const SCOPE_GLOBAL: u32 = u32::from_be_bytes(*b"glob");
+
+/// `kAudioHardwarePropertyDevices`
+const DEVICES: u32 = u32::from_be_bytes(*b"dev#");
+
+/// `kAudioHardwarePropertyDefaultOutputDevice`
+const DEFAULT_OUTPUT: u32 = u32::from_be_bytes(*b"dOut");
+
+/// `kAudioHardwarePropertyDefaultInputDevice`
+const DEFAULT_INPUT: u32 = u32::from_be_bytes(*b"dIn ");
+
+/// `kAudioObjectPropertyName`
+const NAME: u32 = u32::from_be_bytes(*b"lnam");
+
+/// `kAudioDevicePropertyDeviceUID`
+const UID: u32 = u32::from_be_bytes(*b"uid ");
+
+/// `kAudioDevicePropertyTransportType`
+const TRANSPORT_TYPE: u32 = u32::from_be_bytes(*b"tran");
+
+/// `kAudioDevicePropertyStreamConfiguration`
+const STREAM_CONFIGURATION: u32 = u32::from_be_bytes(*b"slay");
+
+/// `kAudioHardwareServiceDeviceProperty_VirtualMainVolume`
+const VIRTUAL_MAIN_VOLUME: u32 = u32::from_be_bytes(*b"vmvc");
+
+/// `kAudioHardwareServiceDeviceProperty_VirtualMainBalance`
+const VIRTUAL_MAIN_BALANCE: u32 = u32::from_be_bytes(*b"vmbl");
+
+/// `kAudioDevicePropertyNominalSampleRate`
+const NOMINAL_SAMPLE_RATE: u32 = u32::from_be_bytes(*b"nsrt");
+
+/// `kAudioDevicePropertyAvailableNominalSampleRates`
+const AVAILABLE_SAMPLE_RATES: u32 = u32::from_be_bytes(*b"nsr#");
+
+/// `kAudioDevicePropertyMute`
+const MUTE: u32 = u32::from_be_bytes(*b"mute");
+
+/// `kAudioDevicePropertyDeviceIsRunningSomewhere`
+const RUNNING_SOMEWHERE: u32 = u32::from_be_bytes(*b"gone");
+
+/// Reads all audio devices
+///
+/// # Errors
+///
+/// Returns an error if the device list cannot be read.
+pub fn devices() -> Result<Vec<Device>, Box<dyn Error>> {
+    let ids: Vec<u32> = get_array(SYSTEM_OBJECT, DEVICES, SCOPE_GLOBAL)
+        .map_err(|status| format!("Failed to list the audio devices (error {status})"))?;
+    Ok(ids
+        .into_iter()
+        .map(|id| Device {
+            id,
+            name: get_string(id, NAME).unwrap_or_else(|| format!("Device {id}")),
+            uid: get_string(id, UID).unwrap_or_default(),
+            transport: transport(get(id, TRANSPORT_TYPE, SCOPE_GLOBAL).unwrap_or(0)),
+            output_channels: channels(id, scope(Direction::Output)),
+            input_channels: channels(id, scope(Direction::Input)),
+        })
+        .collect())
+}
+
+/// Reads the default device of a direction
+///
+/// # Errors
+///
+/// Returns an error if there is no default device.
+pub fn default_device(direction: Direction) -> Result<u32, Box<dyn Error>> {
+    match get::<u32>(SYSTEM_OBJECT, default_selector(direction), SCOPE_GLOBAL) {
+        Ok(id) if id != 0 => Ok(id),
+        Ok(_) => Err(format!("No default {} device", super::label(direction)).into()),
+        Err(status) => Err(format!("Failed to read the default device (error {status})").into()),
+    }
+}
+
+/// Makes a device the default device of a direction
+///
+/// # Errors
+///
+/// Returns an error if switching fails.
+pub fn set_default_device(direction: Direction, device: u32) -> Result<(), Box<dyn Error>> {
+    set(SYSTEM_OBJECT, default_selector(direction), SCOPE_GLOBAL, device)
+        .map_err(|status| format!("Failed to switch the default device (error {status})").into())
+}
+
+/// Reads the volume of a device between 0 and 1
+///
+/// # Errors
+///
+/// Returns an error if the device has no volume control.
+pub fn volume(device: u32, direction: Direction) -> Result<f32, Box<dyn Error>> {
+    if !has(device, VIRTUAL_MAIN_VOLUME, scope(direction)) {
+        return Err("The device has no volume control".into());
+    }
+    get::<f32>(device, VIRTUAL_MAIN_VOLUME, scope(direction))
+        .map_err(|status| format!("Failed to read the volume (error {status})").into())
+}
+
+/// Sets the volume of a device
+///
+/// # Arguments
+///
+/// * `device` - Device id
+/// * `direction` - Output or input volume
+/// * `volume` - New volume between 0 and 1
+///
+/// # Errors
+///
+/// Returns an error if the device has no adjustable volume or setting it fails.
+pub fn set_volume(device: u32, direction: Direction, volume: f32) -> Result<(), Box<dyn Error>> {
+    if !settable(device, VIRTUAL_MAIN_VOLUME, scope(direction)) {
+        return Err("The volume of the device cannot be changed".into());
+    }
+    set(device, VIRTUAL_MAIN_VOLUME, scope(direction), volume.clamp(0.0, 1.0))
+        .map_err(|status| format!("Failed to set the volume (error {status})").into())
+}
+
+/// Reads whether a device is muted
+///
+/// # Errors
+///
+/// Returns an error if the device cannot be muted.
+pub fn muted(device: u32, direction: Direction) -> Result<bool, Box<dyn Error>> {
+    if !has(device, MUTE, scope(direction)) {
+        return Err("The device cannot be muted".into());
+    }
+    get::<u32>(device, MUTE, scope(direction))
+        .map(|muted| muted != 0)
+        .map_err(|status| format!("Failed to read the mute state (error {status})").into())
+}
+
+/// Returns true if a device can be muted
+pub fn can_mute(device: u32, direction: Direction) -> bool {
+    settable(device, MUTE, scope(direction))
+}
+
+/// Mutes or unmutes a device
+///
+/// # Errors
+///
+/// Returns an error if the device cannot be muted or switching fails.
+pub fn set_muted(device: u32, direction: Direction, muted: bool) -> Result<(), Box<dyn Error>> {
+    if !settable(device, MUTE, scope(direction)) {
+        return Err("The device cannot be muted".into());
+    }
+    set(device, MUTE, scope(direction), u32::from(muted))
+        .map_err(|status| format!("Failed to switch the mute state (error {status})").into())
+}
+
+/// Reads the left-right balance of an output device between 0 (left) and 1 (right)
+///
+/// # Errors
+///
+/// Returns an error if the device has no balance control.
+pub fn balance(device: u32) -> Result<f32, Box<dyn Error>> {
+    if !has(device, VIRTUAL_MAIN_BALANCE, scope(Direction::Output)) {
+        return Err("The device has no balance control".into());
+    }
+    get::<f32>(device, VIRTUAL_MAIN_BALANCE, scope(Direction::Output))
+        .map_err(|status| format!("Failed to read the balance (error {status})").into())
+}
+
+/// Sets the left-right balance of an output device
+///
+/// # Errors
+///
+/// Returns an error if the balance cannot be changed.
+pub fn set_balance(device: u32, balance: f32) -> Result<(), Box<dyn Error>> {
+    if !settable(device, VIRTUAL_MAIN_BALANCE, scope(Direction::Output)) {
+        return Err("The balance of the device cannot be changed".into());
+    }
+    set(
+        device,
+        VIRTUAL_MAIN_BALANCE,
+        scope(Direction::Output),
+        balance.clamp(0.0, 1.0),
+    )
+    .map_err(|status| format!("Failed to set the balance (error {status})").into())
+}
+
+/// Reads the sample rate of a device in Hz
+///
+/// # Errors
+///
+/// Returns an error if the sample rate cannot be read.
+pub fn sample_rate(device: u32) -> Result<f64, Box<dyn Error>> {
+    get::<f64>(device, NOMINAL_SAMPLE_RATE, SCOPE_GLOBAL)
+        .map_err(|status| format!("Failed to read the sample rate (error {status})").into())
+}
+
+/// Reads the sample rates a device supports, as ranges of minimum and maximum
+///
+/// Most devices report single rates, with minimum and maximum being equal.
+///
+/// # Errors
+///
+/// Returns an error if the sample rates cannot be read.
+pub fn sample_rates(device: u32) -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
+    let ranges: Vec<ValueRange> = get_array(device, AVAILABLE_SAMPLE_RATES, SCOPE_GLOBAL)
+        .map_err(|status| format!("Failed to read the sample rates (error {status})"))?;
+    Ok(ranges.into_iter().map(|range| (range.minimum, range.maximum)).collect())
+}
+
+/// Sets the sample rate of a device
+///
+/// # Errors
+///
+/// Returns an error if the sample rate cannot be changed.
+pub fn set_sample_rate(device: u32, rate: f64) -> Result<(), Box<dyn Error>> {
+    if !settable(device, NOMINAL_SAMPLE_RATE, SCOPE_GLOBAL) {
+        return Err("The sample rate of the device cannot be changed".into());
+    }
+    set(device, NOMINAL_SAMPLE_RATE, SCOPE_GLOBAL, rate)
+        .map_err(|status| format!("Failed to set the sample rate (error {status})").into())
+}
+
+/// Reads whether any app uses a device
+///
+/// # Errors
+///
+/// Returns an error if the state cannot be read, e.g. because the device was disconnected.
+pub fn running(device: u32) -> Result<bool, Box<dyn Error>> {
+    get::<u32>(device, RUNNING_SOMEWHERE, SCOPE_GLOBAL)
+        .map(|running| running != 0)
+        .map_err(|status| format!("Failed to read whether the device is in use (error {status})").into())
+}
+
+/// Returns the property of the system object holding the default device of a direction
+fn default_selector(direction: Direction) -> u32 {
+    match direction {
+        Direction::Output => DEFAULT_OUTPUT,
+        Direction::Input => DEFAULT_INPUT,
+    }
+}
+
+/// Returns the property scope of a direction
+fn scope(direction: Direction) -> u32 {
+    match direction {
+        Direction::Output => u32::from_be_bytes(*b"outp"),
+        Direction::Input => u32::from_be_bytes(*b"inpt"),
+    }
+}
+
+/// Returns the name of a transport type
+fn transport(kind: u32) -> &'static str {
+    match &kind.to_be_bytes() {
+        b"bltn" => "built-in",
+        b"usb " => "USB",
+        b"blue" | b"blea" => "Bluetooth",
+        b"hdmi" => "HDMI",
+        b"dprt" => "DisplayPort",
+        b"airp" => "AirPlay",
+        b"thun" => "Thunderbolt",
+        b"1394" => "FireWire",
+        b"pci " => "PCI",
+        b"grup" => "aggregate",
+        b"virt" => "virtual",
+        b"ccwd" => "Continuity",
+        _ => "unknown",
+    }
+}
+
+/// Returns the number of channels of a device in a scope
+///
+/// The stream configuration is an `AudioBufferList`: the number of buffers,
+/// followed by buffers of the channel count, the byte size and the data pointer.
+fn channels(device: u32, scope: u32) -> u32 {
+    let address = address(STREAM_CONFIGURATION, scope);
+    let mut size = 0;
+    if unsafe { AudioObjectGetPropertyDataSize(device, &address, 0, ptr::null(), &mut size) } != 0 || size == 0 {
+        return 0;
+    }
+    // u64 elements keep the pointers of the buffers aligned
+    let mut list = vec![0u64; (size as usize).div_ceil(8)];
+    let status =
+        unsafe { AudioObjectGetPropertyData(device, &address, 0, ptr::null(), &mut size, list.as_mut_ptr().cast()) };
+    if status != 0 {
+        return 0;
+    }
+    let bytes: Vec<u8> = list.iter().flat_map(|word| word.to_ne_bytes()).collect();
+    let count = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let buffer_size = 8 + mem::size_of::<*mut c_void>();
+    (0..count)
+        .filter_map(|index| {
+            let offset = 8 + index * buffer_size;
+            bytes
+                .get(offset..offset + 4)
+                .map(|channels| u32::from_ne_bytes([channels[0], channels[1], channels[2], channels[3]]))
+        })
+        .sum()
+}
+
+/// Returns the address of a property of the main element
+fn address(selector: u32, scope: u32) -> PropertyAddress {
+    PropertyAddress {
+        selector,
+        scope,
+        element: ELEMENT_MAIN,
+    }
+}
+
+/// Returns true if an object has a property
+fn has(object: u32, selector: u32, scope: u32) -> bool {
+    unsafe { AudioObjectHasProperty(object, &address(selector, scope)) != 0 }
+}
+
+/// Returns true if a property of an object can be changed
+fn settable(object: u32, selector: u32, scope: u32) -> bool {
+    let mut settable = 0;
+    has(object, selector, scope)
+        && unsafe { AudioObjectIsPropertySettable(object, &address(selector, scope), &mut settable) } == 0
+        && settable != 0
+}
+
+/// Reads a property of a fixed size
+///
+/// # Errors
+///
+/// Returns the `OSStatus` of CoreAudio if the property cannot be read.
+fn get<T: Copy + Default>(object: u32, selector: u32, scope: u32) -> Result<T, i32> {
+    let mut value = T::default();
+    let mut size = mem::size_of::<T>() as u32;
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            object,
+            &address(selector, scope),
+            0,
+            ptr::null(),
+            &mut size,
+            (&mut value as *mut T).cast(),
+        )
+    };
+    if status != 0 {
+        return Err(status);
+    }
+    Ok(value)
+}
+
+/// Reads a property holding an array
+///
+/// # Errors
+///
+/// Returns the `OSStatus` of CoreAudio if the property cannot be read.
+fn get_array<T: Copy + Default>(object: u32, selector: u32, scope: u32) -> Result<Vec<T>, i32> {
+    let address = address(selector, scope);
+    let mut size = 0;
+    let status = unsafe { AudioObjectGetPropertyDataSize(object, &address, 0, ptr::null(), &mut size) };
+    if status != 0 {
+        return Err(status);
+    }
+    let mut values = vec![T::default(); size as usize / mem::size_of::<T>()];
+    let status =
+        unsafe { AudioObjectGetPropertyData(object, &address, 0, ptr::null(), &mut size, values.as_mut_ptr().cast()) };
+    if status != 0 {
+        return Err(status);
+    }
+    values.truncate(size as usize / mem::size_of::<T>());
+    Ok(values)
+}
+
+/// Reads a string property of an object
+fn get_string(object: u32, selector: u32) -> Option<String> {
+    let mut string: CFStringRef = ptr::null();
+    let mut size = mem::size_of::<CFStringRef>() as u32;
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            object,
+            &address(selector, SCOPE_GLOBAL),
+            0,
+            ptr::null(),
+            &mut size,
+            (&mut string as *mut CFStringRef).cast(),
+        )
+    };
+    if status != 0 || string.is_null() {
+        return None;
+    }
+    Some(unsafe { CFString::wrap_under_create_rule(string) }.to_string())
+}
+
+/// Changes a property of a fixed size
+///
+/// # Errors
+///
+/// Returns the `OSStatus` of CoreAudio if the property cannot be changed.
+fn set<T: Copy>(object: u32, selector: u32, scope: u32, value: T) -> Result<(), i32> {
+    let status = unsafe {
+        AudioObjectSetPropertyData(
+            object,
+            &address(selector, scope),
+            0,
+            ptr::null(),
+            mem::size_of::<T>() as u32,
+            (&value as *const T).cast(),
+        )
+    };
+    if status != 0 {
+        return Err(status);
+    }
+    Ok(())
+}
@@ -0,0 +1,323 @@
+//! Input devices and microphone mute
+//!
+//! Lists and switches the default input device, and mutes the microphones for
+//! all apps at once, e.g. for a mute shortcut that works in every meeting app.
+//! Without a device name, `mute` and `unmute` apply to all input devices, so a
+//! meeting app that records from another microphone is muted as well. Devices
+//! without a mute control, like the built-in microphone of some Macs, are
+//! muted by turning their volume down to 0; the previous volume is kept in the
+//! state directory and restored by `unmute`.
+//!
+//! `watch` reports when an app starts or stops recording from a microphone,
+//! optionally as a notification. With `--install`, it runs in the background
+//! as a LaunchAgent that is started at login and restarted if it exits.
+//!
+//! # System Commands Used
+//!
+//! - Devices, volume, mute and use are read and changed through CoreAudio
+//! - `osascript` - Post notifications
+//! - `date` - Format timestamps in local time
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload the LaunchAgent
+
+use super::{
+    Device, Direction, can_mute, devices_of, list, muted, running, select_device, set_muted, set_volume, switch, volume,
+};
+use crate::cli::{AudioInputCommands, AudioInputWatchArgs};
+use crate::config;
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use crate::notify::notify;
+use crate::util::timestamp;
+use colored::Colorize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+/// Label of the LaunchAgent
+const LABEL: &str = "com.github.rfoerthe.rempower.mic-watch";
+
+/// Name of the file receiving the output of the LaunchAgent inside the state directory
+const LOG_FILE: &str = "mic-watch.log";
+
+/// Name of the file with the volumes of devices muted by turning the volume down, inside the state directory
+const VOLUMES_FILE: &str = "mic-volumes";
+
+/// Performs input device operations
+///
+/// # Arguments
+///
+/// * `command` - Input device operation from the command line, `None` for the status
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read or changed.
+pub fn perform(command: Option<AudioInputCommands>) -> Result<(), Box<dyn Error>> {
+    match command {
+        None | Some(AudioInputCommands::Status) => print_status(),
+        Some(AudioInputCommands::List { json }) => list(Direction::Input, json),
+        Some(AudioInputCommands::Set { name }) => {
+            let device = select_device(Some(&name), Direction::Input)?;
+            switch(&device, Direction::Input)
+        }
+        Some(AudioInputCommands::Mute { device }) => mute(device.as_deref(), true),
+        Some(AudioInputCommands::Unmute { device }) => mute(device.as_deref(), false),
+        Some(AudioInputCommands::Toggle) => {
+            let device = select_device(None, Direction::Input)?;
+            mute(None, !is_muted(&device, &read_volumes()))
+        }
+        Some(AudioInputCommands::Watch(args)) => watch(args),
+    }
+}
+
+/// Prints the default input device, its volume, whether it is muted and in use
+///
+/// # Errors
+///
+/// Returns an error if there is no default input device.
+fn print_status() -> Result<(), Box<dyn Error>> {
+    let device = select_device(None, Direction::Input)?;
+    println!("{:<16}{} ({})", "Device".bold(), device.name, device.transport);
+    match volume(device.id, Direction::Input) {
+        Ok(volume) => println!("{:<16}{:.0}%", "Volume".bold(), volume * 100.0),
+        Err(_) => println!("{:<16}{}", "Volume".bold(), "not adjustable".dimmed()),
+    }
+    let muted = if is_muted(&device, &read_volumes()) {
+        "yes".yellow()
+    } else {
+        "no".normal()
+    };
+    println!("{:<16}{muted}", "Muted".bold());
+    let in_use = match running(device.id) {
+        Ok(true) => "yes".yellow(),
+        Ok(false) => "no".normal(),
+        Err(e) => e.to_string().dimmed(),
+    };
+    println!("{:<16}{in_use}", "In use".bold());
+    Ok(())
+}
+
+/// Mutes or unmutes one or all input devices
+///
+/// # Arguments
+///
+/// * `name` - Name of the device, `None` for all input devices
+/// * `mute` - True to mute, false to unmute
+///
+/// # Errors
+///
+/// Returns an error if the named device cannot be (un)muted, or none of all devices.
+fn mute(name: Option<&str>, mute: bool) -> Result<(), Box<dyn Error>> {
+    let devices = match name {
+        Some(name) => vec![select_device(Some(name), Direction::Input)?],
+        None => devices_of(Direction::Input)?,
+    };
+    if devices.is_empty() {
+        return Err("No input device".into());
+    }
+
+    let mut volumes = read_volumes();
+    let mut failed = Vec::new();
+    for device in &devices {
+        print!("{} {}", if mute { "Mute" } else { "Unmute" }, device.name);
+        io::stdout().flush()?;
+        match mute_device(device, mute, &mut volumes) {
+            Ok(()) => println!("{}", " OK".green()),
+            Err(e) => {
+                println!(" {}", e.to_string().red());
+                failed.push(device.name.as_str());
+            }
+        }
+    }
+    write_volumes(&volumes)?;
+
+    if failed.len() == devices.len() {
+        let action = if mute { "muted" } else { "unmuted" };
+        return Err(format!("{} could not be {action}", failed.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Mutes or unmutes a device, through its volume if it has no mute control
+///
+/// # Arguments
+///
+/// * `device` - The device
+/// * `mute` - True to mute, false to unmute
+/// * `volumes` - Volumes of devices muted through their volume, by device uid
+///
+/// # Errors
+///
+/// Returns an error if neither mute nor volume can be changed.
+fn mute_device(device: &Device, mute: bool, volumes: &mut BTreeMap<String, f32>) -> Result<(), Box<dyn Error>> {
+    if can_mute(device.id, Direction::Input) {
+        return set_muted(device.id, Direction::Input, mute);
+    }
+    let current = volume(device.id, Direction::Input).map_err(|_| "The device cannot be muted")?;
+    if mute {
+        if current > 0.0 {
+            volumes.insert(device.uid.clone(), current);
+        }
+        set_volume(device.id, Direction::Input, 0.0)
+    } else {
+        match volumes.remove(&device.uid) {
+            Some(previous) if current == 0.0 => set_volume(device.id, Direction::Input, previous),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns true if a device is muted, or its volume was turned down to mute it
+fn is_muted(device: &Device, volumes: &BTreeMap<String, f32>) -> bool {
+    muted(device.id, Direction::Input).unwrap_or(false)
+        || (volumes.contains_key(&device.uid) && volume(device.id, Direction::Input).is_ok_and(|volume| volume == 0.0))
+}
+
+/// Reads the volumes of devices muted through their volume
+///
+/// Every line holds the uid of a device and its volume, separated by a tab. A
+/// missing or unreadable file yields no volumes.
+fn read_volumes() -> BTreeMap<String, f32> {
+    let Ok(path) = config::state_dir().map(|dir| dir.join(VOLUMES_FILE)) else {
+        return BTreeMap::new();
+    };
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (uid, volume) = line.rsplit_once('\t')?;
+            Some((uid.to_string(), volume.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Writes the volumes of devices muted through their volume, removing the file if there are none
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written or removed.
+fn write_volumes(volumes: &BTreeMap<String, f32>) -> Result<(), Box<dyn Error>> {
+    let path = config::state_dir()?.join(VOLUMES_FILE);
+    if volumes.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content: String = volumes
+        .iter()
+        .map(|(uid, volume)| format!("{uid}\t{volume}\n"))
+        .collect();
+    fs::write(&path, content)?;
+    Ok(())
+}
+
+/// Reports when input devices start or stop being used, or installs or removes the LaunchAgent doing so
+///
+/// # Arguments
+///
+/// * `args` - Watch arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read, or the LaunchAgent cannot be installed or removed.
+fn watch(args: AudioInputWatchArgs) -> Result<(), Box<dyn Error>> {
+    if args.uninstall {
+        return uninstall();
+    }
+    if args.install {
+        return install(&args);
+    }
+
+    devices_of(Direction::Input)?;
+    println!("Watching the microphones every {} s (Ctrl-C to stop)", args.interval);
+
+    let mut in_use: HashMap<String, bool> = HashMap::new();
+    loop {
+        match devices_of(Direction::Input) {
+            Ok(devices) => {
+                for device in devices {
+                    let Ok(running) = running(device.id) else {
+                        continue;
+                    };
+                    let previous = in_use.insert(device.uid.clone(), running).unwrap_or(false);
+                    if running != previous {
+                        report(&device, running, args.notify);
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}", format!("Failed to read the input devices: {e}").red()),
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Prints and optionally posts that a device started or stopped being used
+fn report(device: &Device, running: bool, notify_user: bool) {
+    let title = if running {
+        "Microphone in use"
+    } else {
+        "Microphone no longer in use"
+    };
+    println!("{}  {title}: {}", timestamp(), device.name);
+    if notify_user && let Err(e) = notify(title, &device.name) {
+        eprintln!("{}", e.to_string().red());
+    }
+}
+
+/// Installs and loads the LaunchAgent running the watcher with notifications
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be written or loaded.
+fn install(args: &AudioInputWatchArgs) -> Result<(), Box<dyn Error>> {
+    let program = env::current_exe()?;
+    let log = config::state_dir()?.join(LOG_FILE);
+    if let Some(state_dir) = log.parent() {
+        fs::create_dir_all(state_dir)?;
+    }
+
+    let arguments = [
+        program.to_string_lossy().to_string(),
+        "audio".to_string(),
+        "input".to_string(),
+        "watch".to_string(),
+        "--notify".to_string(),
+        "--interval".to_string(),
+        args.interval.to_string(),
+    ];
+
+    LaunchAgent {
+        label: LABEL,
+        arguments: &arguments,
+        start: Start::KeepAlive,
+        log: Log::All(&log),
+    }
+    .install()?;
+
+    println!(
+        "{} the microphone watcher, logging to {}",
+        "Installed".green(),
+        log.display()
+    );
+    Ok(())
+}
+
+/// Unloads and removes the LaunchAgent
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be unloaded or removed.
+fn uninstall() -> Result<(), Box<dyn Error>> {
+    if !launchd::uninstall(LABEL)? {
+        println!("The microphone watcher is not installed");
+        return Ok(());
+    }
+    println!("{} the microphone watcher", "Removed".green());
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Balance and sample rate of output devices
+//!
+//! macOS sometimes shifts the balance of Bluetooth headphones after they
+//! reconnect, and apps or Audio MIDI Setup may leave a device at a sample rate
+//! that does not match the audio played. Both can only be fixed in the Sound
+//! settings or Audio MIDI Setup otherwise.
+//!
+//! # System Commands Used
+//!
+//! - The virtual main balance and the nominal sample rate of the device are read and changed through CoreAudio
+
+use super::{Direction, sample_rate, sample_rates, select_device, set_balance, set_sample_rate};
+use crate::cli::{AudioBalanceCommands, AudioRateCommands};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Deviation from 0.5 still shown as center
+const CENTER_TOLERANCE: f32 = 0.005;
+
+/// Shows or sets the balance of an output device
+///
+/// # Arguments
+///
+/// * `command` - Balance operation from the command line, `None` to show it
+/// * `device` - Name of the device, `None` for the default output device
+///
+/// # Errors
+///
+/// Returns an error if the device cannot be found or has no balance control.
+pub fn balance(command: Option<AudioBalanceCommands>, device: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let device = select_device(device, Direction::Output)?;
+    match command {
+        None | Some(AudioBalanceCommands::Get) => {
+            println!("{:<16}{}", "Device".bold(), device.name);
+            println!("{:<16}{}", "Balance".bold(), format_balance(super::balance(device.id)?));
+            Ok(())
+        }
+        Some(AudioBalanceCommands::Set { balance }) => {
+            print!("Set the balance of {} to {}", device.name, format_balance(balance));
+            io::stdout().flush()?;
+            if let Err(e) = set_balance(device.id, balance) {
+                println!();
+                return Err(e);
+            }
+            println!("{}", " OK".green());
+            Ok(())
+        }
+    }
+}
+
+/// Lists or sets the sample rate of an output device
+///
+/// # Arguments
+///
+/// * `command` - Sample rate operation from the command line, `None` to list the rates
+/// * `device` - Name of the device, `None` for the default output device
+///
+/// # Errors
+///
+/// Returns an error if the device cannot be found, does not support the rate
+/// or the rate cannot be changed.
+pub fn rate(command: Option<AudioRateCommands>, device: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let device = select_device(device, Direction::Output)?;
+    let rates = sample_rates(device.id)?;
+    match command {
+        None | Some(AudioRateCommands::List) => {
+            let current = sample_rate(device.id).ok();
+            println!("{}", device.name.bold());
+            for (minimum, maximum) in rates {
+                if minimum == maximum {
+                    let rate = format!("{minimum:.0} Hz");
+                    if Some(minimum) == current {
+                        println!("{} {}", "*".green(), rate.green().bold());
+                    } else {
+                        println!("  {rate}");
+                    }
+                } else {
+                    println!("  {minimum:.0} to {maximum:.0} Hz");
+                }
+            }
+            Ok(())
+        }
+        Some(AudioRateCommands::Set { rate }) => {
+            let rate = f64::from(rate);
+            if !rates
+                .iter()
+                .any(|&(minimum, maximum)| (minimum..=maximum).contains(&rate))
+            {
+                return Err(format!(
+                    "{} does not support {rate:.0} Hz, see `rem audio rate list`",
+                    device.name
+                )
+                .into());
+            }
+            print!("Set the sample rate of {} to {rate:.0} Hz", device.name);
+            io::stdout().flush()?;
+            if let Err(e) = set_sample_rate(device.id, rate) {
+                println!();
+                return Err(e);
+            }
+            println!("{}", " OK".green());
+            Ok(())
+        }
+    }
+}
+
+/// Formats a balance with the side it leans to
+fn format_balance(balance: f32) -> String {
+    if (balance - 0.5).abs() < CENTER_TOLERANCE {
+        format!("{balance:.2} (center)")
+    } else if balance < 0.5 {
+        format!("{balance:.2} (left)")
+    } else {
+        format!("{balance:.2} (right)")
+    }
+}
@@ -0,0 +1,137 @@
+//! Output volume
+//!
+//! Shows and changes the volume and mute of the default output device, or of
+//! another one selected by name. Turning the volume up unmutes the device like
+//! the volume keys do. With `--json`, the resulting volume is printed as a
+//! single object after every operation, so that a status bar can update itself
+//! from the output of the command that changed it.
+//!
+//! # System Commands Used
+//!
+//! - The virtual main volume and mute of the device are read and changed through CoreAudio
+
+use super::{Device, Direction, muted, select_device, set_muted, set_volume, volume};
+use crate::cli::{VolumeArgs, VolumeCommands};
+use colored::Colorize;
+use serde::Serialize;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Volume of an output device
+#[derive(Serialize)]
+struct VolumeStatus<'a> {
+    /// The device
+    device: &'a Device,
+    /// Volume in percent, if the device has a volume control
+    volume: Option<u8>,
+    /// True if muted, if the device can be muted
+    muted: Option<bool>,
+}
+
+/// Performs volume operations
+///
+/// # Arguments
+///
+/// * `args` - Volume arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the device cannot be found or its volume cannot be read or changed.
+pub fn perform(args: VolumeArgs) -> Result<(), Box<dyn Error>> {
+    let device = select_device(args.device.as_deref(), Direction::Output)?;
+    let id = device.id;
+    let json = args.json;
+    let show = matches!(args.command, None | Some(VolumeCommands::Get));
+
+    match args.command {
+        None | Some(VolumeCommands::Get) => {}
+        Some(VolumeCommands::Set { percent }) => {
+            change(&format!("Set the volume to {percent}%"), json, || {
+                set_volume(id, Direction::Output, f32::from(percent) / 100.0)
+            })?;
+        }
+        Some(VolumeCommands::Up { step }) => {
+            let percent = (percent(volume(id, Direction::Output)?) + step).min(100);
+            change(&format!("Turn the volume up to {percent}%"), json, || {
+                set_volume(id, Direction::Output, f32::from(percent) / 100.0)?;
+                // Devices without mute control are never muted
+                if muted(id, Direction::Output).unwrap_or(false) {
+                    set_muted(id, Direction::Output, false)?;
+                }
+                Ok(())
+            })?;
+        }
+        Some(VolumeCommands::Down { step }) => {
+            let percent = percent(volume(id, Direction::Output)?).saturating_sub(step);
+            change(&format!("Turn the volume down to {percent}%"), json, || {
+                set_volume(id, Direction::Output, f32::from(percent) / 100.0)
+            })?;
+        }
+        Some(VolumeCommands::Mute) => {
+            change(&format!("Mute {}", device.name), json, || {
+                set_muted(id, Direction::Output, true)
+            })?;
+        }
+        Some(VolumeCommands::Unmute) => {
+            change(&format!("Unmute {}", device.name), json, || {
+                set_muted(id, Direction::Output, false)
+            })?;
+        }
+    }
+
+    let status = VolumeStatus {
+        device: &device,
+        volume: volume(id, Direction::Output).ok().map(percent),
+        muted: muted(id, Direction::Output).ok(),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+    } else if show {
+        print_status(&status);
+    }
+    Ok(())
+}
+
+/// Applies a change, printing it and its result unless JSON is printed
+///
+/// # Errors
+///
+/// Returns an error if the change fails.
+fn change(label: &str, json: bool, apply: impl FnOnce() -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    if json {
+        return apply();
+    }
+    print!("{label}");
+    io::stdout().flush()?;
+    if let Err(e) = apply() {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Prints the volume of a device
+fn print_status(status: &VolumeStatus) {
+    println!(
+        "{:<16}{} ({})",
+        "Device".bold(),
+        status.device.name,
+        status.device.transport
+    );
+    match status.volume {
+        Some(volume) => println!("{:<16}{volume}%", "Volume".bold()),
+        None => println!("{:<16}{}", "Volume".bold(), "not adjustable".dimmed()),
+    }
+    let muted = match status.muted {
+        Some(true) => "yes".yellow(),
+        Some(false) => "no".normal(),
+        None => "not supported".dimmed(),
+    };
+    println!("{:<16}{muted}", "Muted".bold());
+}
+
+/// Converts a volume between 0 and 1 to percent
+fn percent(volume: f32) -> u8 {
+    (volume.clamp(0.0, 1.0) * 100.0).round() as u8
+}
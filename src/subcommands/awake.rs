@@ -0,0 +1,180 @@
+//! Keep the Mac awake
+//!
+//! Prevents idle sleep like `caffeinate`, but takes the power management
+//! assertions directly through IOKit: `PreventUserIdleSystemSleep` keeps the
+//! system awake, `PreventUserIdleDisplaySleep` (with `--display`) also keeps the
+//! display on. The assertions are held for a fixed time, while a command runs or
+//! until Ctrl-C, and show up as `rem awake` in `pmset -g assertions`.
+//!
+//! The remaining (or elapsed) time is shown on a single line that is updated in
+//! place. Ctrl-C and `kill` release the assertions before `rem` exits; a command
+//! started with `--while` receives Ctrl-C as well and is waited for.
+//!
+//! # System Commands Used
+//!
+//! - None; the assertions are taken with `IOPMAssertionCreateWithName`
+
+#[cfg(target_os = "macos")]
+mod iokit;
+
+use crate::cli::AwakeArgs;
+use crate::util::{catch_interrupts, format_clock, interrupted};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "macos")]
+use iokit::Assertion;
+
+/// Assertion type keeping the system from idle sleep
+const PREVENT_SYSTEM_SLEEP: &str = "PreventUserIdleSystemSleep";
+
+/// Assertion type keeping the display from idle sleep
+const PREVENT_DISPLAY_SLEEP: &str = "PreventUserIdleDisplaySleep";
+
+/// Interval of the checks for the end and of the countdown updates
+const TICK: Duration = Duration::from_millis(200);
+
+/// Time the command of `--while` is given to exit after Ctrl-C
+const COMMAND_GRACE: Duration = Duration::from_secs(2);
+
+/// Stand-in for IOKit assertions on other platforms
+#[cfg(not(target_os = "macos"))]
+struct Assertion;
+
+#[cfg(not(target_os = "macos"))]
+impl Assertion {
+    /// Fails, power assertions require IOKit
+    fn create(_kind: &str, _name: &str) -> Result<Self, Box<dyn Error>> {
+        Err("Power assertions are only available on macOS".into())
+    }
+}
+
+/// Reason the assertions were released
+enum End {
+    /// The requested time has passed
+    Elapsed,
+    /// Ctrl-C or `kill`
+    Interrupted,
+    /// The command given with `--while` exited
+    CommandExited(ExitStatus),
+}
+
+/// Keeps the Mac awake for a time, while a command runs or until stopped
+///
+/// # Arguments
+///
+/// * `args` - Awake arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if an assertion cannot be taken, the command cannot be
+/// started, or the command fails.
+pub fn perform(args: AwakeArgs) -> Result<(), Box<dyn Error>> {
+    let name = if args.command.is_empty() {
+        "rem awake".to_string()
+    } else {
+        format!("rem awake while {}", args.command.join(" "))
+    };
+    let mut kinds = vec![PREVENT_SYSTEM_SLEEP];
+    if args.display {
+        kinds.push(PREVENT_DISPLAY_SLEEP);
+    }
+    let assertions = kinds
+        .into_iter()
+        .map(|kind| Assertion::create(kind, &name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    catch_interrupts();
+
+    let mut child = match args.command.split_first() {
+        Some((program, arguments)) => Some(
+            Command::new(program)
+                .args(arguments)
+                .spawn()
+                .map_err(|e| format!("Failed to run {program}: {e}"))?,
+        ),
+        None => None,
+    };
+
+    let what = if args.display {
+        "the Mac and its display"
+    } else {
+        "the Mac"
+    };
+    let until = match (args.duration, child.is_some()) {
+        (Some(duration), true) => format!("for up to {} while the command runs", format_clock(duration)),
+        (Some(duration), false) => format!("for {}", format_clock(duration)),
+        (None, true) => "while the command runs".to_string(),
+        (None, false) => "until stopped".to_string(),
+    };
+    eprintln!("Keeping {what} awake {until} (Ctrl-C to stop)");
+
+    let start = Instant::now();
+    let countdown = io::stderr().is_terminal();
+    let end = loop {
+        if interrupted() {
+            break End::Interrupted;
+        }
+        if let Some(child) = child.as_mut()
+            && let Some(status) = child.try_wait()?
+        {
+            break End::CommandExited(status);
+        }
+        let elapsed = start.elapsed();
+        if args.duration.is_some_and(|duration| elapsed >= duration) {
+            break End::Elapsed;
+        }
+
+        if countdown {
+            let status = match args.duration {
+                Some(duration) => format!("{} left", format_clock(duration.saturating_sub(elapsed))),
+                None => format!("{} elapsed", format_clock(elapsed)),
+            };
+            eprint!("\r\x1b[2K{} {status}", "☕".yellow());
+            io::stderr().flush()?;
+        }
+        thread::sleep(TICK);
+    };
+
+    drop(assertions);
+    if countdown {
+        eprint!("\r\x1b[2K");
+    }
+    let elapsed = format_clock(start.elapsed());
+    match end {
+        End::Elapsed => {
+            eprintln!("{} after {elapsed}", "Released".green());
+            if child.is_some() {
+                eprintln!("The command is still running, the Mac may sleep again");
+            }
+            Ok(())
+        }
+        End::Interrupted => {
+            eprintln!("{} after {elapsed} (interrupted)", "Released".green());
+            // Ctrl-C also reaches the command, give it a moment to exit
+            if let Some(mut child) = child {
+                let deadline = Instant::now() + COMMAND_GRACE;
+                while child.try_wait()?.is_none() {
+                    if Instant::now() >= deadline {
+                        eprintln!("The command is still running, the Mac may sleep again");
+                        break;
+                    }
+                    thread::sleep(TICK);
+                }
+            }
+            Ok(())
+        }
+        End::CommandExited(status) => {
+            eprintln!("{} after {elapsed}, the command finished", "Released".green());
+            if status.success() {
+                Ok(())
+            } else {
+                Err(format!("{} failed ({status})", args.command.join(" ")).into())
+            }
+        }
+    }
+}
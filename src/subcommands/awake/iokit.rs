@@ -0,0 +1,65 @@
+//! IOKit power management assertions
+//!
+//! An assertion keeps the system or the display from sleeping until it is
+//! released. macOS also releases all assertions of a process when it exits, so
+//! an assertion never outlives `rem`.
+
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use std::error::Error;
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOPMAssertionCreateWithName(assertion_type: CFStringRef, level: u32, name: CFStringRef, id: *mut u32) -> i32;
+    fn IOPMAssertionRelease(id: u32) -> i32;
+}
+
+/// `kIOPMAssertionLevelOn`
+const ASSERTION_LEVEL_ON: u32 = 255;
+
+/// `kIOReturnSuccess`
+const SUCCESS: i32 = 0;
+
+/// A power assertion held until dropped
+pub struct Assertion {
+    /// ID assigned by the power management
+    id: u32,
+}
+
+impl Assertion {
+    /// Creates an assertion
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Assertion type, e.g. `PreventUserIdleSystemSleep`
+    /// * `name` - Name shown by `pmset -g assertions`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the power management rejects the assertion.
+    pub fn create(kind: &str, name: &str) -> Result<Self, Box<dyn Error>> {
+        let kind_string = CFString::new(kind);
+        let name_string = CFString::new(name);
+        let mut id = 0;
+        let result = unsafe {
+            IOPMAssertionCreateWithName(
+                kind_string.as_concrete_TypeRef(),
+                ASSERTION_LEVEL_ON,
+                name_string.as_concrete_TypeRef(),
+                &mut id,
+            )
+        };
+        if result != SUCCESS {
+            return Err(format!("Failed to create the {kind} assertion (IOReturn {result:#x})").into());
+        }
+        Ok(Self { id })
+    }
+}
+
+impl Drop for Assertion {
+    fn drop(&mut self) {
+        unsafe {
+            IOPMAssertionRelease(self.id);
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! Battery status and health
+//!
+//! Reads the internal battery through IOKit: charge, charging state and the
+//! estimated time remaining from the power sources, and cycle count, design and
+//! maximum capacity and temperature from the battery controller. The health is
+//! the maximum capacity as a percentage of the design capacity; batteries are
+//! considered worn out below 80%.
+//!
+//! With `--watch`, one line is printed per interval, e.g. to follow charging or
+//! the temperature under load. `rem battery log` records the state over weeks,
+//! `rem battery notify` warns when the battery runs low.
+//!
+//! With `--ups`, UPS devices connected by USB are shown instead, together with
+//! the thresholds at which macOS shuts down while running on UPS power.
+//!
+//! # System Commands Used
+//!
+//! - `pmset -g custom` - Read the shutdown thresholds on UPS power
+//! - `pmset -u haltlevel|haltafter|haltremain` - Set the shutdown thresholds (with sudo)
+//! - The battery is read with `IOPSCopyPowerSourcesInfo` and the
+//!   `AppleSmartBattery` service of the I/O Registry
+
+#[cfg(target_os = "macos")]
+mod iokit;
+pub mod log;
+pub mod notify;
+
+use crate::cli::{BatteryArgs, BatteryCommands};
+use crate::subcommands::power::{pmset_sudo, profiles};
+use crate::util::format_minutes;
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use iokit::read_ups;
+#[cfg(target_os = "macos")]
+pub use iokit::{read_adapter, read_battery};
+
+/// Power management profile used while running on UPS power
+const UPS_PROFILE: &str = "UPS Power";
+
+/// Shutdown thresholds on UPS power with their `pmset` setting and label
+const HALT_SETTINGS: [(&str, &str); 3] = [
+    ("haltlevel", "Halt level"),
+    ("haltafter", "Halt after"),
+    ("haltremain", "Halt remain"),
+];
+
+/// Health below which a battery is considered worn out, in percent
+const WORN_OUT_HEALTH: f64 = 80.0;
+
+/// State of the internal battery
+#[derive(Serialize, Default)]
+pub struct BatteryStatus {
+    /// Charge in percent
+    pub percent: Option<u8>,
+    /// True while charging
+    pub charging: bool,
+    /// True while connected to a charger
+    pub on_ac: bool,
+    /// True if charging finished
+    pub fully_charged: bool,
+    /// Estimated minutes until empty while on battery
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes_to_empty: Option<u32>,
+    /// Estimated minutes until full while charging
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes_to_full: Option<u32>,
+    /// Condition reported by macOS, e.g. `Good` or `Check Battery`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// Number of charge cycles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle_count: Option<u32>,
+    /// Capacity when new in mAh
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub design_capacity_mah: Option<u32>,
+    /// Current full charge capacity in mAh
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_capacity_mah: Option<u32>,
+    /// Battery temperature in degrees Celsius
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature_celsius: Option<f64>,
+    /// Rated power of the connected charger in watts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charger_watts: Option<u32>,
+    /// Battery voltage in mV
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voltage_mv: Option<u32>,
+    /// Current into the battery in mA, negative while discharging
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amperage_ma: Option<i32>,
+}
+
+/// Details of the connected power adapter
+#[derive(Serialize)]
+pub struct AdapterDetails {
+    /// Name, e.g. `96W USB-C Power Adapter`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Manufacturer, e.g. `Apple Inc.`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    /// Serial number, only reported by Apple adapters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Adapter family code identifying the adapter type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<i64>,
+    /// Rated power in watts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watts: Option<u32>,
+    /// Negotiated voltage in mV
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voltage_mv: Option<u32>,
+    /// Negotiated current in mA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_ma: Option<u32>,
+    /// USB Power Delivery profiles offered by the adapter
+    pub profiles: Vec<PdProfile>,
+    /// Index of the profile in use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_profile: Option<usize>,
+}
+
+/// A USB Power Delivery profile offered by an adapter
+#[derive(Serialize)]
+pub struct PdProfile {
+    /// Voltage in mV
+    pub voltage_mv: u32,
+    /// Maximum current in mA
+    pub current_ma: u32,
+}
+
+/// State of an uninterruptible power supply
+#[derive(Serialize)]
+struct UpsStatus {
+    /// Name reported by the UPS, e.g. `Back-UPS ES 700`
+    name: String,
+    /// Connection, e.g. `USB`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transport: Option<String>,
+    /// Serial number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    serial: Option<String>,
+    /// Charge in percent
+    percent: Option<u8>,
+    /// True while the UPS runs on its battery
+    on_battery: bool,
+    /// True while charging
+    charging: bool,
+    /// Estimated minutes until empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minutes_to_empty: Option<u32>,
+    /// Power drawn by the connected devices in watts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    load_watts: Option<f64>,
+}
+
+/// UPS devices with the shutdown thresholds for JSON output
+#[derive(Serialize)]
+struct UpsReport<'a> {
+    /// Connected UPS devices
+    devices: &'a [UpsStatus],
+    /// Shutdown thresholds by `pmset` setting, 0 if disabled
+    thresholds: BTreeMap<&'static str, i64>,
+}
+
+impl BatteryStatus {
+    /// Returns the maximum capacity as a percentage of the design capacity
+    pub fn health_percent(&self) -> Option<f64> {
+        match (self.max_capacity_mah, self.design_capacity_mah) {
+            (Some(max), Some(design)) if design > 0 => Some(f64::from(max) * 100.0 / f64::from(design)),
+            _ => None,
+        }
+    }
+
+    /// Returns the power flowing into the battery in watts, negative while discharging
+    pub fn power_watts(&self) -> Option<f64> {
+        Some(f64::from(self.voltage_mv?) * f64::from(self.amperage_ma?) / 1_000_000.0)
+    }
+
+    /// Describes the charging state, e.g. `charging, 1:05 until full`
+    fn state(&self) -> String {
+        if self.charging {
+            match self.minutes_to_full {
+                Some(minutes) => format!("charging, {} until full", format_minutes(u64::from(minutes))),
+                None => "charging".to_string(),
+            }
+        } else if self.on_ac {
+            if self.fully_charged {
+                "fully charged".to_string()
+            } else {
+                "on power adapter, not charging".to_string()
+            }
+        } else {
+            match self.minutes_to_empty {
+                Some(minutes) => format!("on battery, {} remaining", format_minutes(u64::from(minutes))),
+                None => "on battery, calculating time remaining".to_string(),
+            }
+        }
+    }
+}
+
+/// Prints the battery status once or at an interval
+///
+/// # Arguments
+///
+/// * `args` - Battery arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no battery or the power sources cannot be read.
+pub fn perform(args: BatteryArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Some(BatteryCommands::Log(args)) => return log::perform(args),
+        Some(BatteryCommands::Notify(args)) => return notify::perform(args),
+        None => {}
+    }
+    if args.ups {
+        return ups(&args);
+    }
+    if !args.watch {
+        let status = read_battery()?.ok_or("This Mac has no battery")?;
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&status)?);
+        } else {
+            print_status(&status);
+        }
+        return Ok(());
+    }
+
+    if !args.json {
+        println!("Reading the battery every {} s (Ctrl-C to stop)", args.interval);
+    }
+    loop {
+        match read_battery() {
+            Ok(Some(status)) if args.json => println!("{}", serde_json::to_string(&status)?),
+            Ok(Some(status)) => print_line(&status),
+            Ok(None) => return Err("This Mac has no battery".into()),
+            Err(e) => eprintln!("{}", format!("Failed to read the battery: {e}").red()),
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Shows the UPS devices and shutdown thresholds, or changes the thresholds
+///
+/// # Errors
+///
+/// Returns an error if the power sources cannot be read or `pmset` fails.
+fn ups(args: &BatteryArgs) -> Result<(), Box<dyn Error>> {
+    let changes: Vec<(&str, String)> = [
+        ("haltlevel", args.halt_level.map(u32::from)),
+        ("haltafter", args.halt_after),
+        ("haltremain", args.halt_remain),
+    ]
+    .into_iter()
+    .filter_map(|(setting, value)| Some((setting, value?.to_string())))
+    .collect();
+    if !changes.is_empty() {
+        let mut command = vec!["-u"];
+        for (setting, value) in &changes {
+            command.extend([*setting, value.as_str()]);
+        }
+        print!("Set the shutdown thresholds on UPS power");
+        io::stdout().flush()?;
+        if let Err(e) = pmset_sudo(&command) {
+            println!();
+            return Err(e);
+        }
+        println!("{}", " OK".green());
+        return Ok(());
+    }
+
+    let devices = read_ups()?;
+    let thresholds: BTreeMap<&'static str, i64> = profiles()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|profile| profile.source == UPS_PROFILE)
+        .map(|profile| {
+            HALT_SETTINGS
+                .iter()
+                .filter_map(|(setting, _)| Some((*setting, profile.settings.get(*setting)?.parse().ok()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+    if args.json {
+        let report = UpsReport {
+            devices: &devices,
+            thresholds,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    if devices.is_empty() {
+        println!("No UPS connected");
+    }
+    for ups in &devices {
+        match &ups.transport {
+            Some(transport) => println!("{} ({transport})", ups.name.bold()),
+            None => println!("{}", ups.name.bold()),
+        }
+        let charge = ups
+            .percent
+            .map_or_else(|| "-".to_string(), |percent| format!("{percent}%"));
+        let state = match (ups.on_battery, ups.charging) {
+            (true, _) => "on UPS battery".yellow(),
+            (false, true) => "on AC power, charging".normal(),
+            (false, false) => "on AC power".normal(),
+        };
+        println!("  {:<14}{charge} ({state})", "Charge");
+        if let Some(minutes) = ups.minutes_to_empty {
+            println!("  {:<14}{} on battery", "Runtime", format_minutes(u64::from(minutes)));
+        }
+        if let Some(watts) = ups.load_watts {
+            println!("  {:<14}{watts:.0} W", "Load");
+        }
+        if let Some(serial) = &ups.serial {
+            println!("  {:<14}{serial}", "Serial");
+        }
+    }
+
+    if !thresholds.is_empty() {
+        println!();
+        println!("{}", "Shutdown on UPS power".bold());
+        for (setting, label) in HALT_SETTINGS {
+            let value = match thresholds.get(setting) {
+                Some(value) if *value <= 0 => "off".to_string(),
+                Some(value) if setting == "haltlevel" => format!("at {value}% charge"),
+                Some(value) if setting == "haltafter" => format!("after {value} min"),
+                Some(value) => format!("at {value} min remaining"),
+                None => "-".to_string(),
+            };
+            println!("  {label:<14}{value}");
+        }
+    }
+    Ok(())
+}
+
+/// Prints the battery status as a list of properties
+fn print_status(status: &BatteryStatus) {
+    println!("{:<16}{} ({})", "Charge".bold(), format_percent(status), status.state());
+    if let Some(condition) = &status.condition {
+        println!("{:<16}{condition}", "Condition".bold());
+    }
+    if let Some(health) = status.health_percent() {
+        let text = format!("{health:.1}%");
+        let text = if health < WORN_OUT_HEALTH {
+            text.yellow()
+        } else {
+            text.green()
+        };
+        println!(
+            "{:<16}{text} ({} of {} mAh)",
+            "Health".bold(),
+            status.max_capacity_mah.unwrap_or_default(),
+            status.design_capacity_mah.unwrap_or_default()
+        );
+    }
+    if let Some(cycles) = status.cycle_count {
+        println!("{:<16}{cycles}", "Cycle count".bold());
+    }
+    if let Some(temperature) = status.temperature_celsius {
+        println!("{:<16}{temperature:.1} °C", "Temperature".bold());
+    }
+    if let Some(watts) = status.power_watts() {
+        let direction = if watts < 0.0 { "discharging" } else { "charging" };
+        println!("{:<16}{:.1} W {direction}", "Power".bold(), watts.abs());
+    }
+    if let Some(watts) = status.charger_watts {
+        println!("{:<16}{watts} W", "Charger".bold());
+    }
+}
+
+/// Prints the battery status as a single line for watch mode
+fn print_line(status: &BatteryStatus) {
+    let mut fields = vec![format_percent(status).to_string(), status.state()];
+    if let Some(temperature) = status.temperature_celsius {
+        fields.push(format!("{temperature:.1} °C"));
+    }
+    if let Some(watts) = status.charger_watts {
+        fields.push(format!("{watts} W charger"));
+    }
+    println!("{}", fields.join("  "));
+}
+
+/// Formats the charge, colored by how low it is
+fn format_percent(status: &BatteryStatus) -> ColoredString {
+    match status.percent {
+        Some(percent) if percent < 20 => format!("{percent}%").red(),
+        Some(percent) if percent < 50 && !status.on_ac => format!("{percent}%").yellow(),
+        Some(percent) => format!("{percent}%").normal(),
+        None => "-".normal(),
+    }
+}
+
+/// Reads the state of the internal battery; requires IOKit
+#[cfg(not(target_os = "macos"))]
+pub fn read_battery() -> Result<Option<BatteryStatus>, Box<dyn Error>> {
+    Err("IOKit power sources are only available on macOS".into())
+}
+
+/// Reads the details of the connected power adapter; requires IOKit
+#[cfg(not(target_os = "macos"))]
+pub fn read_adapter() -> Result<Option<AdapterDetails>, Box<dyn Error>> {
+    Err("IOKit power sources are only available on macOS".into())
+}
+
+/// Reads the state of all connected UPS devices; requires IOKit
+#[cfg(not(target_os = "macos"))]
+fn read_ups() -> Result<Vec<UpsStatus>, Box<dyn Error>> {
+    Err("IOKit power sources are only available on macOS".into())
+}
@@ -0,0 +1,218 @@
+//! Battery information from IOKit
+//!
+//! The power source API (`IOPowerSources`) reports the charge, the charging
+//! state and the estimated times as shown in the menu bar. Cycle count,
+//! capacities and temperature are only available as properties of the
+//! `AppleSmartBattery` service in the I/O Registry. UPS devices connected by USB
+//! are power sources as well.
+
+use super::{AdapterDetails, BatteryStatus, PdProfile, UpsStatus};
+use crate::iokit::{Properties, matching_service};
+use core_foundation::array::{CFArray, CFArrayRef};
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use std::error::Error;
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOPSCopyPowerSourcesInfo() -> CFTypeRef;
+    fn IOPSCopyPowerSourcesList(blob: CFTypeRef) -> CFArrayRef;
+    fn IOPSGetPowerSourceDescription(blob: CFTypeRef, source: CFTypeRef) -> CFDictionaryRef;
+    fn IOPSCopyExternalPowerAdapterDetails() -> CFDictionaryRef;
+}
+
+/// Power source type of internal batteries (`kIOPSInternalBatteryType`)
+const INTERNAL_BATTERY: &str = "InternalBattery";
+
+/// Power source type of uninterruptible power supplies (`kIOPSUPSType`)
+const UPS: &str = "UPS";
+
+/// Power source state while connected to a charger (`kIOPSACPowerValue`)
+const AC_POWER: &str = "AC Power";
+
+/// Reads the state of the internal battery
+///
+/// # Returns
+///
+/// `None` if the Mac has no internal battery.
+///
+/// # Errors
+///
+/// Returns an error if the power sources cannot be read.
+pub fn read_battery() -> Result<Option<BatteryStatus>, Box<dyn Error>> {
+    let Some(source) = internal_battery()? else {
+        return Ok(None);
+    };
+
+    let minutes = |key: &str| {
+        source
+            .number(key)
+            .filter(|&minutes| minutes >= 0)
+            .and_then(|minutes| u32::try_from(minutes).ok())
+    };
+    let charging = source.boolean("Is Charging").unwrap_or(false);
+    let on_ac = source.string("Power Source State").as_deref() == Some(AC_POWER);
+    let mut status = BatteryStatus {
+        percent: source
+            .number("Current Capacity")
+            .and_then(|value| u8::try_from(value).ok()),
+        charging,
+        on_ac,
+        fully_charged: source.boolean("Is Charged").unwrap_or(false),
+        minutes_to_empty: if on_ac { None } else { minutes("Time to Empty") },
+        minutes_to_full: if charging { minutes("Time to Full Charge") } else { None },
+        condition: source
+            .string("BatteryHealthCondition")
+            .or_else(|| source.string("BatteryHealth")),
+        ..BatteryStatus::default()
+    };
+
+    if let Some(battery) = smart_battery() {
+        let capacity = |key: &str| battery.number(key).and_then(|value| u32::try_from(value).ok());
+        status.cycle_count = capacity("CycleCount");
+        status.design_capacity_mah = capacity("DesignCapacity");
+        // On Apple silicon "MaxCapacity" is a percentage, the raw value is in mAh
+        status.max_capacity_mah = capacity("AppleRawMaxCapacity")
+            .or_else(|| capacity("NominalChargeCapacity"))
+            .or_else(|| capacity("MaxCapacity").filter(|&value| value > 100));
+        status.temperature_celsius = battery.number("Temperature").map(|value| value as f64 / 100.0);
+        status.voltage_mv = capacity("Voltage");
+        // Negative currents may be stored as unsigned values, the low 32 bits are the signed current
+        status.amperage_ma = battery
+            .number("InstantAmperage")
+            .or_else(|| battery.number("Amperage"))
+            .map(|value| value as i32);
+    }
+
+    let adapter = unsafe { IOPSCopyExternalPowerAdapterDetails() };
+    if !adapter.is_null() {
+        let adapter = Properties(unsafe { CFDictionary::wrap_under_create_rule(adapter) });
+        status.charger_watts = adapter.number("Watts").and_then(|value| u32::try_from(value).ok());
+    }
+
+    Ok(Some(status))
+}
+
+/// Reads the details of the connected power adapter
+///
+/// # Returns
+///
+/// `None` if no power adapter is connected.
+///
+/// # Errors
+///
+/// Never fails on macOS; the signature matches the stub of other platforms.
+pub fn read_adapter() -> Result<Option<AdapterDetails>, Box<dyn Error>> {
+    let adapter = unsafe { IOPSCopyExternalPowerAdapterDetails() };
+    if adapter.is_null() {
+        return Ok(None);
+    }
+    let adapter = Properties(unsafe { CFDictionary::wrap_under_create_rule(adapter) });
+    let unsigned = |key: &str| adapter.number(key).and_then(|value| u32::try_from(value).ok());
+    let text = |key: &str| adapter.string(key).filter(|value| !value.is_empty());
+
+    Ok(Some(AdapterDetails {
+        name: text("Name"),
+        manufacturer: text("Manufacturer"),
+        serial: text("SerialString"),
+        family: adapter.number("FamilyCode"),
+        watts: unsigned("Watts"),
+        voltage_mv: unsigned("AdapterVoltage").or_else(|| unsigned("Voltage")),
+        current_ma: unsigned("Current"),
+        // USB Power Delivery profiles offered by the adapter
+        profiles: adapter
+            .dictionaries("UsbHvcMenu")
+            .iter()
+            .filter_map(|profile| {
+                Some(PdProfile {
+                    voltage_mv: u32::try_from(profile.number("MaxVoltage")?).ok()?,
+                    current_ma: u32::try_from(profile.number("MaxCurrent")?).ok()?,
+                })
+            })
+            .collect(),
+        selected_profile: adapter
+            .number("UsbHvcHvcIndex")
+            .and_then(|index| usize::try_from(index).ok()),
+    }))
+}
+
+/// Returns the description of the internal battery from the power sources
+///
+/// # Errors
+///
+/// Returns an error if the power sources cannot be read.
+fn internal_battery() -> Result<Option<Properties>, Box<dyn Error>> {
+    Ok(power_sources()?
+        .into_iter()
+        .find(|source| source.string("Type").as_deref() == Some(INTERNAL_BATTERY)))
+}
+
+/// Returns the descriptions of all power sources
+///
+/// # Errors
+///
+/// Returns an error if the power sources cannot be read.
+fn power_sources() -> Result<Vec<Properties>, Box<dyn Error>> {
+    let blob = unsafe { IOPSCopyPowerSourcesInfo() };
+    if blob.is_null() {
+        return Err("Failed to read the power sources".into());
+    }
+    let blob = unsafe { CFType::wrap_under_create_rule(blob) };
+    let list = unsafe { IOPSCopyPowerSourcesList(blob.as_CFTypeRef()) };
+    if list.is_null() {
+        return Err("Failed to read the power sources".into());
+    }
+    let list: CFArray<CFType> = unsafe { CFArray::wrap_under_create_rule(list) };
+
+    Ok(list
+        .iter()
+        .filter_map(|source| {
+            let description = unsafe { IOPSGetPowerSourceDescription(blob.as_CFTypeRef(), source.as_CFTypeRef()) };
+            // The description belongs to the blob, it is retained to outlive it
+            (!description.is_null()).then(|| Properties(unsafe { CFDictionary::wrap_under_get_rule(description) }))
+        })
+        .collect())
+}
+
+/// Reads the state of all connected UPS devices
+///
+/// # Errors
+///
+/// Returns an error if the power sources cannot be read.
+pub fn read_ups() -> Result<Vec<UpsStatus>, Box<dyn Error>> {
+    Ok(power_sources()?
+        .into_iter()
+        .filter(|source| source.string("Type").as_deref() == Some(UPS))
+        .map(|source| {
+            let on_battery = source.string("Power Source State").as_deref() != Some(AC_POWER);
+            let percent = match (source.number("Current Capacity"), source.number("Max Capacity")) {
+                (Some(current), Some(max)) if max > 0 => u8::try_from(current * 100 / max).ok(),
+                (current, _) => current.and_then(|value| u8::try_from(value).ok()),
+            };
+            let load_watts = match (source.number("Voltage"), source.number("Current")) {
+                (Some(voltage), Some(current)) => Some((voltage * current).unsigned_abs() as f64 / 1_000_000.0),
+                _ => None,
+            };
+            UpsStatus {
+                name: source.string("Name").unwrap_or_else(|| "UPS".to_string()),
+                transport: source.string("Transport Type"),
+                serial: source
+                    .string("Hardware Serial Number")
+                    .filter(|serial| !serial.is_empty()),
+                percent,
+                on_battery,
+                charging: source.boolean("Is Charging").unwrap_or(false),
+                minutes_to_empty: source
+                    .number("Time to Empty")
+                    .filter(|&minutes| minutes >= 0)
+                    .and_then(|minutes| u32::try_from(minutes).ok()),
+                load_watts,
+            }
+        })
+        .collect())
+}
+
+/// Returns the properties of the `AppleSmartBattery` service
+fn smart_battery() -> Option<Properties> {
+    matching_service(c"AppleSmartBattery")?.properties()
+}
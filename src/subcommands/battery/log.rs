@@ -0,0 +1,320 @@
+//! Battery history
+//!
+//! Appends the battery state to `battery-log.csv` in the rempower state
+//! directory, one sample per run. With `--install`, a LaunchAgent records a
+//! sample at an interval, also right after login and wake.
+//!
+//! `report` groups the samples by week (starting on Monday) and charts the
+//! health with the maximum capacity and cycle count of the last sample of each
+//! week, to follow the wear of the battery over months.
+//!
+//! # System Commands Used
+//!
+//! - `date` - Format timestamps in local time
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload the LaunchAgent
+
+use super::read_battery;
+use crate::cli::{BatteryLogArgs, BatteryLogCommands};
+use crate::config;
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use crate::util::{parse_date, timestamp};
+use colored::Colorize;
+use std::env;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Label of the LaunchAgent
+const LABEL: &str = "com.github.rfoerthe.rempower.battery-log";
+
+/// Name of the log file inside the state directory
+const LOG_FILE: &str = "battery-log.csv";
+
+/// Name of the file receiving errors of the LaunchAgent inside the state directory
+const ERROR_FILE: &str = "battery-log.err";
+
+/// Columns of the log file
+const HEADER: &str = "timestamp,percent,charging,on_ac,cycle_count,design_capacity_mah,max_capacity_mah,health_percent,temperature_celsius";
+
+/// Width of the health bars in the report
+const BAR_WIDTH: usize = 30;
+
+/// Characters drawing the fractional end of a bar, in eighths
+const BAR_EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// Last sample of a week
+struct Week {
+    /// Monday of the week as days since the Unix epoch
+    monday: i64,
+    /// Health in percent
+    health: Option<f64>,
+    /// Maximum capacity in mAh
+    max_capacity: Option<u32>,
+    /// Number of charge cycles
+    cycles: Option<u32>,
+}
+
+/// Records a sample, reports the log, or installs or removes the LaunchAgent
+///
+/// # Arguments
+///
+/// * `args` - Battery log arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no battery, the log cannot be read or
+/// written, or the LaunchAgent cannot be installed or removed.
+pub fn perform(args: BatteryLogArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(BatteryLogCommands::Report { weeks }) = args.command {
+        return report(weeks);
+    }
+    if args.uninstall {
+        return uninstall();
+    }
+    if args.install {
+        return install(args.interval);
+    }
+
+    let path = log_path()?;
+    record(&path)?;
+    println!("Recorded the battery state to {}", path.display());
+    Ok(())
+}
+
+/// Appends the current battery state to the log, creating it with a header
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no battery or the log cannot be written.
+fn record(path: &Path) -> Result<(), Box<dyn Error>> {
+    let status = read_battery()?.ok_or("This Mac has no battery")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let new = !path.exists();
+
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let line = [
+        timestamp(),
+        optional(status.percent.map(|value| value.to_string())),
+        status.charging.to_string(),
+        status.on_ac.to_string(),
+        optional(status.cycle_count.map(|value| value.to_string())),
+        optional(status.design_capacity_mah.map(|value| value.to_string())),
+        optional(status.max_capacity_mah.map(|value| value.to_string())),
+        optional(status.health_percent().map(|value| format!("{value:.1}"))),
+        optional(status.temperature_celsius.map(|value| format!("{value:.1}"))),
+    ]
+    .join(",");
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if new {
+        writeln!(file, "{HEADER}")?;
+    }
+    writeln!(file, "{line}")?;
+    Ok(())
+}
+
+/// Charts the health, capacity and cycle count of the recorded samples by week
+///
+/// # Arguments
+///
+/// * `weeks` - Number of most recent weeks to include
+///
+/// # Errors
+///
+/// Returns an error if the log does not exist or cannot be read.
+fn report(weeks: u64) -> Result<(), Box<dyn Error>> {
+    let path = log_path()?;
+    let content = fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "Failed to read {}: {e}; record samples with `rem battery log --install`",
+            path.display()
+        )
+    })?;
+    let mut all = parse_log(&content);
+    if all.is_empty() {
+        return Err(format!("{} has no samples yet", path.display()).into());
+    }
+    let latest = all.last().map(|week| week.monday).unwrap_or_default();
+    let weeks = i64::try_from(weeks).unwrap_or(i64::MAX);
+    all.retain(|week| latest - week.monday < weeks.saturating_mul(7));
+
+    let healths: Vec<f64> = all.iter().filter_map(|week| week.health).collect();
+    // Bars start a little below the lowest health, so small changes stay visible
+    let low = (healths.iter().copied().fold(f64::INFINITY, f64::min) - 2.0)
+        .floor()
+        .max(0.0);
+    let high = healths.iter().copied().fold(100.0, f64::max);
+
+    println!(
+        "{:<12}{:<w$}{:>10}{:>8}",
+        "Week of".bold(),
+        "Health".bold(),
+        "Capacity".bold(),
+        "Cycles".bold(),
+        w = BAR_WIDTH + 7
+    );
+    for week in &all {
+        let health = match week.health {
+            Some(health) => format!("{} {health:>5.1}%", bar((health - low) / (high - low).max(1.0))),
+            None => format!("{:<BAR_WIDTH$} {:>6}", "", "-"),
+        };
+        let capacity = week
+            .max_capacity
+            .map_or_else(|| "-".to_string(), |value| format!("{value} mAh"));
+        let cycles = week.cycles.map_or_else(|| "-".to_string(), |value| value.to_string());
+        println!("{:<12}{health}{capacity:>10}{cycles:>8}", format_date(week.monday));
+    }
+
+    if let (Some(first), Some(last)) = (all.first(), all.last())
+        && last.monday > first.monday
+    {
+        let span = (last.monday - first.monday) / 7;
+        let mut changes = Vec::new();
+        if let (Some(from), Some(to)) = (first.health, last.health) {
+            changes.push(format!("health {:+.1} points", to - from));
+        }
+        if let (Some(from), Some(to)) = (first.cycles, last.cycles) {
+            let cycles = i64::from(to) - i64::from(from);
+            changes.push(format!(
+                "{cycles:+} cycles ({:.1} per week)",
+                cycles as f64 / span as f64
+            ));
+        }
+        if !changes.is_empty() {
+            println!();
+            println!("Over {span} week(s): {}", changes.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Parses the log into the last sample of each week, oldest first
+///
+/// Lines that cannot be parsed are skipped.
+fn parse_log(content: &str) -> Vec<Week> {
+    let mut lines = content.lines();
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let columns: Vec<&str> = header.split(',').collect();
+    let column = |name: &str| columns.iter().position(|column| *column == name);
+    let (Some(timestamp), Some(health), Some(capacity), Some(cycles)) = (
+        column("timestamp"),
+        column("health_percent"),
+        column("max_capacity_mah"),
+        column("cycle_count"),
+    ) else {
+        return Vec::new();
+    };
+
+    let mut weeks: Vec<Week> = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').collect();
+        let Some(days) = fields.get(timestamp).and_then(|value| parse_date(value)) else {
+            continue;
+        };
+        // 1970-01-01 was a Thursday
+        let monday = days - (days + 3).rem_euclid(7);
+        let field = |index: usize| fields.get(index).filter(|value| !value.is_empty());
+        let week = Week {
+            monday,
+            health: field(health).and_then(|value| value.parse().ok()),
+            max_capacity: field(capacity).and_then(|value| value.parse().ok()),
+            cycles: field(cycles).and_then(|value| value.parse().ok()),
+        };
+        match weeks.iter_mut().find(|existing| existing.monday == monday) {
+            Some(existing) => *existing = week,
+            None => weeks.push(week),
+        }
+    }
+    weeks.sort_by_key(|week| week.monday);
+    weeks
+}
+
+/// Draws a bar for a fraction between 0 and 1
+fn bar(fraction: f64) -> String {
+    let eighths = (fraction.clamp(0.0, 1.0) * (BAR_WIDTH * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths % 8 > 0 {
+        bar.push(BAR_EIGHTHS[eighths % 8]);
+    }
+    format!("{bar:<BAR_WIDTH$}")
+}
+
+/// Formats days since the Unix epoch as `YYYY-MM-DD`
+fn format_date(days: i64) -> String {
+    // Civil date from days, the inverse of `parse_date`
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Returns the path of the log file
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined.
+fn log_path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(config::state_dir()?.join(LOG_FILE))
+}
+
+/// Installs and loads the LaunchAgent recording a sample at an interval
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be written or loaded.
+fn install(interval: u64) -> Result<(), Box<dyn Error>> {
+    let program = env::current_exe()?;
+    let state_dir = config::state_dir()?;
+    fs::create_dir_all(&state_dir)?;
+
+    LaunchAgent {
+        label: LABEL,
+        arguments: &[
+            program.to_string_lossy().to_string(),
+            "battery".to_string(),
+            "log".to_string(),
+        ],
+        start: Start::Interval(interval * 60),
+        log: Log::Errors(&state_dir.join(ERROR_FILE)),
+    }
+    .install()?;
+
+    println!(
+        "{} the battery log, recording every {interval} min to {}",
+        "Installed".green(),
+        state_dir.join(LOG_FILE).display()
+    );
+    Ok(())
+}
+
+/// Unloads and removes the LaunchAgent; the log is kept
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be unloaded or removed.
+fn uninstall() -> Result<(), Box<dyn Error>> {
+    if !launchd::uninstall(LABEL)? {
+        println!("The battery log is not installed");
+        return Ok(());
+    }
+    println!(
+        "{} the battery log LaunchAgent, the recorded samples are kept",
+        "Removed".green()
+    );
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! Low battery notifications
+//!
+//! Checks the charge at an interval and posts a notification when it drops
+//! below the low level, and an urgent one when it drops below the critical
+//! level. At the critical level, an optional hook command is run, e.g.
+//! to save work or put the Mac to sleep. Each level notifies once per
+//! discharge; connecting the power adapter re-arms them.
+//!
+//! With `--install`, the watcher runs in the background as a LaunchAgent that is
+//! started at login and restarted if it exits.
+//!
+//! # System Commands Used
+//!
+//! - `osascript` - Post notifications
+//! - `sh -c` - Run the hook command
+//! - `date` - Format timestamps in local time
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload the LaunchAgent
+
+use super::read_battery;
+use crate::cli::BatteryNotifyArgs;
+use crate::config;
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use crate::notify::notify;
+use crate::util::timestamp;
+use colored::Colorize;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Label of the LaunchAgent
+const LABEL: &str = "com.github.rfoerthe.rempower.battery-notify";
+
+/// Name of the file receiving the output of the LaunchAgent inside the state directory
+const LOG_FILE: &str = "battery-notify.log";
+
+/// Level of a low battery warning
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Level {
+    /// Charge above the low level, or the Mac is charging
+    Normal,
+    /// Charge below the low level
+    Low,
+    /// Charge below the critical level
+    Critical,
+}
+
+/// Watches the charge, or installs or removes the LaunchAgent doing so
+///
+/// # Arguments
+///
+/// * `args` - Notify arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the levels are invalid, the Mac has no battery, or the
+/// LaunchAgent cannot be installed or removed.
+pub fn perform(args: BatteryNotifyArgs) -> Result<(), Box<dyn Error>> {
+    if args.uninstall {
+        return uninstall();
+    }
+    if args.critical >= args.below {
+        return Err(format!(
+            "The critical level ({}%) must be below the low level ({}%)",
+            args.critical, args.below
+        )
+        .into());
+    }
+    if args.install {
+        return install(&args);
+    }
+
+    read_battery()?.ok_or("This Mac has no battery")?;
+    println!(
+        "Watching the battery every {} s, notifying below {}% and {}% (Ctrl-C to stop)",
+        args.interval, args.below, args.critical
+    );
+
+    let mut notified = Level::Normal;
+    loop {
+        match read_battery() {
+            Ok(Some(status)) => {
+                if let Some(percent) = status.percent {
+                    let level = if status.on_ac {
+                        Level::Normal
+                    } else if percent < args.critical {
+                        Level::Critical
+                    } else if percent < args.below {
+                        Level::Low
+                    } else {
+                        Level::Normal
+                    };
+                    // Only escalations are reported; a charge wobbling around a level does not
+                    // re-arm it, only the power adapter does
+                    if level > notified {
+                        warn(level, percent, &args);
+                        notified = level;
+                    } else if status.on_ac {
+                        notified = Level::Normal;
+                    }
+                }
+            }
+            Ok(None) => return Err("This Mac has no battery".into()),
+            Err(e) => eprintln!("{}", format!("Failed to read the battery: {e}").red()),
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Posts the notification of a level and runs the hook at the critical level
+fn warn(level: Level, percent: u8, args: &BatteryNotifyArgs) {
+    let (title, message) = match level {
+        Level::Critical => (
+            "Battery critically low",
+            format!("{percent}% remaining, connect the power adapter now"),
+        ),
+        _ => ("Battery low", format!("{percent}% remaining")),
+    };
+    println!("{}  {title}, {message}", timestamp());
+    if let Err(e) = notify(title, &message) {
+        eprintln!("{}", e.to_string().red());
+    }
+
+    if level == Level::Critical
+        && let Some(hook) = &args.hook
+    {
+        println!("{}  Running {hook}", timestamp());
+        match Command::new("sh")
+            .args(["-c", hook])
+            .env("REM_BATTERY_PERCENT", percent.to_string())
+            .status()
+        {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("{}", format!("The hook failed ({status})").red()),
+            Err(e) => eprintln!("{}", format!("Failed to run the hook: {e}").red()),
+        }
+    }
+}
+
+/// Installs and loads the LaunchAgent running the watcher with the given options
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be written or loaded.
+fn install(args: &BatteryNotifyArgs) -> Result<(), Box<dyn Error>> {
+    let program = env::current_exe()?;
+    let log = config::state_dir()?.join(LOG_FILE);
+    if let Some(state_dir) = log.parent() {
+        fs::create_dir_all(state_dir)?;
+    }
+
+    let mut arguments = vec![
+        program.to_string_lossy().to_string(),
+        "battery".to_string(),
+        "notify".to_string(),
+        "--below".to_string(),
+        args.below.to_string(),
+        "--critical".to_string(),
+        args.critical.to_string(),
+        "--interval".to_string(),
+        args.interval.to_string(),
+    ];
+    if let Some(hook) = &args.hook {
+        arguments.extend(["--hook".to_string(), hook.clone()]);
+    }
+
+    LaunchAgent {
+        label: LABEL,
+        arguments: &arguments,
+        start: Start::KeepAlive,
+        log: Log::All(&log),
+    }
+    .install()?;
+
+    println!(
+        "{} the battery watcher, notifying below {}% and {}%, logging to {}",
+        "Installed".green(),
+        args.below,
+        args.critical,
+        log.display()
+    );
+    Ok(())
+}
+
+/// Unloads and removes the LaunchAgent
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be unloaded or removed.
+fn uninstall() -> Result<(), Box<dyn Error>> {
+    if !launchd::uninstall(LABEL)? {
+        println!("The battery watcher is not installed");
+        return Ok(());
+    }
+    println!("{} the battery watcher", "Removed".green());
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Bluetooth
+//!
+//! Shows and switches the power of the Bluetooth controller through
+//! IOBluetooth, the same way the Bluetooth settings and `blueutil` do, so no
+//! additional tool needs to be installed. The controller turns on and off
+//! asynchronously; `on` and `off` wait until it reports the new state.
+//!
+//! `devices` lists the paired devices, and `connect` and `disconnect` select
+//! one by address or by name, matched loosely like audio devices, so that
+//! `rem bt connect airpods` moves AirPods over to this Mac.
+//!
+//! macOS asks once whether the terminal app may use Bluetooth; without this
+//! permission, Bluetooth appears to be off and no device is paired.
+//!
+//! # System Commands Used
+//!
+//! - None; the power state, controller and devices are read and changed through IOBluetooth
+
+#[cfg(target_os = "macos")]
+mod iobluetooth;
+
+use crate::cli::{BluetoothArgs, BluetoothCommands};
+use crate::util::match_name;
+use colored::Colorize;
+use serde::Serialize;
+use std::error::Error;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "macos")]
+use iobluetooth::{connect, controller, devices, disconnect, powered, set_powered};
+
+/// Time the controller is given to turn on or off
+const SWITCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interval of the checks whether the controller switched
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The Bluetooth controller of the Mac
+pub struct Controller {
+    /// Name the Mac is seen as by other devices
+    pub name: Option<String>,
+    /// Bluetooth address, e.g. `a4:83:e7:12:34:56`
+    pub address: Option<String>,
+}
+
+/// A paired Bluetooth device
+#[derive(Serialize)]
+pub struct Device {
+    /// Name of the device, e.g. `AirPods Pro`
+    pub name: String,
+    /// Bluetooth address, e.g. `a4:83:e7:12:34:56`
+    pub address: String,
+    /// Kind of device from its class, e.g. `audio` or `keyboard`
+    pub kind: &'static str,
+    /// True while connected to this Mac
+    pub connected: bool,
+}
+
+/// Performs Bluetooth operations
+///
+/// # Arguments
+///
+/// * `args` - Bluetooth arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller, it does not
+/// switch in time, or a device cannot be found or (dis)connected.
+pub fn perform(args: BluetoothArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(BluetoothCommands::Status) => print_status(),
+        Some(BluetoothCommands::On) => switch(true),
+        Some(BluetoothCommands::Off) => switch(false),
+        Some(BluetoothCommands::Toggle) => switch(!powered()?),
+        Some(BluetoothCommands::Devices { json }) => list(json),
+        Some(BluetoothCommands::Connect { device }) => change_connection(&device, true),
+        Some(BluetoothCommands::Disconnect { device }) => change_connection(&device, false),
+    }
+}
+
+/// Prints the power state and the controller
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller.
+fn print_status() -> Result<(), Box<dyn Error>> {
+    let power = if powered()? { "on".green() } else { "off".yellow() };
+    println!("{:<16}{power}", "Bluetooth".bold());
+    let controller = controller()?;
+    if let Some(name) = controller.name {
+        println!("{:<16}{name}", "Name".bold());
+    }
+    if let Some(address) = controller.address {
+        println!("{:<16}{address}", "Address".bold());
+    }
+    Ok(())
+}
+
+/// Lists the paired devices, connected devices first
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller.
+fn list(json: bool) -> Result<(), Box<dyn Error>> {
+    let mut devices = devices()?;
+    devices.sort_by_key(|device| (!device.connected, device.name.to_lowercase()));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&devices)?);
+        return Ok(());
+    }
+    if devices.is_empty() {
+        println!("No paired device");
+        return Ok(());
+    }
+    for device in devices {
+        let details = format!("{}, {}", device.kind, device.address);
+        if device.connected {
+            println!("{} {} {}", "*".green(), device.name.green().bold(), details.dimmed());
+        } else {
+            println!("  {} {}", device.name, details.dimmed());
+        }
+    }
+    Ok(())
+}
+
+/// Connects or disconnects a paired device
+///
+/// # Arguments
+///
+/// * `query` - Name, part of the name or address of the device
+/// * `connected` - True to connect, false to disconnect
+///
+/// # Errors
+///
+/// Returns an error if Bluetooth is off, the device cannot be found, or the
+/// connection cannot be opened or closed.
+fn change_connection(query: &str, connected: bool) -> Result<(), Box<dyn Error>> {
+    if !powered()? {
+        return Err("Bluetooth is off, turn it on with `rem bt on`".into());
+    }
+    let device = select_device(query)?;
+    if device.connected == connected {
+        let state = if connected {
+            "already connected"
+        } else {
+            "not connected"
+        };
+        println!("{} is {state}", device.name);
+        return Ok(());
+    }
+
+    print!("{} {}", if connected { "Connect" } else { "Disconnect" }, device.name);
+    io::stdout().flush()?;
+    let result = if connected {
+        connect(&device.address)
+    } else {
+        disconnect(&device.address)
+    };
+    if let Err(e) = result {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Returns a paired device by address or name
+///
+/// # Errors
+///
+/// Returns an error if the devices cannot be read, or no device or more than
+/// one device matches.
+fn select_device(query: &str) -> Result<Device, Box<dyn Error>> {
+    let mut devices = devices()?;
+    let address = query.replace('-', ":").to_lowercase();
+    if let Some(index) = devices.iter().position(|device| device.address == address) {
+        return Ok(devices.swap_remove(index));
+    }
+    let names: Vec<&str> = devices.iter().map(|device| device.name.as_str()).collect();
+    match match_name(&names, query)? {
+        Some(index) => Ok(devices.swap_remove(index)),
+        None => Err(format!("No paired device matches '{query}', see `rem bt devices`").into()),
+    }
+}
+
+/// Turns Bluetooth on or off and waits until the controller switched
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller or it does not
+/// switch within [`SWITCH_TIMEOUT`].
+pub fn switch(on: bool) -> Result<(), Box<dyn Error>> {
+    print!("Turn Bluetooth {}", if on { "on" } else { "off" });
+    io::stdout().flush()?;
+    if let Err(e) = set_powered(on).and_then(|()| wait_for(on)) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Waits until the controller reports a power state
+///
+/// # Errors
+///
+/// Returns an error if it does not within [`SWITCH_TIMEOUT`].
+fn wait_for(on: bool) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    while powered()? != on {
+        if start.elapsed() > SWITCH_TIMEOUT {
+            return Err(format!(
+                "Bluetooth did not turn {} within {} seconds, check that the terminal may use Bluetooth",
+                if on { "on" } else { "off" },
+                SWITCH_TIMEOUT.as_secs()
+            )
+            .into());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+/// Reads whether Bluetooth is on; requires IOBluetooth
+#[cfg(not(target_os = "macos"))]
+fn powered() -> Result<bool, Box<dyn Error>> {
+    Err("Bluetooth can only be controlled on macOS".into())
+}
+
+/// Turns Bluetooth on or off; requires IOBluetooth
+#[cfg(not(target_os = "macos"))]
+fn set_powered(_on: bool) -> Result<(), Box<dyn Error>> {
+    Err("Bluetooth can only be controlled on macOS".into())
+}
+
+/// Reads the Bluetooth controller; requires IOBluetooth
+#[cfg(not(target_os = "macos"))]
+fn controller() -> Result<Controller, Box<dyn Error>> {
+    Err("Bluetooth can only be controlled on macOS".into())
+}
+
+/// Reads the paired devices; requires IOBluetooth
+#[cfg(not(target_os = "macos"))]
+fn devices() -> Result<Vec<Device>, Box<dyn Error>> {
+    Err("Bluetooth can only be controlled on macOS".into())
+}
+
+/// Connects a device; requires IOBluetooth
+#[cfg(not(target_os = "macos"))]
+fn connect(_address: &str) -> Result<(), Box<dyn Error>> {
+    Err("Bluetooth can only be controlled on macOS".into())
+}
+
+/// Disconnects a device; requires IOBluetooth
+#[cfg(not(target_os = "macos"))]
+fn disconnect(_address: &str) -> Result<(), Box<dyn Error>> {
+    Err("Bluetooth can only be controlled on macOS".into())
+}
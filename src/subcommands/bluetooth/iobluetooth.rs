@@ -0,0 +1,186 @@
+//! IOBluetooth access
+//!
+//! The power state is read and set with the C functions IOBluetooth exports
+//! for the Bluetooth settings, which tools like `blueutil` use as well. The
+//! controller and the paired devices are described by the Objective-C classes
+//! `IOBluetoothHostController` and `IOBluetoothDevice`, which are used with
+//! the message sending helpers of [`crate::objc`].
+
+use super::{Controller, Device};
+use crate::objc::{
+    AutoreleasePool, Id, Sel, class, msg_send, ns_string, objects, selector, send_bool, send_id, string,
+};
+use std::error::Error;
+use std::ffi::CStr;
+
+#[link(name = "IOBluetooth", kind = "framework")]
+unsafe extern "C" {
+    fn IOBluetoothPreferencesAvailable() -> i32;
+    fn IOBluetoothPreferenceGetControllerPowerState() -> i32;
+    fn IOBluetoothPreferenceSetControllerPowerState(state: i32);
+}
+
+/// `BluetoothDeviceClassMajor` of peripherals like keyboards and mice
+const MAJOR_PERIPHERAL: u32 = 0x05;
+
+/// Names of the major device classes of the Bluetooth assigned numbers
+const MAJOR_CLASSES: &[(u32, &str)] = &[
+    (0x01, "computer"),
+    (0x02, "phone"),
+    (0x03, "network"),
+    (0x04, "audio"),
+    (0x06, "imaging"),
+    (0x07, "wearable"),
+    (0x08, "toy"),
+    (0x09, "health"),
+];
+
+/// Reads whether Bluetooth is on
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller.
+pub fn powered() -> Result<bool, Box<dyn Error>> {
+    available()?;
+    Ok(unsafe { IOBluetoothPreferenceGetControllerPowerState() } != 0)
+}
+
+/// Turns Bluetooth on or off
+///
+/// The controller changes its state asynchronously, so the change is not
+/// visible in [`powered`] right away.
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller.
+pub fn set_powered(on: bool) -> Result<(), Box<dyn Error>> {
+    available()?;
+    unsafe { IOBluetoothPreferenceSetControllerPowerState(i32::from(on)) };
+    Ok(())
+}
+
+/// Reads the name and address of the Bluetooth controller
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller.
+pub fn controller() -> Result<Controller, Box<dyn Error>> {
+    available()?;
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let controller = send_id(class(c"IOBluetoothHostController"), c"defaultController");
+        Ok(Controller {
+            name: string(send_id(controller, c"nameAsString")),
+            address: string(send_id(controller, c"addressAsString")).map(|address| address.to_lowercase()),
+        })
+    }
+}
+
+/// Reads the paired devices
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no Bluetooth controller.
+pub fn devices() -> Result<Vec<Device>, Box<dyn Error>> {
+    available()?;
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let paired = send_id(class(c"IOBluetoothDevice"), c"pairedDevices");
+        Ok(objects(paired)
+            .into_iter()
+            .filter_map(|device| {
+                let address = string(send_id(device, c"addressString"))?
+                    .replace('-', ":")
+                    .to_lowercase();
+                Some(Device {
+                    name: string(send_id(device, c"name")).unwrap_or_else(|| address.clone()),
+                    address,
+                    kind: kind(device),
+                    connected: send_bool(device, c"isConnected"),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Connects a paired device and waits until it is connected
+///
+/// # Errors
+///
+/// Returns an error if the device is unknown, out of range or refuses the
+/// connection.
+pub fn connect(address: &str) -> Result<(), Box<dyn Error>> {
+    connection(address, c"openConnection", "connect")
+}
+
+/// Disconnects a device
+///
+/// # Errors
+///
+/// Returns an error if the device is unknown or the connection cannot be closed.
+pub fn disconnect(address: &str) -> Result<(), Box<dyn Error>> {
+    connection(address, c"closeConnection", "disconnect")
+}
+
+/// Opens or closes the connection of a device with a method returning an `IOReturn`
+///
+/// # Errors
+///
+/// Returns an error if the device is unknown or the method fails.
+fn connection(address: &str, method: &CStr, action: &str) -> Result<(), Box<dyn Error>> {
+    available()?;
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let with_address: unsafe extern "C" fn(Id, Sel, Id) -> Id = msg_send();
+        let device = with_address(
+            class(c"IOBluetoothDevice"),
+            selector(c"deviceWithAddressString:"),
+            ns_string(&address.replace(':', "-"))?,
+        );
+        if device.is_null() {
+            return Err(format!("No Bluetooth device has the address {address}").into());
+        }
+        let send: unsafe extern "C" fn(Id, Sel) -> i32 = msg_send();
+        let result = send(device, selector(method));
+        if result != 0 {
+            return Err(format!("Failed to {action} (error {:#010x})", result as u32).into());
+        }
+        Ok(())
+    }
+}
+
+/// Returns the kind of a device from its class of device
+unsafe fn kind(device: Id) -> &'static str {
+    unsafe {
+        let send: unsafe extern "C" fn(Id, Sel) -> u32 = msg_send();
+        let major = send(device, selector(c"deviceClassMajor"));
+        if major == MAJOR_PERIPHERAL {
+            // Keyboard and pointing device flags of the minor class of peripherals
+            return match send(device, selector(c"deviceClassMinor")) & 0x30 {
+                0x10 => "keyboard",
+                0x20 => "pointing device",
+                0x30 => "keyboard and pointing device",
+                _ => "peripheral",
+            };
+        }
+        MAJOR_CLASSES
+            .iter()
+            .find(|(class, _)| *class == major)
+            .map_or("other", |(_, name)| name)
+    }
+}
+
+/// Checks that the Mac has a Bluetooth controller
+///
+/// # Errors
+///
+/// Returns an error if there is none.
+fn available() -> Result<(), Box<dyn Error>> {
+    if unsafe { IOBluetoothPreferencesAvailable() } == 0 {
+        return Err("This Mac has no Bluetooth controller".into());
+    }
+    Ok(())
+}
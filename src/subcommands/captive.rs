@@ -0,0 +1,203 @@
+//! Captive portal detection
+//!
+//! Performs the captive portal probe of macOS: a plain HTTP request to
+//! `http://captive.apple.com/hotspot-detect.html`, which replies with a page
+//! containing `Success` when the internet is reachable. A redirect or any other
+//! reply means that the network intercepts traffic, usually to show a login or
+//! terms page. The portal URL is taken from the redirect or a `<meta>` refresh in
+//! the intercepted page; otherwise the probe URL itself leads to the portal when
+//! opened in a browser.
+//!
+//! # System Commands Used
+//!
+//! - `curl` - Request the probe page without following redirects
+//! - `open` - Open the portal in the default browser
+
+use crate::cli::CaptiveArgs;
+use colored::Colorize;
+use regex::Regex;
+use serde::Serialize;
+use std::error::Error;
+use std::process::Command;
+
+/// Probe page of macOS; replies with `<BODY>Success</BODY>` without a portal
+const PROBE_URL: &str = "http://captive.apple.com/hotspot-detect.html";
+
+/// Origin of the probe page, for relative portal URLs
+const PROBE_ORIGIN: &str = "http://captive.apple.com";
+
+/// Body marker of the probe page when the internet is reachable
+const PROBE_SUCCESS: &str = "<BODY>Success</BODY>";
+
+/// Maximum time for the probe request in seconds
+const PROBE_TIMEOUT_SECS: &str = "5";
+
+/// Result of the captive portal probe
+#[derive(Serialize)]
+struct ProbeResult {
+    /// `online`, `captive` or `offline`
+    state: &'static str,
+    /// HTTP status of the reply, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_status: Option<u16>,
+    /// URL of the captive portal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    portal_url: Option<String>,
+    /// Reason why the probe failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Probes for a captive portal and optionally opens it
+///
+/// # Arguments
+///
+/// * `args` - Captive arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `curl` cannot be run or the portal cannot be opened.
+pub fn perform(args: CaptiveArgs) -> Result<(), Box<dyn Error>> {
+    let result = probe()?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        print_result(&result, args.open);
+    }
+
+    if args.open
+        && let Some(portal_url) = &result.portal_url
+    {
+        open_portal(portal_url)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the result of the probe
+///
+/// # Arguments
+///
+/// * `result` - Result of the probe
+/// * `open` - The portal is about to be opened, so no hint is needed
+fn print_result(result: &ProbeResult, open: bool) {
+    match (result.state, &result.portal_url) {
+        ("online", _) => println!("{} Internet access is available, no captive portal", "✓".green()),
+        ("captive", Some(portal_url)) => {
+            let status = result
+                .http_status
+                .map(|status| format!(" (HTTP {status})"))
+                .unwrap_or_default();
+            println!(
+                "{}{status}",
+                "Captive portal detected, the network intercepts traffic"
+                    .yellow()
+                    .bold()
+            );
+            println!("Portal: {portal_url}");
+            if !open {
+                println!("Open it in the browser with `rem captive --open`");
+            }
+        }
+        _ => println!(
+            "{} {}",
+            "No internet access:".red(),
+            result.error.as_deref().unwrap_or("probe failed")
+        ),
+    }
+}
+
+/// Requests the probe page without following redirects
+///
+/// # Returns
+///
+/// The state of the network; a failed request means that the network is offline.
+///
+/// # Errors
+///
+/// Returns an error if `curl` cannot be run.
+fn probe() -> Result<ProbeResult, Box<dyn Error>> {
+    let output = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--max-time",
+            PROBE_TIMEOUT_SECS,
+            "--header",
+            "Cache-Control: no-cache",
+            "--write-out",
+            "\n%{http_code} %{redirect_url}",
+            PROBE_URL,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+
+    if !output.status.success() {
+        return Ok(ProbeResult {
+            state: "offline",
+            http_status: None,
+            portal_url: None,
+            error: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        });
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (body, trailer) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+    let (status, redirect) = trailer.split_once(' ').unwrap_or((trailer, ""));
+    let status: u16 = status.trim().parse().unwrap_or(0);
+
+    if status == 200 && body.contains(PROBE_SUCCESS) {
+        return Ok(ProbeResult {
+            state: "online",
+            http_status: Some(status),
+            portal_url: None,
+            error: None,
+        });
+    }
+
+    let redirect = redirect.trim();
+    let portal_url = if (300..400).contains(&status) && !redirect.is_empty() {
+        redirect.to_string()
+    } else {
+        refresh_url(body)?.unwrap_or_else(|| PROBE_URL.to_string())
+    };
+
+    Ok(ProbeResult {
+        state: "captive",
+        http_status: (status != 0).then_some(status),
+        portal_url: Some(portal_url),
+        error: None,
+    })
+}
+
+/// Returns the target of a `<meta http-equiv="refresh">` tag in an intercepted page
+///
+/// Relative targets are resolved against the origin of the probe page.
+///
+/// # Errors
+///
+/// Returns an error if the pattern is invalid.
+fn refresh_url(body: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let refresh = Regex::new(r#"(?i)<meta[^>]+http-equiv=["']?refresh["']?[^>]*content=["'][^"']*url=([^"'>\s]+)"#)?;
+    Ok(refresh.captures(body).map(|captures| match &captures[1] {
+        url if url.starts_with('/') => format!("{PROBE_ORIGIN}{url}"),
+        url => url.to_string(),
+    }))
+}
+
+/// Opens the portal in the default browser
+///
+/// # Errors
+///
+/// Returns an error if the `open` command fails.
+fn open_portal(portal_url: &str) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("open").arg(portal_url).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to open the portal: {}", stderr.trim()).into());
+    }
+
+    Ok(())
+}
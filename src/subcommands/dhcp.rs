@@ -0,0 +1,316 @@
+//! DHCP lease information and renewal
+//!
+//! Shows the DHCP lease of an interface, decoded from the last DHCP packet the
+//! system received (`ipconfig getpacket`): the assigned address, the DHCP server,
+//! routers, DNS servers, lease and renewal times, and all further options the
+//! server sent. Renewing restarts DHCP on the interface with `ipconfig set <DEVICE>
+//! DHCP`, which requests a new lease without changing the network service
+//! configuration. Without `--interface`, the interface of the default route is used.
+//!
+//! # System Commands Used
+//!
+//! - `ipconfig getpacket` - Read the last DHCP packet of an interface
+//! - `ipconfig set <DEVICE> DHCP` - Renew the lease (with sudo)
+//! - `route -n get default` - Find the primary interface
+//! - `networksetup -listallhardwareports` - Map devices to hardware port names
+
+use super::ip;
+use crate::cli::{DhcpArgs, DhcpCommands};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Maximum number of seconds to wait for a new lease after a renewal
+const RENEW_TIMEOUT_SECS: u64 = 10;
+
+/// DHCP options decoded into dedicated fields of [`Lease`]
+const DECODED_OPTIONS: &[&str] = &[
+    "dhcp_message_type",
+    "server_identifier",
+    "subnet_mask",
+    "router",
+    "domain_name_server",
+    "domain_name",
+    "lease_time",
+    "renewal_t1_time_value",
+    "rebinding_t2_time_value",
+    "end",
+];
+
+/// DHCP lease of an interface
+#[derive(Serialize)]
+struct Lease {
+    /// Device name, e.g. `en0`
+    interface: String,
+    /// Assigned address
+    address: Option<String>,
+    /// Subnet mask of the assigned address
+    subnet_mask: Option<String>,
+    /// Address of the DHCP server
+    server: Option<String>,
+    /// Routers (default gateways)
+    routers: Vec<String>,
+    /// DNS servers
+    dns_servers: Vec<String>,
+    /// DNS domain name
+    domain: Option<String>,
+    /// Lease time in seconds
+    lease_time: Option<u64>,
+    /// Time until the lease is renewed (T1) in seconds
+    renewal_time: Option<u64>,
+    /// Time until any DHCP server may extend the lease (T2) in seconds
+    rebinding_time: Option<u64>,
+    /// Further options by name, as printed by `ipconfig`
+    options: BTreeMap<String, String>,
+    /// Transaction ID of the packet, changes with every new lease
+    #[serde(skip)]
+    xid: Option<String>,
+}
+
+/// Performs DHCP operations
+///
+/// # Arguments
+///
+/// * `args` - DHCP arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the interface cannot be determined or has no DHCP lease,
+/// or the renewal fails.
+pub fn perform(args: DhcpArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Some(DhcpCommands::Renew { interface }) => renew(&select_interface(interface)?),
+        Some(DhcpCommands::Info { interface, json }) => info(&select_interface(interface)?, json),
+        None => info(&select_interface(None)?, false),
+    }
+}
+
+/// Returns the given interface or the interface of the default route
+///
+/// # Errors
+///
+/// Returns an error if no interface is given and there is no default route.
+fn select_interface(interface: Option<String>) -> Result<String, Box<dyn Error>> {
+    match interface {
+        Some(interface) => Ok(interface),
+        None => ip::default_interface().ok_or_else(|| "No default route, use --interface".into()),
+    }
+}
+
+/// Prints the DHCP lease of an interface
+///
+/// # Errors
+///
+/// Returns an error if the interface has no DHCP lease.
+fn info(interface: &str, json: bool) -> Result<(), Box<dyn Error>> {
+    let lease = lease(interface)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&lease)?);
+    } else {
+        print_lease(&lease);
+    }
+
+    Ok(())
+}
+
+/// Renews the DHCP lease of an interface and prints the new lease
+///
+/// # Errors
+///
+/// Returns an error if the interface has no DHCP lease, `ipconfig` fails, or no
+/// new lease is received in time.
+fn renew(interface: &str) -> Result<(), Box<dyn Error>> {
+    // Refuse interfaces without DHCP, `ipconfig set` would switch them to DHCP
+    let before = lease(interface)?;
+
+    println!("Renewing the DHCP lease of {interface}...");
+    let output = Command::new("sudo")
+        .args(["ipconfig", "set", interface, "DHCP"])
+        .output()
+        .map_err(|e| format!("Failed to run ipconfig: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to renew the DHCP lease of {interface}: {}", stderr.trim()).into());
+    }
+
+    for _ in 0..RENEW_TIMEOUT_SECS {
+        thread::sleep(Duration::from_secs(1));
+        if let Ok(lease) = lease(interface)
+            && lease.address.is_some()
+            && lease.xid != before.xid
+        {
+            if lease.address == before.address {
+                println!("{} Lease renewed", "✓".green());
+            } else {
+                println!(
+                    "{} Lease renewed with a new address ({} → {})",
+                    "✓".green(),
+                    before.address.as_deref().unwrap_or("-"),
+                    lease.address.as_deref().unwrap_or("-")
+                );
+            }
+            println!();
+            print_lease(&lease);
+            return Ok(());
+        }
+    }
+
+    Err(format!("No DHCP lease received on {interface} within {RENEW_TIMEOUT_SECS} s").into())
+}
+
+/// Prints a lease as a table of properties
+fn print_lease(lease: &Lease) {
+    let service = ip::hardware_ports().remove(&lease.interface);
+    let interface = match service {
+        Some(service) => format!("{} ({service})", lease.interface),
+        None => lease.interface.clone(),
+    };
+    let address = match (&lease.address, &lease.subnet_mask) {
+        (Some(address), Some(mask)) => format!("{address} / {mask}"),
+        (Some(address), None) => address.clone(),
+        _ => "-".to_string(),
+    };
+
+    let rows = [
+        ("Interface", interface),
+        ("Address", address),
+        ("DHCP server", lease.server.clone().unwrap_or_else(|| "-".to_string())),
+        ("Router", join_or_dash(&lease.routers)),
+        ("DNS servers", join_or_dash(&lease.dns_servers)),
+        ("Domain", lease.domain.clone().unwrap_or_else(|| "-".to_string())),
+        ("Lease time", format_seconds(lease.lease_time)),
+        ("Renewal (T1)", format_seconds(lease.renewal_time)),
+        ("Rebinding (T2)", format_seconds(lease.rebinding_time)),
+    ];
+    for (label, value) in rows {
+        println!("{:<16}{value}", label.bold());
+    }
+
+    if !lease.options.is_empty() {
+        println!();
+        println!("{}", "Further options".bold());
+        for (name, value) in &lease.options {
+            println!("  {name:<30}{value}");
+        }
+    }
+}
+
+/// Joins values with commas, or returns `-` if there are none
+fn join_or_dash(values: &[String]) -> String {
+    if values.is_empty() {
+        "-".to_string()
+    } else {
+        values.join(", ")
+    }
+}
+
+/// Formats a duration in seconds as e.g. `1d 2h 30m (95400 s)`
+fn format_seconds(seconds: Option<u64>) -> String {
+    let Some(seconds) = seconds else {
+        return "-".to_string();
+    };
+
+    // 0xffffffff means infinite (RFC 2131)
+    if seconds == u64::from(u32::MAX) {
+        return "infinite".to_string();
+    }
+    let parts: Vec<String> = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+    ]
+    .into_iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, unit)| format!("{value}{unit}"))
+    .collect();
+    if parts.is_empty() {
+        format!("{seconds} s")
+    } else {
+        format!("{} ({seconds} s)", parts.join(" "))
+    }
+}
+
+/// Reads the DHCP lease of an interface
+///
+/// # Errors
+///
+/// Returns an error if `ipconfig` cannot be run or the interface has no DHCP lease.
+fn lease(interface: &str) -> Result<Lease, Box<dyn Error>> {
+    let output = Command::new("ipconfig")
+        .args(["getpacket", interface])
+        .output()
+        .map_err(|e| format!("Failed to run ipconfig: {e}"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || stdout.trim().is_empty() {
+        return Err(format!("No DHCP lease on {interface} (not connected or configured manually)").into());
+    }
+
+    Ok(parse_packet(interface, &stdout))
+}
+
+/// Parses the output of `ipconfig getpacket`
+///
+/// The header fields are printed as `yiaddr = 192.168.1.20`, the options after
+/// the line `options:` as `<name> (<type>): <value>`, e.g. `router (ip_mult):
+/// {192.168.1.1}` or `lease_time (uint32): 0x15180`.
+fn parse_packet(interface: &str, output: &str) -> Lease {
+    let mut header = BTreeMap::new();
+    let mut options = BTreeMap::new();
+    let mut in_options = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line == "options:" {
+            in_options = true;
+        } else if !in_options {
+            if let Some((name, value)) = line.split_once(" = ") {
+                header.insert(name.trim().to_string(), value.trim().to_string());
+            }
+        } else if let Some((name, value)) = line.split_once("): ")
+            && let Some((name, _)) = name.split_once(" (")
+        {
+            options.insert(name.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    let list = |name: &str| -> Vec<String> {
+        options.get(name).map_or_else(Vec::new, |value| {
+            value
+                .trim_start_matches('{')
+                .trim_end_matches('}')
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+    };
+    let seconds = |name: &str| {
+        options
+            .get(name)
+            .and_then(|value| u64::from_str_radix(value.trim_start_matches("0x"), 16).ok())
+    };
+
+    Lease {
+        interface: interface.to_string(),
+        address: header.get("yiaddr").filter(|address| *address != "0.0.0.0").cloned(),
+        subnet_mask: options.get("subnet_mask").cloned(),
+        server: options.get("server_identifier").cloned(),
+        routers: list("router"),
+        dns_servers: list("domain_name_server"),
+        domain: options.get("domain_name").cloned(),
+        lease_time: seconds("lease_time"),
+        renewal_time: seconds("renewal_t1_time_value"),
+        rebinding_time: seconds("rebinding_t2_time_value"),
+        options: options
+            .iter()
+            .filter(|(name, _)| !DECODED_OPTIONS.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+        xid: header.get("xid").cloned(),
+    }
+}
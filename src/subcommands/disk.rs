@@ -0,0 +1,121 @@
+//! Drives
+//!
+//! `health` reports the SMART data of every physical drive with a verdict;
+//! see [`health`]. `list`, `mount`, `unmount` and `eject` manage the volumes
+//! of the disks and report the apps that keep a volume from unmounting; see
+//! [`volumes`].
+//!
+//! # System Commands Used
+//!
+//! - `diskutil list -plist` / `diskutil info -plist` - Read the disks and volumes
+//! - `plutil -convert json` - Read the property lists of `diskutil`
+
+pub mod health;
+#[cfg(target_os = "macos")]
+mod nvme;
+pub mod volumes;
+
+use crate::cli::{DiskArgs, DiskCommands};
+use regex::Regex;
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Performs disk operations
+///
+/// # Arguments
+///
+/// * `args` - Disk arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `diskutil` fails.
+pub fn perform(args: DiskArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        DiskCommands::Health { json } => health::health(json),
+        DiskCommands::List { json } => volumes::list(json),
+        DiskCommands::Mount { disk } => volumes::mount(&disk),
+        DiskCommands::Unmount { volume, force } => volumes::unmount(&volume, force),
+        DiskCommands::Eject { disk, force } => volumes::eject(&disk, force),
+    }
+}
+
+/// Runs `diskutil`
+///
+/// # Errors
+///
+/// Returns an error if `diskutil` cannot be run; a failure of the command is
+/// left to the caller.
+pub fn diskutil(args: &[&str]) -> Result<Output, Box<dyn Error>> {
+    Ok(Command::new("diskutil")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run diskutil: {e}"))?)
+}
+
+/// Returns the message of a failed `diskutil` command
+///
+/// With `-plist`, the message is the `ErrorMessage` of the property list on
+/// stdout; otherwise it is on stderr or stdout.
+pub fn diskutil_error(output: &Output) -> String {
+    if let Ok(plist) = plist_to_json(&output.stdout)
+        && let Some(message) = plist["ErrorMessage"].as_str()
+    {
+        return message.to_string();
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = if stderr.trim().is_empty() {
+        String::from_utf8_lossy(&output.stdout)
+    } else {
+        stderr
+    };
+    message.trim().to_string()
+}
+
+/// Runs `diskutil` with `-plist` output and reads the property list
+///
+/// # Arguments
+///
+/// * `args` - Arguments of `diskutil`, including `-plist`
+///
+/// # Errors
+///
+/// Returns an error if `diskutil` fails or its output cannot be converted.
+pub fn diskutil_plist(args: &[&str]) -> Result<serde_json::Value, Box<dyn Error>> {
+    let output = diskutil(args)?;
+    if !output.status.success() {
+        return Err(format!("diskutil {} failed: {}", args.join(" "), diskutil_error(&output)).into());
+    }
+    plist_to_json(&output.stdout)
+}
+
+/// Converts an XML property list to JSON with `plutil`
+///
+/// JSON has no data and date values, which `plutil` refuses to convert, so
+/// they are replaced by empty strings first.
+///
+/// # Errors
+///
+/// Returns an error if `plutil` cannot be run or the data is no property list.
+pub fn plist_to_json(plist: &[u8]) -> Result<serde_json::Value, Box<dyn Error>> {
+    let unsupported = Regex::new(r"<(?:data|date)>[^<]*</(?:data|date)>|<data/>")?;
+    let plist = unsupported
+        .replace_all(&String::from_utf8_lossy(plist), "<string></string>")
+        .into_owned();
+    let mut plutil = Command::new("plutil")
+        .args(["-convert", "json", "-o", "-", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run plutil: {e}"))?;
+    if let Some(mut stdin) = plutil.stdin.take() {
+        stdin.write_all(plist.as_bytes())?;
+    }
+    let converted = plutil.wait_with_output()?;
+    if !converted.status.success() {
+        let stderr = String::from_utf8_lossy(&converted.stderr);
+        return Err(format!("Failed to convert the property list: {}", stderr.trim()).into());
+    }
+    Ok(serde_json::from_slice(&converted.stdout)?)
+}
@@ -0,0 +1,375 @@
+//! Drive health
+//!
+//! Reads the SMART data of every physical drive and sums it up as wear,
+//! temperature, errors and a verdict. `diskutil` only knows whether a drive
+//! is verified or failing, so the attributes come from `smartctl` of
+//! smartmontools when it is installed, which also reads SATA drives and many
+//! USB enclosures, and otherwise from the NVMe SMART log through IOKit (see
+//! [`nvme`](super::nvme)), which covers internal SSDs.
+//!
+//! A drive is failing when it reports so, wore out or raised a critical
+//! warning, and needs attention when it reallocated sectors, has media errors,
+//! is almost worn out or runs hot.
+//!
+//! # System Commands Used
+//!
+//! - `diskutil list -plist physical` / `diskutil info -plist` - Read the drives
+//! - `smartctl -a -j` - Read the SMART attributes, if installed
+
+use super::diskutil_plist;
+use crate::util::format_bytes;
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Folders where Homebrew installs `smartctl`, which may not be in the PATH
+const SMARTCTL_PATHS: [&str; 2] = ["/opt/homebrew/bin/smartctl", "/usr/local/bin/smartctl"];
+
+/// `smartctl` exit status bit for a device that could not be opened
+const SMARTCTL_OPEN_FAILED: u64 = 0b10;
+
+/// Wear in percent from which a drive needs attention
+const WEAR_WARNING: u64 = 80;
+
+/// Temperature in °C from which a drive needs attention
+const TEMPERATURE_WARNING: i64 = 70;
+
+/// Available spare in percent below which a drive needs attention
+const SPARE_WARNING: u64 = 10;
+
+/// Bytes in a data unit of the NVMe SMART log
+pub const DATA_UNIT: u64 = 512_000;
+
+/// ATA attribute of the reallocated sectors
+const REALLOCATED_SECTORS: u64 = 5;
+
+/// ATA attribute of the sectors waiting to be reallocated
+const PENDING_SECTORS: u64 = 197;
+
+/// ATA attributes of the remaining life of SSDs, by vendor
+const WEAR_ATTRIBUTES: [u64; 3] = [177, 231, 233];
+
+/// SMART attributes of a drive
+#[derive(Default, Serialize)]
+pub struct SmartLog {
+    /// Whether the drive passed its own assessment
+    pub passed: Option<bool>,
+    /// Share of the rated endurance used in percent
+    pub wear: Option<u64>,
+    /// Temperature in °C
+    pub temperature: Option<i64>,
+    /// Spare blocks left in percent
+    pub available_spare: Option<u64>,
+    /// Sectors replaced by spare sectors
+    pub reallocated_sectors: Option<u64>,
+    /// Sectors waiting to be reallocated
+    pub pending_sectors: Option<u64>,
+    /// Unrecovered data integrity errors
+    pub media_errors: Option<u64>,
+    /// Critical warning bits of NVMe drives
+    pub critical_warning: Option<u64>,
+    /// Hours powered on
+    pub power_on_hours: Option<u64>,
+    /// Bytes written by the host
+    pub bytes_written: Option<u64>,
+}
+
+/// Overall state of a drive
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Verdict {
+    Unknown,
+    Good,
+    Warning,
+    Failing,
+}
+
+/// Health of a drive
+#[derive(Serialize)]
+struct DiskHealth {
+    /// BSD name of the whole disk
+    disk: String,
+    /// Model name
+    name: String,
+    /// Whether the drive is built in
+    internal: bool,
+    /// Bus the drive is connected by
+    protocol: Option<String>,
+    /// Capacity in bytes
+    size: Option<u64>,
+    /// SMART status reported by `diskutil`
+    smart_status: Option<String>,
+    /// Where the attributes come from
+    source: Option<&'static str>,
+    /// SMART attributes
+    #[serde(flatten)]
+    smart: SmartLog,
+    /// Overall state
+    verdict: Verdict,
+    /// Reasons of a verdict other than good
+    reasons: Vec<String>,
+}
+
+/// Shows the health of all physical drives
+///
+/// # Arguments
+///
+/// * `json` - Print the health as JSON
+///
+/// # Errors
+///
+/// Returns an error if the drives cannot be read with `diskutil`.
+pub fn health(json: bool) -> Result<(), Box<dyn Error>> {
+    let disks = diskutil_plist(&["list", "-plist", "physical"])?;
+    let smartctl = smartctl();
+    let mut nvme_logs = nvme_logs();
+
+    let mut report = Vec::new();
+    for disk in disks["WholeDisks"].as_array().into_iter().flatten() {
+        let Some(disk) = disk.as_str() else {
+            continue;
+        };
+        let info = diskutil_plist(&["info", "-plist", disk])?;
+        let mut health = DiskHealth {
+            disk: disk.to_string(),
+            name: info["MediaName"]
+                .as_str()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .unwrap_or("Unknown drive")
+                .to_string(),
+            internal: info["Internal"].as_bool().unwrap_or_default(),
+            protocol: info["BusProtocol"].as_str().map(str::to_string),
+            size: info["TotalSize"].as_u64().or_else(|| info["Size"].as_u64()),
+            smart_status: info["SMARTStatus"].as_str().map(str::to_string),
+            source: None,
+            smart: SmartLog::default(),
+            verdict: Verdict::Unknown,
+            reasons: Vec::new(),
+        };
+        if let Some(log) = smartctl.as_deref().and_then(|smartctl| read_smartctl(smartctl, disk)) {
+            health.smart = log;
+            health.source = Some("smartctl");
+        } else if let Some(index) = nvme_logs.iter().position(|(name, _)| name == disk) {
+            health.smart = nvme_logs.swap_remove(index).1;
+            health.source = Some("IOKit");
+        }
+        judge(&mut health);
+        report.push(health);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    if report.is_empty() {
+        println!("No drives found");
+    }
+    for (index, health) in report.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        print_health(health);
+    }
+    if smartctl.is_none() && report.iter().any(|health| health.source.is_none()) {
+        println!();
+        println!(
+            "{}",
+            "Install smartmontools (brew install smartmontools) to read SATA and USB drives".dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Prints the health of a drive
+fn print_health(health: &DiskHealth) {
+    let mut details = vec![if health.internal { "internal" } else { "external" }.to_string()];
+    details.extend(health.protocol.clone());
+    details.extend(health.size.map(|size| format_bytes(size as f64)));
+    println!(
+        "{}  {}  {}",
+        health.disk.bold(),
+        health.name,
+        details.join(", ").dimmed()
+    );
+
+    let mut verdict = format_verdict(health.verdict);
+    if !health.reasons.is_empty() {
+        verdict = format!("{verdict}: {}", health.reasons.join(", ")).normal();
+    }
+    row("Verdict", verdict);
+    let smart = &health.smart;
+    if let Some(wear) = smart.wear {
+        row("Wear", format!("{wear}% used").normal());
+    }
+    if let Some(spare) = smart.available_spare {
+        row("Spare", format!("{spare}%").normal());
+    }
+    if let Some(temperature) = smart.temperature {
+        row("Temperature", format!("{temperature} °C").normal());
+    }
+    if let Some(sectors) = smart.reallocated_sectors {
+        row("Reallocated", sectors.to_string().normal());
+    }
+    if let Some(sectors) = smart.pending_sectors {
+        row("Pending", sectors.to_string().normal());
+    }
+    if let Some(errors) = smart.media_errors {
+        row("Media errors", errors.to_string().normal());
+    }
+    if let Some(hours) = smart.power_on_hours {
+        row("Powered on", format!("{hours} hours").normal());
+    }
+    if let Some(bytes) = smart.bytes_written {
+        row("Written", format_bytes(bytes as f64).normal());
+    }
+    let source = match (health.source, &health.smart_status) {
+        (Some(source), _) => source.to_string(),
+        (None, Some(status)) => format!("diskutil ({status})"),
+        (None, None) => "no SMART data".to_string(),
+    };
+    row("Source", source.dimmed());
+}
+
+/// Prints a labeled value of a drive
+fn row(label: &str, value: ColoredString) {
+    println!("  {:<14}{value}", label.bold());
+}
+
+/// Formats a verdict in its color
+fn format_verdict(verdict: Verdict) -> ColoredString {
+    match verdict {
+        Verdict::Good => "good".green(),
+        Verdict::Warning => "needs attention".yellow(),
+        Verdict::Failing => "failing".red().bold(),
+        Verdict::Unknown => "unknown".dimmed(),
+    }
+}
+
+/// Sets the verdict of a drive and the reasons for it
+fn judge(health: &mut DiskHealth) {
+    let smart = &health.smart;
+    let mut failing = Vec::new();
+    let mut warnings = Vec::new();
+
+    if health.smart_status.as_deref() == Some("Failing") {
+        failing.push("diskutil reports SMART failing".to_string());
+    }
+    if smart.passed == Some(false) {
+        failing.push("SMART self-assessment failed".to_string());
+    }
+    if let Some(warning) = smart.critical_warning.filter(|&warning| warning != 0) {
+        failing.push(format!("critical warning {warning:#04x}"));
+    }
+    if let Some(wear) = smart.wear {
+        if wear >= 100 {
+            failing.push("worn out".to_string());
+        } else if wear >= WEAR_WARNING {
+            warnings.push(format!("{wear}% worn"));
+        }
+    }
+    if let Some(spare) = smart.available_spare.filter(|&spare| spare < SPARE_WARNING) {
+        warnings.push(format!("{spare}% spare left"));
+    }
+    for (count, what) in [
+        (smart.reallocated_sectors, "reallocated sectors"),
+        (smart.pending_sectors, "pending sectors"),
+        (smart.media_errors, "media errors"),
+    ] {
+        if let Some(count) = count.filter(|&count| count > 0) {
+            warnings.push(format!("{count} {what}"));
+        }
+    }
+    if let Some(temperature) = smart
+        .temperature
+        .filter(|&temperature| temperature >= TEMPERATURE_WARNING)
+    {
+        warnings.push(format!("{temperature} °C"));
+    }
+
+    let known = health.source.is_some() || matches!(health.smart_status.as_deref(), Some("Verified" | "Failing"));
+    (health.verdict, health.reasons) = if !failing.is_empty() {
+        (Verdict::Failing, failing)
+    } else if !warnings.is_empty() {
+        (Verdict::Warning, warnings)
+    } else if known {
+        (Verdict::Good, Vec::new())
+    } else {
+        (Verdict::Unknown, Vec::new())
+    };
+}
+
+/// Returns the path of `smartctl`, if installed
+fn smartctl() -> Option<PathBuf> {
+    let found = Command::new("which")
+        .arg("smartctl")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()));
+    found.or_else(|| SMARTCTL_PATHS.iter().map(PathBuf::from).find(|path| path.exists()))
+}
+
+/// Reads the SMART attributes of a drive with `smartctl`
+///
+/// # Returns
+///
+/// `None` if `smartctl` cannot open the drive or it reports no attributes.
+fn read_smartctl(smartctl: &Path, disk: &str) -> Option<SmartLog> {
+    // The exit status is a bit mask that is nonzero for many drives with data
+    let output = Command::new(smartctl)
+        .args(["-a", "-j", &format!("/dev/{disk}")])
+        .output()
+        .ok()?;
+    let json: Value = serde_json::from_slice(&output.stdout).ok()?;
+    let status = json["smartctl"]["exit_status"].as_u64().unwrap_or_default();
+    if status & SMARTCTL_OPEN_FAILED != 0 {
+        return None;
+    }
+
+    let mut log = SmartLog {
+        passed: json["smart_status"]["passed"].as_bool(),
+        temperature: json["temperature"]["current"].as_i64(),
+        power_on_hours: json["power_on_time"]["hours"].as_u64(),
+        ..SmartLog::default()
+    };
+    let nvme = &json["nvme_smart_health_information_log"];
+    if nvme.is_object() {
+        log.wear = nvme["percentage_used"].as_u64();
+        log.available_spare = nvme["available_spare"].as_u64();
+        log.media_errors = nvme["media_errors"].as_u64();
+        log.critical_warning = nvme["critical_warning"].as_u64();
+        log.bytes_written = nvme["data_units_written"]
+            .as_u64()
+            .map(|units| units.saturating_mul(DATA_UNIT));
+    }
+    if let Some(table) = json["ata_smart_attributes"]["table"].as_array() {
+        let attribute = |id: u64| table.iter().find(|attribute| attribute["id"].as_u64() == Some(id));
+        let raw = |id: u64| attribute(id).and_then(|attribute| attribute["raw"]["value"].as_u64());
+        log.reallocated_sectors = raw(REALLOCATED_SECTORS);
+        log.pending_sectors = raw(PENDING_SECTORS);
+        // The normalized value counts down from 100 as the drive wears
+        log.wear = WEAR_ATTRIBUTES
+            .iter()
+            .find_map(|&id| attribute(id)?["value"].as_u64())
+            .map(|remaining| 100 - remaining.min(100));
+    }
+
+    let found = log.passed.is_some() || log.temperature.is_some() || nvme.is_object() || log.wear.is_some();
+    found.then_some(log)
+}
+
+/// Reads the SMART logs of NVMe drives through IOKit
+#[cfg(target_os = "macos")]
+fn nvme_logs() -> Vec<(String, SmartLog)> {
+    super::nvme::smart_logs()
+}
+
+/// Reads the SMART logs of NVMe drives; requires IOKit
+#[cfg(not(target_os = "macos"))]
+fn nvme_logs() -> Vec<(String, SmartLog)> {
+    Vec::new()
+}
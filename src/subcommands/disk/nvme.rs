@@ -0,0 +1,161 @@
+//! NVMe SMART data from IOKit
+//!
+//! NVMe drives that report `NVMe SMART Capable` have a user client plug-in,
+//! the one smartmontools uses as well, that reads the SMART / Health
+//! Information log page. Internal Apple SSDs and most NVMe drives in
+//! Thunderbolt enclosures support it; drives behind USB bridges do not.
+//!
+//! The log page is 512 bytes with little-endian fields.
+
+use super::health::{DATA_UNIT, SmartLog};
+use crate::iokit::{Service, matching_services};
+use core_foundation::base::TCFType;
+use core_foundation::boolean::CFBoolean;
+use core_foundation::string::CFString;
+use core_foundation::uuid::{CFUUID, CFUUIDBytes, CFUUIDCreateFromUUIDBytes, CFUUIDRef};
+use std::ffi::c_void;
+use std::{mem, ptr};
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOCreatePlugInInterfaceForService(
+        service: u32,
+        plugin_type: CFUUIDRef,
+        interface_type: CFUUIDRef,
+        interface: *mut *mut *mut PlugInInterface,
+        score: *mut i32,
+    ) -> i32;
+    fn IODestroyPlugInInterface(interface: *mut *mut PlugInInterface) -> i32;
+}
+
+/// `kIONVMeSMARTUserClientTypeID`
+const SMART_USER_CLIENT_TYPE: [u8; 16] = [
+    0xAA, 0x0F, 0xA6, 0xF9, 0xC2, 0xD6, 0x45, 0x7F, 0xB1, 0x0B, 0x59, 0xA1, 0x32, 0x53, 0x29, 0x2F,
+];
+
+/// `kIONVMeSMARTInterfaceID`
+const SMART_INTERFACE: [u8; 16] = [
+    0xCC, 0xD1, 0xDB, 0x19, 0xFD, 0x9A, 0x4D, 0xAF, 0xBF, 0x95, 0x12, 0x45, 0x4B, 0x23, 0x0A, 0xB6,
+];
+
+/// `kIOCFPlugInInterfaceID`
+const PLUGIN_INTERFACE: [u8; 16] = [
+    0xC2, 0x44, 0xE8, 0x58, 0x10, 0x9C, 0x11, 0xD4, 0x91, 0xD4, 0x00, 0x50, 0xE4, 0xC6, 0x42, 0x6F,
+];
+
+/// Size of the SMART / Health Information log page
+const LOG_SIZE: usize = 512;
+
+/// Kelvin at 0 °C
+const ZERO_CELSIUS: i64 = 273;
+
+/// The start of `IOCFPlugInInterface`, up to the methods used
+#[repr(C)]
+struct PlugInInterface {
+    reserved: *mut c_void,
+    query_interface: unsafe extern "C" fn(*mut c_void, CFUUIDBytes, *mut *mut c_void) -> i32,
+    add_ref: unsafe extern "C" fn(*mut c_void) -> u32,
+    release: unsafe extern "C" fn(*mut c_void) -> u32,
+}
+
+/// The start of `IONVMeSMARTInterface`, up to the methods used
+#[repr(C)]
+struct SmartInterface {
+    reserved: *mut c_void,
+    query_interface: unsafe extern "C" fn(*mut c_void, CFUUIDBytes, *mut *mut c_void) -> i32,
+    add_ref: unsafe extern "C" fn(*mut c_void) -> u32,
+    release: unsafe extern "C" fn(*mut c_void) -> u32,
+    version: u16,
+    revision: u16,
+    smart_read_data: unsafe extern "C" fn(*mut c_void, *mut [u8; LOG_SIZE]) -> i32,
+}
+
+/// Reads the SMART logs of the NVMe drives that support it
+///
+/// # Returns
+///
+/// The BSD names of the whole disks with their logs; drives whose log cannot
+/// be read are left out.
+pub fn smart_logs() -> Vec<(String, SmartLog)> {
+    matching_services(c"IONVMeBlockStorageDevice")
+        .unwrap_or_default()
+        .iter()
+        .filter(|service| smart_capable(service))
+        .filter_map(|service| Some((bsd_name(service)?, decode(&read_log(service)?))))
+        .collect()
+}
+
+/// Returns whether a drive supports reading its SMART log
+fn smart_capable(service: &Service) -> bool {
+    service
+        .property("NVMe SMART Capable")
+        .and_then(|value| value.downcast::<CFBoolean>())
+        .is_some_and(bool::from)
+}
+
+/// Returns the BSD name of the whole disk below a drive
+fn bsd_name(service: &Service) -> Option<String> {
+    service
+        .search_property("BSD Name")?
+        .downcast::<CFString>()
+        .map(|name| name.to_string())
+}
+
+/// Creates a UUID from its bytes
+fn uuid(bytes: [u8; 16]) -> CFUUID {
+    let bytes: CFUUIDBytes = unsafe { mem::transmute(bytes) };
+    unsafe { CFUUID::wrap_under_create_rule(CFUUIDCreateFromUUIDBytes(ptr::null(), bytes)) }
+}
+
+/// Reads the SMART log page of a drive through the user client plug-in
+fn read_log(service: &Service) -> Option<[u8; LOG_SIZE]> {
+    let mut plugin: *mut *mut PlugInInterface = ptr::null_mut();
+    let mut score = 0;
+    let result = unsafe {
+        IOCreatePlugInInterfaceForService(
+            service.id(),
+            uuid(SMART_USER_CLIENT_TYPE).as_concrete_TypeRef(),
+            uuid(PLUGIN_INTERFACE).as_concrete_TypeRef(),
+            &mut plugin,
+            &mut score,
+        )
+    };
+    if result != 0 || plugin.is_null() {
+        return None;
+    }
+
+    let mut smart: *mut *mut SmartInterface = ptr::null_mut();
+    let interface: CFUUIDBytes = unsafe { mem::transmute(SMART_INTERFACE) };
+    let result = unsafe { ((**plugin).query_interface)(plugin.cast(), interface, (&raw mut smart).cast()) };
+    let mut log = [0u8; LOG_SIZE];
+    let read = result == 0
+        && !smart.is_null()
+        && unsafe {
+            let read = ((**smart).smart_read_data)(smart.cast(), &mut log);
+            ((**smart).release)(smart.cast());
+            read == 0
+        };
+    unsafe { IODestroyPlugInInterface(plugin) };
+    read.then_some(log)
+}
+
+/// Decodes the fields of a SMART log page
+fn decode(log: &[u8; LOG_SIZE]) -> SmartLog {
+    let number = |start: usize, length: usize| {
+        let mut bytes = [0u8; 16];
+        bytes[..length].copy_from_slice(&log[start..start + length]);
+        u128::from_le_bytes(bytes)
+    };
+    let clamp = |value: u128| u64::try_from(value).unwrap_or(u64::MAX);
+    let kelvin = number(1, 2) as i64;
+    SmartLog {
+        critical_warning: Some(u64::from(log[0])),
+        temperature: (kelvin > 0).then_some(kelvin - ZERO_CELSIUS),
+        available_spare: Some(u64::from(log[3])),
+        wear: Some(u64::from(log[5])),
+        bytes_written: Some(clamp(number(48, 16).saturating_mul(u128::from(DATA_UNIT)))),
+        power_on_hours: Some(clamp(number(128, 16))),
+        media_errors: Some(clamp(number(160, 16))),
+        ..SmartLog::default()
+    }
+}
@@ -0,0 +1,436 @@
+//! Disks and volumes
+//!
+//! Lists the disks with their partitions and volumes, and mounts, unmounts
+//! and ejects them with `diskutil`. Volumes can be given by identifier, mount
+//! point or name, so `rem disk unmount backup` finds `/Volumes/Backup`. APFS
+//! volumes live on a synthesized container disk; ejecting one of them ejects
+//! the physical disk the container is stored on.
+//!
+//! When an unmount fails because apps have files open on the volume, the
+//! processes are listed with `lsof`, which only sees the processes of other
+//! users when run with `sudo`. `--force` unmounts anyway; the apps lose
+//! access to their open files.
+//!
+//! # System Commands Used
+//!
+//! - `diskutil list -plist` / `diskutil info -plist` - Read the disks and volumes
+//! - `diskutil mount` / `mountDisk` / `unmount` / `unmountDisk` / `eject` - Mount, unmount and eject
+//! - `lsof` - Find the processes with open files on a volume
+
+use super::{diskutil, diskutil_error, diskutil_plist};
+use crate::util::{format_bytes, match_name};
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// A disk with its partitions or, for an APFS container, its volumes
+#[derive(Serialize)]
+struct Disk {
+    /// BSD name like `disk4`
+    identifier: String,
+    /// Model name of a physical disk
+    name: Option<String>,
+    /// Whether a physical disk is built in
+    internal: Option<bool>,
+    /// Partition scheme or content of the disk
+    content: Option<String>,
+    /// Capacity in bytes
+    size: u64,
+    /// Partitions an APFS container is stored on
+    physical_stores: Vec<String>,
+    /// Partitions or APFS volumes
+    volumes: Vec<Volume>,
+}
+
+/// A partition or APFS volume
+#[derive(Serialize)]
+struct Volume {
+    /// BSD name like `disk4s1`
+    identifier: String,
+    /// Volume name
+    name: Option<String>,
+    /// Partition type or file system
+    content: Option<String>,
+    /// Size in bytes; the space in use for APFS volumes
+    size: u64,
+    /// Where the volume is mounted
+    mount_point: Option<String>,
+}
+
+/// A process with open files on a volume
+struct OpenFiles {
+    /// Process ID
+    pid: u32,
+    /// Command name
+    command: String,
+}
+
+/// Prints the disks with their partitions and volumes
+///
+/// # Arguments
+///
+/// * `json` - Print the disks as JSON
+///
+/// # Errors
+///
+/// Returns an error if `diskutil` fails.
+pub fn list(json: bool) -> Result<(), Box<dyn Error>> {
+    let mut disks = disks()?;
+    for disk in disks.iter_mut().filter(|disk| disk.physical_stores.is_empty()) {
+        let info = diskutil_plist(&["info", "-plist", &disk.identifier])?;
+        disk.name = info["MediaName"]
+            .as_str()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+        disk.internal = info["Internal"].as_bool();
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&disks)?);
+        return Ok(());
+    }
+    for (index, disk) in disks.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        let description = if disk.physical_stores.is_empty() {
+            disk.name.clone().unwrap_or_else(|| "Unknown disk".to_string())
+        } else {
+            format!("APFS container on {}", disk.physical_stores.join(", "))
+        };
+        let mut details = Vec::new();
+        details.extend(
+            disk.internal
+                .map(|internal| if internal { "internal" } else { "external" }.to_string()),
+        );
+        details.push(format_bytes(disk.size as f64));
+        println!(
+            "{}  {description}  {}",
+            disk.identifier.bold(),
+            details.join(", ").dimmed()
+        );
+        for volume in &disk.volumes {
+            let name = match (&volume.name, &volume.content) {
+                (Some(name), _) => name.normal(),
+                (None, Some(content)) => content.dimmed(),
+                (None, None) => "".normal(),
+            };
+            let row = format!(
+                "  {:<12}{:<28}{:>10}  {}",
+                volume.identifier,
+                name,
+                format_bytes(volume.size as f64),
+                volume.mount_point.as_deref().unwrap_or_default()
+            );
+            println!("{}", row.trim_end());
+        }
+    }
+    Ok(())
+}
+
+/// Mounts the volumes of a disk or a single volume
+///
+/// # Arguments
+///
+/// * `query` - Disk or volume identifier, or a volume name
+///
+/// # Errors
+///
+/// Returns an error if the disk does not exist or cannot be mounted.
+pub fn mount(query: &str) -> Result<(), Box<dyn Error>> {
+    let info = lookup(query)?;
+    let identifier = string(&info, "DeviceIdentifier").ok_or("diskutil reported no device identifier")?;
+    let whole = info["WholeDisk"].as_bool().unwrap_or_default();
+
+    print!("Mount {}", describe(&info));
+    io::stdout().flush()?;
+    let output = diskutil(&[if whole { "mountDisk" } else { "mount" }, &identifier])?;
+    if !output.status.success() {
+        println!();
+        return Err(format!("Failed to mount {identifier}: {}", diskutil_error(&output)).into());
+    }
+    println!("{}", " OK".green());
+
+    let mount_points = if whole {
+        mount_points(&identifier)?
+    } else {
+        string(&lookup(&identifier)?, "MountPoint").into_iter().collect()
+    };
+    for mount_point in mount_points {
+        println!("{:<16}{mount_point}", "Mounted at".bold());
+    }
+    Ok(())
+}
+
+/// Unmounts a volume and lists the apps that keep it from unmounting
+///
+/// # Arguments
+///
+/// * `query` - Volume name or part of it, mount point or identifier
+/// * `force` - Unmount even if files are open
+///
+/// # Errors
+///
+/// Returns an error if the volume does not exist, is not mounted or cannot be
+/// unmounted.
+pub fn unmount(query: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    let info = lookup(query)?;
+    let identifier = string(&info, "DeviceIdentifier").ok_or("diskutil reported no device identifier")?;
+    let name = describe(&info);
+    let mount_point = string(&info, "MountPoint").ok_or_else(|| format!("{name} is not mounted"))?;
+
+    print!("Unmount {name}");
+    io::stdout().flush()?;
+    let mut args = vec!["unmount"];
+    if force {
+        args.push("force");
+    }
+    args.push(&identifier);
+    let output = diskutil(&args)?;
+    if !output.status.success() {
+        println!();
+        print_open_files(&[mount_point]);
+        return Err(failed("unmount", &name, &diskutil_error(&output), force));
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Unmounts all volumes of a disk and ejects it
+///
+/// # Arguments
+///
+/// * `query` - Disk identifier, or a volume of the disk
+/// * `force` - Unmount even if files are open
+///
+/// # Errors
+///
+/// Returns an error if the disk does not exist or cannot be ejected.
+pub fn eject(query: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    let disk = physical_disk(&lookup(query)?)?;
+    let info = lookup(&disk)?;
+    let name = match string(&info, "MediaName") {
+        Some(name) => format!("{disk} ({name})"),
+        None => disk.clone(),
+    };
+    let mount_points = mount_points(&disk)?;
+
+    print!("Eject {name}");
+    io::stdout().flush()?;
+    let mut output = if force {
+        diskutil(&["unmountDisk", "force", &disk])?
+    } else {
+        diskutil(&["unmountDisk", &disk])?
+    };
+    if output.status.success() {
+        output = diskutil(&["eject", &disk])?;
+    }
+    if !output.status.success() {
+        println!();
+        print_open_files(&mount_points);
+        return Err(failed("eject", &name, &diskutil_error(&output), force));
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Builds the error of a failed unmount or eject
+fn failed(action: &str, name: &str, message: &str, force: bool) -> Box<dyn Error> {
+    let hint = if force {
+        ""
+    } else {
+        "; quit the apps using it or use --force"
+    };
+    format!("Failed to {action} {name}: {message}{hint}").into()
+}
+
+/// Reads all disks with their partitions and volumes
+///
+/// # Errors
+///
+/// Returns an error if `diskutil` fails.
+fn disks() -> Result<Vec<Disk>, Box<dyn Error>> {
+    let list = diskutil_plist(&["list", "-plist"])?;
+    let disks = list["AllDisksAndPartitions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|disk| {
+            let children = disk["Partitions"].as_array().or_else(|| disk["APFSVolumes"].as_array());
+            let mut volumes: Vec<Volume> = children.into_iter().flatten().map(volume).collect();
+            // A disk without partitions holds a single volume itself
+            if volumes.is_empty() && disk["MountPoint"].is_string() {
+                volumes.push(volume(disk));
+            }
+            Disk {
+                identifier: string(disk, "DeviceIdentifier").unwrap_or_default(),
+                name: None,
+                internal: None,
+                content: string(disk, "Content"),
+                size: disk["Size"].as_u64().unwrap_or_default(),
+                physical_stores: disk["APFSPhysicalStores"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|store| string(store, "DeviceIdentifier"))
+                    .collect(),
+                volumes,
+            }
+        })
+        .collect();
+    Ok(disks)
+}
+
+/// Builds a volume from its entry in `diskutil list`
+fn volume(entry: &Value) -> Volume {
+    // The system volume is mounted as a snapshot
+    let snapshot = entry["MountedSnapshots"]
+        .as_array()
+        .and_then(|snapshots| snapshots.first())
+        .and_then(|snapshot| string(snapshot, "SnapshotMountPoint"));
+    Volume {
+        identifier: string(entry, "DeviceIdentifier").unwrap_or_default(),
+        name: string(entry, "VolumeName"),
+        content: string(entry, "Content"),
+        size: entry["CapacityInUse"]
+            .as_u64()
+            .or_else(|| entry["Size"].as_u64())
+            .unwrap_or_default(),
+        mount_point: string(entry, "MountPoint").or(snapshot),
+    }
+}
+
+/// Reads the information of a disk or volume given by identifier, mount point or name
+///
+/// # Errors
+///
+/// Returns an error if nothing or more than one volume matches.
+fn lookup(query: &str) -> Result<Value, Box<dyn Error>> {
+    if query.starts_with('/') || is_identifier(query) {
+        return diskutil_plist(&["info", "-plist", query]);
+    }
+    let volumes: Vec<Volume> = disks()?
+        .into_iter()
+        .flat_map(|disk| disk.volumes)
+        .filter(|volume| volume.name.is_some())
+        .collect();
+    let names: Vec<&str> = volumes.iter().filter_map(|volume| volume.name.as_deref()).collect();
+    let index = match_name(&names, query)?.ok_or_else(|| format!("No disk or volume matches '{query}'"))?;
+    diskutil_plist(&["info", "-plist", &volumes[index].identifier])
+}
+
+/// Returns whether a query is a BSD name like `disk4` or `disk4s1`
+fn is_identifier(query: &str) -> bool {
+    query.strip_prefix("disk").is_some_and(|rest| {
+        rest.starts_with(|c: char| c.is_ascii_digit()) && rest.chars().all(|c| c.is_ascii_digit() || c == 's')
+    })
+}
+
+/// Returns the physical disk a disk or volume is stored on
+///
+/// # Errors
+///
+/// Returns an error if `diskutil` fails or reports no parent disk.
+fn physical_disk(info: &Value) -> Result<String, Box<dyn Error>> {
+    // APFS volumes are on a synthesized container stored on a physical partition
+    let store = info["APFSPhysicalStores"]
+        .as_array()
+        .and_then(|stores| stores.first())
+        .and_then(|store| string(store, "APFSPhysicalStore"));
+    let parent = match store {
+        Some(store) => string(&diskutil_plist(&["info", "-plist", &store])?, "ParentWholeDisk"),
+        None => string(info, "ParentWholeDisk"),
+    };
+    Ok(parent.ok_or("diskutil reported no parent disk")?)
+}
+
+/// Returns the mount points of the volumes of a disk, including the APFS
+/// volumes stored on it
+///
+/// # Errors
+///
+/// Returns an error if `diskutil` fails.
+pub fn mount_points(disk: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let partition = format!("{disk}s");
+    Ok(disks()?
+        .into_iter()
+        .filter(|entry| {
+            entry.identifier == disk || entry.physical_stores.iter().any(|store| store.starts_with(&partition))
+        })
+        .flat_map(|entry| entry.volumes)
+        .filter_map(|volume| volume.mount_point)
+        .collect())
+}
+
+/// Describes a volume by its name and identifier
+fn describe(info: &Value) -> String {
+    let identifier = string(info, "DeviceIdentifier").unwrap_or_default();
+    match string(info, "VolumeName") {
+        Some(name) => format!("{name} ({identifier})"),
+        None => identifier,
+    }
+}
+
+/// Returns a non-empty string of a property list
+fn string(value: &Value, key: &str) -> Option<String> {
+    value[key]
+        .as_str()
+        .map(str::trim)
+        .filter(|string| !string.is_empty())
+        .map(str::to_string)
+}
+
+/// Prints the processes with open files on volumes
+///
+/// # Arguments
+///
+/// * `mount_points` - Mount points of the volumes
+pub fn print_open_files(mount_points: &[String]) {
+    let mut processes: Vec<OpenFiles> = Vec::new();
+    for mount_point in mount_points {
+        for process in open_files(mount_point) {
+            if !processes.iter().any(|known| known.pid == process.pid) {
+                processes.push(process);
+            }
+        }
+    }
+    if processes.is_empty() {
+        return;
+    }
+    println!("{}", "In use by".bold());
+    for process in processes {
+        println!("  {} {}", process.command, format!("(PID {})", process.pid).dimmed());
+    }
+}
+
+/// Returns the processes with open files on the volume of a mount point
+///
+/// Given a mount point, `lsof` reports all open files on its file system;
+/// nothing is returned if `lsof` fails.
+fn open_files(mount_point: &str) -> Vec<OpenFiles> {
+    let Ok(output) = Command::new("lsof")
+        .args(["+c", "0", "-F", "pc", "--", mount_point])
+        .output()
+    else {
+        return Vec::new();
+    };
+    let mut processes = Vec::new();
+    let mut pid = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(value) = line.strip_prefix('p') {
+            pid = value.parse().ok();
+        } else if let Some(command) = line.strip_prefix('c')
+            && let Some(pid) = pid
+        {
+            processes.push(OpenFiles {
+                pid,
+                command: command.to_string(),
+            });
+        }
+    }
+    processes
+}
@@ -0,0 +1,112 @@
+//! Display settings
+//!
+//! Shows and switches True Tone and the automatic brightness adjustment, which
+//! often need to be turned off for color-sensitive work. Both are only
+//! available on Macs with an ambient light sensor, i.e. laptops, iMacs and
+//! Apple displays.
+//!
+//! `info` lists the connected displays; see the [`info`] module.
+//!
+//! macOS offers no command for these settings; they are switched through the
+//! private CoreBrightness and DisplayServices frameworks, which System Settings
+//! uses as well.
+//!
+//! # System Commands Used
+//!
+//! - True Tone is switched with `CBTrueToneClient` of CoreBrightness
+//! - Automatic brightness is switched with the ambient light compensation of
+//!   DisplayServices for the built-in display
+
+#[cfg(target_os = "macos")]
+mod frameworks;
+pub mod info;
+
+use crate::cli::{DisplayArgs, DisplayCommands, Switch};
+use colored::{ColoredString, Colorize};
+use std::error::Error;
+use std::io::{self, Write};
+
+#[cfg(target_os = "macos")]
+use frameworks::{auto_brightness, set_auto_brightness, set_true_tone, true_tone};
+
+/// Performs display operations
+///
+/// # Arguments
+///
+/// * `args` - Display arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a setting is not supported by this Mac or cannot be switched.
+pub fn perform(args: DisplayArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(DisplayCommands::Status) => {
+            println!("{:<16}{}", "True Tone".bold(), format_state(true_tone()));
+            println!("{:<16}{}", "Auto-brightness".bold(), format_state(auto_brightness()));
+            Ok(())
+        }
+        Some(DisplayCommands::Truetone { state: None }) => {
+            println!("{}", format_state(true_tone()));
+            Ok(())
+        }
+        Some(DisplayCommands::Truetone { state: Some(state) }) => switch("True Tone", state, set_true_tone),
+        Some(DisplayCommands::Autobrightness { state: None }) => {
+            println!("{}", format_state(auto_brightness()));
+            Ok(())
+        }
+        Some(DisplayCommands::Autobrightness { state: Some(state) }) => {
+            switch("automatic brightness", state, set_auto_brightness)
+        }
+        Some(DisplayCommands::Info { edid }) => info::perform(edid),
+    }
+}
+
+/// Switches a setting on or off
+///
+/// # Errors
+///
+/// Returns an error if the setting is not supported or cannot be switched.
+fn switch(label: &str, state: Switch, set: fn(bool) -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    let on = state == Switch::On;
+    print!("Turn {label} {}", if on { "on" } else { "off" });
+    io::stdout().flush()?;
+    if let Err(e) = set(on) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Formats the state of a setting, or why it cannot be read
+fn format_state(state: Result<bool, Box<dyn Error>>) -> ColoredString {
+    match state {
+        Ok(true) => "on".green(),
+        Ok(false) => "off".yellow(),
+        Err(e) => e.to_string().dimmed(),
+    }
+}
+
+/// Reads whether True Tone is on; requires CoreBrightness
+#[cfg(not(target_os = "macos"))]
+fn true_tone() -> Result<bool, Box<dyn Error>> {
+    Err("True Tone is only available on macOS".into())
+}
+
+/// Switches True Tone; requires CoreBrightness
+#[cfg(not(target_os = "macos"))]
+fn set_true_tone(_on: bool) -> Result<(), Box<dyn Error>> {
+    Err("True Tone is only available on macOS".into())
+}
+
+/// Reads whether automatic brightness is on; requires DisplayServices
+#[cfg(not(target_os = "macos"))]
+fn auto_brightness() -> Result<bool, Box<dyn Error>> {
+    Err("Automatic brightness is only available on macOS".into())
+}
+
+/// Switches automatic brightness; requires DisplayServices
+#[cfg(not(target_os = "macos"))]
+fn set_auto_brightness(_on: bool) -> Result<(), Box<dyn Error>> {
+    Err("Automatic brightness is only available on macOS".into())
+}
@@ -0,0 +1,207 @@
+//! True Tone and automatic brightness from private frameworks
+//!
+//! Private frameworks cannot be linked against, so they are loaded at runtime
+//! with `dlopen`. True Tone is controlled by the Objective-C class
+//! `CBTrueToneClient` of CoreBrightness, which is used with the message
+//! sending helpers of [`crate::objc`]. Automatic brightness
+//! is the ambient light compensation of DisplayServices, a C interface taking
+//! the id of a display.
+
+use crate::objc::{Id, Sel, class, msg_send, selector, send_id};
+use std::error::Error;
+use std::ffi::{CStr, c_char, c_void};
+use std::mem;
+
+unsafe extern "C" {
+    fn dlopen(path: *const c_char, mode: i32) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGGetOnlineDisplayList(max_displays: u32, displays: *mut u32, count: *mut u32) -> i32;
+    fn CGDisplayIsBuiltin(display: u32) -> u32;
+}
+
+/// `DisplayServicesHasAmbientLightCompensation`
+type HasCompensation = unsafe extern "C" fn(display: u32) -> bool;
+
+/// `DisplayServicesAmbientLightCompensationEnabled`
+type CompensationEnabled = unsafe extern "C" fn(display: u32, enabled: *mut bool) -> i32;
+
+/// `DisplayServicesEnableAmbientLightCompensation`
+type EnableCompensation = unsafe extern "C" fn(display: u32, enable: bool) -> i32;
+
+/// `RTLD_LAZY`
+const RTLD_LAZY: i32 = 1;
+
+/// Framework with the True Tone client
+const CORE_BRIGHTNESS: &CStr = c"/System/Library/PrivateFrameworks/CoreBrightness.framework/CoreBrightness";
+
+/// Framework with the ambient light compensation
+const DISPLAY_SERVICES: &CStr = c"/System/Library/PrivateFrameworks/DisplayServices.framework/DisplayServices";
+
+/// Maximum number of displays considered
+const MAX_DISPLAYS: usize = 16;
+
+/// An instance of `CBTrueToneClient`, released on drop
+struct TrueToneClient(Id);
+
+impl TrueToneClient {
+    /// Creates a client
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if CoreBrightness or its True Tone client is missing.
+    fn new() -> Result<Self, Box<dyn Error>> {
+        load(CORE_BRIGHTNESS)?;
+        let class = class(c"CBTrueToneClient");
+        if class.is_null() {
+            return Err("True Tone is not supported by this version of macOS".into());
+        }
+        let client = unsafe { send_id(send_id(class, c"alloc"), c"init") };
+        if client.is_null() {
+            return Err("Failed to create the True Tone client".into());
+        }
+        Ok(TrueToneClient(client))
+    }
+
+    /// Sends a message without arguments returning a `BOOL`
+    fn get(&self, name: &CStr) -> bool {
+        unsafe {
+            let send: unsafe extern "C" fn(Id, Sel) -> i8 = msg_send();
+            send(self.0, selector(name)) != 0
+        }
+    }
+
+    /// Sends a message with a `BOOL` argument returning a `BOOL`
+    fn set(&self, name: &CStr, value: bool) -> bool {
+        unsafe {
+            let send: unsafe extern "C" fn(Id, Sel, i8) -> i8 = msg_send();
+            send(self.0, selector(name), i8::from(value)) != 0
+        }
+    }
+}
+
+impl Drop for TrueToneClient {
+    fn drop(&mut self) {
+        unsafe { send_id(self.0, c"release") };
+    }
+}
+
+/// Reads whether True Tone is on
+///
+/// # Errors
+///
+/// Returns an error if the Mac does not support True Tone.
+pub fn true_tone() -> Result<bool, Box<dyn Error>> {
+    let client = TrueToneClient::new()?;
+    if !client.get(c"supported") {
+        return Err("This Mac does not support True Tone".into());
+    }
+    Ok(client.get(c"enabled"))
+}
+
+/// Switches True Tone on or off
+///
+/// # Errors
+///
+/// Returns an error if True Tone is not supported or not available for the
+/// connected displays, or switching fails.
+pub fn set_true_tone(on: bool) -> Result<(), Box<dyn Error>> {
+    let client = TrueToneClient::new()?;
+    if !client.get(c"supported") {
+        return Err("This Mac does not support True Tone".into());
+    }
+    if !client.get(c"available") {
+        return Err("True Tone is not available for the connected displays".into());
+    }
+    if !client.set(c"setEnabled:", on) {
+        return Err("Failed to switch True Tone".into());
+    }
+    Ok(())
+}
+
+/// Reads whether automatic brightness is on
+///
+/// # Errors
+///
+/// Returns an error if no display has an ambient light sensor or the state cannot be read.
+pub fn auto_brightness() -> Result<bool, Box<dyn Error>> {
+    let handle = load(DISPLAY_SERVICES)?;
+    let display = sensor_display(handle)?;
+    let enabled: CompensationEnabled =
+        unsafe { mem::transmute(symbol(handle, c"DisplayServicesAmbientLightCompensationEnabled")?) };
+    let mut on = false;
+    let result = unsafe { enabled(display, &mut on) };
+    if result != 0 {
+        return Err(format!("Failed to read automatic brightness (error {result})").into());
+    }
+    Ok(on)
+}
+
+/// Switches automatic brightness on or off
+///
+/// # Errors
+///
+/// Returns an error if no display has an ambient light sensor or switching fails.
+pub fn set_auto_brightness(on: bool) -> Result<(), Box<dyn Error>> {
+    let handle = load(DISPLAY_SERVICES)?;
+    let display = sensor_display(handle)?;
+    let enable: EnableCompensation =
+        unsafe { mem::transmute(symbol(handle, c"DisplayServicesEnableAmbientLightCompensation")?) };
+    let result = unsafe { enable(display, on) };
+    if result != 0 {
+        return Err(format!("Failed to switch automatic brightness (error {result})").into());
+    }
+    Ok(())
+}
+
+/// Returns the display with an ambient light sensor, preferring the built-in one
+///
+/// # Errors
+///
+/// Returns an error if the displays cannot be listed or none has a sensor.
+fn sensor_display(handle: *mut c_void) -> Result<u32, Box<dyn Error>> {
+    let has_compensation: HasCompensation =
+        unsafe { mem::transmute(symbol(handle, c"DisplayServicesHasAmbientLightCompensation")?) };
+
+    let mut displays = [0u32; MAX_DISPLAYS];
+    let mut count = 0;
+    let result = unsafe { CGGetOnlineDisplayList(MAX_DISPLAYS as u32, displays.as_mut_ptr(), &mut count) };
+    if result != 0 {
+        return Err(format!("Failed to list the displays (error {result})").into());
+    }
+    let mut displays: Vec<u32> = displays[..count as usize].to_vec();
+    displays.sort_by_key(|&display| unsafe { CGDisplayIsBuiltin(display) } == 0);
+    displays
+        .into_iter()
+        .find(|&display| unsafe { has_compensation(display) })
+        .ok_or_else(|| "No display has an ambient light sensor".into())
+}
+
+/// Loads a framework
+///
+/// # Errors
+///
+/// Returns an error if the framework does not exist.
+fn load(path: &CStr) -> Result<*mut c_void, Box<dyn Error>> {
+    let handle = unsafe { dlopen(path.as_ptr(), RTLD_LAZY) };
+    if handle.is_null() {
+        return Err(format!("Failed to load {}", path.to_string_lossy()).into());
+    }
+    Ok(handle)
+}
+
+/// Looks up a function of a loaded framework
+///
+/// # Errors
+///
+/// Returns an error if the framework lacks the function.
+fn symbol(handle: *mut c_void, name: &CStr) -> Result<*mut c_void, Box<dyn Error>> {
+    let function = unsafe { dlsym(handle, name.as_ptr()) };
+    if function.is_null() {
+        return Err(format!("{} is not supported by this version of macOS", name.to_string_lossy()).into());
+    }
+    Ok(function)
+}
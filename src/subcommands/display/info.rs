@@ -0,0 +1,277 @@
+//! Connected displays
+//!
+//! Lists the displays with their current mode from CoreGraphics, and model,
+//! serial number, manufacturing date, connection, bit depth and HDR support
+//! from their EDID, the identification data a display sends to the Mac when it
+//! is connected. A missing or truncated EDID often explains a display that is
+//! detected with the wrong resolution or not at all; `--edid` dumps it raw for
+//! comparison or tools like `edid-decode`.
+//!
+//! The built-in display of Apple silicon Macs has no EDID.
+//!
+//! # System Commands Used
+//!
+//! - Displays and modes are read with `CGGetOnlineDisplayList` and `CGDisplayCopyDisplayMode`
+//! - The EDID is read from the `IODisplay` (Intel) and `IOMobileFramebuffer`
+//!   (Apple silicon) services of the I/O Registry
+
+#[cfg(target_os = "macos")]
+mod registry;
+
+use colored::Colorize;
+use std::error::Error;
+
+#[cfg(target_os = "macos")]
+use registry::read_displays;
+
+/// Fixed header of every EDID
+const EDID_HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+/// Size of an EDID block
+const EDID_BLOCK: usize = 128;
+
+/// Tag of CTA-861 extension blocks
+const CTA_EXTENSION: u8 = 0x02;
+
+/// Extended tag of the HDR static metadata data block in CTA-861 extensions
+const HDR_STATIC_METADATA: u8 = 0x06;
+
+/// Bytes per line of the EDID dump
+const DUMP_WIDTH: usize = 16;
+
+/// A connected display
+struct Display {
+    /// True for the built-in display of laptops and iMacs
+    builtin: bool,
+    /// True for the display with the menu bar
+    main: bool,
+    /// Vendor number, the EDID manufacturer id
+    vendor: u32,
+    /// Model number, the EDID product code
+    model: u32,
+    /// Serial number, 0 if unknown
+    serial: u32,
+    /// Current display mode
+    mode: Option<Mode>,
+    /// Raw EDID
+    edid: Option<Vec<u8>>,
+}
+
+/// A display mode
+struct Mode {
+    /// Width in points
+    width: usize,
+    /// Height in points
+    height: usize,
+    /// Width in pixels
+    pixel_width: usize,
+    /// Height in pixels
+    pixel_height: usize,
+    /// Refresh rate in Hz, 0 if not reported
+    refresh_rate: f64,
+}
+
+/// Information decoded from an EDID
+struct Edid {
+    /// Three letter manufacturer id, e.g. `DEL` or `GSM`
+    manufacturer: String,
+    /// Product name from the display descriptor
+    name: Option<String>,
+    /// Serial number from the display descriptor
+    serial: Option<String>,
+    /// Week of manufacture, if given
+    week: Option<u8>,
+    /// Year of manufacture, or the model year
+    year: u16,
+    /// Video interface, e.g. `HDMI` or `DisplayPort`
+    interface: Option<&'static str>,
+    /// Bits per color, for digital displays with EDID 1.4
+    bit_depth: Option<u8>,
+    /// True if a CTA-861 extension announces HDR static metadata
+    hdr: bool,
+}
+
+/// Lists the connected displays
+///
+/// # Arguments
+///
+/// * `dump` - True to also dump the raw EDID of each display
+///
+/// # Errors
+///
+/// Returns an error if the displays cannot be listed.
+pub fn perform(dump: bool) -> Result<(), Box<dyn Error>> {
+    let displays = read_displays()?;
+    if displays.is_empty() {
+        println!("No display connected");
+        return Ok(());
+    }
+
+    for (index, display) in displays.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        let edid = display.edid.as_deref().and_then(parse_edid);
+        print_display(index, display, edid.as_ref());
+        if dump && let Some(bytes) = &display.edid {
+            println!();
+            for (line, chunk) in bytes.chunks(DUMP_WIDTH).enumerate() {
+                let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+                println!("  {:04x}  {}", line * DUMP_WIDTH, hex.join(" "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the properties of a display
+fn print_display(index: usize, display: &Display, edid: Option<&Edid>) {
+    let name = match (edid.and_then(|edid| edid.name.clone()), display.builtin) {
+        (Some(name), _) => name,
+        (None, true) => "Built-in display".to_string(),
+        (None, false) => format!("Display {:04x}:{:04x}", display.vendor, display.model),
+    };
+    let main = if display.main { " (main)" } else { "" };
+    println!("{} {name}{main}", format!("Display {}:", index + 1).bold());
+
+    if let Some(edid) = edid {
+        let made = match edid.week {
+            Some(week) => format!("week {week} of {}", edid.year),
+            None => format!("model year {}", edid.year),
+        };
+        println!("  {:<16}{}, {made}", "Manufacturer".bold(), edid.manufacturer);
+    }
+    println!("  {:<16}{:#06x}", "Product".bold(), display.model);
+    let serial = edid
+        .and_then(|edid| edid.serial.clone())
+        .or_else(|| (display.serial != 0).then(|| display.serial.to_string()));
+    if let Some(serial) = serial {
+        println!("  {:<16}{serial}", "Serial".bold());
+    }
+    let connection = if display.builtin {
+        "built-in"
+    } else {
+        edid.and_then(|edid| edid.interface).unwrap_or("unknown")
+    };
+    println!("  {:<16}{connection}", "Connection".bold());
+
+    if let Some(mode) = &display.mode {
+        let mut text = format!("{} x {}", mode.width, mode.height);
+        if (mode.pixel_width, mode.pixel_height) != (mode.width, mode.height) {
+            text.push_str(&format!(" ({} x {} pixels)", mode.pixel_width, mode.pixel_height));
+        }
+        if mode.refresh_rate > 0.0 {
+            text.push_str(&format!(" @ {:.0} Hz", mode.refresh_rate));
+        }
+        println!("  {:<16}{text}", "Mode".bold());
+    }
+    if let Some(depth) = edid.and_then(|edid| edid.bit_depth) {
+        println!("  {:<16}{depth} bit per color", "Bit depth".bold());
+    }
+    let hdr = match edid {
+        Some(edid) if edid.hdr => "supported".green(),
+        Some(_) => "not supported".normal(),
+        None => "unknown".dimmed(),
+    };
+    println!("  {:<16}{hdr}", "HDR".bold());
+    let edid_size = match &display.edid {
+        Some(bytes) if edid.is_some() => format!("{} bytes", bytes.len()).normal(),
+        Some(bytes) => format!("{} bytes, invalid", bytes.len()).yellow(),
+        None => "not available".dimmed(),
+    };
+    println!("  {:<16}{edid_size}", "EDID".bold());
+}
+
+/// Decodes the base block and CTA-861 extensions of an EDID
+///
+/// # Returns
+///
+/// `None` if the EDID is shorter than a block or lacks the header.
+fn parse_edid(bytes: &[u8]) -> Option<Edid> {
+    if bytes.len() < EDID_BLOCK || bytes[..8] != EDID_HEADER {
+        return None;
+    }
+
+    // Three letters of five bits each, 1 = A
+    let id = u16::from_be_bytes([bytes[8], bytes[9]]);
+    let manufacturer: String = [10, 5, 0]
+        .iter()
+        .map(|shift| char::from(b'@' + ((id >> shift) & 0x1f) as u8))
+        .collect();
+
+    let mut name = None;
+    let mut serial = None;
+    for descriptor in bytes[54..126].chunks(18) {
+        if descriptor[..3] != [0, 0, 0] {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&descriptor[5..])
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        match descriptor[3] {
+            0xfc if !text.is_empty() => name = Some(text),
+            0xff if !text.is_empty() => serial = Some(text),
+            _ => {}
+        }
+    }
+
+    // Bit depth and interface are only defined by EDID 1.4
+    let input = bytes[20];
+    let digital = input & 0x80 != 0;
+    let version_1_4 = bytes[18] == 1 && bytes[19] >= 4;
+    let bit_depth = match (input >> 4) & 0x07 {
+        depth @ 1..=6 if digital && version_1_4 => Some(4 + 2 * depth),
+        _ => None,
+    };
+    let interface = match input & 0x0f {
+        _ if !digital => Some("VGA"),
+        1 if version_1_4 => Some("DVI"),
+        2 | 3 if version_1_4 => Some("HDMI"),
+        5 if version_1_4 => Some("DisplayPort"),
+        _ => None,
+    };
+
+    let extensions = usize::from(bytes[126]);
+    let hdr = bytes
+        .chunks(EDID_BLOCK)
+        .skip(1)
+        .take(extensions)
+        .filter(|block| block.len() == EDID_BLOCK && block[0] == CTA_EXTENSION)
+        .any(has_hdr_metadata);
+
+    Some(Edid {
+        manufacturer,
+        name,
+        serial,
+        week: (1..=54).contains(&bytes[16]).then_some(bytes[16]),
+        year: 1990 + u16::from(bytes[17]),
+        interface,
+        bit_depth,
+        hdr,
+    })
+}
+
+/// Returns true if a CTA-861 extension block contains HDR static metadata
+fn has_hdr_metadata(block: &[u8]) -> bool {
+    // Data blocks run from byte 4 to the offset of the detailed timings
+    let end = usize::from(block[2]).min(EDID_BLOCK - 1);
+    let mut position = 4;
+    while position < end {
+        let header = block[position];
+        let length = usize::from(header & 0x1f);
+        if header >> 5 == 7 && length > 0 && block.get(position + 1) == Some(&HDR_STATIC_METADATA) {
+            return true;
+        }
+        position += length + 1;
+    }
+    false
+}
+
+/// Reads the connected displays; requires CoreGraphics and IOKit
+#[cfg(not(target_os = "macos"))]
+fn read_displays() -> Result<Vec<Display>, Box<dyn Error>> {
+    Err("Displays can only be read on macOS".into())
+}
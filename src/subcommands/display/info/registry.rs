@@ -0,0 +1,123 @@
+//! Displays from CoreGraphics and their EDID from the I/O Registry
+//!
+//! CoreGraphics identifies displays by vendor, model and serial number, which
+//! are the manufacturer id, product code and serial number of the EDID. The
+//! EDIDs found in the I/O Registry are assigned to the displays by these
+//! numbers.
+
+use super::{Display, Mode};
+use crate::iokit::{Service, matching_services};
+use core_foundation::data::CFData;
+use std::error::Error;
+use std::ffi::{CStr, c_void};
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGGetOnlineDisplayList(max_displays: u32, displays: *mut u32, count: *mut u32) -> i32;
+    fn CGDisplayIsBuiltin(display: u32) -> u32;
+    fn CGDisplayIsMain(display: u32) -> u32;
+    fn CGDisplayVendorNumber(display: u32) -> u32;
+    fn CGDisplayModelNumber(display: u32) -> u32;
+    fn CGDisplaySerialNumber(display: u32) -> u32;
+    fn CGDisplayCopyDisplayMode(display: u32) -> *mut c_void;
+    fn CGDisplayModeGetWidth(mode: *mut c_void) -> usize;
+    fn CGDisplayModeGetHeight(mode: *mut c_void) -> usize;
+    fn CGDisplayModeGetPixelWidth(mode: *mut c_void) -> usize;
+    fn CGDisplayModeGetPixelHeight(mode: *mut c_void) -> usize;
+    fn CGDisplayModeGetRefreshRate(mode: *mut c_void) -> f64;
+    fn CGDisplayModeRelease(mode: *mut c_void);
+}
+
+/// Maximum number of displays considered
+const MAX_DISPLAYS: usize = 16;
+
+/// Services holding the EDID: displays on Intel Macs, framebuffers on Apple silicon
+const EDID_SERVICES: [&CStr; 2] = [c"IODisplay", c"IOMobileFramebuffer"];
+
+/// Properties holding the EDID, the unmodified one first
+const EDID_KEYS: [&str; 3] = ["IODisplayEDIDOriginal", "IODisplayEDID", "EDID"];
+
+/// Reads the online displays with their current mode and EDID
+///
+/// # Errors
+///
+/// Returns an error if the displays cannot be listed.
+pub fn read_displays() -> Result<Vec<Display>, Box<dyn Error>> {
+    let mut ids = [0u32; MAX_DISPLAYS];
+    let mut count = 0;
+    let result = unsafe { CGGetOnlineDisplayList(MAX_DISPLAYS as u32, ids.as_mut_ptr(), &mut count) };
+    if result != 0 {
+        return Err(format!("Failed to list the displays (error {result})").into());
+    }
+
+    let edids = read_edids();
+    Ok(ids[..count as usize]
+        .iter()
+        .map(|&id| {
+            let vendor = unsafe { CGDisplayVendorNumber(id) };
+            let model = unsafe { CGDisplayModelNumber(id) };
+            let serial = unsafe { CGDisplaySerialNumber(id) };
+            let edid = edids
+                .iter()
+                .find(|edid| edid_identity(edid) == Some((vendor, model, serial)))
+                .cloned();
+            Display {
+                builtin: unsafe { CGDisplayIsBuiltin(id) } != 0,
+                main: unsafe { CGDisplayIsMain(id) } != 0,
+                vendor,
+                model,
+                serial,
+                mode: current_mode(id),
+                edid,
+            }
+        })
+        .collect())
+}
+
+/// Returns the current mode of a display
+fn current_mode(id: u32) -> Option<Mode> {
+    let mode = unsafe { CGDisplayCopyDisplayMode(id) };
+    if mode.is_null() {
+        return None;
+    }
+    let result = unsafe {
+        Mode {
+            width: CGDisplayModeGetWidth(mode),
+            height: CGDisplayModeGetHeight(mode),
+            pixel_width: CGDisplayModeGetPixelWidth(mode),
+            pixel_height: CGDisplayModeGetPixelHeight(mode),
+            refresh_rate: CGDisplayModeGetRefreshRate(mode),
+        }
+    };
+    unsafe { CGDisplayModeRelease(mode) };
+    Some(result)
+}
+
+/// Reads all EDIDs found in the I/O Registry
+fn read_edids() -> Vec<Vec<u8>> {
+    EDID_SERVICES
+        .iter()
+        .flat_map(|class| matching_services(class).unwrap_or_default())
+        .filter_map(|service| EDID_KEYS.iter().find_map(|key| data_property(&service, key)))
+        .collect()
+}
+
+/// Returns a data property of a service
+fn data_property(service: &Service, key: &str) -> Option<Vec<u8>> {
+    service
+        .property(key)?
+        .downcast::<CFData>()
+        .map(|data| data.bytes().to_vec())
+        .filter(|bytes| !bytes.is_empty())
+}
+
+/// Returns manufacturer id, product code and serial number of an EDID as
+/// CoreGraphics reports them
+fn edid_identity(edid: &[u8]) -> Option<(u32, u32, u32)> {
+    let bytes = edid.get(8..16)?;
+    Some((
+        u32::from(u16::from_be_bytes([bytes[0], bytes[1]])),
+        u32::from(u16::from_le_bytes([bytes[2], bytes[3]])),
+        u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+    ))
+}
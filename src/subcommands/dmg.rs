@@ -0,0 +1,491 @@
+//! Disk images
+//!
+//! Creates disk images with `hdiutil`, empty or from a folder, and attaches
+//! and detaches them. An image made from a folder is compressed and read-only
+//! unless a size is given; with a size it stays writable, so
+//! `rem dmg create vault --from Private --encrypted --size 2g` turns a folder
+//! into an encrypted vault in one step.
+//!
+//! Encrypted images use AES-256. The password is entered without echo, or
+//! read from stdin without a terminal, and handed to `hdiutil` on stdin. It
+//! is saved in the keychain by the UUID of the image (see [`keychain`]), so
+//! `attach` opens the image without asking.
+//!
+//! # System Commands Used
+//!
+//! - `hdiutil create` / `attach` / `detach` - Create, attach and detach images
+//! - `hdiutil info -plist` - List the attached images
+//! - `hdiutil isencrypted -plist` - Read the encryption and UUID of an image
+//! - `stty` - Turn off the echo while the password is entered
+
+#[cfg(target_os = "macos")]
+mod keychain;
+
+use crate::cli::{DmgArgs, DmgCommands};
+use crate::subcommands::disk::plist_to_json;
+use crate::subcommands::disk::volumes::print_open_files;
+use crate::util::{format_bytes, match_name};
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+#[cfg(target_os = "macos")]
+use keychain::{find_password, save_password};
+
+/// Bytes in a sector, the unit of image sizes given to `hdiutil`
+pub const SECTOR_SIZE: u64 = 512;
+
+/// An attached disk image
+pub struct AttachedImage {
+    /// Path of the image file
+    pub path: PathBuf,
+    /// Device of the whole disk, e.g. `/dev/disk6`
+    pub device: String,
+    /// Mount points of its volumes
+    pub mount_points: Vec<String>,
+    /// Whether the image is encrypted
+    pub encrypted: bool,
+}
+
+/// Performs disk image operations
+///
+/// # Arguments
+///
+/// * `args` - Disk image arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil` fails or the password cannot be read.
+pub fn perform(args: DmgArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(DmgCommands::List) => list(),
+        Some(DmgCommands::Create {
+            image,
+            from,
+            size,
+            encrypted,
+            name,
+            no_keychain,
+        }) => create(&image, from.as_deref(), size, encrypted, name, !no_keychain),
+        Some(DmgCommands::Attach { image, remember }) => attach(&image, remember),
+        Some(DmgCommands::Detach { image, force }) => detach(&image, force),
+    }
+}
+
+/// Prints the attached disk images
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil` fails.
+fn list() -> Result<(), Box<dyn Error>> {
+    let images = attached_images()?;
+    if images.is_empty() {
+        println!("No disk images attached");
+        return Ok(());
+    }
+    for image in &images {
+        let name = image.path.file_name().unwrap_or_default().to_string_lossy();
+        let encrypted = if image.encrypted { "encrypted" } else { "" };
+        println!(
+            "{:<24}{:<12}{}  {}",
+            name.bold(),
+            image.device,
+            image.mount_points.join(", "),
+            encrypted.dimmed()
+        );
+        println!("  {}", image.path.display().to_string().dimmed());
+    }
+    Ok(())
+}
+
+/// Creates a disk image
+///
+/// # Arguments
+///
+/// * `image` - Path of the image; `.dmg` is appended if it has no extension
+/// * `from` - Folder to copy into the image
+/// * `size` - Size of a writable image in bytes
+/// * `encrypted` - Encrypt the image with a password
+/// * `name` - Name of the volume
+/// * `keychain` - Save the password in the keychain
+///
+/// # Errors
+///
+/// Returns an error if the image exists, the folder does not, the passwords
+/// do not match or `hdiutil` fails.
+fn create(
+    image: &Path,
+    from: Option<&Path>,
+    size: Option<u64>,
+    encrypted: bool,
+    name: Option<String>,
+    keychain: bool,
+) -> Result<(), Box<dyn Error>> {
+    let image = if image.extension().is_none() {
+        image.with_extension("dmg")
+    } else {
+        image.to_path_buf()
+    };
+    if image.symlink_metadata().is_ok() {
+        return Err(format!("{} already exists", image.display()).into());
+    }
+    if let Some(from) = from
+        && !from.is_dir()
+    {
+        return Err(format!("{} is not a folder", from.display()).into());
+    }
+    if from.is_none() && size.is_none() {
+        return Err("Use --size to give the size of an empty image, or --from to copy a folder".into());
+    }
+    let name = name
+        .or_else(|| {
+            from.and_then(|from| {
+                from.canonicalize()
+                    .ok()?
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+        })
+        .or_else(|| image.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "Disk Image".to_string());
+    let password = if encrypted { Some(new_password()?) } else { None };
+
+    let sectors = size.map(|size| format!("{}b", size.div_ceil(SECTOR_SIZE)));
+    let mut args = vec!["create", "-volname", &name, "-fs", "APFS"];
+    match (from, &sectors) {
+        (Some(from), Some(sectors)) => {
+            args.extend(["-srcfolder", path_str(from)?, "-format", "UDRW", "-size", sectors]);
+        }
+        (Some(from), None) => args.extend(["-srcfolder", path_str(from)?, "-format", "UDZO"]),
+        (None, Some(sectors)) => args.extend(["-size", sectors]),
+        (None, None) => {}
+    }
+    if encrypted {
+        args.extend(["-encryption", "AES-256", "-stdinpass"]);
+    }
+    args.push(path_str(&image)?);
+
+    print!("Create {}", image.display());
+    io::stdout().flush()?;
+    let output = hdiutil(&args, password.as_deref())?;
+    if !output.status.success() {
+        println!();
+        return Err(format!("Failed to create {}: {}", image.display(), hdiutil_error(&output)).into());
+    }
+    println!("{}", " OK".green());
+    let size = fs::metadata(&image).map(|metadata| metadata.len()).unwrap_or_default();
+    println!("{:<16}{}", "Size".bold(), format_bytes(size as f64));
+
+    if let Some(password) = password
+        && keychain
+    {
+        print!("Save the password in the keychain");
+        io::stdout().flush()?;
+        if let Err(e) = remember(&image, &password) {
+            println!();
+            return Err(e);
+        }
+        println!("{}", " OK".green());
+    }
+    Ok(())
+}
+
+/// Attaches a disk image and prints where its volumes are mounted
+///
+/// # Arguments
+///
+/// * `image` - Path of the image
+/// * `remember_password` - Save the password in the keychain if it had to be entered
+///
+/// # Errors
+///
+/// Returns an error if the image does not exist, the password is wrong or
+/// `hdiutil` fails.
+fn attach(image: &Path, remember_password: bool) -> Result<(), Box<dyn Error>> {
+    if !image.exists() {
+        return Err(format!("{} does not exist", image.display()).into());
+    }
+    let (encrypted, uuid) = encryption(image)?;
+    let saved = if encrypted {
+        uuid.as_deref().and_then(find_password)
+    } else {
+        None
+    };
+    let entered = match (encrypted, &saved) {
+        (true, None) => Some(read_password("Password")?),
+        _ => None,
+    };
+    let password = saved.as_deref().or(entered.as_deref());
+
+    let mut args = vec!["attach", "-plist"];
+    if password.is_some() {
+        args.push("-stdinpass");
+    }
+    args.push(path_str(image)?);
+    print!("Attach {}", image.display());
+    io::stdout().flush()?;
+    let output = hdiutil(&args, password)?;
+    if !output.status.success() {
+        println!();
+        let hint = if saved.is_some() {
+            "; the password in the keychain may be outdated"
+        } else {
+            ""
+        };
+        return Err(format!("Failed to attach {}: {}{hint}", image.display(), hdiutil_error(&output)).into());
+    }
+    println!("{}", " OK".green());
+
+    let plist = plist_to_json(&output.stdout)?;
+    for entity in plist["system-entities"].as_array().into_iter().flatten() {
+        if let Some(mount_point) = entity["mount-point"].as_str() {
+            println!("{:<16}{mount_point}", "Mounted at".bold());
+        }
+    }
+
+    if let Some(password) = entered
+        && remember_password
+    {
+        print!("Save the password in the keychain");
+        io::stdout().flush()?;
+        if let Err(e) = remember(image, &password) {
+            println!();
+            return Err(e);
+        }
+        println!("{}", " OK".green());
+    }
+    Ok(())
+}
+
+/// Detaches a disk image
+///
+/// # Arguments
+///
+/// * `query` - Image name or part of it, image path, mount point or disk identifier
+/// * `force` - Detach even if files are open
+///
+/// # Errors
+///
+/// Returns an error if no attached image matches or `hdiutil` fails.
+fn detach(query: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    let images = attached_images()?;
+    let device = query
+        .strip_prefix("/dev/")
+        .map_or_else(|| format!("/dev/{query}"), |_| query.to_string());
+    let path = Path::new(query).canonicalize().ok();
+    let index = match images.iter().position(|image| {
+        image.device == device
+            || path.as_deref() == Some(image.path.as_path())
+            || image
+                .mount_points
+                .iter()
+                .any(|mount_point| path.as_deref() == Some(Path::new(mount_point)))
+    }) {
+        Some(index) => index,
+        None => {
+            let names: Vec<String> = images
+                .iter()
+                .map(|image| {
+                    image
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            match_name(&names, query)?.ok_or_else(|| format!("No attached disk image matches '{query}'"))?
+        }
+    };
+    let image = &images[index];
+
+    print!("Detach {}", image.path.display());
+    io::stdout().flush()?;
+    let mut args = vec!["detach", image.device.as_str()];
+    if force {
+        args.push("-force");
+    }
+    let output = hdiutil(&args, None)?;
+    if !output.status.success() {
+        println!();
+        print_open_files(&image.mount_points);
+        let hint = if force {
+            ""
+        } else {
+            "; quit the apps using it or use --force"
+        };
+        return Err(format!(
+            "Failed to detach {}: {}{hint}",
+            image.path.display(),
+            hdiutil_error(&output)
+        )
+        .into());
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Reads the attached disk images
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil` fails.
+pub fn attached_images() -> Result<Vec<AttachedImage>, Box<dyn Error>> {
+    let output = hdiutil(&["info", "-plist"], None)?;
+    if !output.status.success() {
+        return Err(format!("hdiutil info failed: {}", hdiutil_error(&output)).into());
+    }
+    let plist = plist_to_json(&output.stdout)?;
+    let images = plist["images"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|image| {
+            let entities = image["system-entities"].as_array()?;
+            // The whole disk has the shortest device name
+            let device = entities
+                .iter()
+                .filter_map(|entity| entity["dev-entry"].as_str())
+                .min_by_key(|device| device.len())?;
+            Some(AttachedImage {
+                path: PathBuf::from(image["image-path"].as_str()?),
+                device: device.to_string(),
+                mount_points: entities
+                    .iter()
+                    .filter_map(|entity| entity["mount-point"].as_str().map(str::to_string))
+                    .collect(),
+                encrypted: image["image-encrypted"].as_bool().unwrap_or_default(),
+            })
+        })
+        .collect();
+    Ok(images)
+}
+
+/// Reads whether an image is encrypted and its UUID
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil` fails.
+fn encryption(image: &Path) -> Result<(bool, Option<String>), Box<dyn Error>> {
+    let output = hdiutil(&["isencrypted", "-plist", path_str(image)?], None)?;
+    if !output.status.success() {
+        return Err(format!("Failed to read {}: {}", image.display(), hdiutil_error(&output)).into());
+    }
+    let plist = plist_to_json(&output.stdout)?;
+    Ok((
+        plist["encrypted"].as_bool().unwrap_or_default(),
+        plist["uuid"].as_str().map(str::to_string),
+    ))
+}
+
+/// Saves the password of an image in the keychain
+///
+/// # Errors
+///
+/// Returns an error if the image has no UUID or the keychain refuses the password.
+fn remember(image: &Path, password: &str) -> Result<(), Box<dyn Error>> {
+    let (_, uuid) = encryption(image)?;
+    let uuid = uuid.ok_or("hdiutil reported no UUID for the image")?;
+    let name = image.file_name().unwrap_or_default().to_string_lossy();
+    save_password(&uuid, &format!("{name} disk image"), password)
+}
+
+/// Runs `hdiutil`, passing a password on stdin
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil` cannot be run; a failure of the command is
+/// left to the caller.
+pub fn hdiutil(args: &[&str], password: Option<&str>) -> Result<Output, Box<dyn Error>> {
+    let mut child = Command::new("hdiutil")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run hdiutil: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take()
+        && let Some(password) = password
+    {
+        // `-stdinpass` reads up to the end of the input, a newline would be part of the password
+        stdin.write_all(password.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+/// Returns the message of a failed `hdiutil` command
+pub fn hdiutil_error(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr.trim().trim_start_matches("hdiutil: ").to_string()
+}
+
+/// Returns a path as a string for the arguments of `hdiutil`
+///
+/// # Errors
+///
+/// Returns an error if the path is not valid UTF-8.
+fn path_str(path: &Path) -> Result<&str, Box<dyn Error>> {
+    Ok(path
+        .to_str()
+        .ok_or_else(|| format!("{} is not a valid path", path.display()))?)
+}
+
+/// Asks for a new password twice
+///
+/// # Errors
+///
+/// Returns an error if the password is empty, the two do not match or stdin
+/// cannot be read.
+fn new_password() -> Result<String, Box<dyn Error>> {
+    let password = read_password("Password")?;
+    if password.is_empty() {
+        return Err("The password must not be empty".into());
+    }
+    if io::stdin().is_terminal() && read_password("Repeat the password")? != password {
+        return Err("The passwords do not match".into());
+    }
+    Ok(password)
+}
+
+/// Reads a password without echo, or a line of stdin without a terminal
+///
+/// # Errors
+///
+/// Returns an error if stdin cannot be read.
+fn read_password(prompt: &str) -> Result<String, Box<dyn Error>> {
+    let terminal = io::stdin().is_terminal();
+    if terminal {
+        print!("{prompt}: ");
+        io::stdout().flush()?;
+        set_echo(false);
+    }
+    let mut password = String::new();
+    let result = io::stdin().lock().read_line(&mut password);
+    if terminal {
+        set_echo(true);
+        println!();
+    }
+    result?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Turns the echo of the terminal on or off
+fn set_echo(echo: bool) {
+    let _ = Command::new("stty").arg(if echo { "echo" } else { "-echo" }).status();
+}
+
+/// Reads the password of an image from the keychain; requires the Security framework
+#[cfg(not(target_os = "macos"))]
+fn find_password(_account: &str) -> Option<String> {
+    None
+}
+
+/// Saves the password of an image in the keychain; requires the Security framework
+#[cfg(not(target_os = "macos"))]
+fn save_password(_account: &str, _label: &str, _password: &str) -> Result<(), Box<dyn Error>> {
+    Err("Passwords can only be saved in the keychain on macOS".into())
+}
@@ -0,0 +1,118 @@
+//! Disk image passwords in the login keychain
+//!
+//! The passwords are generic password items of the service
+//! `rempower disk image` with the UUID of the image as account, so they are
+//! found again after the image was moved or renamed. They are passed to the
+//! Security framework directly, never as arguments of a command where other
+//! processes could see them.
+
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::data::{CFData, CFDataRef};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::string::{CFString, CFStringRef};
+use std::error::Error;
+use std::ptr;
+
+#[link(name = "Security", kind = "framework")]
+unsafe extern "C" {
+    static kSecClass: CFStringRef;
+    static kSecClassGenericPassword: CFStringRef;
+    static kSecAttrService: CFStringRef;
+    static kSecAttrAccount: CFStringRef;
+    static kSecAttrLabel: CFStringRef;
+    static kSecValueData: CFStringRef;
+    static kSecReturnData: CFStringRef;
+    static kSecMatchLimit: CFStringRef;
+    static kSecMatchLimitOne: CFStringRef;
+    fn SecItemAdd(attributes: CFDictionaryRef, result: *mut CFTypeRef) -> i32;
+    fn SecItemUpdate(query: CFDictionaryRef, attributes: CFDictionaryRef) -> i32;
+    fn SecItemCopyMatching(query: CFDictionaryRef, result: *mut CFTypeRef) -> i32;
+}
+
+/// Service of the keychain items
+const SERVICE: &str = "rempower disk image";
+
+/// `errSecDuplicateItem`
+const DUPLICATE_ITEM: i32 = -25299;
+
+/// Wraps a constant of the Security framework
+fn key(key: CFStringRef) -> CFString {
+    unsafe { CFString::wrap_under_get_rule(key) }
+}
+
+/// Returns the attributes that identify the item of an image
+fn item(account: &str) -> Vec<(CFString, CFType)> {
+    unsafe {
+        vec![
+            (key(kSecClass), key(kSecClassGenericPassword).as_CFType()),
+            (key(kSecAttrService), CFString::new(SERVICE).as_CFType()),
+            (key(kSecAttrAccount), CFString::new(account).as_CFType()),
+        ]
+    }
+}
+
+/// Reads the password of an image from the keychain
+///
+/// # Arguments
+///
+/// * `account` - UUID of the image
+///
+/// # Returns
+///
+/// `None` if no password is saved or access to it was denied.
+pub fn find_password(account: &str) -> Option<String> {
+    let mut query = item(account);
+    unsafe {
+        query.push((key(kSecReturnData), CFBoolean::true_value().as_CFType()));
+        query.push((key(kSecMatchLimit), key(kSecMatchLimitOne).as_CFType()));
+    }
+    let query = CFDictionary::from_CFType_pairs(&query);
+    let mut result: CFTypeRef = ptr::null();
+    let status = unsafe { SecItemCopyMatching(query.as_concrete_TypeRef(), &mut result) };
+    if status != 0 || result.is_null() {
+        return None;
+    }
+    let data = unsafe { CFData::wrap_under_create_rule(result as CFDataRef) };
+    String::from_utf8(data.bytes().to_vec()).ok()
+}
+
+/// Saves the password of an image in the keychain, replacing a saved one
+///
+/// # Arguments
+///
+/// * `account` - UUID of the image
+/// * `label` - Name of the item shown in Keychain Access
+/// * `password` - Password of the image
+///
+/// # Errors
+///
+/// Returns an error if the keychain refuses the item.
+pub fn save_password(account: &str, label: &str, password: &str) -> Result<(), Box<dyn Error>> {
+    let secret = unsafe {
+        [
+            (key(kSecAttrLabel), CFString::new(label).as_CFType()),
+            (key(kSecValueData), CFData::from_buffer(password.as_bytes()).as_CFType()),
+        ]
+    };
+    let mut attributes = item(account);
+    attributes.extend(secret.iter().cloned());
+    let mut status = unsafe {
+        SecItemAdd(
+            CFDictionary::from_CFType_pairs(&attributes).as_concrete_TypeRef(),
+            ptr::null_mut(),
+        )
+    };
+    if status == DUPLICATE_ITEM {
+        status = unsafe {
+            SecItemUpdate(
+                CFDictionary::from_CFType_pairs(&item(account)).as_concrete_TypeRef(),
+                CFDictionary::from_CFType_pairs(&secret).as_concrete_TypeRef(),
+            )
+        };
+    }
+    if status != 0 {
+        return Err(format!("Failed to save the password in the keychain (error {status})").into());
+    }
+    Ok(())
+}
@@ -1,8 +1,8 @@
 //! DNS configuration subcommand
 //!
 //! Provides functionality to configure DNS servers on macOS network interfaces.
-//! Supports switching between public DNS servers (CloudFlare and Google) and
-//! DHCP-assigned DNS servers.
+//! Supports switching between public DNS servers of well-known providers,
+//! user-specified DNS servers and DHCP-assigned DNS servers.
 //!
 //! # System Requirements
 //!
@@ -14,22 +14,230 @@
 //! - `networksetup -listallnetworkservices` - List network interfaces
 //! - `networksetup -setdnsservers` - Configure DNS (requires sudo)
 //! - `networksetup -getdnsservers` - Get manually configured DNS
+//! - `networksetup -setsearchdomains` - Configure search domains (requires sudo)
+//! - `networksetup -getsearchdomains` - Get manually configured search domains
 //! - `scutil --dns` - Get all DNS configuration including DHCP
+//! - `dscacheutil -flushcache` and `killall -HUP mDNSResponder` - Flush the DNS cache (requires sudo)
+//! - `killall -INFO mDNSResponder` and `log show` - Dump and read the mDNSResponder state (requires sudo)
+//!
+//! On macOS, network services and their DNS settings are read through the
+//! SystemConfiguration framework instead of `networksetup` (see [`backend`]).
+
+pub mod auto;
+pub mod backend;
+pub mod backup;
+pub mod cachestats;
+pub mod client;
+pub mod doh;
+pub mod export;
+pub mod leaktest;
+pub mod preflight;
+pub mod probe;
+pub mod profiles;
+pub mod providers;
+pub mod query;
+pub mod reverse;
+pub mod schedule;
+pub mod split;
 
-use crate::cli::DnsArgs;
+use crate::cli::{DnsArgs, DnsCommands};
+use crate::config::Config;
+use crate::util::{print_dry_run, run_sudo};
+use backend::{CLEAR_VALUE, DnsBackend, backend, networksetup_args};
 use colored::Colorize;
+use providers::Provider;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::process::Command;
+use std::net::IpAddr;
+use std::thread;
+use std::time::Duration;
+
+/// Timeout of the PTR lookups identifying the operator of a DNS server
+const PTR_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Second-level labels that are part of the public suffix below country code TLDs
+const COUNTRY_SECOND_LEVELS: &[&str] = &["ac", "co", "com", "gov", "ne", "net", "or", "org"];
+
+/// Per-network DNS settings managed via `networksetup`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NetworkSetting {
+    /// DNS server addresses
+    DnsServers,
+    /// DNS search domains
+    SearchDomains,
+}
+
+impl NetworkSetting {
+    /// Returns the `networksetup` option that writes this setting
+    fn set_option(self) -> &'static str {
+        match self {
+            NetworkSetting::DnsServers => "-setdnsservers",
+            NetworkSetting::SearchDomains => "-setsearchdomains",
+        }
+    }
+
+    /// Returns the `networksetup` option that reads this setting
+    fn get_option(self) -> &'static str {
+        match self {
+            NetworkSetting::DnsServers => "-getdnsservers",
+            NetworkSetting::SearchDomains => "-getsearchdomains",
+        }
+    }
+
+    /// Returns a human readable name of this setting
+    fn label(self) -> &'static str {
+        match self {
+            NetworkSetting::DnsServers => "DNS servers",
+            NetworkSetting::SearchDomains => "search domains",
+        }
+    }
+}
+
+/// Origin of the DNS servers active on a network interface
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DnsSource {
+    /// Configured manually, e.g. with `rem dns --pub`
+    Manual,
+    /// Assigned by the DHCP server
+    Dhcp,
+}
+
+impl DnsSource {
+    /// Returns the name of this source as used in the list output
+    fn label(self) -> &'static str {
+        match self {
+            DnsSource::Manual => "manual",
+            DnsSource::Dhcp => "dhcp",
+        }
+    }
+}
+
+/// DNS servers active on a network interface
+#[derive(Serialize)]
+struct DnsStatus {
+    /// Name of the network interface
+    interface: String,
+    /// Origin of the DNS servers
+    source: DnsSource,
+    /// Active DNS server addresses
+    servers: Vec<String>,
+    /// Operators of the identified DNS servers, by server address
+    providers: BTreeMap<String, String>,
+}
+
+/// Pattern matching names, e.g. of network interfaces excluded from bulk DNS operations
+///
+/// Patterns enclosed in slashes (`/VPN$/`) are regular expressions, all other
+/// patterns are globs supporting `*` and `?`. Both match case-insensitively.
+enum NamePattern {
+    /// Glob pattern, matched against the whole name
+    Glob(String),
+    /// Regular expression, matched anywhere in the name
+    Regex(Regex),
+}
 
-/// Public DNS servers (CloudFlare and Google with IPv4 and IPv6)
-const PUBLIC_DNS: &[&str] = &["1.1.1.1", "2606:4700:4700::1111", "8.8.4.4", "2001:4860:4860::8844"];
+impl NamePattern {
+    /// Parses a name pattern
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a regular expression is invalid.
+    fn parse(pattern: &str) -> Result<Self, Box<dyn Error>> {
+        match pattern.strip_prefix('/').and_then(|p| p.strip_suffix('/')) {
+            Some(expression) => RegexBuilder::new(expression)
+                .case_insensitive(true)
+                .build()
+                .map(NamePattern::Regex)
+                .map_err(|e| format!("Invalid pattern '{pattern}': {e}").into()),
+            None => Ok(NamePattern::Glob(pattern.to_lowercase())),
+        }
+    }
+
+    /// Returns true if the name matches the pattern
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(glob) => glob_match(glob.as_bytes(), name.to_lowercase().as_bytes()),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Matches text against a glob pattern with `*` (any sequence) and `?` (any character)
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last `*` and the text position it currently covers
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Combines the exclusion patterns of the command line and the configuration file
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or a pattern is invalid.
+fn exclude_patterns(cli_patterns: &[String]) -> Result<Vec<NamePattern>, Box<dyn Error>> {
+    let config = Config::load()?;
+    cli_patterns
+        .iter()
+        .chain(config.dns.exclude.iter())
+        .map(|pattern| NamePattern::parse(pattern))
+        .collect()
+}
+
+/// A resolver of the system DNS configuration, as listed by `scutil --dns`
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    /// Domain the resolver is restricted to, if any
+    domain: Option<String>,
+    /// Addresses of the resolver's nameservers
+    nameservers: Vec<String>,
+    /// Name of the interface the resolver is bound to, if any
+    interface: Option<String>,
+}
 
 /// Performs DNS configuration operations based on the provided arguments.
 ///
-/// This function handles three operations:
-/// - Switching to public DNS servers (CloudFlare and Google)
+/// This function handles the following operations:
+/// - Switching to public DNS servers of a provider (CloudFlare and Google by default)
+/// - Listing the built-in public DNS providers
+/// - Switching to user-specified DNS servers or those of a named profile
+/// - Managing named DNS profiles
+/// - Generating encrypted DNS (DoH/DoT) configuration profiles
+/// - Testing which upstream resolvers answer DNS queries (leak test)
+/// - Looking up DNS records
 /// - Reverting to DHCP-assigned DNS servers
+/// - Restoring the DNS servers backed up before the first change
+/// - Setting and clearing DNS search domains
 /// - Listing currently configured DNS servers
+/// - Flushing the DNS cache
+///
+/// With `--dry-run`, changes are not applied; the commands that would be run
+/// are printed instead.
 ///
 /// # Arguments
 ///
@@ -39,81 +247,445 @@ const PUBLIC_DNS: &[&str] = &["1.1.1.1", "2606:4700:4700::1111", "8.8.4.4", "200
 ///
 /// Returns an error if the DNS configuration update fails or if network commands fail.
 pub fn perform(args: DnsArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(command) = args.command {
+        return match command {
+            DnsCommands::Profiles { command } => profiles::perform(command),
+            DnsCommands::Doh(args) => doh::perform(args),
+            DnsCommands::Leaktest => leaktest::perform(),
+            DnsCommands::Probe => probe::perform(),
+            DnsCommands::CacheStats => cachestats::perform(),
+            DnsCommands::Schedule(args) => schedule::perform(args),
+            DnsCommands::Export => export::export(),
+            DnsCommands::Import(args) => export::import(args),
+            DnsCommands::Auto(args) => auto::perform(args),
+            DnsCommands::Split(args) => split::perform(args),
+            DnsCommands::Query(args) => query::perform(args),
+            DnsCommands::Reverse(args) => reverse::perform(args),
+            DnsCommands::Providers => {
+                providers::print_providers();
+                Ok(())
+            }
+        };
+    }
+
+    if args.flush {
+        return flush_dns_cache(args.dry_run);
+    }
+
+    let backend = backend();
+    if args.restore {
+        return backup::restore(backend, &args.interfaces, args.dry_run);
+    }
+
+    // Resolve names before touching any network configuration
+    let provider = args.pub_dns.as_deref().map(providers::find).transpose()?;
+    let profile_servers = args.profile.as_deref().map(profiles::profile_servers).transpose()?;
+
+    let networks = select_networks(backend, &args.interfaces, &args.excludes)?;
+
+    if let Some(mode) = args.check {
+        let servers: Vec<IpAddr> = match (provider, &args.set, &profile_servers) {
+            (Some(provider), _, _) => provider
+                .servers
+                .iter()
+                .map(|server| server.parse())
+                .collect::<Result<_, _>>()?,
+            (_, Some(servers), _) | (_, _, Some(servers)) => servers.clone(),
+            _ => Vec::new(),
+        };
+        preflight::check(&servers, mode)?;
+    }
+
+    let changes_servers = args.dhcp || args.pub_dns.is_some() || args.set.is_some() || args.profile.is_some();
+    if changes_servers && !args.dry_run {
+        backup::save(backend, &networks)?;
+    }
+
     if args.dhcp {
-        enable_dhcp_dns()?;
-    } else if args.pub_dns {
-        enable_pub_dns()?;
+        enable_dhcp_dns(backend, &networks, args.dry_run)?;
+    } else if let Some(provider) = provider {
+        enable_pub_dns(backend, &networks, provider, args.dry_run)?;
+    } else if let Some(servers) = &args.set {
+        enable_custom_dns(backend, &networks, servers, args.dry_run)?;
+    } else if let Some(servers) = &profile_servers {
+        enable_custom_dns(backend, &networks, servers, args.dry_run)?;
+    } else if let Some(domains) = &args.set_search_domains {
+        set_search_domains(backend, &networks, domains, args.dry_run)?;
+    } else if args.clear_search_domains {
+        clear_search_domains(backend, &networks, args.dry_run)?;
     } else if args.list {
-        print_current_dns()?;
+        if args.json {
+            print_current_dns_json(backend, &networks)?;
+        } else if args.yaml {
+            print_current_dns_yaml(backend, &networks)?;
+        } else {
+            print_current_dns(backend, &networks)?;
+        }
     }
 
     Ok(())
 }
 
-/// Prints current DNS servers for all active network interfaces
+/// Prints current DNS servers for the given network interfaces
 ///
 /// # Errors
 ///
 /// Returns an error if network commands fail or output cannot be parsed.
-fn print_current_dns() -> Result<(), Box<dyn Error>> {
-    let networks = active_networks()?;
+fn print_current_dns(backend: &dyn DnsBackend, networks: &[String]) -> Result<(), Box<dyn Error>> {
+    for status in current_dns_statuses(backend, networks)? {
+        let network = &status.interface;
+        let servers: Vec<String> = status
+            .servers
+            .iter()
+            .map(|server| match status.providers.get(server) {
+                Some(provider) => format!("{server} {}", format!("[{provider}]").cyan()),
+                None => server.clone(),
+            })
+            .collect();
+        println!(
+            "{network:>30} : {} ({})",
+            if servers.is_empty() {
+                "none".to_string()
+            } else {
+                servers.join(", ")
+            },
+            status.source.label()
+        );
+    }
 
-    for network in networks {
-        let dns_servers = current_dns_servers(&network)?;
-        println!("{:>30} : {:?}", network, dns_servers);
+    Ok(())
+}
+
+/// Prints current DNS servers for the given network interfaces as JSON
+///
+/// Writes an array with one record per network interface.
+///
+/// # Errors
+///
+/// Returns an error if network commands fail or output cannot be parsed.
+fn print_current_dns_json(backend: &dyn DnsBackend, networks: &[String]) -> Result<(), Box<dyn Error>> {
+    let statuses = current_dns_statuses(backend, networks)?;
+    println!("{}", serde_json::to_string_pretty(&statuses)?);
+
+    Ok(())
+}
+
+/// Prints current DNS servers for the given network interfaces as YAML
+///
+/// Writes a sequence with one mapping per network interface. Strings are
+/// emitted as double-quoted scalars, so names containing special characters
+/// stay valid YAML.
+///
+/// # Errors
+///
+/// Returns an error if network commands fail or output cannot be parsed.
+fn print_current_dns_yaml(backend: &dyn DnsBackend, networks: &[String]) -> Result<(), Box<dyn Error>> {
+    if networks.is_empty() {
+        println!("[]");
+    }
+
+    for status in current_dns_statuses(backend, networks)? {
+        println!("- interface: {}", serde_json::to_string(&status.interface)?);
+        println!("  source: {}", status.source.label());
+        if status.servers.is_empty() {
+            println!("  servers: []");
+        } else {
+            println!("  servers:");
+            for server in &status.servers {
+                println!("    - {}", serde_json::to_string(server)?);
+            }
+        }
+        if status.providers.is_empty() {
+            println!("  providers: {{}}");
+        } else {
+            println!("  providers:");
+            for (server, provider) in &status.providers {
+                println!(
+                    "    {}: {}",
+                    serde_json::to_string(server)?,
+                    serde_json::to_string(provider)?
+                );
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Flushes the DNS cache
+///
+/// Runs `dscacheutil -flushcache` and signals `mDNSResponder` with `HUP`
+/// so it drops its cached records. Requires sudo privileges.
+///
+/// # Arguments
+///
+/// * `dry_run` - Only print the commands instead of running them
+///
+/// # Errors
+///
+/// Returns an error if one of the commands fails.
+fn flush_dns_cache(dry_run: bool) -> Result<(), Box<dyn Error>> {
+    if dry_run {
+        print_dry_run(&["dscacheutil", "-flushcache"]);
+        print_dry_run(&["killall", "-HUP", "mDNSResponder"]);
+        return Ok(());
+    }
+
+    print!("Flush DNS cache");
+
+    run_sudo(&["dscacheutil", "-flushcache"])?;
+    run_sudo(&["killall", "-HUP", "mDNSResponder"])?;
+
+    println!("{}", " OK".green());
+
+    Ok(())
+}
+
 /// Returns names of active network interfaces
 ///
-/// Uses `networksetup -listallnetworkservices` to get network interfaces,
-/// filtering out disabled interfaces (marked with asterisk).
+/// Disabled network services are filtered out.
+///
+/// # Errors
+///
+/// Returns an error if the network services cannot be determined.
+fn active_networks(backend: &dyn DnsBackend) -> Result<Vec<String>, Box<dyn Error>> {
+    let services = backend.network_services()?;
+    Ok(services
+        .into_iter()
+        .filter(|service| service.enabled)
+        .map(|service| service.name)
+        .collect())
+}
+
+/// Returns names of all network services, including disabled ones
+///
+/// # Errors
+///
+/// Returns an error if the network services cannot be determined.
+fn all_networks(backend: &dyn DnsBackend) -> Result<Vec<String>, Box<dyn Error>> {
+    let services = backend.network_services()?;
+    Ok(services.into_iter().map(|service| service.name).collect())
+}
+
+/// Determines the network services an operation applies to
+///
+/// Combines the `--exclude` patterns with those of the configuration file and
+/// resolves the `--interface` names, see [`target_networks`].
+///
+/// # Arguments
+///
+/// * `backend` - Backend reading and writing the DNS settings
+/// * `interfaces` - Network service names given with `--interface`
+/// * `excludes` - Exclusion patterns given with `--exclude`
+///
+/// # Errors
+///
+/// Returns an error if a pattern is invalid, network commands fail, or a
+/// requested name cannot be resolved.
+pub fn select_networks(
+    backend: &dyn DnsBackend,
+    interfaces: &[String],
+    excludes: &[String],
+) -> Result<Vec<String>, Box<dyn Error>> {
+    target_networks(backend, interfaces, &exclude_patterns(excludes)?)
+}
+
+/// Determines the network interfaces a DNS operation applies to
+///
+/// Without requested names, all active network interfaces are returned, except
+/// those matching one of the exclusion patterns. Otherwise each requested name is
+/// resolved against all network services with [`resolve_network`]; explicitly
+/// requested interfaces are never excluded.
+///
+/// # Arguments
+///
+/// * `backend` - Backend reading and writing the DNS settings
+/// * `requested` - Network service names given with `--interface`
+/// * `excludes` - Exclusion patterns given with `--exclude` and from the configuration file
 ///
 /// # Errors
 ///
-/// Returns an error if the networksetup command fails or output cannot be parsed.
-fn active_networks() -> Result<Vec<String>, Box<dyn Error>> {
-    let output = Command::new("networksetup").arg("-listallnetworkservices").output()?;
+/// Returns an error if network commands fail or a requested name cannot be resolved.
+fn target_networks(
+    backend: &dyn DnsBackend,
+    requested: &[String],
+    excludes: &[NamePattern],
+) -> Result<Vec<String>, Box<dyn Error>> {
+    if requested.is_empty() {
+        let networks = active_networks(backend)?
+            .into_iter()
+            .filter(|network| !excludes.iter().any(|pattern| pattern.matches(network)))
+            .collect();
+        return Ok(networks);
+    }
 
-    let output_str = str::from_utf8(&output.stdout)?.trim();
-    let active_network_services: Vec<String> = output_str
-        .lines()
-        .filter(|line| !line.contains("An asterisk") && !line.contains("(*)"))
-        .map(|line| line.trim().to_string())
+    let available = all_networks(backend)?;
+    let mut networks: Vec<String> = Vec::new();
+    for name in requested {
+        let network = resolve_network(name, &available)?;
+        if !networks.contains(&network) {
+            networks.push(network);
+        }
+    }
+
+    Ok(networks)
+}
+
+/// Resolves a (possibly abbreviated) network service name
+///
+/// Matching is case-insensitive. An exact match wins; otherwise the name must
+/// be a prefix or substring of exactly one network service, e.g. `wi` resolves
+/// to `Wi-Fi`.
+///
+/// # Arguments
+///
+/// * `name` - Requested network service name
+/// * `available` - Names of all network services
+///
+/// # Errors
+///
+/// Returns an error listing the valid names if nothing matches, or the
+/// candidates if the name is ambiguous.
+fn resolve_network(name: &str, available: &[String]) -> Result<String, Box<dyn Error>> {
+    let wanted = name.to_lowercase();
+
+    if let Some(exact) = available.iter().find(|network| network.to_lowercase() == wanted) {
+        return Ok(exact.clone());
+    }
+
+    let prefixed: Vec<&String> = available
+        .iter()
+        .filter(|network| network.to_lowercase().starts_with(&wanted))
         .collect();
+    let candidates = if prefixed.is_empty() {
+        available
+            .iter()
+            .filter(|network| network.to_lowercase().contains(&wanted))
+            .collect()
+    } else {
+        prefixed
+    };
+
+    match candidates.as_slice() {
+        [network] => Ok((*network).clone()),
+        [] => Err(format!(
+            "Unknown network service '{name}'. Valid names are:\n{}",
+            format_names(available.iter())
+        )
+        .into()),
+        _ => Err(format!(
+            "Network service '{name}' is ambiguous. Matching names are:\n{}",
+            format_names(candidates.into_iter())
+        )
+        .into()),
+    }
+}
+
+/// Formats network service names as an indented list, one name per line
+fn format_names<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    names.map(|name| format!("  {name}")).collect::<Vec<_>>().join("\n")
+}
 
-    Ok(active_network_services)
+/// Enables public DNS servers on the given network interfaces
+///
+/// Sets the DNS servers of a provider from the [`providers`] registry.
+///
+/// # Arguments
+///
+/// * `backend` - Backend reading and writing the DNS settings
+/// * `networks` - Names of the network interfaces to configure
+/// * `provider` - The public DNS provider
+/// * `dry_run` - Only print the `networksetup` invocations instead of running them
+///
+/// # Errors
+///
+/// Returns an error if DNS configuration update fails.
+fn enable_pub_dns(
+    backend: &dyn DnsBackend,
+    networks: &[String],
+    provider: &Provider,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let label = format!("public DNS servers of {}", provider.name);
+    enable_dns_servers(backend, networks, provider.servers, &label, dry_run)
 }
 
-/// Enables public DNS servers on all active network interfaces
+/// Enables user-specified DNS servers on the given network interfaces
+///
+/// # Arguments
 ///
-/// Sets CloudFlare (1.1.1.1, 2606:4700:4700::1111) and Google
-/// (8.8.4.4, 2001:4860:4860::8844) DNS servers.
+/// * `backend` - Backend reading and writing the DNS settings
+/// * `networks` - Names of the network interfaces to configure
+/// * `servers` - DNS server addresses to set, validated by the CLI parser
+/// * `dry_run` - Only print the `networksetup` invocations instead of running them
 ///
 /// # Errors
 ///
 /// Returns an error if DNS configuration update fails.
-fn enable_pub_dns() -> Result<(), Box<dyn Error>> {
+fn enable_custom_dns(
+    backend: &dyn DnsBackend,
+    networks: &[String],
+    servers: &[IpAddr],
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let servers: Vec<String> = servers.iter().map(IpAddr::to_string).collect();
+    let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+    enable_dns_servers(backend, networks, &servers, "DNS servers", dry_run)
+}
+
+/// Sets the given DNS servers on the given network interfaces and verifies the result
+///
+/// Validation succeeds if every expected server is configured on the interface.
+/// Addresses are compared as parsed IP addresses, so differently formatted
+/// IPv6 addresses (e.g. compressed vs. expanded) are treated as equal.
+///
+/// # Arguments
+///
+/// * `backend` - Backend reading and writing the DNS settings
+/// * `networks` - Names of the network interfaces to configure
+/// * `servers` - DNS server addresses to set
+/// * `label` - Description of the servers used in the status message
+/// * `dry_run` - Only print the `networksetup` invocations instead of running them
+///
+/// # Errors
+///
+/// Returns an error if DNS configuration update fails.
+fn enable_dns_servers(
+    backend: &dyn DnsBackend,
+    networks: &[String],
+    servers: &[&str],
+    label: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
     apply_dns_config(
-        PUBLIC_DNS,
-        |network| format!("Enable public DNS servers {:?} on device '{}'", PUBLIC_DNS, network),
+        backend,
+        networks,
+        NetworkSetting::DnsServers,
+        servers,
+        dry_run,
+        |network| format!("Enable {label} {servers:?} on device '{network}'"),
         |current_dns| {
-            PUBLIC_DNS
+            let applied = servers
                 .iter()
-                .all(|&public_dns| current_dns.iter().any(|dns| dns == public_dns))
-        },
-        |current_dns| {
-            format!(
-                " Not OK: (Expected all {:?}, but got {:?})",
-                PUBLIC_DNS, current_dns
-            )
+                .all(|&expected| current_dns.iter().any(|dns| same_address(dns, expected)));
+            applied
+                .then_some(())
+                .ok_or_else(|| format!("Not OK: (Expected all {servers:?}, but got {current_dns:?})"))
         },
     )
 }
 
-/// Reverts to DHCP-assigned DNS servers on all active network interfaces
+/// Compares two DNS server addresses
+///
+/// Falls back to a plain string comparison if either value is not a valid IP address.
+fn same_address(a: &str, b: &str) -> bool {
+    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Reverts to DHCP-assigned DNS servers on the given network interfaces
 ///
 /// Clears manually configured DNS settings, allowing the DHCP server to
 /// provide DNS configuration.
@@ -121,185 +693,631 @@ fn enable_pub_dns() -> Result<(), Box<dyn Error>> {
 /// # Errors
 ///
 /// Returns an error if DNS configuration update fails.
-fn enable_dhcp_dns() -> Result<(), Box<dyn Error>> {
+fn enable_dhcp_dns(backend: &dyn DnsBackend, networks: &[String], dry_run: bool) -> Result<(), Box<dyn Error>> {
     apply_dns_config(
-        &["empty"],
-        |network| format!("Revert to DHCP-assigned DNS servers on device '{}' ", network),
+        backend,
+        networks,
+        NetworkSetting::DnsServers,
+        &[CLEAR_VALUE],
+        dry_run,
+        |network| format!("Revert to DHCP-assigned DNS servers on device '{network}' "),
         |current_dns| {
             current_dns
+                .is_empty()
+                .then_some(())
+                .ok_or_else(|| format!("Not OK (DNS servers still defined: {current_dns:?})"))
+        },
+    )
+}
+
+/// Sets DNS search domains on the given network interfaces
+///
+/// Validation succeeds if every requested domain is configured on the interface.
+///
+/// # Arguments
+///
+/// * `backend` - Backend reading and writing the DNS settings
+/// * `networks` - Names of the network interfaces to configure
+/// * `domains` - Search domains to set, validated by the CLI parser
+/// * `dry_run` - Only print the `networksetup` invocations instead of running them
+///
+/// # Errors
+///
+/// Returns an error if the search domain update fails.
+fn set_search_domains(
+    backend: &dyn DnsBackend,
+    networks: &[String],
+    domains: &[String],
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+    apply_dns_config(
+        backend,
+        networks,
+        NetworkSetting::SearchDomains,
+        &domains,
+        dry_run,
+        |network| format!("Set search domains {domains:?} on device '{network}'"),
+        |current| {
+            let applied = domains
                 .iter()
-                .any(|dns| dns.contains("There aren't any DNS Servers set on"))
+                .all(|&expected| current.iter().any(|domain| domain.eq_ignore_ascii_case(expected)));
+            applied
+                .then_some(())
+                .ok_or_else(|| format!("Not OK: (Expected all {domains:?}, but got {current:?})"))
+        },
+    )
+}
+
+/// Clears manually configured DNS search domains on the given network interfaces
+///
+/// # Errors
+///
+/// Returns an error if the search domain update fails.
+fn clear_search_domains(backend: &dyn DnsBackend, networks: &[String], dry_run: bool) -> Result<(), Box<dyn Error>> {
+    apply_dns_config(
+        backend,
+        networks,
+        NetworkSetting::SearchDomains,
+        &[CLEAR_VALUE],
+        dry_run,
+        |network| format!("Clear search domains on device '{network}' "),
+        |current| {
+            current
+                .is_empty()
+                .then_some(())
+                .ok_or_else(|| format!("Not OK (Search domains still defined: {current:?})"))
         },
-        |current_dns| format!(" Not OK (DNS servers still defined: {:?})", current_dns),
     )
 }
 
-/// Helper function to apply DNS configuration to all active networks
+/// Helper function to apply DNS configuration to the given networks
 ///
-/// Provides a reusable pattern for DNS updates with validation.
+/// Provides a reusable pattern for DNS updates with validation. The backend
+/// updates all networks at once, then they are validated concurrently, one
+/// thread per network, and the results are printed as a summary table in the
+/// order of `networks`.
 ///
 /// # Arguments
 ///
-/// * `dns_servers` - DNS server addresses to set (or ["empty"] for DHCP)
+/// * `backend` - Backend reading and writing the DNS settings
+/// * `networks` - Names of the network interfaces to configure
+/// * `setting` - The DNS setting to update
+/// * `values` - Values to set (or [`CLEAR_VALUE`] to clear the manual configuration)
+/// * `dry_run` - Only print the `networksetup` invocations instead of running them
 /// * `format_msg` - Closure to format the status message for each network
-/// * `validate` - Closure to validate DNS configuration was applied correctly
-/// * `error_msg` - Closure to format error message if validation fails
+/// * `validate` - Closure to validate DNS configuration was applied correctly,
+///   returning the error message if it was not
 ///
 /// # Errors
 ///
-/// Returns an error if network commands fail or DNS update fails.
-fn apply_dns_config<F, V, E>(
-    dns_servers: &[&str],
+/// Returns an error if sudo credentials cannot be obtained or the update of any
+/// network fails. All networks are attempted before the error is returned.
+fn apply_dns_config<F, V>(
+    backend: &dyn DnsBackend,
+    networks: &[String],
+    setting: NetworkSetting,
+    values: &[&str],
+    dry_run: bool,
     format_msg: F,
     validate: V,
-    error_msg: E,
 ) -> Result<(), Box<dyn Error>>
 where
     F: Fn(&str) -> String,
-    V: Fn(&[String]) -> bool,
-    E: Fn(&[String]) -> String,
+    V: Fn(&[String]) -> Result<(), String> + Sync,
 {
-    let networks = active_networks()?;
+    if dry_run {
+        for network in networks {
+            print_dry_run(&networksetup_args(network, setting, values));
+        }
+        return Ok(());
+    }
+    if networks.is_empty() {
+        return Ok(());
+    }
 
     for network in networks {
-        print!("{}", format_msg(&network));
+        println!("{}", format_msg(network).trim_end());
+    }
 
-        update_dns_servers(&network, dns_servers)?;
+    let updates = backend.update_settings(networks, setting, values)?;
+    let results: Vec<Result<Result<(), String>, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = networks
+            .iter()
+            .zip(updates)
+            .map(|(network, update)| {
+                let validate = &validate;
+                scope.spawn(move || {
+                    update?;
+                    let current = backend.manual_setting(network, setting).map_err(|e| e.to_string())?;
+                    Ok(validate(&current))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("Validation thread panicked".to_string()))
+            })
+            .collect()
+    });
 
-        let current_dns = manual_dns_of_network(&network)?;
+    let width = networks.iter().map(String::len).max().unwrap_or(0).max("NETWORK".len());
+    println!();
+    println!("{:<width$}  RESULT", "NETWORK");
+    let mut failures = 0;
+    for (network, result) in networks.iter().zip(&results) {
+        let status = match result {
+            Ok(Ok(())) => "OK".green(),
+            Ok(Err(message)) => message.red(),
+            Err(error) => {
+                failures += 1;
+                error.trim().red()
+            }
+        };
+        println!("{network:<width$}  {status}");
+    }
 
-        if validate(&current_dns) {
-            println!("{}", " OK".green());
-        } else {
-            println!("{}", error_msg(&current_dns).red());
-        }
+    if failures > 0 {
+        return Err(format!("Failed to update {failures} of {} network service(s)", networks.len()).into());
     }
 
     Ok(())
 }
 
-/// Updates DNS servers for a specific network interface
-///
-/// Uses `sudo networksetup -setdnsservers` to modify DNS configuration.
-/// Requires sudo privileges.
+/// Gets manually configured DNS servers for a network interface
 ///
 /// # Arguments
 ///
+/// * `backend` - Backend reading and writing the DNS settings
 /// * `network` - Name of the network interface
-/// * `dns_args` - DNS server addresses (or ["empty"] to clear)
+///
+/// # Returns
+///
+/// Vector of DNS server addresses; empty if no DNS servers are set manually.
 ///
 /// # Errors
 ///
-/// Returns an error if the networksetup command fails.
-fn update_dns_servers(network: &str, dns_args: &[&str]) -> Result<(), Box<dyn Error>> {
-    let output = Command::new("sudo")
-        .arg("networksetup")
-        .arg("-setdnsservers")
-        .arg(&network)
-        .args(dns_args)
-        .output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to update DNS servers: {}", stderr).into());
-    }
-
-    Ok(())
+/// Returns an error if the setting cannot be read.
+fn manual_dns_of_network(backend: &dyn DnsBackend, network: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    backend.manual_setting(network, NetworkSetting::DnsServers)
 }
 
-/// Gets manually configured DNS servers for a network interface
-///
-/// Uses `networksetup -getdnsservers` to retrieve DNS configuration.
-///
-/// # Arguments
+/// Gets current DNS servers and their operators for the given network interfaces
 ///
-/// * `network` - Name of the network interface
-///
-/// # Returns
-///
-/// Vector of DNS server addresses, or a message indicating no manual DNS is set.
+/// The operators of all servers are identified at once, so every server is
+/// looked up only once and the PTR lookups run in parallel.
 ///
 /// # Errors
 ///
-/// Returns an error if the networksetup command fails.
-fn manual_dns_of_network(network: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let dns_output = Command::new("networksetup")
-        .arg("-getdnsservers")
-        .arg(&network)
-        .output()?;
+/// Returns an error if system commands fail.
+fn current_dns_statuses(backend: &dyn DnsBackend, networks: &[String]) -> Result<Vec<DnsStatus>, Box<dyn Error>> {
+    let mut statuses = networks
+        .iter()
+        .map(|network| current_dns_status(backend, network))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let dns_vec = str::from_utf8(&dns_output.stdout)?
-        .lines()
-        .map(|line| line.trim().to_string())
+    let mut servers: Vec<&str> = statuses
+        .iter()
+        .flat_map(|status| status.servers.iter().map(String::as_str))
         .collect();
-    Ok(dns_vec)
+    servers.sort_unstable();
+    servers.dedup();
+    let providers = server_providers(&servers);
+
+    for status in &mut statuses {
+        status.providers = status
+            .servers
+            .iter()
+            .filter_map(|server| Some((server.clone(), providers.get(server)?.clone())))
+            .collect();
+    }
+    Ok(statuses)
 }
 
 /// Gets current DNS servers for a network interface
 ///
-/// Checks manually configured DNS first, then falls back to DHCP-assigned
-/// DNS servers from `scutil --dns` if no manual configuration exists.
+/// The operators of the servers are left empty; see [`current_dns_statuses`].
+///
+/// Checks manually configured DNS first, then falls back to the DHCP-assigned
+/// DNS servers of the system resolver configuration if no manual configuration exists.
 ///
 /// # Arguments
 ///
+/// * `backend` - Backend reading and writing the DNS settings
 /// * `network` - Name of the network interface
 ///
 /// # Returns
 ///
-/// Vector of currently active DNS server addresses.
+/// The currently active DNS server addresses and where they come from.
 ///
 /// # Errors
 ///
 /// Returns an error if system commands fail.
-fn current_dns_servers(network: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let dns_result = manual_dns_of_network(network)?;
+fn current_dns_status(backend: &dyn DnsBackend, network: &str) -> Result<DnsStatus, Box<dyn Error>> {
+    let dns_result = manual_dns_of_network(backend, network)?;
 
     // If DNS servers aren't configured manually, check DHCP-DNS
-    if dns_result
-        .iter()
-        .any(|dns| dns.contains("There aren't any DNS Servers set on"))
-    {
-        let scutil_output = Command::new("scutil").arg("--dns").output()?;
-        let scutil_str = str::from_utf8(&scutil_output.stdout)?;
-        let dns_servers = extract_dns_from_scutil(scutil_str)?;
-        if dns_servers.is_empty() {
-            Ok(dns_result)
-        } else {
-            Ok(dns_servers)
+    let (source, servers) = if dns_result.is_empty() {
+        let mut servers: Vec<String> = Vec::new();
+        for nameserver in backend
+            .resolvers()?
+            .into_iter()
+            .flat_map(|resolver| resolver.nameservers)
+        {
+            if !servers.contains(&nameserver) {
+                servers.push(nameserver);
+            }
         }
+        (DnsSource::Dhcp, servers)
     } else {
-        Ok(dns_result)
+        (DnsSource::Manual, dns_result)
+    };
+
+    Ok(DnsStatus {
+        interface: network.to_string(),
+        source,
+        servers,
+        providers: BTreeMap::new(),
+    })
+}
+
+/// Identifies the operators of DNS servers
+///
+/// Known providers and private networks are identified by address. For other
+/// public addresses, the domain of the PTR record names the operator, usually the ISP.
+/// The system resolver is determined once and the PTR lookups run in parallel.
+///
+/// # Returns
+///
+/// Short labels by server address; servers whose operator cannot be determined are missing.
+fn server_providers(servers: &[&str]) -> BTreeMap<String, String> {
+    let mut providers = BTreeMap::new();
+    let mut unknown = Vec::new();
+    for &server in servers {
+        let Some(address) = server
+            .split('%')
+            .next()
+            .and_then(|address| address.parse::<IpAddr>().ok())
+        else {
+            continue;
+        };
+        match providers::identify(address) {
+            Some(provider) => {
+                providers.insert(server.to_string(), provider);
+            }
+            None => unknown.push((server, address)),
+        }
+    }
+
+    if unknown.is_empty() {
+        return providers;
     }
+    let Some(resolver) = system_nameservers().ok().and_then(|servers| servers.first().copied()) else {
+        return providers;
+    };
+
+    thread::scope(|scope| {
+        let lookups: Vec<_> = unknown
+            .iter()
+            .map(|&(server, address)| {
+                let lookup = scope.spawn(move || {
+                    let name = reverse::ptr_names(address, resolver, PTR_TIMEOUT)
+                        .ok()?
+                        .into_iter()
+                        .next()?;
+                    registered_domain(&name)
+                });
+                (server, lookup)
+            })
+            .collect();
+        for (server, lookup) in lookups {
+            if let Ok(Some(provider)) = lookup.join() {
+                providers.insert(server.to_string(), provider);
+            }
+        }
+    });
+    providers
+}
+
+/// Returns the registered domain of a host name, e.g. `bt.co.uk` for `dns1.bt.co.uk`
+///
+/// Second-level labels like `co` below a country code are treated as part of the suffix.
+fn registered_domain(name: &str) -> Option<String> {
+    let labels: Vec<&str> = name.rsplit('.').collect();
+    let length = match labels.as_slice() {
+        [tld, second, _, ..] if tld.len() == 2 && COUNTRY_SECOND_LEVELS.contains(second) => 3,
+        [_, _, ..] => 2,
+        _ => return None,
+    };
+    let mut domain: Vec<&str> = labels[..length].to_vec();
+    domain.reverse();
+    Some(domain.join("."))
 }
 
-/// Extracts DNS server addresses from `scutil --dns` output
+/// Parses the resolver entries of `scutil --dns` output
 ///
-/// Parses the output to find nameserver entries and deduplicate them.
+/// Each `resolver #N` block becomes one [`Resolver`]. Blocks from the
+/// scoped-queries section are included as well; duplicates are kept.
 ///
 /// # Arguments
 ///
 /// * `scutil_output` - Output from `scutil --dns` command
+fn parse_scutil_resolvers(scutil_output: &str) -> Vec<Resolver> {
+    let mut resolvers: Vec<Resolver> = Vec::new();
+
+    for line in scutil_output.lines() {
+        let line = line.trim();
+        if line.starts_with("resolver #") {
+            resolvers.push(Resolver {
+                domain: None,
+                nameservers: Vec::new(),
+                interface: None,
+            });
+            continue;
+        }
+
+        let (Some(resolver), Some((key, value))) = (resolvers.last_mut(), line.split_once(':')) else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "domain" => resolver.domain = Some(value.to_string()),
+            // Interface is given as "15 (en0)"
+            "if_index" => {
+                resolver.interface = value
+                    .split_once('(')
+                    .map(|(_, name)| name.trim_end_matches(')').to_string())
+            }
+            key if key.starts_with("nameserver[") => resolver.nameservers.push(value.to_string()),
+            _ => {}
+        }
+    }
+
+    resolvers
+}
+
+/// Returns the nameservers of the system's default resolvers
 ///
-/// # Returns
-///
-/// Vector of unique DNS server addresses found in the output.
+/// Resolvers restricted to a domain (e.g. mDNS or split-DNS entries) are skipped.
+/// Zone indices of link-local IPv6 addresses (`fe80::1%en0`) are removed.
 ///
 /// # Errors
 ///
-/// Returns an error if parsing fails.
-fn extract_dns_from_scutil(scutil_output: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let mut dns_servers = Vec::new();
+/// Returns an error if the resolver configuration cannot be read.
+pub fn system_nameservers() -> Result<Vec<IpAddr>, Box<dyn Error>> {
+    let mut nameservers: Vec<IpAddr> = Vec::new();
+    for resolver in backend()
+        .resolvers()?
+        .into_iter()
+        .filter(|resolver| resolver.domain.is_none())
+    {
+        for nameserver in resolver.nameservers {
+            let address = nameserver.split('%').next().unwrap_or(&nameserver).parse();
+            if let Ok(address) = address
+                && !nameservers.contains(&address)
+            {
+                nameservers.push(address);
+            }
+        }
+    }
+    Ok(nameservers)
+}
 
-    for line in scutil_output.lines() {
-        if line.trim().starts_with("nameserver[") {
-            if let Some((_, ip_part)) = line.split_once(':') {
-                let ip = ip_part.trim().to_string();
-                if !dns_servers.contains(&ip) {
-                    dns_servers.push(ip);
-                }
+#[cfg(test)]
+mod tests {
+    use super::backend::{CLEAR_VALUE, DnsBackend, NetworkService};
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Backend keeping the DNS settings in memory
+    struct FakeBackend {
+        /// Network services with their enabled state
+        services: Vec<(&'static str, bool)>,
+        /// Manually configured values by network service and setting
+        settings: Mutex<HashMap<(String, NetworkSetting), Vec<String>>>,
+        /// Resolvers of the system DNS configuration
+        resolvers: Vec<Resolver>,
+        /// Network services whose settings cannot be changed
+        failing: Vec<&'static str>,
+    }
+
+    impl FakeBackend {
+        fn new(services: Vec<(&'static str, bool)>) -> Self {
+            FakeBackend {
+                services,
+                settings: Mutex::new(HashMap::new()),
+                resolvers: Vec::new(),
+                failing: Vec::new(),
             }
         }
+
+        fn setting(&self, network: &str, setting: NetworkSetting) -> Vec<String> {
+            self.manual_setting(network, setting).unwrap()
+        }
+    }
+
+    impl DnsBackend for FakeBackend {
+        fn network_services(&self) -> Result<Vec<NetworkService>, Box<dyn Error>> {
+            Ok(self
+                .services
+                .iter()
+                .map(|&(name, enabled)| NetworkService {
+                    name: name.to_string(),
+                    enabled,
+                })
+                .collect())
+        }
+
+        fn manual_setting(&self, network: &str, setting: NetworkSetting) -> Result<Vec<String>, Box<dyn Error>> {
+            let settings = self.settings.lock().unwrap();
+            Ok(settings
+                .get(&(network.to_string(), setting))
+                .cloned()
+                .unwrap_or_default())
+        }
+
+        fn update_settings(
+            &self,
+            networks: &[String],
+            setting: NetworkSetting,
+            values: &[&str],
+        ) -> Result<Vec<Result<(), String>>, Box<dyn Error>> {
+            let mut settings = self.settings.lock().unwrap();
+            Ok(networks
+                .iter()
+                .map(|network| {
+                    if self.failing.contains(&network.as_str()) {
+                        return Err(format!("Cannot change {network}"));
+                    }
+                    let values = if values == [CLEAR_VALUE] {
+                        Vec::new()
+                    } else {
+                        values.iter().map(|value| value.to_string()).collect()
+                    };
+                    settings.insert((network.clone(), setting), values);
+                    Ok(())
+                })
+                .collect())
+        }
+
+        fn resolvers(&self) -> Result<Vec<Resolver>, Box<dyn Error>> {
+            Ok(self.resolvers.clone())
+        }
     }
 
-    Ok(dns_servers)
+    fn names(networks: &[&str]) -> Vec<String> {
+        networks.iter().map(|network| network.to_string()).collect()
+    }
+
+    fn resolver(domain: Option<&str>, nameservers: &[&str]) -> Resolver {
+        Resolver {
+            domain: domain.map(str::to_string),
+            nameservers: names(nameservers),
+            interface: None,
+        }
+    }
+
+    #[test]
+    fn target_networks_skips_disabled_and_excluded_services() {
+        let backend = FakeBackend::new(vec![("Wi-Fi", true), ("Thunderbolt Bridge", true), ("VPN", false)]);
+        let excludes = vec![NamePattern::parse("Thunderbolt*").unwrap()];
+
+        let networks = target_networks(&backend, &[], &excludes).unwrap();
+
+        assert_eq!(networks, names(&["Wi-Fi"]));
+    }
+
+    #[test]
+    fn target_networks_resolves_requested_names() {
+        let backend = FakeBackend::new(vec![("Wi-Fi", true), ("Thunderbolt Bridge", true), ("VPN", false)]);
+        let excludes = vec![NamePattern::parse("Thunderbolt*").unwrap()];
+
+        let networks = target_networks(&backend, &names(&["wi", "thunder", "vpn", "Wi-Fi"]), &excludes).unwrap();
+
+        assert_eq!(networks, names(&["Wi-Fi", "Thunderbolt Bridge", "VPN"]));
+        assert!(target_networks(&backend, &names(&["Ethernet"]), &excludes).is_err());
+    }
+
+    #[test]
+    fn enable_dns_servers_writes_all_networks() {
+        let backend = FakeBackend::new(vec![("Wi-Fi", true), ("Ethernet", true)]);
+        let networks = names(&["Wi-Fi", "Ethernet"]);
+
+        enable_dns_servers(&backend, &networks, &["1.1.1.1", "2606:4700:4700::1111"], "test", false).unwrap();
+
+        for network in &networks {
+            assert_eq!(
+                backend.setting(network, NetworkSetting::DnsServers),
+                names(&["1.1.1.1", "2606:4700:4700::1111"])
+            );
+        }
+    }
+
+    #[test]
+    fn enable_dns_servers_reports_failed_networks() {
+        let mut backend = FakeBackend::new(vec![("Wi-Fi", true), ("Ethernet", true)]);
+        backend.failing.push("Ethernet");
+
+        let result = enable_dns_servers(&backend, &names(&["Wi-Fi", "Ethernet"]), &["9.9.9.9"], "test", false);
+
+        assert!(result.is_err());
+        assert_eq!(
+            backend.setting("Wi-Fi", NetworkSetting::DnsServers),
+            names(&["9.9.9.9"])
+        );
+        assert!(backend.setting("Ethernet", NetworkSetting::DnsServers).is_empty());
+    }
+
+    #[test]
+    fn dry_run_leaves_settings_unchanged() {
+        let backend = FakeBackend::new(vec![("Wi-Fi", true)]);
+        let networks = names(&["Wi-Fi"]);
+
+        enable_dns_servers(&backend, &networks, &["9.9.9.9"], "test", true).unwrap();
+        set_search_domains(&backend, &networks, &names(&["example.com"]), true).unwrap();
+
+        assert!(backend.setting("Wi-Fi", NetworkSetting::DnsServers).is_empty());
+        assert!(backend.setting("Wi-Fi", NetworkSetting::SearchDomains).is_empty());
+    }
+
+    #[test]
+    fn clearing_settings_reverts_to_dhcp() {
+        let backend = FakeBackend::new(vec![("Wi-Fi", true)]);
+        let networks = names(&["Wi-Fi"]);
+
+        set_search_domains(&backend, &networks, &names(&["example.com"]), false).unwrap();
+        enable_dns_servers(&backend, &networks, &["9.9.9.9"], "test", false).unwrap();
+        clear_search_domains(&backend, &networks, false).unwrap();
+        enable_dhcp_dns(&backend, &networks, false).unwrap();
+
+        assert!(backend.setting("Wi-Fi", NetworkSetting::DnsServers).is_empty());
+        assert!(backend.setting("Wi-Fi", NetworkSetting::SearchDomains).is_empty());
+    }
+
+    #[test]
+    fn current_dns_status_prefers_manual_servers() {
+        let mut backend = FakeBackend::new(vec![("Wi-Fi", true)]);
+        backend.resolvers = vec![resolver(None, &["192.168.1.1"])];
+
+        let status = current_dns_status(&backend, "Wi-Fi").unwrap();
+        assert!(matches!(status.source, DnsSource::Dhcp));
+        assert_eq!(status.servers, names(&["192.168.1.1"]));
+
+        enable_dns_servers(&backend, &names(&["Wi-Fi"]), &["9.9.9.9"], "test", false).unwrap();
+        let status = current_dns_status(&backend, "Wi-Fi").unwrap();
+        assert!(matches!(status.source, DnsSource::Manual));
+        assert_eq!(status.servers, names(&["9.9.9.9"]));
+    }
+
+    #[test]
+    fn current_dns_status_merges_resolver_nameservers() {
+        let mut backend = FakeBackend::new(vec![("Wi-Fi", true)]);
+        backend.resolvers = vec![
+            resolver(None, &["192.168.1.1", "fe80::1%en0"]),
+            resolver(None, &["192.168.1.1"]),
+        ];
+
+        let status = current_dns_status(&backend, "Wi-Fi").unwrap();
+
+        assert_eq!(status.servers, names(&["192.168.1.1", "fe80::1%en0"]));
+    }
+
+    #[test]
+    fn parse_scutil_resolvers_reads_blocks() {
+        let output = "DNS configuration\n\n\
+            resolver #1\n  nameserver[0] : 192.168.1.1\n  nameserver[1] : fe80::1%en0\n  if_index : 15 (en0)\n\n\
+            resolver #2\n  domain   : local\n  options  : mdns\n";
+
+        let resolvers = parse_scutil_resolvers(output);
+
+        assert_eq!(resolvers.len(), 2);
+        assert_eq!(resolvers[0].nameservers, names(&["192.168.1.1", "fe80::1%en0"]));
+        assert_eq!(resolvers[0].interface.as_deref(), Some("en0"));
+        assert_eq!(resolvers[1].domain.as_deref(), Some("local"));
+        assert!(resolvers[1].nameservers.is_empty());
+    }
 }
@@ -0,0 +1,292 @@
+//! Automatic DNS rules
+//!
+//! Rules map a condition on the current network to a DNS preset, e.g. filtered
+//! public DNS at home and the DHCP-assigned servers in the office. They are stored
+//! as `[[dns.rules]]` in the configuration file and evaluated in order; the first
+//! matching rule wins. Rules are applied on demand with `rem dns auto` or
+//! continuously with `rem dns auto --watch`.
+//!
+//! # System Commands Used
+//!
+//! - `networksetup -listallhardwareports` - Find the Wi-Fi device
+//! - `ipconfig getsummary` and `networksetup -getairportnetwork` - Get the current SSID
+//! - `networksetup -getcurrentlocation` - Get the current network location
+
+use super::{NamePattern, perform as perform_dns, profiles, providers};
+use crate::cli::{AutoArgs, AutoCommands, DnsArgs};
+use crate::config::{Config, DnsRule};
+use crate::subcommands::wifi;
+use clap::Parser;
+use colored::Colorize;
+use std::error::Error;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Property of the current network a rule condition refers to
+#[derive(Clone, Copy)]
+enum Property {
+    /// Name of the joined Wi-Fi network
+    Ssid,
+    /// Name of the active network location
+    Location,
+}
+
+/// Parsed condition of a rule
+struct Condition {
+    /// Property the condition checks
+    property: Property,
+    /// Pattern the property value must match
+    pattern: NamePattern,
+}
+
+impl Condition {
+    /// Parses a condition of the form `ssid=<PATTERN>` or `location=<PATTERN>`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the property is unknown or the pattern is invalid.
+    fn parse(condition: &str) -> Result<Self, Box<dyn Error>> {
+        let (property, pattern) = condition
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid condition '{condition}', expected ssid=<NAME> or location=<NAME>"))?;
+        let property = match property.trim().to_lowercase().as_str() {
+            "ssid" => Property::Ssid,
+            "location" => Property::Location,
+            other => return Err(format!("Unknown condition '{other}', expected ssid or location").into()),
+        };
+        Ok(Condition {
+            property,
+            pattern: NamePattern::parse(pattern.trim())?,
+        })
+    }
+
+    /// Returns true if the network state satisfies the condition
+    fn matches(&self, state: &NetworkState) -> bool {
+        let value = match self.property {
+            Property::Ssid => state.ssid.as_deref(),
+            Property::Location => state.location.as_deref(),
+        };
+        value.is_some_and(|value| self.pattern.matches(value))
+    }
+}
+
+/// Properties of the current network that rules can refer to
+#[derive(PartialEq, Eq)]
+struct NetworkState {
+    /// Name of the joined Wi-Fi network, if any
+    ssid: Option<String>,
+    /// Name of the active network location
+    location: Option<String>,
+}
+
+impl NetworkState {
+    /// Determines the current network state
+    fn current() -> Self {
+        NetworkState {
+            ssid: current_ssid(),
+            location: current_location(),
+        }
+    }
+
+    /// Describes the state for status messages
+    fn describe(&self) -> String {
+        format!(
+            "ssid={}, location={}",
+            self.ssid.as_deref().unwrap_or("-"),
+            self.location.as_deref().unwrap_or("-")
+        )
+    }
+}
+
+/// Performs automatic DNS rule operations
+///
+/// # Arguments
+///
+/// * `args` - Rule arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be read or written, a rule is
+/// invalid, or applying a preset fails (except in watch mode).
+pub fn perform(args: AutoArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Some(AutoCommands::List) => list_rules(),
+        Some(AutoCommands::Remove { number }) => remove_rule(number),
+        None => match (args.when, args.preset) {
+            (Some(when), Some(preset)) => add_rule(when, preset),
+            _ if args.watch => watch(Duration::from_secs(args.interval.max(1)), args.dry_run),
+            _ => apply_matching_rule(&NetworkState::current(), args.dry_run),
+        },
+    }
+}
+
+/// Validates a rule and appends it to the configuration
+///
+/// # Errors
+///
+/// Returns an error if the condition or preset is invalid or the configuration
+/// cannot be written.
+fn add_rule(when: String, preset: String) -> Result<(), Box<dyn Error>> {
+    Condition::parse(&when)?;
+    preset_args(&preset)?;
+
+    let mut config = Config::load()?;
+    println!("Add rule {}: when {when} use {preset}", config.dns.rules.len() + 1);
+    config.dns.rules.push(DnsRule { when, preset });
+    config.save(&["dns", "rules"])
+}
+
+/// Prints all rules with their numbers
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be read.
+fn list_rules() -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+    if config.dns.rules.is_empty() {
+        println!("No DNS rules configured");
+        return Ok(());
+    }
+
+    for (index, rule) in config.dns.rules.iter().enumerate() {
+        println!("{:>3}  when {:<32} use {}", index + 1, rule.when, rule.preset);
+    }
+
+    Ok(())
+}
+
+/// Removes a rule by its number
+///
+/// # Errors
+///
+/// Returns an error if no rule has this number or the configuration cannot be written.
+fn remove_rule(number: usize) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load()?;
+    if number == 0 || number > config.dns.rules.len() {
+        return Err(format!("No DNS rule {number} (rules: {})", config.dns.rules.len()).into());
+    }
+
+    let rule = config.dns.rules.remove(number - 1);
+    println!("Remove rule {number}: when {} use {}", rule.when, rule.preset);
+    config.save(&["dns", "rules"])
+}
+
+/// Applies the preset of the first rule matching the network state
+///
+/// # Errors
+///
+/// Returns an error if a rule is invalid or applying the preset fails.
+fn apply_matching_rule(state: &NetworkState, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+
+    for (index, rule) in config.dns.rules.iter().enumerate() {
+        if Condition::parse(&rule.when)?.matches(state) {
+            println!(
+                "Rule {} matches ({}): use {}",
+                index + 1,
+                state.describe(),
+                rule.preset.bold()
+            );
+            return apply_preset(&rule.preset, dry_run);
+        }
+    }
+
+    println!("No DNS rule matches ({})", state.describe());
+    Ok(())
+}
+
+/// Applies the matching rule on every change of the network state
+///
+/// Errors of single applications are reported and do not stop the watcher.
+///
+/// # Errors
+///
+/// Never returns under normal operation.
+fn watch(interval: Duration, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    println!(
+        "Watching network changes every {} s (Ctrl-C to stop)",
+        interval.as_secs()
+    );
+    let mut last: Option<NetworkState> = None;
+
+    loop {
+        let state = NetworkState::current();
+        if last.as_ref() != Some(&state) {
+            if let Err(e) = apply_matching_rule(&state, dry_run) {
+                eprintln!("{}", format!("Failed to apply DNS rule: {e}").red());
+            }
+            last = Some(state);
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Applies a DNS preset through the regular `rem dns` options
+///
+/// # Arguments
+///
+/// * `preset` - `pub`, `pub:<PROVIDER>`, `profile:<NAME>` or `dhcp`
+/// * `dry_run` - Only print the commands that would be run
+///
+/// # Errors
+///
+/// Returns an error if the preset is invalid or the DNS update fails.
+pub fn apply_preset(preset: &str, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let mut args = preset_args(preset)?;
+    if dry_run {
+        args.push("--dry-run".to_string());
+    }
+    let args = DnsArgs::try_parse_from(std::iter::once("rem dns".to_string()).chain(args))?;
+    perform_dns(args)
+}
+
+/// Translates a preset into `rem dns` options
+///
+/// # Errors
+///
+/// Returns an error if the preset is unknown or refers to an unknown provider or profile.
+pub fn preset_args(preset: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let (kind, name) = match preset.split_once(':') {
+        Some((kind, name)) => (kind, Some(name)),
+        None => (preset, None),
+    };
+
+    match (kind, name) {
+        ("dhcp", None) => Ok(vec!["--dhcp".to_string()]),
+        ("pub", None) => Ok(vec!["--pub".to_string()]),
+        ("pub", Some(provider)) => {
+            providers::find(provider)?;
+            Ok(vec!["--pub".to_string(), provider.to_string()])
+        }
+        ("profile", Some(profile)) => {
+            profiles::profile_servers(profile)?;
+            Ok(vec!["--profile".to_string(), profile.to_string()])
+        }
+        _ => Err(format!("Invalid preset '{preset}', expected pub, pub:<PROVIDER>, profile:<NAME> or dhcp").into()),
+    }
+}
+
+/// Returns the SSID of the joined Wi-Fi network
+///
+/// Uses `ipconfig getsummary`, falling back to `networksetup -getairportnetwork`.
+fn current_ssid() -> Option<String> {
+    let device = wifi::wifi_device().ok()?;
+
+    if let Ok(output) = Command::new("ipconfig").args(["getsummary", &device]).output()
+        && let Some(ssid) = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("SSID : "))
+    {
+        return Some(ssid.trim().to_string());
+    }
+
+    wifi::current_network(&device)
+}
+
+/// Returns the name of the active network location
+pub fn current_location() -> Option<String> {
+    let output = Command::new("networksetup").arg("-getcurrentlocation").output().ok()?;
+    let location = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !location.is_empty()).then_some(location)
+}
@@ -0,0 +1,229 @@
+//! Access to the DNS settings of network services
+//!
+//! All reads and writes of per-service DNS settings and the resolver configuration
+//! go through the [`DnsBackend`] trait. Two implementations exist:
+//!
+//! - [`CliBackend`] runs `networksetup` and `scutil --dns`, using `sudo` for changes
+//! - `NativeBackend` (macOS only) uses the SystemConfiguration framework directly
+//!   and falls back to [`CliBackend`] for changes if it lacks the privileges
+//!
+//! The DNS functions take the backend as an argument, so tests can pass a fake one.
+//! Commands use the backend chosen once per process by [`backend`]. The environment
+//! variable `REMPOWER_DNS_BACKEND` (`native` or `cli`) overrides the automatic selection.
+
+#[cfg(target_os = "macos")]
+mod native;
+
+use super::{NetworkSetting, Resolver, parse_scutil_resolvers};
+use crate::util::run_sudo;
+use std::env;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::thread;
+
+/// Environment variable overriding the backend selection
+const BACKEND_VAR: &str = "REMPOWER_DNS_BACKEND";
+
+/// Value passed to `networksetup` to clear a setting
+pub const CLEAR_VALUE: &str = "empty";
+
+/// Message printed by `networksetup -getdnsservers` if no DNS servers are set manually
+const NO_DNS_SERVERS: &str = "There aren't any DNS Servers set on";
+
+/// Message printed by `networksetup -getsearchdomains` if no search domains are set manually
+const NO_SEARCH_DOMAINS: &str = "There aren't any Search Domains set on";
+
+/// A network service as listed by `networksetup -listallnetworkservices`
+pub struct NetworkService {
+    /// User-visible name of the service, e.g. `Wi-Fi`
+    pub name: String,
+    /// False if the service is disabled
+    pub enabled: bool,
+}
+
+/// Reads and writes the DNS settings of network services
+pub trait DnsBackend: Sync {
+    /// Returns all network services of the current location in service order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the network services cannot be determined.
+    fn network_services(&self) -> Result<Vec<NetworkService>, Box<dyn Error>>;
+
+    /// Returns the manually configured values of a DNS setting
+    ///
+    /// The result is empty if nothing is configured manually.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the setting cannot be read.
+    fn manual_setting(&self, network: &str, setting: NetworkSetting) -> Result<Vec<String>, Box<dyn Error>>;
+
+    /// Replaces the manually configured values of a DNS setting on several network services
+    ///
+    /// # Arguments
+    ///
+    /// * `networks` - Names of the network services
+    /// * `setting` - The DNS setting to update
+    /// * `values` - New values (or [`CLEAR_VALUE`] to clear)
+    ///
+    /// # Returns
+    ///
+    /// One result per network service in the order of `networks`, with the
+    /// reason if the setting of that service could not be changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no setting can be changed at all, e.g. because
+    /// `sudo` did not get the password.
+    fn update_settings(
+        &self,
+        networks: &[String],
+        setting: NetworkSetting,
+        values: &[&str],
+    ) -> Result<Vec<Result<(), String>>, Box<dyn Error>>;
+
+    /// Returns the resolvers of the system DNS configuration, the default resolver first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the resolver configuration cannot be read.
+    fn resolvers(&self) -> Result<Vec<Resolver>, Box<dyn Error>>;
+}
+
+/// Returns the backend used by this process
+///
+/// The SystemConfiguration backend is preferred on macOS if the network
+/// preferences can be opened; otherwise `networksetup` is used.
+pub fn backend() -> &'static dyn DnsBackend {
+    static BACKEND: OnceLock<&'static dyn DnsBackend> = OnceLock::new();
+    *BACKEND.get_or_init(select_backend)
+}
+
+/// Selects the backend based on `REMPOWER_DNS_BACKEND` and the platform
+fn select_backend() -> &'static dyn DnsBackend {
+    if env::var(BACKEND_VAR).is_ok_and(|value| value.eq_ignore_ascii_case("cli")) {
+        return &CliBackend;
+    }
+
+    #[cfg(target_os = "macos")]
+    if native::NativeBackend::available() {
+        return &native::NativeBackend;
+    }
+
+    &CliBackend
+}
+
+/// Backend running the `networksetup` command line tool
+pub struct CliBackend;
+
+impl DnsBackend for CliBackend {
+    /// Uses `networksetup -listallnetworkservices`, removing the header line and
+    /// the asterisk that marks disabled services.
+    fn network_services(&self) -> Result<Vec<NetworkService>, Box<dyn Error>> {
+        let output = Command::new("networksetup").arg("-listallnetworkservices").output()?;
+
+        let services = str::from_utf8(&output.stdout)?
+            .trim()
+            .lines()
+            .filter(|line| !line.contains("An asterisk"))
+            .map(|line| {
+                let line = line.trim();
+                NetworkService {
+                    name: line.trim_start_matches('*').to_string(),
+                    enabled: !line.starts_with('*'),
+                }
+            })
+            .collect();
+
+        Ok(services)
+    }
+
+    /// Uses `networksetup -getdnsservers` or `-getsearchdomains`, dropping the
+    /// message printed if nothing is configured manually.
+    fn manual_setting(&self, network: &str, setting: NetworkSetting) -> Result<Vec<String>, Box<dyn Error>> {
+        let output = Command::new("networksetup")
+            .arg(setting.get_option())
+            .arg(network)
+            .output()?;
+
+        let values = str::from_utf8(&output.stdout)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.contains(NO_DNS_SERVERS) && !line.contains(NO_SEARCH_DOMAINS))
+            .map(str::to_string)
+            .collect();
+        Ok(values)
+    }
+
+    /// Runs `sudo networksetup -setdnsservers` or `-setsearchdomains` concurrently,
+    /// one thread per network service.
+    fn update_settings(
+        &self,
+        networks: &[String],
+        setting: NetworkSetting,
+        values: &[&str],
+    ) -> Result<Vec<Result<(), String>>, Box<dyn Error>> {
+        // Ask for the password once instead of letting concurrent sudo invocations prompt.
+        // Without a terminal (e.g. scheduled runs) sudo cannot prompt anyway.
+        if io::stdin().is_terminal() {
+            run_sudo(&["-v"])?;
+        }
+
+        let results = thread::scope(|scope| {
+            let handles: Vec<_> = networks
+                .iter()
+                .map(|network| scope.spawn(move || run_networksetup(network, setting, values)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| Err("Update thread panicked".to_string()))
+                })
+                .collect()
+        });
+        Ok(results)
+    }
+
+    /// Uses `scutil --dns`.
+    fn resolvers(&self) -> Result<Vec<Resolver>, Box<dyn Error>> {
+        let output = Command::new("scutil").arg("--dns").output()?;
+        Ok(parse_scutil_resolvers(str::from_utf8(&output.stdout)?))
+    }
+}
+
+/// Updates a DNS setting of a network service with `sudo networksetup`
+///
+/// # Errors
+///
+/// Returns the reason if `networksetup` cannot be run or fails.
+fn run_networksetup(network: &str, setting: NetworkSetting, values: &[&str]) -> Result<(), String> {
+    let output = Command::new("sudo")
+        .args(networksetup_args(network, setting, values))
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to update {}: {}", setting.label(), stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Builds the privileged `networksetup` command line that updates a DNS setting
+///
+/// # Arguments
+///
+/// * `network` - Name of the network interface
+/// * `setting` - The DNS setting to update
+/// * `values` - New values (or [`CLEAR_VALUE`] to clear)
+pub fn networksetup_args<'a>(network: &'a str, setting: NetworkSetting, values: &[&'a str]) -> Vec<&'a str> {
+    let mut args = vec!["networksetup", setting.set_option(), network];
+    args.extend_from_slice(values);
+    args
+}
@@ -0,0 +1,376 @@
+//! SystemConfiguration backend
+//!
+//! Reads the DNS settings of network services from the network preferences
+//! (`SCPreferences`), the same store `networksetup` works on, and the resolver
+//! configuration from the dynamic store (`SCDynamicStore`) `scutil --dns` is
+//! built from. Writing requires
+//! the preferences lock, which is only granted to root; without it, changes are
+//! delegated to [`CliBackend`] and thereby to `sudo networksetup`. The lock is
+//! taken once for all network services of a change, which are then committed
+//! together, so concurrent writers never compete for it.
+
+use super::{CLEAR_VALUE, CliBackend, DnsBackend, NetworkService, NetworkSetting, Resolver};
+use core_foundation::array::CFArray;
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFMutableDictionary};
+use core_foundation::string::CFString;
+use std::error::Error;
+use std::ffi::{CStr, c_char, c_int};
+use std::ptr;
+use system_configuration::dynamic_store::{SCDynamicStore, SCDynamicStoreBuilder};
+use system_configuration::network_configuration::{SCNetworkService, SCNetworkSet};
+use system_configuration::preferences::SCPreferences;
+use system_configuration::sys::network_configuration::{
+    SCNetworkProtocolGetConfiguration, SCNetworkProtocolRef, SCNetworkProtocolSetConfiguration,
+    SCNetworkServiceAddProtocolType, SCNetworkServiceCopyProtocol, SCNetworkServiceGetEnabled, SCNetworkServiceGetName,
+    SCNetworkSetCopyServices,
+};
+use system_configuration::sys::preferences::{
+    SCPreferencesApplyChanges, SCPreferencesCommitChanges, SCPreferencesCreate, SCPreferencesLock, SCPreferencesUnlock,
+};
+use system_configuration::sys::schema_definitions::{kSCPropNetDNSSearchDomains, kSCPropNetDNSServerAddresses};
+
+/// Name reported to SystemConfiguration as the accessing process
+const PROCESS_NAME: &str = "rempower";
+
+/// Protocol type of the DNS settings of a network service (`kSCNetworkProtocolTypeDNS`)
+const PROTOCOL_TYPE_DNS: &str = "DNS";
+
+/// Dynamic store key of the DNS configuration of the primary service
+const GLOBAL_DNS_KEY: &str = "State:/Network/Global/DNS";
+
+/// Dynamic store key of the IPv4 configuration of the primary service
+const GLOBAL_IPV4_KEY: &str = "State:/Network/Global/IPv4";
+
+/// Pattern of the dynamic store keys of the DNS configuration of each service
+const SERVICE_DNS_PATTERN: &str = "State:/Network/Service/[^/]+/DNS";
+
+#[link(name = "SystemConfiguration", kind = "framework")]
+unsafe extern "C" {
+    fn SCError() -> c_int;
+    fn SCErrorString(status: c_int) -> *const c_char;
+}
+
+/// Backend using the SystemConfiguration framework
+pub struct NativeBackend;
+
+impl NativeBackend {
+    /// Returns true if the network preferences can be opened
+    pub fn available() -> bool {
+        open_preferences().is_ok()
+    }
+}
+
+impl DnsBackend for NativeBackend {
+    fn network_services(&self) -> Result<Vec<NetworkService>, Box<dyn Error>> {
+        let prefs = open_preferences()?;
+        let services = current_services(&prefs)?
+            .iter()
+            .map(|service| NetworkService {
+                name: service_name(service),
+                enabled: unsafe { SCNetworkServiceGetEnabled(service.as_concrete_TypeRef()) != 0 },
+            })
+            .collect();
+        Ok(services)
+    }
+
+    fn manual_setting(&self, network: &str, setting: NetworkSetting) -> Result<Vec<String>, Box<dyn Error>> {
+        let prefs = open_preferences()?;
+        let service = find_service(&prefs, network)?;
+
+        let Some(protocol) = dns_protocol(&service) else {
+            return Ok(Vec::new());
+        };
+
+        let config = unsafe { SCNetworkProtocolGetConfiguration(protocol.as_CFTypeRef() as SCNetworkProtocolRef) };
+        if config.is_null() {
+            return Ok(Vec::new());
+        }
+        let config: CFDictionary<CFString, CFType> = unsafe { CFDictionary::wrap_under_get_rule(config) };
+
+        let values = config
+            .find(setting_key(setting))
+            .map(|value| strings(&value))
+            .unwrap_or_default();
+        Ok(values)
+    }
+
+    fn update_settings(
+        &self,
+        networks: &[String],
+        setting: NetworkSetting,
+        values: &[&str],
+    ) -> Result<Vec<Result<(), String>>, Box<dyn Error>> {
+        let prefs = open_preferences()?;
+        let prefs_ref = prefs.as_concrete_TypeRef();
+
+        if unsafe { SCPreferencesLock(prefs_ref, 0) } == 0 {
+            // Only root may lock the network preferences
+            return CliBackend.update_settings(networks, setting, values);
+        }
+        let mut results: Vec<Result<(), String>> = networks
+            .iter()
+            .map(|network| {
+                write_setting(&prefs, network, setting, values)
+                    .map_err(|e| format!("Failed to update {}: {e}", setting.label()))
+            })
+            .collect();
+        let applied =
+            unsafe { SCPreferencesCommitChanges(prefs_ref) != 0 && SCPreferencesApplyChanges(prefs_ref) != 0 };
+        if !applied {
+            let error = format!("Failed to update {}: {}", setting.label(), last_error());
+            for result in results.iter_mut().filter(|result| result.is_ok()) {
+                *result = Err(error.clone());
+            }
+        }
+        unsafe { SCPreferencesUnlock(prefs_ref) };
+
+        Ok(results)
+    }
+
+    /// Reads the global DNS configuration as default resolver, followed by the
+    /// DNS configuration of every service: services with supplemental match
+    /// domains yield one resolver per domain, like split DNS of VPNs.
+    fn resolvers(&self) -> Result<Vec<Resolver>, Box<dyn Error>> {
+        let store = SCDynamicStoreBuilder::new(PROCESS_NAME).build();
+        let mut resolvers = Vec::new();
+
+        if let Some(global) = store_dictionary(&store, GLOBAL_DNS_KEY) {
+            resolvers.push(Resolver {
+                domain: None,
+                nameservers: string_values(&global, "ServerAddresses"),
+                interface: store_dictionary(&store, GLOBAL_IPV4_KEY)
+                    .and_then(|ipv4| string_value(&ipv4, "PrimaryInterface")),
+            });
+        }
+
+        let keys = store.get_keys(SERVICE_DNS_PATTERN).ok_or_else(last_error)?;
+        for key in keys.iter() {
+            let key = key.to_string();
+            let Some(dns) = store_dictionary(&store, &key) else {
+                continue;
+            };
+            let nameservers = string_values(&dns, "ServerAddresses");
+            if nameservers.is_empty() {
+                continue;
+            }
+            // The interface is part of the IPv4 or IPv6 configuration of the service
+            let service = key.trim_end_matches("/DNS");
+            let interface = string_value(&dns, "InterfaceName").or_else(|| {
+                ["IPv4", "IPv6"].iter().find_map(|protocol| {
+                    store_dictionary(&store, &format!("{service}/{protocol}"))
+                        .and_then(|config| string_value(&config, "InterfaceName"))
+                })
+            });
+
+            let domains = string_values(&dns, "SupplementalMatchDomains");
+            if domains.is_empty() {
+                resolvers.push(Resolver {
+                    domain: None,
+                    nameservers,
+                    interface,
+                });
+            } else {
+                for domain in domains {
+                    resolvers.push(Resolver {
+                        domain: Some(domain),
+                        nameservers: nameservers.clone(),
+                        interface: interface.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(resolvers)
+    }
+}
+
+/// Returns a dictionary of the dynamic store, if the key exists
+fn store_dictionary(store: &SCDynamicStore, key: &str) -> Option<CFDictionary<CFString, CFType>> {
+    let dictionary = store.get(key)?.downcast_into::<CFDictionary>()?;
+    Some(unsafe { CFDictionary::wrap_under_get_rule(dictionary.as_concrete_TypeRef()) })
+}
+
+/// Returns a string of a dictionary
+fn string_value(dictionary: &CFDictionary<CFString, CFType>, key: &'static str) -> Option<String> {
+    dictionary
+        .find(CFString::from_static_string(key))
+        .and_then(|value| value.downcast::<CFString>())
+        .map(|value| value.to_string())
+}
+
+/// Returns the strings of an array of a dictionary; empty if the key is missing
+fn string_values(dictionary: &CFDictionary<CFString, CFType>, key: &'static str) -> Vec<String> {
+    dictionary
+        .find(CFString::from_static_string(key))
+        .map(|value| strings(&value))
+        .unwrap_or_default()
+}
+
+/// Returns the strings of an array; empty if the value is not an array
+fn strings(value: &CFType) -> Vec<String> {
+    value
+        .downcast::<CFArray>()
+        .map(|array| {
+            array
+                .iter()
+                .map(|item| unsafe { CFType::wrap_under_get_rule(*item) })
+                .filter_map(|item| item.downcast::<CFString>())
+                .map(|item| item.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Writes a DNS setting to the locked network preferences
+///
+/// The change takes effect once the preferences are committed and applied.
+/// # Errors
+///
+/// Returns an error if the service does not exist or SystemConfiguration rejects the change.
+fn write_setting(
+    prefs: &SCPreferences,
+    network: &str,
+    setting: NetworkSetting,
+    values: &[&str],
+) -> Result<(), Box<dyn Error>> {
+    let service = find_service(prefs, network)?;
+
+    let protocol = match dns_protocol(&service) {
+        Some(protocol) => protocol,
+        None => {
+            let protocol_type = CFString::from_static_string(PROTOCOL_TYPE_DNS);
+            let added = unsafe {
+                SCNetworkServiceAddProtocolType(service.as_concrete_TypeRef(), protocol_type.as_concrete_TypeRef())
+            };
+            if added == 0 {
+                return Err(last_error().into());
+            }
+            dns_protocol(&service).ok_or_else(last_error)?
+        }
+    };
+    let protocol_ref = protocol.as_CFTypeRef() as SCNetworkProtocolRef;
+
+    let config = unsafe { SCNetworkProtocolGetConfiguration(protocol_ref) };
+    let mut config: CFMutableDictionary<CFString, CFType> = if config.is_null() {
+        CFMutableDictionary::new()
+    } else {
+        let config: CFDictionary<CFString, CFType> = unsafe { CFDictionary::wrap_under_get_rule(config) };
+        CFMutableDictionary::from(&config)
+    };
+
+    let key = setting_key(setting);
+    if values == [CLEAR_VALUE] {
+        config.remove(key);
+    } else {
+        let values: Vec<CFString> = values.iter().map(|value| CFString::new(value)).collect();
+        config.set(key, CFArray::from_CFTypes(&values).as_CFType());
+    }
+
+    let updated =
+        unsafe { SCNetworkProtocolSetConfiguration(protocol_ref, config.to_immutable().as_concrete_TypeRef()) };
+    if updated == 0 {
+        return Err(last_error().into());
+    }
+
+    Ok(())
+}
+
+/// Opens the network preferences
+///
+/// # Errors
+///
+/// Returns an error if SystemConfiguration denies access.
+fn open_preferences() -> Result<SCPreferences, Box<dyn Error>> {
+    let name = CFString::new(PROCESS_NAME);
+    let prefs = unsafe { SCPreferencesCreate(ptr::null(), name.as_concrete_TypeRef(), ptr::null()) };
+    if prefs.is_null() {
+        return Err(format!("Failed to open network preferences: {}", last_error()).into());
+    }
+    Ok(unsafe { SCPreferences::wrap_under_create_rule(prefs) })
+}
+
+/// Returns the network services of the current location in service order
+///
+/// # Errors
+///
+/// Returns an error if no current location exists.
+fn current_services(prefs: &SCPreferences) -> Result<Vec<SCNetworkService>, Box<dyn Error>> {
+    let set = SCNetworkSet::new(prefs);
+    let services = unsafe { SCNetworkSetCopyServices(set.as_concrete_TypeRef()) };
+    if services.is_null() {
+        return Err(format!("Failed to read network services: {}", last_error()).into());
+    }
+    let services: CFArray<SCNetworkService> = unsafe { CFArray::wrap_under_create_rule(services) };
+
+    let order: Vec<String> = set.service_order().iter().map(|id| id.to_string()).collect();
+    let position = |service: &SCNetworkService| {
+        service
+            .id()
+            .and_then(|id| {
+                let id = id.to_string();
+                order.iter().position(|known| *known == id)
+            })
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut services: Vec<SCNetworkService> = services.iter().map(|service| service.clone()).collect();
+    services.sort_by_key(position);
+    Ok(services)
+}
+
+/// Finds a network service of the current location by its name
+///
+/// # Errors
+///
+/// Returns an error if no service has this name.
+fn find_service(prefs: &SCPreferences, network: &str) -> Result<SCNetworkService, Box<dyn Error>> {
+    current_services(prefs)?
+        .into_iter()
+        .find(|service| service_name(service) == network)
+        .ok_or_else(|| format!("Unknown network service '{network}'").into())
+}
+
+/// Returns the user-visible name of a network service
+fn service_name(service: &SCNetworkService) -> String {
+    let name = unsafe { SCNetworkServiceGetName(service.as_concrete_TypeRef()) };
+    if name.is_null() {
+        String::new()
+    } else {
+        unsafe { CFString::wrap_under_get_rule(name) }.to_string()
+    }
+}
+
+/// Returns the key of a DNS setting in the protocol configuration
+fn setting_key(setting: NetworkSetting) -> CFString {
+    let key = match setting {
+        NetworkSetting::DnsServers => unsafe { kSCPropNetDNSServerAddresses },
+        NetworkSetting::SearchDomains => unsafe { kSCPropNetDNSSearchDomains },
+    };
+    unsafe { CFString::wrap_under_get_rule(key) }
+}
+
+/// Returns the DNS protocol of a network service, if it has one
+fn dns_protocol(service: &SCNetworkService) -> Option<CFType> {
+    let protocol_type = CFString::from_static_string(PROTOCOL_TYPE_DNS);
+    let protocol =
+        unsafe { SCNetworkServiceCopyProtocol(service.as_concrete_TypeRef(), protocol_type.as_concrete_TypeRef()) };
+    if protocol.is_null() {
+        None
+    } else {
+        Some(unsafe { CFType::wrap_under_create_rule(protocol.cast()) })
+    }
+}
+
+/// Returns the description of the last SystemConfiguration error
+fn last_error() -> String {
+    unsafe {
+        let status = SCError();
+        let message = SCErrorString(status);
+        if message.is_null() {
+            format!("error {status}")
+        } else {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        }
+    }
+}
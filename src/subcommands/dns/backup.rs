@@ -0,0 +1,153 @@
+//! Backup and restore of DNS server settings
+//!
+//! Before DNS servers are changed, the manually configured DNS servers of every
+//! affected network interface are written to `dns-backup.toml` in the rempower
+//! state directory. `rem dns --restore` reverts the interfaces to exactly these
+//! settings, including manually configured servers that were not assigned by DHCP.
+//! An interface is only backed up on its first change, so repeated changes keep
+//! the original settings until they are restored.
+
+use super::backend::DnsBackend;
+use super::{enable_dhcp_dns, enable_dns_servers, manual_dns_of_network, resolve_network};
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Name of the backup file inside the state directory
+const BACKUP_FILE: &str = "dns-backup.toml";
+
+/// DNS server settings of network interfaces before they were changed
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Backup {
+    /// Backed up settings, keyed by network interface name
+    networks: BTreeMap<String, NetworkBackup>,
+}
+
+/// DNS server settings of a single network interface
+#[derive(Debug, Serialize, Deserialize)]
+struct NetworkBackup {
+    /// Manually configured DNS servers; empty if the servers were assigned by DHCP
+    servers: Vec<String>,
+}
+
+/// Saves the current DNS server settings of the given network interfaces
+///
+/// Interfaces already contained in the backup are left untouched, so the settings
+/// from before the first change survive until they are restored.
+///
+/// # Arguments
+///
+/// * `backend` - Backend reading the DNS settings
+/// * `networks` - Names of the network interfaces about to be changed
+///
+/// # Errors
+///
+/// Returns an error if network commands fail or the backup file cannot be written.
+pub fn save(backend: &dyn DnsBackend, networks: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut backup = load()?.unwrap_or_default();
+
+    for network in networks {
+        if let Entry::Vacant(entry) = backup.networks.entry(network.clone()) {
+            let servers = manual_dns_of_network(backend, network)?;
+            entry.insert(NetworkBackup { servers });
+        }
+    }
+
+    let path = backup_file()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string_pretty(&backup)?)?;
+
+    Ok(())
+}
+
+/// Restores the backed up DNS server settings
+///
+/// Restored interfaces are removed from the backup; the backup file is deleted
+/// once it is empty.
+///
+/// # Arguments
+///
+/// * `backend` - Backend writing the DNS settings
+/// * `requested` - Network service names given with `--interface`; all backed up
+///   interfaces are restored if empty
+/// * `dry_run` - Only print the `networksetup` invocations; the backup is kept
+///
+/// # Errors
+///
+/// Returns an error if no backup exists, a requested interface is not part of the
+/// backup, or the DNS configuration update fails.
+pub fn restore(backend: &dyn DnsBackend, requested: &[String], dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let Some(mut backup) = load()? else {
+        return Err("No DNS backup found. A backup is created whenever DNS servers are changed".into());
+    };
+
+    let available: Vec<String> = backup.networks.keys().cloned().collect();
+    let networks = if requested.is_empty() {
+        available.clone()
+    } else {
+        requested
+            .iter()
+            .map(|name| resolve_network(name, &available))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    for network in &networks {
+        let Some(entry) = backup.networks.remove(network) else {
+            continue;
+        };
+        let target = std::slice::from_ref(network);
+        if entry.servers.is_empty() {
+            enable_dhcp_dns(backend, target, dry_run)?;
+        } else {
+            let servers: Vec<&str> = entry.servers.iter().map(String::as_str).collect();
+            enable_dns_servers(backend, target, &servers, "previous DNS servers", dry_run)?;
+        }
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let path = backup_file()?;
+    if backup.networks.is_empty() {
+        fs::remove_file(path)?;
+    } else {
+        fs::write(path, toml::to_string_pretty(&backup)?)?;
+    }
+
+    Ok(())
+}
+
+/// Loads the backup file
+///
+/// Returns `None` if no backup exists.
+///
+/// # Errors
+///
+/// Returns an error if the backup file cannot be read or parsed.
+fn load() -> Result<Option<Backup>, Box<dyn Error>> {
+    let path = backup_file()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let backup = toml::from_str(&content).map_err(|e| format!("Invalid DNS backup {}: {e}", path.display()))?;
+    Ok(Some(backup))
+}
+
+/// Returns the path of the backup file
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined.
+fn backup_file() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(config::state_dir()?.join(BACKUP_FILE))
+}
@@ -0,0 +1,276 @@
+//! mDNSResponder statistics and cache inspection
+//!
+//! Sending `SIGINFO` to mDNSResponder makes it dump its internal state (cache,
+//! questions, DNS servers, statistics) to the unified log. The dump is read back
+//! with `log show` and condensed into a few tables.
+
+use crate::util::run_sudo;
+use colored::Colorize;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::IpAddr;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Time to wait for mDNSResponder to write its state dump
+const DUMP_DELAY: Duration = Duration::from_secs(2);
+
+/// Time span of the unified log searched for the state dump
+const LOG_WINDOW: &str = "1m";
+
+/// Interface name prefixes recognized in DNS server lines
+const INTERFACE_PREFIXES: &[&str] = &[
+    "en", "utun", "bridge", "lo", "awdl", "llw", "ipsec", "ppp", "pdp_ip", "anpi",
+];
+
+/// Keywords describing the state of a DNS server in the dump
+const SERVER_STATES: &[&str] = &[
+    "Penalized",
+    "Disabled",
+    "Deleted",
+    "Cellular",
+    "Expensive",
+    "Constrained",
+];
+
+/// A DNS server known to mDNSResponder
+struct DnsServer {
+    /// Interface the server is scoped to
+    interface: Option<String>,
+    /// Address of the server
+    address: IpAddr,
+    /// Domain the server is responsible for
+    domain: String,
+    /// State keywords, e.g. `Penalized`
+    state: Vec<&'static str>,
+}
+
+/// Condensed mDNSResponder state dump
+#[derive(Default)]
+struct Stats {
+    /// Number of cached records
+    cache_entries: Option<u64>,
+    /// Number of cached records referenced by active questions
+    cache_in_use: Option<u64>,
+    /// Number of questions
+    questions: Option<u64>,
+    /// Number of active questions
+    active_questions: Option<u64>,
+    /// DNS servers with their interface and state
+    servers: Vec<DnsServer>,
+    /// Lines of the statistics sections
+    statistics: Vec<String>,
+    /// Number of lines per section of the dump
+    sections: BTreeMap<String, usize>,
+}
+
+/// Dumps the mDNSResponder state and prints cache, resolver and query statistics
+///
+/// # Errors
+///
+/// Returns an error if mDNSResponder cannot be signaled (requires sudo), the
+/// unified log cannot be read or contains no state dump.
+pub fn perform() -> Result<(), Box<dyn Error>> {
+    run_sudo(&["killall", "-INFO", "mDNSResponder"])?;
+    thread::sleep(DUMP_DELAY);
+
+    let messages = read_log_messages()?;
+    if messages.is_empty() {
+        return Err("No mDNSResponder state dump found in the unified log".into());
+    }
+
+    print_stats(&parse_dump(&messages)?);
+
+    Ok(())
+}
+
+/// Reads the recent log messages of mDNSResponder
+///
+/// # Errors
+///
+/// Returns an error if `log show` fails or its output cannot be parsed.
+fn read_log_messages() -> Result<Vec<String>, Box<dyn Error>> {
+    let output = Command::new("log")
+        .args([
+            "show",
+            "--last",
+            LOG_WINDOW,
+            "--info",
+            "--style",
+            "ndjson",
+            "--predicate",
+            "process == \"mDNSResponder\"",
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to read the unified log: {}", stderr.trim()).into());
+    }
+
+    let mut messages = Vec::new();
+    for line in str::from_utf8(&output.stdout)?.lines() {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if let Some(message) = event.get("eventMessage").and_then(serde_json::Value::as_str) {
+            messages.extend(message.lines().map(str::to_string));
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Parses the lines of a state dump
+///
+/// The dump consists of sections introduced by headers like
+/// `------------ Cache -------------`. Only the last dump is evaluated if the
+/// log contains several.
+///
+/// # Errors
+///
+/// Returns an error if a regular expression cannot be compiled.
+fn parse_dump(lines: &[String]) -> Result<Stats, Box<dyn Error>> {
+    let header = Regex::new(r"^-{3,}\s*(.*?)\s*-{3,}$")?;
+    let cache = Regex::new(r"(?:Cache currently contains|Cache size) (\d+) entit\w*; (\d+) (?:referenced|in use)")?;
+    let questions = Regex::new(r"(\d+) questions?; (\d+) active")?;
+
+    let mut stats = Stats::default();
+    let mut section = String::new();
+
+    for line in lines.iter().map(|line| line.trim()) {
+        if let Some(captures) = header.captures(line) {
+            section = captures[1].to_string();
+            if section.eq_ignore_ascii_case("Cache") {
+                // A new dump starts, discard the previous one
+                stats = Stats::default();
+            }
+            stats.sections.entry(section.clone()).or_default();
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(count) = stats.sections.get_mut(&section) {
+            *count += 1;
+        }
+
+        if let Some(captures) = cache.captures(line) {
+            stats.cache_entries = captures[1].parse().ok();
+            stats.cache_in_use = captures[2].parse().ok();
+        } else if let Some(captures) = questions.captures(line) {
+            stats.questions = captures[1].parse().ok();
+            stats.active_questions = captures[2].parse().ok();
+        } else if section.starts_with("DNS Server") {
+            stats.servers.extend(parse_server(line));
+        } else if section.contains("Statistic") {
+            stats.statistics.push(line.to_string());
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Parses a line of the DNS servers section
+///
+/// Returns `None` if the line contains no server address.
+fn parse_server(line: &str) -> Option<DnsServer> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let address = tokens.iter().find_map(|token| parse_address(token))?;
+    let interface = tokens
+        .iter()
+        .map(|token| token.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '_'))
+        .find(|token| is_interface(token))
+        .map(str::to_string);
+    let domain = tokens
+        .iter()
+        .skip_while(|token| **token == "DNS" || **token == "Server")
+        .find(|token| token.ends_with('.') || token.contains(".:"))
+        .map(|token| token.split(':').next().unwrap_or(token).to_string())
+        .unwrap_or_else(|| ".".to_string());
+    let state = SERVER_STATES
+        .iter()
+        .copied()
+        .filter(|state| line.contains(state))
+        .collect();
+
+    Some(DnsServer {
+        interface,
+        address,
+        domain,
+        state,
+    })
+}
+
+/// Parses an address token, stripping a port (`1.1.1.1:53`) and an IPv6 zone
+fn parse_address(token: &str) -> Option<IpAddr> {
+    let token = token.trim_matches(|c| c == '[' || c == ']' || c == ',');
+    if let Ok(address) = token.split('%').next()?.parse() {
+        return Some(address);
+    }
+    let (address, port) = token.rsplit_once(':')?;
+    port.parse::<u16>().ok()?;
+    address
+        .trim_matches(|c| c == '[' || c == ']')
+        .split('%')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Returns true if the token is the name of a network interface, e.g. `en0`
+fn is_interface(token: &str) -> bool {
+    INTERFACE_PREFIXES.iter().any(|prefix| {
+        token
+            .strip_prefix(prefix)
+            .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// Prints the condensed state dump
+fn print_stats(stats: &Stats) {
+    let count = |value: Option<u64>| value.map_or_else(|| "-".to_string(), |value| value.to_string());
+
+    println!("{}", "CACHE".bold());
+    println!("  {:<28} {}", "Cached records", count(stats.cache_entries));
+    println!("  {:<28} {}", "Referenced by questions", count(stats.cache_in_use));
+    println!("  {:<28} {}", "Questions", count(stats.questions));
+    println!("  {:<28} {}", "Active questions", count(stats.active_questions));
+
+    println!();
+    println!("{}", "RESOLVERS".bold());
+    if stats.servers.is_empty() {
+        println!("  No DNS servers found in the state dump");
+    } else {
+        println!("  {:<12} {:<40} {:<24} STATE", "INTERFACE", "SERVER", "DOMAIN");
+        for server in &stats.servers {
+            let state = if server.state.is_empty() {
+                "active".green()
+            } else {
+                server.state.join(", ").yellow()
+            };
+            println!(
+                "  {:<12} {:<40} {:<24} {state}",
+                server.interface.as_deref().unwrap_or("-"),
+                server.address.to_string(),
+                server.domain
+            );
+        }
+    }
+
+    if !stats.statistics.is_empty() {
+        println!();
+        println!("{}", "QUERY STATISTICS".bold());
+        for line in &stats.statistics {
+            println!("  {line}");
+        }
+    }
+
+    println!();
+    println!("{}", "SECTIONS".bold());
+    for (section, lines) in &stats.sections {
+        println!("  {section:<40} {lines:>6} line(s)");
+    }
+}
@@ -0,0 +1,649 @@
+//! Minimal DNS client
+//!
+//! Sends single DNS queries over UDP to a given server and parses the response
+//! (RFC 1035), optionally with EDNS(0) and the DNSSEC OK bit. The common record
+//! types (A, AAAA, NS, CNAME, SOA, PTR, MX, TXT, SRV, CAA) are decoded; all other
+//! record data is kept as raw bytes.
+
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::process;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Standard DNS port
+pub const DNS_PORT: u16 = 53;
+
+/// Maximum size of a DNS message received over UDP
+const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// Internet class
+const CLASS_IN: u16 = 1;
+
+/// Record type of the EDNS(0) pseudo-record
+const TYPE_OPT: u16 = 41;
+
+/// UDP payload size advertised in EDNS(0) queries
+const EDNS_UDP_SIZE: u16 = 1232;
+
+/// Options of a DNS query
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueryOptions {
+    /// Add an EDNS(0) OPT record to the query (RFC 6891)
+    pub edns: bool,
+    /// Request DNSSEC records by setting the DO bit; implies `edns`
+    pub dnssec_ok: bool,
+}
+
+/// DNS record types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+    /// IPv4 address
+    A,
+    /// Authoritative name server
+    Ns,
+    /// Canonical name (alias)
+    Cname,
+    /// Start of authority
+    Soa,
+    /// Domain name pointer (reverse lookup)
+    Ptr,
+    /// Mail exchange
+    Mx,
+    /// Text strings
+    Txt,
+    /// IPv6 address
+    Aaaa,
+    /// Service locator
+    Srv,
+    /// Certification authority authorization
+    Caa,
+    /// Any other record type, identified by its numeric code
+    Other(u16),
+}
+
+/// Known record types with their numeric codes and mnemonics
+const RECORD_TYPES: &[(RecordType, u16, &str)] = &[
+    (RecordType::A, 1, "A"),
+    (RecordType::Ns, 2, "NS"),
+    (RecordType::Cname, 5, "CNAME"),
+    (RecordType::Soa, 6, "SOA"),
+    (RecordType::Ptr, 12, "PTR"),
+    (RecordType::Mx, 15, "MX"),
+    (RecordType::Txt, 16, "TXT"),
+    (RecordType::Aaaa, 28, "AAAA"),
+    (RecordType::Srv, 33, "SRV"),
+    (RecordType::Caa, 257, "CAA"),
+];
+
+impl RecordType {
+    /// Returns the numeric code of the record type
+    pub fn code(self) -> u16 {
+        match self {
+            RecordType::Other(code) => code,
+            known => RECORD_TYPES
+                .iter()
+                .find(|(record_type, _, _)| *record_type == known)
+                .map(|(_, code, _)| *code)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the record type for a numeric code
+    pub fn from_code(code: u16) -> Self {
+        RECORD_TYPES
+            .iter()
+            .find(|(_, known, _)| *known == code)
+            .map(|(record_type, _, _)| *record_type)
+            .unwrap_or(RecordType::Other(code))
+    }
+}
+
+impl FromStr for RecordType {
+    type Err = String;
+
+    /// Parses a mnemonic (`MX`, case-insensitive) or a generic type (`TYPE65`)
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some((record_type, _, _)) = RECORD_TYPES
+            .iter()
+            .find(|(_, _, name)| name.eq_ignore_ascii_case(value))
+        {
+            return Ok(*record_type);
+        }
+
+        value
+            .to_ascii_uppercase()
+            .strip_prefix("TYPE")
+            .and_then(|code| code.parse().ok())
+            .map(RecordType::from_code)
+            .ok_or_else(|| {
+                let names: Vec<&str> = RECORD_TYPES.iter().map(|(_, _, name)| *name).collect();
+                format!(
+                    "Unknown record type '{value}' (known: {}, or TYPE<n>)",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match RECORD_TYPES.iter().find(|(record_type, _, _)| record_type == self) {
+            Some((_, _, name)) => write!(f, "{name}"),
+            None => write!(f, "TYPE{}", self.code()),
+        }
+    }
+}
+
+/// Decoded data of a resource record
+#[derive(Debug, Clone)]
+pub enum RecordData {
+    /// IPv4 address of an A record
+    A(Ipv4Addr),
+    /// IPv6 address of an AAAA record
+    Aaaa(Ipv6Addr),
+    /// Domain name of an NS, CNAME or PTR record
+    Name(String),
+    /// Mail exchange of an MX record
+    Mx {
+        /// Preference, lower values are preferred
+        preference: u16,
+        /// Host name of the mail server
+        exchange: String,
+    },
+    /// Zone parameters of an SOA record
+    Soa {
+        /// Primary name server
+        mname: String,
+        /// Mailbox of the responsible person
+        rname: String,
+        /// Zone serial number
+        serial: u32,
+        /// Refresh interval in seconds
+        refresh: u32,
+        /// Retry interval in seconds
+        retry: u32,
+        /// Expiry limit in seconds
+        expire: u32,
+        /// Negative caching TTL in seconds
+        minimum: u32,
+    },
+    /// Service location of an SRV record
+    Srv {
+        /// Priority, lower values are preferred
+        priority: u16,
+        /// Relative weight among records of the same priority
+        weight: u16,
+        /// Port of the service
+        port: u16,
+        /// Host name providing the service
+        target: String,
+    },
+    /// Property of a CAA record
+    Caa {
+        /// Flags (128 = issuer critical)
+        flags: u8,
+        /// Property tag, e.g. `issue`
+        tag: String,
+        /// Property value
+        value: String,
+    },
+    /// Character strings of a TXT record
+    Txt(Vec<String>),
+    /// Undecoded data of other record types
+    Raw(Vec<u8>),
+}
+
+impl fmt::Display for RecordData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordData::A(address) => write!(f, "{address}"),
+            RecordData::Aaaa(address) => write!(f, "{address}"),
+            RecordData::Name(name) => write!(f, "{name}"),
+            RecordData::Mx { preference, exchange } => write!(f, "{preference} {exchange}"),
+            RecordData::Soa {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => write!(f, "{mname} {rname} {serial} {refresh} {retry} {expire} {minimum}"),
+            RecordData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(f, "{priority} {weight} {port} {target}"),
+            RecordData::Caa { flags, tag, value } => write!(f, "{flags} {tag} {value:?}"),
+            RecordData::Txt(strings) => {
+                let quoted: Vec<String> = strings.iter().map(|s| format!("{s:?}")).collect();
+                write!(f, "{}", quoted.join(" "))
+            }
+            RecordData::Raw(bytes) => {
+                write!(f, "\\# {}", bytes.len())?;
+                for byte in bytes {
+                    write!(f, " {byte:02x}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A resource record of a DNS response
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Owner name of the record
+    pub name: String,
+    /// Type of the record
+    pub record_type: RecordType,
+    /// Time to live in seconds
+    pub ttl: u32,
+    /// Decoded record data
+    pub data: RecordData,
+}
+
+/// A parsed DNS response
+#[derive(Debug)]
+pub struct Response {
+    /// Response code (0 = NOERROR, 2 = SERVFAIL, 3 = NXDOMAIN, ...)
+    pub rcode: u8,
+    /// True if the response was truncated to fit into a UDP datagram
+    pub truncated: bool,
+    /// True if the server validated the answer with DNSSEC (AD flag)
+    pub authentic_data: bool,
+    /// UDP payload size of the EDNS(0) OPT record, if the response contains one
+    pub edns_udp_size: Option<u16>,
+    /// Records of the answer section
+    pub answers: Vec<Record>,
+    /// Time between sending the query and receiving the response
+    pub elapsed: Duration,
+}
+
+impl Response {
+    /// Returns the addresses of all A and AAAA records in the answer section
+    pub fn addresses(&self) -> Vec<IpAddr> {
+        self.answers
+            .iter()
+            .filter_map(|record| match record.data {
+                RecordData::A(address) => Some(IpAddr::V4(address)),
+                RecordData::Aaaa(address) => Some(IpAddr::V6(address)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Returns the mnemonic of a DNS response code
+pub fn rcode_name(rcode: u8) -> &'static str {
+    match rcode {
+        0 => "NOERROR",
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Sends a recursive query to a DNS server and waits for the response
+///
+/// # Arguments
+///
+/// * `server` - Address of the DNS server (port 53 is used)
+/// * `name` - Domain name to query
+/// * `record_type` - Record type to query
+/// * `timeout` - Maximum time to wait for the response
+///
+/// # Errors
+///
+/// Returns an error if the query cannot be sent, no response arrives in time,
+/// or the response is malformed.
+pub fn query(
+    server: IpAddr,
+    name: &str,
+    record_type: RecordType,
+    timeout: Duration,
+) -> Result<Response, Box<dyn Error>> {
+    query_with(server, name, record_type, timeout, QueryOptions::default())
+}
+
+/// Sends a recursive query with the given options to a DNS server
+///
+/// # Arguments
+///
+/// * `server` - Address of the DNS server (port 53 is used)
+/// * `name` - Domain name to query
+/// * `record_type` - Record type to query
+/// * `timeout` - Maximum time to wait for the response
+/// * `options` - EDNS and DNSSEC options of the query
+///
+/// # Errors
+///
+/// Returns an error if the query cannot be sent, no response arrives in time,
+/// or the response is malformed.
+pub fn query_with(
+    server: IpAddr,
+    name: &str,
+    record_type: RecordType,
+    timeout: Duration,
+    options: QueryOptions,
+) -> Result<Response, Box<dyn Error>> {
+    let id = query_id();
+    let message = build_query(id, name, record_type, options)?;
+
+    let bind_address: SocketAddr = match server {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        IpAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind_address)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect((server, DNS_PORT))?;
+
+    let start = Instant::now();
+    socket.send(&message)?;
+
+    let mut buffer = [0u8; MAX_MESSAGE_SIZE];
+    loop {
+        let remaining = timeout.checked_sub(start.elapsed()).filter(|d| !d.is_zero());
+        let Some(remaining) = remaining else {
+            return Err(format!("No response from {server} within {} ms", timeout.as_millis()).into());
+        };
+        socket.set_read_timeout(Some(remaining))?;
+
+        let length = match socket.recv(&mut buffer) {
+            Ok(length) => length,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                return Err(format!("No response from {server} within {} ms", timeout.as_millis()).into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        // Ignore stray datagrams that do not belong to this query
+        if length >= 2 && u16::from_be_bytes([buffer[0], buffer[1]]) == id {
+            let mut response = parse_response(&buffer[..length])?;
+            response.elapsed = start.elapsed();
+            return Ok(response);
+        }
+    }
+}
+
+/// Generates a query ID that is hard to predict for off-path attackers
+fn query_id() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    (nanos ^ (process::id() << 7)) as u16
+}
+
+/// Encodes a recursive query message
+///
+/// # Errors
+///
+/// Returns an error if the name contains empty or overlong labels.
+fn build_query(id: u16, name: &str, record_type: RecordType, options: QueryOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    let edns = options.edns || options.dnssec_ok;
+
+    let mut message = Vec::with_capacity(512);
+    message.extend_from_slice(&id.to_be_bytes());
+    // Flags: standard query with recursion desired
+    message.extend_from_slice(&0x0100u16.to_be_bytes());
+    // QDCOUNT = 1, ANCOUNT = NSCOUNT = 0, ARCOUNT = 1 with EDNS
+    message.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, u8::from(edns)]);
+
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if !name.is_empty() {
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(format!("Invalid domain name '{name}'").into());
+            }
+            message.push(label.len() as u8);
+            message.extend_from_slice(label.as_bytes());
+        }
+    }
+    message.push(0);
+
+    message.extend_from_slice(&record_type.code().to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    if edns {
+        // OPT record: root name, UDP payload size as class, DO bit in the TTL field
+        message.push(0);
+        message.extend_from_slice(&TYPE_OPT.to_be_bytes());
+        message.extend_from_slice(&EDNS_UDP_SIZE.to_be_bytes());
+        let flags: u32 = if options.dnssec_ok { 0x8000 } else { 0 };
+        message.extend_from_slice(&flags.to_be_bytes());
+        message.extend_from_slice(&0u16.to_be_bytes());
+    }
+
+    Ok(message)
+}
+
+/// Parses a DNS response message
+///
+/// # Errors
+///
+/// Returns an error if the message is truncated or malformed.
+fn parse_response(message: &[u8]) -> Result<Response, Box<dyn Error>> {
+    let mut reader = Reader { message, position: 0 };
+
+    reader.skip(2)?; // ID, already checked by the caller
+    let flags = reader.u16()?;
+    let question_count = reader.u16()?;
+    let answer_count = reader.u16()?;
+    let authority_count = reader.u16()?;
+    let additional_count = reader.u16()?;
+
+    if flags & 0x8000 == 0 {
+        return Err("Received a DNS query instead of a response".into());
+    }
+
+    for _ in 0..question_count {
+        reader.name()?;
+        reader.skip(4)?; // QTYPE and QCLASS
+    }
+
+    let mut answers = Vec::with_capacity(usize::from(answer_count));
+    for _ in 0..answer_count {
+        answers.push(reader.record()?);
+    }
+
+    // Only the OPT record is of interest in the remaining sections. They are read
+    // leniently, as truncated responses may not contain all announced records.
+    let mut edns_udp_size = None;
+    for _ in 0..u32::from(authority_count) + u32::from(additional_count) {
+        match reader.record_header() {
+            Ok((TYPE_OPT, class)) => edns_udp_size = Some(class),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    Ok(Response {
+        rcode: (flags & 0x000f) as u8,
+        truncated: flags & 0x0200 != 0,
+        authentic_data: flags & 0x0020 != 0,
+        edns_udp_size,
+        answers,
+        elapsed: Duration::ZERO,
+    })
+}
+
+/// Cursor over a DNS message
+struct Reader<'a> {
+    /// The complete message, needed to follow compression pointers
+    message: &'a [u8],
+    /// Current read position
+    position: usize,
+}
+
+impl Reader<'_> {
+    /// Reads the given number of bytes
+    fn bytes(&mut self, count: usize) -> Result<&[u8], Box<dyn Error>> {
+        let end = self.position + count;
+        let bytes = self.message.get(self.position..end).ok_or("Truncated DNS response")?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Skips the given number of bytes
+    fn skip(&mut self, count: usize) -> Result<(), Box<dyn Error>> {
+        self.bytes(count).map(|_| ())
+    }
+
+    /// Reads a single byte
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Reads a big-endian 16-bit integer
+    fn u16(&mut self) -> Result<u16, Box<dyn Error>> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a big-endian 32-bit integer
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a possibly compressed domain name
+    fn name(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut position = self.position;
+        let mut jumped = false;
+        let mut jumps = 0;
+
+        loop {
+            let length = *self.message.get(position).ok_or("Truncated DNS response")?;
+            match length {
+                0 => {
+                    position += 1;
+                    break;
+                }
+                length if length & 0xc0 == 0xc0 => {
+                    let low = *self.message.get(position + 1).ok_or("Truncated DNS response")?;
+                    if !jumped {
+                        self.position = position + 2;
+                    }
+                    jumped = true;
+                    jumps += 1;
+                    if jumps > 32 {
+                        return Err("Compression loop in DNS response".into());
+                    }
+                    position = usize::from(u16::from_be_bytes([length & 0x3f, low]));
+                }
+                length => {
+                    let start = position + 1;
+                    let end = start + usize::from(length);
+                    let label = self.message.get(start..end).ok_or("Truncated DNS response")?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    position = end;
+                }
+            }
+        }
+
+        if !jumped {
+            self.position = position;
+        }
+
+        Ok(if labels.is_empty() {
+            ".".to_string()
+        } else {
+            labels.join(".") + "."
+        })
+    }
+
+    /// Skips a resource record
+    ///
+    /// # Returns
+    ///
+    /// The numeric type and class of the record.
+    fn record_header(&mut self) -> Result<(u16, u16), Box<dyn Error>> {
+        self.name()?;
+        let record_type = self.u16()?;
+        let class = self.u16()?;
+        self.skip(4)?; // TTL
+        let length = usize::from(self.u16()?);
+        self.skip(length)?;
+        Ok((record_type, class))
+    }
+
+    /// Reads a resource record
+    fn record(&mut self) -> Result<Record, Box<dyn Error>> {
+        let name = self.name()?;
+        let record_type = RecordType::from_code(self.u16()?);
+        self.skip(2)?; // CLASS
+        let ttl = self.u32()?;
+        let length = usize::from(self.u16()?);
+        let end = self.position + length;
+
+        let data = match record_type {
+            RecordType::A if length == 4 => {
+                let b = self.bytes(4)?;
+                RecordData::A(Ipv4Addr::new(b[0], b[1], b[2], b[3]))
+            }
+            RecordType::Aaaa if length == 16 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(self.bytes(16)?);
+                RecordData::Aaaa(Ipv6Addr::from(octets))
+            }
+            RecordType::Ns | RecordType::Cname | RecordType::Ptr => RecordData::Name(self.name()?),
+            RecordType::Mx => RecordData::Mx {
+                preference: self.u16()?,
+                exchange: self.name()?,
+            },
+            RecordType::Soa => RecordData::Soa {
+                mname: self.name()?,
+                rname: self.name()?,
+                serial: self.u32()?,
+                refresh: self.u32()?,
+                retry: self.u32()?,
+                expire: self.u32()?,
+                minimum: self.u32()?,
+            },
+            RecordType::Srv => RecordData::Srv {
+                priority: self.u16()?,
+                weight: self.u16()?,
+                port: self.u16()?,
+                target: self.name()?,
+            },
+            RecordType::Caa if length >= 2 => {
+                let flags = self.u8()?;
+                let tag_length = usize::from(self.u8()?);
+                let tag = String::from_utf8_lossy(self.bytes(tag_length)?).into_owned();
+                let value_length = end
+                    .checked_sub(self.position)
+                    .ok_or("Malformed CAA record in DNS response")?;
+                let value = String::from_utf8_lossy(self.bytes(value_length)?).into_owned();
+                RecordData::Caa { flags, tag, value }
+            }
+            RecordType::Txt => {
+                let mut strings = Vec::new();
+                while self.position < end {
+                    let count = usize::from(self.u8()?);
+                    strings.push(String::from_utf8_lossy(self.bytes(count)?).into_owned());
+                }
+                RecordData::Txt(strings)
+            }
+            _ => RecordData::Raw(self.bytes(length)?.to_vec()),
+        };
+
+        if self.position != end {
+            return Err(format!("Malformed {record_type} record in DNS response").into());
+        }
+
+        Ok(Record {
+            name,
+            record_type,
+            ttl,
+            data,
+        })
+    }
+}
@@ -0,0 +1,253 @@
+//! Encrypted DNS configuration profiles
+//!
+//! macOS only supports DNS over HTTPS (DoH) and DNS over TLS (DoT) system-wide
+//! through configuration profiles with a `com.apple.dnsSettings.managed` payload.
+//! This module generates such `.mobileconfig` profiles for the providers of the
+//! [`providers`](super::providers) registry,
+//! opens them for installation and removes them again.
+//!
+//! All generated profiles share the same payload identifier, so installing a
+//! profile for another provider replaces the previous one.
+//!
+//! # System Commands Used
+//!
+//! - `open` - Hand a profile over to System Settings for installation
+//! - `profiles remove -identifier` - Remove an installed profile (requires sudo)
+
+use super::providers::{self, Provider};
+use crate::cli::{DohArgs, DohProtocol};
+use colored::Colorize;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Payload identifier of generated configuration profiles
+const PROFILE_IDENTIFIER: &str = "com.github.rfoerthe.rempower.doh";
+
+impl DohProtocol {
+    /// Returns the `DNSProtocol` value of the configuration profile
+    fn payload_value(self) -> &'static str {
+        match self {
+            DohProtocol::Https => "HTTPS",
+            DohProtocol::Tls => "TLS",
+        }
+    }
+
+    /// Returns the common abbreviation of the protocol
+    fn abbreviation(self) -> &'static str {
+        match self {
+            DohProtocol::Https => "DoH",
+            DohProtocol::Tls => "DoT",
+        }
+    }
+}
+
+/// Performs encrypted DNS profile operations
+///
+/// Without `--output` or `--install` the generated profile is written to stdout.
+///
+/// # Arguments
+///
+/// * `args` - Encrypted DNS arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the profile cannot be written, opened or removed.
+pub fn perform(args: DohArgs) -> Result<(), Box<dyn Error>> {
+    if args.remove {
+        return remove_profile();
+    }
+
+    let Some(provider) = args.provider else {
+        return Err("No encrypted DNS provider specified".into());
+    };
+    let profile = mobileconfig(providers::find(&provider)?, args.protocol)?;
+
+    let path = match (args.output, args.install) {
+        (Some(path), _) => path,
+        (None, true) => env::temp_dir().join(format!("rempower-{}.mobileconfig", args.protocol.abbreviation())),
+        (None, false) => {
+            print!("{profile}");
+            return Ok(());
+        }
+    };
+
+    fs::write(&path, profile)?;
+    println!("{} configuration profile to '{}'", "Wrote".green(), path.display());
+
+    if args.install {
+        install_profile(&path)?;
+    }
+
+    Ok(())
+}
+
+/// Opens a configuration profile so that it can be installed in System Settings
+///
+/// Since macOS 11 configuration profiles can no longer be installed silently
+/// from the command line; the user has to approve them in System Settings.
+///
+/// # Errors
+///
+/// Returns an error if the `open` command fails.
+fn install_profile(path: &Path) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("open").arg(path).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to open configuration profile: {}", stderr.trim()).into());
+    }
+
+    println!("Approve the profile in System Settings > General > Device Management (or Privacy & Security > Profiles)");
+
+    Ok(())
+}
+
+/// Removes the installed encrypted DNS configuration profile
+///
+/// Uses `sudo profiles remove -identifier` and requires sudo privileges.
+///
+/// # Errors
+///
+/// Returns an error if the profile is not installed or cannot be removed.
+fn remove_profile() -> Result<(), Box<dyn Error>> {
+    print!("Remove encrypted DNS configuration profile '{PROFILE_IDENTIFIER}'");
+
+    let output = Command::new("sudo")
+        .args(["profiles", "remove", "-identifier", PROFILE_IDENTIFIER])
+        .output()?;
+
+    if !output.status.success() {
+        println!("{}", " Not OK".red());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to remove configuration profile: {}", stderr.trim()).into());
+    }
+
+    println!("{}", " OK".green());
+
+    Ok(())
+}
+
+/// Generates a `.mobileconfig` configuration profile for encrypted DNS
+///
+/// # Arguments
+///
+/// * `provider` - The encrypted DNS provider
+/// * `protocol` - DNS over HTTPS or DNS over TLS
+///
+/// # Returns
+///
+/// The configuration profile as property list XML.
+///
+/// # Errors
+///
+/// Returns an error if the provider does not offer the requested protocol.
+fn mobileconfig(provider: &Provider, protocol: DohProtocol) -> Result<String, Box<dyn Error>> {
+    let display_name = format!("{} {} (rempower)", provider.name, protocol.abbreviation());
+    let payload_identifier = format!("{PROFILE_IDENTIFIER}.dnssettings");
+
+    let endpoint = match protocol {
+        DohProtocol::Https => provider.doh_url,
+        DohProtocol::Tls => provider.tls_name,
+    };
+    let Some(endpoint) = endpoint else {
+        return Err(format!(
+            "DNS provider '{}' does not support {}",
+            provider.id,
+            protocol.abbreviation()
+        )
+        .into());
+    };
+    let server = match protocol {
+        DohProtocol::Https => format!("<key>ServerURL</key>\n\t\t\t\t<string>{endpoint}</string>"),
+        DohProtocol::Tls => format!("<key>ServerName</key>\n\t\t\t\t<string>{endpoint}</string>"),
+    };
+    let addresses: String = provider
+        .servers
+        .iter()
+        .map(|address| format!("\t\t\t\t\t<string>{address}</string>\n"))
+        .collect();
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>PayloadContent</key>
+	<array>
+		<dict>
+			<key>DNSSettings</key>
+			<dict>
+				<key>DNSProtocol</key>
+				<string>{protocol}</string>
+				{server}
+				<key>ServerAddresses</key>
+				<array>
+{addresses}				</array>
+			</dict>
+			<key>PayloadDisplayName</key>
+			<string>{display_name}</string>
+			<key>PayloadIdentifier</key>
+			<string>{payload_identifier}</string>
+			<key>PayloadType</key>
+			<string>com.apple.dnsSettings.managed</string>
+			<key>PayloadUUID</key>
+			<string>{payload_uuid}</string>
+			<key>PayloadVersion</key>
+			<integer>1</integer>
+		</dict>
+	</array>
+	<key>PayloadDescription</key>
+	<string>Encrypted DNS settings generated by rempower</string>
+	<key>PayloadDisplayName</key>
+	<string>{display_name}</string>
+	<key>PayloadIdentifier</key>
+	<string>{PROFILE_IDENTIFIER}</string>
+	<key>PayloadRemovalDisallowed</key>
+	<false/>
+	<key>PayloadType</key>
+	<string>Configuration</string>
+	<key>PayloadUUID</key>
+	<string>{profile_uuid}</string>
+	<key>PayloadVersion</key>
+	<integer>1</integer>
+</dict>
+</plist>
+"#,
+        protocol = protocol.payload_value(),
+        payload_uuid = stable_uuid(&payload_identifier),
+        profile_uuid = stable_uuid(PROFILE_IDENTIFIER),
+    ))
+}
+
+/// Derives a UUID from a seed string
+///
+/// Configuration profiles require UUIDs for their payloads. Deriving them from
+/// the payload identifier keeps regenerated profiles identical, so reinstalling
+/// updates the existing profile instead of adding a new one.
+fn stable_uuid(seed: &str) -> String {
+    let high = fnv1a(seed.as_bytes(), 0xcbf2_9ce4_8422_2325);
+    let low = fnv1a(seed.as_bytes(), high);
+
+    // Mark as version 4 / RFC 4122 variant so the value is a well-formed UUID
+    let high = (high & 0xffff_ffff_ffff_0fff) | 0x0000_0000_0000_4000;
+    let low = (low & 0x3fff_ffff_ffff_ffff) | 0x8000_0000_0000_0000;
+
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:04X}-{:012X}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// Computes the 64-bit FNV-1a hash of the given bytes
+fn fnv1a(bytes: &[u8], offset_basis: u64) -> u64 {
+    bytes.iter().fold(offset_basis, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
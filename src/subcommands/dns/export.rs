@@ -0,0 +1,160 @@
+//! Export and import of the DNS configuration
+//!
+//! `rem dns export` writes the manually configured DNS servers and search domains
+//! of all network services as JSON. `rem dns import` applies such a file, e.g. on
+//! a new Mac or after an OS upgrade reset the network settings. Empty lists stand
+//! for settings assigned by DHCP.
+
+use super::{
+    NetworkSetting, all_networks, backend, backup, clear_search_domains, enable_dhcp_dns, enable_dns_servers,
+    manual_dns_of_network, set_search_domains,
+};
+use crate::cli::ImportArgs;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Version of the export format
+const FORMAT_VERSION: u32 = 1;
+
+/// DNS configuration of all network services
+#[derive(Serialize, Deserialize)]
+struct Export {
+    /// Version of the export format
+    version: u32,
+    /// Settings per network service
+    networks: Vec<NetworkExport>,
+}
+
+/// DNS configuration of a single network service
+#[derive(Serialize, Deserialize)]
+struct NetworkExport {
+    /// Name of the network service
+    name: String,
+    /// Manually configured DNS servers; empty if assigned by DHCP
+    #[serde(default)]
+    servers: Vec<String>,
+    /// Manually configured search domains; empty if assigned by DHCP
+    #[serde(default)]
+    search_domains: Vec<String>,
+}
+
+/// Prints the DNS configuration of all network services as JSON
+///
+/// # Errors
+///
+/// Returns an error if the network services or their settings cannot be read.
+pub fn export() -> Result<(), Box<dyn Error>> {
+    let backend = backend();
+    let networks = all_networks(backend)?
+        .into_iter()
+        .map(|name| {
+            Ok(NetworkExport {
+                servers: manual_dns_of_network(backend, &name)?,
+                search_domains: backend.manual_setting(&name, NetworkSetting::SearchDomains)?,
+                name,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    let export = Export {
+        version: FORMAT_VERSION,
+        networks,
+    };
+    println!("{}", serde_json::to_string_pretty(&export)?);
+
+    Ok(())
+}
+
+/// Applies the DNS configuration of an exported file
+///
+/// Network services missing on this Mac are skipped with a warning. The current
+/// DNS servers are backed up first, so the import can be reverted with
+/// `rem dns --restore`.
+///
+/// # Arguments
+///
+/// * `args` - Import arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or parsed, or a DNS update fails.
+pub fn import(args: ImportArgs) -> Result<(), Box<dyn Error>> {
+    let export = read_export(&args.file)?;
+    if export.version > FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported export format version {} (supported: {FORMAT_VERSION})",
+            export.version
+        )
+        .into());
+    }
+
+    let backend = backend();
+    let available = all_networks(backend)?;
+    let (known, unknown): (Vec<NetworkExport>, Vec<NetworkExport>) = export
+        .networks
+        .into_iter()
+        .partition(|network| available.contains(&network.name));
+
+    for network in &unknown {
+        println!(
+            "{}",
+            format!("Skip network service '{}': not present on this Mac", network.name).yellow()
+        );
+    }
+
+    if !args.dry_run {
+        let names: Vec<String> = known.iter().map(|network| network.name.clone()).collect();
+        backup::save(backend, &names)?;
+    }
+
+    // Networks sharing the same settings are updated together
+    let mut servers: BTreeMap<&[String], Vec<String>> = BTreeMap::new();
+    let mut domains: BTreeMap<&[String], Vec<String>> = BTreeMap::new();
+    for network in &known {
+        servers.entry(&network.servers).or_default().push(network.name.clone());
+        domains
+            .entry(&network.search_domains)
+            .or_default()
+            .push(network.name.clone());
+    }
+
+    for (servers, networks) in servers {
+        if servers.is_empty() {
+            enable_dhcp_dns(backend, &networks, args.dry_run)?;
+        } else {
+            let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+            enable_dns_servers(backend, &networks, &servers, "imported DNS servers", args.dry_run)?;
+        }
+    }
+    for (domains, networks) in domains {
+        if domains.is_empty() {
+            clear_search_domains(backend, &networks, args.dry_run)?;
+        } else {
+            set_search_domains(backend, &networks, domains, args.dry_run)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads an exported DNS configuration from a file or stdin (`-`)
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a valid export.
+fn read_export(path: &Path) -> Result<Export, Box<dyn Error>> {
+    let content = if path == Path::new("-") {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        content
+    } else {
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?
+    };
+
+    serde_json::from_str(&content).map_err(|e| format!("Invalid DNS export {}: {e}", path.display()).into())
+}
@@ -0,0 +1,202 @@
+//! DNS leak test
+//!
+//! Determines which upstream resolver actually answers queries. Each configured
+//! resolver is asked for `whoami.akamai.net`, whose authoritative servers reply
+//! with the address of the resolver that contacted them (the resolver's egress
+//! address). The same name is also resolved through the system resolver to see
+//! which path ordinary applications take.
+//!
+//! If resolvers are configured on a VPN tunnel interface but the system path
+//! ends up at a different upstream resolver, DNS queries are leaking past the VPN.
+
+use super::Resolver;
+use super::backend::backend;
+use super::client::{self, RecordData, RecordType};
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::Duration;
+
+/// Name whose A record is the egress address of the querying resolver (Akamai)
+const AKAMAI_WHOAMI: &str = "whoami.akamai.net";
+
+/// Name whose TXT record is the egress address of the querying resolver (Google)
+const GOOGLE_WHOAMI: &str = "o-o.myaddr.l.google.com";
+
+/// Timeout of a single probe query
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Interface name prefixes of VPN tunnel interfaces
+const TUNNEL_PREFIXES: &[&str] = &["utun", "ipsec", "ppp", "tun", "tap", "wg"];
+
+/// Result of probing a single configured resolver
+struct Probe {
+    /// Address of the configured resolver
+    resolver: String,
+    /// Interface the resolver is bound to
+    interface: Option<String>,
+    /// Egress address of the upstream resolver, or the reason why it is unknown
+    upstream: Result<IpAddr, String>,
+}
+
+impl Probe {
+    /// Returns true if the resolver is bound to a VPN tunnel interface
+    fn is_tunnel(&self) -> bool {
+        self.interface.as_deref().is_some_and(is_tunnel_interface)
+    }
+}
+
+/// Runs the DNS leak test and prints the results
+///
+/// # Errors
+///
+/// Returns an error if the resolver configuration cannot be read.
+pub fn perform() -> Result<(), Box<dyn Error>> {
+    let resolvers = default_resolvers(backend().resolvers()?);
+    if resolvers.is_empty() {
+        return Err("No DNS resolvers configured".into());
+    }
+
+    println!("{:<40} {:<12} UPSTREAM RESOLVER", "CONFIGURED RESOLVER", "INTERFACE");
+
+    let mut probes = Vec::new();
+    for (resolver, interface) in resolvers {
+        let probe = Probe {
+            upstream: probe_resolver(&resolver),
+            resolver,
+            interface,
+        };
+        let upstream = match &probe.upstream {
+            Ok(address) => address.to_string().normal(),
+            Err(reason) => reason.as_str().yellow(),
+        };
+        println!(
+            "{:<40} {:<12} {upstream}",
+            probe.resolver,
+            probe.interface.as_deref().unwrap_or("-")
+        );
+        probes.push(probe);
+    }
+
+    let system = system_upstream();
+    match &system {
+        Ok(address) => println!("{:<40} {:<12} {address}", "(system resolver)", "-"),
+        Err(reason) => println!("{:<40} {:<12} {}", "(system resolver)", "-", reason.yellow()),
+    }
+
+    println!();
+    print_verdict(&probes, system.ok());
+
+    Ok(())
+}
+
+/// Prints whether DNS queries leak past a VPN
+fn print_verdict(probes: &[Probe], system: Option<IpAddr>) {
+    let tunnel_upstreams: BTreeSet<IpAddr> = probes
+        .iter()
+        .filter(|probe| probe.is_tunnel())
+        .filter_map(|probe| probe.upstream.clone().ok())
+        .collect();
+
+    if !probes.iter().any(Probe::is_tunnel) {
+        println!("No VPN DNS resolvers detected. The upstream resolvers above answer your DNS queries.");
+        return;
+    }
+
+    match system {
+        Some(address) if tunnel_upstreams.contains(&address) => {
+            println!(
+                "{}",
+                "No DNS leak detected: the system resolver uses the VPN's DNS".green()
+            );
+        }
+        Some(address) => {
+            println!(
+                "{}",
+                format!("DNS leak detected: the system resolver is answered by {address}, not by the VPN's DNS").red()
+            );
+        }
+        None => println!("{}", "Could not determine the upstream of the system resolver".yellow()),
+    }
+
+    for probe in probes
+        .iter()
+        .filter(|probe| !probe.is_tunnel() && probe.upstream.is_ok())
+    {
+        println!(
+            "{}",
+            format!(
+                "Warning: resolver {} on {} is reachable outside the VPN",
+                probe.resolver,
+                probe.interface.as_deref().unwrap_or("an unknown interface")
+            )
+            .yellow()
+        );
+    }
+}
+
+/// Selects the unique nameservers of resolvers that are not restricted to a domain
+///
+/// Resolvers with a domain (e.g. `local` for mDNS or split-DNS entries) only
+/// answer queries for that domain and are not relevant for leaks.
+fn default_resolvers(resolvers: Vec<Resolver>) -> Vec<(String, Option<String>)> {
+    let mut result: Vec<(String, Option<String>)> = Vec::new();
+    for resolver in resolvers.into_iter().filter(|resolver| resolver.domain.is_none()) {
+        for nameserver in resolver.nameservers {
+            if !result.iter().any(|(known, _)| *known == nameserver) {
+                result.push((nameserver, resolver.interface.clone()));
+            }
+        }
+    }
+    result
+}
+
+/// Determines the upstream egress address of a configured resolver
+///
+/// Tries Akamai's `whoami` service first and falls back to Google's.
+fn probe_resolver(resolver: &str) -> Result<IpAddr, String> {
+    // Link-local IPv6 resolvers carry a zone (e.g. "fe80::1%en0") that IpAddr cannot parse
+    let address: IpAddr = resolver
+        .split('%')
+        .next()
+        .unwrap_or(resolver)
+        .parse()
+        .map_err(|_| "invalid address".to_string())?;
+
+    let akamai = client::query(address, AKAMAI_WHOAMI, RecordType::A, PROBE_TIMEOUT)
+        .ok()
+        .and_then(|response| response.addresses().into_iter().next());
+    if let Some(upstream) = akamai {
+        return Ok(upstream);
+    }
+
+    let response = client::query(address, GOOGLE_WHOAMI, RecordType::Txt, PROBE_TIMEOUT).map_err(|e| e.to_string())?;
+    response
+        .answers
+        .iter()
+        .filter_map(|record| match &record.data {
+            RecordData::Txt(strings) => strings.iter().find_map(|s| s.parse::<IpAddr>().ok()),
+            _ => None,
+        })
+        .next()
+        .ok_or_else(|| format!("no answer ({})", client::rcode_name(response.rcode)))
+}
+
+/// Determines the upstream egress address of the system resolver
+///
+/// Resolves `whoami.akamai.net` through the operating system, so the query
+/// takes the same path as queries of ordinary applications.
+fn system_upstream() -> Result<IpAddr, String> {
+    (AKAMAI_WHOAMI, 0)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .map(|address| address.ip())
+        .next()
+        .ok_or_else(|| "no answer".to_string())
+}
+
+/// Returns true if the interface name denotes a VPN tunnel interface
+fn is_tunnel_interface(interface: &str) -> bool {
+    TUNNEL_PREFIXES.iter().any(|prefix| interface.starts_with(prefix))
+}
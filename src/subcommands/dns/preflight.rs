@@ -0,0 +1,88 @@
+//! Reachability check of DNS servers before they are applied
+//!
+//! Each candidate server is sent a query for the root name servers. Any answer,
+//! including an error response, proves the server is reachable from the current
+//! network; a timeout or network error does not. This catches networks that block
+//! public resolvers (e.g. corporate networks blocking 8.8.8.8) before the DNS
+//! configuration is changed.
+
+use super::client::{self, RecordType};
+use crate::cli::CheckMode;
+use colored::Colorize;
+use std::error::Error;
+use std::net::IpAddr;
+use std::thread;
+use std::time::Duration;
+
+/// Timeout of a single probe query
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Probes the given DNS servers concurrently and prints the results
+///
+/// # Arguments
+///
+/// * `servers` - DNS servers about to be applied
+/// * `mode` - Whether unreachable servers abort the operation
+///
+/// # Errors
+///
+/// Returns an error if a server is unreachable and `mode` is [`CheckMode::Abort`].
+pub fn check(servers: &[IpAddr], mode: CheckMode) -> Result<(), Box<dyn Error>> {
+    println!("Checking reachability of {} DNS server(s)", servers.len());
+
+    let results: Vec<Result<Duration, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = servers
+            .iter()
+            .map(|&server| scope.spawn(move || probe(server)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("probe thread panicked".to_string()))
+            })
+            .collect()
+    });
+
+    let mut unreachable = 0;
+    for (server, result) in servers.iter().zip(&results) {
+        let status = match result {
+            Ok(elapsed) => format!("OK ({} ms)", elapsed.as_millis()).green(),
+            Err(reason) => {
+                unreachable += 1;
+                format!("unreachable: {reason}").red()
+            }
+        };
+        println!("  {:<40} {status}", server.to_string());
+    }
+
+    if unreachable == 0 {
+        return Ok(());
+    }
+    match mode {
+        CheckMode::Abort => Err(format!(
+            "{unreachable} of {} DNS server(s) unreachable, nothing was changed (use --check warn to apply anyway)",
+            servers.len()
+        )
+        .into()),
+        CheckMode::Warn => {
+            println!(
+                "{}",
+                format!("Warning: applying {unreachable} unreachable DNS server(s)").yellow()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Sends a probe query to a DNS server
+///
+/// # Returns
+///
+/// The response time, or the reason why the server did not answer.
+fn probe(server: IpAddr) -> Result<Duration, String> {
+    client::query(server, ".", RecordType::Ns, PROBE_TIMEOUT)
+        .map(|response| response.elapsed)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,216 @@
+//! DNS resolver capability probe
+//!
+//! Tests each active resolver for the features that determine how well it
+//! protects DNS traffic:
+//!
+//! - **EDNS**: The resolver answers EDNS(0) queries with an OPT record (RFC 6891),
+//!   which DNSSEC and larger responses depend on.
+//! - **DNSSEC**: The resolver validates signatures, i.e. it sets the AD flag for a
+//!   signed zone and refuses (SERVFAIL) a zone with broken signatures.
+//! - **QNAME minimization**: The resolver only sends the minimal part of a name to
+//!   upstream servers (RFC 9156), detected with the `qnamemintest.internet.nl` test.
+//! - **DoT**: The resolver accepts connections on the DNS-over-TLS port 853.
+
+use super::client::{self, QueryOptions, RecordData, RecordType};
+use super::system_nameservers;
+use colored::{ColoredString, Colorize};
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// Timeout of a single probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Zone with valid DNSSEC signatures
+const SIGNED_NAME: &str = "ietf.org";
+
+/// Zone with deliberately broken DNSSEC signatures
+const BROKEN_SIGNATURE_NAME: &str = "dnssec-failed.org";
+
+/// Name whose TXT record tells whether the querying resolver uses QNAME minimization
+const QNAME_MIN_TEST_NAME: &str = "qnamemintest.internet.nl";
+
+/// Port of DNS over TLS (RFC 7858)
+const DOT_PORT: u16 = 853;
+
+/// SERVFAIL response code
+const RCODE_SERVFAIL: u8 = 2;
+
+/// Outcome of a single capability test
+enum Support {
+    /// The resolver supports the capability
+    Yes,
+    /// The resolver does not support the capability
+    No,
+    /// The test was inconclusive
+    Unknown,
+}
+
+impl Support {
+    /// Returns the colored table cell of the outcome
+    fn cell(&self, width: usize) -> ColoredString {
+        match self {
+            Support::Yes => format!("{:<width$}", "yes").green(),
+            Support::No => format!("{:<width$}", "no").red(),
+            Support::Unknown => format!("{:<width$}", "?").yellow(),
+        }
+    }
+}
+
+/// Capabilities of a resolver
+struct Capabilities {
+    /// EDNS(0) support
+    edns: Support,
+    /// DNSSEC validation
+    dnssec: Support,
+    /// QNAME minimization
+    qname_minimization: Support,
+    /// DNS over TLS on port 853
+    dot: Support,
+}
+
+impl Capabilities {
+    /// Summarizes the security posture of the resolver
+    fn posture(&self) -> ColoredString {
+        match (&self.dnssec, &self.qname_minimization, &self.dot) {
+            (Support::Yes, Support::Yes, Support::Yes) => "strong".green(),
+            (Support::Yes, _, _) => "moderate".yellow(),
+            _ => "weak".red(),
+        }
+    }
+}
+
+/// Probes all active resolvers and prints their capabilities
+///
+/// # Errors
+///
+/// Returns an error if the resolver configuration cannot be read or no resolver
+/// is configured.
+pub fn perform() -> Result<(), Box<dyn Error>> {
+    let servers = system_nameservers()?;
+    if servers.is_empty() {
+        return Err("No DNS resolvers configured".into());
+    }
+
+    println!("Probing {} resolver(s), this may take a few seconds", servers.len());
+    let results: Vec<Capabilities> = thread::scope(|scope| {
+        let handles: Vec<_> = servers
+            .iter()
+            .map(|&server| scope.spawn(move || probe(server)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle.join().unwrap_or(Capabilities {
+                    edns: Support::Unknown,
+                    dnssec: Support::Unknown,
+                    qname_minimization: Support::Unknown,
+                    dot: Support::Unknown,
+                })
+            })
+            .collect()
+    });
+
+    println!();
+    println!(
+        "{:<40} {:<6} {:<8} {:<10} {:<6} POSTURE",
+        "RESOLVER", "EDNS", "DNSSEC", "QNAME-MIN", "DOT"
+    );
+    for (server, capabilities) in servers.iter().zip(&results) {
+        println!(
+            "{:<40} {} {} {} {} {}",
+            server.to_string(),
+            capabilities.edns.cell(6),
+            capabilities.dnssec.cell(8),
+            capabilities.qname_minimization.cell(10),
+            capabilities.dot.cell(6),
+            capabilities.posture()
+        );
+    }
+
+    Ok(())
+}
+
+/// Runs all capability tests against a resolver
+fn probe(server: IpAddr) -> Capabilities {
+    Capabilities {
+        edns: probe_edns(server),
+        dnssec: probe_dnssec(server),
+        qname_minimization: probe_qname_minimization(server),
+        dot: probe_dot(server),
+    }
+}
+
+/// Tests whether the resolver answers EDNS(0) queries with an OPT record
+fn probe_edns(server: IpAddr) -> Support {
+    let options = QueryOptions {
+        edns: true,
+        ..QueryOptions::default()
+    };
+    match client::query_with(server, ".", RecordType::Ns, PROBE_TIMEOUT, options) {
+        Ok(response) if response.edns_udp_size.is_some() => Support::Yes,
+        Ok(_) => Support::No,
+        Err(_) => Support::Unknown,
+    }
+}
+
+/// Tests whether the resolver validates DNSSEC signatures
+///
+/// A validating resolver marks answers from a signed zone as authentic and fails
+/// queries for a zone with broken signatures.
+fn probe_dnssec(server: IpAddr) -> Support {
+    let options = QueryOptions {
+        dnssec_ok: true,
+        ..QueryOptions::default()
+    };
+    let signed = client::query_with(server, SIGNED_NAME, RecordType::A, PROBE_TIMEOUT, options);
+    let broken = client::query_with(server, BROKEN_SIGNATURE_NAME, RecordType::A, PROBE_TIMEOUT, options);
+
+    match (signed, broken) {
+        (Ok(signed), Ok(broken)) => {
+            if signed.authentic_data && broken.rcode == RCODE_SERVFAIL {
+                Support::Yes
+            } else {
+                Support::No
+            }
+        }
+        // Validating resolvers may drop instead of fail queries for broken zones
+        (Ok(signed), Err(_)) if signed.authentic_data => Support::Yes,
+        _ => Support::Unknown,
+    }
+}
+
+/// Tests whether the resolver uses QNAME minimization
+///
+/// The test zone answers with a TXT record starting with `HOORAY` if the queries
+/// reaching it were minimized, and with `NO` otherwise.
+fn probe_qname_minimization(server: IpAddr) -> Support {
+    let Ok(response) = client::query(server, QNAME_MIN_TEST_NAME, RecordType::Txt, PROBE_TIMEOUT) else {
+        return Support::Unknown;
+    };
+
+    let text: String = response
+        .answers
+        .iter()
+        .filter_map(|record| match &record.data {
+            RecordData::Txt(strings) => Some(strings.concat()),
+            _ => None,
+        })
+        .collect();
+    if text.starts_with("HOORAY") {
+        Support::Yes
+    } else if text.starts_with("NO") {
+        Support::No
+    } else {
+        Support::Unknown
+    }
+}
+
+/// Tests whether the resolver accepts connections on the DNS-over-TLS port
+fn probe_dot(server: IpAddr) -> Support {
+    match TcpStream::connect_timeout(&SocketAddr::new(server, DOT_PORT), PROBE_TIMEOUT) {
+        Ok(_) => Support::Yes,
+        Err(_) => Support::No,
+    }
+}
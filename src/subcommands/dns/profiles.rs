@@ -0,0 +1,112 @@
+//! Named DNS server profiles
+//!
+//! Profiles are stored in the `[dns.profiles]` table of the rempower
+//! configuration file and can be applied with `rem dns --profile <NAME>`.
+
+use crate::cli::ProfilesCommands;
+use crate::config::{Config, DnsProfile};
+use colored::Colorize;
+use std::error::Error;
+use std::net::IpAddr;
+
+/// Performs profile management operations
+///
+/// # Arguments
+///
+/// * `command` - Profile operation from the command line
+///
+/// # Errors
+///
+/// Returns an error if the configuration file cannot be read or written,
+/// or if a profile to remove does not exist.
+pub fn perform(command: ProfilesCommands) -> Result<(), Box<dyn Error>> {
+    match command {
+        ProfilesCommands::List => list_profiles(),
+        ProfilesCommands::Add { name, servers } => add_profile(name, servers),
+        ProfilesCommands::Remove { name } => remove_profile(&name),
+    }
+}
+
+/// Looks up the DNS servers of a profile
+///
+/// # Arguments
+///
+/// * `name` - Name of the profile
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or the profile does not exist.
+pub fn profile_servers(name: &str) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+    let config = Config::load()?;
+    match config.dns.profiles.get(name) {
+        Some(profile) => Ok(profile.servers.clone()),
+        None => Err(unknown_profile(name, &config)),
+    }
+}
+
+/// Prints all configured profiles with their DNS servers
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded.
+fn list_profiles() -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+
+    if config.dns.profiles.is_empty() {
+        println!("No DNS profiles configured. Add one with 'rem dns profiles add <NAME> <SERVER>...'");
+        return Ok(());
+    }
+
+    for (name, profile) in &config.dns.profiles {
+        println!("{name:>30} : {:?}", profile.servers);
+    }
+
+    Ok(())
+}
+
+/// Adds a profile, replacing an existing profile of the same name
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved.
+fn add_profile(name: String, servers: Vec<IpAddr>) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load()?;
+    let replaced = config
+        .dns
+        .profiles
+        .insert(name.clone(), DnsProfile { servers })
+        .is_some();
+    config.save(&["dns", "profiles"])?;
+
+    let action = if replaced { "Updated" } else { "Added" };
+    println!("{} DNS profile '{name}'", action.green());
+
+    Ok(())
+}
+
+/// Removes a profile
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved, or the profile does not exist.
+fn remove_profile(name: &str) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load()?;
+    if config.dns.profiles.remove(name).is_none() {
+        return Err(unknown_profile(name, &config));
+    }
+    config.save(&["dns", "profiles"])?;
+
+    println!("{} DNS profile '{name}'", "Removed".green());
+
+    Ok(())
+}
+
+/// Builds an error for an unknown profile listing the available profile names
+fn unknown_profile(name: &str, config: &Config) -> Box<dyn Error> {
+    let available: Vec<&str> = config.dns.profiles.keys().map(String::as_str).collect();
+    if available.is_empty() {
+        format!("Unknown DNS profile '{name}' (no profiles configured)").into()
+    } else {
+        format!("Unknown DNS profile '{name}' (available: {})", available.join(", ")).into()
+    }
+}
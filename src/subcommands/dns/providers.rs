@@ -0,0 +1,240 @@
+//! Registry of public DNS providers
+//!
+//! Each entry describes one resolver service of a provider, including filtered
+//! variants (malware blocking, family filters, ad blocking). Entries are selected
+//! by their ID, e.g. `rem dns --pub quad9`. Entries with encrypted endpoints can
+//! also be used with `rem dns doh`.
+
+use colored::Colorize;
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr};
+
+/// ID of the provider used by `rem dns --pub` without a provider name
+pub const DEFAULT_PROVIDER: &str = "default";
+
+/// A public DNS resolver service
+pub struct Provider {
+    /// Unique ID used to select the provider on the command line
+    pub id: &'static str,
+    /// Display name of the provider
+    pub name: &'static str,
+    /// Kind of filtering applied by the resolver
+    pub filter: &'static str,
+    /// IPv4 and IPv6 addresses of the resolver
+    pub servers: &'static [&'static str],
+    /// URL of the DNS over HTTPS endpoint, if available
+    pub doh_url: Option<&'static str>,
+    /// TLS server name of the DNS over TLS endpoint, if available
+    pub tls_name: Option<&'static str>,
+}
+
+/// All known public DNS providers
+pub const PROVIDERS: &[Provider] = &[
+    Provider {
+        id: DEFAULT_PROVIDER,
+        name: "CloudFlare + Google",
+        filter: "none",
+        servers: &["1.1.1.1", "2606:4700:4700::1111", "8.8.4.4", "2001:4860:4860::8844"],
+        doh_url: None,
+        tls_name: None,
+    },
+    Provider {
+        id: "cloudflare",
+        name: "CloudFlare",
+        filter: "none",
+        servers: &["1.1.1.1", "1.0.0.1", "2606:4700:4700::1111", "2606:4700:4700::1001"],
+        doh_url: Some("https://cloudflare-dns.com/dns-query"),
+        tls_name: Some("one.one.one.one"),
+    },
+    Provider {
+        id: "cloudflare-malware",
+        name: "CloudFlare",
+        filter: "malware",
+        servers: &["1.1.1.2", "1.0.0.2", "2606:4700:4700::1112", "2606:4700:4700::1002"],
+        doh_url: Some("https://security.cloudflare-dns.com/dns-query"),
+        tls_name: Some("security.cloudflare-dns.com"),
+    },
+    Provider {
+        id: "cloudflare-family",
+        name: "CloudFlare",
+        filter: "malware, adult content",
+        servers: &["1.1.1.3", "1.0.0.3", "2606:4700:4700::1113", "2606:4700:4700::1003"],
+        doh_url: Some("https://family.cloudflare-dns.com/dns-query"),
+        tls_name: Some("family.cloudflare-dns.com"),
+    },
+    Provider {
+        id: "google",
+        name: "Google",
+        filter: "none",
+        servers: &["8.8.8.8", "8.8.4.4", "2001:4860:4860::8888", "2001:4860:4860::8844"],
+        doh_url: Some("https://dns.google/dns-query"),
+        tls_name: Some("dns.google"),
+    },
+    Provider {
+        id: "quad9",
+        name: "Quad9",
+        filter: "malware",
+        servers: &["9.9.9.9", "149.112.112.112", "2620:fe::fe", "2620:fe::9"],
+        doh_url: Some("https://dns.quad9.net/dns-query"),
+        tls_name: Some("dns.quad9.net"),
+    },
+    Provider {
+        id: "quad9-unfiltered",
+        name: "Quad9",
+        filter: "none",
+        servers: &["9.9.9.10", "149.112.112.10", "2620:fe::10", "2620:fe::fe:10"],
+        doh_url: Some("https://dns10.quad9.net/dns-query"),
+        tls_name: Some("dns10.quad9.net"),
+    },
+    Provider {
+        id: "opendns",
+        name: "OpenDNS",
+        filter: "none",
+        servers: &["208.67.222.222", "208.67.220.220", "2620:119:35::35", "2620:119:53::53"],
+        doh_url: Some("https://doh.opendns.com/dns-query"),
+        tls_name: None,
+    },
+    Provider {
+        id: "opendns-family",
+        name: "OpenDNS FamilyShield",
+        filter: "adult content",
+        servers: &["208.67.222.123", "208.67.220.123"],
+        doh_url: Some("https://doh.familyshield.opendns.com/dns-query"),
+        tls_name: None,
+    },
+    Provider {
+        id: "adguard",
+        name: "AdGuard",
+        filter: "ads, trackers",
+        servers: &["94.140.14.14", "94.140.15.15", "2a10:50c0::ad1:ff", "2a10:50c0::ad2:ff"],
+        doh_url: Some("https://dns.adguard-dns.com/dns-query"),
+        tls_name: Some("dns.adguard-dns.com"),
+    },
+    Provider {
+        id: "adguard-family",
+        name: "AdGuard",
+        filter: "ads, trackers, adult content",
+        servers: &[
+            "94.140.14.15",
+            "94.140.15.16",
+            "2a10:50c0::bad1:ff",
+            "2a10:50c0::bad2:ff",
+        ],
+        doh_url: Some("https://family.adguard-dns.com/dns-query"),
+        tls_name: Some("family.adguard-dns.com"),
+    },
+    Provider {
+        id: "adguard-unfiltered",
+        name: "AdGuard",
+        filter: "none",
+        servers: &["94.140.14.140", "94.140.14.141", "2a10:50c0::1:ff", "2a10:50c0::2:ff"],
+        doh_url: Some("https://unfiltered.adguard-dns.com/dns-query"),
+        tls_name: Some("unfiltered.adguard-dns.com"),
+    },
+    Provider {
+        id: "mullvad",
+        name: "Mullvad",
+        filter: "none",
+        servers: &["194.242.2.2", "2a07:e340::2"],
+        doh_url: Some("https://dns.mullvad.net/dns-query"),
+        tls_name: Some("dns.mullvad.net"),
+    },
+    Provider {
+        id: "mullvad-adblock",
+        name: "Mullvad",
+        filter: "ads, trackers",
+        servers: &["194.242.2.3", "2a07:e340::3"],
+        doh_url: Some("https://adblock.dns.mullvad.net/dns-query"),
+        tls_name: Some("adblock.dns.mullvad.net"),
+    },
+    Provider {
+        id: "mullvad-family",
+        name: "Mullvad",
+        filter: "ads, trackers, malware, adult content, gambling",
+        servers: &["194.242.2.6", "2a07:e340::6"],
+        doh_url: Some("https://family.dns.mullvad.net/dns-query"),
+        tls_name: Some("family.dns.mullvad.net"),
+    },
+];
+
+/// Looks up a provider by its ID
+///
+/// # Arguments
+///
+/// * `id` - Provider ID, compared case-insensitively
+///
+/// # Errors
+///
+/// Returns an error listing all valid IDs if the provider is unknown.
+pub fn find(id: &str) -> Result<&'static Provider, Box<dyn Error>> {
+    PROVIDERS
+        .iter()
+        .find(|provider| provider.id.eq_ignore_ascii_case(id))
+        .ok_or_else(|| {
+            let ids: Vec<&str> = PROVIDERS.iter().map(|provider| provider.id).collect();
+            format!("Unknown DNS provider '{id}' (available: {})", ids.join(", ")).into()
+        })
+}
+
+/// Identifies the operator of a DNS server address
+///
+/// Addresses of known providers are labeled with the provider name and filter,
+/// private and special-purpose addresses with the kind of network they belong to.
+///
+/// # Returns
+///
+/// A short label, or `None` if the address is unknown.
+pub fn identify(address: IpAddr) -> Option<String> {
+    if let Some(provider) = PROVIDERS
+        .iter()
+        .filter(|provider| provider.id != DEFAULT_PROVIDER)
+        .find(|provider| provider.servers.iter().any(|server| server.parse() == Ok(address)))
+    {
+        return Some(if provider.filter == "none" {
+            provider.name.to_string()
+        } else {
+            format!("{} ({})", provider.name, provider.filter)
+        });
+    }
+
+    let label = match address {
+        IpAddr::V4(address) if address == Ipv4Addr::new(100, 100, 100, 100) => "Tailscale",
+        IpAddr::V4(address) if address.is_loopback() => "localhost",
+        IpAddr::V4(address) if address.is_private() || address.is_link_local() => "local network (router)",
+        IpAddr::V4(address) if address.octets()[0] == 100 && address.octets()[1] & 0xc0 == 64 => {
+            "carrier-grade NAT (ISP)"
+        }
+        IpAddr::V6(address) if address.is_loopback() => "localhost",
+        IpAddr::V6(address) if address.is_unique_local() || address.is_unicast_link_local() => "local network (router)",
+        _ => return None,
+    };
+    Some(label.to_string())
+}
+
+/// Prints a table of all known providers
+pub fn print_providers() {
+    println!(
+        "{:<20} {:<22} {:<10} {:<48} SERVERS",
+        "ID".bold(),
+        "NAME".bold(),
+        "ENCRYPTED".bold(),
+        "FILTER".bold()
+    );
+
+    for provider in PROVIDERS {
+        let encrypted = match (provider.doh_url, provider.tls_name) {
+            (Some(_), Some(_)) => "DoH, DoT",
+            (Some(_), None) => "DoH",
+            (None, Some(_)) => "DoT",
+            (None, None) => "-",
+        };
+        println!(
+            "{:<20} {:<22} {:<10} {:<48} {}",
+            provider.id,
+            provider.name,
+            encrypted,
+            provider.filter,
+            provider.servers.join(", ")
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! DNS lookup tool
+//!
+//! A lightweight `dig` replacement: sends a single query to a DNS server and
+//! prints the answer records with their TTLs, the response code and the
+//! response time. Without `--server`, the first nameserver of the system
+//! DNS configuration is used.
+
+use super::client::{self, RecordType};
+use super::system_nameservers;
+use crate::cli::QueryArgs;
+use colored::Colorize;
+use std::error::Error;
+use std::time::Duration;
+
+/// Performs a DNS lookup and prints the result
+///
+/// # Arguments
+///
+/// * `args` - Query arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the record type is unknown, no nameserver is configured,
+/// or the query fails.
+pub fn perform(args: QueryArgs) -> Result<(), Box<dyn Error>> {
+    let record_type: RecordType = args.record_type.parse()?;
+    let server = match args.server {
+        Some(server) => server,
+        None => *system_nameservers()?
+            .first()
+            .ok_or("No DNS servers configured, use --server")?,
+    };
+
+    let response = client::query(server, &args.name, record_type, Duration::from_millis(args.timeout))?;
+
+    let status = client::rcode_name(response.rcode);
+    let status = if response.rcode == 0 {
+        status.green()
+    } else {
+        status.red()
+    };
+    println!(
+        ";; {} {record_type} @{server}: {status}, {} answer(s) in {} ms",
+        args.name,
+        response.answers.len(),
+        response.elapsed.as_millis()
+    );
+    if response.truncated {
+        println!("{}", ";; Response truncated, some records may be missing".yellow());
+    }
+
+    for record in &response.answers {
+        println!(
+            "{:<40} {:>8}  IN  {:<6} {}",
+            record.name,
+            record.ttl,
+            record.record_type.to_string(),
+            record.data
+        );
+    }
+
+    Ok(())
+}
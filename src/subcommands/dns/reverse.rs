@@ -0,0 +1,222 @@
+//! Reverse DNS lookup tool
+//!
+//! Looks up the PTR records of IP addresses given on the command line or read from
+//! stdin, e.g. to audit connection lists of other tools. Lines of stdin may also
+//! contain socket addresses (`140.82.121.4:443`, `[2001:db8::1]:443`) followed by
+//! further columns; only the first column is used. Lookups run in parallel and the
+//! results are printed in input order.
+
+use super::client::{self, RecordData, RecordType};
+use super::system_nameservers;
+use crate::cli::ReverseArgs;
+use colored::Colorize;
+use serde::Serialize;
+use std::error::Error;
+use std::io::{self, BufRead, IsTerminal};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Result of the reverse lookup of a single address
+#[derive(Serialize)]
+struct ReverseResult {
+    /// Looked up address
+    address: IpAddr,
+    /// Host names of the PTR records; empty if the address has none
+    names: Vec<String>,
+    /// Reason why the lookup failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Performs reverse lookups and prints the results
+///
+/// # Arguments
+///
+/// * `args` - Reverse lookup arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if no addresses are given, stdin contains invalid addresses,
+/// or no nameserver is configured.
+pub fn perform(args: ReverseArgs) -> Result<(), Box<dyn Error>> {
+    let mut addresses = if args.addresses.is_empty() {
+        read_addresses()?
+    } else {
+        args.addresses
+    };
+    dedup_in_order(&mut addresses);
+    if addresses.is_empty() {
+        return Err("No IP addresses given".into());
+    }
+
+    let server = match args.server {
+        Some(server) => server,
+        None => *system_nameservers()?
+            .first()
+            .ok_or("No DNS servers configured, use --server")?,
+    };
+    let timeout = Duration::from_millis(args.timeout);
+
+    let results = lookup_all(&addresses, server, timeout, usize::from(args.jobs));
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+
+    println!("{:<40} NAME", "ADDRESS");
+    for result in &results {
+        let names = match (&result.error, result.names.is_empty()) {
+            (Some(error), _) => error.as_str().red(),
+            (None, true) => "(no PTR record)".yellow(),
+            (None, false) => result.names.join(" ").normal(),
+        };
+        println!("{:<40} {names}", result.address.to_string());
+    }
+
+    Ok(())
+}
+
+/// Reads addresses from stdin, one per line
+///
+/// Empty lines and lines starting with `#` are skipped.
+///
+/// # Errors
+///
+/// Returns an error if stdin is a terminal, cannot be read, or a line does not
+/// start with an IP or socket address.
+fn read_addresses() -> Result<Vec<IpAddr>, Box<dyn Error>> {
+    if io::stdin().is_terminal() {
+        return Err("No IP addresses given, pass them as arguments or on stdin".into());
+    }
+
+    let mut addresses = Vec::new();
+    for (number, line) in io::stdin().lock().lines().enumerate() {
+        let line = line?;
+        let Some(token) = line.split_whitespace().next().filter(|token| !token.starts_with('#')) else {
+            continue;
+        };
+        let address = token
+            .parse::<IpAddr>()
+            .or_else(|_| token.parse::<SocketAddr>().map(|socket| socket.ip()))
+            .map_err(|_| format!("Invalid IP address '{token}' in line {}", number + 1))?;
+        addresses.push(address);
+    }
+
+    Ok(addresses)
+}
+
+/// Removes duplicate addresses, keeping the first occurrence
+fn dedup_in_order(addresses: &mut Vec<IpAddr>) {
+    let mut seen = Vec::with_capacity(addresses.len());
+    addresses.retain(|address| {
+        let new = !seen.contains(address);
+        if new {
+            seen.push(*address);
+        }
+        new
+    });
+}
+
+/// Looks up all addresses with a fixed number of parallel workers
+///
+/// # Returns
+///
+/// The results in the order of `addresses`.
+fn lookup_all(addresses: &[IpAddr], server: IpAddr, timeout: Duration, jobs: usize) -> Vec<ReverseResult> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<ReverseResult>>> = Mutex::new(addresses.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(addresses.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&address) = addresses.get(index) else {
+                        break;
+                    };
+                    let result = lookup(address, server, timeout);
+                    if let Ok(mut results) = results.lock() {
+                        results[index] = Some(result);
+                    }
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_default()
+        .into_iter()
+        .zip(addresses)
+        .map(|(result, &address)| {
+            result.unwrap_or(ReverseResult {
+                address,
+                names: Vec::new(),
+                error: Some("lookup aborted".to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Looks up the PTR records of a single address
+fn lookup(address: IpAddr, server: IpAddr, timeout: Duration) -> ReverseResult {
+    let (names, error) = match ptr_names(address, server, timeout) {
+        Ok(names) => (names, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+
+    ReverseResult { address, names, error }
+}
+
+/// Returns the host names of the PTR records of an address, without trailing dot
+///
+/// # Arguments
+///
+/// * `address` - Address to look up
+/// * `server` - DNS server to query
+/// * `timeout` - Maximum time to wait for the response
+///
+/// # Errors
+///
+/// Returns the reason if the query fails; a missing PTR record is not an error.
+pub fn ptr_names(address: IpAddr, server: IpAddr, timeout: Duration) -> Result<Vec<String>, String> {
+    match client::query(server, &reverse_name(address), RecordType::Ptr, timeout) {
+        // NXDOMAIN only means that there is no PTR record
+        Ok(response) if response.rcode == 0 || response.rcode == 3 => Ok(response
+            .answers
+            .iter()
+            .filter_map(|record| match &record.data {
+                RecordData::Name(name) if record.record_type == RecordType::Ptr => {
+                    Some(name.strip_suffix('.').unwrap_or(name).to_string())
+                }
+                _ => None,
+            })
+            .collect()),
+        Ok(response) => Err(client::rcode_name(response.rcode).to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Returns the name of the PTR record of an address
+///
+/// IPv4 addresses map to `in-addr.arpa` (RFC 1035), IPv6 addresses to the nibble
+/// format below `ip6.arpa` (RFC 3596).
+pub fn reverse_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, c, d] = address.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(address) => {
+            let mut name = String::with_capacity(72);
+            for byte in address.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name + "ip6.arpa"
+        }
+    }
+}
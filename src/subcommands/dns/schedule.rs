@@ -0,0 +1,307 @@
+//! Scheduled DNS changes
+//!
+//! Every schedule entry becomes a LaunchAgent in `~/Library/LaunchAgents` that
+//! runs `rem dns <ACTION>` daily at the given time. Output of scheduled runs is
+//! appended to `dns-schedule.log` in the rempower state directory.
+//!
+//! Scheduled runs have no terminal to ask for a sudo password, so `networksetup`
+//! must be allowed to run with sudo without a password.
+//!
+//! # System Commands Used
+//!
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload LaunchAgents
+//! - `plutil -convert json` - Read installed LaunchAgents
+
+use crate::cli::{DnsArgs, ScheduleArgs, ScheduleCommands};
+use crate::config;
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use crate::util::parse_time;
+use clap::Parser;
+use colored::Colorize;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Label prefix of generated LaunchAgents; the time is appended as `HHMM`
+const LABEL_PREFIX: &str = "com.github.rfoerthe.rempower.dns-schedule.";
+
+/// Name of the log file of scheduled runs inside the state directory
+const LOG_FILE: &str = "dns-schedule.log";
+
+/// Option separating the DNS action from its time
+const AT_OPTION: &str = "--at";
+
+/// A DNS action run daily at a fixed time
+struct Entry {
+    /// Hour of the day (0-23)
+    hour: u8,
+    /// Minute of the hour (0-59)
+    minute: u8,
+    /// Arguments passed to `rem dns`
+    action: Vec<String>,
+}
+
+impl Entry {
+    /// Returns the LaunchAgent label of this entry
+    fn label(&self) -> String {
+        format!("{LABEL_PREFIX}{:02}{:02}", self.hour, self.minute)
+    }
+
+    /// Returns the time of this entry as `HH:MM`
+    fn time(&self) -> String {
+        format!("{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// Performs schedule operations
+///
+/// # Arguments
+///
+/// * `args` - Schedule arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the entries are invalid or LaunchAgents cannot be written,
+/// loaded or unloaded.
+pub fn perform(args: ScheduleArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Some(ScheduleCommands::List) => list_entries(),
+        Some(ScheduleCommands::Remove { time, all }) => remove_entries(time.as_deref(), all),
+        None => add_entries(&parse_entries(&args.entries)?),
+    }
+}
+
+/// Splits the command line into schedule entries
+///
+/// # Errors
+///
+/// Returns an error if an action has no time, a time is invalid or occurs twice,
+/// or an action is not a valid `rem dns` action.
+fn parse_entries(args: &[String]) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut action: Vec<String> = Vec::new();
+    let mut tokens = args.iter();
+
+    while let Some(token) = tokens.next() {
+        let time = if token == AT_OPTION {
+            tokens.next().ok_or("Missing time after --at")?.as_str()
+        } else if let Some(time) = token.strip_prefix("--at=") {
+            time
+        } else {
+            action.push(token.clone());
+            continue;
+        };
+
+        if action.is_empty() {
+            return Err(format!("No DNS action given for --at {time}").into());
+        }
+        validate_action(&action)?;
+
+        let (hour, minute) = parse_time(time)?;
+        let entry = Entry {
+            hour,
+            minute,
+            action: std::mem::take(&mut action),
+        };
+        if entries.iter().any(|known| known.label() == entry.label()) {
+            return Err(format!("Time {} is scheduled twice", entry.time()).into());
+        }
+        entries.push(entry);
+    }
+
+    if !action.is_empty() {
+        return Err(format!("Missing --at HH:MM after '{}'", action.join(" ")).into());
+    }
+
+    Ok(entries)
+}
+
+/// Checks that the arguments form a valid `rem dns` action
+///
+/// # Errors
+///
+/// Returns the parser error if the arguments are rejected by `rem dns`.
+fn validate_action(action: &[String]) -> Result<(), Box<dyn Error>> {
+    let args =
+        DnsArgs::try_parse_from(std::iter::once("rem dns").chain(action.iter().map(String::as_str))).map_err(|e| {
+            let message = e.to_string();
+            let reason = message.lines().next().unwrap_or_default().trim_start_matches("error: ");
+            format!("Invalid DNS action '{}': {reason}", action.join(" "))
+        })?;
+    if args.command.is_some() {
+        return Err(format!(
+            "Invalid DNS action '{}': DNS subcommands cannot be scheduled",
+            action.join(" ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Writes and loads a LaunchAgent for each entry
+///
+/// An existing LaunchAgent for the same time is replaced.
+///
+/// # Errors
+///
+/// Returns an error if a LaunchAgent cannot be written or loaded.
+fn add_entries(entries: &[Entry]) -> Result<(), Box<dyn Error>> {
+    let program = env::current_exe()?;
+    let log = config::state_dir()?.join(LOG_FILE);
+    if let Some(state_dir) = log.parent() {
+        fs::create_dir_all(state_dir)?;
+    }
+
+    for entry in entries {
+        let arguments: Vec<String> = [program.to_string_lossy().to_string(), "dns".to_string()]
+            .into_iter()
+            .chain(entry.action.iter().cloned())
+            .collect();
+        LaunchAgent {
+            label: &entry.label(),
+            arguments: &arguments,
+            start: Start::Daily(entry.hour, entry.minute),
+            log: Log::All(&log),
+        }
+        .install()?;
+        println!(
+            "{} rem dns {} daily at {}",
+            "Scheduled".green(),
+            entry.action.join(" "),
+            entry.time()
+        );
+    }
+
+    println!(
+        "{}",
+        "Scheduled runs cannot ask for a password. Allow 'sudo networksetup' without a password by adding\n  \
+         <user> ALL=(root) NOPASSWD: /usr/sbin/networksetup\n\
+         with 'sudo visudo -f /etc/sudoers.d/rempower'"
+            .yellow()
+    );
+
+    Ok(())
+}
+
+/// Prints all scheduled entries ordered by time
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgents cannot be read.
+fn list_entries() -> Result<(), Box<dyn Error>> {
+    let entries = installed_entries()?;
+    if entries.is_empty() {
+        println!("No DNS changes scheduled");
+        return Ok(());
+    }
+
+    for entry in entries {
+        println!("{}  rem dns {}", entry.time().bold(), entry.action.join(" "));
+    }
+
+    Ok(())
+}
+
+/// Unloads and deletes the LaunchAgents of the given time, or all of them
+///
+/// # Errors
+///
+/// Returns an error if the time is invalid, nothing is scheduled at that time or
+/// a LaunchAgent cannot be unloaded.
+fn remove_entries(time: Option<&str>, all: bool) -> Result<(), Box<dyn Error>> {
+    let wanted = time.map(parse_time).transpose()?;
+    let entries: Vec<Entry> = installed_entries()?
+        .into_iter()
+        .filter(|entry| all || wanted == Some((entry.hour, entry.minute)))
+        .collect();
+
+    if entries.is_empty() {
+        return match time {
+            Some(time) => Err(format!("No DNS change scheduled at {time}").into()),
+            None => {
+                println!("No DNS changes scheduled");
+                Ok(())
+            }
+        };
+    }
+
+    for entry in entries {
+        launchd::uninstall(&entry.label())?;
+        println!(
+            "{} rem dns {} at {}",
+            "Removed".green(),
+            entry.action.join(" "),
+            entry.time()
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads all LaunchAgents generated by this module, ordered by time
+///
+/// # Errors
+///
+/// Returns an error if a LaunchAgent cannot be converted by `plutil`.
+fn installed_entries() -> Result<Vec<Entry>, Box<dyn Error>> {
+    let dir = launchd::agents_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        let is_schedule = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(LABEL_PREFIX) && name.ends_with(".plist"));
+        if is_schedule {
+            entries.push(read_launch_agent(&path)?);
+        }
+    }
+    entries.sort_by_key(|entry| (entry.hour, entry.minute));
+
+    Ok(entries)
+}
+
+/// Reads a generated LaunchAgent back into an entry
+///
+/// # Errors
+///
+/// Returns an error if `plutil` fails or the LaunchAgent lacks the expected keys.
+fn read_launch_agent(path: &Path) -> Result<Entry, Box<dyn Error>> {
+    let output = Command::new("plutil")
+        .args(["-convert", "json", "-o", "-"])
+        .arg(path)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to read {}: {}", path.display(), stderr.trim()).into());
+    }
+
+    let plist: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let invalid = || format!("Invalid LaunchAgent {}", path.display());
+    let interval = &plist["StartCalendarInterval"];
+    let number = |key: &str| {
+        interval[key]
+            .as_u64()
+            .and_then(|value| u8::try_from(value).ok())
+            .ok_or_else(invalid)
+    };
+    let action = plist["ProgramArguments"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .skip(2)
+        .filter_map(|arg| arg.as_str().map(str::to_string))
+        .collect();
+
+    Ok(Entry {
+        hour: number("Hour")?,
+        minute: number("Minute")?,
+        action,
+    })
+}
@@ -0,0 +1,269 @@
+//! Split DNS via /etc/resolver
+//!
+//! macOS resolves names below a domain with the DNS servers listed in
+//! `/etc/resolver/<domain>`, while all other names use the regular DNS servers.
+//! This is commonly needed with split-tunnel VPNs whose internal domains are only
+//! resolvable by internal DNS servers.
+//!
+//! Files written by rempower start with a marker comment, so other resolver files
+//! (e.g. installed by VPN clients) are only removed on explicit request. If the
+//! resolver directory is not writable by the current user, it is changed with sudo.
+
+use crate::cli::{SplitArgs, SplitCommands};
+use crate::util::run_sudo;
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// First line of resolver files written by rempower
+const MANAGED_MARKER: &str = "# Managed by rempower";
+
+/// Resolver configuration of a single domain
+struct Resolver {
+    /// Domain the resolver file applies to
+    domain: String,
+    /// DNS servers of the domain
+    nameservers: Vec<String>,
+    /// Port of the DNS servers, if not the default
+    port: Option<String>,
+    /// Whether the file was written by rempower
+    managed: bool,
+}
+
+/// Performs split DNS operations
+///
+/// # Arguments
+///
+/// * `args` - Split DNS arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the resolver directory cannot be read or changed, or the
+/// domain to remove is not configured.
+pub fn perform(args: SplitArgs) -> Result<(), Box<dyn Error>> {
+    let dir = args.dir.as_path();
+    match args.command {
+        SplitCommands::List => list_resolvers(dir),
+        SplitCommands::Add { domain, servers, port } => add_resolver(dir, &domain, &servers, port),
+        SplitCommands::Remove { domain, force } => remove_resolver(dir, &domain, force),
+    }
+}
+
+/// Prints all resolver files of the directory
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read.
+fn list_resolvers(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let resolvers = read_resolvers(dir)?;
+    if resolvers.is_empty() {
+        println!("No split DNS domains configured in {}", dir.display());
+        return Ok(());
+    }
+
+    println!("{:<32} {:<8} NAMESERVERS", "DOMAIN", "SOURCE");
+    for resolver in resolvers {
+        let source = if resolver.managed { "rempower" } else { "external" };
+        let mut servers = resolver.nameservers.join(" ");
+        if let Some(port) = resolver.port {
+            servers.push_str(&format!(" (port {port})"));
+        }
+        println!("{:<32} {source:<8} {servers}", resolver.domain);
+    }
+
+    Ok(())
+}
+
+/// Writes the resolver file of a domain, replacing an existing one written by rempower
+///
+/// # Errors
+///
+/// Returns an error if a resolver file not written by rempower exists or the file
+/// cannot be written.
+fn add_resolver(dir: &Path, domain: &str, servers: &[IpAddr], port: Option<u16>) -> Result<(), Box<dyn Error>> {
+    let domain = domain.to_lowercase();
+    let path = dir.join(&domain);
+    if let Ok(content) = fs::read_to_string(&path)
+        && !is_managed(&content)
+    {
+        return Err(format!(
+            "{} exists and was not created by rempower, remove it first with `rem dns split remove {domain} --force`",
+            path.display()
+        )
+        .into());
+    }
+
+    let mut content = format!("{MANAGED_MARKER}\n");
+    for server in servers {
+        content.push_str(&format!("nameserver {server}\n"));
+    }
+    if let Some(port) = port {
+        content.push_str(&format!("port {port}\n"));
+    }
+
+    create_dir(dir)?;
+    write_file(&path, &content)?;
+    let servers: Vec<String> = servers.iter().map(IpAddr::to_string).collect();
+    println!(
+        "{} split DNS for {}: {}",
+        "Added".green(),
+        domain.bold(),
+        servers.join(" ")
+    );
+
+    Ok(())
+}
+
+/// Removes the resolver file of a domain
+///
+/// # Errors
+///
+/// Returns an error if the domain has no resolver file, the file was not written by
+/// rempower and `force` is not set, or the file cannot be removed.
+fn remove_resolver(dir: &Path, domain: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    let domain = domain.to_lowercase();
+    let path = dir.join(&domain);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(format!("No split DNS configured for {domain}").into());
+        }
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display()).into()),
+    };
+    if !force && !is_managed(&content) {
+        return Err(format!(
+            "{} was not created by rempower, use --force to remove it anyway",
+            path.display()
+        )
+        .into());
+    }
+
+    remove_file(&path)?;
+    println!("{} split DNS for {}", "Removed".green(), domain.bold());
+
+    Ok(())
+}
+
+/// Reads all resolver files of the directory, sorted by domain
+///
+/// A missing directory is treated as empty.
+///
+/// # Errors
+///
+/// Returns an error if the directory exists but cannot be read.
+fn read_resolvers(dir: &Path) -> Result<Vec<Resolver>, Box<dyn Error>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {e}", dir.display()).into()),
+    };
+
+    let mut resolvers = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        resolvers.push(parse_resolver(&entry.file_name().to_string_lossy(), &content));
+    }
+    resolvers.sort_by(|a, b| a.domain.cmp(&b.domain));
+
+    Ok(resolvers)
+}
+
+/// Parses the content of a resolver file (see `man 5 resolver`)
+fn parse_resolver(domain: &str, content: &str) -> Resolver {
+    let mut nameservers = Vec::new();
+    let mut port = None;
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some("nameserver"), Some(server)) => nameservers.push(server.to_string()),
+            (Some("port"), Some(value)) => port = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    Resolver {
+        domain: domain.to_string(),
+        nameservers,
+        port,
+        managed: is_managed(content),
+    }
+}
+
+/// Returns true if a resolver file was written by rempower
+fn is_managed(content: &str) -> bool {
+    content.lines().next() == Some(MANAGED_MARKER)
+}
+
+/// Creates the resolver directory, using sudo if necessary
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be created.
+fn create_dir(dir: &Path) -> Result<(), Box<dyn Error>> {
+    match fs::create_dir_all(dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            run_sudo(&["mkdir", "-p", &dir.to_string_lossy()]).map(|_| ())
+        }
+        Err(e) => Err(format!("Failed to create {}: {e}", dir.display()).into()),
+    }
+}
+
+/// Writes a resolver file, using `sudo tee` if it is not writable by the current user
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+fn write_file(path: &Path, content: &str) -> Result<(), Box<dyn Error>> {
+    match fs::write(path, content) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {}
+        Err(e) => return Err(format!("Failed to write {}: {e}", path.display()).into()),
+    }
+
+    let mut child = Command::new("sudo")
+        .arg("tee")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open stdin of sudo tee")?
+        .write_all(content.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to write {}: {}", path.display(), stderr.trim()).into());
+    }
+
+    Ok(())
+}
+
+/// Removes a resolver file, using sudo if necessary
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be removed.
+fn remove_file(path: &Path) -> Result<(), Box<dyn Error>> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            run_sudo(&["rm", "-f", &path.to_string_lossy()]).map(|_| ())
+        }
+        Err(e) => Err(format!("Failed to remove {}: {e}", path.display()).into()),
+    }
+}
@@ -0,0 +1,148 @@
+//! File search with Spotlight
+//!
+//! Searches the Spotlight index with a friendly query syntax (see [`query`])
+//! and prints the files with their modification date and size, most recently
+//! modified first. `--exec` runs a shell command for every file instead, with
+//! the path passed as argument, so names with spaces or quotes need no
+//! escaping.
+//!
+//! # System Commands Used
+//!
+//! - `mdfind -0` - Search the Spotlight index
+//! - `sh -c` - Run the command of `--exec`
+
+pub mod query;
+
+use crate::cli::FindArgs;
+use crate::util::{format_bytes, format_time};
+use colored::Colorize;
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A file found by Spotlight
+#[derive(Serialize)]
+struct Found {
+    /// Path of the file
+    path: PathBuf,
+    /// Size in bytes; `None` for folders
+    size: Option<u64>,
+    /// Time of the last modification in local time
+    modified: String,
+    /// Time of the last modification in seconds since 1970
+    #[serde(skip)]
+    modified_seconds: i64,
+}
+
+/// Searches files with Spotlight
+///
+/// # Arguments
+///
+/// * `args` - Find arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the query is invalid, `mdfind` fails or a command of
+/// `--exec` fails.
+pub fn perform(args: FindArgs) -> Result<(), Box<dyn Error>> {
+    let predicate = query::predicate(&args.query.join(" "))?;
+    if args.predicate {
+        println!("{predicate}");
+        return Ok(());
+    }
+
+    let mut command = Command::new("mdfind");
+    command.arg("-0");
+    if let Some(folder) = &args.folder {
+        if !folder.is_dir() {
+            return Err(format!("{} is not a folder", folder.display()).into());
+        }
+        command.arg("-onlyin").arg(folder);
+    }
+    let output = command
+        .arg(&predicate)
+        .output()
+        .map_err(|e| format!("Failed to run mdfind: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("mdfind failed: {}", stderr.trim()).into());
+    }
+
+    // Files deleted since they were indexed are left out
+    let mut found: Vec<Found> = String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .filter_map(|path| {
+            let metadata = fs::metadata(path).ok()?;
+            Some(Found {
+                path: PathBuf::from(path),
+                size: metadata.is_file().then_some(metadata.len()),
+                modified: format_time(metadata.mtime(), true),
+                modified_seconds: metadata.mtime(),
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| b.modified_seconds.cmp(&a.modified_seconds));
+    if let Some(limit) = args.limit {
+        found.truncate(limit as usize);
+    }
+
+    if let Some(exec) = &args.exec {
+        return exec_each(exec, &found);
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+        return Ok(());
+    }
+    if found.is_empty() {
+        println!("No files found");
+        return Ok(());
+    }
+    for file in &found {
+        let size = match file.size {
+            Some(size) => format_bytes(size as f64).normal(),
+            None => "folder".dimmed(),
+        };
+        println!(
+            "{:<18}{:>10}  {}",
+            format_time(file.modified_seconds, false),
+            size,
+            file.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Runs a shell command for every file
+///
+/// `{}` in the command is replaced by the path, which is appended otherwise.
+/// The path is passed to the shell as `$1`, never pasted into the command.
+///
+/// # Errors
+///
+/// Returns an error if the shell cannot be run or a command fails.
+fn exec_each(exec: &str, found: &[Found]) -> Result<(), Box<dyn Error>> {
+    let script = if exec.contains("{}") {
+        exec.replace("{}", "\"$1\"")
+    } else {
+        format!("{exec} \"$1\"")
+    };
+    let mut failed = 0;
+    for file in found {
+        let status = Command::new("sh")
+            .args(["-c", &script, "sh"])
+            .arg(&file.path)
+            .status()
+            .map_err(|e| format!("Failed to run sh: {e}"))?;
+        if !status.success() {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("The command failed for {failed} of {} files", found.len()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//! Friendly search syntax
+//!
+//! Translates a query like `kind:pdf modified:<7d invoice` into a Spotlight
+//! query expression for `mdfind`. Every term must match:
+//!
+//! - `kind:pdf` - Content type, e.g. `image`, `movie`, `audio`, `folder`, `app`;
+//!   an unknown kind is taken as file extension
+//! - `ext:txt` - File extension
+//! - `name:report` - Part of the file name
+//! - `modified:<7d`, `created:>1y`, `used:today` - Within or older than an age
+//!   in days, weeks or years, or before or after a date like `>2024-01-31`
+//! - `size:>100MB` - Larger or smaller than a size
+//! - `tag:red` - Finder tag
+//! - `author:smith` - Part of an author name
+//! - Other words - Part of the name or a word of the contents
+//!
+//! A leading `-` excludes a term, quotes keep spaces: `name:"annual report"`.
+
+use crate::cli::{parse_age, parse_size};
+use std::error::Error;
+
+/// Kinds with the content type their files conform to
+const KINDS: [(&str, &str); 18] = [
+    ("pdf", "com.adobe.pdf"),
+    ("image", "public.image"),
+    ("movie", "public.movie"),
+    ("video", "public.movie"),
+    ("audio", "public.audio"),
+    ("music", "public.audio"),
+    ("text", "public.text"),
+    ("folder", "public.folder"),
+    ("app", "com.apple.application-bundle"),
+    ("application", "com.apple.application-bundle"),
+    ("archive", "public.archive"),
+    ("code", "public.source-code"),
+    ("presentation", "public.presentation"),
+    ("spreadsheet", "public.spreadsheet"),
+    ("email", "com.apple.mail.emlx"),
+    ("font", "public.font"),
+    ("contact", "public.contact"),
+    ("bookmark", "com.apple.safari.bookmark"),
+];
+
+/// Date attributes by filter name
+const DATES: [(&str, &str); 4] = [
+    ("modified", "kMDItemFSContentChangeDate"),
+    ("created", "kMDItemFSCreationDate"),
+    ("used", "kMDItemLastUsedDate"),
+    ("opened", "kMDItemLastUsedDate"),
+];
+
+/// Builds the Spotlight query expression of a friendly query
+///
+/// # Arguments
+///
+/// * `query` - Words and filters separated by spaces
+///
+/// # Errors
+///
+/// Returns an error if a filter has an invalid value or the query is empty.
+pub fn predicate(query: &str) -> Result<String, Box<dyn Error>> {
+    let terms = split(query)?;
+    if terms.is_empty() {
+        return Err("The query is empty".into());
+    }
+    let conditions: Vec<String> = terms.iter().map(|term| condition(term)).collect::<Result<_, _>>()?;
+    Ok(conditions.join(" && "))
+}
+
+/// Splits a query into terms at spaces outside of quotes and removes the quotes
+///
+/// # Errors
+///
+/// Returns an error if a quote is not closed.
+fn split(query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+    if quoted {
+        return Err("A quote in the query is not closed".into());
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    Ok(terms)
+}
+
+/// Builds the condition of a term
+///
+/// # Errors
+///
+/// Returns an error if a filter has an invalid value.
+fn condition(term: &str) -> Result<String, Box<dyn Error>> {
+    if let Some(excluded) = term.strip_prefix('-').filter(|excluded| !excluded.is_empty()) {
+        return Ok(format!("!({})", condition(excluded)?));
+    }
+    let Some((filter, value)) = term.split_once(':').filter(|(_, value)| !value.is_empty()) else {
+        return Ok(text(term));
+    };
+    let filter = filter.to_lowercase();
+    if let Some((_, attribute)) = DATES.iter().find(|(name, _)| *name == filter) {
+        return date(attribute, value);
+    }
+    let condition = match filter.as_str() {
+        "kind" | "type" => {
+            let kind = value.to_lowercase();
+            match KINDS.iter().find(|(name, _)| *name == kind) {
+                Some((_, content_type)) => format!("kMDItemContentTypeTree == \"{content_type}\""),
+                None => extension(value),
+            }
+        }
+        "ext" | "extension" => extension(value),
+        "name" => format!("kMDItemFSName == \"*{}*\"cd", escape(value)),
+        "size" => size(value)?,
+        "tag" => format!("kMDItemUserTags == \"{}\"cd", escape(value)),
+        "author" | "from" => format!("kMDItemAuthors == \"*{}*\"cd", escape(value)),
+        // Not a filter, e.g. a time like 10:30
+        _ => text(term),
+    };
+    Ok(condition)
+}
+
+/// Matches a word in the name or the contents
+fn text(word: &str) -> String {
+    let word = escape(word);
+    format!("(kMDItemFSName == \"*{word}*\"cd || kMDItemTextContent == \"{word}*\"cdw)")
+}
+
+/// Matches a file extension
+fn extension(value: &str) -> String {
+    format!("kMDItemFSName == \"*.{}\"c", escape(value.trim_start_matches('.')))
+}
+
+/// Compares a size: `>100MB`, `<1k`; larger if no comparison is given
+///
+/// # Errors
+///
+/// Returns an error if the size is invalid.
+fn size(value: &str) -> Result<String, Box<dyn Error>> {
+    let (operator, size) = match value.strip_prefix('<') {
+        Some(size) => ("<", size),
+        None => (">", value.strip_prefix('>').unwrap_or(value)),
+    };
+    let bytes = parse_size(size)?;
+    Ok(format!("kMDItemFSSize {operator} {bytes}"))
+}
+
+/// Compares a date with an age or a date
+///
+/// `<7d` is younger than 7 days, `>7d` older; `<2024-01-31` is before the day,
+/// `>2024-01-31` after it. Without a comparison an age means younger.
+///
+/// # Errors
+///
+/// Returns an error if the value is neither an age nor a date.
+fn date(attribute: &str, value: &str) -> Result<String, Box<dyn Error>> {
+    let lowercase = value.to_lowercase();
+    if lowercase == "today" {
+        return Ok(format!("{attribute} >= $time.today"));
+    }
+    if lowercase == "yesterday" {
+        return Ok(format!("{attribute} >= $time.today(-1) && {attribute} < $time.today"));
+    }
+    let (older, value) = match value.strip_prefix('>') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('<').unwrap_or(value)),
+    };
+    if is_date(value) {
+        let operator = if older { ">=" } else { "<" };
+        return Ok(format!("{attribute} {operator} $time.iso({value})"));
+    }
+    let age = parse_age(value)?.as_secs();
+    let operator = if older { "<" } else { ">=" };
+    Ok(format!("{attribute} {operator} $time.now(-{age})"))
+}
+
+/// Returns whether a value is a date like `2024-01-31`
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    parts.len() == 3
+        && [4, 2, 2]
+            .iter()
+            .zip(&parts)
+            .all(|(length, part)| part.len() == *length && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Escapes quotes, backslashes and wildcards in a string of a query expression
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '*' | '?') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_keeps_quoted_spaces() {
+        assert_eq!(
+            split(r#"  name:"annual report"  kind:pdf "#).unwrap(),
+            ["name:annual report", "kind:pdf"]
+        );
+        assert_eq!(split(r#""""#).unwrap(), Vec::<String>::new());
+        assert!(split(r#"name:"annual report"#).is_err());
+    }
+
+    #[test]
+    fn predicate_joins_the_conditions() {
+        assert_eq!(
+            predicate("kind:pdf invoice").unwrap(),
+            "kMDItemContentTypeTree == \"com.adobe.pdf\" && \
+             (kMDItemFSName == \"*invoice*\"cd || kMDItemTextContent == \"invoice*\"cdw)"
+        );
+        assert!(predicate("   ").is_err());
+        assert!(predicate("size:huge").is_err());
+    }
+
+    #[test]
+    fn condition_translates_filters() {
+        assert_eq!(
+            condition("kind:IMAGE").unwrap(),
+            "kMDItemContentTypeTree == \"public.image\""
+        );
+        assert_eq!(condition("kind:heic").unwrap(), "kMDItemFSName == \"*.heic\"c");
+        assert_eq!(condition("ext:.txt").unwrap(), "kMDItemFSName == \"*.txt\"c");
+        assert_eq!(condition("name:report").unwrap(), "kMDItemFSName == \"*report*\"cd");
+        assert_eq!(condition("tag:Red").unwrap(), "kMDItemUserTags == \"Red\"cd");
+        assert_eq!(condition("author:smith").unwrap(), "kMDItemAuthors == \"*smith*\"cd");
+    }
+
+    #[test]
+    fn condition_takes_other_words_as_text() {
+        let text = "(kMDItemFSName == \"*10:30*\"cd || kMDItemTextContent == \"10:30*\"cdw)";
+        assert_eq!(condition("10:30").unwrap(), text);
+        assert!(
+            condition("name:")
+                .unwrap()
+                .starts_with("(kMDItemFSName == \"*name:*\"cd")
+        );
+        assert!(condition("-").unwrap().starts_with("(kMDItemFSName == \"*-*\"cd"));
+    }
+
+    #[test]
+    fn condition_negates_terms() {
+        assert_eq!(condition("-ext:log").unwrap(), "!(kMDItemFSName == \"*.log\"c)");
+    }
+
+    #[test]
+    fn size_compares_larger_by_default() {
+        assert_eq!(size("100MB").unwrap(), "kMDItemFSSize > 100000000");
+        assert_eq!(size(">1.5k").unwrap(), "kMDItemFSSize > 1500");
+        assert_eq!(size("<2g").unwrap(), "kMDItemFSSize < 2000000000");
+        assert!(size("<").is_err());
+    }
+
+    #[test]
+    fn date_compares_ages_and_dates() {
+        let attribute = "kMDItemFSContentChangeDate";
+        assert_eq!(
+            date(attribute, "<7d").unwrap(),
+            format!("{attribute} >= $time.now(-604800)")
+        );
+        assert_eq!(
+            date(attribute, "7d").unwrap(),
+            format!("{attribute} >= $time.now(-604800)")
+        );
+        assert_eq!(
+            date(attribute, ">1w").unwrap(),
+            format!("{attribute} < $time.now(-604800)")
+        );
+        assert_eq!(
+            date(attribute, "<2024-01-31").unwrap(),
+            format!("{attribute} < $time.iso(2024-01-31)")
+        );
+        assert_eq!(
+            date(attribute, ">2024-01-31").unwrap(),
+            format!("{attribute} >= $time.iso(2024-01-31)")
+        );
+        assert_eq!(date(attribute, "Today").unwrap(), format!("{attribute} >= $time.today"));
+        assert!(date(attribute, "2024-1-31").is_err());
+        assert!(date(attribute, ">soon").is_err());
+    }
+
+    #[test]
+    fn escape_quotes_wildcards() {
+        assert_eq!(escape(r#"a"b\c*d?e"#), r#"a\"b\\c\*d\?e"#);
+        assert_eq!(condition("name:*.rs").unwrap(), "kMDItemFSName == \"*\\*.rs*\"cd");
+    }
+}
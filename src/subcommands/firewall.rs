@@ -0,0 +1,260 @@
+//! Application firewall subcommand
+//!
+//! Wraps `socketfilterfw`, the command line interface of the macOS application
+//! firewall. Its output differs between settings and macOS versions ("Firewall is
+//! enabled. (State = 1)", "Firewall stealth mode is on", "Block all DISABLED!"),
+//! so it is normalized to on/off values and tables.
+//!
+//! # System Commands Used
+//!
+//! - `socketfilterfw --getglobalstate` / `--getstealthmode` / `--getblockall` - Read settings
+//! - `socketfilterfw --setglobalstate` / `--setstealthmode` - Change settings (with sudo)
+//! - `socketfilterfw --listapps` - List application rules
+//! - `socketfilterfw --add` / `--unblockapp` / `--blockapp` - Change application rules (with sudo)
+
+use crate::cli::{FirewallArgs, FirewallCommands, Switch};
+use crate::config::home_dir;
+use crate::util::run_sudo;
+use colored::Colorize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Path of the firewall command line tool
+const SOCKETFILTERFW: &str = "/usr/libexec/ApplicationFirewall/socketfilterfw";
+
+/// Directories searched for applications given by name
+const APPLICATION_DIRS: &[&str] = &["/Applications", "/Applications/Utilities", "/System/Applications"];
+
+/// An application with a firewall rule
+struct AppRule {
+    /// Path of the application
+    path: String,
+    /// True if incoming connections are allowed
+    allowed: bool,
+}
+
+/// Performs firewall operations
+///
+/// # Arguments
+///
+/// * `args` - Firewall arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `socketfilterfw` fails or an application cannot be found.
+pub fn perform(args: FirewallArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(FirewallCommands::Status) => print_status(),
+        Some(FirewallCommands::On) => set_setting("--setglobalstate", "Firewall", Switch::On),
+        Some(FirewallCommands::Off) => set_setting("--setglobalstate", "Firewall", Switch::Off),
+        Some(FirewallCommands::Stealth { state }) => set_setting("--setstealthmode", "Stealth mode", state),
+        Some(FirewallCommands::List) => list_apps(),
+        Some(FirewallCommands::Allow { app }) => set_app_rule(&app, true),
+        Some(FirewallCommands::Block { app }) => set_app_rule(&app, false),
+    }
+}
+
+/// Prints the global firewall settings
+///
+/// # Errors
+///
+/// Returns an error if a setting cannot be read.
+fn print_status() -> Result<(), Box<dyn Error>> {
+    let settings = [
+        ("Firewall", "--getglobalstate"),
+        ("Stealth mode", "--getstealthmode"),
+        ("Block all incoming", "--getblockall"),
+    ];
+    for (label, option) in settings {
+        let output = socketfilterfw(&[option])?;
+        let state = match parse_state(&output) {
+            Some(true) => "on".green(),
+            Some(false) => "off".yellow(),
+            None => output.trim().normal(),
+        };
+        println!("{label:<20} {state}");
+    }
+    Ok(())
+}
+
+/// Turns a global setting on or off
+///
+/// # Errors
+///
+/// Returns an error if `socketfilterfw` fails.
+fn set_setting(option: &str, label: &str, state: Switch) -> Result<(), Box<dyn Error>> {
+    let value = match state {
+        Switch::On => "on",
+        Switch::Off => "off",
+    };
+    print!("Turn {} {value}", label.to_lowercase());
+    socketfilterfw_sudo(&[option, value])?;
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Prints the applications with firewall rules
+///
+/// # Errors
+///
+/// Returns an error if the rules cannot be read.
+fn list_apps() -> Result<(), Box<dyn Error>> {
+    let rules = parse_app_rules(&socketfilterfw(&["--listapps"])?);
+    if rules.is_empty() {
+        println!("No application rules");
+        return Ok(());
+    }
+
+    let width = rules
+        .iter()
+        .map(|rule| app_name(&rule.path).len())
+        .max()
+        .unwrap_or(0)
+        .max("APPLICATION".len());
+    println!("{:<width$}  {:<8} PATH", "APPLICATION", "INCOMING");
+    for rule in &rules {
+        let state = if rule.allowed {
+            format!("{:<8}", "allow").green()
+        } else {
+            format!("{:<8}", "block").red()
+        };
+        println!("{:<width$}  {state} {}", app_name(&rule.path), rule.path);
+    }
+    Ok(())
+}
+
+/// Adds an application to the firewall rules and allows or blocks its incoming connections
+///
+/// # Errors
+///
+/// Returns an error if the application cannot be found or `socketfilterfw` fails.
+fn set_app_rule(app: &str, allow: bool) -> Result<(), Box<dyn Error>> {
+    let path = resolve_app(app)?;
+    let path = path.to_string_lossy();
+
+    print!(
+        "{} incoming connections for {}",
+        if allow { "Allow" } else { "Block" },
+        path.bold()
+    );
+    socketfilterfw_sudo(&["--add", &path])?;
+    socketfilterfw_sudo(&[if allow { "--unblockapp" } else { "--blockapp" }, &path])?;
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Returns the display name of an application path, e.g. `Slack` for `/Applications/Slack.app`
+fn app_name(path: &str) -> String {
+    let name = Path::new(path)
+        .file_name()
+        .map_or(path.to_string(), |name| name.to_string_lossy().to_string());
+    name.strip_suffix(".app").map(str::to_string).unwrap_or(name)
+}
+
+/// Resolves an application given by path or name
+///
+/// Paths are resolved to the actual file, as the firewall identifies executables
+/// by their real path rather than by symbolic links to them. Names are matched
+/// against the applications that already have a rule, then looked up as
+/// `<name>.app` in the application directories of the system and the user.
+///
+/// # Errors
+///
+/// Returns an error if no application is found.
+fn resolve_app(app: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let path = Path::new(app);
+    if path.exists() {
+        return Ok(path.canonicalize()?);
+    }
+
+    let rules = parse_app_rules(&socketfilterfw(&["--listapps"])?);
+    if let Some(rule) = rules.iter().find(|rule| app_name(&rule.path).eq_ignore_ascii_case(app)) {
+        return Ok(PathBuf::from(&rule.path));
+    }
+
+    let bundle = if app.ends_with(".app") {
+        app.to_string()
+    } else {
+        format!("{app}.app")
+    };
+    let mut dirs: Vec<PathBuf> = APPLICATION_DIRS.iter().map(PathBuf::from).collect();
+    if let Ok(home) = home_dir() {
+        dirs.push(home.join("Applications"));
+    }
+    dirs.into_iter()
+        .map(|dir| dir.join(&bundle))
+        .find(|candidate| candidate.exists())
+        .ok_or_else(|| format!("Application '{app}' not found, give the path of the application").into())
+}
+
+/// Interprets the output of a `--get...` option as on or off
+///
+/// # Returns
+///
+/// `None` if the output contains neither state.
+fn parse_state(output: &str) -> Option<bool> {
+    let output = output.to_lowercase();
+    // "disabled" contains "enabled", so it is checked first
+    if output.contains("disabled") || output.contains(" is off") || output.contains("state = 0") {
+        Some(false)
+    } else if output.contains("enabled") || output.contains(" is on") || output.contains("state = 1") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Parses the output of `--listapps`
+///
+/// Each application is printed as `<N> :  <path>` followed by a line with
+/// `( Allow incoming connections )` or `( Block incoming connections )`.
+fn parse_app_rules(output: &str) -> Vec<AppRule> {
+    let mut rules: Vec<AppRule> = Vec::new();
+    for line in output.lines().map(str::trim) {
+        if let Some((number, path)) = line.split_once(" : ")
+            && number.trim().parse::<u32>().is_ok()
+        {
+            rules.push(AppRule {
+                path: path.trim().to_string(),
+                allowed: true,
+            });
+        } else if line.starts_with('(')
+            && let Some(rule) = rules.last_mut()
+        {
+            rule.allowed = !line.to_lowercase().contains("block");
+        }
+    }
+    rules
+}
+
+/// Runs `socketfilterfw` and returns its output
+///
+/// # Errors
+///
+/// Returns an error if the tool cannot be run or fails.
+fn socketfilterfw(args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new(SOCKETFILTERFW)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {SOCKETFILTERFW}: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("socketfilterfw failed: {}", stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs `socketfilterfw` with sudo, as changing the firewall requires administrator rights
+///
+/// # Errors
+///
+/// Returns an error containing the output of `socketfilterfw` if it fails.
+fn socketfilterfw_sudo(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let stdout = run_sudo(&[&[SOCKETFILTERFW], args].concat())?;
+    // socketfilterfw reports some errors on stdout with exit code 0
+    if stdout.to_lowercase().contains("error") {
+        return Err(format!("socketfilterfw failed: {}", stdout.trim()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,171 @@
+//! Hostname management subcommand
+//!
+//! macOS keeps three names for a computer:
+//!
+//! - **ComputerName**: The user-friendly name shown in Finder and used for sharing
+//! - **LocalHostName**: The Bonjour name, reachable as `<name>.local`
+//! - **HostName**: The name returned by `hostname` and used in the shell prompt;
+//!   unset by default, in which case it is derived from the network
+//!
+//! `rem hostname set` sets all three to the same name, so the computer is known by
+//! one name everywhere. As the LocalHostName only allows letters, digits and
+//! hyphens, the name is restricted to these characters.
+//!
+//! # System Commands Used
+//!
+//! - `scutil --get` - Read a name
+//! - `scutil --set` - Set a name
+
+use crate::cli::{HostnameArgs, HostnameCommands};
+use crate::util::print_dry_run;
+use colored::Colorize;
+use std::error::Error;
+use std::process::Command;
+
+/// Names kept by macOS, as used by `scutil`
+const NAME_KEYS: [&str; 3] = ["ComputerName", "LocalHostName", "HostName"];
+
+/// Maximum length of a LocalHostName (a single DNS label)
+const MAX_NAME_LENGTH: usize = 63;
+
+/// Performs hostname operations
+///
+/// # Arguments
+///
+/// * `args` - Hostname arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a name cannot be read, the new name is invalid, or setting
+/// a name fails.
+pub fn perform(args: HostnameArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(HostnameCommands::Get) => print_names(),
+        Some(HostnameCommands::Set { name, dry_run }) => set_names(&name, dry_run),
+    }
+}
+
+/// Prints all three names
+///
+/// # Errors
+///
+/// Returns an error if `scutil` cannot be run.
+fn print_names() -> Result<(), Box<dyn Error>> {
+    for key in NAME_KEYS {
+        let value = match get_name(key)? {
+            Some(value) => value.normal(),
+            None => "(not set)".yellow(),
+        };
+        println!("{key:>13} : {value}");
+    }
+    Ok(())
+}
+
+/// Sets all three names and prints the values before and after the change
+///
+/// Names that already have the new value are not set again.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid or `scutil` fails.
+fn set_names(name: &str, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    validate_name(name)?;
+
+    let before: Vec<Option<String>> = NAME_KEYS.iter().map(|key| get_name(key)).collect::<Result<_, _>>()?;
+    let changes: Vec<&str> = NAME_KEYS
+        .iter()
+        .zip(&before)
+        .filter(|(_, value)| value.as_deref() != Some(name))
+        .map(|(key, _)| *key)
+        .collect();
+
+    if changes.is_empty() {
+        println!("All names are already set to {}", name.bold());
+        return Ok(());
+    }
+
+    if dry_run {
+        for key in &changes {
+            print_dry_run(&["scutil", "--set", key, name]);
+        }
+        let after = vec![Some(name.to_string()); NAME_KEYS.len()];
+        print_diff(&before, &after);
+        return Ok(());
+    }
+
+    for key in &changes {
+        let output = Command::new("sudo").args(["scutil", "--set", key, name]).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to set {key}: {}", stderr.trim()).into());
+        }
+    }
+
+    let after: Vec<Option<String>> = NAME_KEYS.iter().map(|key| get_name(key)).collect::<Result<_, _>>()?;
+    print_diff(&before, &after);
+
+    Ok(())
+}
+
+/// Prints the names before and after a change, highlighting changed names
+fn print_diff(before: &[Option<String>], after: &[Option<String>]) {
+    for ((key, old), new) in NAME_KEYS.iter().zip(before).zip(after) {
+        let old = old.as_deref().unwrap_or("(not set)");
+        let new = new.as_deref().unwrap_or("(not set)");
+        if old == new {
+            println!("{key:>13} : {old}");
+        } else {
+            println!("{key:>13} : {} → {}", old.red(), new.green());
+        }
+    }
+}
+
+/// Checks that a name is a valid LocalHostName
+///
+/// Bonjour names consist of ASCII letters, digits and hyphens, must not start or
+/// end with a hyphen and are at most 63 characters long.
+///
+/// # Errors
+///
+/// Returns an error naming the invalid characters or the violated rule.
+fn validate_name(name: &str) -> Result<(), Box<dyn Error>> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(format!("Name must have 1 to {MAX_NAME_LENGTH} characters").into());
+    }
+
+    let mut invalid: Vec<char> = Vec::new();
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() && c != '-' && !invalid.contains(&c) {
+            invalid.push(c);
+        }
+    }
+    if !invalid.is_empty() {
+        let invalid: String = invalid.iter().map(|c| format!("'{c}'")).collect::<Vec<_>>().join(", ");
+        return Err(format!(
+            "Invalid character(s) {invalid} in '{name}', only letters, digits and hyphens are allowed"
+        )
+        .into());
+    }
+
+    if name.starts_with('-') || name.ends_with('-') {
+        return Err(format!("Name '{name}' must not start or end with a hyphen").into());
+    }
+
+    Ok(())
+}
+
+/// Reads a name with `scutil --get`
+///
+/// # Returns
+///
+/// The name, or `None` if it is not set.
+///
+/// # Errors
+///
+/// Returns an error if `scutil` cannot be run.
+fn get_name(key: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let output = Command::new("scutil").args(["--get", key]).output()?;
+    // scutil prints "<key>: not set" and fails if the name is not set
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((output.status.success() && !value.is_empty()).then_some(value))
+}
@@ -0,0 +1,394 @@
+//! Hosts file management subcommand
+//!
+//! Manages entries of `/etc/hosts` in named blocks delimited by marker comments:
+//!
+//! ```text
+//! # >>> rempower: dev
+//! 127.0.0.1 app.test api.test
+//! # <<< rempower: dev
+//! ```
+//!
+//! Entries outside of these blocks are never modified. Disabling a block
+//! comments out its entries with a dedicated `#rem# ` prefix, enabling it
+//! restores only these, so other comments inside a block are kept as they are.
+//!
+//! Before every change the current hosts file is copied to `hosts.backup` in the
+//! rempower state directory. If the hosts file is not writable by the current
+//! user, it is written with `sudo tee`.
+
+use crate::cli::{HostsArgs, HostsCommands};
+use crate::config;
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Prefix of the comment starting a managed block
+const BLOCK_START: &str = "# >>> rempower: ";
+
+/// Prefix of the comment ending a managed block
+const BLOCK_END: &str = "# <<< rempower: ";
+
+/// Prefix of entries in disabled blocks, distinct from regular comments
+const DISABLED_PREFIX: &str = "#rem# ";
+
+/// Name of the backup file inside the state directory
+const BACKUP_FILE: &str = "hosts.backup";
+
+/// A managed block of the hosts file
+struct Block {
+    /// Tag naming the block
+    tag: String,
+    /// Index of the start marker line
+    start: usize,
+    /// Index of the end marker line
+    end: usize,
+}
+
+/// Performs hosts file operations based on the provided arguments
+///
+/// # Arguments
+///
+/// * `args` - Hosts operation arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the hosts file cannot be read or written, or the
+/// requested block or entry does not exist.
+pub fn perform(args: HostsArgs) -> Result<(), Box<dyn Error>> {
+    let path = args.file.as_path();
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    match args.command {
+        HostsCommands::List { tag } => {
+            list_entries(&lines, tag.as_deref());
+            return Ok(());
+        }
+        HostsCommands::Add { ip, hostnames, tag } => add_entry(&mut lines, &tag, ip, &hostnames)?,
+        HostsCommands::Remove { hostnames, tag } => remove_entries(&mut lines, &hostnames, tag.as_deref())?,
+        HostsCommands::Enable { tag } => set_block_enabled(&mut lines, &tag, true)?,
+        HostsCommands::Disable { tag } => set_block_enabled(&mut lines, &tag, false)?,
+    }
+
+    let mut new_content = lines.join("\n");
+    new_content.push('\n');
+    if new_content == content {
+        println!("Hosts file unchanged");
+        return Ok(());
+    }
+
+    backup_hosts_file(&content)?;
+    write_hosts_file(path, &new_content)?;
+    println!("{} {}", "Updated".green(), path.display());
+
+    Ok(())
+}
+
+/// Prints the entries of the hosts file grouped by managed block
+///
+/// # Arguments
+///
+/// * `lines` - Lines of the hosts file
+/// * `tag` - Only print the block with this tag
+fn list_entries(lines: &[String], tag: Option<&str>) {
+    let blocks = find_blocks(lines);
+
+    if tag.is_none() {
+        let unmanaged: Vec<&String> = lines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !blocks.iter().any(|block| (block.start..=block.end).contains(index)))
+            .map(|(_, line)| line)
+            .filter(|line| parse_entry(line).is_some())
+            .collect();
+        println!("{}", "(unmanaged)".bold());
+        for line in unmanaged {
+            println!("    {}", line.trim());
+        }
+    }
+
+    for block in blocks.iter().filter(|block| tag.is_none_or(|tag| block.tag == tag)) {
+        let entries = &lines[block.start + 1..block.end];
+        let state = if block_enabled(entries) {
+            "enabled".green()
+        } else {
+            "disabled".yellow()
+        };
+        println!("{} ({state})", block.tag.bold());
+        for line in entries {
+            println!("    {}", line.strip_prefix(DISABLED_PREFIX).unwrap_or(line).trim());
+        }
+    }
+}
+
+/// Adds an entry to a managed block, creating the block if necessary
+///
+/// If one of the hostnames is already mapped in the block, its mapping is
+/// replaced. New entries of a disabled block are added disabled.
+///
+/// # Errors
+///
+/// Returns an error if the tag is invalid.
+fn add_entry(lines: &mut Vec<String>, tag: &str, ip: IpAddr, hostnames: &[String]) -> Result<(), Box<dyn Error>> {
+    validate_tag(tag)?;
+
+    let block = match find_blocks(lines).into_iter().find(|block| block.tag == tag) {
+        Some(block) => block,
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("{BLOCK_START}{tag}"));
+            lines.push(format!("{BLOCK_END}{tag}"));
+            Block {
+                tag: tag.to_string(),
+                start: lines.len() - 2,
+                end: lines.len() - 1,
+            }
+        }
+    };
+
+    let enabled = block_enabled(&lines[block.start + 1..block.end]);
+    let removed = remove_hostnames(lines, block.start + 1, block.end, hostnames);
+    let end = block.end - removed;
+
+    let entry = format!("{ip}\t{}", hostnames.join(" "));
+    let entry = if enabled {
+        entry
+    } else {
+        format!("{DISABLED_PREFIX}{entry}")
+    };
+    lines.insert(end, entry);
+
+    println!("Add {ip} {} to block '{tag}'", hostnames.join(" "));
+
+    Ok(())
+}
+
+/// Removes hostnames, or a whole block, from the managed blocks
+///
+/// # Arguments
+///
+/// * `lines` - Lines of the hosts file
+/// * `hostnames` - Hostnames to remove; if empty, the block given by `tag` is removed
+/// * `tag` - Restrict removal to the block with this tag
+///
+/// # Errors
+///
+/// Returns an error if the block does not exist or none of the hostnames was found.
+fn remove_entries(lines: &mut Vec<String>, hostnames: &[String], tag: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let blocks: Vec<Block> = find_blocks(lines)
+        .into_iter()
+        .filter(|block| tag.is_none_or(|tag| block.tag == tag))
+        .collect();
+
+    if let Some(tag) = tag
+        && blocks.is_empty()
+    {
+        return Err(format!("No managed block '{tag}' in hosts file").into());
+    }
+
+    if hostnames.is_empty() {
+        // Only reachable with a tag, enforced by the CLI
+        for block in blocks.iter().rev() {
+            lines.drain(block.start..=block.end);
+            if block.start > 0 && block.start <= lines.len() && lines[block.start - 1].trim().is_empty() {
+                lines.remove(block.start - 1);
+            }
+            println!("Remove block '{}'", block.tag);
+        }
+        return Ok(());
+    }
+
+    let mut removed = 0;
+    for block in blocks.iter().rev() {
+        let count = remove_hostnames(lines, block.start + 1, block.end, hostnames);
+        if count > 0 {
+            println!("Remove {} from block '{}'", hostnames.join(" "), block.tag);
+        }
+        removed += count;
+    }
+
+    if removed == 0 {
+        return Err(format!("None of {hostnames:?} found in managed blocks").into());
+    }
+
+    Ok(())
+}
+
+/// Enables or disables all entries of a managed block
+///
+/// Only lines carrying the disabled prefix are enabled and only entries are
+/// disabled; comments and empty lines are left alone.
+///
+/// # Errors
+///
+/// Returns an error if the block does not exist.
+fn set_block_enabled(lines: &mut [String], tag: &str, enabled: bool) -> Result<(), Box<dyn Error>> {
+    let block = find_blocks(lines)
+        .into_iter()
+        .find(|block| block.tag == tag)
+        .ok_or_else(|| format!("No managed block '{tag}' in hosts file"))?;
+
+    for line in &mut lines[block.start + 1..block.end] {
+        if enabled {
+            if let Some(entry) = line.strip_prefix(DISABLED_PREFIX) {
+                *line = entry.to_string();
+            }
+        } else if parse_entry(line).is_some() {
+            *line = format!("{DISABLED_PREFIX}{line}");
+        }
+    }
+
+    println!("{} block '{tag}'", if enabled { "Enable" } else { "Disable" });
+
+    Ok(())
+}
+
+/// Removes hostnames from the entries between `start` and `end` (exclusive)
+///
+/// Entries left without hostnames are deleted.
+///
+/// # Returns
+///
+/// The number of deleted lines.
+fn remove_hostnames(lines: &mut Vec<String>, start: usize, end: usize, hostnames: &[String]) -> usize {
+    let mut deleted = 0;
+    let mut index = start;
+
+    while index < end - deleted {
+        let line = &lines[index];
+        let (prefix, entry) = match line.strip_prefix(DISABLED_PREFIX) {
+            Some(entry) => (DISABLED_PREFIX, entry),
+            None => ("", line.as_str()),
+        };
+
+        let Some((ip, names)) = parse_entry(entry) else {
+            index += 1;
+            continue;
+        };
+        let remaining: Vec<&str> = names
+            .iter()
+            .copied()
+            .filter(|name| !hostnames.iter().any(|hostname| hostname.eq_ignore_ascii_case(name)))
+            .collect();
+
+        if remaining.is_empty() {
+            lines.remove(index);
+            deleted += 1;
+        } else {
+            if remaining.len() != names.len() {
+                lines[index] = format!("{prefix}{ip}\t{}", remaining.join(" "));
+            }
+            index += 1;
+        }
+    }
+
+    deleted
+}
+
+/// Finds all managed blocks of the hosts file
+///
+/// Start markers without a matching end marker are ignored.
+fn find_blocks(lines: &[String]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(String, usize)> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(tag) = line.trim().strip_prefix(BLOCK_START) {
+            open = Some((tag.trim().to_string(), index));
+        } else if let Some(tag) = line.trim().strip_prefix(BLOCK_END)
+            && let Some((open_tag, start)) = open.take()
+            && open_tag == tag.trim()
+        {
+            blocks.push(Block {
+                tag: open_tag,
+                start,
+                end: index,
+            });
+        }
+    }
+
+    blocks
+}
+
+/// Returns true if a block contains no disabled entries
+fn block_enabled(entries: &[String]) -> bool {
+    !entries.iter().any(|line| line.starts_with(DISABLED_PREFIX))
+}
+
+/// Parses a hosts file entry into its address and hostnames
+///
+/// Returns `None` for comments, empty lines and lines without a valid address.
+fn parse_entry(line: &str) -> Option<(IpAddr, Vec<&str>)> {
+    let line = line.split('#').next()?;
+    let mut fields = line.split_whitespace();
+    let ip = fields.next()?.parse().ok()?;
+    let names: Vec<&str> = fields.collect();
+    (!names.is_empty()).then_some((ip, names))
+}
+
+/// Checks that a tag consists of letters, digits, `-` and `_` only
+///
+/// # Errors
+///
+/// Returns an error if the tag is empty or contains other characters.
+fn validate_tag(tag: &str) -> Result<(), Box<dyn Error>> {
+    if !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        Ok(())
+    } else {
+        Err(format!("Invalid tag '{tag}': only letters, digits, '-' and '_' are allowed").into())
+    }
+}
+
+/// Copies the current hosts file content to the rempower state directory
+///
+/// # Errors
+///
+/// Returns an error if the backup cannot be written.
+fn backup_hosts_file(content: &str) -> Result<(), Box<dyn Error>> {
+    let dir = config::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(BACKUP_FILE), content)?;
+    Ok(())
+}
+
+/// Writes the hosts file, using `sudo tee` if it is not writable by the current user
+///
+/// `tee` keeps the owner and permissions of the existing file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+fn write_hosts_file(path: &Path, content: &str) -> Result<(), Box<dyn Error>> {
+    match fs::write(path, content) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {}
+        Err(e) => return Err(format!("Failed to write {}: {e}", path.display()).into()),
+    }
+
+    let mut child = Command::new("sudo")
+        .arg("tee")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open stdin of sudo tee")?
+        .write_all(content.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to write {}: {}", path.display(), stderr.trim()).into());
+    }
+
+    Ok(())
+}
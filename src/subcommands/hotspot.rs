@@ -0,0 +1,240 @@
+//! Internet Sharing
+//!
+//! Shows and toggles macOS Internet Sharing, which shares the connection of one
+//! interface (e.g. Ethernet or an iPhone) with devices connected to other
+//! interfaces (e.g. a Wi-Fi hotspot). The configuration lives in the NAT property
+//! list of the system configuration; the `com.apple.NetworkSharing` launch daemon
+//! provides the bridge, DHCP and NAT while sharing is on.
+//!
+//! Which connection is shared to which interfaces is chosen once in System
+//! Settings (General > Sharing > Internet Sharing); `on` and `off` keep that
+//! choice and only switch sharing.
+//!
+//! # System Commands Used
+//!
+//! - `plutil -convert json` - Read the Internet Sharing configuration
+//! - `plutil -replace` - Enable or disable Internet Sharing (with sudo)
+//! - `launchctl load -w` / `launchctl unload -w` - Start and stop the sharing daemon (with sudo)
+//! - `launchctl print` - Read the state of the sharing daemon
+//! - `ifconfig` - Read the address of the shared network
+//! - `networksetup -listallhardwareports` - Map devices to hardware port names
+
+use super::ip;
+use crate::cli::{HotspotArgs, HotspotCommands};
+use crate::util::run_sudo;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+/// Property list with the Internet Sharing configuration
+const NAT_PLIST: &str = "/Library/Preferences/SystemConfiguration/com.apple.nat.plist";
+
+/// Launch daemon providing Internet Sharing
+const LAUNCH_DAEMON: &str = "/System/Library/LaunchDaemons/com.apple.NetworkSharing.plist";
+
+/// Service target of the launch daemon in the system domain
+const SERVICE: &str = "system/com.apple.NetworkSharing";
+
+/// Bridge interface of the shared network
+const BRIDGE_INTERFACE: &str = "bridge100";
+
+/// Internet Sharing configuration
+struct SharingConfig {
+    /// True if sharing is switched on
+    enabled: bool,
+    /// Device whose connection is shared
+    from: Option<String>,
+    /// Devices the connection is shared to
+    to: Vec<String>,
+    /// Network name of the Wi-Fi hotspot, if sharing to Wi-Fi is configured
+    network_name: Option<String>,
+}
+
+/// Performs Internet Sharing operations
+///
+/// # Arguments
+///
+/// * `args` - Hotspot arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be read, Internet Sharing has
+/// never been configured, or switching it fails.
+pub fn perform(args: HotspotArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(HotspotCommands::Status) => print_status(),
+        Some(HotspotCommands::On) => switch(true),
+        Some(HotspotCommands::Off) => switch(false),
+    }
+}
+
+/// Prints whether Internet Sharing is on and which interfaces it connects
+///
+/// # Errors
+///
+/// Returns an error if the configuration exists but cannot be read.
+fn print_status() -> Result<(), Box<dyn Error>> {
+    let Some(config) = read_config()? else {
+        println!("{:<16}{}", "Sharing".bold(), "never configured".dimmed());
+        return Ok(());
+    };
+    let ports = ip::hardware_ports();
+    let describe = |device: &str| match ports.get(device) {
+        Some(service) => format!("{device} ({service})"),
+        None => device.to_string(),
+    };
+
+    let state = if config.enabled { "on".green() } else { "off".yellow() };
+    println!("{:<16}{state}", "Sharing".bold());
+    let daemon = if daemon_running() {
+        "running".green()
+    } else {
+        "not running".normal()
+    };
+    println!("{:<16}{daemon}", "Daemon".bold());
+    println!(
+        "{:<16}{}",
+        "Shared from".bold(),
+        config.from.as_deref().map_or_else(|| "-".to_string(), describe)
+    );
+    let to: Vec<String> = config.to.iter().map(|device| describe(device)).collect();
+    println!(
+        "{:<16}{}",
+        "Shared to".bold(),
+        if to.is_empty() { "-".to_string() } else { to.join(", ") }
+    );
+    if let Some(name) = &config.network_name
+        && config
+            .to
+            .iter()
+            .any(|device| ports.get(device).is_some_and(|service| service == "Wi-Fi"))
+    {
+        println!("{:<16}{name}", "Wi-Fi network".bold());
+    }
+    if let Some(address) = bridge_address() {
+        println!("{:<16}{address} ({BRIDGE_INTERFACE})", "Gateway".bold());
+    }
+
+    Ok(())
+}
+
+/// Switches Internet Sharing on or off
+///
+/// # Errors
+///
+/// Returns an error if Internet Sharing has never been configured or a command
+/// fails.
+fn switch(on: bool) -> Result<(), Box<dyn Error>> {
+    let config = read_config()?.ok_or(
+        "Internet Sharing has never been configured, choose the shared connection in \
+         System Settings > General > Sharing once",
+    )?;
+    if on && (config.from.is_none() || config.to.is_empty()) {
+        return Err(
+            "Internet Sharing lacks the connection to share or the devices to share to, \
+                    choose them in System Settings > General > Sharing"
+                .into(),
+        );
+    }
+
+    print!("Turn Internet Sharing {}", if on { "on" } else { "off" });
+    run_sudo(&[
+        "plutil",
+        "-replace",
+        "NAT.Enabled",
+        "-integer",
+        if on { "1" } else { "0" },
+        NAT_PLIST,
+    ])
+    .and_then(|_| run_sudo(&["launchctl", if on { "load" } else { "unload" }, "-w", LAUNCH_DAEMON]))
+    // End the line of the announcement before the error is printed
+    .inspect_err(|_| println!())?;
+    println!("{}", " OK".green());
+
+    Ok(())
+}
+
+/// Reads the Internet Sharing configuration
+///
+/// # Returns
+///
+/// `None` if Internet Sharing has never been configured.
+///
+/// # Errors
+///
+/// Returns an error if the property list cannot be read.
+fn read_config() -> Result<Option<SharingConfig>, Box<dyn Error>> {
+    if !Path::new(NAT_PLIST).exists() {
+        return Ok(None);
+    }
+    let output = Command::new("plutil")
+        .args(["-convert", "json", "-o", "-", NAT_PLIST])
+        .output()
+        .map_err(|e| format!("Failed to run plutil: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to read {NAT_PLIST}: {}", stderr.trim()).into());
+    }
+
+    let plist: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let nat = &plist["NAT"];
+    if nat.is_null() {
+        return Ok(None);
+    }
+    // Flags are stored as integers or booleans depending on the macOS version
+    let enabled = nat["Enabled"]
+        .as_i64()
+        .map(|value| value != 0)
+        .or(nat["Enabled"].as_bool());
+
+    Ok(Some(SharingConfig {
+        enabled: enabled.unwrap_or(false),
+        from: nat["PrimaryInterface"]["Device"].as_str().map(str::to_string),
+        to: nat["SharingDevices"]
+            .as_array()
+            .map(|devices| {
+                devices
+                    .iter()
+                    .filter_map(|device| device.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        network_name: nat["AirPort"]["NetworkName"]
+            .as_str()
+            .filter(|name| !name.is_empty())
+            .map(str::to_string),
+    }))
+}
+
+/// Returns true if the sharing daemon is running
+fn daemon_running() -> bool {
+    let Ok(output) = Command::new("launchctl").args(["print", SERVICE]).output() else {
+        return false;
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let properties: BTreeMap<&str, &str> = stdout
+        .lines()
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    output.status.success() && properties.get("state") == Some(&"running")
+}
+
+/// Reads the IPv4 address of the bridge of the shared network
+///
+/// # Returns
+///
+/// `None` if the bridge does not exist, i.e. sharing is not active.
+fn bridge_address() -> Option<String> {
+    let output = Command::new("ifconfig").arg(BRIDGE_INTERFACE).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("inet ")?.split_whitespace().next())
+        .map(str::to_string)
+}
@@ -0,0 +1,190 @@
+//! Trackpad and mouse settings
+//!
+//! Shows and changes tap to click, the tracking speeds and natural scrolling
+//! by writing the preferences System Settings writes. The trackpad settings
+//! are written for the built-in and for Bluetooth trackpads.
+//!
+//! Written preferences are only read when the user logs in. `activateSettings`
+//! of the SystemAdministration framework makes the running session apply them
+//! right away; where it is missing, the changes take effect after the next
+//! login.
+//!
+//! # System Commands Used
+//!
+//! - `defaults read` - Read the trackpad and mouse preferences
+//! - `defaults write` - Write the trackpad and mouse preferences
+//! - `activateSettings -u` - Apply the written preferences to the running session
+
+use crate::cli::{InputArgs, InputCommands, MouseArgs, Switch, TrackpadArgs};
+use colored::{ColoredString, Colorize};
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// Preference domains of the built-in and of Bluetooth trackpads
+const TRACKPAD_DOMAINS: [&str; 2] = [
+    "com.apple.AppleMultitouchTrackpad",
+    "com.apple.driver.AppleBluetoothMultitouch.trackpad",
+];
+
+/// Global preference with the trackpad tracking speed
+const TRACKPAD_SCALING: &str = "com.apple.trackpad.scaling";
+
+/// Global preference with the mouse tracking speed
+const MOUSE_SCALING: &str = "com.apple.mouse.scaling";
+
+/// Global preference with the scroll direction, false for the classic direction
+const NATURAL_SCROLL: &str = "com.apple.swipescrolldirection";
+
+/// Global preference with the tap behavior, 1 for tap to click
+const TAP_BEHAVIOR: &str = "com.apple.mouse.tapBehavior";
+
+/// Tool applying written preferences to the running session
+const ACTIVATE_SETTINGS: &str =
+    "/System/Library/PrivateFrameworks/SystemAdministration.framework/Resources/activateSettings";
+
+/// Performs input device operations
+///
+/// # Arguments
+///
+/// * `args` - Input arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a preference cannot be written.
+pub fn perform(args: InputArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        InputCommands::Trackpad(args) => trackpad(args),
+        InputCommands::Mouse(args) => mouse(args),
+    }
+}
+
+/// Shows or changes the trackpad settings
+///
+/// # Errors
+///
+/// Returns an error if a preference cannot be written.
+fn trackpad(args: TrackpadArgs) -> Result<(), Box<dyn Error>> {
+    if args.tap_to_click.is_none() && args.tracking.is_none() && args.natural_scroll.is_none() {
+        let tap = read_default(&[TRACKPAD_DOMAINS[0], "Clicking"]).is_some_and(|value| value == "1");
+        println!("{:<16}{}", "Tap to click".bold(), on_off(tap));
+        println!("{:<16}{}", "Tracking".bold(), speed(TRACKPAD_SCALING));
+        println!("{:<16}{}", "Natural scroll".bold(), on_off(natural_scroll()));
+        return Ok(());
+    }
+
+    if let Some(state) = args.tap_to_click {
+        let on = state == Switch::On;
+        change(&format!("Turn tap to click {}", on_off(on)), || {
+            for domain in TRACKPAD_DOMAINS {
+                write_default(&[domain, "Clicking", "-bool", &on.to_string()])?;
+            }
+            write_default(&["-g", TAP_BEHAVIOR, "-int", if on { "1" } else { "0" }])
+        })?;
+    }
+    if let Some(tracking) = args.tracking {
+        change(&format!("Set the trackpad tracking speed to {tracking}"), || {
+            write_default(&["-g", TRACKPAD_SCALING, "-float", &tracking.to_string()])
+        })?;
+    }
+    if let Some(state) = args.natural_scroll {
+        let on = state == Switch::On;
+        change(&format!("Turn natural scrolling {}", on_off(on)), || {
+            write_default(&["-g", NATURAL_SCROLL, "-bool", &on.to_string()])
+        })?;
+    }
+    activate();
+    Ok(())
+}
+
+/// Shows or changes the mouse settings
+///
+/// # Errors
+///
+/// Returns an error if a preference cannot be written.
+fn mouse(args: MouseArgs) -> Result<(), Box<dyn Error>> {
+    let Some(speed_value) = args.speed else {
+        println!("{:<16}{}", "Speed".bold(), speed(MOUSE_SCALING));
+        println!("{:<16}{}", "Natural scroll".bold(), on_off(natural_scroll()));
+        return Ok(());
+    };
+    change(&format!("Set the mouse tracking speed to {speed_value}"), || {
+        write_default(&["-g", MOUSE_SCALING, "-float", &speed_value.to_string()])
+    })?;
+    activate();
+    Ok(())
+}
+
+/// Applies the written preferences to the running session
+///
+/// Prints a hint to log in again if this is not possible.
+fn activate() {
+    let applied = Command::new(ACTIVATE_SETTINGS)
+        .arg("-u")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !applied {
+        println!("Log out and in again to apply the changes");
+    }
+}
+
+/// Applies a change and prints it with its result
+///
+/// # Errors
+///
+/// Returns an error if the change fails.
+fn change(label: &str, apply: impl FnOnce() -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    print!("{label}");
+    io::stdout().flush()?;
+    if let Err(e) = apply() {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Returns true if natural scrolling is on, which is the default
+fn natural_scroll() -> bool {
+    read_default(&["-g", NATURAL_SCROLL]).is_none_or(|value| value == "1")
+}
+
+/// Formats a tracking speed preference, or marks it as the default
+fn speed(key: &str) -> ColoredString {
+    match read_default(&["-g", key]).and_then(|value| value.parse::<f32>().ok()) {
+        Some(speed) => format!("{speed} of 3").normal(),
+        None => "macOS default".dimmed(),
+    }
+}
+
+/// Reads a preference; `None` if it is not set
+fn read_default(args: &[&str]) -> Option<String> {
+    let output = Command::new("defaults").arg("read").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Writes a preference
+///
+/// # Errors
+///
+/// Returns an error if `defaults` fails.
+fn write_default(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("defaults")
+        .arg("write")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run defaults: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to write {}: {}", args[1], stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Formats a switch state
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
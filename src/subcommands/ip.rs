@@ -0,0 +1,455 @@
+//! IP address information subcommand
+//!
+//! Shows the addresses of the local network interfaces, the default gateways and
+//! the public IP addresses as seen from the internet. The public addresses are
+//! requested from an HTTPS endpoint that replies with the client address as plain
+//! text, once over IPv4 and once over IPv6. The endpoint can be changed with
+//! `--endpoint` or `public_ip_url` in the `[ip]` table of the configuration file.
+//!
+//! `rem ip watch` keeps polling the public address and logs its changes (see
+//! [`watch`]).
+//!
+//! # System Commands Used
+//!
+//! - `ifconfig` - List interface addresses
+//! - `networksetup -listallhardwareports` - Map devices to hardware port names
+//! - `route -n get default` - Get the default gateways
+//! - `curl` - Request the public IP addresses
+
+pub mod watch;
+
+use crate::cli::{IpArgs, IpCommands};
+use crate::config::Config;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::{IpAddr, Ipv6Addr};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Default endpoint replying with the client address; answers over IPv4 and IPv6
+pub const DEFAULT_PUBLIC_IP_URL: &str = "https://api64.ipify.org";
+
+/// Maximum time for a public IP request in seconds
+const PUBLIC_IP_TIMEOUT_SECS: &str = "5";
+
+/// Address of a local network interface
+#[derive(Serialize, PartialEq, Eq)]
+struct InterfaceAddress {
+    /// The address
+    address: IpAddr,
+    /// Length of the network prefix
+    prefix: u8,
+}
+
+/// Addresses of a local network interface
+#[derive(Serialize, PartialEq, Eq)]
+struct Interface {
+    /// Device name, e.g. `en0`
+    name: String,
+    /// Hardware port of the device, e.g. `Wi-Fi`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<String>,
+    /// Addresses in the order reported by `ifconfig`
+    addresses: Vec<InterfaceAddress>,
+}
+
+/// Default gateway of an address family
+#[derive(Serialize, PartialEq, Eq)]
+pub struct Gateway {
+    /// Address of the gateway
+    pub address: IpAddr,
+    /// Device the gateway is reached through
+    pub interface: Option<String>,
+}
+
+/// Public addresses of this host as seen by the endpoint
+#[derive(Serialize, PartialEq, Eq, Default)]
+struct PublicAddresses {
+    /// Public IPv4 address, if reachable over IPv4
+    ipv4: Option<IpAddr>,
+    /// Public IPv6 address, if reachable over IPv6
+    ipv6: Option<IpAddr>,
+}
+
+/// Collected address information
+#[derive(Serialize, PartialEq, Eq)]
+struct IpInfo {
+    /// Local interfaces with at least one address, loopback excluded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interfaces: Option<Vec<Interface>>,
+    /// Default gateways by address family
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gateways: Option<BTreeMap<&'static str, Gateway>>,
+    /// Public addresses; absent if not looked up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public: Option<PublicAddresses>,
+}
+
+/// Shows IP address information
+///
+/// # Arguments
+///
+/// * `args` - IP arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded, the local addresses
+/// cannot be read, or only the public addresses are requested and none could be
+/// determined.
+pub fn perform(args: IpArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(IpCommands::Watch(args)) = args.command {
+        return watch::perform(args);
+    }
+
+    let endpoint = public_ip_url(args.endpoint)?;
+    let endpoint = (!args.no_public).then_some(endpoint.as_str());
+
+    if !args.watch {
+        let info = collect(endpoint, args.public_only)?;
+        return print_info(&info, args.json, false);
+    }
+
+    let interval = Duration::from_secs(args.interval.max(1));
+    if !args.json {
+        println!("Watching addresses every {} s (Ctrl-C to stop)", interval.as_secs());
+    }
+    let mut last: Option<IpInfo> = None;
+    loop {
+        match collect(endpoint, args.public_only) {
+            Ok(info) if last.as_ref() != Some(&info) => {
+                if last.is_some() && !args.json {
+                    println!();
+                    println!("{}", "Addresses changed".yellow());
+                }
+                if let Err(e) = print_info(&info, args.json, true) {
+                    eprintln!("{}", e.to_string().red());
+                }
+                last = Some(info);
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("{}", format!("Failed to read addresses: {e}").red()),
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Returns the endpoint for the public addresses
+///
+/// # Arguments
+///
+/// * `endpoint` - Endpoint from the command line, overriding the configuration file
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded.
+pub fn public_ip_url(endpoint: Option<String>) -> Result<String, Box<dyn Error>> {
+    Ok(match endpoint {
+        Some(endpoint) => endpoint,
+        None => Config::load()?
+            .ip
+            .public_ip_url
+            .unwrap_or_else(|| DEFAULT_PUBLIC_IP_URL.to_string()),
+    })
+}
+
+/// Collects the address information
+///
+/// # Arguments
+///
+/// * `endpoint` - Endpoint for the public addresses; `None` skips the lookup
+/// * `public_only` - Only look up the public addresses
+///
+/// # Errors
+///
+/// Returns an error if the local addresses cannot be read.
+fn collect(endpoint: Option<&str>, public_only: bool) -> Result<IpInfo, Box<dyn Error>> {
+    let public = endpoint.map(|endpoint| {
+        // Both families in parallel, each request may take up to the timeout
+        thread::scope(|scope| {
+            let ipv6 = scope.spawn(|| public_address(endpoint, false));
+            PublicAddresses {
+                ipv4: public_address(endpoint, true),
+                ipv6: ipv6.join().ok().flatten(),
+            }
+        })
+    });
+    if public_only {
+        return Ok(IpInfo {
+            interfaces: None,
+            gateways: None,
+            public,
+        });
+    }
+
+    let services = hardware_ports();
+    let mut interfaces = local_interfaces()?;
+    for interface in &mut interfaces {
+        interface.service = services.get(&interface.name).cloned();
+    }
+
+    let mut gateways = BTreeMap::new();
+    if let Some(gateway) = default_gateway(false) {
+        gateways.insert("ipv4", gateway);
+    }
+    if let Some(gateway) = default_gateway(true) {
+        gateways.insert("ipv6", gateway);
+    }
+
+    Ok(IpInfo {
+        interfaces: Some(interfaces),
+        gateways: Some(gateways),
+        public,
+    })
+}
+
+/// Prints the address information as a table or JSON
+///
+/// # Arguments
+///
+/// * `info` - Address information to print
+/// * `json` - Print JSON instead of a table
+/// * `compact` - Print JSON on a single line, e.g. to log changes in watch mode
+///
+/// # Errors
+///
+/// Returns an error if only public addresses were requested and none is known,
+/// or the JSON serialization fails.
+fn print_info(info: &IpInfo, json: bool, compact: bool) -> Result<(), Box<dyn Error>> {
+    if json {
+        if compact {
+            println!("{}", serde_json::to_string(info)?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(info)?);
+        }
+        return Ok(());
+    }
+
+    let Some(interfaces) = &info.interfaces else {
+        // Only the public addresses, one per line for use in scripts
+        let public = info.public.as_ref().map(|public| [public.ipv4, public.ipv6]);
+        let addresses: Vec<IpAddr> = public.into_iter().flatten().flatten().collect();
+        if addresses.is_empty() {
+            return Err("Public IP address could not be determined".into());
+        }
+        for address in addresses {
+            println!("{address}");
+        }
+        return Ok(());
+    };
+
+    println!("{:<10} {:<24} ADDRESS", "INTERFACE", "SERVICE");
+    for interface in interfaces {
+        for (index, address) in interface.addresses.iter().enumerate() {
+            let (name, service) = if index == 0 {
+                (interface.name.as_str(), interface.service.as_deref().unwrap_or("-"))
+            } else {
+                ("", "")
+            };
+            let mut text = format!("{}/{}", address.address, address.prefix).normal();
+            if is_link_local(address.address) {
+                text = format!("{text} (link-local)").dimmed();
+            }
+            println!("{name:<10} {service:<24} {text}");
+        }
+    }
+
+    println!();
+    for (family, key) in [("IPv4", "ipv4"), ("IPv6", "ipv6")] {
+        let gateway = info.gateways.as_ref().and_then(|gateways| gateways.get(key));
+        let text = match gateway {
+            Some(Gateway {
+                address,
+                interface: Some(interface),
+            }) => format!("{address} via {interface}").normal(),
+            Some(Gateway { address, .. }) => address.to_string().normal(),
+            None => "none".yellow(),
+        };
+        println!("{:<35} {text}", format!("Default gateway ({family})"));
+    }
+
+    if let Some(public) = &info.public {
+        for (family, address) in [("IPv4", public.ipv4), ("IPv6", public.ipv6)] {
+            let text = match address {
+                Some(address) => address.to_string().green(),
+                None => "unavailable".yellow(),
+            };
+            println!("{:<35} {text}", format!("Public address ({family})"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true for link-local addresses, which are only valid on their network segment
+fn is_link_local(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => address.is_link_local(),
+        IpAddr::V6(address) => address.segments()[0] & 0xffc0 == 0xfe80,
+    }
+}
+
+/// Returns the addresses of all local interfaces except loopback
+///
+/// # Errors
+///
+/// Returns an error if `ifconfig` fails.
+fn local_interfaces() -> Result<Vec<Interface>, Box<dyn Error>> {
+    let output = Command::new("ifconfig").output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list interfaces: {}", stderr.trim()).into());
+    }
+    Ok(parse_ifconfig(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the output of `ifconfig`
+///
+/// Interface sections start with `<name>: flags=...` and contain lines like
+/// `inet 192.168.1.2 netmask 0xffffff00 ...` and `inet6 fe80::1%en0 prefixlen 64 ...`.
+/// Loopback interfaces and interfaces without addresses are skipped.
+fn parse_ifconfig(output: &str) -> Vec<Interface> {
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut loopback = false;
+
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            if let Some((name, rest)) = line.split_once(':') {
+                loopback = rest.contains("LOOPBACK");
+                interfaces.push(Interface {
+                    name: name.to_string(),
+                    service: None,
+                    addresses: Vec::new(),
+                });
+            }
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let address = match fields.as_slice() {
+            ["inet", address, rest @ ..] => address.parse().ok().map(|address| InterfaceAddress {
+                address: IpAddr::V4(address),
+                prefix: option_value(rest, "netmask").map_or(32, netmask_prefix),
+            }),
+            ["inet6", address, rest @ ..] => {
+                let address = address.split('%').next().unwrap_or(address);
+                address.parse::<Ipv6Addr>().ok().map(|address| InterfaceAddress {
+                    address: IpAddr::V6(address),
+                    prefix: option_value(rest, "prefixlen")
+                        .and_then(|prefix| prefix.parse().ok())
+                        .unwrap_or(128),
+                })
+            }
+            _ => None,
+        };
+        if let (Some(address), Some(interface)) = (address, interfaces.last_mut())
+            && !loopback
+        {
+            interface.addresses.push(address);
+        }
+    }
+
+    interfaces.retain(|interface| !interface.addresses.is_empty());
+    interfaces
+}
+
+/// Returns the value following an option name in a list of fields
+fn option_value<'a>(fields: &[&'a str], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .position(|field| *field == name)
+        .and_then(|index| fields.get(index + 1))
+        .copied()
+}
+
+/// Converts a netmask in hex (`0xffffff00`) or dotted notation to a prefix length
+fn netmask_prefix(netmask: &str) -> u8 {
+    let mask = match netmask.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => netmask.parse::<std::net::Ipv4Addr>().ok().map(u32::from),
+    };
+    mask.map_or(32, |mask| mask.count_ones() as u8)
+}
+
+/// Returns the hardware port names by device name, e.g. `en0` → `Wi-Fi`
+pub fn hardware_ports() -> BTreeMap<String, String> {
+    let Ok(output) = Command::new("networksetup").arg("-listallhardwareports").output() else {
+        return BTreeMap::new();
+    };
+
+    let mut ports = BTreeMap::new();
+    let mut port: Option<String> = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(name) = line.trim().strip_prefix("Hardware Port:") {
+            port = Some(name.trim().to_string());
+        } else if let Some(device) = line.trim().strip_prefix("Device:")
+            && let Some(port) = port.take()
+        {
+            ports.insert(device.trim().to_string(), port);
+        }
+    }
+    ports
+}
+
+/// Returns the device of the IPv4 default route, e.g. `en0`
+pub fn default_interface() -> Option<String> {
+    default_gateway(false)?.interface
+}
+
+/// Returns the default gateway of an address family
+///
+/// Uses `route -n get default`, which prints lines like `gateway: 192.168.1.1`
+/// and `interface: en0`.
+pub fn default_gateway(ipv6: bool) -> Option<Gateway> {
+    let mut command = Command::new("route");
+    command.args(["-n", "get"]);
+    if ipv6 {
+        command.arg("-inet6");
+    }
+    let output = command.arg("default").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let field = |name: &str| {
+        stdout
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name))
+            .map(|value| value.trim().to_string())
+    };
+    let address = field("gateway:")?;
+    let address = address.split('%').next()?.parse().ok()?;
+    Some(Gateway {
+        address,
+        interface: field("interface:"),
+    })
+}
+
+/// Requests the public address of one address family from the endpoint
+///
+/// # Returns
+///
+/// The address, or `None` if the family has no connectivity, the request fails
+/// or the reply is not an address of the requested family.
+pub fn public_address(endpoint: &str, ipv4: bool) -> Option<IpAddr> {
+    let output = Command::new("curl")
+        .args([
+            "--silent",
+            "--fail",
+            "--max-time",
+            PUBLIC_IP_TIMEOUT_SECS,
+            if ipv4 { "-4" } else { "-6" },
+            endpoint,
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let address: IpAddr = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    (address.is_ipv4() == ipv4).then_some(address)
+}
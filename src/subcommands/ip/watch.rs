@@ -0,0 +1,243 @@
+//! Public IP address watcher
+//!
+//! Polls the public address at a fixed interval and logs every change with a
+//! timestamp, e.g. to keep track of the address of a dynamic home connection
+//! running services. Changes are printed and appended to `public-ip.log` in the
+//! rempower state directory; with `--notify`, a notification is posted as well.
+//!
+//! The last known address is kept in the state directory, so a change that
+//! happens while the watcher is not running is reported on its next start.
+//! Failed lookups (e.g. while offline) are not treated as a change.
+//!
+//! With `--install`, the watcher runs in the background as a LaunchAgent that is
+//! started at login and restarted if it exits.
+//!
+//! # System Commands Used
+//!
+//! - `curl` - Request the public IP addresses
+//! - `date` - Format timestamps in local time
+//! - `osascript` - Post notifications
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload the LaunchAgent
+
+use super::{public_address, public_ip_url};
+use crate::cli::IpWatchArgs;
+use crate::config;
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use crate::notify::notify;
+use crate::util::timestamp;
+use colored::Colorize;
+use std::env;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// Label of the LaunchAgent
+const LABEL: &str = "com.github.rfoerthe.rempower.ip-watch";
+
+/// Name of the log file of address changes inside the state directory
+const LOG_FILE: &str = "public-ip.log";
+
+/// Name of the file with the last known addresses inside the state directory
+const STATE_FILE: &str = "public-ip";
+
+/// Address family of a public address
+#[derive(Clone, Copy)]
+struct Family {
+    /// Name used in logs and the state file
+    name: &'static str,
+    /// True for IPv4
+    ipv4: bool,
+}
+
+/// Public IPv4 address
+const IPV4: Family = Family {
+    name: "IPv4",
+    ipv4: true,
+};
+
+/// Public IPv6 address
+const IPV6: Family = Family {
+    name: "IPv6",
+    ipv4: false,
+};
+
+/// Watches the public address, or installs or removes the LaunchAgent doing so
+///
+/// # Arguments
+///
+/// * `args` - Watch arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded, the state directory
+/// cannot be written, or the LaunchAgent cannot be installed or removed.
+pub fn perform(args: IpWatchArgs) -> Result<(), Box<dyn Error>> {
+    if args.uninstall {
+        return uninstall();
+    }
+    if args.install {
+        return install(&args);
+    }
+
+    let endpoint = public_ip_url(args.endpoint)?;
+    let dir = config::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let log = dir.join(LOG_FILE);
+    let state = dir.join(STATE_FILE);
+
+    let families: Vec<Family> = if args.ipv6 { vec![IPV4, IPV6] } else { vec![IPV4] };
+    let mut known = read_state(&state);
+    println!(
+        "Watching the public address every {} s, logging to {} (Ctrl-C to stop)",
+        args.interval,
+        log.display()
+    );
+
+    loop {
+        let mut changed = false;
+        for family in &families {
+            let Some(address) = public_address(&endpoint, family.ipv4) else {
+                continue;
+            };
+            let previous = known
+                .iter()
+                .find(|(name, _)| name == family.name)
+                .map(|(_, address)| *address);
+            if previous == Some(address) {
+                continue;
+            }
+
+            let message = match previous {
+                Some(previous) => format!("{} changed from {previous} to {address}", family.name),
+                None => format!("{} is {address}", family.name),
+            };
+            log_change(&log, &message);
+            if args.notify
+                && let Some(previous) = previous
+                && let Err(e) = notify(
+                    &format!("Public {} address changed", family.name),
+                    &format!("{previous} → {address}"),
+                )
+            {
+                eprintln!("{}", e.to_string().red());
+            }
+
+            known.retain(|(name, _)| name != family.name);
+            known.push((family.name.to_string(), address));
+            changed = true;
+        }
+        if changed && let Err(e) = write_state(&state, &known) {
+            eprintln!("{}", format!("Failed to write {}: {e}", state.display()).red());
+        }
+
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Prints a change and appends it to the log file with a timestamp
+fn log_change(log: &Path, message: &str) {
+    let line = format!("{}  {message}", timestamp());
+    println!("{line}");
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .and_then(|mut file| writeln!(file, "{line}"));
+    if let Err(e) = written {
+        eprintln!("{}", format!("Failed to write {}: {e}", log.display()).red());
+    }
+}
+
+/// Reads the last known addresses from the state file
+///
+/// Every line holds a family and its address, e.g. `IPv4 203.0.113.7`. A missing
+/// or unreadable file yields no addresses.
+fn read_state(path: &Path) -> Vec<(String, IpAddr)> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (family, address) = line.split_once(' ')?;
+            Some((family.to_string(), address.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Writes the last known addresses to the state file
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+fn write_state(path: &Path, known: &[(String, IpAddr)]) -> Result<(), Box<dyn Error>> {
+    let content: String = known
+        .iter()
+        .map(|(family, address)| format!("{family} {address}\n"))
+        .collect();
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Installs and loads the LaunchAgent running the watcher with the given options
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be written or loaded.
+fn install(args: &IpWatchArgs) -> Result<(), Box<dyn Error>> {
+    let program = env::current_exe()?;
+    let log = config::state_dir()?.join(LOG_FILE);
+    if let Some(state_dir) = log.parent() {
+        fs::create_dir_all(state_dir)?;
+    }
+
+    let mut arguments = vec![
+        program.to_string_lossy().to_string(),
+        "ip".to_string(),
+        "watch".to_string(),
+        "--interval".to_string(),
+        args.interval.to_string(),
+    ];
+    if args.notify {
+        arguments.push("--notify".to_string());
+    }
+    if args.ipv6 {
+        arguments.push("--ipv6".to_string());
+    }
+    if let Some(endpoint) = &args.endpoint {
+        arguments.extend(["--endpoint".to_string(), endpoint.clone()]);
+    }
+
+    // The output only repeats the log file, so only errors are kept
+    LaunchAgent {
+        label: LABEL,
+        arguments: &arguments,
+        start: Start::KeepAlive,
+        log: Log::Errors(&log),
+    }
+    .install()?;
+
+    println!(
+        "{} the public IP watcher, checking every {} s, logging to {}",
+        "Installed".green(),
+        args.interval,
+        log.display()
+    );
+    Ok(())
+}
+
+/// Unloads and removes the LaunchAgent
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be unloaded or removed.
+fn uninstall() -> Result<(), Box<dyn Error>> {
+    if !launchd::uninstall(LABEL)? {
+        println!("The public IP watcher is not installed");
+        return Ok(());
+    }
+    println!("{} the public IP watcher", "Removed".green());
+    Ok(())
+}
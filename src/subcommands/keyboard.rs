@@ -0,0 +1,119 @@
+//! Keyboard settings
+//!
+//! `backlight` shows and sets the brightness of the keyboard backlight of
+//! laptops and switches its adjustment to the ambient light, like the
+//! brightness keys and the Keyboard settings. macOS offers no command for
+//! this; the backlight is controlled through the private CoreBrightness
+//! framework.
+//!
+//! `repeat` and `swap-caps-ctrl` set the key repeat and swap Caps Lock and
+//! Control; see the [`keys`] module.
+//!
+//! # System Commands Used
+//!
+//! - The backlight is read and set with `KeyboardBrightnessClient` of CoreBrightness
+
+#[cfg(target_os = "macos")]
+mod corebrightness;
+pub mod keys;
+
+use crate::cli::{KeyboardArgs, KeyboardBacklightArgs, KeyboardBacklightCommands, KeyboardCommands, Switch};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, Write};
+
+#[cfg(target_os = "macos")]
+use corebrightness::{auto_brightness, brightness, set_auto_brightness, set_brightness};
+
+/// Performs keyboard operations
+///
+/// # Arguments
+///
+/// * `args` - Keyboard arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no keyboard backlight or a setting cannot be changed.
+pub fn perform(args: KeyboardArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        KeyboardCommands::Backlight(args) => backlight(args),
+        KeyboardCommands::Repeat(args) => keys::repeat(args),
+        KeyboardCommands::SwapCapsCtrl { state } => keys::swap_caps_ctrl(state),
+    }
+}
+
+/// Shows or sets the keyboard backlight
+///
+/// # Errors
+///
+/// Returns an error if the Mac has no keyboard backlight or it cannot be changed.
+fn backlight(args: KeyboardBacklightArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(state) = args.auto {
+        let on = state == Switch::On;
+        return change(
+            &format!("Turn automatic keyboard brightness {}", if on { "on" } else { "off" }),
+            || set_auto_brightness(on),
+        );
+    }
+    match args.command {
+        None | Some(KeyboardBacklightCommands::Get) => {
+            println!("{:<16}{}%", "Backlight".bold(), percent(brightness()?));
+            let auto = if auto_brightness()? {
+                "on".green()
+            } else {
+                "off".normal()
+            };
+            println!("{:<16}{auto}", "Automatic".bold());
+            Ok(())
+        }
+        Some(KeyboardBacklightCommands::Set { brightness }) => change(
+            &format!("Set the keyboard backlight to {}%", percent(brightness)),
+            || set_brightness(brightness),
+        ),
+    }
+}
+
+/// Applies a change and prints it with its result
+///
+/// # Errors
+///
+/// Returns an error if the change fails.
+fn change(label: &str, apply: impl FnOnce() -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    print!("{label}");
+    io::stdout().flush()?;
+    if let Err(e) = apply() {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Converts a brightness between 0 and 1 to percent
+fn percent(brightness: f32) -> u8 {
+    (brightness.clamp(0.0, 1.0) * 100.0).round() as u8
+}
+
+/// Reads the keyboard brightness; requires CoreBrightness
+#[cfg(not(target_os = "macos"))]
+fn brightness() -> Result<f32, Box<dyn Error>> {
+    Err("The keyboard backlight can only be controlled on macOS".into())
+}
+
+/// Sets the keyboard brightness; requires CoreBrightness
+#[cfg(not(target_os = "macos"))]
+fn set_brightness(_brightness: f32) -> Result<(), Box<dyn Error>> {
+    Err("The keyboard backlight can only be controlled on macOS".into())
+}
+
+/// Reads whether the keyboard brightness is automatic; requires CoreBrightness
+#[cfg(not(target_os = "macos"))]
+fn auto_brightness() -> Result<bool, Box<dyn Error>> {
+    Err("The keyboard backlight can only be controlled on macOS".into())
+}
+
+/// Switches the automatic keyboard brightness; requires CoreBrightness
+#[cfg(not(target_os = "macos"))]
+fn set_auto_brightness(_on: bool) -> Result<(), Box<dyn Error>> {
+    Err("The keyboard backlight can only be controlled on macOS".into())
+}
@@ -0,0 +1,167 @@
+//! Keyboard backlight from the private CoreBrightness framework
+//!
+//! The backlight is controlled by the Objective-C class
+//! `KeyboardBrightnessClient` of CoreBrightness, which the brightness keys and
+//! Control Center use as well. Its methods take the id of a keyboard with a
+//! backlight; the built-in keyboard is used. CoreBrightness is private, so it
+//! is loaded at runtime with `dlopen`.
+
+use crate::objc::{Id, Sel, class, msg_send, objects, selector, send_id};
+use std::error::Error;
+use std::ffi::{CStr, c_char, c_void};
+
+unsafe extern "C" {
+    fn dlopen(path: *const c_char, mode: i32) -> *mut c_void;
+}
+
+/// `RTLD_LAZY`
+const RTLD_LAZY: i32 = 1;
+
+/// Framework with the keyboard brightness client
+const CORE_BRIGHTNESS: &CStr = c"/System/Library/PrivateFrameworks/CoreBrightness.framework/CoreBrightness";
+
+/// An instance of `KeyboardBrightnessClient` with the keyboard it controls, released on drop
+struct KeyboardClient {
+    /// The client
+    client: Id,
+    /// Id of the keyboard with a backlight
+    keyboard: u64,
+}
+
+impl KeyboardClient {
+    /// Creates a client for the built-in keyboard, or else the first one with a backlight
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if CoreBrightness or its keyboard client is missing, or
+    /// no keyboard has a backlight.
+    fn new() -> Result<Self, Box<dyn Error>> {
+        if unsafe { dlopen(CORE_BRIGHTNESS.as_ptr(), RTLD_LAZY) }.is_null() {
+            return Err(format!("Failed to load {}", CORE_BRIGHTNESS.to_string_lossy()).into());
+        }
+        let class = class(c"KeyboardBrightnessClient");
+        if class.is_null() {
+            return Err("The keyboard backlight is not supported by this version of macOS".into());
+        }
+        let client = unsafe { send_id(send_id(class, c"alloc"), c"init") };
+        if client.is_null() {
+            return Err("Failed to create the keyboard brightness client".into());
+        }
+        let mut keyboard = KeyboardClient { client, keyboard: 0 };
+        keyboard.keyboard = keyboard.backlit_keyboard()?;
+        Ok(keyboard)
+    }
+
+    /// Returns the id of the built-in keyboard, or else of the first keyboard with a backlight
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no keyboard has a backlight.
+    fn backlit_keyboard(&self) -> Result<u64, Box<dyn Error>> {
+        unsafe {
+            // `copy` returns an owned array
+            let ids = send_id(self.client, c"copyKeyboardBacklightIDs");
+            if ids.is_null() {
+                return Err("No keyboard has a backlight".into());
+            }
+            let value: unsafe extern "C" fn(Id, Sel) -> u64 = msg_send();
+            let keyboards: Vec<u64> = objects(ids)
+                .into_iter()
+                .map(|id| value(id, selector(c"unsignedLongLongValue")))
+                .collect();
+            send_id(ids, c"release");
+
+            keyboards
+                .iter()
+                .copied()
+                .find(|&keyboard| self.get(c"isKeyboardBuiltIn:", keyboard))
+                .or_else(|| keyboards.first().copied())
+                .ok_or_else(|| "No keyboard has a backlight".into())
+        }
+    }
+
+    /// Sends a message with a keyboard id returning a `BOOL`
+    fn get(&self, name: &CStr, keyboard: u64) -> bool {
+        unsafe {
+            let send: unsafe extern "C" fn(Id, Sel, u64) -> i8 = msg_send();
+            send(self.client, selector(name), keyboard) != 0
+        }
+    }
+}
+
+impl Drop for KeyboardClient {
+    fn drop(&mut self) {
+        unsafe { send_id(self.client, c"release") };
+    }
+}
+
+/// Reads the brightness of the keyboard backlight
+///
+/// # Errors
+///
+/// Returns an error if no keyboard has a backlight.
+pub fn brightness() -> Result<f32, Box<dyn Error>> {
+    let client = KeyboardClient::new()?;
+    unsafe {
+        let send: unsafe extern "C" fn(Id, Sel, u64) -> f32 = msg_send();
+        Ok(send(
+            client.client,
+            selector(c"brightnessForKeyboard:"),
+            client.keyboard,
+        ))
+    }
+}
+
+/// Sets the brightness of the keyboard backlight
+///
+/// # Errors
+///
+/// Returns an error if no keyboard has a backlight or setting fails.
+pub fn set_brightness(brightness: f32) -> Result<(), Box<dyn Error>> {
+    let client = KeyboardClient::new()?;
+    let set = unsafe {
+        let send: unsafe extern "C" fn(Id, Sel, f32, u64) -> i8 = msg_send();
+        send(
+            client.client,
+            selector(c"setBrightness:forKeyboard:"),
+            brightness,
+            client.keyboard,
+        )
+    };
+    if set == 0 {
+        return Err("Failed to set the keyboard backlight".into());
+    }
+    Ok(())
+}
+
+/// Reads whether the backlight adjusts to the ambient light
+///
+/// # Errors
+///
+/// Returns an error if no keyboard has a backlight.
+pub fn auto_brightness() -> Result<bool, Box<dyn Error>> {
+    let client = KeyboardClient::new()?;
+    Ok(client.get(c"isAutoBrightnessEnabledForKeyboard:", client.keyboard))
+}
+
+/// Switches the adjustment to the ambient light on or off
+///
+/// # Errors
+///
+/// Returns an error if no keyboard has a backlight or switching fails.
+pub fn set_auto_brightness(on: bool) -> Result<(), Box<dyn Error>> {
+    let client = KeyboardClient::new()?;
+    let set = unsafe {
+        let send: unsafe extern "C" fn(Id, Sel, i8, u64) -> i8 = msg_send();
+        send(
+            client.client,
+            selector(c"enableAutoBrightness:forKeyboard:"),
+            i8::from(on),
+            client.keyboard,
+        )
+    };
+    if set == 0 {
+        return Err("Failed to switch the automatic keyboard brightness".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,193 @@
+//! Key repeat and modifier keys
+//!
+//! `repeat` writes the global key repeat preferences, which apps read when
+//! they start, so the new rate applies to all apps after the next login. The
+//! preferences accept faster values than the sliders of System Settings.
+//!
+//! `swap-caps-ctrl` remaps Caps Lock and the left Control key with `hidutil`,
+//! which takes effect right away but only lasts until the next restart. A
+//! LaunchAgent applies the mapping again at every login. The mapping replaces
+//! other key mappings made with `hidutil`.
+//!
+//! # System Commands Used
+//!
+//! - `defaults read -g KeyRepeat InitialKeyRepeat` - Read the key repeat
+//! - `defaults write -g KeyRepeat InitialKeyRepeat` - Set the key repeat
+//! - `hidutil property --get UserKeyMapping` - Read the key mapping
+//! - `hidutil property --set` - Swap Caps Lock and Control or restore them
+
+use crate::cli::{KeyboardRepeatArgs, Switch};
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use colored::{ColoredString, Colorize};
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// Global preference with the interval between key repeats
+const KEY_REPEAT: &str = "KeyRepeat";
+
+/// Global preference with the delay until a held key repeats
+const INITIAL_KEY_REPEAT: &str = "InitialKeyRepeat";
+
+/// Milliseconds per unit of the key repeat preferences
+const REPEAT_UNIT_MS: u32 = 15;
+
+/// HID usage of Caps Lock
+const CAPS_LOCK: u64 = 0x7_0000_0039;
+
+/// HID usage of the left Control key
+const LEFT_CONTROL: u64 = 0x7_0000_00e0;
+
+/// Label of the LaunchAgent applying the key mapping at login
+const LABEL: &str = "com.github.rfoerthe.rempower.swap-caps-ctrl";
+
+/// Location of `hidutil`
+const HIDUTIL: &str = "/usr/bin/hidutil";
+
+/// Shows or sets the key repeat
+///
+/// # Arguments
+///
+/// * `args` - Repeat arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a preference cannot be written.
+pub fn repeat(args: KeyboardRepeatArgs) -> Result<(), Box<dyn Error>> {
+    if args.rate.is_none() && args.delay.is_none() {
+        println!("{:<16}{}", "Repeat rate".bold(), repeat_setting(KEY_REPEAT));
+        println!("{:<16}{}", "Repeat delay".bold(), repeat_setting(INITIAL_KEY_REPEAT));
+        return Ok(());
+    }
+    if let Some(rate) = args.rate {
+        set_repeat("Set the repeat rate", KEY_REPEAT, rate)?;
+    }
+    if let Some(delay) = args.delay {
+        set_repeat("Set the repeat delay", INITIAL_KEY_REPEAT, delay)?;
+    }
+    println!("Apps apply the key repeat when they start; log out and in again to apply it everywhere");
+    Ok(())
+}
+
+/// Shows or switches swapping Caps Lock and Control
+///
+/// # Arguments
+///
+/// * `state` - `On` to swap the keys, `Off` to restore them, `None` to show the state
+///
+/// # Errors
+///
+/// Returns an error if `hidutil` fails or the LaunchAgent cannot be written or removed.
+pub fn swap_caps_ctrl(state: Option<Switch>) -> Result<(), Box<dyn Error>> {
+    let Some(state) = state else {
+        let swapped = if caps_ctrl_swapped()? {
+            if launchd::installed(LABEL)? {
+                "on, also after restarts".green()
+            } else {
+                "on until the next restart".yellow()
+            }
+        } else {
+            "off".normal()
+        };
+        println!("{:<16}{swapped}", "Swap Caps/Ctrl".bold());
+        return Ok(());
+    };
+
+    let swap = state == Switch::On;
+    let action = if swap { "Swap" } else { "Restore" };
+    print!("{action} Caps Lock and Control");
+    io::stdout().flush()?;
+    let mapping = key_mapping(swap);
+    if let Err(e) = hidutil(&["property", "--set", &mapping]) {
+        println!();
+        return Err(e);
+    }
+    if swap {
+        // The LaunchAgent runs hidutil once at every login and does not stay running
+        let arguments = [HIDUTIL, "property", "--set", &mapping].map(str::to_string);
+        LaunchAgent {
+            label: LABEL,
+            arguments: &arguments,
+            start: Start::AtLogin,
+            log: Log::None,
+        }
+        .install()?;
+    } else {
+        launchd::uninstall(LABEL)?;
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Writes a key repeat preference and prints it with its result
+///
+/// # Errors
+///
+/// Returns an error if `defaults` fails.
+fn set_repeat(label: &str, key: &str, value: u32) -> Result<(), Box<dyn Error>> {
+    print!("{label} to {value} ({} ms)", value * REPEAT_UNIT_MS);
+    io::stdout().flush()?;
+    let output = Command::new("defaults")
+        .args(["write", "-g", key, "-int", &value.to_string()])
+        .output()
+        .map_err(|e| format!("Failed to run defaults: {e}"))?;
+    if !output.status.success() {
+        println!();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to write {key}: {}", stderr.trim()).into());
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Formats a key repeat preference, or marks it as the default
+fn repeat_setting(key: &str) -> ColoredString {
+    let value = Command::new("defaults")
+        .args(["read", "-g", key])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse::<u32>().ok());
+    match value {
+        Some(value) => format!("{value} ({} ms)", value * REPEAT_UNIT_MS).normal(),
+        None => "macOS default".dimmed(),
+    }
+}
+
+/// Returns true if the current key mapping swaps Caps Lock and Control
+///
+/// # Errors
+///
+/// Returns an error if `hidutil` fails.
+fn caps_ctrl_swapped() -> Result<bool, Box<dyn Error>> {
+    // The mapping is printed as a property list with decimal usages
+    let mapping = hidutil(&["property", "--get", "UserKeyMapping"])?;
+    Ok(mapping.contains(&CAPS_LOCK.to_string()) && mapping.contains(&LEFT_CONTROL.to_string()))
+}
+
+/// Returns the `UserKeyMapping` property swapping Caps Lock and Control, or an empty one
+fn key_mapping(swap: bool) -> String {
+    if !swap {
+        return r#"{"UserKeyMapping":[]}"#.to_string();
+    }
+    format!(
+        r#"{{"UserKeyMapping":[{{"HIDKeyboardModifierMappingSrc":{CAPS_LOCK},"HIDKeyboardModifierMappingDst":{LEFT_CONTROL}}},{{"HIDKeyboardModifierMappingSrc":{LEFT_CONTROL},"HIDKeyboardModifierMappingDst":{CAPS_LOCK}}}]}}"#
+    )
+}
+
+/// Runs `hidutil` and returns its output
+///
+/// # Errors
+///
+/// Returns an error if `hidutil` fails.
+fn hidutil(args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new(HIDUTIL)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run hidutil: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("hidutil failed: {}", stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,177 @@
+//! Network location subcommand
+//!
+//! Lists, switches, creates and deletes macOS network locations. A location holds a
+//! complete set of network service settings; switching it together with a DNS
+//! preset (`rem location switch Office --dns dhcp`) changes the whole network
+//! environment in one step.
+//!
+//! # System Commands Used
+//!
+//! - `networksetup -listlocations` - List network locations
+//! - `networksetup -getcurrentlocation` - Get the active location
+//! - `networksetup -switchtolocation` - Activate a location
+//! - `networksetup -createlocation` - Create a location
+//! - `networksetup -deletelocation` - Delete a location
+
+use crate::cli::{LocationArgs, LocationCommands};
+use crate::subcommands::dns::auto::{apply_preset, current_location, preset_args};
+use colored::Colorize;
+use std::error::Error;
+use std::process::Command;
+
+/// Performs network location operations
+///
+/// # Arguments
+///
+/// * `args` - Location arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the locations cannot be read, the named location does not
+/// exist (or already exists when creating it), or `networksetup` fails.
+pub fn perform(args: LocationArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(LocationCommands::List) => list(),
+        Some(LocationCommands::Switch { name, dns }) => switch(&name, dns.as_deref()),
+        Some(LocationCommands::Create { name }) => create(&name),
+        Some(LocationCommands::Delete { name }) => delete(&name),
+    }
+}
+
+/// Prints all locations, marking the active one
+///
+/// # Errors
+///
+/// Returns an error if the locations cannot be read.
+fn list() -> Result<(), Box<dyn Error>> {
+    let current = current_location();
+    for location in locations()? {
+        if current.as_deref() == Some(location.as_str()) {
+            println!("{} {}", "*".green(), location.green().bold());
+        } else {
+            println!("  {location}");
+        }
+    }
+    Ok(())
+}
+
+/// Activates a location and optionally applies a DNS preset
+///
+/// The preset is validated before switching, so an invalid preset leaves the
+/// location unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the location does not exist, the preset is invalid, or
+/// switching or applying the preset fails.
+fn switch(name: &str, dns: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let location = resolve_location(name)?;
+    if let Some(preset) = dns {
+        preset_args(preset)?;
+    }
+
+    if current_location().as_deref() == Some(location.as_str()) {
+        println!("Location {} is already active", location.bold());
+    } else {
+        print!("Switch to location {}", location.bold());
+        run_networksetup(&["-switchtolocation", &location])?;
+        println!("{}", " OK".green());
+    }
+
+    match dns {
+        Some(preset) => apply_preset(preset, false),
+        None => Ok(()),
+    }
+}
+
+/// Creates a location populated with the default network services
+///
+/// # Errors
+///
+/// Returns an error if a location with this name exists or `networksetup` fails.
+fn create(name: &str) -> Result<(), Box<dyn Error>> {
+    if let Some(existing) = find_location(&locations()?, name) {
+        return Err(format!("Location '{existing}' already exists").into());
+    }
+
+    print!("Create location {}", name.bold());
+    run_networksetup(&["-createlocation", name, "populate"])?;
+    println!("{}", " OK".green());
+
+    Ok(())
+}
+
+/// Deletes a location
+///
+/// # Errors
+///
+/// Returns an error if the location does not exist, is active, or `networksetup` fails.
+fn delete(name: &str) -> Result<(), Box<dyn Error>> {
+    let location = resolve_location(name)?;
+    if current_location().as_deref() == Some(location.as_str()) {
+        return Err(format!("Location '{location}' is active, switch to another location first").into());
+    }
+
+    print!("Delete location {}", location.bold());
+    run_networksetup(&["-deletelocation", &location])?;
+    println!("{}", " OK".green());
+
+    Ok(())
+}
+
+/// Returns the exact name of an existing location
+///
+/// # Errors
+///
+/// Returns an error listing the available locations if none matches.
+fn resolve_location(name: &str) -> Result<String, Box<dyn Error>> {
+    let locations = locations()?;
+    find_location(&locations, name)
+        .map(str::to_string)
+        .ok_or_else(|| format!("Unknown location '{name}', available: {}", locations.join(", ")).into())
+}
+
+/// Finds a location by name, preferring an exact over a case-insensitive match
+fn find_location<'a>(locations: &'a [String], name: &str) -> Option<&'a str> {
+    locations
+        .iter()
+        .find(|location| *location == name)
+        .or_else(|| locations.iter().find(|location| location.eq_ignore_ascii_case(name)))
+        .map(String::as_str)
+}
+
+/// Returns the names of all locations
+///
+/// # Errors
+///
+/// Returns an error if `networksetup` fails.
+fn locations() -> Result<Vec<String>, Box<dyn Error>> {
+    let output = Command::new("networksetup").arg("-listlocations").output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list network locations: {}", stderr.trim()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Runs `networksetup` with sudo, as changing locations requires administrator rights
+///
+/// # Errors
+///
+/// Returns an error containing the output of `networksetup` if it fails.
+fn run_networksetup(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("sudo").arg("networksetup").args(args).output()?;
+    // networksetup reports some errors on stdout with exit code 0
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || stdout.contains("Error") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("networksetup failed: {} {}", stdout.trim(), stderr.trim()).into());
+    }
+    Ok(())
+}
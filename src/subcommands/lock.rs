@@ -0,0 +1,220 @@
+//! Screen lock and screen saver
+//!
+//! `lock` locks the screen right away like Lock Screen in the Apple menu, also
+//! when the password is only required some time after the screen saver
+//! begins; see the [`login`] module. With `--keychain`, the default keychain is
+//! locked first, so that apps ask for its password again after unlocking.
+//!
+//! `lock settings` shows and sets when the password is required after sleep
+//! or the screen saver begins. Since macOS 13 this setting is no longer read
+//! from the screen saver preferences; `sysadminctl` changes it and asks for the
+//! login password of the user to do so.
+//!
+//! # System Commands Used
+//!
+//! - `security lock-keychain` - Lock the default keychain
+//! - `sysadminctl -screenLock` - Read and set the password delay of the screen lock
+//! - `defaults -currentHost read com.apple.screensaver idleTime` - Read the screen saver delay
+//! - `open -a ScreenSaverEngine` - Start the screen saver
+
+#[cfg(target_os = "macos")]
+mod login;
+
+use crate::cli::{LockArgs, LockCommands, LockSettingsArgs, PasswordDelay, ScreensaverArgs, ScreensaverCommands};
+use colored::{ColoredString, Colorize};
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::Command;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use login::lock_screen;
+
+/// Performs screen lock operations
+///
+/// # Arguments
+///
+/// * `args` - Lock arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the screen or keychain cannot be locked, or the screen
+/// lock settings cannot be read or changed.
+pub fn perform(args: LockArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None => lock(args.keychain),
+        Some(LockCommands::Settings(args)) => settings(args),
+    }
+}
+
+/// Performs screen saver operations
+///
+/// # Arguments
+///
+/// * `args` - Screen saver arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the screen saver cannot be started.
+pub fn screensaver(args: ScreensaverArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        ScreensaverCommands::Start => {
+            let output = Command::new("open")
+                .args(["-a", "ScreenSaverEngine"])
+                .output()
+                .map_err(|e| format!("Failed to run open: {e}"))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("Failed to start the screen saver: {}", stderr.trim()).into());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Locks the screen, and the default keychain before
+///
+/// # Errors
+///
+/// Returns an error if the keychain or the screen cannot be locked.
+fn lock(keychain: bool) -> Result<(), Box<dyn Error>> {
+    if keychain {
+        print!("Lock the keychain");
+        io::stdout().flush()?;
+        if let Err(e) = lock_keychain() {
+            println!();
+            return Err(e);
+        }
+        println!("{}", " OK".green());
+    }
+    lock_screen()
+}
+
+/// Locks the default keychain
+///
+/// # Errors
+///
+/// Returns an error if `security` fails.
+fn lock_keychain() -> Result<(), Box<dyn Error>> {
+    let output = Command::new("security")
+        .arg("lock-keychain")
+        .output()
+        .map_err(|e| format!("Failed to run security: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to lock the keychain: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Shows or sets when the password is required
+///
+/// # Errors
+///
+/// Returns an error if `sysadminctl` fails or the setting did not change.
+fn settings(args: LockSettingsArgs) -> Result<(), Box<dyn Error>> {
+    let Some(delay) = args.require_password else {
+        let delay = password_delay()?;
+        let password = match delay {
+            PasswordDelay::Immediately => describe(delay).green(),
+            PasswordDelay::After(_) => describe(delay).normal(),
+            PasswordDelay::Never => describe(delay).yellow(),
+        };
+        println!("{:<16}{password}", "Password".bold());
+        println!("{:<16}{}", "Screen saver".bold(), screensaver_delay());
+        return Ok(());
+    };
+
+    let value = match delay {
+        PasswordDelay::Immediately => "immediate".to_string(),
+        PasswordDelay::After(delay) => delay.as_secs().to_string(),
+        PasswordDelay::Never => "off".to_string(),
+    };
+    println!("Changing the screen lock requires the login password");
+    // `-password -` prompts for the password on the terminal
+    let status = Command::new("sysadminctl")
+        .args(["-screenLock", &value, "-password", "-"])
+        .status()
+        .map_err(|e| format!("Failed to run sysadminctl: {e}"))?;
+
+    print!("Require the password {}", describe(delay));
+    io::stdout().flush()?;
+    if !status.success() || password_delay().ok() != Some(delay) {
+        println!();
+        return Err("Failed to change the screen lock, check the password".into());
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Reads when the password is required after sleep or the screen saver
+///
+/// # Errors
+///
+/// Returns an error if `sysadminctl` fails or reports an unknown setting.
+fn password_delay() -> Result<PasswordDelay, Box<dyn Error>> {
+    let output = Command::new("sysadminctl")
+        .args(["-screenLock", "status"])
+        .output()
+        .map_err(|e| format!("Failed to run sysadminctl: {e}"))?;
+    // The status is logged to stderr, e.g. `... screenLock delay is 300 seconds`
+    let log = String::from_utf8_lossy(&output.stderr);
+    let status = log
+        .lines()
+        .find_map(|line| line.split_once("screenLock ").map(|(_, status)| status.trim()))
+        .ok_or("Failed to read the screen lock settings")?;
+    if status == "is off" {
+        return Ok(PasswordDelay::Never);
+    }
+    match status
+        .strip_prefix("delay is ")
+        .map(|delay| delay.trim_end_matches(" seconds"))
+    {
+        Some("immediate") | Some("0") => Ok(PasswordDelay::Immediately),
+        Some(seconds) => seconds
+            .parse()
+            .map(|seconds| PasswordDelay::After(Duration::from_secs(seconds)))
+            .map_err(|_| format!("Unknown screen lock setting '{status}'").into()),
+        None => Err(format!("Unknown screen lock setting '{status}'").into()),
+    }
+}
+
+/// Returns the idle time until the screen saver starts
+fn screensaver_delay() -> ColoredString {
+    let seconds = Command::new("defaults")
+        .args(["-currentHost", "read", "com.apple.screensaver", "idleTime"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok());
+    match seconds {
+        Some(0) => "never".yellow(),
+        Some(seconds) => format!("after {} idle", format_seconds(seconds)).normal(),
+        None => "macOS default".dimmed(),
+    }
+}
+
+/// Describes when the password is required
+fn describe(delay: PasswordDelay) -> String {
+    match delay {
+        PasswordDelay::Immediately => "immediately".to_string(),
+        PasswordDelay::After(delay) => format!("after {}", format_seconds(delay.as_secs())),
+        PasswordDelay::Never => "never".to_string(),
+    }
+}
+
+/// Formats seconds in the largest whole unit, e.g. `5 minutes`
+fn format_seconds(seconds: u64) -> String {
+    let (count, unit) = match seconds {
+        s if s >= 3600 && s % 3600 == 0 => (s / 3600, "hour"),
+        s if s >= 60 && s % 60 == 0 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    format!("{count} {unit}{}", if count == 1 { "" } else { "s" })
+}
+
+/// Locks the screen; requires the login framework
+#[cfg(not(target_os = "macos"))]
+fn lock_screen() -> Result<(), Box<dyn Error>> {
+    Err("The screen can only be locked on macOS".into())
+}
@@ -0,0 +1,47 @@
+//! Immediate screen lock from the private login framework
+//!
+//! `SACLockScreenImmediate` is what the Lock Screen item of the Apple menu
+//! calls. It locks regardless of the password delay of the screen lock
+//! settings. The framework is private and cannot be linked against, so it is
+//! loaded at runtime with `dlopen`.
+
+use std::error::Error;
+use std::ffi::{CStr, c_char, c_void};
+use std::mem;
+
+unsafe extern "C" {
+    fn dlopen(path: *const c_char, mode: i32) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+/// `SACLockScreenImmediate`
+type LockScreenImmediate = unsafe extern "C" fn() -> i32;
+
+/// `RTLD_LAZY`
+const RTLD_LAZY: i32 = 1;
+
+/// Framework with the screen lock
+const LOGIN: &CStr = c"/System/Library/PrivateFrameworks/login.framework/Versions/Current/login";
+
+/// Locks the screen right away
+///
+/// # Errors
+///
+/// Returns an error if the login framework or its lock function is missing,
+/// or locking fails.
+pub fn lock_screen() -> Result<(), Box<dyn Error>> {
+    let handle = unsafe { dlopen(LOGIN.as_ptr(), RTLD_LAZY) };
+    if handle.is_null() {
+        return Err(format!("Failed to load {}", LOGIN.to_string_lossy()).into());
+    }
+    let function = unsafe { dlsym(handle, c"SACLockScreenImmediate".as_ptr()) };
+    if function.is_null() {
+        return Err("Locking the screen is not supported by this version of macOS".into());
+    }
+    let lock: LockScreenImmediate = unsafe { mem::transmute(function) };
+    let result = unsafe { lock() };
+    if result != 0 {
+        return Err(format!("Failed to lock the screen (error {result})").into());
+    }
+    Ok(())
+}
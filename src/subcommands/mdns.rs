@@ -0,0 +1,161 @@
+//! Bonjour (mDNS) service advertising
+//!
+//! Registers a service with the mDNS responder of macOS, so that other devices
+//! on the local network find it by name, e.g. a development server shows up as
+//! `devbox` in Bonjour browsers and resolves to this Mac without knowing its IP
+//! address. The registration lasts as long as `rem` runs and is withdrawn when it
+//! stops; if the name is already taken on the network, the responder picks a
+//! free one like `devbox (2)`.
+//!
+//! # System Commands Used
+//!
+//! - `dns-sd -R` - Register the service for the lifetime of the process
+//! - `scutil --get LocalHostName` - Read the Bonjour host name
+
+use crate::cli::{MdnsAdvertiseArgs, MdnsArgs, MdnsCommands};
+use colored::Colorize;
+use regex::Regex;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+/// Maximum length of a service instance name in bytes
+const MAX_NAME_LENGTH: usize = 63;
+
+/// Maximum length of a TXT record entry in bytes
+const MAX_TXT_LENGTH: usize = 255;
+
+/// Reply of `dns-sd` once the service is registered
+const REGISTERED: &str = "Name now registered and active";
+
+/// Prefix of the replies of `dns-sd`, followed by the full service name
+const REPLY_PREFIX: &str = "Got a reply for service ";
+
+/// Performs mDNS operations
+///
+/// # Arguments
+///
+/// * `args` - mDNS arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the service cannot be registered.
+pub fn perform(args: MdnsArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        MdnsCommands::Advertise(args) => advertise(&args),
+    }
+}
+
+/// Registers a service and keeps it registered until the process is stopped
+///
+/// # Errors
+///
+/// Returns an error if the name, type or TXT entries are invalid, `dns-sd`
+/// cannot be run, or the mDNS responder rejects the registration.
+fn advertise(args: &MdnsAdvertiseArgs) -> Result<(), Box<dyn Error>> {
+    let name = args.name.as_deref().unwrap_or_default();
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(format!("Invalid name '{name}', names are at most {MAX_NAME_LENGTH} bytes long").into());
+    }
+    let type_regex = Regex::new(r"^_[A-Za-z0-9][A-Za-z0-9-]{0,14}\._(tcp|udp)$")?;
+    if !type_regex.is_match(&args.service_type) {
+        return Err(format!(
+            "Invalid service type '{}', expected e.g. _http._tcp or _osc._udp",
+            args.service_type
+        )
+        .into());
+    }
+    for entry in &args.txt {
+        if entry.starts_with('=') || !entry.contains('=') || entry.len() > MAX_TXT_LENGTH {
+            return Err(
+                format!("Invalid TXT entry '{entry}', expected KEY=VALUE of at most {MAX_TXT_LENGTH} bytes").into(),
+            );
+        }
+    }
+
+    // An empty name makes the responder use the computer name
+    let mut child = Command::new("dns-sd")
+        .arg("-R")
+        .args([name, &args.service_type, &args.domain, &args.port.to_string()])
+        .args(&args.txt)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run dns-sd: {e}"))?;
+    let stdout = child.stdout.take().ok_or("Failed to read the output of dns-sd")?;
+
+    // e.g. "12:00:00.123  Got a reply for service devbox._http._tcp.local.: Name now registered and active"
+    let suffix = format!(".{}.{}.", args.service_type, args.domain.trim_end_matches('.'));
+    let mut registered = false;
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        let Some((_, reply)) = line.split_once(REPLY_PREFIX) else {
+            continue;
+        };
+        let Some((service, status)) = reply.split_once(": ") else {
+            continue;
+        };
+        let instance = service.strip_suffix(&suffix).unwrap_or(service);
+
+        if status.trim() == REGISTERED {
+            print_registered(instance, name, args);
+            registered = true;
+        } else if registered {
+            println!("{} {instance}: {}", "mDNS responder:".yellow(), status.trim());
+        } else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Failed to register '{instance}': {}", status.trim()).into());
+        }
+    }
+
+    let status = child.wait()?;
+    Err(format!("dns-sd stopped unexpectedly ({status}), the service is no longer advertised").into())
+}
+
+/// Prints the registered service and how to reach it
+///
+/// # Arguments
+///
+/// * `instance` - Name the service was registered with
+/// * `requested` - Name given on the command line, empty for the computer name
+/// * `args` - Advertise arguments from the command line
+fn print_registered(instance: &str, requested: &str, args: &MdnsAdvertiseArgs) {
+    println!(
+        "{} '{}' ({}) on port {} in {} (Ctrl-C to stop)",
+        "Advertising".green(),
+        instance.bold(),
+        args.service_type,
+        args.port,
+        args.domain.trim_end_matches('.')
+    );
+    if !requested.is_empty() && instance != requested {
+        println!(
+            "{}",
+            format!("The name '{requested}' is taken on the network, the responder renamed the service").yellow()
+        );
+    }
+    for entry in &args.txt {
+        println!("  {entry}");
+    }
+
+    let scheme = match args.service_type.as_str() {
+        "_http._tcp" => Some("http"),
+        "_https._tcp" => Some("https"),
+        _ => None,
+    };
+    if let Some(scheme) = scheme
+        && let Some(host) = local_host_name()
+    {
+        println!("Reachable at {scheme}://{host}.local:{}", args.port);
+    }
+}
+
+/// Reads the Bonjour host name of this Mac
+fn local_host_name() -> Option<String> {
+    let output = Command::new("scutil").args(["--get", "LocalHostName"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
@@ -0,0 +1,307 @@
+//! MTU inspection and configuration
+//!
+//! Shows and sets the MTU (maximum transmission unit) of an interface, and
+//! discovers the path MTU to a host: the largest IPv4 packet that reaches the
+//! host without fragmentation. The path MTU is found with a binary search over
+//! pings with the Don't Fragment bit set. Paths that silently drop such packets
+//! instead of reporting that fragmentation is needed ("blackholes", common on
+//! VPNs) stall large transfers while small requests still work; lowering the MTU
+//! of the interface to the path MTU works around this.
+//!
+//! The MTU of hardware ports (e.g. Wi-Fi) is set with `networksetup` and persists
+//! across reboots. Other interfaces like VPN tunnels are set with `ifconfig`,
+//! which lasts until the interface is recreated.
+//!
+//! # System Commands Used
+//!
+//! - `ifconfig` - Read the MTU of an interface, set it for interfaces without hardware port (with sudo)
+//! - `networksetup -listValidMTURange` - Read the valid MTU range of a hardware port
+//! - `networksetup -setMTU` - Set the MTU of a hardware port (with sudo)
+//! - `ping -D` - Probe the path with unfragmented packets
+//! - `route -n get default` - Find the primary interface
+
+use super::ip;
+use crate::cli::{MtuArgs, MtuCommands};
+use colored::Colorize;
+use std::error::Error;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::process::Command;
+use std::time::Duration;
+
+/// Bytes of the IPv4 and ICMP headers of an echo request
+const ICMP_OVERHEAD: u32 = 28;
+
+/// Smallest packet size probed, which every IPv4 host must be able to receive
+const MIN_PROBE_SIZE: u32 = 576;
+
+/// MTU assumed if the interface MTU is unknown
+const DEFAULT_MTU: u32 = 1500;
+
+/// Time to wait for the reply to a probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Performs MTU operations
+///
+/// # Arguments
+///
+/// * `args` - MTU arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the interface cannot be determined, the MTU cannot be
+/// read or set, or the host cannot be resolved or does not answer pings.
+pub fn perform(args: MtuArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Some(MtuCommands::Get { interface }) => get(&select_interface(interface)?),
+        Some(MtuCommands::Set { value, interface }) => set(&select_interface(interface)?, value),
+        Some(MtuCommands::Discover { host, max }) => discover(&host, max),
+        None => get(&select_interface(None)?),
+    }
+}
+
+/// Returns the given interface or the interface of the default route
+///
+/// # Errors
+///
+/// Returns an error if no interface is given and there is no default route.
+fn select_interface(interface: Option<String>) -> Result<String, Box<dyn Error>> {
+    match interface {
+        Some(interface) => Ok(interface),
+        None => ip::default_interface().ok_or_else(|| "No default route, use --interface".into()),
+    }
+}
+
+/// Prints the MTU and the valid MTU range of an interface
+///
+/// # Errors
+///
+/// Returns an error if the MTU of the interface cannot be read.
+fn get(device: &str) -> Result<(), Box<dyn Error>> {
+    let mtu = interface_mtu(device).ok_or_else(|| format!("Cannot read the MTU of {device}"))?;
+    let interface = match ip::hardware_ports().remove(device) {
+        Some(service) => format!("{device} ({service})"),
+        None => device.to_string(),
+    };
+
+    println!("{:<16}{interface}", "Interface".bold());
+    println!("{:<16}{mtu}", "MTU".bold());
+    if let Some((min, max)) = valid_range(device) {
+        println!("{:<16}{min}–{max}", "Valid range".bold());
+    }
+
+    Ok(())
+}
+
+/// Sets the MTU of an interface
+///
+/// # Errors
+///
+/// Returns an error if the value is outside the valid range of the hardware port
+/// or the MTU cannot be set.
+fn set(device: &str, value: u32) -> Result<(), Box<dyn Error>> {
+    let hardware_port = ip::hardware_ports().contains_key(device);
+    if let Some((min, max)) = valid_range(device)
+        && !(min..=max).contains(&value)
+    {
+        return Err(format!("MTU {value} is outside the valid range of {device} ({min}–{max})").into());
+    }
+
+    let output = if hardware_port {
+        Command::new("sudo")
+            .args(["networksetup", "-setMTU", device, &value.to_string()])
+            .output()
+            .map_err(|e| format!("Failed to run networksetup: {e}"))?
+    } else {
+        Command::new("sudo")
+            .args(["ifconfig", device, "mtu", &value.to_string()])
+            .output()
+            .map_err(|e| format!("Failed to run ifconfig: {e}"))?
+    };
+    // networksetup reports errors on stdout with exit code 0
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || stdout.contains("Error") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = if stderr.trim().is_empty() {
+            stdout.trim()
+        } else {
+            stderr.trim()
+        };
+        return Err(format!("Failed to set the MTU of {device}: {message}").into());
+    }
+
+    println!("{} the MTU of {device} to {value}", "Set".green());
+    if !hardware_port {
+        println!("{device} is no hardware port, the MTU resets when the interface is recreated");
+    }
+
+    Ok(())
+}
+
+/// Discovers the path MTU to a host and prints the probes
+///
+/// # Arguments
+///
+/// * `host` - Host name or IPv4 address
+/// * `max` - Largest packet size probed (default: MTU of the primary interface)
+///
+/// # Errors
+///
+/// Returns an error if the host cannot be resolved to an IPv4 address or does not
+/// answer pings.
+fn discover(host: &str, max: Option<u32>) -> Result<(), Box<dyn Error>> {
+    let address = resolve(host)?;
+    let device = ip::default_interface();
+    let interface_mtu = device.as_deref().and_then(interface_mtu);
+    let max = max.or(interface_mtu).unwrap_or(DEFAULT_MTU);
+
+    println!(
+        "Discovering the path MTU to {} ({address}), up to {max} bytes",
+        host.bold()
+    );
+    let path_mtu = path_mtu(address, max, PROBE_TIMEOUT, |size, passed| {
+        if passed {
+            println!("  {size:>5} bytes  {}", "✓".green());
+        } else {
+            println!("  {size:>5} bytes  {}", "✗ no reply".red());
+        }
+    })
+    .ok_or_else(|| format!("{host} does not answer pings, the path MTU cannot be discovered"))?;
+
+    println!();
+    println!("{:<16}{path_mtu} bytes", "Path MTU".bold());
+    if let (Some(device), Some(mtu)) = (device, interface_mtu) {
+        println!("{:<16}{mtu} bytes ({device})", "Interface MTU".bold());
+        if path_mtu < mtu {
+            println!(
+                "{}",
+                format!("Lower the MTU with `rem mtu set {path_mtu} --interface {device}` if large transfers stall")
+                    .yellow()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the largest packet size that passes the path to a host unfragmented
+///
+/// Probes `max` first, then searches between the minimum probe size of 576 bytes
+/// and `max`. A lost reply cannot be told apart from a dropped packet, so a lossy
+/// path may yield a lower value.
+///
+/// # Arguments
+///
+/// * `target` - IPv4 address of the host
+/// * `max` - Largest packet size probed, usually the interface MTU
+/// * `timeout` - Time to wait for each reply
+/// * `probed` - Called with the size and outcome of every probe
+///
+/// # Returns
+///
+/// The path MTU, or `None` if the host does not answer pings of the minimum size.
+pub fn path_mtu(target: IpAddr, max: u32, timeout: Duration, mut probed: impl FnMut(u32, bool)) -> Option<u32> {
+    let mut probe = |size| {
+        let passed = unfragmented_ping(target, size, timeout);
+        probed(size, passed);
+        passed
+    };
+
+    if probe(max) {
+        return Some(max);
+    }
+    if max <= MIN_PROBE_SIZE || !probe(MIN_PROBE_SIZE) {
+        return None;
+    }
+    let (mut low, mut high) = (MIN_PROBE_SIZE, max);
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+        if probe(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Some(low)
+}
+
+/// Sends a ping of the given packet size with the Don't Fragment bit set
+///
+/// Up to two echo requests are sent, so a single lost packet is not taken for a
+/// packet that is too large.
+///
+/// # Returns
+///
+/// True if a reply was received.
+fn unfragmented_ping(target: IpAddr, packet_size: u32, timeout: Duration) -> bool {
+    let payload = packet_size.saturating_sub(ICMP_OVERHEAD).to_string();
+    let wait = timeout.as_millis().to_string();
+    Command::new("ping")
+        .args([
+            "-n",
+            "-c",
+            "2",
+            "-o",
+            "-D",
+            "-s",
+            &payload,
+            "-W",
+            &wait,
+            &target.to_string(),
+        ])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Reads the MTU of an interface from `ifconfig`
+fn interface_mtu(device: &str) -> Option<u32> {
+    let output = Command::new("ifconfig").arg(device).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .skip_while(|field| *field != "mtu")
+        .nth(1)
+        .and_then(|mtu| mtu.parse().ok())
+}
+
+/// Reads the valid MTU range of a hardware port
+///
+/// # Returns
+///
+/// `None` if the interface is no hardware port.
+fn valid_range(device: &str) -> Option<(u32, u32)> {
+    // e.g. "Valid MTU Range: 1280-1500"
+    let output = Command::new("networksetup")
+        .args(["-listValidMTURange", device])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (min, max) = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Valid MTU Range:"))?
+        .trim()
+        .split_once('-')?;
+    Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+}
+
+/// Resolves a host to an IPv4 address
+///
+/// # Errors
+///
+/// Returns an error if the host cannot be resolved or has no IPv4 address.
+fn resolve(host: &str) -> Result<IpAddr, Box<dyn Error>> {
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return match address {
+            IpAddr::V4(_) => Ok(address),
+            IpAddr::V6(_) => Err("Path MTU discovery supports IPv4 only".into()),
+        };
+    }
+
+    (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve '{host}': {e}"))?
+        .map(|address| address.ip())
+        .find(IpAddr::is_ipv4)
+        .ok_or_else(|| format!("'{host}' has no IPv4 address").into())
+}
@@ -0,0 +1,24 @@
+//! General network tools
+//!
+//! Tools that look at the network connection as a whole rather than at a single
+//! setting.
+
+pub mod doctor;
+
+use crate::cli::{NetArgs, NetCommands};
+use std::error::Error;
+
+/// Performs network operations
+///
+/// # Arguments
+///
+/// * `args` - Network arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the operation fails.
+pub fn perform(args: NetArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        NetCommands::Doctor(args) => doctor::perform(args),
+    }
+}
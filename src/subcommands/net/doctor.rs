@@ -0,0 +1,492 @@
+//! Network diagnostics
+//!
+//! Runs a series of checks from the local interface outwards and prints a report
+//! with the outcome of each check and a suggested fix for every problem:
+//!
+//! 1. Interface: the interface of the default route is active and has an address
+//! 2. Gateway: the default gateway answers pings
+//! 3. DNS: every configured nameserver answers queries
+//! 4. Public IP: the public address can be requested over HTTPS
+//! 5. Latency: TCP connections to well-known endpoints and their setup times
+//! 6. MTU: full-sized packets pass the path without fragmentation
+//! 7. Proxy: configured proxies of the network service are reachable
+//!
+//! If no nameserver answers, resetting DNS to the servers assigned by DHCP is
+//! offered (like `rem dns --dhcp`), as stale manual DNS servers are a common cause
+//! of "connected but nothing loads".
+//!
+//! # System Commands Used
+//!
+//! - `route -n get default` - Find the default gateway and interface
+//! - `ifconfig` - Read the state, addresses and MTU of the interface
+//! - `ping` - Check the gateway and the path MTU
+//! - `scutil --dns` - Read the configured nameservers
+//! - `curl` - Request the public IP addresses
+//! - `networksetup` - Read the proxy settings and reset DNS
+
+use crate::cli::DoctorArgs;
+use crate::config::Config;
+use crate::subcommands::dns::auto::apply_preset;
+use crate::subcommands::dns::client::{self, RecordType};
+use crate::subcommands::dns::system_nameservers;
+use crate::subcommands::{ip, mtu, ping, proxy};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Name looked up to check the nameservers
+const DNS_CHECK_NAME: &str = "apple.com";
+
+/// Endpoints whose connection setup time is measured
+const LATENCY_ENDPOINTS: &[&str] = &["1.1.1.1:443", "8.8.8.8:443", "www.apple.com:443"];
+
+/// Connection setup time above which the latency is reported as high
+const HIGH_LATENCY_MS: f64 = 150.0;
+
+/// Target of the path MTU check
+const MTU_CHECK_TARGET: IpAddr = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+
+/// Timeout of the single network operations
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Outcome of a check
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The check succeeded
+    Pass,
+    /// The check found a problem that does not prevent connectivity
+    Warn,
+    /// The check failed
+    Fail,
+}
+
+/// Result of a single check
+struct Check {
+    /// Name of the check
+    name: &'static str,
+    /// Outcome of the check
+    outcome: Outcome,
+    /// What was found
+    detail: String,
+    /// Suggested fix for a warning or failure
+    fix: Option<String>,
+}
+
+impl Check {
+    /// Creates a passed check
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            outcome: Outcome::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    /// Creates a check with a warning and a suggested fix
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name,
+            outcome: Outcome::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    /// Creates a failed check with a suggested fix
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name,
+            outcome: Outcome::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    /// Prints the check as a report line with the fix below it
+    fn print(&self) {
+        let symbol = match self.outcome {
+            Outcome::Pass => "✓".green(),
+            Outcome::Warn => "!".yellow(),
+            Outcome::Fail => "✗".red(),
+        };
+        println!("{symbol} {:<10} {}", self.name.bold(), self.detail);
+        if let Some(fix) = &self.fix {
+            println!("  {:<10} {} {fix}", "", "→".cyan());
+        }
+    }
+}
+
+/// State of the primary interface
+struct InterfaceState {
+    /// Device name, e.g. `en0`
+    device: String,
+    /// Name of the network service, e.g. `Wi-Fi`
+    service: Option<String>,
+    /// MTU of the interface
+    mtu: Option<u32>,
+}
+
+/// Runs all checks and prints the report
+///
+/// # Arguments
+///
+/// * `args` - Doctor arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if any check failed.
+pub fn perform(args: DoctorArgs) -> Result<(), Box<dyn Error>> {
+    let mut checks = Vec::new();
+    let mut run = |check: Check| {
+        check.print();
+        checks.push(check);
+    };
+
+    let gateway = ip::default_gateway(false);
+    let (check, interface) = check_interface(gateway.as_ref().and_then(|gateway| gateway.interface.clone()));
+    run(check);
+
+    match interface {
+        Some(interface) => {
+            run(check_gateway(gateway.map(|gateway| gateway.address)));
+            let dns = check_dns();
+            let dns_failed = dns.outcome == Outcome::Fail;
+            run(dns);
+            run(check_public_ip());
+            run(check_latency());
+            run(check_mtu(&interface.device, interface.mtu));
+            run(check_proxy(interface.service.as_deref()));
+
+            if dns_failed {
+                offer_dns_reset(args.fix)?;
+            }
+        }
+        None => println!(
+            "{}",
+            "Skipping the remaining checks without a network connection".dimmed()
+        ),
+    }
+
+    let failed = checks.iter().filter(|check| check.outcome == Outcome::Fail).count();
+    let warnings = checks.iter().filter(|check| check.outcome == Outcome::Warn).count();
+    println!();
+    println!(
+        "{} passed, {warnings} warning(s), {failed} failed",
+        checks.len() - failed - warnings
+    );
+
+    if failed > 0 {
+        return Err(format!("{failed} check(s) failed").into());
+    }
+    Ok(())
+}
+
+/// Checks that the interface of the default route is active and has an address
+///
+/// # Returns
+///
+/// The check and the state of the interface if it is usable.
+fn check_interface(device: Option<String>) -> (Check, Option<InterfaceState>) {
+    const NAME: &str = "Interface";
+    let fix = "Connect to a network, e.g. with `rem wifi join <SSID>` or a cable";
+
+    let Some(device) = device else {
+        return (Check::fail(NAME, "No default route", fix), None);
+    };
+    let output = match Command::new("ifconfig").arg(&device).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        _ => {
+            return (
+                Check::fail(NAME, format!("Cannot read the state of {device}"), fix),
+                None,
+            );
+        }
+    };
+
+    let mtu = output
+        .split_whitespace()
+        .skip_while(|field| *field != "mtu")
+        .nth(1)
+        .and_then(|mtu| mtu.parse().ok());
+    let inactive = output.lines().any(|line| line.trim() == "status: inactive");
+    let address = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("inet "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(str::to_string);
+    let service = ip::hardware_ports().remove(&device);
+    let label = match &service {
+        Some(service) => format!("{device} ({service})"),
+        None => device.clone(),
+    };
+
+    let check = match address {
+        Some(address) if !inactive => Check::pass(NAME, format!("{label} is up with address {address}")),
+        Some(_) => return (Check::fail(NAME, format!("{label} is inactive"), fix), None),
+        None => {
+            return (
+                Check::fail(
+                    NAME,
+                    format!("{label} has no IPv4 address"),
+                    "Renew the lease with `rem dhcp renew`",
+                ),
+                None,
+            );
+        }
+    };
+    (check, Some(InterfaceState { device, service, mtu }))
+}
+
+/// Checks that the default gateway answers pings
+fn check_gateway(gateway: Option<IpAddr>) -> Check {
+    const NAME: &str = "Gateway";
+
+    let Some(gateway) = gateway else {
+        return Check::fail(NAME, "No default gateway", "Renew the lease with `rem dhcp renew`");
+    };
+    match ping::ping(gateway, CHECK_TIMEOUT) {
+        Ok(Some(rtt)) => Check::pass(NAME, format!("{gateway} answers in {rtt:.1} ms")),
+        Ok(None) => Check::warn(
+            NAME,
+            format!("{gateway} does not answer pings"),
+            "Restart the router if the following checks fail; some routers do not answer pings",
+        ),
+        Err(e) => Check::warn(
+            NAME,
+            format!("Cannot ping {gateway}: {e}"),
+            "Check that `ping` is available",
+        ),
+    }
+}
+
+/// Checks that every configured nameserver answers queries
+fn check_dns() -> Check {
+    const NAME: &str = "DNS";
+
+    let nameservers = match system_nameservers() {
+        Ok(nameservers) if !nameservers.is_empty() => nameservers,
+        Ok(_) => return Check::fail(NAME, "No DNS servers configured", "Reset DNS with `rem dns --dhcp`"),
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                format!("Cannot read the DNS configuration: {e}"),
+                "Check `scutil --dns`",
+            );
+        }
+    };
+
+    let mut failing = Vec::new();
+    let mut fastest: Option<(IpAddr, Duration)> = None;
+    for &server in &nameservers {
+        match client::query(server, DNS_CHECK_NAME, RecordType::A, CHECK_TIMEOUT) {
+            Ok(response) if response.rcode == 0 && !response.answers.is_empty() => {
+                if fastest.is_none_or(|(_, elapsed)| response.elapsed < elapsed) {
+                    fastest = Some((server, response.elapsed));
+                }
+            }
+            Ok(response) => failing.push(format!("{server} ({})", client::rcode_name(response.rcode))),
+            Err(_) => failing.push(format!("{server} (no answer)")),
+        }
+    }
+
+    match fastest {
+        None => Check::fail(
+            NAME,
+            format!("No nameserver resolves {DNS_CHECK_NAME}: {}", failing.join(", ")),
+            "Reset DNS to the servers of the network with `rem dns --dhcp`",
+        ),
+        Some((server, elapsed)) if failing.is_empty() => Check::pass(
+            NAME,
+            format!(
+                "{} nameserver(s) resolve {DNS_CHECK_NAME}, fastest {server} in {} ms",
+                nameservers.len(),
+                elapsed.as_millis()
+            ),
+        ),
+        Some(_) => Check::warn(
+            NAME,
+            format!(
+                "Some nameservers do not resolve {DNS_CHECK_NAME}: {}",
+                failing.join(", ")
+            ),
+            "Remove them with `rem dns --set <SERVER>...` or reset DNS with `rem dns --dhcp`",
+        ),
+    }
+}
+
+/// Checks that the public addresses can be requested
+fn check_public_ip() -> Check {
+    const NAME: &str = "Public IP";
+
+    let endpoint = Config::load()
+        .ok()
+        .and_then(|config| config.ip.public_ip_url)
+        .unwrap_or_else(|| ip::DEFAULT_PUBLIC_IP_URL.to_string());
+    let addresses: Vec<String> = [true, false]
+        .into_iter()
+        .filter_map(|ipv4| ip::public_address(&endpoint, ipv4))
+        .map(|address| address.to_string())
+        .collect();
+
+    if addresses.is_empty() {
+        Check::fail(
+            NAME,
+            format!("No reply from {endpoint}"),
+            "Check for a captive portal with `rem captive` and for firewall or proxy restrictions",
+        )
+    } else {
+        Check::pass(NAME, addresses.join(", "))
+    }
+}
+
+/// Measures the TCP connection setup time to well-known endpoints
+fn check_latency() -> Check {
+    const NAME: &str = "Latency";
+
+    let mut results = Vec::new();
+    let mut slowest = 0.0_f64;
+    let mut unreachable = Vec::new();
+    for endpoint in LATENCY_ENDPOINTS {
+        match connect_time(endpoint) {
+            Some(ms) => {
+                slowest = slowest.max(ms);
+                results.push(format!("{} {ms:.0} ms", endpoint.trim_end_matches(":443")));
+            }
+            None => unreachable.push(endpoint.trim_end_matches(":443")),
+        }
+    }
+
+    if results.is_empty() {
+        return Check::fail(
+            NAME,
+            "No endpoint reachable",
+            "Check for a captive portal with `rem captive` or a blocking firewall",
+        );
+    }
+    let mut detail = results.join(", ");
+    if !unreachable.is_empty() {
+        detail.push_str(&format!("; unreachable: {}", unreachable.join(", ")));
+    }
+    if !unreachable.is_empty() || slowest > HIGH_LATENCY_MS {
+        Check::warn(
+            NAME,
+            detail,
+            "Monitor the connection with `rem ping 1.1.1.1` and `rem wifi monitor`",
+        )
+    } else {
+        Check::pass(NAME, detail)
+    }
+}
+
+/// Returns the TCP connection setup time to `HOST:PORT` in milliseconds
+fn connect_time(endpoint: &str) -> Option<f64> {
+    let address: SocketAddr = endpoint.to_socket_addrs().ok()?.next()?;
+    let start = Instant::now();
+    TcpStream::connect_timeout(&address, CHECK_TIMEOUT).ok()?;
+    Some(start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Checks that packets of the interface MTU pass the path without fragmentation
+///
+/// If they do not, the path MTU is determined (see [`mtu::path_mtu`]).
+fn check_mtu(device: &str, mtu: Option<u32>) -> Check {
+    const NAME: &str = "MTU";
+
+    let Some(mtu) = mtu else {
+        return Check::warn(NAME, "Unknown interface MTU", "Check the interface with `ifconfig`");
+    };
+    match mtu::path_mtu(MTU_CHECK_TARGET, mtu, CHECK_TIMEOUT, |_, _| {}) {
+        None => {
+            return Check::pass(
+                NAME,
+                format!("{mtu} (path not checked, {MTU_CHECK_TARGET} does not answer pings)"),
+            );
+        }
+        Some(path_mtu) if path_mtu < mtu => {
+            return Check::warn(
+                NAME,
+                format!("Interface MTU is {mtu}, but only {path_mtu} bytes pass the path unfragmented"),
+                format!("Lower the MTU with `rem mtu set {path_mtu} --interface {device}` if large transfers stall"),
+            );
+        }
+        Some(_) => {}
+    }
+    if mtu < 1280 {
+        return Check::warn(
+            NAME,
+            format!("{mtu} is below the IPv6 minimum of 1280"),
+            format!("Raise the MTU with `rem mtu set 1500 --interface {device}`"),
+        );
+    }
+    Check::pass(NAME, format!("{mtu}, full-sized packets pass the path unfragmented"))
+}
+
+/// Checks that the proxies of the network service are reachable
+fn check_proxy(service: Option<&str>) -> Check {
+    const NAME: &str = "Proxy";
+
+    let Some(service) = service else {
+        return Check::pass(NAME, "Not checked, the interface has no network service");
+    };
+    let proxies = match proxy::enabled_proxies(service) {
+        Ok(proxies) => proxies,
+        Err(e) => return Check::warn(NAME, e.to_string(), "Check the settings with `rem proxy`"),
+    };
+    if proxies.is_empty() {
+        return Check::pass(NAME, format!("No proxy configured for {service}"));
+    }
+
+    let mut unreachable = Vec::new();
+    for (label, value) in &proxies {
+        // Auto proxy URLs are only fetched by the applications
+        if !value.contains("://") && connect_time(value).is_none() {
+            unreachable.push(format!("{label} {value}"));
+        }
+    }
+    let configured: Vec<String> = proxies
+        .iter()
+        .map(|(label, value)| format!("{label} {value}"))
+        .collect();
+
+    if unreachable.is_empty() {
+        Check::pass(NAME, configured.join(", "))
+    } else {
+        Check::fail(
+            NAME,
+            format!("Unreachable: {}", unreachable.join(", ")),
+            "Disable the proxies with `rem proxy --off` if they are not needed on this network",
+        )
+    }
+}
+
+/// Offers to reset DNS to the servers assigned by DHCP
+///
+/// # Arguments
+///
+/// * `fix` - Reset without asking
+///
+/// # Errors
+///
+/// Returns an error if stdin cannot be read or the reset fails.
+fn offer_dns_reset(fix: bool) -> Result<(), Box<dyn Error>> {
+    if !fix {
+        if !io::stdin().is_terminal() {
+            return Ok(());
+        }
+        println!();
+        print!("Reset DNS to the servers assigned by DHCP (rem dns --dhcp)? [y/N] ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            return Ok(());
+        }
+    }
+
+    println!();
+    apply_preset("dhcp", false)
+}
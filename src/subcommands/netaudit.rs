@@ -0,0 +1,425 @@
+//! Network connections by application
+//!
+//! Takes a snapshot of the current outbound connections with `nettop`, which
+//! reports every socket together with its owning process and the bytes it has
+//! transferred. Processes are grouped by the application bundle they belong to,
+//! so helper processes (e.g. `Google Chrome Helper`) count towards their app.
+//! Connections to loopback addresses and inbound connections to listening
+//! sockets are left out.
+//!
+//! Remote addresses are enriched with the host name of their PTR record and the
+//! registry country of the announced prefix, which is looked up in the IP to ASN
+//! mapping of Team Cymru like `rem trace` does. With `--countries`, connections
+//! to any other country are flagged, a lightweight check for unexpected traffic.
+//!
+//! # System Commands Used
+//!
+//! - `nettop -L 1` - Read the sockets and byte counters of all processes
+//! - `ps -axo pid=,comm=` - Map processes to their executables
+//! - `scutil --dns` - Find the nameserver for the lookups
+
+use super::dns::reverse::ptr_names;
+use super::dns::system_nameservers;
+use super::trace::origin;
+use crate::cli::NetauditArgs;
+use crate::util::format_bytes;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::net::IpAddr;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Maximum time for each enrichment lookup
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Outbound connection of an application
+#[derive(Serialize)]
+struct Connection {
+    /// Protocol and address family, e.g. `tcp4` or `udp6`
+    protocol: String,
+    /// ID of the owning process
+    pid: u32,
+    /// Remote address
+    address: IpAddr,
+    /// Remote port
+    port: u16,
+    /// Host name of the PTR record of the remote address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    /// Country the prefix of the remote address is registered in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    country: Option<String>,
+    /// True if the country is not one of the expected countries
+    unexpected: bool,
+    /// Bytes received
+    bytes_in: u64,
+    /// Bytes sent
+    bytes_out: u64,
+}
+
+/// Connections of the processes of an application
+#[derive(Serialize)]
+struct Application {
+    /// Name of the application bundle or process
+    name: String,
+    /// IDs of the processes with connections
+    pids: BTreeSet<u32>,
+    /// Bytes received over all connections
+    bytes_in: u64,
+    /// Bytes sent over all connections
+    bytes_out: u64,
+    /// Outbound connections, most traffic first
+    connections: Vec<Connection>,
+}
+
+/// Socket reported by `nettop`
+struct Socket {
+    /// Protocol and address family, e.g. `tcp4`
+    protocol: String,
+    /// ID of the owning process
+    pid: u32,
+    /// Name of the owning process, possibly truncated
+    process: String,
+    /// Local port, `None` for the wildcard port
+    local_port: Option<u16>,
+    /// Remote address and port, `None` for listening and unconnected sockets
+    remote: Option<(IpAddr, u16)>,
+    /// Bytes received
+    bytes_in: u64,
+    /// Bytes sent
+    bytes_out: u64,
+}
+
+/// Host name and country of a remote address
+#[derive(Clone, Default)]
+struct Enrichment {
+    /// Host name of the PTR record
+    host: Option<String>,
+    /// Registry country of the announced prefix
+    country: Option<String>,
+}
+
+/// Prints the outbound connections grouped by application
+///
+/// # Arguments
+///
+/// * `args` - Netaudit arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `nettop` cannot be run or fails.
+pub fn perform(args: NetauditArgs) -> Result<(), Box<dyn Error>> {
+    let sockets = read_sockets()?;
+    let executables = executables();
+
+    // Connections to a port a process listens on were accepted, not initiated
+    let listening: HashSet<(u32, u16)> = sockets
+        .iter()
+        .filter(|socket| socket.remote.is_none())
+        .filter_map(|socket| Some((socket.pid, socket.local_port?)))
+        .collect();
+    let expected: Vec<String> = args.countries.iter().map(|country| country.to_uppercase()).collect();
+
+    let outbound: Vec<&Socket> = sockets
+        .iter()
+        .filter(|socket| {
+            socket.remote.is_some_and(|(address, _)| !address.is_loopback())
+                && !socket
+                    .local_port
+                    .is_some_and(|port| listening.contains(&(socket.pid, port)))
+                && args.process.as_deref().is_none_or(|wanted| {
+                    wanted.parse::<u32>().is_ok_and(|pid| pid == socket.pid)
+                        || application_name(socket, &executables)
+                            .to_lowercase()
+                            .contains(&wanted.to_lowercase())
+                })
+        })
+        .collect();
+    let addresses: BTreeSet<IpAddr> = outbound
+        .iter()
+        .filter_map(|socket| socket.remote.map(|(address, _)| address))
+        .collect();
+    let enrichments = if args.numeric {
+        HashMap::new()
+    } else {
+        enrich(&addresses)
+    };
+
+    let mut applications: HashMap<String, Application> = HashMap::new();
+    for socket in outbound {
+        let Some((address, port)) = socket.remote else {
+            continue;
+        };
+        let name = application_name(socket, &executables);
+        let enrichment = enrichments.get(&address).cloned().unwrap_or_default();
+        let unexpected = !expected.is_empty()
+            && enrichment
+                .country
+                .as_ref()
+                .is_some_and(|country| !expected.contains(country));
+        let application = applications.entry(name.clone()).or_insert_with(|| Application {
+            name,
+            pids: BTreeSet::new(),
+            bytes_in: 0,
+            bytes_out: 0,
+            connections: Vec::new(),
+        });
+        application.pids.insert(socket.pid);
+        application.bytes_in += socket.bytes_in;
+        application.bytes_out += socket.bytes_out;
+        application.connections.push(Connection {
+            protocol: socket.protocol.clone(),
+            pid: socket.pid,
+            address,
+            port,
+            host: enrichment.host,
+            country: enrichment.country,
+            unexpected,
+            bytes_in: socket.bytes_in,
+            bytes_out: socket.bytes_out,
+        });
+    }
+
+    let mut applications: Vec<Application> = applications.into_values().collect();
+    for application in &mut applications {
+        application
+            .connections
+            .sort_by_key(|connection| std::cmp::Reverse(connection.bytes_in + connection.bytes_out));
+    }
+    applications.sort_by(|a, b| {
+        (b.bytes_in + b.bytes_out)
+            .cmp(&(a.bytes_in + a.bytes_out))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&applications)?);
+        return Ok(());
+    }
+    print_applications(&applications, !expected.is_empty());
+
+    Ok(())
+}
+
+/// Prints the applications with their connections
+///
+/// # Arguments
+///
+/// * `applications` - Applications to print, in order
+/// * `checked` - True if connections were checked against expected countries
+fn print_applications(applications: &[Application], checked: bool) {
+    if applications.is_empty() {
+        println!("No outbound connections");
+        return;
+    }
+
+    let remote = |connection: &Connection| match &connection.host {
+        Some(host) => format!("{host} ({})", connection.address),
+        None => connection.address.to_string(),
+    };
+    let remote_width = applications
+        .iter()
+        .flat_map(|application| &application.connections)
+        .map(|connection| remote(connection).chars().count())
+        .max()
+        .unwrap_or(0);
+
+    for application in applications {
+        let pids: Vec<String> = application.pids.iter().map(u32::to_string).collect();
+        println!(
+            "{}  {}  {} connection(s)  ↓ {}  ↑ {}",
+            application.name.bold(),
+            format!("(pid {})", pids.join(", ")).dimmed(),
+            application.connections.len(),
+            format_bytes(application.bytes_in as f64),
+            format_bytes(application.bytes_out as f64)
+        );
+        for connection in &application.connections {
+            let country = connection.country.as_deref().unwrap_or("-");
+            let country = if connection.unexpected {
+                format!("{country:<2} ⚠").red().bold()
+            } else {
+                country.normal()
+            };
+            println!(
+                "  {:<remote_width$}  {:>5}  {:<4}  ↓ {:>9}  ↑ {:>9}  {country}",
+                remote(connection),
+                connection.port,
+                connection.protocol,
+                format_bytes(connection.bytes_in as f64),
+                format_bytes(connection.bytes_out as f64)
+            );
+        }
+    }
+
+    let unexpected = applications
+        .iter()
+        .flat_map(|application| &application.connections)
+        .filter(|connection| connection.unexpected)
+        .count();
+    if checked {
+        println!();
+        if unexpected == 0 {
+            println!("{}", "No connections to unexpected countries".green());
+        } else {
+            println!(
+                "{}",
+                format!("{unexpected} connection(s) to unexpected countries")
+                    .red()
+                    .bold()
+            );
+        }
+    }
+}
+
+/// Reads the sockets of all processes with their byte counters from `nettop`
+///
+/// # Errors
+///
+/// Returns an error if `nettop` cannot be run or fails.
+fn read_sockets() -> Result<Vec<Socket>, Box<dyn Error>> {
+    let output = Command::new("nettop")
+        // One sample as CSV with numeric addresses and exact byte counts
+        .args(["-L", "1", "-n", "-x", "-J", "bytes_in,bytes_out"])
+        .output()
+        .map_err(|e| format!("Failed to run nettop: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("nettop failed: {}", stderr.trim()).into());
+    }
+
+    Ok(parse_nettop(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the CSV output of `nettop -L 1 -J bytes_in,bytes_out`
+///
+/// A process line like `Safari.1234,5000,800,` is followed by the lines of its
+/// sockets like `tcp4 192.168.1.2:52000<->17.253.1.1:443,4000,600,`. The column
+/// of the process and socket names has an empty header.
+fn parse_nettop(output: &str) -> Vec<Socket> {
+    let mut lines = output.lines();
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let header: Vec<&str> = header.split(',').collect();
+    let column = |name: &str| header.iter().position(|field| *field == name);
+    let (Some(name_column), Some(in_column), Some(out_column)) = (column(""), column("bytes_in"), column("bytes_out"))
+    else {
+        return Vec::new();
+    };
+
+    let mut sockets = Vec::new();
+    let mut process = (0, String::new());
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').collect();
+        let Some(name) = fields.get(name_column) else {
+            continue;
+        };
+        let bytes = |index: usize| fields.get(index).and_then(|value| value.parse().ok()).unwrap_or(0);
+
+        match name.split_once(' ') {
+            Some((protocol, endpoints)) if protocol.starts_with("tcp") || protocol.starts_with("udp") => {
+                let (local, remote) = endpoints.split_once("<->").unwrap_or((endpoints, "*:*"));
+                sockets.push(Socket {
+                    protocol: protocol.to_string(),
+                    pid: process.0,
+                    process: process.1.clone(),
+                    local_port: parse_endpoint(local).map(|(_, port)| port),
+                    remote: parse_endpoint(remote).and_then(|(address, port)| Some((address?, port))),
+                    bytes_in: bytes(in_column),
+                    bytes_out: bytes(out_column),
+                });
+            }
+            _ => {
+                // e.g. "Google Chrome H.1234"
+                if let Some((name, pid)) = name.rsplit_once('.')
+                    && let Ok(pid) = pid.parse()
+                {
+                    process = (pid, name.to_string());
+                }
+            }
+        }
+    }
+
+    sockets
+}
+
+/// Parses an endpoint of `nettop` like `192.168.1.2:52000` or `fe80::1%en0.52000`
+///
+/// # Returns
+///
+/// The address (`None` for the wildcard `*`) and port, or `None` for the
+/// wildcard port.
+fn parse_endpoint(endpoint: &str) -> Option<(Option<IpAddr>, u16)> {
+    // IPv6 addresses contain colons, so their port is separated by a dot
+    let (address, port) = if endpoint.matches(':').count() == 1 {
+        endpoint.rsplit_once(':')?
+    } else {
+        endpoint.rsplit_once('.')?
+    };
+    let port = port.parse().ok()?;
+    let address = address.split('%').next().unwrap_or(address);
+    Some((address.parse().ok(), port))
+}
+
+/// Reads the executable paths of all processes
+fn executables() -> HashMap<u32, String> {
+    let Ok(output) = Command::new("ps").args(["-axo", "pid=,comm="]).output() else {
+        return HashMap::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, path) = line.trim_start().split_once(' ')?;
+            Some((pid.parse().ok()?, path.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Returns the name of the application a socket belongs to
+///
+/// This is the outermost `.app` bundle of the executable, e.g. `Google Chrome`
+/// for its helper processes, otherwise the name of the executable or process.
+fn application_name(socket: &Socket, executables: &HashMap<u32, String>) -> String {
+    let Some(path) = executables.get(&socket.pid) else {
+        return socket.process.clone();
+    };
+    path.split('/')
+        .find_map(|component| component.strip_suffix(".app"))
+        .or_else(|| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(&socket.process)
+        .to_string()
+}
+
+/// Looks up the host names and countries of remote addresses in parallel
+///
+/// Failed lookups leave the fields empty; without nameserver nothing is looked up.
+fn enrich(addresses: &BTreeSet<IpAddr>) -> HashMap<IpAddr, Enrichment> {
+    let Some(server) = system_nameservers().ok().and_then(|servers| servers.first().copied()) else {
+        eprintln!(
+            "{}",
+            "No DNS servers configured, skipping host name and country lookups".yellow()
+        );
+        return HashMap::new();
+    };
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = addresses
+            .iter()
+            .map(|&address| {
+                scope.spawn(move || {
+                    let host = ptr_names(address, server, LOOKUP_TIMEOUT)
+                        .ok()
+                        .and_then(|names| names.into_iter().next());
+                    let country = origin(address, server, LOOKUP_TIMEOUT).and_then(|origin| origin.country);
+                    (address, Enrichment { host, country })
+                })
+            })
+            .collect();
+        handles.into_iter().filter_map(|handle| handle.join().ok()).collect()
+    })
+}
@@ -0,0 +1,308 @@
+//! Per-interface bandwidth monitor
+//!
+//! Reads the byte counters of the network interfaces, which `netstat -ibn` takes
+//! from the kernel via `sysctl` as 64-bit values. Without `--watch`, the counters
+//! since boot are shown. With `--watch`, the counters are sampled at a fixed
+//! interval and the receive and transmit rates of every interface are shown in a
+//! table that is redrawn in place, together with the bytes transferred since the
+//! monitor was started.
+//!
+//! By default, the loopback interface, interfaces that are down and interfaces
+//! that have never transferred any data are hidden.
+//!
+//! # System Commands Used
+//!
+//! - `netstat -ibn` - Read the interface byte counters
+//! - `networksetup -listallhardwareports` - Map devices to hardware port names
+
+use super::ip;
+use crate::cli::NetstatArgs;
+use crate::util::format_bytes;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::process::Command;
+use std::thread;
+use std::time::Instant;
+
+/// Byte counters of a network interface
+#[derive(Serialize)]
+struct InterfaceCounters {
+    /// Device name, e.g. `en0`
+    name: String,
+    /// Hardware port of the device, e.g. `Wi-Fi`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<String>,
+    /// True if the interface is up
+    up: bool,
+    /// Bytes received since boot
+    rx_bytes: u64,
+    /// Bytes transmitted since boot
+    tx_bytes: u64,
+}
+
+/// Row of the bandwidth table in watch mode
+struct TrafficRow {
+    /// Interface label, e.g. `en0 (Wi-Fi)`
+    label: String,
+    /// Receive rate in bytes per second
+    rx_rate: f64,
+    /// Transmit rate in bytes per second
+    tx_rate: f64,
+    /// Bytes received since the monitor was started
+    rx_total: u64,
+    /// Bytes transmitted since the monitor was started
+    tx_total: u64,
+}
+
+/// Shows the interface byte counters or monitors the bandwidth
+///
+/// # Arguments
+///
+/// * `args` - Netstat arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the interface counters cannot be read, or an interface
+/// given with `--interface` does not exist.
+pub fn perform(args: NetstatArgs) -> Result<(), Box<dyn Error>> {
+    let ports = ip::hardware_ports();
+    let interfaces = read_counters(&ports)?;
+
+    for name in &args.interfaces {
+        if !interfaces.iter().any(|interface| &interface.name == name) {
+            let names: Vec<&str> = interfaces.iter().map(|interface| interface.name.as_str()).collect();
+            return Err(format!("Unknown interface '{name}' (interfaces: {})", names.join(", ")).into());
+        }
+    }
+
+    if args.watch {
+        return watch(&args, &ports, interfaces);
+    }
+
+    let interfaces: Vec<InterfaceCounters> = interfaces
+        .into_iter()
+        .filter(|interface| is_shown(interface, &args))
+        .collect();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&interfaces)?);
+        return Ok(());
+    }
+
+    println!("{:<24}  {:>12}  {:>12}", "INTERFACE", "RECEIVED", "SENT");
+    for interface in &interfaces {
+        println!(
+            "{:<24}  {:>12}  {:>12}",
+            label(interface),
+            format_bytes(interface.rx_bytes as f64),
+            format_bytes(interface.tx_bytes as f64)
+        );
+    }
+
+    Ok(())
+}
+
+/// Samples the counters and redraws the bandwidth table until interrupted
+///
+/// Errors while reading the counters are reported and do not stop the monitor.
+///
+/// # Arguments
+///
+/// * `args` - Netstat arguments from the command line
+/// * `ports` - Hardware port names by device name
+/// * `initial` - Counters at the start of the monitor
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be written to stdout.
+fn watch(
+    args: &NetstatArgs,
+    ports: &BTreeMap<String, String>,
+    initial: Vec<InterfaceCounters>,
+) -> Result<(), Box<dyn Error>> {
+    let redraw = io::stdout().is_terminal();
+    let start = Instant::now();
+    let mut previous: HashMap<String, (u64, u64)> = initial
+        .iter()
+        .map(|interface| (interface.name.clone(), (interface.rx_bytes, interface.tx_bytes)))
+        .collect();
+    let mut totals: HashMap<String, (u64, u64)> = HashMap::new();
+    let mut sampled = Instant::now();
+    let mut drawn = 0;
+
+    println!("Sampling every {:?} (Ctrl-C to stop)", args.interval);
+    loop {
+        thread::sleep(args.interval);
+        let interfaces = match read_counters(ports) {
+            Ok(interfaces) => interfaces,
+            Err(e) => {
+                eprintln!("{}", format!("Failed to read interface counters: {e}").red());
+                drawn = 0;
+                continue;
+            }
+        };
+        let seconds = sampled.elapsed().as_secs_f64();
+        sampled = Instant::now();
+
+        let mut rows = Vec::new();
+        for interface in &interfaces {
+            // Counters start again at zero when an interface is recreated, e.g. a VPN tunnel
+            let (rx_before, tx_before) = previous
+                .insert(interface.name.clone(), (interface.rx_bytes, interface.tx_bytes))
+                .unwrap_or((interface.rx_bytes, interface.tx_bytes));
+            let rx_delta = interface.rx_bytes.saturating_sub(rx_before);
+            let tx_delta = interface.tx_bytes.saturating_sub(tx_before);
+            let total = totals.entry(interface.name.clone()).or_default();
+            total.0 += rx_delta;
+            total.1 += tx_delta;
+
+            if is_shown(interface, args) {
+                rows.push(TrafficRow {
+                    label: label(interface),
+                    rx_rate: rx_delta as f64 / seconds,
+                    tx_rate: tx_delta as f64 / seconds,
+                    rx_total: total.0,
+                    tx_total: total.1,
+                });
+            }
+        }
+
+        let lines = render(&rows, start.elapsed().as_secs());
+        let mut stdout = io::stdout().lock();
+        if redraw && drawn > 0 {
+            // Move the cursor to the start of the previous table and clear it
+            write!(stdout, "\x1b[{drawn}A\x1b[J")?;
+        } else if !redraw {
+            writeln!(stdout)?;
+        }
+        for line in &lines {
+            writeln!(stdout, "{line}")?;
+        }
+        stdout.flush()?;
+        drawn = lines.len();
+    }
+}
+
+/// Renders the bandwidth table with a row for the sum of all interfaces
+///
+/// # Arguments
+///
+/// * `rows` - Rows of the shown interfaces
+/// * `elapsed` - Seconds since the monitor was started
+fn render(rows: &[TrafficRow], elapsed: u64) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{:<24}  {:>12}  {:>12}  {:>10}  {:>10}",
+        "INTERFACE", "↓ RATE", "↑ RATE", "↓ SESSION", "↑ SESSION"
+    )];
+
+    for row in rows {
+        lines.push(format!(
+            "{:<24}  {:>12}  {:>12}  {:>10}  {:>10}",
+            row.label,
+            format_rate(row.rx_rate),
+            format_rate(row.tx_rate),
+            format_bytes(row.rx_total as f64),
+            format_bytes(row.tx_total as f64)
+        ));
+    }
+
+    let total = format!(
+        "{:<24}  {:>12}  {:>12}  {:>10}  {:>10}",
+        format!("Total ({})", format_elapsed(elapsed)),
+        format_rate(rows.iter().map(|row| row.rx_rate).sum()),
+        format_rate(rows.iter().map(|row| row.tx_rate).sum()),
+        format_bytes(rows.iter().map(|row| row.rx_total).sum::<u64>() as f64),
+        format_bytes(rows.iter().map(|row| row.tx_total).sum::<u64>() as f64)
+    );
+    lines.push(total.bold().to_string());
+    lines
+}
+
+/// Returns true if an interface is shown with the given arguments
+///
+/// Interfaces given with `--interface` are always shown. Otherwise, `--all` shows
+/// every interface and by default only active interfaces other than loopback.
+fn is_shown(interface: &InterfaceCounters, args: &NetstatArgs) -> bool {
+    if !args.interfaces.is_empty() {
+        return args.interfaces.contains(&interface.name);
+    }
+    args.all || (interface.up && !interface.name.starts_with("lo") && interface.rx_bytes + interface.tx_bytes > 0)
+}
+
+/// Returns the label of an interface, e.g. `en0 (Wi-Fi)`
+fn label(interface: &InterfaceCounters) -> String {
+    match &interface.service {
+        Some(service) => format!("{} ({service})", interface.name),
+        None => interface.name.clone(),
+    }
+}
+
+/// Reads the byte counters of all interfaces in the order reported by `netstat`
+///
+/// # Arguments
+///
+/// * `ports` - Hardware port names by device name
+///
+/// # Errors
+///
+/// Returns an error if `netstat` cannot be run or fails.
+fn read_counters(ports: &BTreeMap<String, String>) -> Result<Vec<InterfaceCounters>, Box<dyn Error>> {
+    let output = Command::new("netstat")
+        .arg("-ibn")
+        .output()
+        .map_err(|e| format!("Failed to run netstat: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("netstat failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    let mut interfaces = parse_netstat(&String::from_utf8_lossy(&output.stdout));
+    for interface in &mut interfaces {
+        interface.service = ports.get(&interface.name).cloned();
+    }
+    Ok(interfaces)
+}
+
+/// Parses the output of `netstat -ibn`
+///
+/// Every interface has one `<Link#N>` row with the counters of the link layer,
+/// followed by rows for its addresses, which are skipped. The address column of the
+/// link row is empty for interfaces without a MAC address, so the counters are
+/// taken from the end of the row: `Ibytes` is the fifth and `Obytes` the second
+/// last column. Names of interfaces that are down end with `*`.
+fn parse_netstat(output: &str) -> Vec<InterfaceCounters> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if !fields.get(2)?.starts_with("<Link#") || fields.len() < 9 {
+                return None;
+            }
+            let name = fields[0];
+            Some(InterfaceCounters {
+                name: name.trim_end_matches('*').to_string(),
+                service: None,
+                up: !name.ends_with('*'),
+                rx_bytes: fields[fields.len() - 5].parse().ok()?,
+                tx_bytes: fields[fields.len() - 2].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Formats a rate in bytes per second, e.g. `1.5 MB/s`
+fn format_rate(bytes_per_second: f64) -> String {
+    format!("{}/s", format_bytes(bytes_per_second))
+}
+
+/// Formats elapsed seconds as `1h 02m 03s`, `2m 03s` or `3s`
+fn format_elapsed(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m {seconds:02}s"),
+        _ => format!("{hours}h {minutes:02}m {seconds:02}s"),
+    }
+}
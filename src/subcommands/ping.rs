@@ -0,0 +1,240 @@
+//! Ping monitor
+//!
+//! Pings a host at a fixed interval and keeps rolling statistics over the most
+//! recent pings: packet loss, minimum, average and maximum round trip time and
+//! jitter (the average difference between consecutive round trip times). Each
+//! ping prints one line with a sparkline of the window, so latency spikes and
+//! losses of a flaky connection stand out.
+//!
+//! With `--alert-loss`, a notification is posted when the loss within the window
+//! exceeds the threshold, and again only after it has recovered in between.
+//!
+//! # System Commands Used
+//!
+//! - `ping` / `ping6` - Send a single ICMP echo request
+//! - `osascript` - Post notifications
+
+use crate::cli::PingArgs;
+use crate::notify::notify;
+use colored::Colorize;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Characters of the sparkline from lowest to highest round trip time
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Sparkline character of a lost ping
+const LOST: char = '×';
+
+/// Statistics over a window of pings
+struct Statistics {
+    /// Number of pings
+    sent: usize,
+    /// Number of pings without reply
+    lost: usize,
+    /// Minimum round trip time in milliseconds
+    min: f64,
+    /// Average round trip time in milliseconds
+    avg: f64,
+    /// Maximum round trip time in milliseconds
+    max: f64,
+    /// Average difference between consecutive round trip times in milliseconds
+    jitter: f64,
+}
+
+impl Statistics {
+    /// Computes the statistics of a series of round trip times; `None` marks a lost ping
+    fn of<'a>(samples: impl IntoIterator<Item = &'a Option<f64>>) -> Self {
+        let samples: Vec<Option<f64>> = samples.into_iter().copied().collect();
+        let replies: Vec<f64> = samples.iter().flatten().copied().collect();
+
+        let (min, max) = replies
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &rtt| {
+                (min.min(rtt), max.max(rtt))
+            });
+        let avg = replies.iter().sum::<f64>() / replies.len().max(1) as f64;
+        let jitter = if replies.len() > 1 {
+            replies.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum::<f64>() / (replies.len() - 1) as f64
+        } else {
+            0.0
+        };
+
+        Statistics {
+            sent: samples.len(),
+            lost: samples.len() - replies.len(),
+            min: if replies.is_empty() { 0.0 } else { min },
+            avg,
+            max: if replies.is_empty() { 0.0 } else { max },
+            jitter,
+        }
+    }
+
+    /// Returns the packet loss in percent
+    fn loss(&self) -> f64 {
+        self.lost as f64 * 100.0 / self.sent.max(1) as f64
+    }
+}
+
+/// Pings a host until interrupted or the count is reached
+///
+/// # Arguments
+///
+/// * `args` - Ping arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the host cannot be resolved or `ping` cannot be run.
+pub fn perform(args: PingArgs) -> Result<(), Box<dyn Error>> {
+    let address = (args.host.as_str(), 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve '{}': {e}", args.host))?
+        .next()
+        .ok_or_else(|| format!("'{}' has no address", args.host))?
+        .ip();
+    let window = usize::from(args.window);
+
+    println!(
+        "Pinging {} ({address}) every {}, statistics over the last {window} pings (Ctrl-C to stop)",
+        args.host.bold(),
+        format_interval(args.interval)
+    );
+
+    let mut recent: VecDeque<Option<f64>> = VecDeque::with_capacity(window);
+    let mut all: Vec<Option<f64>> = Vec::new();
+    let mut alerted = false;
+    let mut sequence: u64 = 0;
+
+    while args.count.is_none_or(|count| sequence < count) {
+        let start = Instant::now();
+        let rtt = ping(address, args.timeout)?;
+        sequence += 1;
+
+        if recent.len() == window {
+            recent.pop_front();
+        }
+        recent.push_back(rtt);
+        if args.count.is_some() {
+            all.push(rtt);
+        }
+
+        let stats = Statistics::of(&recent);
+        let latency = match rtt {
+            Some(rtt) => format!("{rtt:>7.1} ms").normal(),
+            None => format!("{:>10}", "lost").red(),
+        };
+        println!(
+            "{sequence:>5}  {latency}  {}  loss {:>5.1}%  min/avg/max {:.1}/{:.1}/{:.1} ms  jitter {:.1} ms",
+            sparkline(&recent, window),
+            stats.loss(),
+            stats.min,
+            stats.avg,
+            stats.max,
+            stats.jitter
+        );
+
+        if let Some(threshold) = args.alert_loss {
+            // Only alert on full windows, a single lost first ping is 100% loss
+            let exceeded = recent.len() == window && stats.loss() > f64::from(threshold);
+            if exceeded && !alerted {
+                let message = format!(
+                    "{:.0}% packet loss to {} over the last {window} pings",
+                    stats.loss(),
+                    args.host
+                );
+                if let Err(e) = notify("rem ping", &message) {
+                    eprintln!("{}", e.to_string().red());
+                }
+            }
+            alerted = exceeded;
+        }
+
+        if args.count.is_none_or(|count| sequence < count) {
+            thread::sleep(args.interval.saturating_sub(start.elapsed()));
+        }
+    }
+
+    let stats = Statistics::of(&all);
+    println!(
+        "{} sent, {} lost ({:.1}% loss), min/avg/max {:.1}/{:.1}/{:.1} ms, jitter {:.1} ms",
+        stats.sent,
+        stats.lost,
+        stats.loss(),
+        stats.min,
+        stats.avg,
+        stats.max,
+        stats.jitter
+    );
+
+    Ok(())
+}
+
+/// Sends a single echo request
+///
+/// # Returns
+///
+/// The round trip time in milliseconds, or `None` if there was no reply within the timeout.
+///
+/// # Errors
+///
+/// Returns an error if `ping` cannot be run.
+pub fn ping(address: IpAddr, timeout: Duration) -> Result<Option<f64>, Box<dyn Error>> {
+    // ping only handles IPv4 on macOS
+    let program = if address.is_ipv6() { "ping6" } else { "ping" };
+    let mut command = Command::new(program);
+    command.args(["-n", "-c", "1"]);
+    if address.is_ipv4() {
+        // Wait time for the reply in milliseconds
+        command.args(["-W", &timeout.as_millis().max(1).to_string()]);
+    }
+    let output = command
+        .arg(address.to_string())
+        .output()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+
+    // Reply line: "64 bytes from 1.1.1.1: icmp_seq=0 ttl=57 time=12.345 ms"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.split_once(" time=").map(|(_, rest)| rest))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|time| time.parse().ok())
+        .filter(|rtt: &f64| output.status.success() && *rtt <= timeout.as_secs_f64() * 1000.0))
+}
+
+/// Formats an interval as milliseconds below one second, otherwise as seconds
+fn format_interval(interval: Duration) -> String {
+    if interval < Duration::from_secs(1) {
+        format!("{} ms", interval.as_millis())
+    } else {
+        format!("{:.1} s", interval.as_secs_f64())
+    }
+}
+
+/// Renders round trip times as a sparkline scaled between their minimum and maximum
+///
+/// The sparkline is padded to `width` characters to keep the following columns aligned.
+fn sparkline(samples: &VecDeque<Option<f64>>, width: usize) -> String {
+    let stats = Statistics::of(samples);
+    let range = stats.max - stats.min;
+
+    let mut line: String = samples
+        .iter()
+        .map(|sample| match sample {
+            Some(rtt) => {
+                let level = if range > 0.0 {
+                    ((rtt - stats.min) / range * (SPARKS.len() - 1) as f64).round() as usize
+                } else {
+                    0
+                };
+                SPARKS[level.min(SPARKS.len() - 1)].to_string()
+            }
+            None => LOST.to_string().red().to_string(),
+        })
+        .collect();
+    line.push_str(&" ".repeat(width.saturating_sub(samples.len())));
+    line
+}
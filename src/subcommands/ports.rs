@@ -0,0 +1,228 @@
+//! Listening sockets and connections
+//!
+//! Lists TCP and UDP sockets with the process owning them, based on the field
+//! output of `lsof` (`-F`), which is stable across versions unlike its column
+//! output. Without root privileges, `lsof` only reports sockets of processes of
+//! the current user, so `sudo rem ports` shows the sockets of all processes.
+//!
+//! # System Commands Used
+//!
+//! - `lsof -i` - List the internet sockets of all processes
+
+use crate::cli::PortsArgs;
+use colored::Colorize;
+use serde::Serialize;
+use std::error::Error;
+use std::process::Command;
+
+/// Internet socket of a process
+#[derive(Serialize)]
+struct Socket {
+    /// Protocol and address family, e.g. `tcp4` or `udp6`
+    protocol: String,
+    /// Local address and port, e.g. `*:22` or `127.0.0.1:5432`
+    local: String,
+    /// Remote address and port of a connected socket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+    /// TCP state, e.g. `LISTEN` or `ESTABLISHED`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+    /// ID of the owning process
+    pid: u32,
+    /// Name of the owning process
+    process: String,
+}
+
+impl Socket {
+    /// Returns true if the socket accepts connections or datagrams from any peer
+    fn is_listening(&self) -> bool {
+        match &self.state {
+            Some(state) => state == "LISTEN",
+            None => self.remote.is_none(),
+        }
+    }
+}
+
+/// Lists sockets matching the filters
+///
+/// # Arguments
+///
+/// * `args` - Ports arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `lsof` cannot be run or fails.
+pub fn perform(args: PortsArgs) -> Result<(), Box<dyn Error>> {
+    let mut sockets: Vec<Socket> = read_sockets()?
+        .into_iter()
+        .filter(|socket| matches(socket, &args))
+        .collect();
+    sockets.sort_by(|a, b| {
+        b.is_listening()
+            .cmp(&a.is_listening())
+            .then_with(|| port(&a.local).cmp(&port(&b.local)))
+            .then_with(|| a.process.cmp(&b.process))
+    });
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&sockets)?);
+        return Ok(());
+    }
+    if sockets.is_empty() {
+        println!("No matching sockets");
+        return Ok(());
+    }
+
+    let local_width = column_width(sockets.iter().map(|socket| socket.local.len()), "LOCAL");
+    let remote_width = column_width(
+        sockets
+            .iter()
+            .map(|socket| socket.remote.as_ref().map_or(1, String::len)),
+        "REMOTE",
+    );
+    println!(
+        "{:<5}  {:<local_width$}  {:<remote_width$}  {:<11}  {:>6}  PROCESS",
+        "PROTO", "LOCAL", "REMOTE", "STATE", "PID"
+    );
+    for socket in &sockets {
+        let state = socket.state.as_deref().unwrap_or("-");
+        let state = match state {
+            "LISTEN" => state.green(),
+            "ESTABLISHED" => state.normal(),
+            _ => state.dimmed(),
+        };
+        println!(
+            "{:<5}  {:<local_width$}  {:<remote_width$}  {state:<11}  {:>6}  {}",
+            socket.protocol,
+            socket.local,
+            socket.remote.as_deref().unwrap_or("-"),
+            socket.pid,
+            socket.process.bold()
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns true if a socket matches all given filters
+///
+/// * `--port` matches the local or remote port
+/// * `--process` matches the process ID or a part of the process name (ignoring case)
+/// * `--state` matches the TCP state (ignoring case)
+/// * `--listen` matches listening TCP and unconnected UDP sockets
+fn matches(socket: &Socket, args: &PortsArgs) -> bool {
+    let port_matches = args.port.is_none_or(|wanted| {
+        port(&socket.local) == Some(wanted) || socket.remote.as_deref().and_then(port) == Some(wanted)
+    });
+    let process_matches = args.process.as_deref().is_none_or(|wanted| {
+        wanted.parse::<u32>().is_ok_and(|pid| pid == socket.pid)
+            || socket.process.to_lowercase().contains(&wanted.to_lowercase())
+    });
+    let state_matches = args.state.as_deref().is_none_or(|wanted| {
+        socket
+            .state
+            .as_deref()
+            .is_some_and(|state| state.eq_ignore_ascii_case(wanted))
+    });
+
+    port_matches && process_matches && state_matches && (!args.listen || socket.is_listening())
+}
+
+/// Returns the width of a table column
+fn column_width(lengths: impl Iterator<Item = usize>, header: &str) -> usize {
+    lengths.max().unwrap_or(0).max(header.len())
+}
+
+/// Returns the port of an address like `127.0.0.1:5432` or `[::1]:5432`
+///
+/// # Returns
+///
+/// `None` for the wildcard port `*`.
+fn port(address: &str) -> Option<u16> {
+    address.rsplit_once(':')?.1.parse().ok()
+}
+
+/// Reads the TCP and UDP sockets of all processes visible to the current user
+///
+/// # Errors
+///
+/// Returns an error if `lsof` cannot be run or fails.
+fn read_sockets() -> Result<Vec<Socket>, Box<dyn Error>> {
+    let output = Command::new("lsof")
+        // Numeric addresses and ports, full command names, TCP state
+        .args(["-n", "-P", "+c", "0", "-Ts", "-iTCP", "-iUDP", "-F", "pcftPnT"])
+        .output()
+        .map_err(|e| format!("Failed to run lsof: {e}"))?;
+
+    // lsof also exits with 1 if no socket was found
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && !stderr.trim().is_empty() {
+        return Err(format!("lsof failed: {}", stderr.trim()).into());
+    }
+
+    Ok(parse_lsof(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the field output of `lsof -F pcftPnT`
+///
+/// Every line starts with a field identifier: `p` (process ID) and `c` (command)
+/// start a process, `f` (file descriptor) starts a socket of the current process,
+/// followed by `t` (`IPv4`/`IPv6`), `P` (protocol), `n` (addresses, connected
+/// sockets as `local->remote`) and `T` (TCP information like `ST=LISTEN`).
+fn parse_lsof(output: &str) -> Vec<Socket> {
+    let mut sockets = Vec::new();
+    let mut pid = 0;
+    let mut process = String::new();
+    let mut current: Option<Socket> = None;
+
+    for line in output.lines() {
+        let Some(field) = line.chars().next() else {
+            continue;
+        };
+        let value = &line[field.len_utf8()..];
+        match field {
+            'p' | 'f' => {
+                sockets.extend(current.take());
+                if field == 'p' {
+                    pid = value.parse().unwrap_or(0);
+                } else {
+                    current = Some(Socket {
+                        protocol: String::new(),
+                        local: String::new(),
+                        remote: None,
+                        state: None,
+                        pid,
+                        process: process.clone(),
+                    });
+                }
+            }
+            'c' => process = value.to_string(),
+            _ => {
+                let Some(socket) = current.as_mut() else {
+                    continue;
+                };
+                match field {
+                    't' => socket.protocol.push(if value == "IPv6" { '6' } else { '4' }),
+                    'P' => socket.protocol.insert_str(0, &value.to_lowercase()),
+                    'n' => match value.split_once("->") {
+                        Some((local, remote)) => {
+                            socket.local = local.to_string();
+                            socket.remote = Some(remote.to_string());
+                        }
+                        None => socket.local = value.to_string(),
+                    },
+                    'T' => {
+                        if let Some(state) = value.strip_prefix("ST=") {
+                            socket.state = Some(state.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    sockets.extend(current);
+
+    sockets
+}
@@ -0,0 +1,223 @@
+//! TCP port scanner
+//!
+//! Tries to connect to each port of a host with a bounded number of concurrent
+//! connection attempts on an asynchronous runtime. A port is reported as
+//! **open** if the connection succeeds, **closed** if it is refused, and
+//! **filtered** if there is no answer within the timeout (e.g. because a firewall
+//! drops the connection attempt).
+//!
+//! Service names of open ports are guesses based on the port number, taken from
+//! `/etc/services` and a built-in list of common services.
+
+use crate::cli::PortscanArgs;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Service database of the system
+const SERVICES_FILE: &str = "/etc/services";
+
+/// Common services, used if `/etc/services` does not list a port
+const COMMON_SERVICES: &[(u16, &str)] = &[
+    (21, "ftp"),
+    (22, "ssh"),
+    (23, "telnet"),
+    (25, "smtp"),
+    (53, "domain"),
+    (80, "http"),
+    (110, "pop3"),
+    (143, "imap"),
+    (443, "https"),
+    (445, "microsoft-ds"),
+    (548, "afp"),
+    (587, "submission"),
+    (631, "ipp"),
+    (853, "domain-s"),
+    (993, "imaps"),
+    (995, "pop3s"),
+    (1883, "mqtt"),
+    (3000, "http-alt"),
+    (3306, "mysql"),
+    (3389, "ms-wbt-server"),
+    (5000, "airplay"),
+    (5432, "postgresql"),
+    (5900, "vnc"),
+    (6379, "redis"),
+    (7000, "airplay"),
+    (8080, "http-alt"),
+    (8443, "https-alt"),
+    (9000, "http-alt"),
+    (27017, "mongodb"),
+];
+
+/// Outcome of a connection attempt
+enum PortState {
+    /// The connection succeeded
+    Open,
+    /// The connection was refused
+    Closed,
+    /// No answer within the timeout, or the attempt failed otherwise
+    Filtered,
+}
+
+/// Scans the ports of a host and prints the open ports
+///
+/// # Arguments
+///
+/// * `args` - Port scan arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the port list is invalid, the host cannot be resolved, or
+/// the asynchronous runtime cannot be started.
+pub fn perform(args: PortscanArgs) -> Result<(), Box<dyn Error>> {
+    let ports = parse_ports(&args.ports)?;
+    let address = (args.host.as_str(), 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve '{}': {e}", args.host))?
+        .next()
+        .ok_or_else(|| format!("'{}' has no address", args.host))?;
+
+    println!(
+        "Scanning {} TCP port(s) of {} ({}), {} at a time",
+        ports.len(),
+        args.host.bold(),
+        address.ip(),
+        args.concurrency
+    );
+
+    let start = Instant::now();
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let results = runtime.block_on(scan(address, &ports, args.timeout, usize::from(args.concurrency)));
+    let elapsed = start.elapsed();
+
+    let services = service_names();
+    let (mut closed, mut filtered) = (0, 0);
+    let mut open = Vec::new();
+    for (port, state) in results {
+        match state {
+            PortState::Open => open.push(port),
+            PortState::Closed => closed += 1,
+            PortState::Filtered => filtered += 1,
+        }
+    }
+
+    if !open.is_empty() {
+        println!();
+        println!("{:<7} SERVICE", "PORT");
+        for port in &open {
+            let service = services.get(port).map_or("?", String::as_str);
+            println!("{:<7} {service}", port.to_string().green());
+        }
+    }
+
+    println!();
+    println!(
+        "{} open, {closed} closed, {filtered} filtered port(s) scanned in {:.2} s",
+        open.len(),
+        elapsed.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+/// Tries to connect to all ports with at most `concurrency` attempts at a time
+///
+/// # Returns
+///
+/// The state of each port, ordered by port number.
+async fn scan(address: SocketAddr, ports: &[u16], timeout: Duration, concurrency: usize) -> Vec<(u16, PortState)> {
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = JoinSet::new();
+
+    for &port in ports {
+        let permits = Arc::clone(&permits);
+        tasks.spawn(async move {
+            // The semaphore is never closed, so acquiring only waits for a free permit
+            let _permit = permits.acquire_owned().await.ok();
+            let target = SocketAddr::new(address.ip(), port);
+            let state = match tokio::time::timeout(timeout, TcpStream::connect(target)).await {
+                Ok(Ok(_)) => PortState::Open,
+                Ok(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => PortState::Closed,
+                Ok(Err(_)) | Err(_) => PortState::Filtered,
+            };
+            (port, state)
+        });
+    }
+
+    let mut results = Vec::with_capacity(ports.len());
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(result) = result {
+            results.push(result);
+        }
+    }
+    results.sort_by_key(|(port, _)| *port);
+    results
+}
+
+/// Parses a port list like `22,80,8000-8100` into sorted, distinct ports
+///
+/// # Errors
+///
+/// Returns an error if a port or range is invalid.
+fn parse_ports(spec: &str) -> Result<Vec<u16>, Box<dyn Error>> {
+    let parse = |port: &str| match port.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!("Invalid port '{}', expected 1-65535", port.trim())),
+    };
+
+    let mut ports = Vec::new();
+    for part in spec.split(',').filter(|part| !part.trim().is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    return Err(format!("Invalid port range '{}'", part.trim()).into());
+                }
+                ports.extend(first..=last);
+            }
+            None => ports.push(parse(part)?),
+        }
+    }
+    ports.sort_unstable();
+    ports.dedup();
+
+    if ports.is_empty() {
+        return Err("No ports given".into());
+    }
+    Ok(ports)
+}
+
+/// Returns the service names of TCP ports
+///
+/// Entries of `/etc/services` (`http 80/tcp www # World Wide Web HTTP`) take
+/// precedence over the built-in list.
+fn service_names() -> BTreeMap<u16, String> {
+    let mut services: BTreeMap<u16, String> = COMMON_SERVICES
+        .iter()
+        .map(|(port, name)| (*port, name.to_string()))
+        .collect();
+
+    let content = fs::read_to_string(SERVICES_FILE).unwrap_or_default();
+    let mut system = BTreeMap::new();
+    for line in content.lines() {
+        let mut fields = line.split('#').next().unwrap_or_default().split_whitespace();
+        if let (Some(name), Some(port)) = (fields.next(), fields.next())
+            && let Some(port) = port.strip_suffix("/tcp")
+            && let Ok(port) = port.parse::<u16>()
+        {
+            // Keep the first name of a port, later lines are usually aliases
+            system.entry(port).or_insert_with(|| name.to_string());
+        }
+    }
+    services.extend(system);
+    services
+}
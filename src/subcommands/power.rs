@@ -0,0 +1,143 @@
+//! Power management
+//!
+//! Wraps `pmset` for settings that System Settings hides or spreads over
+//! several panes. Changes to the power management settings require sudo.
+
+pub mod adapter;
+pub mod blame;
+pub mod graceful;
+pub mod lowpower;
+pub mod monitor;
+pub mod schedule;
+pub mod shutdown;
+pub mod timers;
+
+use crate::cli::{PowerArgs, PowerCommands, PowerSourceArgs};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Command;
+
+/// Performs power management operations
+///
+/// # Arguments
+///
+/// * `args` - Power arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails or the requested change is invalid.
+pub fn perform(args: PowerArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        PowerCommands::Schedule { command } => schedule::perform(command),
+        PowerCommands::Lowpower { command } => lowpower::perform(command),
+        PowerCommands::Timers(args) => timers::perform(args),
+        PowerCommands::Blame(args) => blame::perform(args),
+        PowerCommands::Monitor(args) => monitor::perform(args),
+        PowerCommands::Shutdown(args) => shutdown::perform(args, false),
+        PowerCommands::Restart(args) => shutdown::perform(args, true),
+        PowerCommands::Adapter(args) => adapter::perform(args),
+    }
+}
+
+/// Power management settings used on one power source
+pub struct Profile {
+    /// Power source, e.g. `Battery Power` or `AC Power`
+    pub source: String,
+    /// Settings by name, e.g. `displaysleep` → `10`
+    pub settings: BTreeMap<String, String>,
+}
+
+/// Reads the power management settings of all power sources
+///
+/// Desktop Macs only have the `AC Power` profile.
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails.
+pub fn profiles() -> Result<Vec<Profile>, Box<dyn Error>> {
+    let output = pmset(&["-g", "custom"])?;
+    let mut profiles: Vec<Profile> = Vec::new();
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            if let Some(source) = line.trim().strip_suffix(':') {
+                profiles.push(Profile {
+                    source: source.to_string(),
+                    settings: BTreeMap::new(),
+                });
+            }
+            continue;
+        }
+        if let Some(profile) = profiles.last_mut()
+            && let Some((name, value)) = line.trim().split_once(char::is_whitespace)
+        {
+            profile.settings.insert(name.to_string(), value.trim().to_string());
+        }
+    }
+    Ok(profiles)
+}
+
+/// Returns the power source the Mac currently draws from, e.g. `AC Power`
+pub fn current_source() -> Option<String> {
+    let output = pmset(&["-g", "ps"]).ok()?;
+    let line = output.lines().next()?;
+    let (_, rest) = line.split_once('\'')?;
+    let (source, _) = rest.split_once('\'')?;
+    Some(source.to_string())
+}
+
+/// Returns the `pmset` option selecting the power sources to change
+pub fn source_option(source: &PowerSourceArgs) -> &'static str {
+    if source.battery {
+        "-b"
+    } else if source.ac {
+        "-c"
+    } else {
+        "-a"
+    }
+}
+
+/// Describes the power sources to change, e.g. ` for battery`
+pub fn source_label(source: &PowerSourceArgs) -> &'static str {
+    if source.battery {
+        " for battery"
+    } else if source.ac {
+        " for power adapter"
+    } else {
+        ""
+    }
+}
+
+/// Runs `pmset` without sudo and returns its output
+///
+/// # Errors
+///
+/// Returns an error if `pmset` cannot be run or fails.
+pub fn pmset(args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("pmset")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run pmset: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("pmset {} failed: {}", args.join(" "), stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs `pmset` with sudo to change power management settings
+///
+/// # Errors
+///
+/// Returns an error if `pmset` cannot be run or fails.
+pub fn pmset_sudo(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("sudo")
+        .arg("pmset")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("pmset {} failed: {}", args.join(" "), stderr.trim()).into());
+    }
+    Ok(())
+}
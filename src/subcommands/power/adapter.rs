@@ -0,0 +1,175 @@
+//! Power adapter details
+//!
+//! Shows the rated power of the connected adapter, the voltage and current
+//! negotiated over USB Power Delivery and the profiles the adapter offers, and
+//! whether the battery is actually charging. A battery that discharges while
+//! the adapter is connected means the adapter cannot supply the current load,
+//! e.g. a phone charger or a USB-C port of a monitor.
+//!
+//! Serial number and family code are only reported by Apple adapters.
+//!
+//! # System Commands Used
+//!
+//! - The adapter is read with `IOPSCopyExternalPowerAdapterDetails`, the
+//!   battery with `IOPSCopyPowerSourcesInfo` and the `AppleSmartBattery`
+//!   service of the I/O Registry
+
+use crate::cli::PowerAdapterArgs;
+use crate::subcommands::battery::{AdapterDetails, BatteryStatus, read_adapter, read_battery};
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use std::error::Error;
+
+/// Power in watts below which the battery is considered neither charging nor discharging
+const IDLE_WATTS: f64 = 0.5;
+
+/// Charging state of the battery while the adapter is connected
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ChargeState {
+    /// The battery is charging
+    Charging,
+    /// The battery is full or held at its charge, the adapter powers the Mac
+    Maintaining,
+    /// The battery discharges, the adapter cannot supply the load
+    Discharging,
+    /// The Mac has no battery
+    NoBattery,
+}
+
+/// Adapter details with the charging state for JSON output
+#[derive(Serialize)]
+struct AdapterReport<'a> {
+    /// Connected adapter
+    adapter: &'a AdapterDetails,
+    /// Charging state of the battery
+    state: ChargeState,
+    /// Power flowing into the battery in watts, negative while discharging
+    #[serde(skip_serializing_if = "Option::is_none")]
+    battery_watts: Option<f64>,
+}
+
+/// Shows the connected power adapter
+///
+/// # Arguments
+///
+/// * `args` - Adapter arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the power sources cannot be read.
+pub fn perform(args: PowerAdapterArgs) -> Result<(), Box<dyn Error>> {
+    let Some(adapter) = read_adapter()? else {
+        if args.json {
+            println!("null");
+        } else {
+            println!("No power adapter connected");
+        }
+        return Ok(());
+    };
+    let battery = read_battery()?;
+    let battery_watts = battery.as_ref().and_then(BatteryStatus::power_watts);
+    let state = charge_state(battery.as_ref());
+
+    if args.json {
+        let report = AdapterReport {
+            adapter: &adapter,
+            state,
+            battery_watts,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let name = match (&adapter.name, &adapter.manufacturer) {
+        (Some(name), Some(manufacturer)) => format!("{name} ({manufacturer})"),
+        (Some(name), None) => name.clone(),
+        (None, Some(manufacturer)) => manufacturer.clone(),
+        (None, None) => "unknown".to_string(),
+    };
+    println!("{:<16}{name}", "Adapter".bold());
+    if let Some(watts) = adapter.watts {
+        println!("{:<16}{watts} W", "Rated power".bold());
+    }
+    if let (Some(voltage), Some(current)) = (adapter.voltage_mv, adapter.current_ma) {
+        println!(
+            "{:<16}{} at {}",
+            "Negotiated".bold(),
+            format_volts(voltage),
+            format_amps(current)
+        );
+    }
+    if !adapter.profiles.is_empty() {
+        let profiles: Vec<String> = adapter
+            .profiles
+            .iter()
+            .enumerate()
+            .map(|(index, profile)| {
+                let text = format!(
+                    "{} {}",
+                    format_volts(profile.voltage_mv),
+                    format_amps(profile.current_ma)
+                );
+                if adapter.selected_profile == Some(index) {
+                    text.green().to_string()
+                } else {
+                    text
+                }
+            })
+            .collect();
+        println!("{:<16}{}", "Profiles".bold(), profiles.join(", "));
+    }
+    if let Some(serial) = &adapter.serial {
+        println!("{:<16}{serial}", "Serial".bold());
+    }
+    if let Some(family) = adapter.family {
+        // Family codes are 32 bit values, negative ones are stored sign-extended
+        println!("{:<16}{:#010x}", "Family".bold(), family as u32);
+    }
+    println!("{:<16}{}", "Battery".bold(), format_state(state, battery_watts));
+
+    if state == ChargeState::Discharging {
+        println!(
+            "{}",
+            "The battery discharges although the adapter is connected, the adapter is too weak for the current load"
+                .yellow()
+        );
+    }
+    Ok(())
+}
+
+/// Determines whether the adapter charges the battery
+fn charge_state(battery: Option<&BatteryStatus>) -> ChargeState {
+    let Some(battery) = battery else {
+        return ChargeState::NoBattery;
+    };
+    let watts = battery.power_watts().unwrap_or(0.0);
+    if battery.charging {
+        ChargeState::Charging
+    } else if !battery.on_ac || watts < -IDLE_WATTS {
+        ChargeState::Discharging
+    } else {
+        ChargeState::Maintaining
+    }
+}
+
+/// Describes the charging state with the battery power, e.g. `charging at 45.2 W`
+fn format_state(state: ChargeState, watts: Option<f64>) -> ColoredString {
+    let at = |watts: Option<f64>| watts.map_or_else(String::new, |watts| format!(" at {:.1} W", watts.abs()));
+    match state {
+        ChargeState::Charging => format!("charging{}", at(watts)).green(),
+        ChargeState::Maintaining => "maintaining, not charging".normal(),
+        ChargeState::Discharging => format!("discharging{}", at(watts)).yellow(),
+        ChargeState::NoBattery => "none".dimmed(),
+    }
+}
+
+/// Formats a voltage in mV as volts, e.g. `20 V` or `14.8 V`
+fn format_volts(millivolts: u32) -> String {
+    format!("{} V", f64::from(millivolts) / 1000.0)
+}
+
+/// Formats a current in mA as amperes, e.g. `3 A` or `4.7 A`
+fn format_amps(milliamps: u32) -> String {
+    format!("{} A", f64::from(milliamps) / 1000.0)
+}
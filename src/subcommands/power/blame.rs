@@ -0,0 +1,301 @@
+//! Processes preventing sleep
+//!
+//! Lists the power management assertions that keep the Mac awake or its display
+//! on, grouped by what they prevent, with the process holding each assertion,
+//! how long it has been held and the reason given by the process. Assertions
+//! taken on behalf of another process (e.g. by `coreaudiod` for a music player)
+//! name that process as well.
+//!
+//! With `--watch`, assertions are printed as they are taken and released; with
+//! `--notify`, a notification is posted for each new one.
+//!
+//! # System Commands Used
+//!
+//! - `pmset -g assertions` - Read the assertions by owning process
+//! - `ps -p` - Read the names of processes assertions are taken for
+//! - `date` - Format timestamps in watch mode
+//! - `osascript` - Post notifications
+
+use super::pmset;
+use crate::cli::PowerBlameArgs;
+use crate::notify::notify;
+use crate::util::timestamp;
+use colored::Colorize;
+use regex::Regex;
+use std::collections::HashMap;
+use std::error::Error;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Assertion types keeping the system from idle sleep
+const SYSTEM_TYPES: [&str; 4] = [
+    "PreventUserIdleSystemSleep",
+    "PreventUserIdleSleep",
+    "PreventSystemSleep",
+    "NoIdleSleepAssertion",
+];
+
+/// Assertion types keeping the display from idle sleep
+const DISPLAY_TYPES: [&str; 2] = ["PreventUserIdleDisplaySleep", "NoDisplaySleepAssertion"];
+
+/// What an assertion prevents
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Prevents {
+    /// Idle sleep of the system
+    SystemSleep,
+    /// Idle sleep of the display
+    DisplaySleep,
+}
+
+impl Prevents {
+    /// Returns a heading for assertions of this kind
+    fn heading(self) -> &'static str {
+        match self {
+            Prevents::SystemSleep => "Keeping the Mac awake",
+            Prevents::DisplaySleep => "Keeping the display on",
+        }
+    }
+}
+
+/// A power management assertion preventing sleep
+struct Blocker {
+    /// Process ID of the process holding the assertion
+    pid: u32,
+    /// Name of the process holding the assertion
+    process: String,
+    /// Process ID of the process the assertion was taken for, if another one
+    created_for: Option<u32>,
+    /// Assertion type, e.g. `PreventUserIdleSystemSleep`
+    kind: String,
+    /// What the assertion prevents
+    prevents: Prevents,
+    /// Time the assertion has been held as `HH:MM:SS`
+    held: String,
+    /// Reason given by the process
+    name: String,
+}
+
+impl Blocker {
+    /// Returns a key identifying the assertion across checks
+    fn key(&self) -> (u32, String, String) {
+        (self.pid, self.kind.clone(), self.name.clone())
+    }
+
+    /// Describes the process holding the assertion, e.g. `coreaudiod (123) for Music (456)`
+    fn owner(&self, names: &HashMap<u32, String>) -> String {
+        let owner = format!("{} ({})", self.process, self.pid);
+        match self.created_for {
+            Some(pid) => match names.get(&pid) {
+                Some(name) => format!("{owner} for {name} ({pid})"),
+                None => format!("{owner} for process {pid}"),
+            },
+            None => owner,
+        }
+    }
+}
+
+/// Shows the processes preventing sleep once or watches for changes
+///
+/// # Arguments
+///
+/// * `args` - Blame arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails.
+pub fn perform(args: PowerBlameArgs) -> Result<(), Box<dyn Error>> {
+    let blockers = read_blockers()?;
+    let names = process_names(&blockers);
+    print_blockers(&blockers, &names);
+    if !args.watch {
+        return Ok(());
+    }
+
+    println!();
+    println!("Watching for changes every {} s (Ctrl-C to stop)", args.interval);
+    let mut known = blockers;
+    loop {
+        thread::sleep(Duration::from_secs(args.interval));
+        let blockers = match read_blockers() {
+            Ok(blockers) => blockers,
+            Err(e) => {
+                eprintln!("{}", e.to_string().red());
+                continue;
+            }
+        };
+        let names = process_names(&blockers);
+
+        for blocker in &blockers {
+            if known.iter().any(|old| old.key() == blocker.key()) {
+                continue;
+            }
+            println!(
+                "{}  {} {} {} \"{}\"",
+                timestamp(),
+                "+".red(),
+                blocker.owner(&names),
+                blocker.kind,
+                blocker.name
+            );
+            if args.notify
+                && let Err(e) = notify(
+                    &format!("{} prevents sleep", blocker.process),
+                    &format!("{}: {}", blocker.kind, blocker.name),
+                )
+            {
+                eprintln!("{}", e.to_string().red());
+            }
+        }
+        for blocker in &known {
+            if !blockers.iter().any(|new| new.key() == blocker.key()) {
+                println!(
+                    "{}  {} {} ({}) {} \"{}\"",
+                    timestamp(),
+                    "-".green(),
+                    blocker.process,
+                    blocker.pid,
+                    blocker.kind,
+                    blocker.name
+                );
+            }
+        }
+        known = blockers;
+    }
+}
+
+/// Prints the assertions grouped by what they prevent
+fn print_blockers(blockers: &[Blocker], names: &HashMap<u32, String>) {
+    if blockers.is_empty() {
+        println!("Nothing prevents idle sleep");
+        return;
+    }
+
+    let width = blockers
+        .iter()
+        .map(|blocker| blocker.owner(names).chars().count())
+        .max()
+        .unwrap_or_default()
+        + 2;
+    let mut first = true;
+    for prevents in [Prevents::SystemSleep, Prevents::DisplaySleep] {
+        let group: Vec<&Blocker> = blockers.iter().filter(|blocker| blocker.prevents == prevents).collect();
+        if group.is_empty() {
+            continue;
+        }
+        if !first {
+            println!();
+        }
+        first = false;
+        println!("{}", prevents.heading().bold());
+        for blocker in group {
+            println!(
+                "  {:<width$}{}  {}",
+                blocker.owner(names),
+                blocker.held.dimmed(),
+                blocker.name
+            );
+        }
+    }
+}
+
+/// Reads the assertions preventing system or display sleep
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails.
+fn read_blockers() -> Result<Vec<Blocker>, Box<dyn Error>> {
+    let output = pmset(&["-g", "assertions"])?;
+    parse_assertions(&output)
+}
+
+/// Parses the assertions listed by owning process in `pmset -g assertions`
+///
+/// Assertion lines look like
+/// `pid 123(coreaudiod): [0x00003a8400019a1f] 00:05:23 PreventUserIdleSleep named: "..."`
+/// and may be followed by `Created for PID: 456.`; other assertion types are
+/// skipped.
+///
+/// # Errors
+///
+/// Returns an error if the pattern cannot be compiled.
+fn parse_assertions(output: &str) -> Result<Vec<Blocker>, Box<dyn Error>> {
+    let assertion = Regex::new(r#"^pid (\d+)\((.*)\): \[0x[0-9a-fA-F]+\] (\S+) (\w+) named: "(.*)""#)?;
+    let created_for = Regex::new(r"^Created for PID: (\d+)")?;
+
+    let mut blockers: Vec<Blocker> = Vec::new();
+    let mut listed = false;
+    let mut last_matched = false;
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            listed = line.trim() == "Listed by owning process:";
+            continue;
+        }
+        if !listed {
+            continue;
+        }
+        let line = line.trim();
+
+        if let Some(captures) = assertion.captures(line) {
+            let kind = &captures[4];
+            let prevents = if SYSTEM_TYPES.contains(&kind) {
+                Prevents::SystemSleep
+            } else if DISPLAY_TYPES.contains(&kind) {
+                Prevents::DisplaySleep
+            } else {
+                last_matched = false;
+                continue;
+            };
+            blockers.push(Blocker {
+                pid: captures[1].parse()?,
+                process: captures[2].to_string(),
+                created_for: None,
+                kind: kind.to_string(),
+                prevents,
+                held: captures[3].to_string(),
+                name: captures[5].to_string(),
+            });
+            last_matched = true;
+        } else if last_matched
+            && let Some(captures) = created_for.captures(line)
+            && let Some(blocker) = blockers.last_mut()
+        {
+            blocker.created_for = captures[1].parse().ok().filter(|pid| *pid != blocker.pid);
+        }
+    }
+    Ok(blockers)
+}
+
+/// Reads the names of the processes assertions were taken for
+///
+/// The name is the outermost `.app` bundle of the executable, e.g. `Google
+/// Chrome` for its helper processes, otherwise the name of the executable.
+fn process_names(blockers: &[Blocker]) -> HashMap<u32, String> {
+    let pids: Vec<String> = blockers
+        .iter()
+        .filter_map(|blocker| blocker.created_for)
+        .map(|pid| pid.to_string())
+        .collect();
+    if pids.is_empty() {
+        return HashMap::new();
+    }
+    let Ok(output) = Command::new("ps")
+        .args(["-p", &pids.join(","), "-o", "pid=,comm="])
+        .output()
+    else {
+        return HashMap::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, path) = line.trim_start().split_once(' ')?;
+            let path = path.trim();
+            let name = path
+                .split('/')
+                .find_map(|component| component.strip_suffix(".app"))
+                .or_else(|| path.rsplit('/').next())
+                .filter(|name| !name.is_empty())?;
+            Some((pid.parse().ok()?, name.to_string()))
+        })
+        .collect()
+}
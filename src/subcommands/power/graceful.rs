@@ -0,0 +1,204 @@
+//! Graceful shutdown and restart
+//!
+//! Asks all running apps to quit through AppleEvents, as the Apple menu does,
+//! and waits until they have quit. Apps asking to save documents keep running
+//! until the question is answered; if any app is still running when the timeout
+//! expires, the shutdown or restart is cancelled and the apps are reported, so
+//! no unsaved work is lost. `sudo reboot` and `shutdown -r now` end the apps
+//! without asking them.
+//!
+//! The app running rem, e.g. Terminal, is not asked to quit; it is ended by the
+//! restart itself. Listing the apps through System Events requires the
+//! Automation permission, which macOS asks for on first use.
+//!
+//! # System Commands Used
+//!
+//! - `osascript` - List the running apps through System Events and ask them to quit
+//! - `ps -o ppid=` - Find the app running rem
+//! - `sudo -v` - Ask for the password before any app is quit
+//! - `shutdown -h now` / `shutdown -r now` - Shut down or restart (with sudo)
+
+use crate::cli::PowerShutdownArgs;
+use crate::util::{confirm, format_count};
+use colored::Colorize;
+use std::error::Error;
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Lists the regular apps as lines of process id, bundle identifier and name, separated by tabs
+const LIST_APPS: &str = r#"
+set output to ""
+tell application "System Events"
+    repeat with app_process in (every application process whose background only is false)
+        set bundle_id to bundle identifier of app_process
+        if bundle_id is missing value then set bundle_id to ""
+        set output to output & (unix id of app_process) & tab & bundle_id & tab & (name of app_process) & linefeed
+    end repeat
+end tell
+return output
+"#;
+
+/// Seconds between checks whether the apps have quit
+const POLL_SECONDS: u64 = 1;
+
+/// A running app
+struct App {
+    /// Process id
+    pid: u32,
+    /// Bundle identifier, e.g. `com.apple.Safari`
+    bundle_id: String,
+    /// Name shown in the Dock
+    name: String,
+}
+
+/// Quits all apps, then shuts down or restarts right away
+///
+/// # Arguments
+///
+/// * `args` - Shutdown arguments from the command line
+/// * `restart` - True to restart instead of shutting down
+///
+/// # Errors
+///
+/// Returns an error if the apps cannot be listed, an app did not quit in time,
+/// or `shutdown` fails.
+pub fn perform(args: &PowerShutdownArgs, restart: bool) -> Result<(), Box<dyn Error>> {
+    let action = if restart { "restart" } else { "shut down" };
+    let ancestors = ancestors();
+    let (hosts, apps): (Vec<App>, Vec<App>) = running_apps()?
+        .into_iter()
+        .partition(|app| ancestors.contains(&app.pid));
+    // Apps without a bundle identifier cannot be addressed, they are ended by the restart
+    let (apps, unaddressable): (Vec<App>, Vec<App>) = apps.into_iter().partition(|app| !app.bundle_id.is_empty());
+
+    if !args.yes
+        && !confirm(&format!(
+            "Quit {} and {action} the Mac?",
+            format_count(apps.len(), "app")
+        ))?
+    {
+        println!("Nothing quit");
+        return Ok(());
+    }
+    // The password is asked for while all apps are still running
+    let status = Command::new("sudo")
+        .arg("-v")
+        .status()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !status.success() {
+        return Err("sudo failed, no app was quit".into());
+    }
+
+    println!("Asking {} to quit", format_count(apps.len(), "app"));
+    quit(&apps)?;
+    let start = Instant::now();
+    let mut remaining: Vec<&App> = apps.iter().collect();
+    while !remaining.is_empty() && start.elapsed() < Duration::from_secs(args.timeout) {
+        thread::sleep(Duration::from_secs(POLL_SECONDS));
+        let running: Vec<u32> = running_apps()?.iter().map(|app| app.pid).collect();
+        remaining.retain(|app| running.contains(&app.pid));
+    }
+
+    if !remaining.is_empty() {
+        for app in &remaining {
+            println!("{} {}", "Still running:".yellow(), app.name);
+        }
+        return Err(format!(
+            "{} did not quit within {} s, e.g. because of unsaved documents; did not {action}",
+            format_count(remaining.len(), "app"),
+            args.timeout
+        )
+        .into());
+    }
+    println!("{} quit", format_count(apps.len(), "app").green());
+    for app in hosts.iter().chain(&unaddressable) {
+        println!("{} {}", "Left running:".dimmed(), app.name);
+    }
+
+    let status = Command::new("sudo")
+        .args(["shutdown", if restart { "-r" } else { "-h" }, "now"])
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !status.success() {
+        return Err(format!("shutdown failed ({status})").into());
+    }
+    Ok(())
+}
+
+/// Lists the running regular apps, i.e. those shown in the Dock
+///
+/// # Errors
+///
+/// Returns an error if `osascript` cannot be run or System Events refuses access.
+fn running_apps() -> Result<Vec<App>, Box<dyn Error>> {
+    let output = Command::new("osascript")
+        .args(["-e", LIST_APPS])
+        .output()
+        .map_err(|e| format!("Failed to run osascript: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list the running apps: {}", stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            Some(App {
+                pid: fields.next()?.trim().parse().ok()?,
+                bundle_id: fields.next()?.to_string(),
+                name: fields.next()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Asks apps to quit without waiting for their answers
+///
+/// # Errors
+///
+/// Returns an error if `osascript` cannot be run or fails.
+fn quit(apps: &[App]) -> Result<(), Box<dyn Error>> {
+    if apps.is_empty() {
+        return Ok(());
+    }
+    // Apps answer once their documents are saved, which would block the script
+    let mut script = String::from("ignoring application responses\n");
+    for app in apps {
+        script.push_str(&format!(
+            "    tell application id \"{}\" to quit\n",
+            app.bundle_id.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    }
+    script.push_str("end ignoring\n");
+
+    let output = Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .map_err(|e| format!("Failed to run osascript: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to ask the apps to quit: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Returns the process ids of rem and its parent processes, e.g. the terminal app
+fn ancestors() -> Vec<u32> {
+    let mut pids = vec![process::id()];
+    while let Some(&pid) = pids.last()
+        && pid > 1
+    {
+        let parent = Command::new("ps")
+            .args(["-o", "ppid=", "-p", &pid.to_string()])
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok());
+        match parent {
+            Some(parent) if !pids.contains(&parent) => pids.push(parent),
+            _ => break,
+        }
+    }
+    pids
+}
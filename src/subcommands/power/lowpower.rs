@@ -0,0 +1,114 @@
+//! Low Power Mode
+//!
+//! Low Power Mode reduces the clock speed and background activity to extend the
+//! battery runtime or to keep the Mac quiet. It is set separately for battery and
+//! power adapter, like the other power management settings.
+//!
+//! Macs that offer High Power Mode use the `powermode` setting instead of
+//! `lowpowermode`, where 1 selects Low Power Mode and 2 High Power Mode. Macs
+//! and macOS versions without either setting do not support Low Power Mode.
+//!
+//! # System Commands Used
+//!
+//! - `pmset -g custom` - Read the settings of each power source
+//! - `pmset -g ps` - Read the current power source
+//! - `pmset lowpowermode` / `pmset powermode` - Switch Low Power Mode (with sudo)
+
+use super::{Profile, current_source, pmset_sudo, profiles, source_label, source_option};
+use crate::cli::{PowerLowpowerCommands, PowerSourceArgs};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Settings switching Low Power Mode, preferred first
+const SETTINGS: [&str; 2] = ["lowpowermode", "powermode"];
+
+/// Performs Low Power Mode operations
+///
+/// # Arguments
+///
+/// * `command` - Low Power Mode operation from the command line
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails or Low Power Mode is not supported.
+pub fn perform(command: Option<PowerLowpowerCommands>) -> Result<(), Box<dyn Error>> {
+    match command {
+        None | Some(PowerLowpowerCommands::Status) => print_status(),
+        Some(PowerLowpowerCommands::On { source }) => switch(true, &source),
+        Some(PowerLowpowerCommands::Off { source }) => switch(false, &source),
+    }
+}
+
+/// Prints whether Low Power Mode is on for each power source
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails.
+fn print_status() -> Result<(), Box<dyn Error>> {
+    let profiles = profiles()?;
+    let Some(setting) = find_setting(&profiles) else {
+        println!("Low Power Mode is not supported on this Mac");
+        return Ok(());
+    };
+    let current = current_source();
+
+    for profile in &profiles {
+        let state = match profile.settings.get(setting).map(String::as_str) {
+            Some("1") => "on".green(),
+            Some("2") => "off (High Power Mode)".normal(),
+            Some(_) => "off".normal(),
+            None => "-".normal(),
+        };
+        let active = if current.as_deref() == Some(profile.source.as_str()) {
+            " (current)".dimmed()
+        } else {
+            "".normal()
+        };
+        println!("{:<16}{state}{active}", profile.source.bold());
+    }
+    Ok(())
+}
+
+/// Switches Low Power Mode on or off
+///
+/// # Arguments
+///
+/// * `on` - True to turn Low Power Mode on
+/// * `source` - Power sources to change
+///
+/// # Errors
+///
+/// Returns an error if Low Power Mode is not supported, the Mac has no battery
+/// but the battery setting is to be changed, or `pmset` fails.
+fn switch(on: bool, source: &PowerSourceArgs) -> Result<(), Box<dyn Error>> {
+    let profiles = profiles()?;
+    let setting = find_setting(&profiles).ok_or("Low Power Mode is not supported on this Mac")?;
+    if source.battery && !profiles.iter().any(|profile| profile.source == "Battery Power") {
+        return Err("This Mac has no battery".into());
+    }
+
+    print!(
+        "Turn Low Power Mode {}{}",
+        if on { "on" } else { "off" },
+        source_label(source)
+    );
+    io::stdout().flush()?;
+    if let Err(e) = pmset_sudo(&[source_option(source), setting, if on { "1" } else { "0" }]) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Returns the setting switching Low Power Mode on this Mac
+///
+/// # Returns
+///
+/// `None` if no power source has a Low Power Mode setting.
+fn find_setting(profiles: &[Profile]) -> Option<&'static str> {
+    SETTINGS
+        .into_iter()
+        .find(|setting| profiles.iter().any(|profile| profile.settings.contains_key(*setting)))
+}
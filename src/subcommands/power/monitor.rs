@@ -0,0 +1,308 @@
+//! Power consumption monitor
+//!
+//! Samples the power drawn by CPU, GPU and Neural Engine with `powermetrics`
+//! and the power drawn from the battery, e.g. to compare the battery impact of
+//! apps. The total power of recent samples is drawn as a rolling graph; when
+//! stopped with Ctrl-C, the averages and peaks of the session are printed.
+//!
+//! `powermetrics` reports CPU, GPU and Neural Engine separately on Apple
+//! silicon and the package power on Intel Macs. It requires root, so it is run
+//! with sudo.
+//!
+//! # System Commands Used
+//!
+//! - `powermetrics --samplers cpu_power,gpu_power` - Sample the power consumption (with sudo)
+//! - The battery is read with `IOPSCopyPowerSourcesInfo` and the
+//!   `AppleSmartBattery` service of the I/O Registry
+
+use crate::cli::PowerMonitorArgs;
+use crate::subcommands::battery::read_battery;
+use crate::util::{catch_interrupts, format_clock, interrupted};
+use colored::Colorize;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// Number of samples shown in the rolling graph
+const GRAPH_WIDTH: usize = 60;
+
+/// Characters of the rolling graph from low to high
+const GRAPH_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Number of lines redrawn for every sample on a terminal
+const DISPLAY_LINES: usize = 3;
+
+/// Minimum session length before the battery drain rate is shown, in seconds
+const MIN_DRAIN_SECONDS: u64 = 60;
+
+/// Power consumption in one sample, in watts
+#[derive(Default, Clone, Copy)]
+struct Sample {
+    /// Power of the CPU cores (Apple silicon)
+    cpu: Option<f64>,
+    /// Power of the GPU (Apple silicon)
+    gpu: Option<f64>,
+    /// Power of the Neural Engine (Apple silicon)
+    ane: Option<f64>,
+    /// Combined power of CPU, GPU and Neural Engine, or the package power on Intel Macs
+    total: Option<f64>,
+    /// Power drawn from the battery, if running on battery
+    battery: Option<f64>,
+}
+
+impl Sample {
+    /// Returns true if no power value was read
+    fn is_empty(&self) -> bool {
+        self.cpu.is_none() && self.gpu.is_none() && self.ane.is_none() && self.total.is_none()
+    }
+
+    /// Returns the total power, or the sum of the parts if no total was reported
+    fn total(&self) -> f64 {
+        self.total
+            .unwrap_or_else(|| self.cpu.unwrap_or(0.0) + self.gpu.unwrap_or(0.0) + self.ane.unwrap_or(0.0))
+    }
+}
+
+/// Running statistics of a monitoring session
+#[derive(Default)]
+struct Session {
+    /// Number of samples
+    samples: usize,
+    /// Sums of the power values
+    sum: Sample,
+    /// Number of samples with a battery reading
+    battery_samples: usize,
+    /// Highest total power
+    peak: f64,
+    /// Total power of the most recent samples for the graph
+    recent: VecDeque<f64>,
+}
+
+impl Session {
+    /// Adds a sample to the statistics
+    fn add(&mut self, sample: &Sample) {
+        let add = |sum: &mut Option<f64>, value: Option<f64>| {
+            if let Some(value) = value {
+                *sum = Some(sum.unwrap_or(0.0) + value);
+            }
+        };
+        add(&mut self.sum.cpu, sample.cpu);
+        add(&mut self.sum.gpu, sample.gpu);
+        add(&mut self.sum.ane, sample.ane);
+        add(&mut self.sum.total, Some(sample.total()));
+        add(&mut self.sum.battery, sample.battery);
+        if sample.battery.is_some() {
+            self.battery_samples += 1;
+        }
+        self.samples += 1;
+        self.peak = self.peak.max(sample.total());
+        self.recent.push_back(sample.total());
+        if self.recent.len() > GRAPH_WIDTH {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Returns the average power values of the session
+    fn average(&self) -> Sample {
+        let samples = self.samples.max(1) as f64;
+        Sample {
+            cpu: self.sum.cpu.map(|sum| sum / samples),
+            gpu: self.sum.gpu.map(|sum| sum / samples),
+            ane: self.sum.ane.map(|sum| sum / samples),
+            total: self.sum.total.map(|sum| sum / samples),
+            battery: self.sum.battery.map(|sum| sum / self.battery_samples.max(1) as f64),
+        }
+    }
+
+    /// Draws the total power of the recent samples, scaled to the highest one
+    fn graph(&self) -> String {
+        let max = self.recent.iter().copied().fold(1.0, f64::max);
+        self.recent
+            .iter()
+            .map(|watts| {
+                let level = (watts / max * (GRAPH_LEVELS.len() - 1) as f64).round() as usize;
+                GRAPH_LEVELS[level.min(GRAPH_LEVELS.len() - 1)]
+            })
+            .collect()
+    }
+}
+
+/// Monitors the power consumption until Ctrl-C
+///
+/// # Arguments
+///
+/// * `args` - Monitor arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `powermetrics` cannot be run or reports no samples.
+pub fn perform(args: PowerMonitorArgs) -> Result<(), Box<dyn Error>> {
+    catch_interrupts();
+    let interval = (args.interval * 1000).to_string();
+    let mut child = Command::new("sudo")
+        .args(["powermetrics", "--samplers", "cpu_power,gpu_power", "-i", &interval])
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    let stdout = child.stdout.take().ok_or("Failed to read the output of powermetrics")?;
+
+    let start = Instant::now();
+    let start_percent = read_battery().ok().flatten().and_then(|status| status.percent);
+    let terminal = io::stdout().is_terminal();
+    let mut session = Session::default();
+    let mut sample = Sample::default();
+    // The GPU sampler repeats the GPU power after the total, which is ignored
+    let mut recorded = false;
+
+    eprintln!("Sampling every {} s (Ctrl-C to stop)", args.interval);
+    for line in BufReader::new(stdout).lines() {
+        if interrupted() {
+            break;
+        }
+        let Ok(line) = line else {
+            break;
+        };
+        let line = line.trim();
+
+        // Samples without a total end at the header of the next sample
+        if line.starts_with("*** Sampled system activity") {
+            if !recorded && !sample.is_empty() {
+                record(&mut session, &mut sample, terminal)?;
+            }
+            sample = Sample::default();
+            recorded = false;
+            continue;
+        }
+        if recorded {
+            continue;
+        }
+        let Some((label, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(watts) = parse_watts(value) else {
+            continue;
+        };
+        match label {
+            "CPU Power" => sample.cpu = Some(watts),
+            "GPU Power" => sample.gpu = Some(watts),
+            "ANE Power" => sample.ane = Some(watts),
+            _ if label.starts_with("Combined Power")
+                || label == "Package Power"
+                || label.starts_with("Intel energy model derived package power") =>
+            {
+                sample.total = Some(watts);
+                record(&mut session, &mut sample, terminal)?;
+                recorded = true;
+            }
+            _ => {}
+        }
+    }
+    if !recorded && !sample.is_empty() {
+        record(&mut session, &mut sample, terminal)?;
+    }
+    // powermetrics receives Ctrl-C as well, it is only killed if rem was stopped otherwise
+    if child.try_wait()?.is_none() {
+        let _ = child.kill();
+    }
+
+    if session.samples == 0 {
+        return Err("powermetrics reported no samples".into());
+    }
+    println!();
+    print_summary(&session, start, start_percent);
+    Ok(())
+}
+
+/// Adds a sample with the current battery power to the session and displays it
+///
+/// # Errors
+///
+/// Returns an error if the display cannot be written.
+fn record(session: &mut Session, sample: &mut Sample, terminal: bool) -> Result<(), Box<dyn Error>> {
+    sample.battery = read_battery()
+        .ok()
+        .flatten()
+        .filter(|status| !status.on_ac)
+        .and_then(|status| status.power_watts())
+        .map(f64::abs);
+    let first = session.samples == 0;
+    session.add(sample);
+
+    let now = format!("{}  {}", "Now".bold(), format_sample(sample));
+    if !terminal {
+        println!("{now}");
+        return Ok(());
+    }
+    let mut stdout = io::stdout().lock();
+    if !first {
+        write!(stdout, "\x1b[{DISPLAY_LINES}A")?;
+    }
+    writeln!(
+        stdout,
+        "\r\x1b[2K{}  max {:.2} W",
+        session.graph().cyan(),
+        session.recent.iter().copied().fold(0.0, f64::max)
+    )?;
+    writeln!(stdout, "\r\x1b[2K{now}")?;
+    writeln!(
+        stdout,
+        "\r\x1b[2K{}  {}",
+        "Avg".bold(),
+        format_sample(&session.average())
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Prints the averages and peaks of the session
+fn print_summary(session: &Session, start: Instant, start_percent: Option<u8>) {
+    let elapsed = start.elapsed();
+    let average = session.average();
+    println!(
+        "{:<12}{}, {} samples",
+        "Session".bold(),
+        format_clock(elapsed),
+        session.samples
+    );
+    println!("{:<12}{}", "Average".bold(), format_sample(&average));
+    println!("{:<12}{:.2} W", "Peak".bold(), session.peak);
+
+    let Some(battery) = average.battery else {
+        return;
+    };
+    let mut line = format!("{battery:.2} W average drain");
+    let end_percent = read_battery().ok().flatten().and_then(|status| status.percent);
+    if let (Some(start), Some(end)) = (start_percent, end_percent)
+        && elapsed.as_secs() >= MIN_DRAIN_SECONDS
+    {
+        let per_hour = f64::from(start.saturating_sub(end)) * 3600.0 / elapsed.as_secs_f64();
+        line.push_str(&format!(", {start}% → {end}% ({per_hour:.1}%/h)"));
+    }
+    println!("{:<12}{line}", "Battery".bold());
+}
+
+/// Formats the power values of a sample, e.g. `CPU 1.20 W  GPU 0.05 W  Total 1.25 W`
+fn format_sample(sample: &Sample) -> String {
+    let mut fields = Vec::new();
+    for (label, value) in [("CPU", sample.cpu), ("GPU", sample.gpu), ("ANE", sample.ane)] {
+        if let Some(watts) = value {
+            fields.push(format!("{label} {watts:.2} W"));
+        }
+    }
+    fields.push(format!("Total {:.2} W", sample.total()));
+    if let Some(watts) = sample.battery {
+        fields.push(format!("Battery {watts:.2} W"));
+    }
+    fields.join("  ")
+}
+
+/// Parses a power value printed by powermetrics, e.g. `1234 mW` or `5.23W`, into watts
+fn parse_watts(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if let Some(milliwatts) = value.strip_suffix("mW") {
+        return milliwatts.trim().parse::<f64>().ok().map(|value| value / 1000.0);
+    }
+    value.strip_suffix('W')?.trim().parse().ok()
+}
@@ -0,0 +1,477 @@
+//! Repeating sleep and wake schedule
+//!
+//! Wraps `pmset repeat`, which holds at most one repeating event that wakes or
+//! powers on the Mac and one that puts it to sleep, shuts it down or restarts
+//! it. `pmset repeat` always replaces the whole schedule, so setting one event
+//! re-issues the other one unchanged.
+//!
+//! Days are given as `daily`, `weekdays`, `weekends`, the pmset day letters
+//! `MTWRFSU` (e.g. `MWF`) or a comma-separated list of day names (e.g.
+//! `mon,wed,fri`).
+//!
+//! # System Commands Used
+//!
+//! - `pmset -g sched` - Read the schedule
+//! - `pmset repeat` - Change the repeating events (with sudo)
+
+use super::{pmset, pmset_sudo};
+use crate::cli::PowerScheduleCommands;
+use crate::util::{confirm, parse_time, print_dry_run};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Day letters used by pmset, Monday first
+const DAY_LETTERS: [char; 7] = ['M', 'T', 'W', 'R', 'F', 'S', 'U'];
+
+/// Short day names, Monday first
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Days of the week as a bit mask, Monday in the lowest bit
+const EVERY_DAY: u8 = 0x7f;
+
+/// Monday to Friday
+const WEEKDAYS: u8 = 0x1f;
+
+/// Saturday and Sunday
+const WEEKENDS: u8 = 0x60;
+
+/// Event types that wake or power on the Mac
+const POWER_ON_TYPES: [&str; 3] = ["wake", "poweron", "wakeorpoweron"];
+
+/// A repeating power event
+#[derive(Debug, PartialEq)]
+struct RepeatEvent {
+    /// Event type as accepted by `pmset repeat`, e.g. `wakeorpoweron` or `sleep`
+    kind: String,
+    /// Days of the week as a bit mask, Monday in the lowest bit
+    days: u8,
+    /// Hour of the day (0-23)
+    hour: u8,
+    /// Minute of the hour (0-59)
+    minute: u8,
+}
+
+impl RepeatEvent {
+    /// Returns true if the event wakes or powers on the Mac
+    fn powers_on(&self) -> bool {
+        POWER_ON_TYPES.contains(&self.kind.as_str())
+    }
+
+    /// Returns the arguments of this event for `pmset repeat`
+    fn pmset_args(&self) -> [String; 3] {
+        [
+            self.kind.clone(),
+            day_letters(self.days),
+            format!("{:02}:{:02}:00", self.hour, self.minute),
+        ]
+    }
+}
+
+/// Performs schedule operations
+///
+/// # Arguments
+///
+/// * `command` - Schedule operation from the command line
+///
+/// # Errors
+///
+/// Returns an error if the time or days are invalid or `pmset` fails.
+pub fn perform(command: PowerScheduleCommands) -> Result<(), Box<dyn Error>> {
+    match command {
+        PowerScheduleCommands::Show => show(),
+        PowerScheduleCommands::Wake {
+            time,
+            days,
+            yes,
+            dry_run,
+        } => set("wakeorpoweron", &time, &days, yes, dry_run),
+        PowerScheduleCommands::Sleep {
+            time,
+            days,
+            yes,
+            dry_run,
+        } => set("sleep", &time, &days, yes, dry_run),
+        PowerScheduleCommands::Clear { yes, dry_run } => apply(&["repeat", "cancel"], yes, dry_run),
+    }
+}
+
+/// Prints the repeating events and the upcoming one-time events
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails or its output cannot be parsed.
+fn show() -> Result<(), Box<dyn Error>> {
+    let output = pmset(&["-g", "sched"])?;
+    let events = parse_repeating(&output)?;
+    if events.is_empty() {
+        println!("No repeating power events");
+    } else {
+        println!("{}", "Repeating".bold());
+        for event in &events {
+            println!(
+                "  {:<18}{:02}:{:02}  {}",
+                describe_kind(&event.kind),
+                event.hour,
+                event.minute,
+                describe_days(event.days)
+            );
+        }
+    }
+
+    let scheduled = parse_scheduled(&output);
+    if !scheduled.is_empty() {
+        println!("{}", "Scheduled".bold());
+        for event in scheduled {
+            println!("  {event}");
+        }
+    }
+    Ok(())
+}
+
+/// Sets the repeating wake or sleep event, keeping the other one
+///
+/// # Arguments
+///
+/// * `kind` - Event type for `pmset repeat`
+/// * `time` - Time of the day as `HH:MM`
+/// * `days` - Days the event repeats on
+/// * `yes` - Apply without asking
+/// * `dry_run` - Only print the `pmset` command
+///
+/// # Errors
+///
+/// Returns an error if the time or days are invalid, the current schedule
+/// cannot be parsed or `pmset` fails.
+fn set(kind: &str, time: &str, days: &str, yes: bool, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let (hour, minute) = parse_time(time)?;
+    let event = RepeatEvent {
+        kind: kind.to_string(),
+        days: parse_days(days)?,
+        hour,
+        minute,
+    };
+
+    let mut events: Vec<RepeatEvent> = parse_repeating(&pmset(&["-g", "sched"])?)?
+        .into_iter()
+        .filter(|existing| existing.powers_on() != event.powers_on())
+        .collect();
+    events.push(event);
+    // pmset expects the power on event first
+    events.sort_by_key(|event| !event.powers_on());
+
+    let mut args = vec!["repeat".to_string()];
+    args.extend(events.iter().flat_map(RepeatEvent::pmset_args));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    apply(&args, yes, dry_run)
+}
+
+/// Previews a `pmset` command and runs it with sudo after confirmation
+///
+/// The confirmation is skipped with `yes`, which is required without a terminal.
+///
+/// # Errors
+///
+/// Returns an error if stdin cannot be read or `pmset` fails.
+fn apply(args: &[&str], yes: bool, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let mut command = vec!["pmset"];
+    command.extend(args);
+    if dry_run {
+        print_dry_run(&command);
+        return Ok(());
+    }
+
+    println!("sudo {}", command.join(" "));
+    if !yes && !confirm("Apply?")? {
+        println!("Schedule not changed");
+        return Ok(());
+    }
+
+    print!("Update power schedule");
+    io::stdout().flush()?;
+    if let Err(e) = pmset_sudo(args) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Parses the days an event repeats on into a bit mask
+///
+/// # Errors
+///
+/// Returns an error if a day is unknown or no day is given.
+fn parse_days(days: &str) -> Result<u8, Box<dyn Error>> {
+    let invalid =
+        || format!("Invalid days '{days}', expected daily, weekdays, weekends, letters of MTWRFSU or e.g. mon,wed,fri");
+    match days.to_lowercase().as_str() {
+        "daily" | "everyday" | "every-day" => return Ok(EVERY_DAY),
+        "weekdays" => return Ok(WEEKDAYS),
+        "weekends" => return Ok(WEEKENDS),
+        _ => {}
+    }
+
+    let mut mask = 0;
+    if days.contains(',') || days.len() > DAY_LETTERS.len() || days.chars().any(|c| c.is_lowercase()) {
+        for name in days.split(',').map(str::trim) {
+            let index = DAY_NAMES
+                .iter()
+                .position(|day| name.get(..3).is_some_and(|prefix| day.eq_ignore_ascii_case(prefix)))
+                .ok_or_else(invalid)?;
+            mask |= 1 << index;
+        }
+    } else {
+        for letter in days.chars() {
+            let index = DAY_LETTERS.iter().position(|&day| day == letter).ok_or_else(invalid)?;
+            mask |= 1 << index;
+        }
+    }
+    if mask == 0 {
+        return Err(invalid().into());
+    }
+    Ok(mask)
+}
+
+/// Returns the pmset day letters of a bit mask, e.g. `MTWRF`
+fn day_letters(days: u8) -> String {
+    DAY_LETTERS
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| days & (1 << index) != 0)
+        .map(|(_, letter)| letter)
+        .collect()
+}
+
+/// Describes the days of a bit mask, e.g. `weekdays` or `Mon, Wed, Fri`
+fn describe_days(days: u8) -> String {
+    match days {
+        EVERY_DAY => "every day".to_string(),
+        WEEKDAYS => "weekdays".to_string(),
+        WEEKENDS => "weekends".to_string(),
+        _ => DAY_NAMES
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| days & (1 << index) != 0)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Describes an event type, e.g. `Wake or power on`
+fn describe_kind(kind: &str) -> &str {
+    match kind {
+        "wake" => "Wake",
+        "poweron" => "Power on",
+        "wakeorpoweron" => "Wake or power on",
+        "sleep" => "Sleep",
+        "shutdown" => "Shut down",
+        "restart" => "Restart",
+        other => other,
+    }
+}
+
+/// Parses the repeating events of `pmset -g sched`
+///
+/// Lines look like `wakepoweron at 7:30AM weekdays only` or
+/// `sleep at 11:00PM every day`.
+///
+/// # Errors
+///
+/// Returns an error naming the first line that cannot be parsed, as `set`
+/// would otherwise drop that event from the schedule.
+fn parse_repeating(output: &str) -> Result<Vec<RepeatEvent>, Box<dyn Error>> {
+    section(output, "Repeating power events:")
+        .iter()
+        .map(|line| parse_event(line).ok_or_else(|| format!("Cannot read the repeating power event '{line}'").into()))
+        .collect()
+}
+
+/// Parses a repeating event line of `pmset -g sched`
+fn parse_event(line: &str) -> Option<RepeatEvent> {
+    let (kind, rest) = line.split_once(" at ")?;
+    let (time, days) = rest.split_once(' ').unwrap_or((rest, ""));
+    // A time with a separate AM/PM marker, e.g. `7:30 AM`
+    let (time, days) = match days.split_once(' ') {
+        Some((marker, days)) if marker.eq_ignore_ascii_case("am") || marker.eq_ignore_ascii_case("pm") => {
+            (format!("{time}{marker}"), days)
+        }
+        _ if days.eq_ignore_ascii_case("am") || days.eq_ignore_ascii_case("pm") => (format!("{time}{days}"), ""),
+        _ => (time.to_string(), days),
+    };
+    let (hour, minute) = parse_clock(&time)?;
+    // `pmset -g sched` prints `wakepoweron`, `pmset repeat` expects `wakeorpoweron`
+    let kind = match kind.trim() {
+        "wakepoweron" => "wakeorpoweron",
+        kind => kind,
+    };
+    Some(RepeatEvent {
+        kind: kind.to_string(),
+        days: parse_sched_days(days)?,
+        hour,
+        minute,
+    })
+}
+
+/// Parses the upcoming one-time events of `pmset -g sched`
+///
+/// The index prefix (e.g. `[0]`) is removed from every line.
+fn parse_scheduled(output: &str) -> Vec<String> {
+    section(output, "Scheduled power events:")
+        .iter()
+        .map(
+            |line| match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+                Some((_, event)) => event.trim().to_string(),
+                None => line.to_string(),
+            },
+        )
+        .collect()
+}
+
+/// Returns the trimmed lines of a section of `pmset -g sched`
+///
+/// A section starts with its heading and ends at the next unindented line.
+fn section<'a>(output: &'a str, heading: &str) -> Vec<&'a str> {
+    output
+        .lines()
+        .skip_while(|line| line.trim() != heading)
+        .skip(1)
+        .take_while(|line| line.starts_with(char::is_whitespace))
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Parses a time printed by pmset, e.g. `7:30AM`, `11:00PM` or `23:00:00`
+fn parse_clock(time: &str) -> Option<(u8, u8)> {
+    let upper = time.to_uppercase();
+    let (clock, offset) = match (upper.strip_suffix("AM"), upper.strip_suffix("PM")) {
+        (Some(clock), _) => (clock, Some(0)),
+        (_, Some(clock)) => (clock, Some(12)),
+        _ => (upper.as_str(), None),
+    };
+    let mut parts = clock.trim().split(':');
+    let hour: u8 = parts.next()?.parse().ok()?;
+    let minute: u8 = parts.next()?.parse().ok()?;
+    let hour = match offset {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        None => hour,
+    };
+    (hour <= 23 && minute <= 59).then_some((hour, minute))
+}
+
+/// Parses the days printed by pmset, e.g. `weekdays only`, `every day` or `MWF`
+fn parse_sched_days(days: &str) -> Option<u8> {
+    match days.trim().to_lowercase().as_str() {
+        "" | "every day" => Some(EVERY_DAY),
+        "weekdays only" => Some(WEEKDAYS),
+        "weekends only" => Some(WEEKENDS),
+        other if other.contains(' ') => parse_days(&other.split_whitespace().collect::<Vec<_>>().join(",")).ok(),
+        _ => parse_days(days.trim()).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of `pmset -g sched` with both repeating events and an alarm
+    const SCHED: &str = "Repeating power events:
+  wakepoweron at 7:30AM weekdays only
+  sleep at 11:00PM every day
+Scheduled power events:
+ [0]  wake at 01/02/2026 07:30:00 by 'com.apple.alarm'
+";
+
+    fn event(kind: &str, days: u8, hour: u8, minute: u8) -> RepeatEvent {
+        RepeatEvent {
+            kind: kind.to_string(),
+            days,
+            hour,
+            minute,
+        }
+    }
+
+    #[test]
+    fn parse_repeating_reads_both_events() {
+        let events = parse_repeating(SCHED).unwrap();
+        assert_eq!(
+            events,
+            [
+                event("wakeorpoweron", WEEKDAYS, 7, 30),
+                event("sleep", EVERY_DAY, 23, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_repeating_reads_separate_markers_and_day_letters() {
+        let output = "Repeating power events:\n  wake at 7:30 AM MWF\n  shutdown at 12:00 AM\n";
+        let events = parse_repeating(output).unwrap();
+        assert_eq!(
+            events,
+            [event("wake", 0b10101, 7, 30), event("shutdown", EVERY_DAY, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn parse_repeating_rejects_unknown_lines() {
+        let output = "Repeating power events:\n  wakepoweron at 7:30AM weekdays only\n  sleep at noon\n";
+        let error = parse_repeating(output).unwrap_err().to_string();
+        assert!(error.contains("sleep at noon"), "{error}");
+    }
+
+    #[test]
+    fn parse_repeating_without_events() {
+        let output = "Scheduled power events:\n [0]  wake at 01/02/2026 07:30:00 by 'com.apple.alarm'\n";
+        assert!(parse_repeating(output).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_scheduled_drops_the_index() {
+        assert_eq!(
+            parse_scheduled(SCHED),
+            ["wake at 01/02/2026 07:30:00 by 'com.apple.alarm'"]
+        );
+    }
+
+    #[test]
+    fn parse_clock_converts_to_24_hours() {
+        assert_eq!(parse_clock("7:30AM"), Some((7, 30)));
+        assert_eq!(parse_clock("12:00AM"), Some((0, 0)));
+        assert_eq!(parse_clock("12:30PM"), Some((12, 30)));
+        assert_eq!(parse_clock("11:00PM"), Some((23, 0)));
+        assert_eq!(parse_clock("23:00:00"), Some((23, 0)));
+    }
+
+    #[test]
+    fn parse_clock_rejects_invalid_times() {
+        assert_eq!(parse_clock("13:00PM"), None);
+        assert_eq!(parse_clock("0:30AM"), None);
+        assert_eq!(parse_clock("24:00"), None);
+        assert_eq!(parse_clock("7"), None);
+    }
+
+    #[test]
+    fn parse_days_accepts_names_and_letters() {
+        assert_eq!(parse_days("MWF").unwrap(), 0b10101);
+        assert_eq!(parse_days("mon,wed,fri").unwrap(), 0b10101);
+        assert_eq!(parse_days("weekdays").unwrap(), WEEKDAYS);
+        assert_eq!(parse_days("weekends").unwrap(), WEEKENDS);
+        assert_eq!(parse_days("daily").unwrap(), EVERY_DAY);
+    }
+
+    #[test]
+    fn parse_days_rejects_unknown_days() {
+        assert!(parse_days("xyz").is_err());
+        assert!(parse_days("").is_err());
+    }
+
+    #[test]
+    fn day_letters_follow_pmset() {
+        assert_eq!(day_letters(WEEKDAYS), "MTWRF");
+        assert_eq!(day_letters(WEEKENDS), "SU");
+    }
+}
@@ -0,0 +1,141 @@
+//! Scheduled shutdown and restart
+//!
+//! Wraps `shutdown` so a one-off shutdown or restart can be scheduled at a time
+//! of the day or after a delay without remembering its syntax. The time is
+//! converted to minutes from now (`shutdown -h +MINUTES`); `shutdown` then waits
+//! in the background, warns logged-in users and can be cancelled until the time
+//! has come. With `--graceful`, all apps are asked to quit first and the
+//! shutdown or restart happens right away once they have.
+//!
+//! # System Commands Used
+//!
+//! - `shutdown -h` / `shutdown -r` - Schedule the shutdown or restart (with sudo)
+//! - `pgrep -x shutdown` - Find a scheduled shutdown
+//! - `killall shutdown` - Cancel a scheduled shutdown (with sudo)
+//! - `date` - Read the local time
+
+use super::graceful;
+use crate::cli::{PowerShutdownArgs, PowerShutdownCommands};
+use crate::util::{confirm, format_minutes, parse_time};
+use colored::Colorize;
+use std::error::Error;
+use std::process::{Command, Stdio};
+
+/// Seconds of a day
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+/// Schedules or cancels a shutdown or restart
+///
+/// # Arguments
+///
+/// * `args` - Shutdown arguments from the command line
+/// * `restart` - True to restart instead of shutting down
+///
+/// # Errors
+///
+/// Returns an error if the time is invalid or `shutdown` fails.
+pub fn perform(args: PowerShutdownArgs, restart: bool) -> Result<(), Box<dyn Error>> {
+    if let Some(PowerShutdownCommands::Cancel) = args.command {
+        return cancel();
+    }
+    if args.graceful {
+        return graceful::perform(&args, restart);
+    }
+
+    let now = local_seconds()?;
+    let seconds = match (&args.at, args.delay) {
+        (Some(time), _) => {
+            let (hour, minute) = parse_time(time)?;
+            let target = u64::from(hour) * 3600 + u64::from(minute) * 60;
+            // The next occurrence of the time, tomorrow if it has passed today
+            (target + DAY_SECONDS - now - 1) % DAY_SECONDS + 1
+        }
+        (None, Some(delay)) => delay.as_secs(),
+        (None, None) => return Err("Missing --at or --in".into()),
+    };
+    let minutes = seconds.div_ceil(60).max(1);
+    let at = (now + minutes * 60) % DAY_SECONDS;
+    let action = if restart { "Restart" } else { "Shut down" };
+
+    if !args.yes
+        && !confirm(&format!(
+            "{action} the Mac at {:02}:{:02} (in {})?",
+            at / 3600,
+            at / 60 % 60,
+            format_minutes(minutes)
+        ))?
+    {
+        println!("Nothing scheduled");
+        return Ok(());
+    }
+
+    let status = Command::new("sudo")
+        .args(["shutdown", if restart { "-r" } else { "-h" }, &format!("+{minutes}")])
+        // shutdown keeps waiting in the background, its output must not be waited for
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !status.success() {
+        return Err(format!("shutdown failed ({status})").into());
+    }
+
+    println!(
+        "{} at {:02}:{:02}, cancel with `rem power {} cancel`",
+        format!("{action} scheduled").green(),
+        at / 3600,
+        at / 60 % 60,
+        if restart { "restart" } else { "shutdown" }
+    );
+    Ok(())
+}
+
+/// Cancels a scheduled shutdown or restart
+///
+/// # Errors
+///
+/// Returns an error if `killall` fails.
+fn cancel() -> Result<(), Box<dyn Error>> {
+    let scheduled = Command::new("pgrep")
+        .args(["-x", "shutdown"])
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !scheduled {
+        println!("No shutdown or restart is scheduled");
+        return Ok(());
+    }
+
+    let output = Command::new("sudo")
+        .args(["killall", "shutdown"])
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("killall failed: {}", stderr.trim()).into());
+    }
+    println!("{}", "Scheduled shutdown cancelled".green());
+    Ok(())
+}
+
+/// Returns the seconds since midnight in local time
+///
+/// # Errors
+///
+/// Returns an error if `date` cannot be run or its output is malformed.
+fn local_seconds() -> Result<u64, Box<dyn Error>> {
+    let output = Command::new("date")
+        .arg("+%H:%M:%S")
+        .output()
+        .map_err(|e| format!("Failed to run date: {e}"))?;
+    let time = String::from_utf8_lossy(&output.stdout);
+    let parts: Vec<u64> = time
+        .trim()
+        .split(':')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Unexpected output of date: {}", time.trim()))?;
+    match parts[..] {
+        [hour, minute, second] => Ok(hour * 3600 + minute * 60 + second),
+        _ => Err(format!("Unexpected output of date: {}", time.trim()).into()),
+    }
+}
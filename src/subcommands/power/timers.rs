@@ -0,0 +1,179 @@
+//! Sleep timers
+//!
+//! Shows and sets the minutes of inactivity after which the display sleeps, the
+//! system sleeps and disks spin down. Each power source has its own timers; the
+//! timers of battery and power adapter are shown side by side.
+//!
+//! A display sleep timer longer than the system sleep timer has no effect, so
+//! such a combination is rejected before anything is changed.
+//!
+//! # System Commands Used
+//!
+//! - `pmset -g custom` - Read the timers of each power source
+//! - `pmset -g ps` - Read the current power source
+//! - `pmset displaysleep|sleep|disksleep` - Set the timers (with sudo)
+
+use super::{Profile, current_source, pmset_sudo, profiles, source_label, source_option};
+use crate::cli::{PowerTimersArgs, PowerTimersCommands};
+use crate::util::format_minutes;
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Timers with their `pmset` setting and label
+const TIMERS: [(&str, &str); 3] = [
+    ("displaysleep", "Display sleep"),
+    ("sleep", "System sleep"),
+    ("disksleep", "Disk sleep"),
+];
+
+/// Shows or sets the sleep timers
+///
+/// # Arguments
+///
+/// * `args` - Timer arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails or the new timers are inconsistent.
+pub fn perform(args: PowerTimersArgs) -> Result<(), Box<dyn Error>> {
+    let changes: Vec<(&str, u32)> = [
+        ("displaysleep", args.display),
+        ("sleep", args.sleep),
+        ("disksleep", args.disk),
+    ]
+    .into_iter()
+    .filter_map(|(setting, minutes)| Some((setting, minutes?)))
+    .collect();
+
+    match args.command {
+        Some(PowerTimersCommands::Show) => show(),
+        None if changes.is_empty() => show(),
+        None => set(&changes, &args),
+    }
+}
+
+/// Prints the timers of all power sources side by side
+///
+/// # Errors
+///
+/// Returns an error if `pmset` fails.
+fn show() -> Result<(), Box<dyn Error>> {
+    let profiles = profiles()?;
+    if profiles.is_empty() {
+        return Err("pmset reported no power management settings".into());
+    }
+    let current = current_source();
+
+    let headers: Vec<String> = profiles
+        .iter()
+        .map(|profile| {
+            let name = profile.source.strip_suffix(" Power").unwrap_or(&profile.source);
+            if current.as_deref() == Some(profile.source.as_str()) {
+                format!("{name} (current)")
+            } else {
+                name.to_string()
+            }
+        })
+        .collect();
+    let width = headers.iter().map(String::len).max().unwrap_or_default().max(8) + 2;
+
+    print!("{:<16}", "");
+    for header in &headers {
+        print!("{}", format!("{header:<width$}").bold());
+    }
+    println!();
+    for (setting, label) in TIMERS {
+        print!("{:<16}", label.bold());
+        for profile in &profiles {
+            print!("{:<width$}", format_timer(timer(profile, setting)));
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Sets timers of the selected power sources
+///
+/// # Arguments
+///
+/// * `changes` - Settings and their new minutes
+/// * `args` - Timer arguments with the selected power sources
+///
+/// # Errors
+///
+/// Returns an error if the display would sleep after the system, the Mac has
+/// no battery but the battery timers are to be changed, or `pmset` fails.
+fn set(changes: &[(&str, u32)], args: &PowerTimersArgs) -> Result<(), Box<dyn Error>> {
+    let profiles = profiles()?;
+    if args.source.battery && !profiles.iter().any(|profile| profile.source == "Battery Power") {
+        return Err("This Mac has no battery".into());
+    }
+
+    let affected = profiles.iter().filter(|profile| match profile.source.as_str() {
+        "Battery Power" => !args.source.ac,
+        "AC Power" => !args.source.battery,
+        _ => !args.source.battery && !args.source.ac,
+    });
+    for profile in affected {
+        let value = |setting: &str| {
+            changes
+                .iter()
+                .find(|(name, _)| *name == setting)
+                .map(|(_, minutes)| *minutes)
+                .or_else(|| timer(profile, setting))
+        };
+        if let (Some(display), Some(sleep)) = (value("displaysleep"), value("sleep"))
+            && display > 0
+            && sleep > 0
+            && display > sleep
+        {
+            return Err(format!(
+                "Display sleep ({display} min) must not be longer than system sleep ({sleep} min) on {}",
+                profile.source
+            )
+            .into());
+        }
+    }
+
+    let values: Vec<String> = changes.iter().map(|(_, minutes)| minutes.to_string()).collect();
+    let mut command = vec![source_option(&args.source)];
+    for ((setting, _), value) in changes.iter().zip(&values) {
+        command.extend([*setting, value.as_str()]);
+    }
+
+    let summary: Vec<String> = changes
+        .iter()
+        .map(|(setting, minutes)| {
+            let label = TIMERS
+                .iter()
+                .find(|(name, _)| name == setting)
+                .map_or(*setting, |(_, label)| label);
+            format!("{} {}", label.to_lowercase(), format_timer(Some(*minutes)))
+        })
+        .collect();
+    print!("Set {}{}", summary.join(", "), source_label(&args.source));
+    io::stdout().flush()?;
+    if let Err(e) = pmset_sudo(&command) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Returns the minutes of a timer of a power source
+///
+/// Values may be followed by a note, e.g. `1 (sleep prevented by caffeinate)`.
+fn timer(profile: &Profile, setting: &str) -> Option<u32> {
+    profile.settings.get(setting)?.split_whitespace().next()?.parse().ok()
+}
+
+/// Formats the minutes of a timer, e.g. `10 min` or `never`
+fn format_timer(minutes: Option<u32>) -> String {
+    match minutes {
+        Some(0) => "never".to_string(),
+        Some(minutes) => format_minutes(u64::from(minutes)),
+        None => "-".to_string(),
+    }
+}
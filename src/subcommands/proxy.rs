@@ -0,0 +1,396 @@
+//! Proxy configuration subcommand
+//!
+//! Gets and sets the web (HTTP), secure web (HTTPS) and SOCKS proxies and the
+//! proxy auto-configuration (PAC) URL of network services. Like DNS changes, proxy
+//! changes apply to all active network services unless restricted with
+//! `--interface`, honor the `--exclude` patterns and the `exclude` list of the
+//! `[dns]` configuration, and can be previewed with `--dry-run`.
+//!
+//! Named proxy settings are stored as presets in the `[proxy.presets]` table of
+//! the configuration file, see [`presets`].
+//!
+//! # System Commands Used
+//!
+//! - `networksetup -getwebproxy` / `-getsecurewebproxy` / `-getsocksfirewallproxy` - Read proxies
+//! - `networksetup -getautoproxyurl` - Read the PAC URL
+//! - `networksetup -setwebproxy` / `-setsecurewebproxy` / `-setsocksfirewallproxy` - Set and enable proxies
+//! - `networksetup -setautoproxyurl` - Set and enable the PAC URL
+//! - `networksetup -set...state <SERVICE> off` - Disable proxies
+
+pub mod presets;
+
+use crate::cli::{ProxyArgs, ProxyCommands};
+use crate::config::ProxyPreset;
+use crate::subcommands::dns::backend::backend;
+use crate::subcommands::dns::select_networks;
+use crate::util::{print_dry_run, run_sudo};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::process::Command;
+
+/// Kinds of proxies configurable per network service
+#[derive(Clone, Copy)]
+enum ProxyKind {
+    /// Web (HTTP) proxy
+    Web,
+    /// Secure web (HTTPS) proxy
+    SecureWeb,
+    /// SOCKS proxy
+    Socks,
+    /// Proxy auto-configuration URL
+    Pac,
+}
+
+impl ProxyKind {
+    /// All proxy kinds in display order
+    const ALL: [ProxyKind; 4] = [ProxyKind::Web, ProxyKind::SecureWeb, ProxyKind::Socks, ProxyKind::Pac];
+
+    /// Returns the human-readable name of the proxy kind
+    fn label(self) -> &'static str {
+        match self {
+            ProxyKind::Web => "web proxy",
+            ProxyKind::SecureWeb => "secure web proxy",
+            ProxyKind::Socks => "SOCKS proxy",
+            ProxyKind::Pac => "auto proxy URL",
+        }
+    }
+
+    /// Returns the `networksetup` option reading the setting
+    fn get_option(self) -> &'static str {
+        match self {
+            ProxyKind::Web => "-getwebproxy",
+            ProxyKind::SecureWeb => "-getsecurewebproxy",
+            ProxyKind::Socks => "-getsocksfirewallproxy",
+            ProxyKind::Pac => "-getautoproxyurl",
+        }
+    }
+
+    /// Returns the `networksetup` option setting and enabling the proxy
+    fn set_option(self) -> &'static str {
+        match self {
+            ProxyKind::Web => "-setwebproxy",
+            ProxyKind::SecureWeb => "-setsecurewebproxy",
+            ProxyKind::Socks => "-setsocksfirewallproxy",
+            ProxyKind::Pac => "-setautoproxyurl",
+        }
+    }
+
+    /// Returns the `networksetup` option enabling or disabling the proxy
+    fn state_option(self) -> &'static str {
+        match self {
+            ProxyKind::Web => "-setwebproxystate",
+            ProxyKind::SecureWeb => "-setsecurewebproxystate",
+            ProxyKind::Socks => "-setsocksfirewallproxystate",
+            ProxyKind::Pac => "-setautoproxystate",
+        }
+    }
+}
+
+/// Proxy settings of one network service for JSON output; disabled proxies are `null`
+#[derive(Serialize)]
+struct ProxyStatus {
+    /// Name of the network service
+    interface: String,
+    /// Web (HTTP) proxy as `HOST:PORT`
+    web: Option<String>,
+    /// Secure web (HTTPS) proxy as `HOST:PORT`
+    secure_web: Option<String>,
+    /// SOCKS proxy as `HOST:PORT`
+    socks: Option<String>,
+    /// URL of the proxy auto-configuration file
+    pac: Option<String>,
+}
+
+/// Performs proxy configuration operations based on the provided arguments
+///
+/// Without an action, the proxy settings of the target network services are
+/// listed. With `--dry-run`, changes are not applied; the commands that would be
+/// run are printed instead.
+///
+/// # Arguments
+///
+/// * `args` - Proxy operation arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a preset does not exist, network commands fail, or the
+/// update of a network service fails.
+pub fn perform(args: ProxyArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(command) = args.command {
+        return match command {
+            ProxyCommands::Presets { command } => presets::perform(command),
+        };
+    }
+
+    // Resolve the preset before touching any network configuration
+    let preset = args.preset.as_deref().map(presets::preset).transpose()?;
+    let networks = select_networks(backend(), &args.interfaces, &args.excludes)?;
+
+    let changes: Vec<(ProxyKind, Option<String>)> = if args.off {
+        ProxyKind::ALL.iter().map(|&kind| (kind, None)).collect()
+    } else if let Some(preset) = &preset {
+        // A preset describes the complete proxy configuration
+        ProxyKind::ALL
+            .iter()
+            .map(|&kind| (kind, preset_value(preset, kind).cloned()))
+            .collect()
+    } else {
+        let servers = &args.servers;
+        [
+            (ProxyKind::Web, &servers.web),
+            (ProxyKind::SecureWeb, &servers.secure_web),
+            (ProxyKind::Socks, &servers.socks),
+            (ProxyKind::Pac, &servers.pac),
+        ]
+        .into_iter()
+        .filter_map(|(kind, value)| value.clone().map(|value| (kind, Some(value))))
+        .collect()
+    };
+
+    if changes.is_empty() {
+        return if args.json {
+            print_proxies_json(&networks)
+        } else {
+            print_proxies(&networks)
+        };
+    }
+
+    apply_proxies(&networks, &changes, args.dry_run)
+}
+
+/// Returns the value of a proxy kind in a preset
+fn preset_value(preset: &ProxyPreset, kind: ProxyKind) -> Option<&String> {
+    match kind {
+        ProxyKind::Web => preset.web.as_ref(),
+        ProxyKind::SecureWeb => preset.secure_web.as_ref(),
+        ProxyKind::Socks => preset.socks.as_ref(),
+        ProxyKind::Pac => preset.pac.as_ref(),
+    }
+}
+
+/// Prints the proxy settings of the given network services
+///
+/// # Errors
+///
+/// Returns an error if a setting cannot be read.
+fn print_proxies(networks: &[String]) -> Result<(), Box<dyn Error>> {
+    for network in networks {
+        println!("{}", network.bold());
+        for kind in ProxyKind::ALL {
+            let value = match current_proxy(network, kind)? {
+                Some(value) => value.green(),
+                None => "off".normal(),
+            };
+            println!("  {:<17} {value}", kind.label());
+        }
+    }
+    Ok(())
+}
+
+/// Prints the proxy settings of the given network services as JSON
+///
+/// # Errors
+///
+/// Returns an error if a setting cannot be read or serialized.
+fn print_proxies_json(networks: &[String]) -> Result<(), Box<dyn Error>> {
+    let statuses = networks
+        .iter()
+        .map(|network| {
+            Ok(ProxyStatus {
+                interface: network.clone(),
+                web: current_proxy(network, ProxyKind::Web)?,
+                secure_web: current_proxy(network, ProxyKind::SecureWeb)?,
+                socks: current_proxy(network, ProxyKind::Socks)?,
+                pac: current_proxy(network, ProxyKind::Pac)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    println!("{}", serde_json::to_string_pretty(&statuses)?);
+    Ok(())
+}
+
+/// Applies proxy changes to the given network services
+///
+/// All network services are attempted before an error is returned.
+///
+/// # Arguments
+///
+/// * `networks` - Names of the network services to configure
+/// * `changes` - Proxy kinds to change with their new value; `None` disables the proxy
+/// * `dry_run` - Only print the `networksetup` invocations instead of running them
+///
+/// # Errors
+///
+/// Returns an error if sudo credentials cannot be obtained or the update of any
+/// network service fails.
+fn apply_proxies(
+    networks: &[String],
+    changes: &[(ProxyKind, Option<String>)],
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let commands: Vec<(&String, Vec<Vec<String>>)> = networks
+        .iter()
+        .map(|network| {
+            let commands = changes
+                .iter()
+                .map(|(kind, value)| networksetup_args(network, *kind, value.as_deref()))
+                .collect();
+            (network, commands)
+        })
+        .collect();
+
+    if dry_run {
+        for (_, commands) in &commands {
+            for command in commands {
+                let command: Vec<&str> = command.iter().map(String::as_str).collect();
+                print_dry_run(&command);
+            }
+        }
+        return Ok(());
+    }
+    if networks.is_empty() {
+        return Ok(());
+    }
+
+    for (kind, value) in changes {
+        match value {
+            Some(value) => println!("Set {} to {}", kind.label(), value.bold()),
+            None => println!("Disable {}", kind.label()),
+        }
+    }
+
+    // Ask for the password once instead of once per command
+    if io::stdin().is_terminal() {
+        run_sudo(&["-v"])?;
+    }
+
+    let width = networks.iter().map(String::len).max().unwrap_or(0).max("NETWORK".len());
+    println!();
+    println!("{:<width$}  RESULT", "NETWORK");
+    let mut failures = 0;
+    for (network, commands) in &commands {
+        let result = commands.iter().try_for_each(|command| networksetup_sudo(command));
+        let status = match result {
+            Ok(()) => "OK".green(),
+            Err(e) => {
+                failures += 1;
+                e.to_string().red()
+            }
+        };
+        println!("{network:<width$}  {status}");
+    }
+
+    if failures > 0 {
+        return Err(format!("Failed to update {failures} of {} network service(s)", networks.len()).into());
+    }
+
+    Ok(())
+}
+
+/// Builds the `networksetup` invocation changing a proxy of a network service
+///
+/// # Arguments
+///
+/// * `network` - Name of the network service
+/// * `kind` - Proxy to change
+/// * `value` - `HOST:PORT` of the proxy, or the PAC URL; `None` disables the proxy
+fn networksetup_args(network: &str, kind: ProxyKind, value: Option<&str>) -> Vec<String> {
+    let mut args = vec!["networksetup".to_string()];
+    match (kind, value) {
+        (ProxyKind::Pac, Some(url)) => args.extend([kind.set_option(), network, url].map(str::to_string)),
+        (_, Some(server)) => {
+            let (host, port) = split_server(server);
+            args.extend([kind.set_option(), network, host, port].map(str::to_string));
+        }
+        (_, None) => args.extend([kind.state_option(), network, "off"].map(str::to_string)),
+    }
+    args
+}
+
+/// Splits a validated `HOST:PORT` into host and port, removing IPv6 brackets
+fn split_server(server: &str) -> (&str, &str) {
+    let (host, port) = server.rsplit_once(':').unwrap_or((server, ""));
+    let host = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host);
+    (host, port)
+}
+
+/// Reads the current setting of a proxy of a network service
+///
+/// # Returns
+///
+/// `HOST:PORT` of the proxy, or the PAC URL; `None` if the proxy is disabled.
+///
+/// # Errors
+///
+/// Returns an error if `networksetup` fails.
+fn current_proxy(network: &str, kind: ProxyKind) -> Result<Option<String>, Box<dyn Error>> {
+    let output = Command::new("networksetup")
+        .args([kind.get_option(), network])
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to read the {} of '{network}': {}", kind.label(), stderr.trim()).into());
+    }
+
+    // Output lines are "Key: Value", e.g. "Enabled: Yes", "Server: proxy", "Port: 3128" or "URL: http://..."
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: BTreeMap<&str, &str> = stdout
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    if fields.get("Enabled") != Some(&"Yes") {
+        return Ok(None);
+    }
+
+    Ok(match kind {
+        ProxyKind::Pac => fields.get("URL").map(|url| url.to_string()),
+        _ => fields.get("Server").map(|server| {
+            let port = fields.get("Port").unwrap_or(&"0");
+            if server.contains(':') {
+                format!("[{server}]:{port}")
+            } else {
+                format!("{server}:{port}")
+            }
+        }),
+    })
+}
+
+/// Returns the enabled proxies of a network service
+///
+/// # Returns
+///
+/// The label and value of every enabled proxy, e.g. `("web proxy", "proxy:3128")`.
+///
+/// # Errors
+///
+/// Returns an error if a setting cannot be read.
+pub fn enabled_proxies(network: &str) -> Result<Vec<(&'static str, String)>, Box<dyn Error>> {
+    let mut proxies = Vec::new();
+    for kind in ProxyKind::ALL {
+        if let Some(value) = current_proxy(network, kind)? {
+            proxies.push((kind.label(), value));
+        }
+    }
+    Ok(proxies)
+}
+
+/// Runs `networksetup` with sudo and checks that it succeeded
+///
+/// `networksetup` reports some errors on stdout with exit code 0, so its
+/// output is checked as well.
+///
+/// # Errors
+///
+/// Returns an error containing the command's output if it fails.
+fn networksetup_sudo(command: &[String]) -> Result<(), Box<dyn Error>> {
+    let stdout = run_sudo(command)?;
+    if stdout.contains("Error") {
+        return Err(stdout.trim().into());
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! Named proxy presets
+//!
+//! Presets are stored in the `[proxy.presets]` table of the rempower
+//! configuration file and can be applied with `rem proxy --preset <NAME>`.
+
+use crate::cli::{PresetsCommands, ProxyServers};
+use crate::config::{Config, ProxyPreset};
+use colored::Colorize;
+use std::error::Error;
+
+/// Performs preset management operations
+///
+/// # Arguments
+///
+/// * `command` - Preset operation from the command line
+///
+/// # Errors
+///
+/// Returns an error if the configuration file cannot be read or written, a
+/// preset to add has no proxies, or a preset to remove does not exist.
+pub fn perform(command: PresetsCommands) -> Result<(), Box<dyn Error>> {
+    match command {
+        PresetsCommands::List => list_presets(),
+        PresetsCommands::Add { name, servers } => add_preset(name, servers),
+        PresetsCommands::Remove { name } => remove_preset(&name),
+    }
+}
+
+/// Looks up a preset
+///
+/// # Arguments
+///
+/// * `name` - Name of the preset
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or the preset does not exist.
+pub fn preset(name: &str) -> Result<ProxyPreset, Box<dyn Error>> {
+    let config = Config::load()?;
+    match config.proxy.presets.get(name) {
+        Some(preset) => Ok(preset.clone()),
+        None => Err(unknown_preset(name, &config)),
+    }
+}
+
+/// Prints all configured presets with their proxies
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded.
+fn list_presets() -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+
+    if config.proxy.presets.is_empty() {
+        println!("No proxy presets configured. Add one with 'rem proxy presets add <NAME> --web <HOST:PORT>'");
+        return Ok(());
+    }
+
+    for (name, preset) in &config.proxy.presets {
+        let proxies: Vec<String> = [
+            ("web", &preset.web),
+            ("secure web", &preset.secure_web),
+            ("socks", &preset.socks),
+            ("pac", &preset.pac),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.as_ref().map(|value| format!("{label} {value}")))
+        .collect();
+        println!("{name:>30} : {}", proxies.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Adds a preset, replacing an existing preset of the same name
+///
+/// # Errors
+///
+/// Returns an error if no proxy is given or the configuration cannot be loaded or saved.
+fn add_preset(name: String, servers: ProxyServers) -> Result<(), Box<dyn Error>> {
+    let preset = ProxyPreset {
+        web: servers.web,
+        secure_web: servers.secure_web,
+        socks: servers.socks,
+        pac: servers.pac,
+    };
+    if preset.web.is_none() && preset.secure_web.is_none() && preset.socks.is_none() && preset.pac.is_none() {
+        return Err("A proxy preset needs at least one of --web, --secure-web, --socks or --pac".into());
+    }
+
+    let mut config = Config::load()?;
+    let replaced = config.proxy.presets.insert(name.clone(), preset).is_some();
+    config.save(&["proxy", "presets"])?;
+
+    let action = if replaced { "Updated" } else { "Added" };
+    println!("{} proxy preset '{name}'", action.green());
+
+    Ok(())
+}
+
+/// Removes a preset
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved, or the preset does not exist.
+fn remove_preset(name: &str) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load()?;
+    if config.proxy.presets.remove(name).is_none() {
+        return Err(unknown_preset(name, &config));
+    }
+    config.save(&["proxy", "presets"])?;
+
+    println!("{} proxy preset '{name}'", "Removed".green());
+
+    Ok(())
+}
+
+/// Builds an error for an unknown preset listing the available preset names
+fn unknown_preset(name: &str, config: &Config) -> Box<dyn Error> {
+    let available: Vec<&str> = config.proxy.presets.keys().map(String::as_str).collect();
+    if available.is_empty() {
+        format!("Unknown proxy preset '{name}' (no presets configured)").into()
+    } else {
+        format!("Unknown proxy preset '{name}' (available: {})", available.join(", ")).into()
+    }
+}
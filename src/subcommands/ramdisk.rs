@@ -0,0 +1,440 @@
+//! RAM disks
+//!
+//! Creates disks in memory with `hdiutil attach ram://`, formats them with
+//! APFS or Mac OS Extended and mounts them in `/Volumes`. Their contents are
+//! lost when they are destroyed or the Mac shuts down, which makes them fast
+//! scratch space for builds and temporary files.
+//!
+//! `--at-login` installs a LaunchAgent that runs `rem ramdisk create` with
+//! the same options at every login. Creating a RAM disk that exists does
+//! nothing, so the LaunchAgent is loaded right away. `destroy` removes it.
+//!
+//! # System Commands Used
+//!
+//! - `hdiutil attach -nomount ram://` / `detach` - Create and destroy RAM disks
+//! - `hdiutil info -plist` - List the RAM disks
+//! - `diskutil erasevolume` - Format and mount a RAM disk
+//! - `diskutil list -plist` - Find the volumes of the RAM disks
+//! - `sysctl -n hw.memsize` - Read the size of the memory
+//! - `launchctl load -w` / `launchctl unload -w` - Load and unload the LaunchAgents
+//! - `plutil -convert json` - Read installed LaunchAgents
+
+use crate::cli::{RamdiskArgs, RamdiskCommands};
+use crate::config;
+use crate::launchd::{self, LaunchAgent, Log, Start};
+use crate::subcommands::disk::volumes::{mount_points, print_open_files};
+use crate::subcommands::disk::{diskutil, diskutil_error};
+use crate::subcommands::dmg::{SECTOR_SIZE, attached_images, hdiutil, hdiutil_error};
+use crate::util::{format_bytes, match_name};
+use colored::Colorize;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+/// Label prefix of the LaunchAgents; the name of the disk is appended
+const LABEL_PREFIX: &str = "com.github.rfoerthe.rempower.ramdisk.";
+
+/// Name of the log file of the LaunchAgents inside the state directory
+const LOG_FILE: &str = "ramdisk.log";
+
+/// Folder the RAM disks are mounted in
+const VOLUMES: &str = "/Volumes";
+
+/// An attached RAM disk
+struct RamDisk {
+    /// Name of its volume, `None` if it is not formatted
+    name: Option<String>,
+    /// Device of the disk, e.g. `/dev/disk6`
+    device: String,
+    /// Size in bytes
+    size: u64,
+    /// Mount points of its volumes
+    mount_points: Vec<String>,
+}
+
+/// A RAM disk created at login
+struct LoginDisk {
+    /// Label of the LaunchAgent
+    label: String,
+    /// Name of the volume
+    name: String,
+    /// Size in bytes
+    size: u64,
+}
+
+/// Performs RAM disk operations
+///
+/// # Arguments
+///
+/// * `args` - RAM disk arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil`, `diskutil` or `launchctl` fails.
+pub fn perform(args: RamdiskArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(RamdiskCommands::List) => list(),
+        Some(RamdiskCommands::Create {
+            size,
+            name,
+            hfs,
+            at_login,
+        }) => create(size, &name, hfs, at_login),
+        Some(RamdiskCommands::Destroy { name, force }) => destroy(name.as_deref(), force),
+    }
+}
+
+/// Prints the RAM disks and the ones created at login that do not exist
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil` or `diskutil` fails.
+fn list() -> Result<(), Box<dyn Error>> {
+    let disks = ram_disks()?;
+    let logins = login_disks()?;
+    if disks.is_empty() && logins.is_empty() {
+        println!("No RAM disks");
+        return Ok(());
+    }
+    for disk in &disks {
+        let name = disk.name.as_deref().unwrap_or("(not formatted)");
+        let at_login = if logins
+            .iter()
+            .any(|login| disk.name.as_deref() == Some(login.name.as_str()))
+        {
+            "  created at login"
+        } else {
+            ""
+        };
+        println!(
+            "{:<16}{:>10}  {}  {}{}",
+            name.bold(),
+            format_bytes(disk.size as f64),
+            disk.mount_points.join(", "),
+            disk.device.dimmed(),
+            at_login.dimmed()
+        );
+    }
+    for login in &logins {
+        if !disks
+            .iter()
+            .any(|disk| disk.name.as_deref() == Some(login.name.as_str()))
+        {
+            println!(
+                "{:<16}{:>10}  {}",
+                login.name.bold(),
+                format_bytes(login.size as f64),
+                "created at the next login".dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Creates, formats and mounts a RAM disk
+///
+/// # Arguments
+///
+/// * `size` - Size in bytes
+/// * `name` - Name of the volume
+/// * `hfs` - Format as Mac OS Extended instead of APFS
+/// * `at_login` - Install a LaunchAgent creating the disk at every login
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid or taken, the size exceeds the
+/// memory, or `hdiutil`, `diskutil` or `launchctl` fails.
+fn create(size: u64, name: &str, hfs: bool, at_login: bool) -> Result<(), Box<dyn Error>> {
+    if name.trim().is_empty() || name.contains(['/', ':']) {
+        return Err(format!("'{name}' is not a valid volume name").into());
+    }
+    let mount_point = Path::new(VOLUMES).join(name);
+    if ram_disks()?.iter().any(|disk| disk.name.as_deref() == Some(name)) {
+        println!("RAM disk {name} exists at {}", mount_point.display());
+    } else {
+        if mount_point.exists() {
+            return Err(format!("{} already exists; choose another name", mount_point.display()).into());
+        }
+        if let Some(memory) = memory_size() {
+            if size > memory {
+                return Err(format!(
+                    "{} is more than the memory of this Mac ({})",
+                    format_bytes(size as f64),
+                    format_bytes(memory as f64)
+                )
+                .into());
+            }
+            if size > memory / 2 {
+                println!(
+                    "{}",
+                    "The RAM disk takes more than half of the memory; apps may have to swap".yellow()
+                );
+            }
+        }
+
+        print!("Create RAM disk {name} ({})", format_bytes(size as f64));
+        io::stdout().flush()?;
+        let output = hdiutil(
+            &["attach", "-nomount", &format!("ram://{}", size.div_ceil(SECTOR_SIZE))],
+            None,
+        )?;
+        if !output.status.success() {
+            println!();
+            return Err(format!("Failed to create the RAM disk: {}", hdiutil_error(&output)).into());
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let device = stdout
+            .split_whitespace()
+            .next()
+            .ok_or("hdiutil reported no device for the RAM disk")?;
+        let format = if hfs { "HFS+" } else { "APFS" };
+        let output = diskutil(&["erasevolume", format, name, device])?;
+        if !output.status.success() {
+            println!();
+            let _ = hdiutil(&["detach", device, "-force"], None);
+            return Err(format!("Failed to format the RAM disk: {}", diskutil_error(&output)).into());
+        }
+        println!("{}", " OK".green());
+        println!("{:<16}{}", "Mounted at".bold(), mount_point.display());
+    }
+
+    if at_login {
+        install(size, name, hfs)?;
+    }
+    Ok(())
+}
+
+/// Detaches a RAM disk and removes its LaunchAgent
+///
+/// # Arguments
+///
+/// * `query` - Name of the disk or part of it; `None` if there is only one
+/// * `force` - Detach even if apps have files open on it
+///
+/// # Errors
+///
+/// Returns an error if no RAM disk or several match, or `hdiutil` or
+/// `launchctl` fails.
+fn destroy(query: Option<&str>, force: bool) -> Result<(), Box<dyn Error>> {
+    let disks = ram_disks()?;
+    let logins = login_disks()?;
+    let names: Vec<&str> = disks
+        .iter()
+        .map(|disk| disk.name.as_deref().unwrap_or_default())
+        .collect();
+    let (disk, name) = match query {
+        None => match disks.as_slice() {
+            [disk] => (Some(disk), disk.name.clone().unwrap_or_else(|| disk.device.clone())),
+            [] => return Err("There is no RAM disk".into()),
+            _ => return Err(format!("There are several RAM disks, give one of {}", names.join(", ")).into()),
+        },
+        Some(query) => match match_name(&names, query)? {
+            Some(index) => (Some(&disks[index]), names[index].to_string()),
+            // A disk created at login may not exist yet
+            None => {
+                let login_names: Vec<&str> = logins.iter().map(|login| login.name.as_str()).collect();
+                let index = match_name(&login_names, query)?.ok_or_else(|| format!("No RAM disk matches '{query}'"))?;
+                (None, logins[index].name.clone())
+            }
+        },
+    };
+
+    if let Some(disk) = disk {
+        print!("Destroy RAM disk {name}");
+        io::stdout().flush()?;
+        let mut args = vec!["detach", disk.device.as_str()];
+        if force {
+            args.push("-force");
+        }
+        let output = hdiutil(&args, None)?;
+        if !output.status.success() {
+            println!();
+            print_open_files(&disk.mount_points);
+            let hint = if force {
+                ""
+            } else {
+                "; quit the apps using it or use --force"
+            };
+            return Err(format!("Failed to destroy {name}: {}{hint}", hdiutil_error(&output)).into());
+        }
+        println!("{}", " OK".green());
+    }
+
+    if let Some(login) = logins.iter().find(|login| login.name == name) {
+        launchd::uninstall(&login.label)?;
+        println!("{} the LaunchAgent creating {name} at login", "Removed".green());
+    }
+    Ok(())
+}
+
+/// Reads the attached RAM disks
+///
+/// # Errors
+///
+/// Returns an error if `hdiutil` or `diskutil` fails.
+fn ram_disks() -> Result<Vec<RamDisk>, Box<dyn Error>> {
+    let mut disks = Vec::new();
+    for image in attached_images()? {
+        let Some(sectors) = image
+            .path
+            .to_str()
+            .and_then(|path| path.strip_prefix("ram://"))
+            .and_then(|sectors| sectors.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        // APFS volumes are on a container outside of the image
+        let mount_points = mount_points(image.device.trim_start_matches("/dev/"))?;
+        let name = mount_points
+            .first()
+            .and_then(|mount_point| Path::new(mount_point).file_name())
+            .map(|name| name.to_string_lossy().into_owned());
+        disks.push(RamDisk {
+            name,
+            device: image.device,
+            size: sectors * SECTOR_SIZE,
+            mount_points,
+        });
+    }
+    Ok(disks)
+}
+
+/// Reads the RAM disks created at login from their LaunchAgents
+///
+/// # Errors
+///
+/// Returns an error if the home directory cannot be determined.
+fn login_disks() -> Result<Vec<LoginDisk>, Box<dyn Error>> {
+    let dir = launchd::agents_dir()?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut disks = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(label) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".plist"))
+            .filter(|label| label.starts_with(LABEL_PREFIX))
+        else {
+            continue;
+        };
+        let output = Command::new("plutil")
+            .args(["-convert", "json", "-o", "-"])
+            .arg(&path)
+            .output()?;
+        let Ok(plist) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            continue;
+        };
+        let arguments: Vec<&str> = plist["ProgramArguments"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|argument| argument.as_str())
+            .collect();
+        let option = |name: &str| {
+            arguments
+                .iter()
+                .position(|argument| *argument == name)
+                .and_then(|index| arguments.get(index + 1))
+        };
+        if let (Some(name), Some(size)) = (option("--name"), option("--size").and_then(|size| size.parse().ok())) {
+            disks.push(LoginDisk {
+                label: label.to_string(),
+                name: name.to_string(),
+                size,
+            });
+        }
+    }
+    disks.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(disks)
+}
+
+/// Installs and loads the LaunchAgent creating a RAM disk at login
+///
+/// # Errors
+///
+/// Returns an error if the LaunchAgent cannot be written or loaded.
+fn install(size: u64, name: &str, hfs: bool) -> Result<(), Box<dyn Error>> {
+    let program = env::current_exe()?;
+    let log = config::state_dir()?.join(LOG_FILE);
+    if let Some(state_dir) = log.parent() {
+        fs::create_dir_all(state_dir)?;
+    }
+
+    let mut arguments = vec![
+        program.to_string_lossy().to_string(),
+        "ramdisk".to_string(),
+        "create".to_string(),
+        "--size".to_string(),
+        size.to_string(),
+        "--name".to_string(),
+        name.to_string(),
+    ];
+    if hfs {
+        arguments.push("--hfs".to_string());
+    }
+
+    let label = format!("{LABEL_PREFIX}{}", label_name(name));
+    // Agents of this name installed under an older label would create the disk twice
+    for login in login_disks()?
+        .iter()
+        .filter(|login| login.name == name && login.label != label)
+    {
+        launchd::uninstall(&login.label)?;
+    }
+    LaunchAgent {
+        label: &label,
+        arguments: &arguments,
+        start: Start::AtLogin,
+        log: Log::All(&log),
+    }
+    .install()?;
+
+    println!(
+        "{} the LaunchAgent creating {name} at login, logging to {}",
+        "Installed".green(),
+        log.display()
+    );
+    Ok(())
+}
+
+/// Encodes a volume name for a LaunchAgent label
+///
+/// Letters and digits are kept, every other byte becomes `_` and its hex
+/// value, so different names never share a label.
+fn label_name(name: &str) -> String {
+    name.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() {
+                char::from(byte).to_string()
+            } else {
+                format!("_{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Reads the size of the memory in bytes
+fn memory_size() -> Option<u64> {
+    let output = Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_name_keeps_names_apart() {
+        assert_eq!(label_name("Scratch1"), "Scratch1");
+        assert_eq!(label_name("Scratch 1"), "Scratch_201");
+        assert_eq!(label_name("Scratch-1"), "Scratch_2D1");
+        assert_eq!(label_name("_20"), "_5F20");
+        assert_eq!(label_name("Café"), "Caf_C3_A9");
+    }
+}
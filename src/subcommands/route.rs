@@ -0,0 +1,451 @@
+//! Routing table display and static routes
+//!
+//! Shows the IPv4 and IPv6 routing tables of `netstat -rn` as a table, with the
+//! abbreviated network destinations of `netstat` (e.g. `192.168.1` or `10.8/16`)
+//! expanded to CIDR notation. Host routes cloned from other routes, which are
+//! mostly ARP and neighbor discovery entries, are hidden unless `--all` is given.
+//!
+//! Static routes are added and deleted with `route` (with sudo). They are not
+//! persistent and disappear on reboot. Routes added with `--expire` are deleted
+//! after the given number of minutes by a background process, which keeps running
+//! after `rem` exits.
+//!
+//! # System Commands Used
+//!
+//! - `netstat -rn` - Read the routing tables
+//! - `route -n add` / `route -n delete` - Add and delete routes (with sudo)
+
+use crate::cli::{RouteArgs, RouteCommands};
+use colored::Colorize;
+use serde::Serialize;
+use std::error::Error;
+use std::net::IpAddr;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Meaning of the route flags printed by `netstat -r`
+const FLAGS: &[(char, &str)] = &[
+    ('U', "up"),
+    ('G', "gateway"),
+    ('H', "host"),
+    ('S', "static"),
+    ('C', "cloning"),
+    ('c', "protocol-specified cloning"),
+    ('W', "cloned"),
+    ('L', "link-layer"),
+    ('D', "dynamic"),
+    ('M', "modified"),
+    ('R', "reject"),
+    ('B', "blackhole"),
+    ('b', "broadcast"),
+    ('m', "multicast"),
+    ('I', "interface scoped"),
+    ('i', "interface reference"),
+    ('r', "router"),
+    ('g', "cloned gateway"),
+    ('X', "external resolution"),
+    ('Y', "proxy"),
+];
+
+/// A route of the routing table
+#[derive(Serialize)]
+struct Route {
+    /// Destination network in CIDR notation, a host address, or `default`
+    destination: String,
+    /// Gateway address, `link#<N>` for directly connected networks, or a MAC address
+    gateway: String,
+    /// Route flags, see [`FLAGS`]
+    flags: String,
+    /// Device name of the outgoing interface, e.g. `en0`
+    interface: String,
+    /// Remaining lifetime of the route in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expire: Option<u64>,
+    /// True for IPv6 routes
+    ipv6: bool,
+}
+
+/// A route destination given on the command line
+struct Destination {
+    /// Destination as passed to `route`, e.g. `10.0.0.0/8`, `1.2.3.4` or `default`
+    address: String,
+    /// True for IPv6 destinations
+    ipv6: bool,
+    /// True for a single host, false for a network
+    host: bool,
+}
+
+impl Destination {
+    /// Returns the `route` arguments selecting the destination
+    fn route_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.ipv6 {
+            args.push("-inet6".to_string());
+        }
+        if self.address != "default" {
+            args.push(if self.host { "-host" } else { "-net" }.to_string());
+        }
+        args.push(self.address.clone());
+        args
+    }
+}
+
+/// Performs routing table operations
+///
+/// # Arguments
+///
+/// * `args` - Route arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the routing table cannot be read, a destination or gateway
+/// is invalid, or `route` fails.
+pub fn perform(args: RouteArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Some(RouteCommands::List { ipv4, ipv6, all, json }) => list(ipv4, ipv6, all, json),
+        Some(RouteCommands::Add {
+            destination,
+            gateway,
+            interface,
+            expire,
+        }) => add(&destination, gateway, interface.as_deref(), expire),
+        Some(RouteCommands::Delete { destination, gateway }) => delete(&destination, gateway),
+        None => list(false, false, false, false),
+    }
+}
+
+/// Prints the routing tables
+///
+/// # Arguments
+///
+/// * `ipv4` - Only show IPv4 routes
+/// * `ipv6` - Only show IPv6 routes
+/// * `all` - Also show cloned host routes
+/// * `json` - Print the routes as JSON
+///
+/// # Errors
+///
+/// Returns an error if `netstat` cannot be run or fails.
+fn list(ipv4: bool, ipv6: bool, all: bool, json: bool) -> Result<(), Box<dyn Error>> {
+    let routes: Vec<Route> = read_routes()?
+        .into_iter()
+        .filter(|route| if route.ipv6 { !ipv4 } else { !ipv6 })
+        .filter(|route| all || !route.flags.contains('W'))
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&routes)?);
+        return Ok(());
+    }
+
+    for (title, family_is_ipv6) in [("IPv4", false), ("IPv6", true)] {
+        let family: Vec<&Route> = routes.iter().filter(|route| route.ipv6 == family_is_ipv6).collect();
+        if family.is_empty() {
+            continue;
+        }
+        if family_is_ipv6 && routes.iter().any(|route| !route.ipv6) {
+            println!();
+        }
+        println!("{}", title.bold());
+        let destination_width = column_width(family.iter().map(|route| route.destination.len()), "DESTINATION");
+        let gateway_width = column_width(family.iter().map(|route| route.gateway.len()), "GATEWAY");
+        let flags_width = column_width(family.iter().map(|route| route.flags.len()), "FLAGS");
+        let with_expire = family.iter().any(|route| route.expire.is_some());
+
+        let header = format!(
+            "{:<destination_width$}  {:<gateway_width$}  {:<flags_width$}  {:<9}  {}",
+            "DESTINATION",
+            "GATEWAY",
+            "FLAGS",
+            "INTERFACE",
+            if with_expire { "EXPIRE" } else { "" }
+        );
+        println!("{}", header.trim_end());
+        for route in family {
+            let destination = format!("{:<destination_width$}", route.destination);
+            let destination = if route.destination == "default" {
+                destination.green()
+            } else if route.flags.contains('S') {
+                destination.cyan()
+            } else {
+                destination.normal()
+            };
+            let expire = route.expire.map(|seconds| format!("{seconds} s")).unwrap_or_default();
+            let row = format!(
+                "{destination}  {:<gateway_width$}  {:<flags_width$}  {:<9}  {expire}",
+                route.gateway, route.flags, route.interface
+            );
+            println!("{}", row.trim_end());
+        }
+    }
+
+    let legend: Vec<String> = FLAGS
+        .iter()
+        .filter(|(flag, _)| routes.iter().any(|route| route.flags.contains(*flag)))
+        .map(|(flag, meaning)| format!("{flag} {meaning}"))
+        .collect();
+    if !legend.is_empty() {
+        println!();
+        println!("{}", format!("Flags: {}", legend.join(", ")).dimmed());
+    }
+
+    Ok(())
+}
+
+/// Adds a static route
+///
+/// # Arguments
+///
+/// * `destination` - Network in CIDR notation, host address, or `default`
+/// * `gateway` - Gateway to route the destination through
+/// * `interface` - Interface to route the destination to directly, instead of a gateway
+/// * `expire` - Minutes after which the route is deleted again
+///
+/// # Errors
+///
+/// Returns an error if the destination is invalid or does not match the address
+/// family of the gateway, `route` fails, or the expiry cannot be scheduled.
+fn add(
+    destination: &str,
+    gateway: Option<IpAddr>,
+    interface: Option<&str>,
+    expire: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    let destination = parse_destination(destination, gateway.map(|gateway| gateway.is_ipv6()))?;
+
+    let mut args = destination.route_args();
+    match (gateway, interface) {
+        (Some(gateway), _) => args.push(gateway.to_string()),
+        (None, Some(interface)) => args.extend(["-interface".to_string(), interface.to_string()]),
+        (None, None) => return Err("Missing gateway or --interface".into()),
+    }
+
+    run_route("add", &args)?;
+    let target = gateway.map_or_else(
+        || interface.unwrap_or_default().to_string(),
+        |gateway| gateway.to_string(),
+    );
+    println!("{} route {} via {target}", "Added".green(), destination.address.bold());
+
+    if let Some(minutes) = expire {
+        schedule_delete(&args, minutes)?;
+        println!("The route expires in {minutes} min");
+    }
+
+    Ok(())
+}
+
+/// Deletes a route
+///
+/// # Arguments
+///
+/// * `destination` - Network in CIDR notation, host address, or `default`
+/// * `gateway` - Gateway of the route, to delete only the route through it
+///
+/// # Errors
+///
+/// Returns an error if the destination is invalid or `route` fails.
+fn delete(destination: &str, gateway: Option<IpAddr>) -> Result<(), Box<dyn Error>> {
+    let destination = parse_destination(destination, gateway.map(|gateway| gateway.is_ipv6()))?;
+
+    let mut args = destination.route_args();
+    args.extend(gateway.map(|gateway| gateway.to_string()));
+    run_route("delete", &args)?;
+    println!("{} route {}", "Deleted".green(), destination.address.bold());
+
+    Ok(())
+}
+
+/// Runs `sudo route -n <operation> <args>`
+///
+/// # Errors
+///
+/// Returns an error if `route` cannot be run or fails.
+fn run_route(operation: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("sudo")
+        .args(["route", "-n", operation])
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run route: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.trim().trim_start_matches("route: ");
+        return Err(format!("Failed to {operation} the route: {message}").into());
+    }
+    Ok(())
+}
+
+/// Starts a background process deleting a route after a number of minutes
+///
+/// The process runs in its own process group, so it is neither stopped by Ctrl-C
+/// nor when the terminal is closed. It reuses the sudo credentials of the
+/// preceding `route add`.
+///
+/// # Arguments
+///
+/// * `args` - `route` arguments selecting the route, as passed to `route add`
+/// * `minutes` - Minutes until the route is deleted
+///
+/// # Errors
+///
+/// Returns an error if the process cannot be started or sudo asks for a password.
+fn schedule_delete(args: &[String], minutes: u32) -> Result<(), Box<dyn Error>> {
+    let seconds = u64::from(minutes) * 60;
+    let mut child = Command::new("sudo")
+        .args([
+            "-n",
+            "sh",
+            "-c",
+            r#"sleep "$1"; shift; exec route -n delete "$@""#,
+            "sh",
+        ])
+        .arg(seconds.to_string())
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("Failed to schedule the expiry of the route: {e}"))?;
+
+    // sudo exits right away if the credentials are no longer cached
+    thread::sleep(Duration::from_millis(200));
+    if let Some(status) = child.try_wait()?
+        && !status.success()
+    {
+        return Err("Failed to schedule the expiry of the route, delete it with 'rem route delete'".into());
+    }
+
+    Ok(())
+}
+
+/// Parses a destination given on the command line
+///
+/// # Arguments
+///
+/// * `destination` - Network in CIDR notation, host address, or `default`
+/// * `ipv6` - Address family of the gateway, if known; selects the family of `default`
+///
+/// # Errors
+///
+/// Returns an error if the destination is malformed or its address family differs
+/// from the gateway.
+fn parse_destination(destination: &str, ipv6: Option<bool>) -> Result<Destination, Box<dyn Error>> {
+    if destination == "default" {
+        return Ok(Destination {
+            address: destination.to_string(),
+            ipv6: ipv6.unwrap_or(false),
+            host: false,
+        });
+    }
+
+    let invalid =
+        || format!("Invalid destination '{destination}', expected an address, a network like 10.0.0.0/8 or 'default'");
+    let (address, prefix) = match destination.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u8>().map_err(|_| invalid())?)),
+        None => (destination, None),
+    };
+    let address: IpAddr = address.parse().map_err(|_| invalid())?;
+    let max_prefix = if address.is_ipv6() { 128 } else { 32 };
+    if prefix.is_some_and(|prefix| prefix > max_prefix) {
+        return Err(invalid().into());
+    }
+    if ipv6.is_some_and(|ipv6| ipv6 != address.is_ipv6()) {
+        return Err(format!("Destination '{destination}' and gateway have different address families").into());
+    }
+
+    Ok(Destination {
+        address: destination.to_string(),
+        ipv6: address.is_ipv6(),
+        host: prefix.is_none_or(|prefix| prefix == max_prefix),
+    })
+}
+
+/// Returns the width of a table column
+fn column_width(lengths: impl Iterator<Item = usize>, header: &str) -> usize {
+    lengths.max().unwrap_or(0).max(header.len())
+}
+
+/// Reads the IPv4 and IPv6 routing tables
+///
+/// # Errors
+///
+/// Returns an error if `netstat` cannot be run or fails.
+fn read_routes() -> Result<Vec<Route>, Box<dyn Error>> {
+    let output = Command::new("netstat")
+        .arg("-rn")
+        .output()
+        .map_err(|e| format!("Failed to run netstat: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("netstat failed: {}", stderr.trim()).into());
+    }
+
+    Ok(parse_routes(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the output of `netstat -rn`
+///
+/// The tables are introduced by `Internet:` and `Internet6:`, followed by a header
+/// line and rows like `default  192.168.1.1  UGScg  en0` with an optional expire
+/// time in seconds (or `!` for routes that do not expire).
+fn parse_routes(output: &str) -> Vec<Route> {
+    let mut routes = Vec::new();
+    let mut ipv6 = None;
+
+    for line in output.lines() {
+        match line.trim() {
+            "Internet:" => ipv6 = Some(false),
+            "Internet6:" => ipv6 = Some(true),
+            _ => {
+                let Some(ipv6) = ipv6 else {
+                    continue;
+                };
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() < 4 || fields[0] == "Destination" {
+                    continue;
+                }
+                routes.push(Route {
+                    destination: if ipv6 {
+                        fields[0].to_string()
+                    } else {
+                        expand_ipv4_destination(fields[0])
+                    },
+                    gateway: fields[1].to_string(),
+                    flags: fields[2].to_string(),
+                    interface: fields[3].to_string(),
+                    expire: fields.get(4).and_then(|expire| expire.parse().ok()),
+                    ipv6,
+                });
+            }
+        }
+    }
+
+    routes
+}
+
+/// Expands an abbreviated IPv4 destination of `netstat` to CIDR notation
+///
+/// `netstat` omits trailing zero octets of networks and the prefix length of
+/// classful networks: `192.168.1` is `192.168.1.0/24`, `10.8/16` is `10.8.0.0/16`.
+/// Host addresses and `default` are returned unchanged.
+fn expand_ipv4_destination(destination: &str) -> String {
+    let (network, prefix) = match destination.split_once('/') {
+        Some((network, prefix)) => (network, Some(prefix)),
+        None => (destination, None),
+    };
+    let octets: Vec<&str> = network.split('.').collect();
+    if destination == "default"
+        || (octets.len() == 4 && prefix.is_none())
+        || octets.len() > 4
+        || octets.iter().any(|octet| octet.parse::<u8>().is_err())
+    {
+        return destination.to_string();
+    }
+
+    let prefix = prefix.map_or_else(|| (octets.len() * 8).to_string(), str::to_string);
+    let padding = [".0"; 3][..4 - octets.len()].concat();
+    format!("{network}{padding}/{prefix}")
+}
@@ -0,0 +1,209 @@
+//! Screenshot settings
+//!
+//! Shows and changes where screenshots are saved, their file format, whether
+//! window screenshots include the shadow and whether the floating thumbnail is
+//! shown. These are the `com.apple.screencapture` preferences of the Options
+//! menu of the Screenshot app; SystemUIServer is restarted so that they take
+//! effect right away.
+//!
+//! `take` captures a screenshot with these settings; see the [`take`] module.
+//!
+//! # System Commands Used
+//!
+//! - `defaults read com.apple.screencapture` - Read the screenshot settings
+//! - `defaults write com.apple.screencapture` - Change the screenshot settings
+//! - `killall SystemUIServer` - Apply the changed settings
+
+pub mod take;
+
+use crate::cli::{ScreenshotArgs, ScreenshotCommands, ScreenshotConfigArgs, ScreenshotFormat};
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// Preference domain of the screenshot settings
+const DOMAIN: &str = "com.apple.screencapture";
+
+/// Folder screenshots are saved in unless `location` is set
+pub const DEFAULT_LOCATION: &str = "~/Desktop";
+
+/// Performs screenshot operations
+///
+/// # Arguments
+///
+/// * `args` - Screenshot arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the settings cannot be changed or the screenshot fails.
+pub fn perform(args: ScreenshotArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(ScreenshotCommands::Show) => {
+            print_settings();
+            Ok(())
+        }
+        Some(ScreenshotCommands::Config(args)) => configure(args),
+        Some(ScreenshotCommands::Take(args)) => take::perform(args),
+    }
+}
+
+/// Prints the current screenshot settings, marking the defaults of macOS
+fn print_settings() {
+    let location = read_default("location");
+    let format = read_default("type");
+    let shadow = read_default("disable-shadow").map(|value| value != "1");
+    let thumbnail = read_default("show-thumbnail").map(|value| value != "0");
+
+    println!(
+        "{:<16}{}",
+        "Location".bold(),
+        with_default(location, DEFAULT_LOCATION.to_string())
+    );
+    println!("{:<16}{}", "Format".bold(), with_default(format, "png".to_string()));
+    println!(
+        "{:<16}{}",
+        "Shadow".bold(),
+        with_default(shadow.map(on_off), on_off(true))
+    );
+    println!(
+        "{:<16}{}",
+        "Thumbnail".bold(),
+        with_default(thumbnail.map(on_off), on_off(true))
+    );
+}
+
+/// Changes the given screenshot settings and restarts SystemUIServer
+///
+/// # Arguments
+///
+/// * `args` - Settings to change
+///
+/// # Errors
+///
+/// Returns an error if the location cannot be created or a setting cannot be written.
+fn configure(args: ScreenshotConfigArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(location) = args.location {
+        fs::create_dir_all(&location).map_err(|e| format!("Failed to create {}: {e}", location.display()))?;
+        let location = fs::canonicalize(&location)?;
+        let location = location.to_string_lossy();
+        apply(
+            &format!("Save screenshots in {location}"),
+            "location",
+            "-string",
+            &location,
+        )?;
+    }
+    if let Some(format) = args.format {
+        let extension = extension(format);
+        apply(
+            &format!("Save screenshots as {extension}"),
+            "type",
+            "-string",
+            extension,
+        )?;
+    }
+    if args.shadow || args.no_shadow {
+        let label = format!("Turn window shadow {}", on_off(args.shadow));
+        apply(
+            &label,
+            "disable-shadow",
+            "-bool",
+            if args.no_shadow { "true" } else { "false" },
+        )?;
+    }
+    if args.thumbnail || args.no_thumbnail {
+        let label = format!("Turn floating thumbnail {}", on_off(args.thumbnail));
+        apply(
+            &label,
+            "show-thumbnail",
+            "-bool",
+            if args.thumbnail { "true" } else { "false" },
+        )?;
+    }
+
+    // SystemUIServer is relaunched by launchd and reads the new settings
+    let output = Command::new("killall")
+        .arg("SystemUIServer")
+        .output()
+        .map_err(|e| format!("Failed to run killall: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to restart SystemUIServer: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Writes a setting, printing the change and its result
+///
+/// # Errors
+///
+/// Returns an error if `defaults` fails.
+fn apply(label: &str, key: &str, kind: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    print!("{label}");
+    io::stdout().flush()?;
+    if let Err(e) = write_default(key, kind, value) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Reads a screenshot setting
+///
+/// # Returns
+///
+/// `None` if the setting is not set.
+pub fn read_default(key: &str) -> Option<String> {
+    let output = Command::new("defaults").args(["read", DOMAIN, key]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Writes a screenshot setting
+///
+/// # Arguments
+///
+/// * `key` - Name of the setting
+/// * `kind` - Type option of `defaults`, e.g. `-string` or `-bool`
+/// * `value` - New value
+///
+/// # Errors
+///
+/// Returns an error if `defaults` cannot be run or fails.
+fn write_default(key: &str, kind: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("defaults")
+        .args(["write", DOMAIN, key, kind, value])
+        .output()
+        .map_err(|e| format!("Failed to run defaults: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to write {key}: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Returns a setting, or the default of macOS marked as such
+fn with_default(value: Option<String>, default: String) -> String {
+    value.unwrap_or_else(|| format!("{default} {}", "(default)".dimmed()))
+}
+
+/// Formats a switch state
+fn on_off(on: bool) -> String {
+    if on { "on" } else { "off" }.to_string()
+}
+
+/// Returns the file extension `screencapture` uses for a format
+pub fn extension(format: ScreenshotFormat) -> &'static str {
+    match format {
+        ScreenshotFormat::Png => "png",
+        ScreenshotFormat::Jpg => "jpg",
+        ScreenshotFormat::Heic => "heic",
+        ScreenshotFormat::Tiff => "tiff",
+        ScreenshotFormat::Pdf => "pdf",
+    }
+}
@@ -0,0 +1,192 @@
+//! Taking screenshots
+//!
+//! Wraps `screencapture` so that a screenshot is taken with the configured
+//! format and shadow setting and saved in the configured folder, named like
+//! the screenshots of macOS (`Screenshot 2025-06-01 at 14.03.22.png`). Without
+//! a target, only the main display is captured instead of one file per display.
+//!
+//! The text of a screenshot is recognized with the Vision framework, called
+//! from AppleScriptObjC so that no helper needs to be installed.
+//!
+//! # System Commands Used
+//!
+//! - `screencapture` - Capture the screen, a display, a window or a region
+//! - `osascript` - Recognize text with `VNRecognizeTextRequest` of the Vision framework
+//! - `open` - Open the screenshot in Preview
+
+use super::{DEFAULT_LOCATION, extension, read_default};
+use crate::cli::{ScreenshotFormat, ScreenshotTakeArgs};
+use crate::config::home_dir;
+use crate::util::timestamp;
+use clap::ValueEnum;
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// AppleScript printing the text recognized in the image given as argument, one line per observation
+const OCR_SCRIPT: &str = r#"use framework "Foundation"
+use framework "Vision"
+use scripting additions
+
+on run argv
+    set imageURL to current application's NSURL's fileURLWithPath:(item 1 of argv)
+    set request to current application's VNRecognizeTextRequest's alloc()'s init()
+    request's setUsesLanguageCorrection:true
+    set handler to current application's VNImageRequestHandler's alloc()'s initWithURL:imageURL options:(missing value)
+    set {success, theError} to handler's performRequests:{request} |error|:(reference)
+    if not success then error (theError's localizedDescription() as text)
+    set texts to {}
+    repeat with observation in (request's results() as list)
+        set end of texts to ((observation's topCandidates:1)'s firstObject()'s |string|()) as text
+    end repeat
+    set AppleScript's text item delimiters to linefeed
+    return texts as text
+end run"#;
+
+/// Takes a screenshot
+///
+/// # Arguments
+///
+/// * `args` - Target, destination and follow-up actions from the command line
+///
+/// # Errors
+///
+/// Returns an error if the screenshot folder cannot be created, `screencapture`
+/// fails, or the text cannot be recognized or the screenshot opened.
+pub fn perform(args: ScreenshotTakeArgs) -> Result<(), Box<dyn Error>> {
+    let format = args
+        .format
+        .or_else(|| args.output.as_deref().and_then(format_of))
+        .or_else(|| read_default("type").and_then(|value| ScreenshotFormat::from_str(&value, true).ok()))
+        .unwrap_or(ScreenshotFormat::Png);
+
+    let mut command = Command::new("screencapture");
+    command.args(["-t", extension(format)]);
+    if args.window {
+        command.args(["-i", "-w"]);
+    } else if args.region {
+        command.args(["-i", "-s"]);
+    } else if let Some(display) = args.display {
+        command.args(["-D", &display.to_string()]);
+    } else {
+        command.arg("-m");
+    }
+    if let Some(delay) = args.delay {
+        command.args(["-T", &delay.to_string()]);
+    }
+    if read_default("disable-shadow").as_deref() == Some("1") {
+        command.arg("-o");
+    }
+
+    let path = if args.clipboard {
+        command.arg("-c");
+        None
+    } else {
+        let path = match args.output {
+            Some(path) => path,
+            None => unused_path(&screenshot_folder()?, format),
+        };
+        command.arg(&path);
+        Some(path)
+    };
+
+    let status = command
+        .status()
+        .map_err(|e| format!("Failed to run screencapture: {e}"))?;
+    if !status.success() {
+        return Err(format!("screencapture failed with {status}").into());
+    }
+
+    let Some(path) = path else {
+        println!("Copied the screenshot to the clipboard");
+        return Ok(());
+    };
+    // Interactive captures canceled with Escape leave no file
+    if !path.exists() {
+        println!("Screenshot canceled");
+        return Ok(());
+    }
+    println!("Saved {}", path.display().to_string().green());
+
+    if args.ocr {
+        let text = recognize_text(&path)?;
+        if text.trim().is_empty() {
+            println!("{}", "No text recognized".dimmed());
+        } else {
+            println!("\n{}", text.trim_end());
+        }
+    }
+    if args.open {
+        let output = Command::new("open").arg(&path).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to open {}: {}", path.display(), stderr.trim()).into());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the configured screenshot folder, creating it if missing
+///
+/// # Errors
+///
+/// Returns an error if `HOME` is not set or the folder cannot be created.
+fn screenshot_folder() -> Result<PathBuf, Box<dyn Error>> {
+    let location = read_default("location").unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+    let folder = match location.strip_prefix("~/") {
+        Some(relative) => home_dir()?.join(relative),
+        None => PathBuf::from(location),
+    };
+    fs::create_dir_all(&folder).map_err(|e| format!("Failed to create {}: {e}", folder.display()))?;
+    Ok(folder)
+}
+
+/// Returns a path in a folder named with the current time, e.g.
+/// `Screenshot 2025-06-01 at 14.03.22.png`
+///
+/// A number is appended if a screenshot was already taken in the same second.
+fn unused_path(folder: &Path, format: ScreenshotFormat) -> PathBuf {
+    let now = timestamp();
+    let name = match now.split_once(' ') {
+        Some((date, time)) => format!("Screenshot {date} at {}", time.replace(':', ".")),
+        None => format!("Screenshot {now}"),
+    };
+    let extension = extension(format);
+    let mut path = folder.join(format!("{name}.{extension}"));
+    let mut number = 2;
+    while path.exists() {
+        path = folder.join(format!("{name} ({number}).{extension}"));
+        number += 1;
+    }
+    path
+}
+
+/// Returns the format matching the extension of a file
+fn format_of(path: &Path) -> Option<ScreenshotFormat> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "jpeg" => Some(ScreenshotFormat::Jpg),
+        "tif" => Some(ScreenshotFormat::Tiff),
+        _ => ScreenshotFormat::from_str(&extension, true).ok(),
+    }
+}
+
+/// Recognizes the text in an image
+///
+/// # Errors
+///
+/// Returns an error if `osascript` cannot be run or the recognition fails.
+fn recognize_text(path: &Path) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("osascript")
+        .args(["-e", OCR_SCRIPT])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run osascript: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to recognize text: {}", stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
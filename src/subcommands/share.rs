@@ -0,0 +1,532 @@
+//! SMB and AFP network shares
+//!
+//! Mounts network shares in `/Volumes` like the Finder does (AppleScript `mount
+//! volume`). Without `--keychain`, macOS looks up the password in the keychain
+//! itself and shows its login dialog if none is stored. With `--keychain`, the
+//! password stored in the login keychain for the server is passed to the mount,
+//! so no dialog appears; if none is stored, `security` asks for it in the
+//! terminal and stores it first.
+//!
+//! Shares can be saved as favorites in the `[share.favorites]` table of the
+//! rempower configuration file and mounted by name.
+//!
+//! # System Commands Used
+//!
+//! - `osascript` - Mount shares with `mount volume`
+//! - `mount` - List mounted network file systems
+//! - `diskutil unmount` - Unmount shares
+//! - `security find-internet-password` / `add-internet-password` - Read and store share passwords
+
+use crate::cli::{ShareArgs, ShareCommands};
+use crate::config::{Config, ShareFavorite};
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// File system types of network shares in the output of `mount`
+const NETWORK_FILE_SYSTEMS: &[&str] = &["smbfs", "afpfs", "nfs", "webdav"];
+
+/// URL of a network share
+struct ShareUrl {
+    /// Protocol, `smb` or `afp`
+    scheme: String,
+    /// User name to log in with
+    user: Option<String>,
+    /// Server name or address, optionally with port
+    server: String,
+    /// Shared volume, optionally followed by a path inside it
+    path: String,
+}
+
+impl ShareUrl {
+    /// Returns the name of the shared volume, i.e. the first path component
+    fn volume(&self) -> String {
+        percent_decode(self.path.split('/').next().unwrap_or_default())
+    }
+
+    /// Returns the server name without port, as used for keychain entries
+    fn host(&self) -> &str {
+        match self.server.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() && !host.contains(':') => host,
+            _ => &self.server,
+        }
+    }
+
+    /// Returns the keychain protocol code of the scheme
+    fn keychain_protocol(&self) -> &'static str {
+        if self.scheme == "afp" { "afp " } else { "smb " }
+    }
+}
+
+impl std::fmt::Display for ShareUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.user {
+            Some(user) => write!(f, "{}://{user}@{}/{}", self.scheme, self.server, self.path),
+            None => write!(f, "{}://{}/{}", self.scheme, self.server, self.path),
+        }
+    }
+}
+
+/// A mounted network file system
+struct Mount {
+    /// Source of the mount, e.g. `//alice@nas.local/media` for SMB
+    source: String,
+    /// Mount point, e.g. `/Volumes/media`
+    mount_point: String,
+    /// File system type, e.g. `smbfs`
+    file_system: String,
+}
+
+impl Mount {
+    /// Returns the source as URL for SMB and AFP shares, otherwise as reported by `mount`
+    fn url(&self) -> String {
+        match self.file_system.as_str() {
+            "smbfs" => format!("smb:{}", self.source),
+            "afpfs" => format!("afp:{}", self.source),
+            _ => self.source.clone(),
+        }
+    }
+
+    /// Returns true if this mount is the volume of a share URL
+    fn is_share(&self, url: &ShareUrl) -> bool {
+        let Some(mounted) = parse_url(&self.url()).ok() else {
+            return false;
+        };
+        mounted.host().eq_ignore_ascii_case(url.host()) && mounted.volume().eq_ignore_ascii_case(&url.volume())
+    }
+}
+
+/// Performs network share operations
+///
+/// # Arguments
+///
+/// * `args` - Share arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the share is unknown or invalid, mounting or unmounting
+/// fails, or the configuration cannot be read or written.
+pub fn perform(args: ShareArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        ShareCommands::Mount { share, keychain, alias } => mount(&share, keychain, alias),
+        ShareCommands::Unmount { share, force } => unmount(&share, force),
+        ShareCommands::List => list(),
+        ShareCommands::Remove { name } => remove_favorite(&name),
+    }
+}
+
+/// Mounts a share given by URL or favorite name
+///
+/// # Arguments
+///
+/// * `share` - URL or favorite name of the share
+/// * `keychain` - Log in with the password stored in the keychain
+/// * `alias` - Name to save the share under as favorite
+///
+/// # Errors
+///
+/// Returns an error if the share is neither a valid URL nor a favorite, no
+/// password can be found or stored, or mounting fails.
+fn mount(share: &str, keychain: bool, alias: Option<String>) -> Result<(), Box<dyn Error>> {
+    let (url, keychain) = if share.contains("://") {
+        (parse_url(share)?, keychain)
+    } else {
+        if alias.is_some() {
+            return Err(format!("Invalid share URL '{share}', --alias needs a URL like smb://server/volume").into());
+        }
+        let config = Config::load()?;
+        let favorite = config
+            .share
+            .favorites
+            .get(share)
+            .ok_or_else(|| unknown_favorite(share, &config))?;
+        (parse_url(&favorite.url)?, keychain || favorite.keychain)
+    };
+
+    if let Some(mounted) = mounted_shares()?.into_iter().find(|mount| mount.is_share(&url)) {
+        println!("{url} is already mounted at {}", mounted.mount_point.bold());
+    } else {
+        let credentials = if keychain {
+            Some(keychain_credentials(&url)?)
+        } else {
+            None
+        };
+        mount_volume(&url, credentials)?;
+        match mounted_shares()?.into_iter().find(|mount| mount.is_share(&url)) {
+            Some(mounted) => println!("{} {url} at {}", "Mounted".green(), mounted.mount_point.bold()),
+            None => println!("{} {url}", "Mounted".green()),
+        }
+    }
+
+    if let Some(name) = alias {
+        add_favorite(name, &url, keychain)?;
+    }
+
+    Ok(())
+}
+
+/// Mounts a share with AppleScript `mount volume`
+///
+/// The script is passed on stdin to keep the password out of the process list.
+///
+/// # Arguments
+///
+/// * `url` - URL of the share
+/// * `credentials` - User name and password to log in with
+///
+/// # Errors
+///
+/// Returns an error if `osascript` cannot be run or the mount fails.
+fn mount_volume(url: &ShareUrl, credentials: Option<(String, String)>) -> Result<(), Box<dyn Error>> {
+    let mut script = format!("mount volume \"{}\"", applescript_escape(&url.to_string()));
+    if let Some((user, password)) = credentials {
+        script.push_str(&format!(
+            " as user name \"{}\" with password \"{}\"",
+            applescript_escape(&user),
+            applescript_escape(&password)
+        ));
+    }
+
+    let mut child = Command::new("osascript")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run osascript: {e}"))?;
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open stdin of osascript")?
+        .write_all(script.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.trim().rsplit("execution error: ").next().unwrap_or_default();
+        return Err(format!("Failed to mount {url}: {message}").into());
+    }
+
+    Ok(())
+}
+
+/// Returns the user name and password of a share from the keychain
+///
+/// If no password is stored for the server, `security` asks for it in the
+/// terminal and stores it in the login keychain.
+///
+/// # Errors
+///
+/// Returns an error if no password is stored and none can be asked for.
+fn keychain_credentials(url: &ShareUrl) -> Result<(String, String), Box<dyn Error>> {
+    if let Some(credentials) = find_keychain_password(url) {
+        return Ok(credentials);
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(format!("No password for {} stored in the keychain", url.host()).into());
+    }
+    let user = match &url.user {
+        Some(user) => user.clone(),
+        None => std::env::var("USER").map_err(|_| "No user name given, use smb://USER@SERVER/VOLUME")?,
+    };
+    println!("No password for {user}@{} stored in the keychain", url.host());
+    let status = Command::new("security")
+        .args(["add-internet-password", "-U", "-a", &user, "-s", url.host()])
+        .args(["-r", url.keychain_protocol(), "-l", url.host()])
+        // -w as last option makes security ask for the password
+        .arg("-w")
+        .status()
+        .map_err(|e| format!("Failed to run security: {e}"))?;
+    if !status.success() {
+        return Err(format!("Failed to store the password for {} in the keychain", url.host()).into());
+    }
+
+    find_keychain_password(url).ok_or_else(|| format!("No password for {} stored in the keychain", url.host()).into())
+}
+
+/// Looks up the account and password of a server in the keychain
+///
+/// If the URL has no user name, the account of the first keychain entry of the
+/// server is used.
+fn find_keychain_password(url: &ShareUrl) -> Option<(String, String)> {
+    let lookup = |extra: &[&str]| {
+        let mut command = Command::new("security");
+        command
+            .args([
+                "find-internet-password",
+                "-s",
+                url.host(),
+                "-r",
+                url.keychain_protocol(),
+            ])
+            .args(extra);
+        if let Some(user) = &url.user {
+            command.args(["-a", user]);
+        }
+        command.output().ok().filter(|output| output.status.success())
+    };
+
+    // The attributes contain the account as `"acct"<blob>="alice"`
+    let attributes = lookup(&[])?;
+    let user = match &url.user {
+        Some(user) => user.clone(),
+        None => String::from_utf8_lossy(&attributes.stdout)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("\"acct\"<blob>=\""))
+            .and_then(|account| account.strip_suffix('"'))
+            .map(str::to_string)?,
+    };
+
+    let mut with_account = Vec::new();
+    if url.user.is_none() {
+        with_account.extend(["-a", user.as_str()]);
+    }
+    with_account.push("-w");
+    let output = lookup(&with_account)?;
+    let password = String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches('\n')
+        .to_string();
+    Some((user, password))
+}
+
+/// Unmounts a share
+///
+/// # Arguments
+///
+/// * `share` - Mount point, URL, volume name or favorite name of the share
+/// * `force` - Unmount even if files are open
+///
+/// # Errors
+///
+/// Returns an error if no mounted share matches or `diskutil` fails.
+fn unmount(share: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    let mounts = mounted_shares()?;
+    let url = if share.contains("://") {
+        Some(parse_url(share)?)
+    } else {
+        Config::load()?
+            .share
+            .favorites
+            .get(share)
+            .and_then(|favorite| parse_url(&favorite.url).ok())
+    };
+    let mount = mounts
+        .iter()
+        .find(|mount| match &url {
+            Some(url) => mount.is_share(url),
+            None => {
+                mount.mount_point == share.trim_end_matches('/')
+                    || mount.mount_point.rsplit('/').next() == Some(share)
+                    || parse_url(&mount.url()).is_ok_and(|mounted| mounted.volume().eq_ignore_ascii_case(share))
+            }
+        })
+        .ok_or_else(|| format!("No mounted share matches '{share}'"))?;
+
+    let mut command = Command::new("diskutil");
+    command.arg("unmount");
+    if force {
+        command.arg("force");
+    }
+    let output = command
+        .arg(&mount.mount_point)
+        .output()
+        .map_err(|e| format!("Failed to run diskutil: {e}"))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        let message = if message.trim().is_empty() {
+            String::from_utf8_lossy(&output.stdout)
+        } else {
+            message
+        };
+        return Err(format!("Failed to unmount {}: {}", mount.mount_point, message.trim()).into());
+    }
+
+    println!("{} {}", "Unmounted".green(), mount.mount_point.bold());
+
+    Ok(())
+}
+
+/// Prints the mounted network shares and the favorites
+///
+/// # Errors
+///
+/// Returns an error if `mount` cannot be run or the configuration cannot be loaded.
+fn list() -> Result<(), Box<dyn Error>> {
+    let mounts = mounted_shares()?;
+    let config = Config::load()?;
+
+    println!("{}", "Mounted".bold());
+    if mounts.is_empty() {
+        println!("  No network shares mounted");
+    }
+    let width = mounts.iter().map(|mount| mount.url().len()).max().unwrap_or(0);
+    for mount in &mounts {
+        println!(
+            "  {:<width$}  {}  {}",
+            mount.url(),
+            mount.mount_point.bold(),
+            format!("({})", mount.file_system).dimmed()
+        );
+    }
+
+    println!();
+    println!("{}", "Favorites".bold());
+    if config.share.favorites.is_empty() {
+        println!("  No favorites saved. Save one with 'rem share mount <URL> --alias <NAME>'");
+    }
+    for (name, favorite) in &config.share.favorites {
+        let mounted = parse_url(&favorite.url).is_ok_and(|url| mounts.iter().any(|mount| mount.is_share(&url)));
+        let mut line = format!("{name:>30} : {}", favorite.url);
+        if favorite.keychain {
+            line.push_str(" (keychain)");
+        }
+        if mounted {
+            println!("{line}  {}", "mounted".green());
+        } else {
+            println!("{line}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Saves a share as favorite, replacing an existing favorite of the same name
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved.
+fn add_favorite(name: String, url: &ShareUrl, keychain: bool) -> Result<(), Box<dyn Error>> {
+    let favorite = ShareFavorite {
+        url: url.to_string(),
+        keychain,
+    };
+
+    let mut config = Config::load()?;
+    let replaced = config.share.favorites.insert(name.clone(), favorite).is_some();
+    config.save(&["share", "favorites"])?;
+
+    let action = if replaced { "Updated" } else { "Added" };
+    println!("{} favorite '{name}'", action.green());
+
+    Ok(())
+}
+
+/// Removes a favorite
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved, or the favorite does not exist.
+fn remove_favorite(name: &str) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load()?;
+    if config.share.favorites.remove(name).is_none() {
+        return Err(unknown_favorite(name, &config));
+    }
+    config.save(&["share", "favorites"])?;
+
+    println!("{} favorite '{name}'", "Removed".green());
+
+    Ok(())
+}
+
+/// Builds an error for an unknown favorite listing the saved favorites
+fn unknown_favorite(name: &str, config: &Config) -> Box<dyn Error> {
+    let available: Vec<&str> = config.share.favorites.keys().map(String::as_str).collect();
+    if available.is_empty() {
+        format!("Unknown favorite '{name}' (no favorites saved)").into()
+    } else {
+        format!("Unknown favorite '{name}' (available: {})", available.join(", ")).into()
+    }
+}
+
+/// Parses a share URL like `smb://alice@nas.local/media`
+///
+/// # Errors
+///
+/// Returns an error if the scheme is not `smb` or `afp`, the URL has no server or
+/// volume, or it contains a password.
+fn parse_url(url: &str) -> Result<ShareUrl, Box<dyn Error>> {
+    let invalid = || format!("Invalid share URL '{url}', expected smb://[USER@]SERVER/VOLUME or afp://...");
+    let (scheme, rest) = url.split_once("://").ok_or_else(invalid)?;
+    let scheme = scheme.to_lowercase();
+    if scheme != "smb" && scheme != "afp" {
+        return Err(invalid().into());
+    }
+    let (authority, path) = rest.split_once('/').ok_or_else(invalid)?;
+    let (user, server) = match authority.rsplit_once('@') {
+        Some((user, server)) => (Some(user), server),
+        None => (None, authority),
+    };
+    if user.is_some_and(|user| user.contains(':')) {
+        return Err("Do not put passwords in share URLs, use --keychain".into());
+    }
+    let path = path.trim_end_matches('/');
+    if server.is_empty() || path.is_empty() || user.is_some_and(str::is_empty) {
+        return Err(invalid().into());
+    }
+
+    Ok(ShareUrl {
+        scheme,
+        user: user.map(str::to_string),
+        server: server.to_string(),
+        path: path.to_string(),
+    })
+}
+
+/// Returns the mounted network file systems
+///
+/// # Errors
+///
+/// Returns an error if `mount` cannot be run.
+fn mounted_shares() -> Result<Vec<Mount>, Box<dyn Error>> {
+    let output = Command::new("mount")
+        .output()
+        .map_err(|e| format!("Failed to run mount: {e}"))?;
+
+    Ok(parse_mounts(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the output of `mount`, keeping network file systems
+///
+/// Lines look like `//alice@nas.local/media on /Volumes/media (smbfs, nodev,
+/// nosuid, mounted by alice)`.
+fn parse_mounts(output: &str) -> Vec<Mount> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (source, rest) = line.split_once(" on ")?;
+            let (mount_point, options) = rest.rsplit_once(" (")?;
+            let file_system = options.split([',', ')']).next()?.trim();
+            NETWORK_FILE_SYSTEMS.contains(&file_system).then(|| Mount {
+                source: source.to_string(),
+                mount_point: mount_point.to_string(),
+                file_system: file_system.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Decodes percent-encoded characters like `%20`
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Escapes a value for an AppleScript string literal
+fn applescript_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,303 @@
+//! File shredding and purgeable space
+//!
+//! Overwrites files with random data several times, flushing every pass to
+//! the drive, and then truncates, renames and deletes them. This destroys the
+//! data on hard disks, but not reliably on SSDs and APFS: APFS writes changes
+//! to new blocks, so snapshots and clones keep the old ones, and the SSD
+//! controller remaps writes to other cells. On these, only the encryption of
+//! FileVault protects deleted data, which is why a warning is shown first.
+//!
+//! `--purgeable` removes the local Time Machine snapshots of the startup
+//! volume, which make up most of its purgeable space, so the free space
+//! matches the space Finder shows as available before the volume is imaged.
+//! Caches and iCloud files are purged by macOS itself when space is needed.
+//!
+//! # System Commands Used
+//!
+//! - `tmutil thinlocalsnapshots` - Remove the local Time Machine snapshots (with sudo)
+
+#[cfg(target_os = "macos")]
+mod capacity;
+
+use crate::cli::ShredArgs;
+use crate::subcommands::storage::scan::clone_info;
+use crate::util::{confirm, format_bytes};
+use colored::Colorize;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(target_os = "macos")]
+use capacity::capacity;
+
+/// Size of the blocks written per pass
+const CHUNK: usize = 1024 * 1024;
+
+/// Source of the random data
+const RANDOM: &str = "/dev/urandom";
+
+/// Bytes asked to be purged, more than any volume has
+const PURGE_ALL: &str = "999999999999999";
+
+/// Urgency of the purge, 4 being the highest
+const URGENCY: &str = "4";
+
+/// Shreds files or purges the purgeable space of the startup volume
+///
+/// # Arguments
+///
+/// * `args` - Shred arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be shredded, stdin cannot be read or
+/// the snapshots cannot be removed.
+pub fn perform(args: ShredArgs) -> Result<(), Box<dyn Error>> {
+    if args.purgeable {
+        return purge(args.yes);
+    }
+
+    let mut files = Vec::new();
+    let mut folders = Vec::new();
+    for path in &args.files {
+        collect(path, args.recursive, &mut files, &mut folders)?;
+    }
+    if files.is_empty() && folders.is_empty() {
+        println!("Nothing to shred");
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        "Overwriting does not reliably destroy data on SSDs and APFS volumes, which write \
+         changes to new blocks and keep old ones in snapshots and clones. Use FileVault to keep \
+         deleted data encrypted."
+            .yellow()
+    );
+    if !args.yes {
+        let count = if files.len() == 1 {
+            "1 file".to_string()
+        } else {
+            format!("{} files", files.len())
+        };
+        if !confirm(&format!("Overwrite and delete {count} for good?"))? {
+            println!("Nothing shredded");
+            return Ok(());
+        }
+    }
+
+    let mut failed = 0;
+    for file in &files {
+        print!("Shred {}", file.display());
+        io::stdout().flush()?;
+        // Clones share the old blocks, which overwriting the file does not touch
+        let cloned = clone_info(file).is_some();
+        match shred(file, args.passes, args.zero) {
+            Ok(()) if cloned => println!("{}", " OK, but its clones keep the old data".yellow()),
+            Ok(()) => println!("{}", " OK".green()),
+            Err(e) => {
+                failed += 1;
+                println!("{}", format!(" {e}").red());
+            }
+        }
+    }
+    // Subfolders come after their parents
+    for folder in folders.iter().rev() {
+        if let Err(e) = fs::remove_dir(folder) {
+            println!("{}", format!("Failed to remove {}: {e}", folder.display()).red());
+        }
+    }
+    if failed > 0 {
+        return Err(format!("Failed to shred {failed} of {} files", files.len()).into());
+    }
+    Ok(())
+}
+
+/// Collects the files to shred
+///
+/// # Arguments
+///
+/// * `path` - File, or folder if `recursive` is set
+/// * `recursive` - Shred the files in folders and remove the folders
+/// * `files` - Files found
+/// * `folders` - Folders found, parents first
+///
+/// # Errors
+///
+/// Returns an error if a path does not exist, is a folder without
+/// `recursive`, a symbolic link or a file with several hard links.
+fn collect(
+    path: &Path,
+    recursive: bool,
+    files: &mut Vec<PathBuf>,
+    folders: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let metadata = path
+        .symlink_metadata()
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    if metadata.is_symlink() {
+        return Err(format!("{} is a symbolic link; shred the file it points to", path.display()).into());
+    }
+    if metadata.is_dir() {
+        if !recursive {
+            return Err(format!(
+                "{} is a folder; use --recursive to shred the files in it",
+                path.display()
+            )
+            .into());
+        }
+        folders.push(path.to_path_buf());
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+        for entry in entries {
+            collect(&entry, recursive, files, folders)?;
+        }
+        return Ok(());
+    }
+    if !metadata.is_file() {
+        return Err(format!("{} is not a regular file", path.display()).into());
+    }
+    // Overwriting would destroy the data of the other names as well
+    if metadata.nlink() > 1 {
+        return Err(format!(
+            "{} has {} hard links; delete the others first",
+            path.display(),
+            metadata.nlink()
+        )
+        .into());
+    }
+    files.push(path.to_path_buf());
+    Ok(())
+}
+
+/// Overwrites a file, then truncates, renames and deletes it
+///
+/// # Arguments
+///
+/// * `path` - File to shred
+/// * `passes` - Number of passes with random data
+/// * `zero` - Finish with a pass of zeros
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written or deleted.
+fn shred(path: &Path, passes: u32, zero: bool) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let size = file.metadata()?.len();
+    let mut random = File::open(RANDOM)?;
+    let mut buffer = vec![0; CHUNK];
+    for pass in 0..passes + u32::from(zero) {
+        let zeros = pass == passes;
+        if zeros {
+            buffer.fill(0);
+        }
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = size;
+        while remaining > 0 {
+            let length = remaining.min(CHUNK as u64) as usize;
+            if !zeros {
+                random.read_exact(&mut buffer[..length])?;
+            }
+            file.write_all(&buffer[..length])?;
+            remaining -= length as u64;
+        }
+        // Flushes the drive cache as well
+        file.sync_all()?;
+    }
+    file.set_len(0)?;
+    file.sync_all()?;
+    drop(file);
+
+    // Renaming to zeros of the same length hides the name in the folder
+    let length = path.file_name().map_or(1, |name| name.len());
+    let hidden = path.with_file_name("0".repeat(length));
+    let path = if hidden.symlink_metadata().is_err() && fs::rename(path, &hidden).is_ok() {
+        hidden
+    } else {
+        path.to_path_buf()
+    };
+    fs::remove_file(path)
+}
+
+/// Removes the local Time Machine snapshots of the startup volume
+///
+/// # Arguments
+///
+/// * `yes` - Remove the snapshots without asking for confirmation
+///
+/// # Errors
+///
+/// Returns an error if `tmutil` fails, or confirmation is needed without a terminal.
+fn purge(yes: bool) -> Result<(), Box<dyn Error>> {
+    let volume = Path::new("/");
+    let before = capacity(volume);
+    if let Some((free, available)) = before {
+        print_capacity(free, available);
+    }
+
+    if !yes && !confirm("Remove all local Time Machine snapshots for good?")? {
+        println!("No snapshots removed");
+        return Ok(());
+    }
+
+    print!("Remove local Time Machine snapshots");
+    io::stdout().flush()?;
+    let output = Command::new("sudo")
+        .args(["tmutil", "thinlocalsnapshots"])
+        .arg(volume)
+        .args([PURGE_ALL, URGENCY])
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !output.status.success() {
+        println!();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("tmutil failed: {}", stderr.trim()).into());
+    }
+    // The snapshots follow a heading line
+    let removed = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .count();
+    let snapshots = if removed == 1 { "snapshot" } else { "snapshots" };
+    println!("{}", format!(" {removed} {snapshots} removed").green());
+
+    if let (Some((before, _)), Some((free, available))) = (before, capacity(volume)) {
+        print_capacity(free, available);
+        println!(
+            "{:<16}{}",
+            "Recovered".bold(),
+            format_bytes(free.saturating_sub(before) as f64).green()
+        );
+        if available > free {
+            println!(
+                "{}",
+                "The remaining purgeable space are caches and iCloud files macOS removes when space is needed".dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Prints the free and purgeable space
+fn print_capacity(free: u64, available: u64) {
+    println!("{:<16}{}", "Free".bold(), format_bytes(free as f64));
+    println!(
+        "{:<16}{}",
+        "Purgeable".bold(),
+        format_bytes(available.saturating_sub(free) as f64)
+    );
+}
+
+/// Reads the free space and the space available with purgeable space of a volume; requires macOS
+#[cfg(not(target_os = "macos"))]
+fn capacity(_volume: &Path) -> Option<(u64, u64)> {
+    None
+}
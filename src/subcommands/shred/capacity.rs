@@ -0,0 +1,53 @@
+//! Free and purgeable space of a volume
+//!
+//! The free space of a volume leaves out purgeable space: local Time Machine
+//! snapshots, caches and downloaded iCloud files that macOS removes when the
+//! space is needed. The capacity available for important usage, which Finder
+//! shows as available, includes it.
+
+use core_foundation::base::{CFTypeRef, TCFType};
+use core_foundation::number::{CFNumber, CFNumberRef};
+use core_foundation::string::CFStringRef;
+use core_foundation::url::{CFURL, CFURLRef};
+use std::ffi::c_void;
+use std::path::Path;
+use std::ptr;
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    static kCFURLVolumeAvailableCapacityKey: CFStringRef;
+    static kCFURLVolumeAvailableCapacityForImportantUsageKey: CFStringRef;
+    fn CFURLCopyResourcePropertyForKey(
+        url: CFURLRef,
+        key: CFStringRef,
+        value: *mut CFTypeRef,
+        error: *mut c_void,
+    ) -> u8;
+}
+
+/// Reads the free space and the space available with purgeable space of a volume
+///
+/// # Arguments
+///
+/// * `volume` - Mount point or any path on the volume
+///
+/// # Returns
+///
+/// The free and the available space in bytes, or `None` if they cannot be read.
+pub fn capacity(volume: &Path) -> Option<(u64, u64)> {
+    let url = CFURL::from_path(volume, true)?;
+    let free = unsafe { number(&url, kCFURLVolumeAvailableCapacityKey) }?;
+    let available = unsafe { number(&url, kCFURLVolumeAvailableCapacityForImportantUsageKey) }?;
+    Some((free, available))
+}
+
+/// Reads a number resource property of a URL
+unsafe fn number(url: &CFURL, key: CFStringRef) -> Option<u64> {
+    let mut value: CFTypeRef = ptr::null();
+    let found = unsafe { CFURLCopyResourcePropertyForKey(url.as_concrete_TypeRef(), key, &mut value, ptr::null_mut()) };
+    if found == 0 || value.is_null() {
+        return None;
+    }
+    let number = unsafe { CFNumber::wrap_under_create_rule(value as CFNumberRef) };
+    number.to_i64().and_then(|number| u64::try_from(number).ok())
+}
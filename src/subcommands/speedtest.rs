@@ -0,0 +1,152 @@
+//! Network speed test subcommand
+//!
+//! Runs Apple's `networkQuality` tool (macOS 12 and later), which saturates the
+//! connection with parallel HTTP/2 transfers to Apple's CDN, and prints its
+//! machine-readable result in a compact form.
+//!
+//! Besides throughput, `networkQuality` measures the idle latency and the
+//! responsiveness under load in round trips per minute (RPM); a low
+//! responsiveness means that latency rises sharply while the link is busy
+//! (bufferbloat).
+//!
+//! # System Commands Used
+//!
+//! - `networkQuality -c` - Run the test with JSON output
+
+use crate::cli::SpeedtestArgs;
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::io::ErrorKind;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Result of a speed test
+#[derive(Serialize)]
+struct SpeedResult {
+    /// End of the test as reported by `networkQuality`, or seconds since the Unix epoch
+    timestamp: String,
+    /// Tested network interface
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interface: Option<String>,
+    /// Download throughput in Mbit/s
+    download_mbps: Option<f64>,
+    /// Upload throughput in Mbit/s
+    upload_mbps: Option<f64>,
+    /// Round trip time of the idle connection in milliseconds
+    idle_latency_ms: Option<f64>,
+    /// Round trips per minute under load
+    responsiveness_rpm: Option<f64>,
+}
+
+/// Runs a speed test and prints the result
+///
+/// # Arguments
+///
+/// * `args` - Speed test arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if `networkQuality` is not available, fails, or prints
+/// output that is not understood.
+pub fn perform(args: SpeedtestArgs) -> Result<(), Box<dyn Error>> {
+    let mut command = Command::new("networkQuality");
+    command.arg("-c");
+    if args.sequential {
+        command.arg("-s");
+    }
+    if let Some(interface) = &args.interface {
+        command.args(["-I", interface]);
+    }
+
+    if !args.json {
+        println!("Measuring network quality, this takes about 20 seconds...");
+    }
+    let output = command.output().map_err(|e| match e.kind() {
+        ErrorKind::NotFound => "networkQuality not found, it requires macOS 12 or later".into(),
+        _ => Box::<dyn Error>::from(e),
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("networkQuality failed: {}", stderr.trim()).into());
+    }
+
+    let report: Value =
+        serde_json::from_slice(&output.stdout).map_err(|e| format!("Unexpected output of networkQuality: {e}"))?;
+    let result = parse_report(&report);
+
+    if args.json {
+        println!("{}", serde_json::to_string(&result)?);
+        return Ok(());
+    }
+
+    let format_rate = |rate: Option<f64>| rate.map_or("-".to_string(), |rate| format!("{rate:.1} Mbit/s"));
+    println!();
+    println!("{:<16} {}", "Download", format_rate(result.download_mbps).bold());
+    println!("{:<16} {}", "Upload", format_rate(result.upload_mbps).bold());
+    println!(
+        "{:<16} {}",
+        "Idle latency",
+        result
+            .idle_latency_ms
+            .map_or("-".to_string(), |latency| format!("{latency:.1} ms"))
+    );
+    println!(
+        "{:<16} {}",
+        "Responsiveness",
+        result
+            .responsiveness_rpm
+            .map_or("-".normal(), |rpm| format!("{rpm:.0} RPM ({})", rating(rpm)).normal())
+    );
+    if let Some(interface) = &result.interface {
+        println!("{:<16} {interface}", "Interface");
+    }
+
+    Ok(())
+}
+
+/// Extracts the result from the JSON report of `networkQuality`
+///
+/// Throughput is reported in bit/s. Newer versions report the responsiveness
+/// separately for download and upload; the lower value is used then.
+fn parse_report(report: &Value) -> SpeedResult {
+    let number = |key: &str| report.get(key).and_then(Value::as_f64);
+    let mbps = |key: &str| number(key).map(|bits| bits / 1_000_000.0);
+
+    let responsiveness =
+        number("responsiveness").or_else(|| match (number("dl_responsiveness"), number("ul_responsiveness")) {
+            (Some(download), Some(upload)) => Some(download.min(upload)),
+            (download, upload) => download.or(upload),
+        });
+    let timestamp = report
+        .get("end_date")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs().to_string())
+                .unwrap_or_default()
+        });
+
+    SpeedResult {
+        timestamp,
+        interface: report.get("interface_name").and_then(Value::as_str).map(str::to_string),
+        download_mbps: mbps("dl_throughput"),
+        upload_mbps: mbps("ul_throughput"),
+        idle_latency_ms: number("base_rtt"),
+        responsiveness_rpm: responsiveness,
+    }
+}
+
+/// Rates a responsiveness value
+fn rating(rpm: f64) -> ColoredString {
+    if rpm >= 800.0 {
+        "high".green()
+    } else if rpm >= 200.0 {
+        "medium".yellow()
+    } else {
+        "low".red()
+    }
+}
@@ -0,0 +1,437 @@
+//! Spotlight indexing
+//!
+//! Shows whether Spotlight indexes each volume and how much CPU its
+//! processes use, turns indexing of a volume on and off, and rebuilds an
+//! index, which helps when `mds` keeps a core busy or a volume refuses to
+//! index. Volumes are given by mount point or by name below `/Volumes`.
+//!
+//! The folders listed under Privacy in the Spotlight settings are the
+//! `Exclusions` of `.Spotlight-V100/VolumeConfiguration.plist` at the root of
+//! their volume, on the startup disk the data volume. The file belongs to
+//! root, so reading and changing it uses `sudo`, and recent macOS versions
+//! additionally require Full Disk Access for the terminal. `mds` is restarted
+//! after a change to pick it up.
+//!
+//! # System Commands Used
+//!
+//! - `mdutil -s -a` - Read the indexing state of all volumes
+//! - `sudo mdutil -i on|off` / `sudo mdutil -E` - Switch indexing and rebuild an index
+//! - `sudo plutil -extract` / `-replace` - Read and change the exclusions
+//! - `sudo killall mds` - Restart Spotlight after changing the exclusions
+//! - `ps` - Read the CPU usage of the Spotlight processes
+
+use crate::cli::{SpotlightArgs, SpotlightCommands, SpotlightExclusionsCommands};
+use crate::util::match_name;
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{self, Path, PathBuf};
+use std::process::Command;
+
+/// Folder with the mount points of other volumes
+const VOLUMES: &str = "/Volumes";
+
+/// Mount point of the data volume of the startup disk
+const DATA_VOLUME: &str = "/System/Volumes/Data";
+
+/// Configuration of Spotlight relative to the root of a volume
+const VOLUME_CONFIGURATION: &str = ".Spotlight-V100/VolumeConfiguration.plist";
+
+/// Name prefixes of the Spotlight processes
+const PROCESSES: [&str; 2] = ["mds", "mdworker"];
+
+/// Performs Spotlight operations
+///
+/// # Arguments
+///
+/// * `args` - Spotlight arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a volume does not exist or `mdutil` or `plutil` fails.
+pub fn perform(args: SpotlightArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(SpotlightCommands::Status) => status(),
+        Some(SpotlightCommands::On { volume }) => set_indexing(volume.as_deref(), true),
+        Some(SpotlightCommands::Off { volume }) => set_indexing(volume.as_deref(), false),
+        Some(SpotlightCommands::Rebuild { volume }) => rebuild(volume.as_deref()),
+        Some(SpotlightCommands::Exclusions { command }) => match command {
+            None | Some(SpotlightExclusionsCommands::List) => list_exclusions(),
+            Some(SpotlightExclusionsCommands::Add { paths }) => add_exclusions(&paths),
+            Some(SpotlightExclusionsCommands::Remove { paths }) => remove_exclusions(&paths),
+        },
+    }
+}
+
+/// Prints the indexing state of all volumes and the CPU used by Spotlight
+///
+/// # Errors
+///
+/// Returns an error if `mdutil` fails.
+fn status() -> Result<(), Box<dyn Error>> {
+    let output = Command::new("mdutil")
+        .args(["-s", "-a"])
+        .output()
+        .map_err(|e| format!("Failed to run mdutil: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("mdutil failed: {}", stderr.trim()).into());
+    }
+
+    // Every volume is a line ending with a colon, followed by its indented state
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut volume: Option<&str> = None;
+    for line in stdout.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            volume = line.trim().strip_suffix(':');
+            continue;
+        }
+        let Some(name) = volume.take() else {
+            continue;
+        };
+        let state = line.trim().trim_end_matches('.');
+        let state = if state.contains("enabled") {
+            state.green()
+        } else if state.contains("disabled") {
+            state.yellow()
+        } else {
+            state.dimmed()
+        };
+        println!("{:<32}{state}", name.bold());
+    }
+
+    if let Some(cpu) = cpu_usage() {
+        println!();
+        println!("{:<32}{cpu:.1}%", "CPU used by Spotlight".bold());
+    }
+    Ok(())
+}
+
+/// Returns the CPU usage of the Spotlight processes in percent of one core
+fn cpu_usage() -> Option<f64> {
+    let output = Command::new("ps")
+        .args(["-A", "-o", "%cpu=", "-o", "comm="])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let usage = stdout
+        .lines()
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .filter(|(_, command)| {
+            let name = command.trim().rsplit('/').next().unwrap_or_default();
+            PROCESSES.iter().any(|process| name.starts_with(process))
+        })
+        .filter_map(|(cpu, _)| cpu.parse::<f64>().ok())
+        .sum();
+    Some(usage)
+}
+
+/// Turns indexing of a volume on or off
+///
+/// # Arguments
+///
+/// * `volume` - Mount point or volume name; the startup volume if `None`
+/// * `on` - Whether to index the volume
+///
+/// # Errors
+///
+/// Returns an error if the volume does not exist or `mdutil` fails.
+fn set_indexing(volume: Option<&str>, on: bool) -> Result<(), Box<dyn Error>> {
+    let volume = resolve_volume(volume)?;
+    let state = if on { "on" } else { "off" };
+    print!("Turn Spotlight indexing {state} for {}", volume.display());
+    io::stdout().flush()?;
+    if let Err(e) = mdutil(&["-i", state], &volume) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Deletes the index of a volume so Spotlight builds it again
+///
+/// # Arguments
+///
+/// * `volume` - Mount point or volume name; the startup volume if `None`
+///
+/// # Errors
+///
+/// Returns an error if the volume does not exist or `mdutil` fails.
+fn rebuild(volume: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let volume = resolve_volume(volume)?;
+    print!("Rebuild the Spotlight index of {}", volume.display());
+    io::stdout().flush()?;
+    if let Err(e) = mdutil(&["-E"], &volume) {
+        println!();
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+    println!("Spotlight indexes the volume in the background, which can take a while");
+    Ok(())
+}
+
+/// Runs `mdutil` with sudo for a volume
+///
+/// # Errors
+///
+/// Returns an error if `mdutil` fails; it reports some errors on stdout with
+/// a successful exit status.
+fn mdutil(args: &[&str], volume: &Path) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("sudo")
+        .arg("mdutil")
+        .args(args)
+        .arg(volume)
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if let Some(error) = stdout.lines().find(|line| line.contains("Error")) {
+        return Err(format!("mdutil failed: {}", error.trim()).into());
+    }
+    if !output.status.success() {
+        return Err(format!("mdutil failed: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Returns the mount point of a volume given by mount point or name
+///
+/// # Errors
+///
+/// Returns an error if no volume or more than one volume matches.
+fn resolve_volume(volume: Option<&str>) -> Result<PathBuf, Box<dyn Error>> {
+    let Some(volume) = volume else {
+        return Ok(PathBuf::from("/"));
+    };
+    if volume.starts_with('/') {
+        let path = PathBuf::from(volume);
+        if !path.is_dir() {
+            return Err(format!("{volume} is not a mounted volume").into());
+        }
+        return Ok(path);
+    }
+    let names: Vec<String> = fs::read_dir(VOLUMES)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let index = match_name(&names, volume)?.ok_or_else(|| format!("No volume matches '{volume}'"))?;
+    Ok(Path::new(VOLUMES).join(names[index]))
+}
+
+/// Prints the excluded folders of all volumes
+///
+/// # Errors
+///
+/// Returns an error if the exclusions of the startup disk cannot be read.
+fn list_exclusions() -> Result<(), Box<dyn Error>> {
+    let mut exclusions = read_exclusions(Path::new(DATA_VOLUME))?;
+    // Other volumes that were never indexed have no configuration
+    if let Ok(volumes) = fs::read_dir(VOLUMES) {
+        for volume in volumes.flatten() {
+            if volume.file_type().is_ok_and(|file_type| file_type.is_dir())
+                && let Ok(paths) = read_exclusions(&volume.path())
+            {
+                exclusions.extend(paths);
+            }
+        }
+    }
+    if exclusions.is_empty() {
+        println!("No folders are excluded from Spotlight");
+    }
+    for exclusion in exclusions {
+        println!("{exclusion}");
+    }
+    Ok(())
+}
+
+/// Excludes folders from Spotlight
+///
+/// # Errors
+///
+/// Returns an error if a folder does not exist or the exclusions cannot be changed.
+fn add_exclusions(paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    let mut changed = false;
+    for path in paths {
+        let path = path
+            .canonicalize()
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        let exclusion = path.to_string_lossy().into_owned();
+        let volume = volume_of(&path)?;
+        let mut exclusions = read_exclusions(&volume)?;
+        if exclusions.contains(&exclusion) {
+            println!("{exclusion} is already excluded");
+            continue;
+        }
+        print!("Exclude {exclusion} from Spotlight");
+        io::stdout().flush()?;
+        exclusions.push(exclusion);
+        if let Err(e) = write_exclusions(&volume, &exclusions) {
+            println!();
+            return Err(e);
+        }
+        println!("{}", " OK".green());
+        changed = true;
+    }
+    if changed {
+        restart()?;
+    }
+    Ok(())
+}
+
+/// Lets Spotlight index excluded folders again
+///
+/// # Errors
+///
+/// Returns an error if the exclusions cannot be changed.
+fn remove_exclusions(paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    let mut changed = false;
+    for path in paths {
+        // Excluded folders may have been deleted since
+        let path = path.canonicalize().or_else(|_| path::absolute(path))?;
+        let exclusion = path.to_string_lossy().into_owned();
+        let volume = volume_of(&path)?;
+        let mut exclusions = read_exclusions(&volume)?;
+        let Some(index) = exclusions.iter().position(|excluded| *excluded == exclusion) else {
+            println!("{exclusion} is not excluded");
+            continue;
+        };
+        print!("Index {exclusion} again");
+        io::stdout().flush()?;
+        exclusions.remove(index);
+        if let Err(e) = write_exclusions(&volume, &exclusions) {
+            println!();
+            return Err(e);
+        }
+        println!("{}", " OK".green());
+        changed = true;
+    }
+    if changed {
+        restart()?;
+    }
+    Ok(())
+}
+
+/// Returns the mount point of the volume a path is on
+///
+/// Paths of the startup disk like `/Users` are on its data volume through
+/// firmlinks, so they belong to the data volume rather than to `/`.
+///
+/// # Errors
+///
+/// Returns an error if no part of the path exists.
+fn volume_of(path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| format!("Failed to open {}", path.display()))?;
+    let device = fs::metadata(existing)?.dev();
+    if fs::metadata(DATA_VOLUME).is_ok_and(|metadata| metadata.dev() == device) {
+        return Ok(PathBuf::from(DATA_VOLUME));
+    }
+    let mut volume = existing;
+    while let Some(parent) = volume.parent()
+        && fs::metadata(parent).is_ok_and(|metadata| metadata.dev() == device)
+    {
+        volume = parent;
+    }
+    Ok(volume.to_path_buf())
+}
+
+/// Reads the excluded folders of a volume
+///
+/// # Errors
+///
+/// Returns an error if the configuration of the volume cannot be read.
+fn read_exclusions(volume: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let configuration = volume.join(VOLUME_CONFIGURATION);
+    let output = Command::new("sudo")
+        .args(["plutil", "-extract", "Exclusions", "json", "-o", "-"])
+        .arg(&configuration)
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !output.status.success() {
+        let message = plutil_error(&output.stdout, &output.stderr);
+        // A configuration without exclusions has no such key
+        if message.contains("No value at that key path") {
+            return Ok(Vec::new());
+        }
+        return Err(configuration_error("read", &configuration, &message));
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Replaces the excluded folders of a volume
+///
+/// # Errors
+///
+/// Returns an error if the configuration of the volume cannot be changed.
+fn write_exclusions(volume: &Path, exclusions: &[String]) -> Result<(), Box<dyn Error>> {
+    let configuration = volume.join(VOLUME_CONFIGURATION);
+    let output = Command::new("sudo")
+        .args([
+            "plutil",
+            "-replace",
+            "Exclusions",
+            "-json",
+            &serde_json::to_string(exclusions)?,
+        ])
+        .arg(&configuration)
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !output.status.success() {
+        let message = plutil_error(&output.stdout, &output.stderr);
+        return Err(configuration_error("change", &configuration, &message));
+    }
+    Ok(())
+}
+
+/// Returns the message of a failed `plutil` command, which may be on stdout
+fn plutil_error(stdout: &[u8], stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    if stderr.trim().is_empty() {
+        String::from_utf8_lossy(stdout).trim().to_string()
+    } else {
+        stderr.trim().to_string()
+    }
+}
+
+/// Builds the error of a configuration that cannot be read or changed
+fn configuration_error(action: &str, configuration: &Path, message: &str) -> Box<dyn Error> {
+    let hint = if message.contains("Operation not permitted") {
+        "; give the terminal Full Disk Access in System Settings > Privacy & Security"
+    } else {
+        ""
+    };
+    format!("Failed to {action} {}: {message}{hint}", configuration.display()).into()
+}
+
+/// Restarts Spotlight so it picks up changed exclusions
+///
+/// # Errors
+///
+/// Returns an error if `mds` cannot be restarted.
+fn restart() -> Result<(), Box<dyn Error>> {
+    print!("Restart Spotlight");
+    io::stdout().flush()?;
+    // launchd starts mds again right away
+    let output = Command::new("sudo")
+        .args(["killall", "mds"])
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    if !output.status.success() {
+        println!();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to restart mds: {}", stderr.trim()).into());
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
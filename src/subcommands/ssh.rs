@@ -0,0 +1,114 @@
+//! SSH client configuration
+//!
+//! Manages host entries of the SSH client configuration (`~/.ssh/config`) and
+//! removes stale host keys from `~/.ssh/known_hosts`. Both files are edited line
+//! by line: comments, blank lines, indentation and all entries that are not
+//! changed stay exactly as they are.
+//!
+//! Before every change the current file is copied to the rempower state
+//! directory (`ssh_config.backup` and `known_hosts.backup`).
+
+pub mod hosts;
+pub mod known;
+
+use crate::cli::{SshArgs, SshCommands};
+use crate::config;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+/// Performs SSH configuration operations
+///
+/// # Arguments
+///
+/// * `args` - SSH arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or written, or the requested entry
+/// does not exist.
+pub fn perform(args: SshArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        SshCommands::Hosts { command } => hosts::perform(command),
+        SshCommands::Known { command } => known::perform(command),
+    }
+}
+
+/// Returns the path of a file in the SSH directory of the current user (`~/.ssh`)
+///
+/// # Errors
+///
+/// Returns an error if the home directory cannot be determined.
+pub fn ssh_file(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(config::home_dir()?.join(".ssh").join(name))
+}
+
+/// Reads a file of the SSH directory as lines
+///
+/// # Returns
+///
+/// The content and its lines; both are empty if the file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read.
+pub fn read_lines(path: &Path) -> Result<(String, Vec<String>), Box<dyn Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display()).into()),
+    };
+    let lines = content.lines().map(str::to_string).collect();
+    Ok((content, lines))
+}
+
+/// Writes changed lines back to a file of the SSH directory
+///
+/// The previous content is copied to `backup` in the rempower state directory
+/// first. Missing files are created readable by the owner only, as SSH requires.
+///
+/// # Arguments
+///
+/// * `path` - File to write
+/// * `content` - Previous content of the file
+/// * `lines` - New lines of the file
+/// * `backup` - Name of the backup file inside the state directory
+///
+/// # Returns
+///
+/// False if the content did not change and nothing was written.
+///
+/// # Errors
+///
+/// Returns an error if the backup or the file cannot be written.
+pub fn write_lines(path: &Path, content: &str, lines: &[String], backup: &str) -> Result<bool, Box<dyn Error>> {
+    let mut new_content = lines.join("\n");
+    if !new_content.is_empty() {
+        new_content.push('\n');
+    }
+    if new_content == content {
+        return Ok(false);
+    }
+
+    let dir = config::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(backup), content)?;
+
+    if let Some(parent) = path.parent()
+        && !parent.exists()
+    {
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(parent)?;
+    }
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(new_content.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    Ok(true)
+}
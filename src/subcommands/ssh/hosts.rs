@@ -0,0 +1,460 @@
+//! Host entries of the SSH client configuration
+//!
+//! A host entry is a `Host` line with its options up to the next `Host` or
+//! `Match` line. SSH uses the first value it finds for each option, so new
+//! entries are inserted before the first `Host *` or `Match` block, whose
+//! defaults would otherwise take precedence.
+
+use super::{read_lines, ssh_file, write_lines};
+use crate::cli::SshHostsCommands;
+use colored::Colorize;
+use std::error::Error;
+use std::path::Path;
+
+/// Name of the SSH client configuration file inside `~/.ssh`
+const CONFIG_FILE: &str = "config";
+
+/// Name of the backup file inside the state directory
+const BACKUP_FILE: &str = "ssh_config.backup";
+
+/// Indentation of options in new entries if the file has no indented options
+const DEFAULT_INDENT: &str = "    ";
+
+/// A `Host` or `Match` block of the configuration
+struct Block {
+    /// Patterns of the `Host` line; empty for `Match` blocks
+    patterns: Vec<String>,
+    /// True for `Match` blocks
+    is_match: bool,
+    /// Index of the `Host` or `Match` line
+    start: usize,
+    /// Index of the last option line of the block
+    end: usize,
+    /// Options of the block as lowercase keyword and value, in file order
+    options: Vec<(String, String)>,
+}
+
+impl Block {
+    /// Returns the first value of an option
+    fn option(&self, keyword: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(key, _)| key == keyword)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns true if the block applies to all hosts (`Host *` or `Match`)
+    fn is_catch_all(&self) -> bool {
+        self.is_match || self.patterns.iter().any(|pattern| pattern == "*")
+    }
+}
+
+/// Options of a new host entry
+struct HostEntry {
+    /// Real host name or address to connect to
+    hostname: String,
+    /// User name to log in with
+    user: Option<String>,
+    /// Port to connect to
+    port: Option<u16>,
+    /// Private key file
+    identity: Option<String>,
+    /// Jump hosts to connect through
+    jump: Option<String>,
+    /// Further options as `KEY=VALUE`
+    options: Vec<String>,
+}
+
+/// Performs host entry operations
+///
+/// # Arguments
+///
+/// * `command` - Host operation from the command line
+///
+/// # Errors
+///
+/// Returns an error if the configuration file cannot be read or written, an
+/// alias or option is invalid, or a host to remove does not exist.
+pub fn perform(command: SshHostsCommands) -> Result<(), Box<dyn Error>> {
+    let path = ssh_file(CONFIG_FILE)?;
+    let (content, mut lines) = read_lines(&path)?;
+
+    match command {
+        SshHostsCommands::List => {
+            list_hosts(&lines, &path);
+            return Ok(());
+        }
+        SshHostsCommands::Add {
+            alias,
+            hostname,
+            user,
+            port,
+            identity,
+            jump,
+            options,
+        } => {
+            let entry = HostEntry {
+                hostname,
+                user,
+                port,
+                identity,
+                jump,
+                options,
+            };
+            let replaced = add_host(&mut lines, &alias, &entry)?;
+            let action = if replaced { "Updated" } else { "Added" };
+            println!("{} host '{alias}'", action.green());
+        }
+        SshHostsCommands::Remove { alias } => {
+            remove_host(&mut lines, &alias)?;
+            println!("{} host '{alias}'", "Removed".green());
+        }
+    }
+
+    if !write_lines(&path, &content, &lines, BACKUP_FILE)? {
+        println!("{} unchanged", path.display());
+    }
+
+    Ok(())
+}
+
+/// Prints the host entries as a table
+fn list_hosts(lines: &[String], path: &Path) {
+    let blocks: Vec<Block> = parse_blocks(lines)
+        .into_iter()
+        .filter(|block| !block.is_match)
+        .collect();
+    if blocks.is_empty() {
+        println!("No hosts configured in {}", path.display());
+        return;
+    }
+
+    let rows: Vec<[String; 5]> = blocks
+        .iter()
+        .map(|block| {
+            let value = |keyword| block.option(keyword).unwrap_or("-").to_string();
+            [
+                block.patterns.join(" "),
+                value("hostname"),
+                value("user"),
+                value("port"),
+                value("identityfile"),
+            ]
+        })
+        .collect();
+    let headers = ["HOST", "HOSTNAME", "USER", "PORT", "IDENTITY"];
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(headers[column].len())
+        })
+        .collect();
+
+    let format_row = |cells: &[String]| {
+        let row: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        row.join("  ").trim_end().to_string()
+    };
+    println!("{}", format_row(&headers.map(str::to_string)));
+    for (block, row) in blocks.iter().zip(&rows) {
+        let row = format_row(row);
+        if block.is_catch_all() || block.patterns.iter().any(|pattern| pattern.contains(['*', '?', '!'])) {
+            println!("{}", row.dimmed());
+        } else {
+            println!("{row}");
+        }
+    }
+}
+
+/// Adds a host entry, replacing an existing entry for exactly this alias
+///
+/// # Returns
+///
+/// True if an existing entry was replaced.
+///
+/// # Errors
+///
+/// Returns an error if the alias or an option is invalid.
+fn add_host(lines: &mut Vec<String>, alias: &str, entry: &HostEntry) -> Result<bool, Box<dyn Error>> {
+    if alias.is_empty() || alias.contains(|c: char| c.is_whitespace() || "*?!,\"".contains(c)) {
+        return Err(format!("Invalid alias '{alias}', aliases cannot contain spaces or patterns").into());
+    }
+    let optional = [&entry.user, &entry.identity, &entry.jump];
+    let mut values = optional.into_iter().flatten().chain(&entry.options);
+    if entry.hostname.contains(['\n', '\r']) || values.any(|value| value.contains(['\n', '\r'])) {
+        return Err("Values cannot contain line breaks".into());
+    }
+
+    let indent = lines
+        .iter()
+        .find(|line| line.starts_with([' ', '\t']) && !line.trim().is_empty())
+        .map(|line| line[..line.len() - line.trim_start().len()].to_string())
+        .unwrap_or_else(|| DEFAULT_INDENT.to_string());
+    let mut new_block = vec![format!("Host {alias}"), format!("{indent}HostName {}", entry.hostname)];
+    let optional = [
+        ("User", entry.user.clone()),
+        ("Port", entry.port.map(|port| port.to_string())),
+        ("IdentityFile", entry.identity.clone()),
+        ("ProxyJump", entry.jump.clone()),
+    ];
+    for (keyword, value) in optional {
+        if let Some(value) = value {
+            new_block.push(format!("{indent}{keyword} {value}"));
+        }
+    }
+    for option in &entry.options {
+        let (keyword, value) = split_option(option)
+            .filter(|(_, value)| !value.is_empty())
+            .ok_or_else(|| format!("Invalid option '{option}', expected KEY=VALUE"))?;
+        // These would start a new block and split the entry
+        if keyword.eq_ignore_ascii_case("host") || keyword.eq_ignore_ascii_case("match") {
+            return Err(format!("Invalid option '{option}', {keyword} cannot be set within a host entry").into());
+        }
+        new_block.push(format!("{indent}{keyword} {value}"));
+    }
+
+    let blocks = parse_blocks(lines);
+    if let Some(block) = blocks.iter().find(|block| block.patterns == [alias]) {
+        lines.splice(block.start..=block.end, new_block);
+        return Ok(true);
+    }
+
+    match blocks.iter().find(|block| block.is_catch_all()) {
+        Some(block) => {
+            // Keep comments describing the catch-all block above it
+            let mut position = block.start;
+            while position > 0 && lines[position - 1].trim_start().starts_with('#') {
+                position -= 1;
+            }
+            new_block.push(String::new());
+            lines.splice(position..position, new_block);
+        }
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.extend(new_block);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Removes the entry of an alias, or the alias from a `Host` line with several patterns
+///
+/// # Errors
+///
+/// Returns an error if no `Host` line contains the alias.
+fn remove_host(lines: &mut Vec<String>, alias: &str) -> Result<(), Box<dyn Error>> {
+    let blocks = parse_blocks(lines);
+    let block = blocks
+        .iter()
+        .find(|block| block.patterns.iter().any(|pattern| pattern == alias))
+        .ok_or_else(|| {
+            let aliases: Vec<String> = blocks
+                .iter()
+                .filter(|block| !block.is_catch_all())
+                .flat_map(|block| block.patterns.clone())
+                .collect();
+            format!("Unknown host '{alias}' (configured: {})", aliases.join(", "))
+        })?;
+
+    if block.patterns.len() > 1 {
+        let line = &lines[block.start];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let keyword = line.trim_start().split([' ', '\t', '=']).next().unwrap_or("Host");
+        let remaining: Vec<&str> = block
+            .patterns
+            .iter()
+            .filter(|pattern| *pattern != alias)
+            .map(String::as_str)
+            .collect();
+        lines[block.start] = format!("{indent}{keyword} {}", remaining.join(" "));
+        return Ok(());
+    }
+
+    lines.drain(block.start..=block.end);
+    // Do not leave two blank lines where the entry was
+    let blank = |index: usize, lines: &Vec<String>| lines.get(index).is_some_and(|line| line.trim().is_empty());
+    if block.start < lines.len() && blank(block.start, lines) && (block.start == 0 || blank(block.start - 1, lines)) {
+        lines.remove(block.start);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    Ok(())
+}
+
+/// Splits the `Host` and `Match` blocks of the configuration
+///
+/// Options before the first block (global options) are not part of any block.
+/// Keywords are case-insensitive and separated from their values by whitespace
+/// or `=`.
+fn parse_blocks(lines: &[String]) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some((keyword, value)) = split_option(line.trim()) else {
+            continue;
+        };
+        match keyword.to_lowercase().as_str() {
+            "host" => blocks.push(Block {
+                patterns: value
+                    .split_whitespace()
+                    .map(|pattern| pattern.trim_matches('"').to_string())
+                    .collect(),
+                is_match: false,
+                start: index,
+                end: index,
+                options: Vec::new(),
+            }),
+            "match" => blocks.push(Block {
+                patterns: Vec::new(),
+                is_match: true,
+                start: index,
+                end: index,
+                options: Vec::new(),
+            }),
+            keyword => {
+                if let Some(block) = blocks.last_mut() {
+                    block.end = index;
+                    block
+                        .options
+                        .push((keyword.to_string(), value.trim_matches('"').to_string()));
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Splits a configuration line into keyword and value
+///
+/// # Returns
+///
+/// `None` for blank lines and comments.
+fn split_option(line: &str) -> Option<(&str, &str)> {
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let end = line.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(line.len());
+    let (keyword, rest) = line.split_at(end);
+    let rest = rest.trim_start();
+    let value = rest.strip_prefix('=').unwrap_or(rest).trim();
+    Some((keyword, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn entry(hostname: &str, options: &[&str]) -> HostEntry {
+        HostEntry {
+            hostname: hostname.to_string(),
+            user: Some("admin".to_string()),
+            port: None,
+            identity: None,
+            jump: None,
+            options: options.iter().map(|option| option.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parse_blocks_splits_host_and_match_blocks() {
+        let config =
+            lines("ForwardAgent no\n\nHost web web.local\n  HostName 10.0.0.5\n  Port=2222\n\nMatch all\n  User me");
+
+        let blocks = parse_blocks(&config);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].patterns, ["web", "web.local"]);
+        assert_eq!((blocks[0].start, blocks[0].end), (2, 4));
+        assert_eq!(blocks[0].option("port"), Some("2222"));
+        assert!(blocks[1].is_match && blocks[1].is_catch_all());
+    }
+
+    #[test]
+    fn add_host_inserts_before_host_star() {
+        let mut config = lines("Host old\n    HostName 10.0.0.1\n\n# Defaults\nHost *\n    User me");
+
+        let replaced = add_host(&mut config, "new", &entry("10.0.0.2", &["ForwardAgent=yes"])).unwrap();
+
+        assert!(!replaced);
+        assert_eq!(
+            config,
+            lines(
+                "Host old\n    HostName 10.0.0.1\n\nHost new\n    HostName 10.0.0.2\n    User admin\n    \
+                 ForwardAgent yes\n\n# Defaults\nHost *\n    User me"
+            )
+        );
+    }
+
+    #[test]
+    fn add_host_inserts_before_match() {
+        let mut config = lines("Match host *.internal\n\tUser ops");
+
+        add_host(&mut config, "new", &entry("10.0.0.2", &[])).unwrap();
+
+        assert_eq!(
+            config,
+            lines("Host new\n\tHostName 10.0.0.2\n\tUser admin\n\nMatch host *.internal\n\tUser ops")
+        );
+    }
+
+    #[test]
+    fn add_host_replaces_an_entry() {
+        let mut config = lines("Host web\n  HostName 10.0.0.1\n  Port 22\n\nHost db\n  HostName 10.0.0.9");
+
+        let replaced = add_host(&mut config, "web", &entry("10.0.0.5", &[])).unwrap();
+
+        assert!(replaced);
+        assert_eq!(
+            config,
+            lines("Host web\n  HostName 10.0.0.5\n  User admin\n\nHost db\n  HostName 10.0.0.9")
+        );
+    }
+
+    #[test]
+    fn add_host_rejects_block_keywords_and_line_breaks() {
+        let config = lines("Host web\n  HostName 10.0.0.1");
+        for options in [["Host=other"], ["match all"], ["User=a\nHost b"]] {
+            let mut changed = config.clone();
+            assert!(add_host(&mut changed, "new", &entry("10.0.0.2", &options)).is_err());
+            assert_eq!(changed, config);
+        }
+        let mut changed = config.clone();
+        assert!(add_host(&mut changed, "new", &entry("10.0.0.2\nHost evil", &[])).is_err());
+        assert!(add_host(&mut changed, "two words", &entry("10.0.0.2", &[])).is_err());
+    }
+
+    #[test]
+    fn remove_host_removes_one_alias_of_several() {
+        let mut config = lines("  Host web web.local  www\n  HostName 10.0.0.5");
+
+        remove_host(&mut config, "web.local").unwrap();
+
+        assert_eq!(config, lines("  Host web www\n  HostName 10.0.0.5"));
+    }
+
+    #[test]
+    fn remove_host_removes_an_entry() {
+        let mut config = lines("Host web\n  HostName 10.0.0.5\n\nHost db\n  HostName 10.0.0.9\n\nHost *\n  User me");
+
+        remove_host(&mut config, "db").unwrap();
+
+        assert_eq!(config, lines("Host web\n  HostName 10.0.0.5\n\nHost *\n  User me"));
+        assert!(remove_host(&mut config, "db").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Known host keys
+//!
+//! Removes the keys of a host from `~/.ssh/known_hosts`, e.g. after the host was
+//! reinstalled and SSH refuses to connect because the key changed. Host aliases
+//! of the SSH configuration are resolved to the real host name and port with
+//! `ssh -G`, and the keys stored for the addresses of the host are removed as
+//! well. Entries are looked up with `ssh-keygen -F`, which also finds hashed
+//! host names; the file is then edited line by line, so comments and all other
+//! entries stay untouched.
+//!
+//! # System Commands Used
+//!
+//! - `ssh -G` - Resolve host aliases of the SSH configuration
+//! - `ssh-keygen -F` - Find the entries of a host
+
+use super::{read_lines, ssh_file, write_lines};
+use crate::cli::SshKnownCommands;
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::process::Command;
+
+/// Name of the known hosts file inside `~/.ssh`
+const KNOWN_HOSTS_FILE: &str = "known_hosts";
+
+/// Name of the backup file inside the state directory
+const BACKUP_FILE: &str = "known_hosts.backup";
+
+/// Default SSH port, which is not written to known hosts entries
+const DEFAULT_PORT: u16 = 22;
+
+/// Performs known hosts operations
+///
+/// # Arguments
+///
+/// * `command` - Known hosts operation from the command line
+///
+/// # Errors
+///
+/// Returns an error if the known hosts file cannot be read or written.
+pub fn perform(command: SshKnownCommands) -> Result<(), Box<dyn Error>> {
+    match command {
+        SshKnownCommands::Prune { host, port } => prune(&host, port),
+    }
+}
+
+/// Removes all keys of a host and its addresses
+///
+/// # Arguments
+///
+/// * `host` - Host name, address or alias of the SSH configuration
+/// * `port` - Port overriding the port of the SSH configuration
+///
+/// # Errors
+///
+/// Returns an error if the known hosts file cannot be read or written.
+fn prune(host: &str, port: Option<u16>) -> Result<(), Box<dyn Error>> {
+    let path = ssh_file(KNOWN_HOSTS_FILE)?;
+    let (content, mut lines) = read_lines(&path)?;
+
+    let (hostname, configured_port) = resolve_alias(host);
+    let port = port.or(configured_port).unwrap_or(DEFAULT_PORT);
+    let mut names = vec![hostname.clone()];
+    if let Ok(addresses) = (hostname.as_str(), port).to_socket_addrs() {
+        for address in addresses {
+            let address = address.ip().to_string();
+            if !names.contains(&address) {
+                names.push(address);
+            }
+        }
+    }
+    let names: Vec<String> = names
+        .into_iter()
+        .map(|name| {
+            if port == DEFAULT_PORT {
+                name
+            } else {
+                format!("[{name}]:{port}")
+            }
+        })
+        .collect();
+
+    let mut found = BTreeSet::new();
+    for name in &names {
+        found.extend(find_entries(&path, name)?);
+    }
+    found.retain(|&index| index < lines.len());
+    if found.is_empty() {
+        println!("No keys of {} in {}", names.join(", "), path.display());
+        return Ok(());
+    }
+
+    for &index in &found {
+        println!("{} {}", "Removing".yellow(), describe_entry(&lines[index]));
+    }
+    for &index in found.iter().rev() {
+        lines.remove(index);
+    }
+    write_lines(&path, &content, &lines, BACKUP_FILE)?;
+    println!(
+        "{} {} key(s) of {} from {}",
+        "Removed".green(),
+        found.len(),
+        names.join(", "),
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Resolves a host alias of the SSH configuration
+///
+/// # Returns
+///
+/// The host name and port SSH would connect to, or the host itself if `ssh -G`
+/// is not available.
+fn resolve_alias(host: &str) -> (String, Option<u16>) {
+    let Ok(output) = Command::new("ssh").args(["-G", host]).output() else {
+        return (host.to_string(), None);
+    };
+    if !output.status.success() {
+        return (host.to_string(), None);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = |keyword: &str| {
+        stdout.lines().find_map(|line| {
+            line.strip_prefix(keyword)
+                .and_then(|value| value.strip_prefix(' '))
+                .map(str::to_string)
+        })
+    };
+    (
+        value("hostname").unwrap_or_else(|| host.to_string()),
+        value("port").and_then(|port| port.parse().ok()),
+    )
+}
+
+/// Finds the entries of a host name with `ssh-keygen -F`
+///
+/// # Returns
+///
+/// Indexes of the matching lines.
+///
+/// # Errors
+///
+/// Returns an error if `ssh-keygen` cannot be run.
+fn find_entries(path: &Path, name: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let output = Command::new("ssh-keygen")
+        .args(["-F", name, "-f"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run ssh-keygen: {e}"))?;
+
+    // e.g. "# Host example.com found: line 3"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once("found: line ")?.1.trim().parse::<usize>().ok())
+        .filter_map(|number| number.checked_sub(1))
+        .collect())
+}
+
+/// Describes an entry by its host names and key type, without the key itself
+fn describe_entry(line: &str) -> String {
+    let mut fields = line.split_whitespace().peekable();
+    let marker = fields.next_if(|field| field.starts_with('@'));
+    let hosts = fields.next().unwrap_or_default();
+    let hosts = if hosts.starts_with("|1|") {
+        "(hashed host name)"
+    } else {
+        hosts
+    };
+    let key_type = fields.next().unwrap_or_default();
+    match marker {
+        Some(marker) => format!("{marker} {hosts} {key_type}"),
+        None => format!("{hosts} {key_type}"),
+    }
+}
@@ -0,0 +1,158 @@
+//! Disk usage and cleanup
+//!
+//! `du` scans a folder with everything below it in parallel (see [`scan`])
+//! and opens a browser in the terminal to drill into the largest folders (see
+//! [`browser`]). With `--top`, or when the output is not a terminal, it prints
+//! the largest items of the folder instead.
+//!
+//! The sizes are the space allocated on disk, so sparse and compressed files
+//! count with what they really use, and APFS clones are not counted twice.
+//!
+//! `bigfiles` finds files above a size and optionally not modified for a
+//! while, groups them by folder and type and moves the selected ones to the
+//! Trash; see [`bigfiles`].
+//!
+//! `dupes` finds files with the same contents and replaces them with APFS
+//! clones or hard links; see [`dupes`].
+//!
+//! `clean` deletes caches, logs and build files that are recreated when
+//! needed; see [`clean`].
+//!
+//! # System Commands Used
+//!
+//! - None for scanning; the folders are read directly and the clone information comes from `getattrlist`;
+//!   duplicates are replaced with `clonefile` and hard links
+//! - `osascript` - Move the files selected by `bigfiles` to the Trash with Finder
+
+pub mod bigfiles;
+mod browser;
+pub mod clean;
+pub mod dupes;
+pub mod scan;
+
+use crate::cli::DiskUsageArgs;
+use crate::util::format_bytes;
+use colored::Colorize;
+use scan::Tree;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+
+/// Number of items printed when the output is not a terminal
+const DEFAULT_TOP: usize = 10;
+
+/// An item of a folder: a subfolder or the files directly in it
+pub struct Item {
+    /// Name of the subfolder, or the number of files
+    pub name: String,
+    /// Size with everything below it
+    pub size: u64,
+    /// Index of the subfolder in the tree, `None` for the files
+    pub folder: Option<usize>,
+}
+
+/// Shows what takes up space in a folder
+///
+/// # Arguments
+///
+/// * `args` - Disk usage arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the folder does not exist or the terminal fails.
+pub fn disk_usage(args: DiskUsageArgs) -> Result<(), Box<dyn Error>> {
+    let root = args
+        .path
+        .canonicalize()
+        .map_err(|e| format!("Failed to open {}: {e}", args.path.display()))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()).into());
+    }
+
+    let progress = io::stderr().is_terminal();
+    let tree = scan::scan(&root, |count| {
+        if progress {
+            eprint!("\r\x1b[2KScanning {} ({count} folders)", root.display());
+        }
+    });
+    if progress {
+        eprint!("\r\x1b[2K");
+    }
+
+    match args.top {
+        Some(top) => print_top(&tree, top as usize),
+        None if io::stdout().is_terminal() => browser::browse(&tree)?,
+        None => print_top(&tree, DEFAULT_TOP),
+    }
+    Ok(())
+}
+
+/// Returns the items of a folder, largest first
+///
+/// # Arguments
+///
+/// * `tree` - Scanned folders
+/// * `folder` - Index of the folder in the tree
+pub fn items(tree: &Tree, folder: usize) -> Vec<Item> {
+    let folder = &tree.folders[folder];
+    let mut items: Vec<Item> = folder
+        .children
+        .iter()
+        .map(|&child| Item {
+            name: format!("{}/", tree.folders[child].name),
+            size: tree.folders[child].size,
+            folder: Some(child),
+        })
+        .collect();
+    if folder.files > 0 {
+        items.push(Item {
+            name: format!("{} {}", folder.files, if folder.files == 1 { "file" } else { "files" }),
+            size: folder.files_size,
+            folder: None,
+        });
+    }
+    items.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    items
+}
+
+/// Returns the share of a size in a total in percent
+pub fn percent(size: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    size as f64 * 100.0 / total as f64
+}
+
+/// Prints the largest items of the scanned folder
+///
+/// # Arguments
+///
+/// * `tree` - Scanned folders
+/// * `top` - Number of items to print
+fn print_top(tree: &Tree, top: usize) {
+    let root = &tree.folders[0];
+    println!("{}  {}", root.name.bold(), format_bytes(root.size as f64).bold());
+    for item in items(tree, 0).into_iter().take(top) {
+        let name = if item.folder.is_some() {
+            item.name.normal()
+        } else {
+            item.name.dimmed()
+        };
+        println!(
+            "{:>10}  {:>5.1}%  {name}",
+            format_bytes(item.size as f64),
+            percent(item.size, root.size)
+        );
+    }
+    if tree.unreadable > 0 {
+        println!("{}", unreadable(tree.unreadable).dimmed());
+    }
+}
+
+/// Describes the number of folders that could not be read
+pub fn unreadable(count: usize) -> String {
+    if count == 1 {
+        "1 folder could not be read".to_string()
+    } else {
+        format!("{count} folders could not be read")
+    }
+}
@@ -0,0 +1,226 @@
+//! Large and old files
+//!
+//! Scans a folder in parallel like `du` (see [`scan`]) and collects the files
+//! that take at least a given space on disk and, optionally, were not modified
+//! for a given time. The files are summed up by the folder they are in and by
+//! their type, which is their extension.
+//!
+//! In a terminal the files are shown in a list to select the ones to move to
+//! the Trash (see [`picker`]); they are moved by Finder, so they can be put
+//! back. With `--list` or `--top`, or when the output is not a terminal, the
+//! groups and files are printed instead.
+
+mod picker;
+
+use super::scan::{self, BLOCK_SIZE};
+use crate::cli::BigFilesArgs;
+use crate::subcommands::trash;
+use crate::util::{confirm, format_bytes, format_count, format_time};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of groups printed per grouping
+const GROUPS: usize = 10;
+
+/// Type of files without an extension
+const NO_EXTENSION: &str = "no extension";
+
+/// A file found by the scan
+pub struct BigFile {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Path relative to the scanned folder
+    pub name: String,
+    /// Space on disk
+    pub size: u64,
+    /// Time of the last modification in seconds since 1970
+    pub modified: i64,
+    /// Folder relative to the scanned folder, `.` for the scanned folder itself
+    pub folder: String,
+    /// Lowercase extension, or [`NO_EXTENSION`]
+    pub kind: String,
+}
+
+/// Files sharing a folder or a type
+pub struct Group {
+    /// Folder or type
+    pub name: String,
+    /// Space of the files on disk
+    pub size: u64,
+    /// Indices of the files, largest first
+    pub files: Vec<usize>,
+}
+
+/// Finds large and old files and moves the selected ones to the Trash
+///
+/// # Arguments
+///
+/// * `args` - Bigfiles arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the folder does not exist, the terminal fails or a
+/// file cannot be moved to the Trash.
+pub fn perform(args: BigFilesArgs) -> Result<(), Box<dyn Error>> {
+    let root = args
+        .path
+        .canonicalize()
+        .map_err(|e| format!("Failed to open {}: {e}", args.path.display()))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()).into());
+    }
+    let modified_before = match args.older_than {
+        Some(age) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .saturating_sub(age)
+            .as_secs() as i64,
+        None => i64::MAX,
+    };
+
+    let found = Mutex::new(Vec::new());
+    let progress = io::stderr().is_terminal();
+    let tree = scan::scan_files(
+        &root,
+        |count| {
+            if progress {
+                eprint!("\r\x1b[2KScanning {} ({count} folders)", root.display());
+            }
+        },
+        &|path, metadata| {
+            let size = metadata.blocks() * BLOCK_SIZE;
+            if metadata.is_file() && size >= args.min_size && metadata.mtime() < modified_before {
+                let file = big_file(&root, path, size, metadata.mtime());
+                found.lock().unwrap_or_else(|e| e.into_inner()).push(file);
+            }
+        },
+    );
+    if progress {
+        eprint!("\r\x1b[2K");
+    }
+    let mut files = found.into_inner().unwrap_or_else(|e| e.into_inner());
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    if files.is_empty() {
+        let modified = match args.older_than {
+            Some(_) => format!(" modified before {}", &format_time(modified_before, false)[..10]),
+            None => String::new(),
+        };
+        println!("No files of {}{modified} found", format_bytes(args.min_size as f64));
+        return Ok(());
+    }
+    if args.list || args.top.is_some() || !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        print(&files, args.top.map_or(files.len(), |top| top as usize));
+        if tree.unreadable > 0 {
+            println!("{}", super::unreadable(tree.unreadable).dimmed());
+        }
+        return Ok(());
+    }
+
+    let selected = picker::pick(&root, &files, tree.unreadable)?;
+    if selected.is_empty() {
+        println!("Nothing moved to the Trash");
+        return Ok(());
+    }
+    let size: u64 = selected.iter().map(|&index| files[index].size).sum();
+    let question = format!(
+        "Move {} ({}) to the Trash?",
+        format_count(selected.len(), "file"),
+        format_bytes(size as f64)
+    );
+    if !confirm(&question)? {
+        println!("Nothing moved to the Trash");
+        return Ok(());
+    }
+    let paths: Vec<PathBuf> = selected.iter().map(|&index| files[index].path.clone()).collect();
+    trash::put(&paths)
+}
+
+/// Describes a file found by the scan
+fn big_file(root: &Path, path: &Path, size: u64, modified: i64) -> BigFile {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let folder = match relative.parent() {
+        Some(folder) if !folder.as_os_str().is_empty() => folder.display().to_string(),
+        _ => ".".to_string(),
+    };
+    let kind = path.extension().map_or(NO_EXTENSION.to_string(), |extension| {
+        extension.to_string_lossy().to_lowercase()
+    });
+    BigFile {
+        path: path.to_path_buf(),
+        name: relative.display().to_string(),
+        size,
+        modified,
+        folder,
+        kind,
+    }
+}
+
+/// Groups the files by a key, largest group first
+///
+/// # Arguments
+///
+/// * `files` - Files, largest first
+/// * `key` - Returns the group of a file
+pub fn groups(files: &[BigFile], key: impl Fn(&BigFile) -> &str) -> Vec<Group> {
+    let mut groups: HashMap<&str, Group> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        let group = groups.entry(key(file)).or_insert_with(|| Group {
+            name: key(file).to_string(),
+            size: 0,
+            files: Vec::new(),
+        });
+        group.size += file.size;
+        group.files.push(index);
+    }
+    let mut groups: Vec<Group> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    groups
+}
+
+/// Prints the largest groups by folder and type and the largest files
+///
+/// # Arguments
+///
+/// * `files` - Files, largest first
+/// * `top` - Number of files to print
+fn print(files: &[BigFile], top: usize) {
+    for (title, groups) in [
+        ("By folder", groups(files, |file| &file.folder)),
+        ("By type", groups(files, |file| &file.kind)),
+    ] {
+        println!("{}", title.bold());
+        for group in groups.iter().take(GROUPS) {
+            println!(
+                "{:>10}  {:>9}  {}",
+                format_bytes(group.size as f64),
+                format_count(group.files.len(), "file"),
+                group.name
+            );
+        }
+        if groups.len() > GROUPS {
+            println!("{}", format!("and {} more", groups.len() - GROUPS).dimmed());
+        }
+        println!();
+    }
+
+    println!("{}", "Files".bold());
+    for file in files.iter().take(top) {
+        println!(
+            "{:>10}  {:<16}  {}",
+            format_bytes(file.size as f64),
+            format_time(file.modified, false),
+            file.name
+        );
+    }
+    let total: u64 = files.iter().map(|file| file.size).sum();
+    println!(
+        "{}",
+        format!("{}, {}", format_count(files.len(), "file"), format_bytes(total as f64)).bold()
+    );
+}
@@ -0,0 +1,279 @@
+//! Selection of files to move to the Trash
+//!
+//! Shows the files with their size, modification date and path in the
+//! terminal, largest first or grouped by folder or type. Marking a group marks
+//! all of its files.
+//!
+//! Keys: ↑/↓ or `k`/`j` select, Space marks the selected file or group, `a`
+//! marks all files or none, `g` switches the grouping, Enter moves the marked
+//! files to the Trash, `q` or Esc quit without moving anything.
+
+use super::{BigFile, Group, groups};
+use crate::subcommands::storage::unreadable;
+use crate::util::{format_bytes, format_count, format_time};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState};
+use std::error::Error;
+use std::path::Path;
+
+/// How the files are listed
+#[derive(Clone, Copy)]
+enum Grouping {
+    /// Largest first
+    None,
+    /// By the folder they are in
+    Folder,
+    /// By their type
+    Type,
+}
+
+/// A row of the list
+enum Row {
+    /// Heading of a group with its index
+    Group(usize),
+    /// A file with its index
+    File(usize),
+}
+
+/// The files being selected
+struct Picker<'a> {
+    /// Scanned folder
+    root: &'a Path,
+    /// Files, largest first
+    files: &'a [BigFile],
+    /// Number of folders that could not be read
+    unreadable: usize,
+    /// How the files are listed
+    grouping: Grouping,
+    /// Groups of the grouping
+    groups: Vec<Group>,
+    /// Rows of the list
+    rows: Vec<Row>,
+    /// Marked files by index
+    marked: Vec<bool>,
+    /// Selected row
+    state: ListState,
+}
+
+/// Lets the user mark files until they confirm or quit
+///
+/// # Arguments
+///
+/// * `root` - Scanned folder
+/// * `files` - Files, largest first
+/// * `unreadable` - Number of folders that could not be read
+///
+/// # Returns
+///
+/// The indices of the marked files, largest first; empty if the user quit.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be set up or read.
+pub fn pick(root: &Path, files: &[BigFile], unreadable: usize) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut terminal = ratatui::try_init()?;
+    let result = run(&mut terminal, root, files, unreadable);
+    ratatui::restore();
+    result
+}
+
+/// Draws the list and handles the keys
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be drawn or read.
+fn run(
+    terminal: &mut DefaultTerminal,
+    root: &Path,
+    files: &[BigFile],
+    unreadable: usize,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut picker = Picker {
+        root,
+        files,
+        unreadable,
+        grouping: Grouping::None,
+        groups: Vec::new(),
+        rows: Vec::new(),
+        marked: vec![false; files.len()],
+        state: ListState::default().with_selected(Some(0)),
+    };
+    picker.group(Grouping::None);
+    loop {
+        terminal.draw(|frame| picker.render(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Vec::new()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(Vec::new()),
+            KeyCode::Down | KeyCode::Char('j') => picker.state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => picker.state.select_previous(),
+            KeyCode::Home => picker.state.select_first(),
+            KeyCode::End | KeyCode::Char('G') => picker.state.select_last(),
+            KeyCode::Char(' ') => picker.mark(),
+            KeyCode::Char('a') => picker.mark_all(),
+            KeyCode::Char('g') => picker.group(match picker.grouping {
+                Grouping::None => Grouping::Folder,
+                Grouping::Folder => Grouping::Type,
+                Grouping::Type => Grouping::None,
+            }),
+            KeyCode::Enter => {
+                return Ok((0..files.len()).filter(|&index| picker.marked[index]).collect());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Picker<'_> {
+    /// Lists the files with a grouping and selects the first row
+    fn group(&mut self, grouping: Grouping) {
+        self.grouping = grouping;
+        self.groups = match grouping {
+            Grouping::None => Vec::new(),
+            Grouping::Folder => groups(self.files, |file| &file.folder),
+            Grouping::Type => groups(self.files, |file| &file.kind),
+        };
+        self.rows = match grouping {
+            Grouping::None => (0..self.files.len()).map(Row::File).collect(),
+            _ => self
+                .groups
+                .iter()
+                .enumerate()
+                .flat_map(|(index, group)| {
+                    std::iter::once(Row::Group(index)).chain(group.files.iter().map(|&file| Row::File(file)))
+                })
+                .collect(),
+        };
+        self.state = ListState::default().with_selected(Some(0));
+    }
+
+    /// Marks or unmarks the selected file, or all files of the selected group
+    fn mark(&mut self) {
+        match self.state.selected().and_then(|selected| self.rows.get(selected)) {
+            Some(&Row::File(file)) => self.marked[file] = !self.marked[file],
+            Some(&Row::Group(group)) => {
+                let files = &self.groups[group].files;
+                let mark = !files.iter().all(|&file| self.marked[file]);
+                for &file in files {
+                    self.marked[file] = mark;
+                }
+            }
+            None => return,
+        }
+        self.state.select_next();
+    }
+
+    /// Marks all files, or none if all are marked
+    fn mark_all(&mut self) {
+        let mark = !self.marked.iter().all(|&marked| marked);
+        self.marked.fill(mark);
+    }
+
+    /// Draws the folder and the marked space, the rows and the keys
+    fn render(&mut self, frame: &mut Frame) {
+        let [header, list, footer] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+
+        let total: u64 = self.files.iter().map(|file| file.size).sum();
+        let marked: Vec<&BigFile> = self
+            .files
+            .iter()
+            .zip(&self.marked)
+            .filter(|(_, marked)| **marked)
+            .map(|(file, _)| file)
+            .collect();
+        let marked_size: u64 = marked.iter().map(|file| file.size).sum();
+        frame.render_widget(
+            Line::from(vec![
+                Span::from(self.root.display().to_string()).bold(),
+                Span::from(format!(
+                    "  {}, {}",
+                    format_count(self.files.len(), "file"),
+                    format_bytes(total as f64)
+                )),
+                Span::from(format!(
+                    "  {} marked, {}",
+                    format_count(marked.len(), "file"),
+                    format_bytes(marked_size as f64)
+                ))
+                .cyan(),
+            ]),
+            header,
+        );
+
+        let rows: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match *row {
+                Row::Group(group) => {
+                    let group = &self.groups[group];
+                    let all = group.files.iter().all(|&file| self.marked[file]);
+                    let any = group.files.iter().any(|&file| self.marked[file]);
+                    let check = if all {
+                        "[x]"
+                    } else if any {
+                        "[-]"
+                    } else {
+                        "[ ]"
+                    };
+                    ListItem::new(
+                        Line::from(format!(
+                            "{check} {:>10}  {:<16}  {}",
+                            format_bytes(group.size as f64),
+                            format_count(group.files.len(), "file"),
+                            group.name
+                        ))
+                        .bold(),
+                    )
+                }
+                Row::File(file) => {
+                    let check = if self.marked[file] { "[x]" } else { "[ ]" };
+                    let file = &self.files[file];
+                    let name = match self.grouping {
+                        Grouping::None => Span::from(file.name.as_str()),
+                        _ => Span::from(format!("  {}", file.name)),
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::from(format!(
+                            "{check} {:>10}  {:<16}  ",
+                            format_bytes(file.size as f64),
+                            format_time(file.modified, false)
+                        )),
+                        name,
+                    ]))
+                }
+            })
+            .collect();
+        frame.render_stateful_widget(
+            List::new(rows).highlight_style(Style::new().reversed()),
+            list,
+            &mut self.state,
+        );
+
+        let grouping = match self.grouping {
+            Grouping::None => "by size",
+            Grouping::Folder => "by folder",
+            Grouping::Type => "by type",
+        };
+        let mut keys = vec![
+            Span::from(format!(
+                "↑↓ select  space mark  a all  g group ({grouping})  enter move to Trash  q quit"
+            ))
+            .dim(),
+        ];
+        if self.unreadable > 0 {
+            keys.push(Span::from(format!("  {}", unreadable(self.unreadable))).yellow());
+        }
+        frame.render_widget(Line::from(keys), footer);
+    }
+}
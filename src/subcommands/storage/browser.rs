@@ -0,0 +1,161 @@
+//! Disk usage browser
+//!
+//! Shows the items of a folder with their size, share and a bar in the
+//! terminal, largest first, and drills into the selected folder. The files
+//! directly in a folder are shown as one item.
+//!
+//! Keys: ↑/↓ or `k`/`j` select, Enter, → or `l` open the selected folder,
+//! ←, Backspace or `h` go back to the parent folder, `q` or Esc quit.
+
+use super::scan::Tree;
+use super::{Item, items, percent, unreadable};
+use crate::util::format_bytes;
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState};
+use std::error::Error;
+
+/// Width of the share bars in characters
+const BAR_WIDTH: usize = 20;
+
+/// The folder being browsed
+struct Browser<'a> {
+    /// Scanned folders
+    tree: &'a Tree,
+    /// Index of the shown folder
+    folder: usize,
+    /// Items of the shown folder, largest first
+    items: Vec<Item>,
+    /// Selected item
+    state: ListState,
+    /// Selected items of the parent folders, to select them again when going back
+    selections: Vec<usize>,
+}
+
+/// Browses the scanned folders until the user quits
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be set up or read.
+pub fn browse(tree: &Tree) -> Result<(), Box<dyn Error>> {
+    let mut terminal = ratatui::try_init()?;
+    let result = run(&mut terminal, tree);
+    ratatui::restore();
+    result
+}
+
+/// Draws the browser and handles the keys
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be drawn or read.
+fn run(terminal: &mut DefaultTerminal, tree: &Tree) -> Result<(), Box<dyn Error>> {
+    let mut browser = Browser {
+        tree,
+        folder: 0,
+        items: items(tree, 0),
+        state: ListState::default().with_selected(Some(0)),
+        selections: Vec::new(),
+    };
+    loop {
+        terminal.draw(|frame| browser.render(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => browser.state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => browser.state.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => browser.state.select_first(),
+            KeyCode::End | KeyCode::Char('G') => browser.state.select_last(),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => browser.open(),
+            KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => browser.back(),
+            _ => {}
+        }
+    }
+}
+
+impl Browser<'_> {
+    /// Shows the selected folder
+    fn open(&mut self) {
+        let Some(selected) = self.state.selected() else {
+            return;
+        };
+        let Some(folder) = self.items.get(selected).and_then(|item| item.folder) else {
+            return;
+        };
+        self.selections.push(selected);
+        self.show(folder, 0);
+    }
+
+    /// Shows the parent folder with the folder that was open selected
+    fn back(&mut self) {
+        if let Some(parent) = self.tree.folders[self.folder].parent {
+            let selected = self.selections.pop().unwrap_or_default();
+            self.show(parent, selected);
+        }
+    }
+
+    /// Shows a folder and selects an item
+    fn show(&mut self, folder: usize, selected: usize) {
+        self.folder = folder;
+        self.items = items(self.tree, folder);
+        self.state = ListState::default().with_selected(Some(selected));
+    }
+
+    /// Draws the path and size of the folder, its items and the keys
+    fn render(&mut self, frame: &mut Frame) {
+        let [header, list, footer] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let total = self.tree.folders[self.folder].size;
+
+        frame.render_widget(
+            Line::from(vec![
+                Span::from(self.tree.path(self.folder).display().to_string()).bold(),
+                Span::from("  "),
+                Span::from(format_bytes(total as f64)).bold(),
+            ]),
+            header,
+        );
+
+        let rows: Vec<ListItem> = self
+            .items
+            .iter()
+            .map(|item| {
+                let share = percent(item.size, total);
+                let filled = ((share / 100.0) * BAR_WIDTH as f64).round() as usize;
+                let name = if item.folder.is_some() {
+                    Span::from(item.name.as_str())
+                } else {
+                    Span::from(item.name.as_str()).dim()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::from(format!("{:>10}  {share:>5.1}%  ", format_bytes(item.size as f64))),
+                    Span::from("█".repeat(filled)).cyan(),
+                    Span::from("░".repeat(BAR_WIDTH - filled)).dim(),
+                    Span::from("  "),
+                    name,
+                ]))
+            })
+            .collect();
+        frame.render_stateful_widget(
+            List::new(rows).highlight_style(Style::new().reversed()),
+            list,
+            &mut self.state,
+        );
+
+        let mut keys = vec![Span::from("↑↓ select  → open  ← back  q quit").dim()];
+        if self.tree.unreadable > 0 {
+            keys.push(Span::from(format!("  {}", unreadable(self.tree.unreadable))).yellow());
+        }
+        frame.render_widget(Line::from(keys), footer);
+    }
+}
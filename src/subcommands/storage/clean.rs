@@ -0,0 +1,208 @@
+//! Cache and junk cleanup
+//!
+//! Knows where macOS apps, Xcode, Homebrew and npm keep files that are
+//! recreated when needed, shows how much space each category takes and
+//! deletes the files after confirmation, which requires `--yes` without a
+//! terminal. Only the contents of the folders are deleted, never the folders
+//! themselves, which apps expect to exist.
+//!
+//! The categories are cleaned from the most specific to the most general, so
+//! a folder like the Homebrew cache inside `~/Library/Caches` belongs to the
+//! Homebrew category when both are cleaned. Files protected by macOS or in use
+//! are skipped and counted. Quitting the apps first keeps them from writing
+//! their caches again right away.
+//!
+//! # System Commands Used
+//!
+//! - None; the sizes are scanned like `du` and the files are deleted directly
+
+use super::scan::{self, BLOCK_SIZE};
+use crate::cli::CleanArgs;
+use crate::config::home_dir;
+use crate::util::{confirm, format_bytes};
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// A category of files that are recreated when needed
+struct Category {
+    /// Name shown in the report
+    name: &'static str,
+    /// Folders below the home folder whose contents are deleted
+    folders: &'static [&'static str],
+}
+
+/// Build products, device support files and simulator caches of Xcode
+const XCODE: Category = Category {
+    name: "Xcode",
+    folders: &[
+        "Library/Developer/Xcode/DerivedData",
+        "Library/Developer/Xcode/iOS DeviceSupport",
+        "Library/Developer/CoreSimulator/Caches",
+    ],
+};
+
+/// Downloads cached by Homebrew
+const BREW: Category = Category {
+    name: "Homebrew",
+    folders: &["Library/Caches/Homebrew"],
+};
+
+/// Packages cached by npm
+const NPM: Category = Category {
+    name: "npm",
+    folders: &[".npm/_cacache"],
+};
+
+/// Caches of all apps
+const CACHES: Category = Category {
+    name: "Caches",
+    folders: &["Library/Caches"],
+};
+
+/// Logs and crash reports
+const LOGS: Category = Category {
+    name: "Logs",
+    folders: &["Library/Logs"],
+};
+
+/// Shows and deletes the files of the selected categories
+///
+/// # Arguments
+///
+/// * `args` - Clean arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the home folder cannot be determined or stdin cannot be read.
+pub fn perform(args: CleanArgs) -> Result<(), Box<dyn Error>> {
+    let home = home_dir()?;
+    let all = !(args.caches || args.logs || args.xcode || args.brew || args.npm);
+    // Specific categories come first and claim their folders inside more general ones
+    let categories: Vec<&Category> = [
+        (args.xcode, &XCODE),
+        (args.brew, &BREW),
+        (args.npm, &NPM),
+        (args.caches, &CACHES),
+        (args.logs, &LOGS),
+    ]
+    .into_iter()
+    .filter(|(selected, _)| all || *selected)
+    .map(|(_, category)| category)
+    .collect();
+
+    let mut claimed: Vec<PathBuf> = Vec::new();
+    let mut found = Vec::new();
+    for category in categories {
+        let folders: Vec<PathBuf> = category.folders.iter().map(|folder| home.join(folder)).collect();
+        let skipped = claimed.clone();
+        let size: u64 = folders.iter().map(|folder| size(folder, &skipped)).sum();
+        claimed.extend(folders.iter().cloned());
+        let locations: Vec<String> = category
+            .folders
+            .iter()
+            .filter(|folder| home.join(folder).exists())
+            .map(|folder| format!("~/{folder}"))
+            .collect();
+        println!(
+            "{:<16}{:>10}  {}",
+            category.name.bold(),
+            format_bytes(size as f64),
+            locations.join(", ").dimmed()
+        );
+        found.push((category, folders, skipped, size));
+    }
+
+    let total: u64 = found.iter().map(|(_, _, _, size)| size).sum();
+    println!("{:<16}{:>10}", "Total".bold(), format_bytes(total as f64).bold());
+    if total == 0 {
+        println!("Nothing to clean");
+        return Ok(());
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+    if !args.yes {
+        println!();
+        if !confirm(&format!("Delete {}?", format_bytes(total as f64)))? {
+            println!("Nothing deleted");
+            return Ok(());
+        }
+    }
+
+    let mut recovered = 0;
+    for (category, folders, skipped, before) in found {
+        if before == 0 {
+            continue;
+        }
+        print!("Clean {}", category.name);
+        io::stdout().flush()?;
+        let failed: usize = folders.iter().map(|folder| delete_contents(folder, &skipped)).sum();
+        let after: u64 = folders.iter().map(|folder| size(folder, &skipped)).sum();
+        recovered += before.saturating_sub(after);
+        if failed == 0 {
+            println!("{}", " OK".green());
+        } else {
+            let items = if failed == 1 { "item" } else { "items" };
+            println!("{}", format!(" {failed} {items} in use or protected").yellow());
+        }
+    }
+    println!("{:<16}{}", "Recovered".bold(), format_bytes(recovered as f64).green());
+    Ok(())
+}
+
+/// Returns the size of the contents of a folder
+///
+/// # Arguments
+///
+/// * `folder` - Folder to measure; 0 if it does not exist
+/// * `skipped` - Subfolders that are not counted
+fn size(folder: &Path, skipped: &[PathBuf]) -> u64 {
+    if !folder.is_dir() {
+        return 0;
+    }
+    let tree = scan::scan(folder, |_| {});
+    let root = &tree.folders[0];
+    let own = folder
+        .symlink_metadata()
+        .map_or(0, |metadata| metadata.blocks() * BLOCK_SIZE);
+    let skipped: u64 = root
+        .children
+        .iter()
+        .filter(|&&child| skipped.contains(&folder.join(&tree.folders[child].name)))
+        .map(|&child| tree.folders[child].size)
+        .sum();
+    root.size.saturating_sub(own + skipped)
+}
+
+/// Deletes the contents of a folder
+///
+/// # Arguments
+///
+/// * `folder` - Folder to empty
+/// * `skipped` - Subfolders that are kept
+///
+/// # Returns
+///
+/// The number of items that could not be deleted completely.
+fn delete_contents(folder: &Path, skipped: &[PathBuf]) -> usize {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| !skipped.contains(path))
+        .filter(|path| {
+            let result = if path.symlink_metadata().is_ok_and(|metadata| metadata.is_dir()) {
+                fs::remove_dir_all(path)
+            } else {
+                fs::remove_file(path)
+            };
+            result.is_err()
+        })
+        .count()
+}
@@ -0,0 +1,550 @@
+//! Duplicate files
+//!
+//! Finds files with the same contents below a folder in stages, each of which
+//! only looks at the files the previous one left: files of the same size on
+//! the same volume, then the BLAKE3 hash of their beginning, then the hash of
+//! their whole contents. The folder is scanned in parallel like `du` (see
+//! [`scan`]) and the hashes are computed by worker threads.
+//!
+//! Of every group the file modified first is kept. The others can be replaced
+//! by APFS clones of it, which share its blocks until one of them is changed,
+//! or by hard links to it, which are the same file. A duplicate is only
+//! replaced if neither it nor the kept file changed since the scan, and the
+//! clone or link is created next to it and renamed over it, so the duplicate
+//! is never missing. Clones keep the permissions and dates of the duplicate.
+//! Hard links share the owner and permissions of the kept file, so duplicates
+//! with a different owner or mode are skipped unless linking is forced.
+//!
+//! Hard links to one file count as one file. Duplicates that already are
+//! clones of the kept file only count with the blocks they do not share.
+//! Duplicates with more hard links, inside or outside the folder, are listed
+//! but not replaced, as replacing one of their paths frees no space.
+
+#[cfg(target_os = "macos")]
+mod clonefile;
+
+use super::scan::{self, BLOCK_SIZE, clone_info};
+use crate::cli::DupesArgs;
+use crate::util::{confirm, format_bytes};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File, FileTimes};
+use std::io::{self, IsTerminal, Read, Write};
+use std::num::NonZero;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use clonefile::clone_file;
+
+/// Bytes hashed of every file before the whole contents are
+const PARTIAL: u64 = 64 * 1024;
+
+/// A file found by the scan
+struct Candidate {
+    /// Path of the file
+    path: PathBuf,
+    /// Device of the volume
+    device: u64,
+    /// Inode on the volume
+    inode: u64,
+    /// Size in bytes
+    size: u64,
+    /// Space on disk
+    allocated: u64,
+    /// Number of hard links
+    links: u64,
+    /// Time of the last modification in seconds since 1970
+    modified: i64,
+    /// Nanoseconds of the time of the last modification
+    modified_nanos: i64,
+    /// User owning the file
+    uid: u32,
+    /// Group owning the file
+    gid: u32,
+    /// File type and permissions
+    mode: u32,
+}
+
+/// A file with the same contents as the kept file of its group
+#[derive(Serialize)]
+struct Duplicate {
+    /// Path of the file
+    path: PathBuf,
+    /// Space freed by replacing it
+    space: u64,
+    /// True if it already is a clone of the kept file
+    clone: bool,
+    /// True if it has more hard links, so replacing it frees nothing
+    linked: bool,
+    /// Index of the file in the scanned files
+    #[serde(skip)]
+    index: usize,
+}
+
+/// Files with the same contents
+#[derive(Serialize)]
+struct Group {
+    /// Size of each file in bytes
+    size: u64,
+    /// Space freed by replacing the duplicates
+    reclaimable: u64,
+    /// File that is kept, modified first
+    keep: PathBuf,
+    /// The other files
+    duplicates: Vec<Duplicate>,
+    /// Index of the kept file in the scanned files
+    #[serde(skip)]
+    index: usize,
+}
+
+/// What the duplicates are replaced with
+#[derive(Clone, Copy, PartialEq)]
+enum Replacement {
+    /// APFS clones of the kept file
+    Clone,
+    /// Hard links to the kept file
+    HardLink,
+}
+
+/// Finds duplicate files and replaces them with clones or hard links
+///
+/// # Arguments
+///
+/// * `args` - Dupes arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the folder does not exist, stdin cannot be read or a
+/// duplicate cannot be replaced.
+pub fn perform(args: DupesArgs) -> Result<(), Box<dyn Error>> {
+    let root = args
+        .path
+        .canonicalize()
+        .map_err(|e| format!("Failed to open {}: {e}", args.path.display()))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()).into());
+    }
+
+    let found = Mutex::new(Vec::new());
+    let progress = io::stderr().is_terminal();
+    let tree = scan::scan_files(
+        &root,
+        |count| {
+            if progress {
+                eprint!("\r\x1b[2KScanning {} ({count} folders)", root.display());
+            }
+        },
+        &|path, metadata| {
+            if metadata.is_file() && metadata.len() >= args.min_size {
+                let file = Candidate {
+                    path: path.to_path_buf(),
+                    device: metadata.dev(),
+                    inode: metadata.ino(),
+                    size: metadata.len(),
+                    allocated: metadata.blocks() * BLOCK_SIZE,
+                    links: metadata.nlink(),
+                    modified: metadata.mtime(),
+                    modified_nanos: metadata.mtime_nsec(),
+                    uid: metadata.uid(),
+                    gid: metadata.gid(),
+                    mode: metadata.mode(),
+                };
+                found.lock().unwrap_or_else(|e| e.into_inner()).push(file);
+            }
+        },
+    );
+    let mut files = found.into_inner().unwrap_or_else(|e| e.into_inner());
+    // Hard links to one file are found once
+    files.sort_by(|a, b| (a.device, a.inode, &a.path).cmp(&(b.device, b.inode, &b.path)));
+    files.dedup_by_key(|file| (file.device, file.inode));
+
+    let mut same_size: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        same_size.entry((file.device, file.size)).or_default().push(index);
+    }
+    let candidates: Vec<Vec<usize>> = same_size.into_values().filter(|group| group.len() > 1).collect();
+    let candidates = refine(&files, candidates, Some(PARTIAL), progress);
+    // The beginning of small files is all of them
+    let (small, large): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|group| files[group[0]].size <= PARTIAL);
+    let mut candidates = refine(&files, large, None, progress);
+    candidates.extend(small);
+    if progress {
+        eprint!("\r\x1b[2K");
+    }
+
+    let mut groups: Vec<Group> = candidates.into_iter().map(|group| describe(&files, group)).collect();
+    groups.sort_by(|a, b| b.reclaimable.cmp(&a.reclaimable).then_with(|| a.keep.cmp(&b.keep)));
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+    if groups.is_empty() {
+        println!("No duplicate files found");
+    } else {
+        print(&groups);
+    }
+    if tree.unreadable > 0 {
+        println!("{}", super::unreadable(tree.unreadable).dimmed());
+    }
+
+    let replacement = match (args.clone, args.hardlink) {
+        (true, _) => Replacement::Clone,
+        (_, true) => Replacement::HardLink,
+        _ => return Ok(()),
+    };
+    replace_all(&files, &groups, replacement, args.force, args.yes)
+}
+
+/// Splits groups of files by the hash of their contents
+///
+/// # Arguments
+///
+/// * `files` - Scanned files
+/// * `groups` - Indices of files that may have the same contents
+/// * `limit` - Number of bytes to hash of every file, `None` for all
+/// * `progress` - Show the progress on stderr
+///
+/// # Returns
+///
+/// The groups of files with the same hash; files that cannot be read and
+/// files without a duplicate are left out.
+fn refine(files: &[Candidate], groups: Vec<Vec<usize>>, limit: Option<u64>, progress: bool) -> Vec<Vec<usize>> {
+    let jobs: Vec<usize> = groups.iter().flatten().copied().collect();
+    let hashes = hash_all(files, &jobs, limit, |done| {
+        if progress {
+            let stage = if limit.is_some() { "Comparing" } else { "Hashing" };
+            eprint!("\r\x1b[2K{stage} {done} of {} files", jobs.len());
+        }
+    });
+    let hashes: HashMap<usize, blake3::Hash> = jobs
+        .into_iter()
+        .zip(hashes)
+        .filter_map(|(file, hash)| Some((file, hash?)))
+        .collect();
+
+    let mut refined = Vec::new();
+    for group in groups {
+        let mut same_hash: HashMap<blake3::Hash, Vec<usize>> = HashMap::new();
+        for file in group {
+            if let Some(hash) = hashes.get(&file) {
+                same_hash.entry(*hash).or_default().push(file);
+            }
+        }
+        refined.extend(same_hash.into_values().filter(|group| group.len() > 1));
+    }
+    refined
+}
+
+/// Hashes files with worker threads
+///
+/// # Arguments
+///
+/// * `files` - Scanned files
+/// * `jobs` - Indices of the files to hash
+/// * `limit` - Number of bytes to hash of every file, `None` for all
+/// * `progress` - Called repeatedly with the number of hashed files
+///
+/// # Returns
+///
+/// The hash of every job, `None` if its file cannot be read.
+fn hash_all(
+    files: &[Candidate],
+    jobs: &[usize],
+    limit: Option<u64>,
+    progress: impl Fn(usize),
+) -> Vec<Option<blake3::Hash>> {
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let hashes = Mutex::new(vec![None; jobs.len()]);
+    let workers = thread::available_parallelism().map_or(4, NonZero::get);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    loop {
+                        let job = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&file) = jobs.get(job) else {
+                            break;
+                        };
+                        let hash = hash(&files[file].path, limit).ok();
+                        hashes.lock().unwrap_or_else(|e| e.into_inner())[job] = hash;
+                        done.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+            })
+            .collect();
+        while !handles.iter().all(|handle| handle.is_finished()) {
+            progress(done.load(Ordering::Relaxed));
+            thread::sleep(Duration::from_millis(100));
+        }
+    });
+    hashes.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Hashes the contents of a file
+///
+/// # Arguments
+///
+/// * `path` - File to hash
+/// * `limit` - Number of bytes to hash, `None` for all
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+fn hash(path: &Path, limit: Option<u64>) -> io::Result<blake3::Hash> {
+    let file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    match limit {
+        Some(limit) => hasher.update_reader(file.take(limit))?,
+        None => hasher.update_reader(file)?,
+    };
+    Ok(hasher.finalize())
+}
+
+/// Chooses the kept file of files with the same contents and the space the others free
+fn describe(files: &[Candidate], mut group: Vec<usize>) -> Group {
+    group.sort_by(|&a, &b| {
+        let (a, b) = (&files[a], &files[b]);
+        (a.modified, a.modified_nanos, &a.path).cmp(&(b.modified, b.modified_nanos, &b.path))
+    });
+    let keep = &files[group[0]];
+    let clone_id = clone_info(&keep.path).map(|clone| clone.clone_id);
+    let duplicates: Vec<Duplicate> = group[1..]
+        .iter()
+        .map(|&index| {
+            let file = &files[index];
+            let (space, clone) = match clone_info(&file.path) {
+                Some(clone) if Some(clone.clone_id) == clone_id => (clone.private_size, true),
+                _ => (file.allocated, false),
+            };
+            let linked = file.links > 1;
+            Duplicate {
+                path: file.path.clone(),
+                space: if linked { 0 } else { space },
+                clone,
+                linked,
+                index,
+            }
+        })
+        .collect();
+    Group {
+        size: keep.size,
+        reclaimable: duplicates.iter().map(|duplicate| duplicate.space).sum(),
+        keep: keep.path.clone(),
+        duplicates,
+        index: group[0],
+    }
+}
+
+/// Prints the groups, most space to free first
+fn print(groups: &[Group]) {
+    for group in groups {
+        println!(
+            "{}  {}",
+            format!(
+                "{} copies of {}",
+                group.duplicates.len() + 1,
+                format_bytes(group.size as f64)
+            )
+            .bold(),
+            format!("{} reclaimable", format_bytes(group.reclaimable as f64)).dimmed()
+        );
+        println!("  {:<6}{}", "keep".green(), group.keep.display());
+        for duplicate in &group.duplicates {
+            let note = match (duplicate.clone, duplicate.linked) {
+                (_, true) => " (hard linked elsewhere)",
+                (true, false) => " (clone)",
+                (false, false) => "",
+            };
+            println!("  {:<6}{}{}", "", duplicate.path.display(), note.dimmed());
+        }
+        println!();
+    }
+    let duplicates: usize = groups.iter().map(|group| group.duplicates.len()).sum();
+    let reclaimable: u64 = groups.iter().map(|group| group.reclaimable).sum();
+    let count = if groups.len() == 1 {
+        "1 group".to_string()
+    } else {
+        format!("{} groups", groups.len())
+    };
+    let plural = if duplicates == 1 { "duplicate" } else { "duplicates" };
+    println!(
+        "{}",
+        format!(
+            "{count}, {duplicates} {plural}, {} reclaimable",
+            format_bytes(reclaimable as f64)
+        )
+        .bold()
+    );
+}
+
+/// Replaces the duplicates of all groups after confirmation
+///
+/// # Arguments
+///
+/// * `files` - Scanned files
+/// * `groups` - Files with the same contents
+/// * `replacement` - What the duplicates are replaced with
+/// * `yes` - Replace without asking
+///
+/// # Errors
+///
+/// Returns an error if stdin cannot be read or a duplicate cannot be replaced.
+fn replace_all(
+    files: &[Candidate],
+    groups: &[Group],
+    replacement: Replacement,
+    force: bool,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    // Clones of the kept file need no replacing; hard links to it would save their private blocks
+    let mut pending: Vec<(&Group, &Duplicate)> = groups
+        .iter()
+        .flat_map(|group| group.duplicates.iter().map(move |duplicate| (group, duplicate)))
+        .filter(|(_, duplicate)| !(duplicate.clone && replacement == Replacement::Clone))
+        .collect();
+    // The contents of a duplicate with more hard links stay on disk through the others
+    pending.retain(|(_, duplicate)| {
+        if duplicate.linked {
+            println!(
+                "{}",
+                format!(
+                    "Skip {}: it has more hard links, replacing it would free no space",
+                    duplicate.path.display()
+                )
+                .yellow()
+            );
+        }
+        !duplicate.linked
+    });
+    if replacement == Replacement::HardLink && !force {
+        // A hard link would silently give the duplicate the owner and permissions of the kept file
+        pending.retain(|(group, duplicate)| {
+            let (keep, file) = (&files[group.index], &files[duplicate.index]);
+            let same = keep.uid == file.uid && keep.gid == file.gid && keep.mode == file.mode;
+            if !same {
+                println!(
+                    "{}",
+                    format!(
+                        "Skip {}: owner or permissions differ from {}, use --force to link it anyway",
+                        duplicate.path.display(),
+                        group.keep.display()
+                    )
+                    .yellow()
+                );
+            }
+            same
+        });
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let (noun, verb) = match replacement {
+        Replacement::Clone => ("clones", "Clone"),
+        Replacement::HardLink => ("hard links", "Link"),
+    };
+    if !yes {
+        println!();
+        let plural = if pending.len() == 1 { "duplicate" } else { "duplicates" };
+        if !confirm(&format!("Replace {} {plural} with {noun}?", pending.len()))? {
+            println!("Nothing replaced");
+            return Ok(());
+        }
+    }
+
+    let mut failed = 0;
+    let mut reclaimed = 0;
+    for (group, duplicate) in &pending {
+        print!("{verb} {}", duplicate.path.display());
+        io::stdout().flush()?;
+        match replace(&files[group.index], &files[duplicate.index], replacement) {
+            Ok(()) => {
+                reclaimed += duplicate.space;
+                println!("{}", " OK".green());
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{}", format!(" {e}").red());
+            }
+        }
+    }
+    println!("{:<16}{}", "Reclaimed".bold(), format_bytes(reclaimed as f64).green());
+    if failed > 0 {
+        return Err(format!("Failed to replace {failed} of {} duplicates", pending.len()).into());
+    }
+    Ok(())
+}
+
+/// Replaces a duplicate with a clone of or a hard link to the kept file
+///
+/// # Errors
+///
+/// Returns an error if a file changed since the scan or the clone or link
+/// cannot be created.
+fn replace(keep: &Candidate, duplicate: &Candidate, replacement: Replacement) -> Result<(), Box<dyn Error>> {
+    if changed(keep) || changed(duplicate) {
+        return Err("changed since the scan".into());
+    }
+    let name = duplicate.path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = duplicate.path.with_file_name(format!(".{name}.rem-dupe"));
+    match replacement {
+        Replacement::Clone => {
+            clone_file(&keep.path, &temporary)?;
+            if let Err(e) = copy_attributes(&duplicate.path, &temporary) {
+                let _ = fs::remove_file(&temporary);
+                return Err(e.into());
+            }
+        }
+        Replacement::HardLink => fs::hard_link(&keep.path, &temporary)?,
+    }
+    if let Err(e) = fs::rename(&temporary, &duplicate.path) {
+        let _ = fs::remove_file(&temporary);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Returns whether a file was changed, replaced or removed since the scan
+fn changed(file: &Candidate) -> bool {
+    match file.path.symlink_metadata() {
+        Ok(metadata) => {
+            metadata.dev() != file.device
+                || metadata.ino() != file.inode
+                || metadata.len() != file.size
+                || metadata.mtime() != file.modified
+                || metadata.mtime_nsec() != file.modified_nanos
+        }
+        Err(_) => true,
+    }
+}
+
+/// Gives a file the dates and permissions of another one
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or changed.
+fn copy_attributes(source: &Path, target: &Path) -> io::Result<()> {
+    let metadata = source.symlink_metadata()?;
+    let times = FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+    File::open(target)?.set_times(times)?;
+    fs::set_permissions(target, metadata.permissions())
+}
+
+/// Creates a clone of a file; requires APFS
+#[cfg(not(target_os = "macos"))]
+fn clone_file(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "clones require APFS"))
+}
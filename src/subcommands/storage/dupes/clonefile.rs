@@ -0,0 +1,35 @@
+//! APFS clones
+//!
+//! `clonefile` creates a file that shares the blocks of another one until
+//! either is changed, so the copy takes no additional space.
+
+use std::ffi::{CString, c_char};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+unsafe extern "C" {
+    fn clonefile(source: *const c_char, target: *const c_char, flags: u32) -> i32;
+}
+
+/// `CLONE_NOFOLLOW`
+const CLONE_NOFOLLOW: u32 = 0x0001;
+
+/// Creates a clone of a file
+///
+/// # Arguments
+///
+/// * `source` - File to clone
+/// * `target` - Path of the clone, which must not exist
+///
+/// # Errors
+///
+/// Returns an error if the target exists or the volume does not support clones.
+pub fn clone_file(source: &Path, target: &Path) -> io::Result<()> {
+    let source = CString::new(source.as_os_str().as_bytes())?;
+    let target = CString::new(target.as_os_str().as_bytes())?;
+    if unsafe { clonefile(source.as_ptr(), target.as_ptr(), CLONE_NOFOLLOW) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
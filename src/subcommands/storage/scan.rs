@@ -0,0 +1,314 @@
+//! Parallel folder scanner
+//!
+//! Worker threads take folders from a shared queue, add up the sizes of their
+//! files and queue their subfolders, so wide and deep trees keep all threads
+//! busy. The result is a tree of folders with the size of everything below
+//! them. [`scan_files`] additionally shows every file to a callback.
+//!
+//! Sizes are the space allocated on disk. A file with several hard links is
+//! counted once. On APFS, clones share their blocks until they are changed;
+//! the shared blocks are counted once per clone family, for every other clone
+//! only its private blocks.
+
+#[cfg(target_os = "macos")]
+mod apfs;
+
+use std::collections::HashSet;
+use std::fs;
+use std::num::NonZero;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+pub use apfs::clone_info;
+
+/// Other volumes and the system volumes, which the firmlinks of `/` already include
+const SKIPPED: [&str; 3] = ["/Volumes", "/System/Volumes", "/dev"];
+
+/// Size of the blocks counted by `st_blocks`
+pub const BLOCK_SIZE: u64 = 512;
+
+/// A scanned folder
+pub struct Folder {
+    /// Name of the folder, the full path for the scanned folder
+    pub name: String,
+    /// Index of the parent folder, `None` for the scanned folder
+    pub parent: Option<usize>,
+    /// Indices of the subfolders
+    pub children: Vec<usize>,
+    /// Size of the folder with everything below it; only its own entry during the scan
+    pub size: u64,
+    /// Number of files directly in the folder
+    pub files: u64,
+    /// Size of the files directly in the folder
+    pub files_size: u64,
+}
+
+/// The folders below a scanned folder, which is the first one
+pub struct Tree {
+    /// All folders; every folder comes after its parent
+    pub folders: Vec<Folder>,
+    /// Number of folders that could not be read
+    pub unreadable: usize,
+}
+
+impl Tree {
+    /// Returns the path of a folder
+    pub fn path(&self, folder: usize) -> PathBuf {
+        let mut names = Vec::new();
+        let mut index = Some(folder);
+        while let Some(current) = index {
+            names.push(self.folders[current].name.as_str());
+            index = self.folders[current].parent;
+        }
+        names.iter().rev().collect()
+    }
+}
+
+/// Clone information of a file that may share blocks with other files
+pub struct CloneInfo {
+    /// Size of the blocks only this file uses
+    pub private_size: u64,
+    /// Id shared by the clones of a file
+    pub clone_id: u64,
+}
+
+/// Folders waiting to be scanned
+struct Queue {
+    /// Folder indices and paths
+    pending: Vec<(usize, PathBuf)>,
+    /// Number of folders being scanned
+    busy: usize,
+}
+
+/// Files and subfolders found directly in a folder
+#[derive(Default)]
+struct Contents {
+    /// Number of files
+    files: u64,
+    /// Size of the files
+    files_size: u64,
+    /// Names, paths and own sizes of the subfolders
+    folders: Vec<(String, PathBuf, u64)>,
+    /// True if the folder could not be read
+    unreadable: bool,
+}
+
+/// Called by the worker threads with the path and metadata of every file
+type Visit<'a> = &'a (dyn Fn(&Path, &fs::Metadata) + Sync);
+
+/// State shared by the worker threads
+struct Scanner<'a> {
+    /// Folders waiting to be scanned
+    queue: Mutex<Queue>,
+    /// Signals new folders in the queue and the end of the scan
+    changed: Condvar,
+    /// The folders found so far
+    folders: Mutex<Vec<Folder>>,
+    /// Device and inode of files with several hard links
+    links: Mutex<HashSet<(u64, u64)>>,
+    /// Clone families whose shared blocks were counted
+    clones: Mutex<HashSet<u64>>,
+    /// Number of scanned folders
+    scanned: AtomicUsize,
+    /// Number of folders that could not be read
+    unreadable: AtomicUsize,
+    /// Shown every file
+    visit: Visit<'a>,
+}
+
+/// Scans a folder and everything below it
+///
+/// # Arguments
+///
+/// * `root` - Folder to scan
+/// * `progress` - Called repeatedly during the scan with the number of scanned folders
+pub fn scan(root: &Path, progress: impl Fn(usize)) -> Tree {
+    scan_files(root, progress, &|_, _| {})
+}
+
+/// Scans a folder and everything below it and shows every file to a callback
+///
+/// # Arguments
+///
+/// * `root` - Folder to scan
+/// * `progress` - Called repeatedly during the scan with the number of scanned folders
+/// * `visit` - Called from the worker threads with the path and metadata of every
+///   file; symbolic links are files as well
+pub fn scan_files(root: &Path, progress: impl Fn(usize), visit: Visit) -> Tree {
+    let scanner = Scanner {
+        queue: Mutex::new(Queue {
+            pending: vec![(0, root.to_path_buf())],
+            busy: 0,
+        }),
+        changed: Condvar::new(),
+        folders: Mutex::new(vec![Folder {
+            name: root.display().to_string(),
+            parent: None,
+            children: Vec::new(),
+            size: root
+                .symlink_metadata()
+                .map_or(0, |metadata| metadata.blocks() * BLOCK_SIZE),
+            files: 0,
+            files_size: 0,
+        }]),
+        links: Mutex::new(HashSet::new()),
+        clones: Mutex::new(HashSet::new()),
+        scanned: AtomicUsize::new(0),
+        unreadable: AtomicUsize::new(0),
+        visit,
+    };
+    // Scanning waits for the disk more than for the CPU
+    let workers = thread::available_parallelism().map_or(4, NonZero::get) * 2;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| scope.spawn(|| scanner.work())).collect();
+        while !handles.iter().all(|handle| handle.is_finished()) {
+            progress(scanner.scanned.load(Ordering::Relaxed));
+            thread::sleep(Duration::from_millis(100));
+        }
+    });
+
+    let mut folders = scanner.folders.into_inner().unwrap_or_else(|e| e.into_inner());
+    for folder in &mut folders {
+        folder.size += folder.files_size;
+    }
+    // Children come after their parents, so adding in reverse sums up whole subtrees
+    for index in (1..folders.len()).rev() {
+        if let Some(parent) = folders[index].parent {
+            folders[parent].size += folders[index].size;
+        }
+    }
+    Tree {
+        folders,
+        unreadable: scanner.unreadable.into_inner(),
+    }
+}
+
+impl Scanner<'_> {
+    /// Scans queued folders until all folders are scanned
+    fn work(&self) {
+        while let Some((index, path)) = self.next() {
+            let contents = self.read(&path);
+            if contents.unreadable {
+                self.unreadable.fetch_add(1, Ordering::Relaxed);
+            }
+
+            let mut queued = Vec::with_capacity(contents.folders.len());
+            {
+                let mut folders = self.folders.lock().unwrap_or_else(|e| e.into_inner());
+                folders[index].files = contents.files;
+                folders[index].files_size = contents.files_size;
+                for (name, path, size) in contents.folders {
+                    let child = folders.len();
+                    folders.push(Folder {
+                        name,
+                        parent: Some(index),
+                        children: Vec::new(),
+                        size,
+                        files: 0,
+                        files_size: 0,
+                    });
+                    folders[index].children.push(child);
+                    queued.push((child, path));
+                }
+            }
+
+            self.scanned.fetch_add(1, Ordering::Relaxed);
+            let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+            queue.pending.extend(queued);
+            queue.busy -= 1;
+            self.changed.notify_all();
+        }
+    }
+
+    /// Takes the next folder from the queue, waiting while other threads may still queue folders
+    ///
+    /// # Returns
+    ///
+    /// The index and path of the folder, or `None` when all folders are scanned.
+    fn next(&self) -> Option<(usize, PathBuf)> {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(folder) = queue.pending.pop() {
+                queue.busy += 1;
+                return Some(folder);
+            }
+            if queue.busy == 0 {
+                return None;
+            }
+            queue = self.changed.wait(queue).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Reads the files and subfolders directly in a folder
+    fn read(&self, path: &Path) -> Contents {
+        let mut contents = Contents::default();
+        let Ok(entries) = fs::read_dir(path) else {
+            contents.unreadable = true;
+            return contents;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // Symbolic links are not followed
+            let Ok(metadata) = path.symlink_metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                if !SKIPPED.iter().any(|skipped| path == Path::new(skipped)) {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    contents.folders.push((name, path, metadata.blocks() * BLOCK_SIZE));
+                }
+                continue;
+            }
+            (self.visit)(&path, &metadata);
+            contents.files += 1;
+            contents.files_size += self.size(&path, &metadata);
+        }
+        contents
+    }
+
+    /// Returns the space a file adds to the scanned folders
+    fn size(&self, path: &Path, metadata: &fs::Metadata) -> u64 {
+        if metadata.nlink() > 1 {
+            let mut links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+            if !links.insert((metadata.dev(), metadata.ino())) {
+                return 0;
+            }
+        }
+        let allocated = metadata.blocks() * BLOCK_SIZE;
+        if !metadata.is_file() || allocated == 0 {
+            return allocated;
+        }
+        match clone_info(path) {
+            Some(clone) if clone.private_size < allocated => {
+                let mut clones = self.clones.lock().unwrap_or_else(|e| e.into_inner());
+                if clones.insert(clone.clone_id) {
+                    allocated
+                } else {
+                    clone.private_size
+                }
+            }
+            _ => allocated,
+        }
+    }
+}
+
+/// Returns the space a file or a folder with everything below it takes on disk
+pub fn size(path: &Path) -> u64 {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => scan(path, |_| {}).folders[0].size,
+        Ok(metadata) => metadata.blocks() * BLOCK_SIZE,
+        Err(_) => 0,
+    }
+}
+
+/// Reads the clone information of a file; requires APFS
+#[cfg(not(target_os = "macos"))]
+pub fn clone_info(_path: &Path) -> Option<CloneInfo> {
+    None
+}
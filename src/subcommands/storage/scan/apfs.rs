@@ -0,0 +1,99 @@
+//! Clone information from APFS
+//!
+//! `getattrlist` reports the extended flags of a file, which tell whether it
+//! may share blocks with clones, the size of the blocks only the file uses and
+//! the id of its clone family.
+
+use super::CloneInfo;
+use std::ffi::{CString, c_char, c_void};
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+unsafe extern "C" {
+    fn getattrlist(path: *const c_char, list: *mut AttrList, buffer: *mut c_void, size: usize, options: u32) -> i32;
+}
+
+/// `struct attrlist`
+#[repr(C)]
+struct AttrList {
+    bitmap_count: u16,
+    reserved: u16,
+    common: u32,
+    volume: u32,
+    directory: u32,
+    file: u32,
+    fork: u32,
+}
+
+/// Attributes returned by `getattrlist`, in the order of their bits
+#[repr(C, packed(4))]
+struct Attributes {
+    length: u32,
+    private_size: i64,
+    clone_id: u64,
+    flags: u64,
+}
+
+/// `ATTR_BIT_MAP_COUNT`
+const ATTR_BIT_MAP_COUNT: u16 = 5;
+
+/// `ATTR_CMNEXT_PRIVATESIZE`
+const ATTR_CMNEXT_PRIVATESIZE: u32 = 0x0000_0008;
+
+/// `ATTR_CMNEXT_CLONEID`
+const ATTR_CMNEXT_CLONEID: u32 = 0x0000_0100;
+
+/// `ATTR_CMNEXT_EXT_FLAGS`
+const ATTR_CMNEXT_EXT_FLAGS: u32 = 0x0000_0200;
+
+/// `FSOPT_NOFOLLOW`
+const FSOPT_NOFOLLOW: u32 = 0x0000_0001;
+
+/// `FSOPT_ATTR_CMN_EXTENDED`, which makes the fork attributes extended common attributes
+const FSOPT_ATTR_CMN_EXTENDED: u32 = 0x0000_0020;
+
+/// `EF_MAY_SHARE_BLOCKS`
+const EF_MAY_SHARE_BLOCKS: u64 = 0x0000_0001;
+
+/// Reads the clone information of a file
+///
+/// # Returns
+///
+/// The private size and clone id, or `None` if the file shares no blocks or
+/// the volume does not support clones.
+pub fn clone_info(path: &Path) -> Option<CloneInfo> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut list = AttrList {
+        bitmap_count: ATTR_BIT_MAP_COUNT,
+        reserved: 0,
+        common: 0,
+        volume: 0,
+        directory: 0,
+        file: 0,
+        fork: ATTR_CMNEXT_PRIVATESIZE | ATTR_CMNEXT_CLONEID | ATTR_CMNEXT_EXT_FLAGS,
+    };
+    let mut attributes: Attributes = unsafe { mem::zeroed() };
+    let result = unsafe {
+        getattrlist(
+            path.as_ptr(),
+            &mut list,
+            (&raw mut attributes).cast(),
+            mem::size_of::<Attributes>(),
+            FSOPT_NOFOLLOW | FSOPT_ATTR_CMN_EXTENDED,
+        )
+    };
+    let Attributes {
+        length,
+        private_size,
+        clone_id,
+        flags,
+    } = attributes;
+    if result != 0 || (length as usize) < mem::size_of::<Attributes>() || flags & EF_MAY_SHARE_BLOCKS == 0 {
+        return None;
+    }
+    Some(CloneInfo {
+        private_size: u64::try_from(private_size).ok()?,
+        clone_id,
+    })
+}
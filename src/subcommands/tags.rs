@@ -0,0 +1,297 @@
+//! Finder tags
+//!
+//! Lists, adds and removes the tags of files and folders, which Finder
+//! stores in the `com.apple.metadata:_kMDItemUserTags` extended attribute as
+//! binary property list (see [`plist`]). Every tag is a string with the name,
+//! followed by a line break and the number of its color if it has one, e.g.
+//! `Red\n6`. Spotlight indexes the attribute, so tags set here show up in
+//! Finder and `find` searches them with `mdfind`.
+//!
+//! # System Commands Used
+//!
+//! - `mdfind -0` - Search the files with a tag
+
+use crate::cli::{FindArgs, TagColor, TagsArgs, TagsCommands};
+use crate::subcommands::find;
+use crate::xattr::{get_xattr, plist, remove_xattr, set_xattr};
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Extended attribute holding the tags
+const ATTRIBUTE: &str = "com.apple.metadata:_kMDItemUserTags";
+
+/// Tag colors by their number
+const COLORS: [&str; 8] = ["none", "gray", "green", "purple", "blue", "yellow", "red", "orange"];
+
+/// A Finder tag
+#[derive(Serialize)]
+struct Tag {
+    /// Name of the tag
+    name: String,
+    /// Color of the tag; `None` if it has none
+    color: Option<&'static str>,
+    /// Number of the color, 0 for none
+    #[serde(skip)]
+    number: usize,
+}
+
+/// The tags of a file
+#[derive(Serialize)]
+struct Tagged {
+    /// Path of the file
+    path: PathBuf,
+    /// Tags of the file
+    tags: Vec<Tag>,
+}
+
+/// Executes a tags operation
+///
+/// # Arguments
+///
+/// * `args` - Tags arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the tags of a file cannot be read or changed or the
+/// Spotlight search fails.
+pub fn perform(args: TagsArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        TagsCommands::List { paths, json } => list(&paths, json),
+        TagsCommands::Add { tag, paths, color } => add(&tag, &paths, color),
+        TagsCommands::Remove { tag, paths } => remove(&tag, &paths),
+        TagsCommands::Find { tag, folder, json } => {
+            check_name(&tag)?;
+            // The quotes of the query cannot be escaped
+            if tag.contains('"') {
+                return Err("Tags with quotes cannot be searched".into());
+            }
+            find::perform(FindArgs {
+                query: vec![format!("tag:\"{tag}\"")],
+                folder,
+                limit: None,
+                json,
+                exec: None,
+                predicate: false,
+            })
+        }
+    }
+}
+
+/// Prints the tags of files
+///
+/// # Arguments
+///
+/// * `paths` - Files and folders
+/// * `json` - Print the tags as JSON
+///
+/// # Errors
+///
+/// Returns an error if the tags of a file cannot be read.
+fn list(paths: &[PathBuf], json: bool) -> Result<(), Box<dyn Error>> {
+    let tagged: Vec<Tagged> = paths
+        .iter()
+        .map(|path| {
+            read(path)
+                .map(|tags| Tagged {
+                    path: path.clone(),
+                    tags,
+                })
+                .map_err(|e| format!("Failed to read the tags of {}: {e}", path.display()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tagged)?);
+        return Ok(());
+    }
+    // A single file gets one tag per line, which is easy to use in scripts
+    let indent = if tagged.len() > 1 { "  " } else { "" };
+    for file in &tagged {
+        if tagged.len() > 1 {
+            println!("{}", file.path.display().to_string().bold());
+        }
+        if file.tags.is_empty() {
+            println!("{indent}{}", "No tags".dimmed());
+        }
+        for tag in &file.tags {
+            println!("{indent}{}", paint(tag));
+        }
+    }
+    Ok(())
+}
+
+/// Adds a tag to files
+///
+/// # Arguments
+///
+/// * `name` - Name of the tag
+/// * `paths` - Files and folders to tag
+/// * `color` - Color of the tag; the color of Finder's tag with the name if omitted
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid or a file cannot be tagged.
+fn add(name: &str, paths: &[PathBuf], color: Option<TagColor>) -> Result<(), Box<dyn Error>> {
+    check_name(name)?;
+    let number = color.map(|color| color as usize);
+    let mut failed = 0;
+    for path in paths {
+        print!("Tag {} with {name}", path.display());
+        io::stdout().flush()?;
+        let result = read(path).and_then(|mut tags| {
+            match tags.iter_mut().find(|tag| tag.name.eq_ignore_ascii_case(name)) {
+                Some(tag) if number.is_none_or(|number| number == tag.number) => return Ok(false),
+                Some(tag) => *tag = Tag::new(&tag.name, number.unwrap_or_default()),
+                None => tags.push(Tag::new(name, number.unwrap_or_else(|| standard_color(name)))),
+            }
+            write(path, &tags).map(|()| true)
+        });
+        match result {
+            Ok(true) => println!("{}", " OK".green()),
+            Ok(false) => println!("{}", " already tagged".yellow()),
+            Err(e) => {
+                failed += 1;
+                println!("{}", format!(" {e}").red());
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("Failed to tag {failed} of {} files", paths.len()).into());
+    }
+    Ok(())
+}
+
+/// Removes a tag from files
+///
+/// # Arguments
+///
+/// * `name` - Name of the tag
+/// * `paths` - Files and folders to remove the tag from
+///
+/// # Errors
+///
+/// Returns an error if the tag cannot be removed from a file.
+fn remove(name: &str, paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    let mut failed = 0;
+    for path in paths {
+        print!("Remove {name} from {}", path.display());
+        io::stdout().flush()?;
+        let result = read(path).and_then(|mut tags| {
+            let count = tags.len();
+            tags.retain(|tag| !tag.name.eq_ignore_ascii_case(name));
+            if tags.len() == count {
+                return Ok(false);
+            }
+            write(path, &tags).map(|()| true)
+        });
+        match result {
+            Ok(true) => println!("{}", " OK".green()),
+            Ok(false) => println!("{}", " not tagged".yellow()),
+            Err(e) => {
+                failed += 1;
+                println!("{}", format!(" {e}").red());
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("Failed to remove the tag from {failed} of {} files", paths.len()).into());
+    }
+    Ok(())
+}
+
+impl Tag {
+    /// Creates a tag with the number of its color
+    fn new(name: &str, number: usize) -> Self {
+        let color = COLORS.get(number).copied().filter(|_| number > 0);
+        Tag {
+            name: name.to_string(),
+            color,
+            number: if color.is_some() { number } else { 0 },
+        }
+    }
+
+    /// Parses a tag stored as name with an optional line break and color number
+    fn parse(tag: &str) -> Self {
+        match tag.split_once('\n') {
+            Some((name, number)) => Tag::new(name, number.trim().parse().unwrap_or_default()),
+            None => Tag::new(tag, 0),
+        }
+    }
+
+    /// Formats the tag the way it is stored
+    fn stored(&self) -> String {
+        if self.number == 0 {
+            self.name.clone()
+        } else {
+            format!("{}\n{}", self.name, self.number)
+        }
+    }
+}
+
+/// Returns the color number of Finder's color tag with a name, or 0
+fn standard_color(name: &str) -> usize {
+    COLORS
+        .iter()
+        .skip(1)
+        .position(|color| color.eq_ignore_ascii_case(name))
+        .map_or(0, |position| position + 1)
+}
+
+/// Colors the name of a tag like its color
+fn paint(tag: &Tag) -> ColoredString {
+    match tag.color {
+        Some("gray") => tag.name.bright_black(),
+        Some("green") => tag.name.green(),
+        Some("purple") => tag.name.magenta(),
+        Some("blue") => tag.name.blue(),
+        Some("yellow") => tag.name.yellow(),
+        Some("red") => tag.name.red(),
+        Some(_) => tag.name.truecolor(255, 149, 0),
+        None => tag.name.normal(),
+    }
+}
+
+/// Checks that a tag name is not empty and has no line breaks
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid.
+fn check_name(name: &str) -> Result<(), Box<dyn Error>> {
+    if name.trim().is_empty() {
+        return Err("The tag name is empty".into());
+    }
+    if name.contains('\n') {
+        return Err("Tag names cannot contain line breaks".into());
+    }
+    Ok(())
+}
+
+/// Reads the tags of a file
+///
+/// # Errors
+///
+/// Returns an error if the attribute cannot be read or is no valid property list.
+fn read(path: &Path) -> Result<Vec<Tag>, Box<dyn Error>> {
+    match get_xattr(path, ATTRIBUTE)? {
+        Some(value) => Ok(plist::decode(&value)?.iter().map(|tag| Tag::parse(tag)).collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Writes the tags of a file, removing the attribute if there are none
+///
+/// # Errors
+///
+/// Returns an error if the attribute cannot be written.
+fn write(path: &Path, tags: &[Tag]) -> Result<(), Box<dyn Error>> {
+    if tags.is_empty() {
+        remove_xattr(path, ATTRIBUTE)?;
+    } else {
+        let stored: Vec<String> = tags.iter().map(Tag::stored).collect();
+        set_xattr(path, ATTRIBUTE, &plist::encode(&stored))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,204 @@
+//! Thermal state and throttling
+//!
+//! Reports the thermal pressure level macOS publishes to apps, the CPU speed
+//! limit from the power management and the fan speeds from the SMC. Under
+//! sustained load the pressure rises from `nominal` to `moderate` and `heavy`,
+//! and macOS throttles the CPU; Intel Macs also report the speed limit in
+//! percent.
+//!
+//! With `--watch`, one line is printed per interval, e.g. to observe throttling
+//! during heavy builds.
+//!
+//! # System Commands Used
+//!
+//! - `notifyutil -g com.apple.system.thermalpressurelevel` - Read the thermal pressure level
+//! - `pmset -g therm` - Read the CPU speed limit and thermal warnings
+//! - Fan speeds are read from the `AppleSMC` service through IOKit
+
+#[cfg(target_os = "macos")]
+mod smc;
+
+use crate::cli::ThermalsArgs;
+use crate::subcommands::power::pmset;
+use crate::util::timestamp;
+use colored::{ColoredString, Colorize};
+use std::error::Error;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use smc::read_fans;
+
+/// Notification state holding the thermal pressure level
+const PRESSURE_STATE: &str = "com.apple.system.thermalpressurelevel";
+
+/// Names of the thermal pressure levels, starting at 0
+const PRESSURE_LEVELS: [&str; 5] = ["nominal", "moderate", "heavy", "trapping", "sleeping"];
+
+/// Speed of a fan in revolutions per minute
+struct Fan {
+    /// Current speed
+    rpm: f64,
+    /// Lowest speed the fan runs at
+    min_rpm: Option<f64>,
+    /// Highest speed of the fan
+    max_rpm: Option<f64>,
+}
+
+/// Thermal state of the Mac
+struct Thermals {
+    /// Thermal pressure level, 0 (nominal) to 4 (sleeping)
+    pressure: Option<u64>,
+    /// CPU speed limit in percent, only reported by Intel Macs
+    speed_limit: Option<u32>,
+    /// Thermal and performance warnings, e.g. `Thermal warning level: 1`
+    warnings: Vec<String>,
+    /// Fans, or why they cannot be read
+    fans: Result<Vec<Fan>, String>,
+}
+
+/// Shows the thermal state once or at an interval
+///
+/// # Arguments
+///
+/// * `args` - Thermals arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if none of the thermal information can be read.
+pub fn perform(args: ThermalsArgs) -> Result<(), Box<dyn Error>> {
+    if !args.watch {
+        let thermals = read_thermals();
+        if thermals.pressure.is_none() && thermals.speed_limit.is_none() && thermals.fans.is_err() {
+            return Err("Failed to read the thermal state".into());
+        }
+        print_status(&thermals);
+        return Ok(());
+    }
+
+    println!("Reading the thermal state every {} s (Ctrl-C to stop)", args.interval);
+    loop {
+        print_line(&read_thermals());
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Reads the thermal pressure, the speed limit and the fans
+fn read_thermals() -> Thermals {
+    let (speed_limit, warnings) = match pmset(&["-g", "therm"]) {
+        Ok(output) => parse_therm(&output),
+        Err(_) => (None, Vec::new()),
+    };
+    Thermals {
+        pressure: pressure_level(),
+        speed_limit,
+        warnings,
+        fans: read_fans().map_err(|e| e.to_string()),
+    }
+}
+
+/// Prints the thermal state as a list of properties
+fn print_status(thermals: &Thermals) {
+    println!("{:<16}{}", "Pressure".bold(), format_pressure(thermals.pressure));
+    let limit = match thermals.speed_limit {
+        Some(limit) => format_limit(limit),
+        None => "not reported".dimmed(),
+    };
+    println!("{:<16}{limit}", "CPU speed limit".bold());
+
+    match &thermals.fans {
+        Ok(fans) if fans.is_empty() => println!("{:<16}{}", "Fans".bold(), "none".dimmed()),
+        Ok(fans) => {
+            for (index, fan) in fans.iter().enumerate() {
+                let range = match (fan.min_rpm, fan.max_rpm) {
+                    (Some(min), Some(max)) => format!(" ({min:.0}-{max:.0} rpm)"),
+                    _ => String::new(),
+                };
+                println!("{:<16}{:.0} rpm{range}", format!("Fan {}", index + 1).bold(), fan.rpm);
+            }
+        }
+        Err(e) => println!("{:<16}{}", "Fans".bold(), e.dimmed()),
+    }
+
+    for warning in &thermals.warnings {
+        println!("{}", warning.yellow());
+    }
+}
+
+/// Prints the thermal state as a single line for watch mode
+fn print_line(thermals: &Thermals) {
+    let mut fields = vec![timestamp(), format!("pressure {}", format_pressure(thermals.pressure))];
+    if let Some(limit) = thermals.speed_limit {
+        fields.push(format!("speed limit {}", format_limit(limit)));
+    }
+    if let Ok(fans) = &thermals.fans
+        && !fans.is_empty()
+    {
+        let speeds: Vec<String> = fans.iter().map(|fan| format!("{:.0}", fan.rpm)).collect();
+        fields.push(format!("fans {} rpm", speeds.join("/")));
+    }
+    println!("{}", fields.join("  "));
+}
+
+/// Reads the thermal pressure level published by macOS
+fn pressure_level() -> Option<u64> {
+    let output = Command::new("notifyutil").args(["-g", PRESSURE_STATE]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()?
+        .parse()
+        .ok()
+}
+
+/// Parses the CPU speed limit and warnings of `pmset -g therm`
+///
+/// Intel Macs print `CPU_Speed_Limit = 100`; lines starting with `Note:` say
+/// that nothing has been recorded and are skipped.
+fn parse_therm(output: &str) -> (Option<u32>, Vec<String>) {
+    let mut speed_limit = None;
+    let mut warnings = Vec::new();
+    for line in output.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("Note:") {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) if key.trim() == "CPU_Speed_Limit" => speed_limit = value.trim().parse().ok(),
+            Some(_) => {}
+            None if line.to_lowercase().contains("warning level") => warnings.push(line.to_string()),
+            None => {}
+        }
+    }
+    (speed_limit, warnings)
+}
+
+/// Formats the thermal pressure level, colored by severity
+fn format_pressure(level: Option<u64>) -> ColoredString {
+    let Some(level) = level else {
+        return "unknown".dimmed();
+    };
+    let name = usize::try_from(level)
+        .ok()
+        .and_then(|index| PRESSURE_LEVELS.get(index))
+        .map_or_else(|| format!("level {level}"), |name| name.to_string());
+    match level {
+        0 => name.green(),
+        1 => name.yellow(),
+        _ => name.red(),
+    }
+}
+
+/// Formats the CPU speed limit, colored if the CPU is throttled
+fn format_limit(limit: u32) -> ColoredString {
+    let text = format!("{limit}%");
+    if limit < 100 { text.yellow() } else { text.green() }
+}
+
+/// Reads the fans from the SMC; requires IOKit
+#[cfg(not(target_os = "macos"))]
+fn read_fans() -> Result<Vec<Fan>, Box<dyn Error>> {
+    Err("The SMC is only available on macOS".into())
+}
@@ -0,0 +1,187 @@
+//! Fan speeds from the System Management Controller
+//!
+//! The SMC is read through the user client of the `AppleSMC` service: every
+//! value is addressed by a four character key, e.g. `FNum` for the number of
+//! fans and `F0Ac` for the current speed of the first fan. Intel Macs store fan
+//! speeds as unsigned fixed point numbers (`fpe2`), Apple silicon as floats
+//! (`flt `).
+
+use super::Fan;
+use crate::iokit::matching_service;
+use std::error::Error;
+use std::ffi::c_void;
+use std::mem;
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOServiceOpen(service: u32, owning_task: u32, kind: u32, connect: *mut u32) -> i32;
+    fn IOServiceClose(connect: u32) -> i32;
+    fn IOConnectCallStructMethod(
+        connect: u32,
+        selector: u32,
+        input: *const c_void,
+        input_size: usize,
+        output: *mut c_void,
+        output_size: *mut usize,
+    ) -> i32;
+}
+
+unsafe extern "C" {
+    static mach_task_self_: u32;
+}
+
+/// Method of the SMC user client handling all requests
+const HANDLE_EVENT: u32 = 2;
+
+/// Request reading the value of a key
+const READ_KEY: u8 = 5;
+
+/// Request reading the size and type of a key
+const GET_KEY_INFO: u8 = 9;
+
+/// Firmware version in an SMC request
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct Version {
+    major: u8,
+    minor: u8,
+    build: u8,
+    reserved: u8,
+    release: u16,
+}
+
+/// Power limits in an SMC request
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct PowerLimits {
+    version: u16,
+    length: u16,
+    cpu: u32,
+    gpu: u32,
+    memory: u32,
+}
+
+/// Size and type of a key
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct KeyInfo {
+    size: u32,
+    data_type: u32,
+    attributes: u8,
+}
+
+/// Request and response of the SMC user client (`SMCKeyData_t`)
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct KeyData {
+    key: u32,
+    version: Version,
+    power_limits: PowerLimits,
+    key_info: KeyInfo,
+    result: u8,
+    status: u8,
+    command: u8,
+    data: u32,
+    bytes: [u8; 32],
+}
+
+/// An open connection to the SMC, closed on drop
+struct Smc(u32);
+
+impl Smc {
+    /// Opens a connection to the SMC
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Mac has no SMC or it cannot be opened.
+    fn open() -> Result<Self, Box<dyn Error>> {
+        let service = matching_service(c"AppleSMC").ok_or("Failed to find the SMC")?;
+        let mut connect = 0;
+        let result = unsafe { IOServiceOpen(service.id(), mach_task_self_, 0, &mut connect) };
+        if result != 0 {
+            return Err(format!("Failed to open the SMC (error {result:#x})").into());
+        }
+        Ok(Smc(connect))
+    }
+
+    /// Sends a request to the SMC
+    fn call(&self, input: &KeyData) -> Option<KeyData> {
+        let mut output = KeyData::default();
+        let mut size = mem::size_of::<KeyData>();
+        let result = unsafe {
+            IOConnectCallStructMethod(
+                self.0,
+                HANDLE_EVENT,
+                (input as *const KeyData).cast(),
+                mem::size_of::<KeyData>(),
+                (&mut output as *mut KeyData).cast(),
+                &mut size,
+            )
+        };
+        (result == 0 && output.result == 0).then_some(output)
+    }
+
+    /// Reads a numeric value
+    ///
+    /// # Returns
+    ///
+    /// `None` if the key does not exist or has an unsupported type.
+    fn read(&self, key: &str) -> Option<f64> {
+        let key = four_char_code(key)?;
+        let info = self.call(&KeyData {
+            key,
+            command: GET_KEY_INFO,
+            ..KeyData::default()
+        })?;
+        let value = self.call(&KeyData {
+            key,
+            key_info: info.key_info,
+            command: READ_KEY,
+            ..KeyData::default()
+        })?;
+
+        let bytes = value.bytes;
+        match &info.key_info.data_type.to_be_bytes() {
+            b"fpe2" => Some(f64::from(u16::from_be_bytes([bytes[0], bytes[1]])) / 4.0),
+            b"flt " => Some(f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))),
+            b"ui8 " => Some(f64::from(bytes[0])),
+            b"ui16" => Some(f64::from(u16::from_be_bytes([bytes[0], bytes[1]]))),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Smc {
+    fn drop(&mut self) {
+        unsafe { IOServiceClose(self.0) };
+    }
+}
+
+/// Reads the current, minimum and maximum speed of all fans
+///
+/// # Returns
+///
+/// An empty list on Macs without fans.
+///
+/// # Errors
+///
+/// Returns an error if the SMC cannot be opened.
+pub fn read_fans() -> Result<Vec<Fan>, Box<dyn Error>> {
+    let smc = Smc::open()?;
+    let count = smc.read("FNum").unwrap_or(0.0) as u8;
+    Ok((0..count)
+        .filter_map(|index| {
+            Some(Fan {
+                rpm: smc.read(&format!("F{index}Ac"))?,
+                min_rpm: smc.read(&format!("F{index}Mn")),
+                max_rpm: smc.read(&format!("F{index}Mx")),
+            })
+        })
+        .collect())
+}
+
+/// Converts a four character key to its numeric form, e.g. `FNum`
+fn four_char_code(key: &str) -> Option<u32> {
+    let bytes: [u8; 4] = key.as_bytes().try_into().ok()?;
+    Some(u32::from_be_bytes(bytes))
+}
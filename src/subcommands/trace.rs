@@ -0,0 +1,363 @@
+//! Traceroute with hop enrichment
+//!
+//! Wraps the system `traceroute` (or `traceroute6`) and enriches every hop with
+//! the host name of its PTR record and the autonomous system announcing its
+//! address. The AS number, AS name and the registry country of the prefix are
+//! looked up in the IP to ASN mapping of Team Cymru, which is queried over DNS
+//! (`<reversed address>.origin.asn.cymru.com` and `AS<N>.asn.cymru.com`, both TXT)
+//! with the built-in DNS client. Private addresses are not looked up.
+//!
+//! Hops are printed as soon as `traceroute` reports them. With `--json`, all hops
+//! are printed at the end, e.g. to diff the routes of different networks.
+//!
+//! # System Commands Used
+//!
+//! - `traceroute` / `traceroute6` - Probe the route with UDP or ICMP packets
+//! - `scutil --dns` - Find the nameserver for the lookups
+
+use super::dns::client::{self, RecordData, RecordType};
+use super::dns::reverse::{ptr_names, reverse_name};
+use super::dns::system_nameservers;
+use crate::cli::TraceArgs;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Maximum time for each enrichment lookup
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A hop of the route
+#[derive(Serialize)]
+struct Hop {
+    /// Number of the hop, starting at 1
+    hop: u8,
+    /// Address of the router that replied, `None` if no probe was answered
+    address: Option<IpAddr>,
+    /// Round trip times of the answered probes in milliseconds
+    rtts: Vec<f64>,
+    /// Number of probes without reply
+    lost: usize,
+    /// Host name of the PTR record
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Autonomous system announcing the address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    asn: Option<u32>,
+    /// Name of the autonomous system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    as_name: Option<String>,
+    /// Country the announced prefix is registered in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    country: Option<String>,
+}
+
+/// Looks up host names and autonomous systems of hop addresses
+struct Enricher {
+    /// Nameserver for the lookups
+    server: IpAddr,
+    /// AS names by AS number, to query every AS only once
+    as_names: HashMap<u32, Option<String>>,
+}
+
+impl Enricher {
+    /// Adds the host name and autonomous system to a hop
+    ///
+    /// Failed lookups leave the fields empty.
+    fn enrich(&mut self, hop: &mut Hop) {
+        let Some(address) = hop.address else {
+            return;
+        };
+        hop.name = ptr_names(address, self.server, LOOKUP_TIMEOUT)
+            .ok()
+            .and_then(|names| names.into_iter().next());
+        let Some(origin) = origin(address, self.server, LOOKUP_TIMEOUT) else {
+            return;
+        };
+        hop.asn = origin.asn;
+        hop.country = origin.country;
+
+        if let Some(asn) = hop.asn {
+            if !self.as_names.contains_key(&asn) {
+                // e.g. "3320 | DE | ripencc | 1995-01-01 | DTAG Internet service provider operations, DE"
+                let as_name = txt(&format!("AS{asn}.asn.cymru.com"), self.server, LOOKUP_TIMEOUT)
+                    .and_then(|record| record.split('|').nth(4).map(|name| name.trim().to_string()));
+                self.as_names.insert(asn, as_name);
+            }
+            hop.as_name = self.as_names[&asn].clone();
+        }
+    }
+}
+
+/// Traces the route to a host and prints the hops
+///
+/// # Arguments
+///
+/// * `args` - Trace arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the host cannot be resolved or `traceroute` cannot be run
+/// or fails.
+pub fn perform(args: TraceArgs) -> Result<(), Box<dyn Error>> {
+    let address = resolve(&args.host, args.ipv6)?;
+
+    let mut enricher = if args.numeric {
+        None
+    } else {
+        match system_nameservers().map(|servers| servers.first().copied()) {
+            Ok(Some(server)) => Some(Enricher {
+                server,
+                as_names: HashMap::new(),
+            }),
+            _ => {
+                eprintln!(
+                    "{}",
+                    "No DNS servers configured, skipping host name and AS lookups".yellow()
+                );
+                None
+            }
+        }
+    };
+
+    let program = if address.is_ipv6() { "traceroute6" } else { "traceroute" };
+    let mut command = Command::new(program);
+    command
+        .args(["-n", "-q", &args.queries.to_string(), "-w", &args.wait.to_string()])
+        .args(["-m", &args.max_hops.to_string()]);
+    if args.icmp {
+        command.arg("-I");
+    }
+    let mut child = command
+        .arg(address.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+
+    let width = if address.is_ipv6() { 39 } else { 15 };
+    if !args.json {
+        println!(
+            "Tracing the route to {} ({address}), {} hops max",
+            args.host.bold(),
+            args.max_hops
+        );
+        println!("{:>3}  {:<width$}  {:>9}  {:<40}  AS", "HOP", "ADDRESS", "RTT", "NAME");
+    }
+
+    let mut hops = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let Some(mut hop) = parse_hop(&line?) else {
+                continue;
+            };
+            if let Some(enricher) = enricher.as_mut() {
+                enricher.enrich(&mut hop);
+            }
+            if !args.json {
+                print_hop(&hop, width);
+            }
+            hops.push(hop);
+        }
+    }
+
+    let mut stderr = String::new();
+    if let Some(mut pipe) = child.stderr.take() {
+        pipe.read_to_string(&mut stderr)?;
+    }
+    if !child.wait()?.success() {
+        return Err(format!("{program} failed: {}", stderr.trim()).into());
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hops)?);
+    }
+
+    Ok(())
+}
+
+/// Resolves the host to an address of the requested family
+///
+/// IP addresses are used as given, regardless of `ipv6`.
+///
+/// # Errors
+///
+/// Returns an error if the host cannot be resolved or has no address of the family.
+fn resolve(host: &str, ipv6: bool) -> Result<IpAddr, Box<dyn Error>> {
+    if let Ok(address) = host.parse() {
+        return Ok(address);
+    }
+
+    (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve '{host}': {e}"))?
+        .map(|address| address.ip())
+        .find(|address| address.is_ipv6() == ipv6)
+        .ok_or_else(|| format!("'{host}' has no {} address", if ipv6 { "IPv6" } else { "IPv4" }).into())
+}
+
+/// Prints a hop as a table row
+///
+/// # Arguments
+///
+/// * `hop` - Hop to print
+/// * `width` - Width of the address column
+fn print_hop(hop: &Hop, width: usize) {
+    let Some(address) = hop.address else {
+        println!("{:>3}  {}", hop.hop, "*".dimmed());
+        return;
+    };
+
+    let rtt = if hop.rtts.is_empty() {
+        "*".to_string()
+    } else {
+        format!("{:.1} ms", hop.rtts.iter().sum::<f64>() / hop.rtts.len() as f64)
+    };
+    let autonomous_system = match (hop.asn, &hop.as_name) {
+        (Some(asn), Some(as_name)) => format!("AS{asn} {}", as_name_without_country(as_name, hop.country.as_deref())),
+        (Some(asn), None) => format!("AS{asn}"),
+        _ => String::new(),
+    };
+    let autonomous_system = match &hop.country {
+        Some(country) if !autonomous_system.is_empty() => format!("{autonomous_system} ({country})"),
+        _ => autonomous_system,
+    };
+    let rtt = if hop.lost > 0 { rtt.yellow() } else { rtt.normal() };
+
+    let row = format!(
+        "{:>3}  {:<width$}  {rtt:>9}  {:<40}",
+        hop.hop,
+        address.to_string(),
+        hop.name.as_deref().unwrap_or("-")
+    );
+    if autonomous_system.is_empty() {
+        println!("{}", row.trim_end());
+    } else {
+        println!("{row}  {}", autonomous_system.cyan());
+    }
+}
+
+/// Removes the `, <COUNTRY>` suffix of Team Cymru AS names, e.g. `GOOGLE, US`
+fn as_name_without_country<'a>(as_name: &'a str, country: Option<&str>) -> &'a str {
+    country
+        .and_then(|country| as_name.strip_suffix(country))
+        .and_then(|name| name.strip_suffix(", "))
+        .unwrap_or(as_name)
+}
+
+/// Parses a hop line of `traceroute -n`
+///
+/// Lines look like ` 3  62.155.245.50  10.611 ms  10.427 ms *`: the hop number
+/// followed by the replies of the probes. A reply starts with the address if it
+/// differs from the previous one, lost probes are shown as `*` and annotations
+/// like `!H` (host unreachable) are ignored.
+fn parse_hop(line: &str) -> Option<Hop> {
+    let mut tokens = line.split_whitespace().peekable();
+    let hop = tokens.next()?.parse().ok()?;
+    let mut address = None;
+    let mut rtts = Vec::new();
+    let mut lost = 0;
+
+    while let Some(token) = tokens.next() {
+        if token == "*" {
+            lost += 1;
+        } else if let Ok(replied) = token.parse::<IpAddr>() {
+            address.get_or_insert(replied);
+        } else if let Ok(rtt) = token.parse::<f64>()
+            && tokens.peek() == Some(&"ms")
+        {
+            tokens.next();
+            rtts.push(rtt);
+        }
+    }
+
+    Some(Hop {
+        hop,
+        address,
+        rtts,
+        lost,
+        name: None,
+        asn: None,
+        as_name: None,
+        country: None,
+    })
+}
+
+/// Autonomous system announcing an address
+pub struct Origin {
+    /// Number of the autonomous system
+    pub asn: Option<u32>,
+    /// Country the announced prefix is registered in
+    pub country: Option<String>,
+}
+
+/// Looks up the autonomous system announcing a public address at Team Cymru
+///
+/// # Arguments
+///
+/// * `address` - Address to look up
+/// * `server` - Nameserver to query
+/// * `timeout` - Maximum time for the lookup
+///
+/// # Returns
+///
+/// `None` for addresses that are not public or if the lookup fails.
+pub fn origin(address: IpAddr, server: IpAddr, timeout: Duration) -> Option<Origin> {
+    if !is_public(address) {
+        return None;
+    }
+
+    // e.g. "3320 | 62.152.0.0/14 | DE | ripencc | 1997-02-25"
+    let name = reverse_name(address)
+        .replace(".in-addr.arpa", ".origin.asn.cymru.com")
+        .replace(".ip6.arpa", ".origin6.asn.cymru.com");
+    let origin = txt(&name, server, timeout)?;
+    let fields: Vec<&str> = origin.split('|').map(str::trim).collect();
+    Some(Origin {
+        asn: fields
+            .first()
+            .and_then(|asns| asns.split_whitespace().next())
+            .and_then(|asn| asn.parse().ok()),
+        country: fields
+            .get(2)
+            .filter(|country| !country.is_empty())
+            .map(|country| country.to_string()),
+    })
+}
+
+/// Returns the first string of the first TXT record of a name
+fn txt(name: &str, server: IpAddr, timeout: Duration) -> Option<String> {
+    let response = client::query(server, name, RecordType::Txt, timeout).ok()?;
+    response.answers.into_iter().find_map(|record| match record.data {
+        RecordData::Txt(strings) => strings.into_iter().next(),
+        _ => None,
+    })
+}
+
+/// Returns true if an address is routed on the internet
+///
+/// Private, shared (carrier-grade NAT), loopback, link-local and unique local
+/// addresses are not.
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, ..] = address.octets();
+            !(address.is_private()
+                || address.is_loopback()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(address) => {
+            let first = address.segments()[0];
+            !(address.is_loopback()
+                || address.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
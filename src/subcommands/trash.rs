@@ -0,0 +1,314 @@
+//! Trash
+//!
+//! Lists, empties and restores the Trash and moves files to it. Every volume
+//! has its own trash: `~/.Trash` on the startup volume and `.Trashes/<uid>` at
+//! the root of other volumes. All of them are listed and emptied.
+//!
+//! Files are moved to the Trash by Finder, which records where they came from
+//! in the `.DS_Store` file of the trash (see [`ds_store`]). This is what the
+//! Put Back command of Finder uses, and `restore` uses it as well.
+//!
+//! The time an item was moved to the Trash is its status change time, which
+//! moving it sets.
+//!
+//! # System Commands Used
+//!
+//! - `osascript` - Move files to the Trash with Finder
+
+pub mod ds_store;
+
+use crate::cli::{TrashArgs, TrashCommands};
+use crate::config::home_dir;
+use crate::subcommands::storage::scan;
+use crate::util::{confirm, format_bytes, format_count, match_name};
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{self, Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+unsafe extern "C" {
+    fn getuid() -> u32;
+}
+
+/// Folder with the mount points of other volumes
+const VOLUMES: &str = "/Volumes";
+
+/// Seconds per day
+const DAY_SECONDS: u64 = 86_400;
+
+/// An item in a trash
+struct TrashItem {
+    /// Name in the trash
+    name: String,
+    /// Location in the trash
+    path: PathBuf,
+    /// Space on disk
+    size: u64,
+    /// Seconds since the item was moved to the trash
+    age: u64,
+    /// Where the item came from, if Finder recorded it
+    original: Option<PathBuf>,
+}
+
+/// Performs trash operations
+///
+/// # Arguments
+///
+/// * `args` - Trash arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the Trash cannot be read or an item cannot be moved.
+pub fn perform(args: TrashArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(TrashCommands::List) => list(),
+        Some(TrashCommands::Empty { older_than, yes }) => empty(older_than, yes),
+        Some(TrashCommands::Put { files }) => put(&files),
+        Some(TrashCommands::Restore { item }) => restore(&item),
+    }
+}
+
+/// Prints the items in the Trash, most recently trashed first
+///
+/// # Errors
+///
+/// Returns an error if the home folder cannot be determined.
+fn list() -> Result<(), Box<dyn Error>> {
+    let items = items()?;
+    if items.is_empty() {
+        println!("The Trash is empty");
+        return Ok(());
+    }
+    for item in &items {
+        let original = item
+            .original
+            .as_ref()
+            .and_then(|original| original.parent())
+            .map(|folder| format!("from {}", folder.display()))
+            .unwrap_or_default();
+        println!(
+            "{:<12}{:>10}  {}  {}",
+            format_age(item.age),
+            format_bytes(item.size as f64),
+            item.name,
+            original.dimmed()
+        );
+    }
+    let total: u64 = items.iter().map(|item| item.size).sum();
+    println!(
+        "{}",
+        format!("{}, {}", format_count(items.len(), "item"), format_bytes(total as f64)).bold()
+    );
+    Ok(())
+}
+
+/// Deletes the items in the Trash for good
+///
+/// # Arguments
+///
+/// * `older_than` - Only delete items trashed longer ago
+/// * `yes` - Delete without asking
+///
+/// # Errors
+///
+/// Returns an error if the Trash cannot be read or stdin cannot be read.
+fn empty(older_than: Option<Duration>, yes: bool) -> Result<(), Box<dyn Error>> {
+    let min_age = older_than.map_or(0, |age| age.as_secs());
+    let items: Vec<TrashItem> = items()?.into_iter().filter(|item| item.age >= min_age).collect();
+    if items.is_empty() {
+        match older_than {
+            Some(_) => println!("Nothing in the Trash is that old"),
+            None => println!("The Trash is empty"),
+        }
+        return Ok(());
+    }
+
+    let total: u64 = items.iter().map(|item| item.size).sum();
+    if !yes {
+        let question = format!(
+            "Delete {} ({}) for good?",
+            format_count(items.len(), "item"),
+            format_bytes(total as f64)
+        );
+        if !confirm(&question)? {
+            println!("Nothing deleted");
+            return Ok(());
+        }
+    }
+
+    print!("Empty the Trash");
+    io::stdout().flush()?;
+    let mut failed = 0;
+    let mut recovered = 0;
+    for item in &items {
+        let result = if item.path.symlink_metadata().is_ok_and(|metadata| metadata.is_dir()) {
+            fs::remove_dir_all(&item.path)
+        } else {
+            fs::remove_file(&item.path)
+        };
+        match result {
+            Ok(()) => recovered += item.size,
+            Err(_) => failed += 1,
+        }
+    }
+    if failed == 0 {
+        println!("{}", " OK".green());
+    } else {
+        println!(
+            "{}",
+            format!(" {} locked or in use", format_count(failed, "item")).yellow()
+        );
+    }
+    println!("{:<16}{}", "Recovered".bold(), format_bytes(recovered as f64).green());
+    Ok(())
+}
+
+/// Moves files to the Trash with Finder
+///
+/// # Arguments
+///
+/// * `files` - Files and folders to move, in this order
+///
+/// # Errors
+///
+/// Returns an error if a file does not exist or Finder fails to move it.
+pub fn put(files: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    for file in files {
+        // Symbolic links themselves are trashed, not their targets
+        file.symlink_metadata()
+            .map_err(|e| format!("Failed to open {}: {e}", file.display()))?;
+        let path = path::absolute(file)?;
+        print!("Move {} to the Trash", file.display());
+        io::stdout().flush()?;
+        let output = Command::new("osascript")
+            .args([
+                "-e",
+                "on run argv",
+                "-e",
+                "tell application \"Finder\" to delete (POSIX file (item 1 of argv) as alias)",
+                "-e",
+                "end run",
+            ])
+            .arg(&path)
+            .output()
+            .map_err(|e| format!("Failed to run osascript: {e}"))?;
+        if !output.status.success() {
+            println!();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Finder failed to move {}: {}", file.display(), stderr.trim()).into());
+        }
+        println!("{}", " OK".green());
+    }
+    Ok(())
+}
+
+/// Moves an item from the Trash back to where it came from
+///
+/// # Arguments
+///
+/// * `query` - Name or part of the name of the item
+///
+/// # Errors
+///
+/// Returns an error if no item or several items match, Finder did not record
+/// where the item came from, or it cannot be moved back.
+fn restore(query: &str) -> Result<(), Box<dyn Error>> {
+    let items = items()?;
+    let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+    let index = match_name(&names, query)?.ok_or_else(|| format!("No item in the Trash matches '{query}'"))?;
+    let item = &items[index];
+    let original = item.original.as_ref().ok_or_else(|| {
+        format!(
+            "Finder did not record where {} came from; it was not moved to the Trash by Finder",
+            item.name
+        )
+    })?;
+    if original.symlink_metadata().is_ok() {
+        return Err(format!("{} already exists", original.display()).into());
+    }
+    let folder = original.parent().unwrap_or(Path::new("/"));
+    if !folder.is_dir() {
+        return Err(format!("The folder {} no longer exists", folder.display()).into());
+    }
+
+    print!("Restore {} to {}", item.name, folder.display());
+    io::stdout().flush()?;
+    if let Err(e) = fs::rename(&item.path, original) {
+        println!();
+        return Err(format!("Failed to move {}: {e}", item.name).into());
+    }
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Reads the items in the trashes of all volumes, most recently trashed first
+///
+/// # Errors
+///
+/// Returns an error if the home folder cannot be determined.
+fn items() -> Result<Vec<TrashItem>, Box<dyn Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut items = Vec::new();
+    for (trash, volume) in trashes()? {
+        let Ok(entries) = fs::read_dir(&trash) else {
+            continue;
+        };
+        let put_back = ds_store::put_back(&trash);
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == ".DS_Store" {
+                continue;
+            }
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            let original = put_back.get(&name).map(|put_back| {
+                volume
+                    .join(&put_back.folder)
+                    .join(put_back.name.as_deref().unwrap_or(&name))
+            });
+            items.push(TrashItem {
+                size: scan::size(&entry.path()),
+                age: now.saturating_sub(u64::try_from(metadata.ctime()).unwrap_or_default()),
+                path: entry.path(),
+                name,
+                original,
+            });
+        }
+    }
+    items.sort_by_key(|item| item.age);
+    Ok(items)
+}
+
+/// Returns the trashes of the current user with the root of their volume
+///
+/// # Errors
+///
+/// Returns an error if the home folder cannot be determined.
+fn trashes() -> Result<Vec<(PathBuf, PathBuf)>, Box<dyn Error>> {
+    let mut trashes = vec![(home_dir()?.join(".Trash"), PathBuf::from("/"))];
+    let uid = unsafe { getuid() };
+    if let Ok(volumes) = fs::read_dir(VOLUMES) {
+        // The startup volume is a symbolic link to `/` and skipped
+        for volume in volumes.flatten() {
+            if volume.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                let volume = volume.path();
+                trashes.push((volume.join(".Trashes").join(uid.to_string()), volume));
+            }
+        }
+    }
+    Ok(trashes)
+}
+
+/// Formats the time since an item was trashed
+fn format_age(seconds: u64) -> String {
+    match seconds / DAY_SECONDS {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        days => format!("{days} days ago"),
+    }
+}
@@ -0,0 +1,190 @@
+//! Put Back information from the `.DS_Store` of a trash
+//!
+//! When Finder moves an item to the Trash, it records where the item came
+//! from in the `.DS_Store` file of the trash: `ptbL` holds the folder relative
+//! to the root of the volume, `ptbN` the original name if the item had to be
+//! renamed in the trash.
+//!
+//! A `.DS_Store` file is a B-tree of records stored in the blocks of a buddy
+//! allocator. All numbers are big-endian, all strings UTF-16.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Where a trashed item came from
+#[derive(Default)]
+pub struct PutBack {
+    /// Folder relative to the root of the volume
+    pub folder: String,
+    /// Original name, if it differs from the name in the trash
+    pub name: Option<String>,
+}
+
+/// Magic of the buddy allocator after the leading version number
+const MAGIC: &[u8; 4] = b"Bud1";
+
+/// Record code of the original folder
+const PUT_BACK_LOCATION: &[u8; 4] = b"ptbL";
+
+/// Record code of the original name
+const PUT_BACK_NAME: &[u8; 4] = b"ptbN";
+
+/// Depth at which a B-tree is considered corrupt
+const MAX_DEPTH: usize = 32;
+
+/// A position in the file
+struct Reader<'a> {
+    /// Contents of the file
+    data: &'a [u8],
+    /// Offset of the next byte to read
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads a number of bytes
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position.checked_add(count)?)?;
+        self.position += count;
+        Some(bytes)
+    }
+
+    /// Reads a 32-bit number
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    /// Reads a four character code
+    fn code(&mut self) -> Option<[u8; 4]> {
+        self.bytes(4)?.try_into().ok()
+    }
+
+    /// Reads a string of UTF-16 code units preceded by their number
+    fn string(&mut self) -> Option<String> {
+        let length = self.u32()? as usize;
+        let units: Vec<u16> = self
+            .bytes(length.checked_mul(2)?)?
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        Some(String::from_utf16_lossy(&units))
+    }
+}
+
+/// Reads the Put Back information of the items in a trash
+///
+/// # Arguments
+///
+/// * `trash` - Folder of the trash
+///
+/// # Returns
+///
+/// The Put Back information by name of the item in the trash; empty if the
+/// trash has no readable `.DS_Store` file.
+pub fn put_back(trash: &Path) -> HashMap<String, PutBack> {
+    let mut items: HashMap<String, PutBack> = HashMap::new();
+    let Ok(data) = fs::read(trash.join(".DS_Store")) else {
+        return items;
+    };
+    let mut records = Vec::new();
+    // A damaged file still yields the records before the damage
+    let _ = read_records(&data, &mut records);
+    for (name, code, value) in records {
+        if &code == PUT_BACK_LOCATION {
+            items.entry(name).or_default().folder = value;
+        } else if &code == PUT_BACK_NAME {
+            items.entry(name).or_default().name = Some(value);
+        }
+    }
+    items.retain(|_, put_back| !put_back.folder.is_empty());
+    items
+}
+
+/// Reads the string records of a `.DS_Store` file
+///
+/// Stops at the first record of an unknown type, as its length is unknown.
+///
+/// # Returns
+///
+/// `None` if the file is not a `.DS_Store` file or damaged.
+fn read_records(data: &[u8], records: &mut Vec<(String, [u8; 4], String)>) -> Option<()> {
+    if data.get(4..8)? != MAGIC {
+        return None;
+    }
+    // Offsets in the file do not count the leading version number
+    let mut header = Reader { data, position: 8 };
+    let root = header.u32()? as usize + 4;
+
+    let mut allocator = Reader { data, position: root };
+    let block_count = allocator.u32()? as usize;
+    allocator.u32()?;
+    let addresses: Vec<u32> = (0..block_count).map(|_| allocator.u32()).collect::<Option<_>>()?;
+    // The block addresses are padded to a multiple of 256
+    allocator.bytes((block_count.div_ceil(256) * 256 - block_count) * 4)?;
+
+    let directories = allocator.u32()?;
+    let mut database = None;
+    for _ in 0..directories {
+        let length = allocator.bytes(1)?[0] as usize;
+        let name = allocator.bytes(length)?;
+        let block = allocator.u32()?;
+        if name == b"DSDB" {
+            database = Some(block);
+        }
+    }
+
+    let block = |id: u32| -> Option<Reader> {
+        let address = *addresses.get(id as usize)?;
+        Some(Reader {
+            data,
+            position: (address & !0x1f) as usize + 4,
+        })
+    };
+    let root_node = block(database?)?.u32()?;
+    read_node(&block, root_node, 0, records)
+}
+
+/// Reads the records of a B-tree node and its children in order
+fn read_node<'a>(
+    block: &impl Fn(u32) -> Option<Reader<'a>>,
+    node: u32,
+    depth: usize,
+    records: &mut Vec<(String, [u8; 4], String)>,
+) -> Option<()> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let mut reader = block(node)?;
+    // Leaves have no rightmost child
+    let rightmost = reader.u32()?;
+    let count = reader.u32()?;
+    for _ in 0..count {
+        if rightmost != 0 {
+            let child = reader.u32()?;
+            read_node(block, child, depth + 1, records)?;
+        }
+        let name = reader.string()?;
+        let code = reader.code()?;
+        match &reader.code()? {
+            b"ustr" => records.push((name, code, reader.string()?)),
+            b"bool" => {
+                reader.bytes(1)?;
+            }
+            b"long" | b"shor" | b"type" => {
+                reader.bytes(4)?;
+            }
+            b"comp" | b"dutc" => {
+                reader.bytes(8)?;
+            }
+            b"blob" => {
+                let length = reader.u32()? as usize;
+                reader.bytes(length)?;
+            }
+            _ => return None,
+        }
+    }
+    if rightmost != 0 {
+        read_node(block, rightmost, depth + 1, records)?;
+    }
+    Some(())
+}
@@ -0,0 +1,179 @@
+//! USB devices
+//!
+//! Lists the connected USB devices from the I/O Registry with vendor, ids,
+//! serial number, speed and the power allocated to them, indented below the
+//! hub they are connected to. The tree follows from the location ids: the top
+//! byte is the bus, and every following nibble is a port number on the path
+//! from the bus to the device.
+//!
+//! `--watch` compares the devices every interval and prints every device that
+//! was connected or disconnected with a timestamp, which shows flaky cables
+//! and hubs that drop their devices.
+//!
+//! # System Commands Used
+//!
+//! - None; the devices are read from the `IOUSBHostDevice` services of the I/O Registry
+
+#[cfg(target_os = "macos")]
+mod iokit;
+
+use crate::cli::{UsbArgs, UsbCommands, UsbListArgs};
+use crate::util::timestamp;
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use iokit::devices;
+
+/// Number of port nibbles of a location id below the bus byte
+const PORT_NIBBLES: u32 = 6;
+
+/// A connected USB device
+#[derive(Serialize)]
+pub struct UsbDevice {
+    /// Product name from the device descriptor
+    pub name: String,
+    /// Vendor name from the device descriptor
+    pub vendor: Option<String>,
+    /// USB vendor id
+    pub vendor_id: u16,
+    /// USB product id
+    pub product_id: u16,
+    /// Serial number, if the device reports one
+    pub serial: Option<String>,
+    /// Negotiated signaling rate, e.g. `480 Mb/s`
+    pub speed: Option<&'static str>,
+    /// Power in mA allocated to the device by its port
+    pub power_ma: Option<u32>,
+    /// Location of the device: bus and path of ports
+    pub location_id: u32,
+}
+
+impl UsbDevice {
+    /// Returns the number of hubs and ports between the bus and the device, 1 for a port of the bus
+    fn depth(&self) -> usize {
+        (0..PORT_NIBBLES)
+            .take_while(|nibble| (self.location_id >> (20 - 4 * nibble)) & 0xf != 0)
+            .count()
+    }
+
+    /// Returns the bus of the device
+    fn bus(&self) -> u32 {
+        self.location_id >> 24
+    }
+
+    /// Returns true if both are the same device at the same location
+    fn same(&self, other: &UsbDevice) -> bool {
+        self.location_id == other.location_id
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+            && self.serial == other.serial
+    }
+
+    /// Formats ids, vendor, speed, power and serial number
+    fn details(&self) -> String {
+        let mut details = vec![format!("{:04x}:{:04x}", self.vendor_id, self.product_id)];
+        details.extend(self.vendor.clone());
+        details.extend(self.speed.map(str::to_string));
+        details.extend(self.power_ma.map(|power| format!("{power} mA")));
+        details.extend(self.serial.as_ref().map(|serial| format!("serial {serial}")));
+        details.join(", ")
+    }
+}
+
+/// Performs USB operations
+///
+/// # Arguments
+///
+/// * `args` - USB arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the USB devices cannot be read.
+pub fn perform(args: UsbArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        UsbCommands::List(args) if args.watch => watch(&args),
+        UsbCommands::List(args) => list(args.json),
+    }
+}
+
+/// Prints the devices as a tree of buses, hubs and devices
+///
+/// # Errors
+///
+/// Returns an error if the USB devices cannot be read.
+fn list(json: bool) -> Result<(), Box<dyn Error>> {
+    let mut devices = devices()?;
+    // Sorting by location puts every device right below its hub
+    devices.sort_by_key(|device| device.location_id);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&devices)?);
+        return Ok(());
+    }
+    if devices.is_empty() {
+        println!("No USB device connected");
+        return Ok(());
+    }
+    let mut bus = None;
+    for device in &devices {
+        if bus != Some(device.bus()) {
+            bus = Some(device.bus());
+            println!("{}", format!("Bus {}", device.bus()).bold());
+        }
+        let indent = "  ".repeat(device.depth().max(1));
+        println!("{indent}{} {}", device.name, device.details().dimmed());
+    }
+    Ok(())
+}
+
+/// Prints every device that is connected or disconnected until interrupted
+///
+/// # Errors
+///
+/// Returns an error if the USB devices cannot be read at the start.
+fn watch(args: &UsbListArgs) -> Result<(), Box<dyn Error>> {
+    let mut known = devices()?;
+    println!(
+        "Watching {} USB devices every {} s (Ctrl-C to stop)",
+        known.len(),
+        args.interval
+    );
+    loop {
+        thread::sleep(Duration::from_secs(args.interval));
+        let current = match devices() {
+            Ok(devices) => devices,
+            Err(e) => {
+                eprintln!("{}", format!("Failed to read the USB devices: {e}").red());
+                continue;
+            }
+        };
+        for device in known
+            .iter()
+            .filter(|device| !current.iter().any(|other| other.same(device)))
+        {
+            event("Disconnected".red(), device);
+        }
+        for device in current
+            .iter()
+            .filter(|device| !known.iter().any(|other| other.same(device)))
+        {
+            event("Connected".green(), device);
+        }
+        known = current;
+    }
+}
+
+/// Prints a connection event with a timestamp
+fn event(label: ColoredString, device: &UsbDevice) {
+    println!("{} {label} {} {}", timestamp(), device.name, device.details().dimmed());
+}
+
+/// Reads the connected USB devices; requires IOKit
+#[cfg(not(target_os = "macos"))]
+fn devices() -> Result<Vec<UsbDevice>, Box<dyn Error>> {
+    Err("USB devices can only be listed on macOS".into())
+}
@@ -0,0 +1,60 @@
+//! USB devices from the I/O Registry
+//!
+//! Every connected device, hubs included, is an `IOUSBHostDevice` service
+//! whose properties hold the descriptors, the negotiated speed and the power
+//! the port allocated to it.
+
+use super::UsbDevice;
+use crate::iokit::{Properties, matching_services};
+use std::error::Error;
+
+/// Signaling rates of the `Device Speed` values (`kUSBHostPortSpeed...`)
+const SPEEDS: [&str; 6] = ["1.5 Mb/s", "12 Mb/s", "480 Mb/s", "5 Gb/s", "10 Gb/s", "20 Gb/s"];
+
+/// Property keys of the product name, newest first
+const PRODUCT_KEYS: [&str; 2] = ["kUSBProductString", "USB Product Name"];
+
+/// Property keys of the vendor name, newest first
+const VENDOR_KEYS: [&str; 2] = ["kUSBVendorString", "USB Vendor Name"];
+
+/// Property keys of the serial number, newest first
+const SERIAL_KEYS: [&str; 2] = ["kUSBSerialNumberString", "USB Serial Number"];
+
+/// Reads the connected USB devices
+///
+/// # Errors
+///
+/// Returns an error if the I/O Registry cannot be searched.
+pub fn devices() -> Result<Vec<UsbDevice>, Box<dyn Error>> {
+    Ok(matching_services(c"IOUSBHostDevice")?
+        .iter()
+        .filter_map(|service| service.properties())
+        .map(|properties| device(&properties))
+        .collect())
+}
+
+/// Builds a device from the properties of its service
+fn device(properties: &Properties) -> UsbDevice {
+    let id = |key| properties.number(key).unwrap_or_default() as u16;
+    // The first non-empty string property of several keys
+    let string = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|key| properties.string(key))
+            .map(|value| value.trim().to_string())
+            .find(|value| !value.is_empty())
+    };
+    UsbDevice {
+        name: string(&PRODUCT_KEYS).unwrap_or_else(|| "Unknown device".to_string()),
+        vendor: string(&VENDOR_KEYS),
+        vendor_id: id("idVendor"),
+        product_id: id("idProduct"),
+        serial: string(&SERIAL_KEYS),
+        speed: properties
+            .number("Device Speed")
+            .and_then(|speed| SPEEDS.get(usize::try_from(speed).ok()?).copied()),
+        power_ma: properties
+            .number("UsbPowerSinkAllocation")
+            .and_then(|power| u32::try_from(power).ok()),
+        location_id: properties.number("locationID").unwrap_or_default() as u32,
+    }
+}
@@ -0,0 +1,177 @@
+//! Desktop pictures
+//!
+//! Shows and sets the desktop picture of each display through `NSWorkspace`,
+//! which keeps the scaling chosen in System Settings. Given a folder, `set`
+//! takes the image after the current one, or a random one with `--random`;
+//! with `--all-displays`, every display gets a different image of the folder.
+//!
+//! macOS keeps a desktop picture per display and space, and `NSWorkspace`
+//! changes the one of the current space. Other spaces keep their picture and
+//! can be changed by running `set` again while they are active.
+//!
+//! # System Commands Used
+//!
+//! - The displays and their pictures are read and set with `NSScreen` and
+//!   `NSWorkspace` of AppKit
+
+#[cfg(target_os = "macos")]
+mod appkit;
+
+use crate::cli::{WallpaperArgs, WallpaperCommands, WallpaperSetArgs};
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(target_os = "macos")]
+use appkit::{screens, set_picture};
+
+/// Extensions of the images macOS can use as desktop picture
+const IMAGE_EXTENSIONS: [&str; 9] = ["heic", "jpg", "jpeg", "png", "tif", "tiff", "gif", "bmp", "webp"];
+
+/// A display with its desktop picture
+struct Screen {
+    /// Name of the display, e.g. `Built-in Retina Display`
+    name: String,
+    /// Desktop picture of the current space
+    picture: Option<PathBuf>,
+}
+
+/// Performs wallpaper operations
+///
+/// # Arguments
+///
+/// * `args` - Wallpaper arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the displays cannot be read, the images cannot be found
+/// or a desktop picture cannot be set.
+pub fn perform(args: WallpaperArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        None | Some(WallpaperCommands::Get) => print_pictures(),
+        Some(WallpaperCommands::Set(args)) => set(args),
+    }
+}
+
+/// Prints the desktop picture of each display
+///
+/// # Errors
+///
+/// Returns an error if the displays cannot be read.
+fn print_pictures() -> Result<(), Box<dyn Error>> {
+    for (index, screen) in screens()?.iter().enumerate() {
+        let picture = match &screen.picture {
+            Some(path) => path.display().to_string().normal(),
+            None => "none".dimmed(),
+        };
+        println!("{} {}", format!("Display {}:", index + 1).bold(), screen.name);
+        println!("  {picture}");
+    }
+    Ok(())
+}
+
+/// Sets the desktop picture of the chosen displays
+///
+/// # Errors
+///
+/// Returns an error if the display does not exist, the folder holds no image,
+/// or a picture cannot be set.
+fn set(args: WallpaperSetArgs) -> Result<(), Box<dyn Error>> {
+    let screens = screens()?;
+    let targets: Vec<usize> = if args.all_displays {
+        (0..screens.len()).collect()
+    } else {
+        let index = args.display.unwrap_or(1) as usize - 1;
+        if index >= screens.len() {
+            return Err(format!("There is no display {}, see `rem wallpaper get`", index + 1).into());
+        }
+        vec![index]
+    };
+
+    let images = if args.path.is_dir() {
+        images_in(&args.path)?
+    } else if args.path.is_file() {
+        vec![fs::canonicalize(&args.path)?]
+    } else {
+        return Err(format!("{} does not exist", args.path.display()).into());
+    };
+
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    let start = if args.random {
+        shuffle(&mut order);
+        0
+    } else {
+        // Continue after the picture of the first display to rotate through the folder
+        screens[targets[0]]
+            .picture
+            .as_ref()
+            .and_then(|current| images.iter().position(|image| image == current))
+            .map_or(0, |position| position + 1)
+    };
+
+    for (number, &index) in targets.iter().enumerate() {
+        let image = &images[order[(start + number) % order.len()]];
+        print!("Set display {} to {}", index + 1, image.display());
+        io::stdout().flush()?;
+        if let Err(e) = set_picture(index, image) {
+            println!();
+            return Err(e);
+        }
+        println!("{}", " OK".green());
+    }
+    Ok(())
+}
+
+/// Returns the images in a folder, sorted by name
+///
+/// # Errors
+///
+/// Returns an error if the folder cannot be read or holds no image.
+fn images_in(folder: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut images: Vec<PathBuf> = fs::read_dir(folder)
+        .map_err(|e| format!("Failed to read {}: {e}", folder.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|extension| {
+                    IMAGE_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+                })
+        })
+        .collect();
+    if images.is_empty() {
+        return Err(format!("{} holds no image", folder.display()).into());
+    }
+    images.sort();
+    images.iter().map(|image| Ok(fs::canonicalize(image)?)).collect()
+}
+
+/// Shuffles indices with a xorshift generator seeded from the clock
+fn shuffle(order: &mut [usize]) {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    let mut state = (nanos ^ (u64::from(process::id()) << 32)) | 1;
+    for index in (1..order.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        order.swap(index, (state % (index as u64 + 1)) as usize);
+    }
+}
+
+/// Reads the displays and their pictures; requires AppKit
+#[cfg(not(target_os = "macos"))]
+fn screens() -> Result<Vec<Screen>, Box<dyn Error>> {
+    Err("Desktop pictures can only be changed on macOS".into())
+}
+
+/// Sets the picture of a display; requires AppKit
+#[cfg(not(target_os = "macos"))]
+fn set_picture(_screen: usize, _image: &Path) -> Result<(), Box<dyn Error>> {
+    Err("Desktop pictures can only be changed on macOS".into())
+}
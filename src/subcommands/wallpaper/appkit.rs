@@ -0,0 +1,101 @@
+//! AppKit access through the Objective-C runtime
+//!
+//! `NSScreen` lists the displays, with the main display first, and
+//! `NSWorkspace` reads and sets their desktop pictures. The options of the
+//! current picture, i.e. its scaling and fill color, are passed on when
+//! setting a new one.
+
+use super::Screen;
+use crate::objc::{
+    AutoreleasePool, Id, Sel, class, error_description, msg_send, ns_string, objects, selector, send_id, string,
+};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+#[link(name = "AppKit", kind = "framework")]
+unsafe extern "C" {}
+
+/// Reads the displays and the desktop pictures of their current spaces
+///
+/// # Errors
+///
+/// Returns an error if AppKit reports no display, e.g. when run without a
+/// login session.
+pub fn screens() -> Result<Vec<Screen>, Box<dyn Error>> {
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let workspace = shared_workspace();
+        let screens = screen_objects();
+        if screens.is_empty() {
+            return Err("No display found, desktop pictures require a login session".into());
+        }
+        Ok(screens
+            .into_iter()
+            .map(|screen| {
+                let image_url: unsafe extern "C" fn(Id, Sel, Id) -> Id = msg_send();
+                let url = image_url(workspace, selector(c"desktopImageURLForScreen:"), screen);
+                Screen {
+                    name: string(send_id(screen, c"localizedName")).unwrap_or_else(|| "Display".to_string()),
+                    picture: string(send_id(url, c"path")).map(PathBuf::from),
+                }
+            })
+            .collect())
+    }
+}
+
+/// Sets the desktop picture of the current space of a display
+///
+/// # Arguments
+///
+/// * `index` - Index of the display in the list of [`screens`]
+/// * `image` - Absolute path of the image
+///
+/// # Errors
+///
+/// Returns an error if the display is gone or AppKit rejects the image.
+pub fn set_picture(index: usize, image: &Path) -> Result<(), Box<dyn Error>> {
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let workspace = shared_workspace();
+        let screen = *screen_objects()
+            .get(index)
+            .ok_or_else(|| format!("Display {} is gone", index + 1))?;
+
+        let file_url: unsafe extern "C" fn(Id, Sel, Id) -> Id = msg_send();
+        let url = file_url(
+            class(c"NSURL"),
+            selector(c"fileURLWithPath:"),
+            ns_string(&image.to_string_lossy())?,
+        );
+        let image_options: unsafe extern "C" fn(Id, Sel, Id) -> Id = msg_send();
+        let options = image_options(workspace, selector(c"desktopImageOptionsForScreen:"), screen);
+
+        let mut error: Id = ptr::null_mut();
+        let set_image: unsafe extern "C" fn(Id, Sel, Id, Id, Id, *mut Id) -> i8 = msg_send();
+        if set_image(
+            workspace,
+            selector(c"setDesktopImageURL:forScreen:options:error:"),
+            url,
+            screen,
+            options,
+            &mut error,
+        ) == 0
+        {
+            return Err(error_description(error).into());
+        }
+        Ok(())
+    }
+}
+
+/// Returns `[NSWorkspace sharedWorkspace]`
+unsafe fn shared_workspace() -> Id {
+    unsafe { send_id(class(c"NSWorkspace"), c"sharedWorkspace") }
+}
+
+/// Returns the elements of `[NSScreen screens]`, the main display first
+unsafe fn screen_objects() -> Vec<Id> {
+    unsafe { objects(send_id(class(c"NSScreen"), c"screens")) }
+}
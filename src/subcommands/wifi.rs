@@ -0,0 +1,848 @@
+//! Wi-Fi subcommand
+//!
+//! Turns Wi-Fi on and off, and scans for, joins and leaves Wi-Fi networks using the
+//! CoreWLAN framework. If
+//! CoreWLAN is not available for a scan, or does not reveal network names because
+//! the terminal lacks Location Services access, the legacy `airport -s` command is
+//! used instead.
+//!
+//! # System Commands Used
+//!
+//! - `airport -s` - Scan for Wi-Fi networks (fallback)
+//! - `networksetup -listallhardwareports` - Find the Wi-Fi device
+//! - `networksetup -getairportpower` / `-setairportpower` - Query and switch the Wi-Fi power
+//! - `networksetup -getairportnetwork` - Get the current network (fallback)
+//! - `security find-generic-password` - Read stored Wi-Fi passwords from the keychain
+//! - `networksetup -listpreferredwirelessnetworks` - List known networks
+//! - `networksetup -removepreferredwirelessnetwork` - Forget a known network
+//! - `networksetup -addpreferredwirelessnetworkatindex` - Add a known network at a position
+
+#[cfg(target_os = "macos")]
+mod corewlan;
+
+#[cfg(target_os = "macos")]
+use corewlan::{associate, disassociate, known_networks, link_status};
+
+use crate::cli::{KnownArgs, KnownCommands, ScanOrder, WifiArgs, WifiCommands, WifiScanArgs};
+use crate::util::{confirm, format_count, print_dry_run};
+use colored::Colorize;
+use regex::Regex;
+use serde::Serialize;
+use std::error::Error;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Error of Wi-Fi operations on systems without CoreWLAN
+#[cfg(not(target_os = "macos"))]
+const COREWLAN_UNAVAILABLE: &str = "CoreWLAN is only available on macOS";
+
+/// Width of the signal bar of `rem wifi monitor` in characters
+const BAR_WIDTH: usize = 30;
+
+/// Keychain entry description of Wi-Fi passwords
+const KEYCHAIN_DESCRIPTION: &str = "AirPort network password";
+
+/// Keychain holding the Wi-Fi passwords shared by all users
+const SYSTEM_KEYCHAIN: &str = "/Library/Keychains/System.keychain";
+
+/// Path of the legacy `airport` command-line tool
+const AIRPORT_PATH: &str = "/System/Library/PrivateFrameworks/Apple80211.framework/Versions/Current/Resources/airport";
+
+/// A Wi-Fi network found by a scan
+#[derive(Serialize)]
+pub struct Network {
+    /// Network name; `None` if hidden or not revealed by the system
+    pub ssid: Option<String>,
+    /// MAC address of the access point, if revealed by the system
+    pub bssid: Option<String>,
+    /// Received signal strength in dBm
+    pub rssi: i64,
+    /// Noise level in dBm, if known
+    pub noise: Option<i64>,
+    /// Channel number
+    pub channel: u32,
+    /// Frequency band, e.g. `5 GHz`
+    pub band: Option<&'static str>,
+    /// Channel width in MHz, if known
+    pub channel_width: Option<u32>,
+    /// Strongest supported security type, e.g. `WPA2 Personal`
+    pub security: String,
+}
+
+/// State of the current Wi-Fi association
+pub struct LinkStatus {
+    /// Network name; `None` if not revealed by the system
+    pub ssid: Option<String>,
+    /// MAC address of the access point, if revealed by the system
+    pub bssid: Option<String>,
+    /// Received signal strength in dBm
+    pub rssi: i64,
+    /// Noise level in dBm, if known
+    pub noise: Option<i64>,
+    /// Current transmit rate in Mbit/s
+    pub tx_rate: f64,
+    /// Channel number
+    pub channel: u32,
+    /// Frequency band, e.g. `5 GHz`
+    pub band: Option<&'static str>,
+    /// Channel width in MHz, if known
+    pub channel_width: Option<u32>,
+}
+
+/// A known (preferred) Wi-Fi network
+pub struct KnownNetwork {
+    /// Network name
+    pub ssid: String,
+    /// Security type, e.g. `WPA2 Personal`
+    pub security: &'static str,
+}
+
+/// Performs Wi-Fi operations based on the provided arguments
+///
+/// # Arguments
+///
+/// * `args` - Wi-Fi arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the Wi-Fi operation fails.
+pub fn perform(args: WifiArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        WifiCommands::Scan(args) => scan(args),
+        WifiCommands::Join {
+            ssid,
+            password,
+            keychain,
+        } => join(&ssid, password, keychain),
+        WifiCommands::On => set_power(true),
+        WifiCommands::Off => set_power(false),
+        WifiCommands::Status => status(),
+        WifiCommands::Disconnect => disconnect(),
+        WifiCommands::Known(args) => known(args),
+        WifiCommands::Monitor { interval, count } => monitor(Duration::from_millis(interval), count),
+    }
+}
+
+/// Joins a Wi-Fi network
+///
+/// Without a password, the password stored in the keychain is used; open networks
+/// are joined without password if none is stored.
+///
+/// # Arguments
+///
+/// * `ssid` - Name of the network
+/// * `password` - Password given on the command line
+/// * `keychain` - Fail if no password is stored in the keychain
+///
+/// # Errors
+///
+/// Returns an error if no password is found although required, or the network
+/// cannot be joined.
+fn join(ssid: &str, password: Option<String>, keychain: bool) -> Result<(), Box<dyn Error>> {
+    let (password, source) = match password {
+        Some(password) => (Some(password), None),
+        None => match keychain_password(ssid) {
+            Some(password) => (Some(password), Some("keychain password")),
+            None if keychain => return Err(format!("No password for '{ssid}' stored in the keychain").into()),
+            None => (None, Some("no password")),
+        },
+    };
+
+    match source {
+        Some(source) => println!("Join Wi-Fi network {} ({source})", ssid.bold()),
+        None => println!("Join Wi-Fi network {}", ssid.bold()),
+    }
+    associate(ssid, password.as_deref())?;
+    println!("{}", "Joined".green());
+
+    Ok(())
+}
+
+/// Turns the Wi-Fi device on or off
+///
+/// # Errors
+///
+/// Returns an error if there is no Wi-Fi device or `networksetup` fails.
+fn set_power(on: bool) -> Result<(), Box<dyn Error>> {
+    let device = wifi_device()?;
+    let state = if on { "on" } else { "off" };
+
+    let output = Command::new("networksetup")
+        .args(["-setairportpower", &device, state])
+        .output()?;
+    // networksetup reports some errors on stdout with exit code 0
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || stdout.contains("Error") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to turn Wi-Fi {state}: {} {}", stdout.trim(), stderr.trim()).into());
+    }
+    println!(
+        "Wi-Fi ({device}) turned {}",
+        if on { state.green() } else { state.yellow() }
+    );
+
+    Ok(())
+}
+
+/// Prints whether Wi-Fi is on and, if so, the current association
+///
+/// # Errors
+///
+/// Returns an error if there is no Wi-Fi device or its power state cannot be read.
+fn status() -> Result<(), Box<dyn Error>> {
+    let device = wifi_device()?;
+    if !is_powered(&device)? {
+        println!("Wi-Fi ({device}): {}", "off".yellow());
+        return Ok(());
+    }
+    println!("Wi-Fi ({device}): {}", "on".green());
+
+    match link_status() {
+        Ok(Some(status)) => {
+            println!(
+                "Connected to {} via {}",
+                status.ssid.as_deref().unwrap_or("(hidden network)").bold(),
+                status.bssid.as_deref().unwrap_or("unknown access point")
+            );
+            println!(
+                "Channel {}{}, RSSI {} dBm, transmit rate {:.0} Mbps",
+                status.channel,
+                status.band.map(|band| format!(" ({band})")).unwrap_or_default(),
+                status.rssi,
+                status.tx_rate
+            );
+        }
+        Ok(None) => println!("{}", "Not connected to a network".yellow()),
+        Err(_) => match current_network(&device) {
+            Some(ssid) => println!("Connected to {}", ssid.bold()),
+            None => println!("{}", "Not connected to a network".yellow()),
+        },
+    }
+
+    Ok(())
+}
+
+/// Returns true if the Wi-Fi device is powered on
+///
+/// # Errors
+///
+/// Returns an error if `networksetup` fails or its output is not understood.
+fn is_powered(device: &str) -> Result<bool, Box<dyn Error>> {
+    let output = Command::new("networksetup")
+        .args(["-getairportpower", device])
+        .output()?;
+    // Output: "Wi-Fi Power (en0): On"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.trim().rsplit_once(": ").map(|(_, state)| state) {
+        Some("On") => Ok(true),
+        Some("Off") => Ok(false),
+        _ => Err(format!("Failed to read the Wi-Fi power state: {}", stdout.trim()).into()),
+    }
+}
+
+/// Returns the SSID of the current network as reported by `networksetup`
+///
+/// # Arguments
+///
+/// * `device` - Wi-Fi device name, see [`wifi_device`]
+pub fn current_network(device: &str) -> Option<String> {
+    let output = Command::new("networksetup")
+        .args(["-getairportnetwork", device])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .strip_prefix("Current Wi-Fi Network: ")
+        .map(str::to_string)
+}
+
+/// Disconnects from the current Wi-Fi network
+///
+/// # Errors
+///
+/// Returns an error if there is no Wi-Fi interface.
+fn disconnect() -> Result<(), Box<dyn Error>> {
+    match disassociate()? {
+        Some(ssid) => println!("Disconnected from {}", ssid.bold()),
+        None => println!("Disconnected"),
+    }
+    Ok(())
+}
+
+/// Performs known network operations
+///
+/// # Errors
+///
+/// Returns an error if the known networks cannot be read or changed.
+fn known(args: KnownArgs) -> Result<(), Box<dyn Error>> {
+    let device = wifi_device()?;
+
+    match args.command {
+        None if args.forget_open_networks => forget_open_networks(&device, args.yes, args.dry_run),
+        None | Some(KnownCommands::List) => list_known(&device),
+        Some(KnownCommands::Forget { ssid }) => {
+            require_known(&device, &ssid)?;
+            forget(&device, &ssid)
+        }
+        Some(KnownCommands::Priority { ssid, position }) => set_priority(&device, &ssid, position as usize),
+    }
+}
+
+/// Prints the known networks in order of preference
+///
+/// # Errors
+///
+/// Returns an error if the known networks cannot be read.
+fn list_known(device: &str) -> Result<(), Box<dyn Error>> {
+    let names = preferred_networks(device)?;
+    if names.is_empty() {
+        println!("No known Wi-Fi networks");
+        return Ok(());
+    }
+
+    let security = known_security();
+    println!("{:>3}  {:<32} SECURITY", "#", "SSID");
+    for (index, name) in names.iter().enumerate() {
+        let security = match security_of(&security, name) {
+            Some("Open") => "Open".yellow(),
+            Some(security) => security.normal(),
+            None => "-".normal(),
+        };
+        println!("{:>3}  {name:<32} {security}", index + 1);
+    }
+
+    Ok(())
+}
+
+/// Forgets all known networks without encryption after confirmation
+///
+/// # Arguments
+///
+/// * `device` - Wi-Fi device
+/// * `yes` - Forget the networks without asking
+/// * `dry_run` - Print the commands instead of running them
+///
+/// # Errors
+///
+/// Returns an error if the security types are unknown, the confirmation is
+/// needed without a terminal, or a network cannot be removed.
+fn forget_open_networks(device: &str, yes: bool, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let security = known_security();
+    if security.is_empty() {
+        return Err("Security types of the known networks are not available".into());
+    }
+
+    let open: Vec<String> = preferred_networks(device)?
+        .into_iter()
+        .filter(|name| security_of(&security, name) == Some("Open"))
+        .collect();
+    if open.is_empty() {
+        println!("No open networks among the known Wi-Fi networks");
+        return Ok(());
+    }
+
+    println!("Open networks among the known Wi-Fi networks:");
+    for name in &open {
+        println!("  {name}");
+    }
+    if dry_run {
+        for name in &open {
+            print_dry_run(&["networksetup", "-removepreferredwirelessnetwork", device, name]);
+        }
+        return Ok(());
+    }
+    if !yes && !confirm(&format!("Forget {}?", format_count(open.len(), "network")))? {
+        println!("Nothing forgotten");
+        return Ok(());
+    }
+
+    for name in &open {
+        forget(device, name)?;
+    }
+    println!("Forgot {}", format_count(open.len(), "open network"));
+
+    Ok(())
+}
+
+/// Removes a network from the known networks
+///
+/// The password stored in the keychain is kept.
+///
+/// # Errors
+///
+/// Returns an error if `networksetup` fails.
+fn forget(device: &str, ssid: &str) -> Result<(), Box<dyn Error>> {
+    print!("Forget {}", ssid.bold());
+    run_networksetup(&["-removepreferredwirelessnetwork", device, ssid])?;
+    println!("{}", " OK".green());
+    Ok(())
+}
+
+/// Moves a known network to a position in the list of preferred networks
+///
+/// The network is removed and added again at the new position with its security
+/// type. Its password stays in the keychain, where macOS finds it when joining,
+/// so it is never passed to `networksetup` where other processes could see it.
+///
+/// # Errors
+///
+/// Returns an error if the network is not known, its security type cannot be
+/// determined, or `networksetup` fails.
+fn set_priority(device: &str, ssid: &str, position: usize) -> Result<(), Box<dyn Error>> {
+    let names = preferred_networks(device)?;
+    let current = names
+        .iter()
+        .position(|name| name == ssid)
+        .ok_or_else(|| format!("'{ssid}' is not a known Wi-Fi network"))?;
+    let position = position.min(names.len());
+    if current + 1 == position {
+        println!("{} is already at position {position}", ssid.bold());
+        return Ok(());
+    }
+
+    let security = security_of(&known_security(), ssid)
+        .and_then(networksetup_security)
+        .ok_or_else(|| format!("Security type of '{ssid}' is unknown or not supported by networksetup"))?;
+    let add = |index: usize| {
+        run_networksetup(&[
+            "-addpreferredwirelessnetworkatindex",
+            device,
+            ssid,
+            &index.to_string(),
+            security,
+        ])
+    };
+
+    print!("Move {} to position {position}", ssid.bold());
+    run_networksetup(&["-removepreferredwirelessnetwork", device, ssid])?;
+    if let Err(e) = add(position - 1) {
+        // Restore the previous position rather than losing the network
+        add(current)?;
+        return Err(e);
+    }
+    println!("{}", " OK".green());
+
+    Ok(())
+}
+
+/// Returns an error if a network is not in the known networks
+fn require_known(device: &str, ssid: &str) -> Result<(), Box<dyn Error>> {
+    if preferred_networks(device)?.iter().any(|name| name == ssid) {
+        Ok(())
+    } else {
+        Err(format!("'{ssid}' is not a known Wi-Fi network").into())
+    }
+}
+
+/// Returns the names of the known networks in order of preference
+///
+/// # Errors
+///
+/// Returns an error if `networksetup` fails.
+fn preferred_networks(device: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let output = Command::new("networksetup")
+        .args(["-listpreferredwirelessnetworks", device])
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list known Wi-Fi networks: {}", stderr.trim()).into());
+    }
+
+    // The first line is a header: "Preferred networks on en0:"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Returns the known networks with their security types, empty if not available
+fn known_security() -> Vec<KnownNetwork> {
+    known_networks().unwrap_or_default()
+}
+
+/// Returns the security type of a known network
+fn security_of(known: &[KnownNetwork], ssid: &str) -> Option<&'static str> {
+    known
+        .iter()
+        .find(|network| network.ssid == ssid)
+        .map(|network| network.security)
+}
+
+/// Maps a security type to the name expected by `networksetup`
+fn networksetup_security(security: &str) -> Option<&'static str> {
+    match security {
+        "Open" => Some("OPEN"),
+        "WEP" => Some("WEP"),
+        "WPA Personal" => Some("WPA"),
+        "WPA/WPA2 Personal" | "WPA2 Personal" => Some("WPA2"),
+        "WPA2/WPA3 Personal" | "WPA3 Personal" => Some("WPA3"),
+        "WPA Enterprise" => Some("WPAE"),
+        "WPA/WPA2 Enterprise" | "WPA2 Enterprise" => Some("WPA2E"),
+        "WPA3 Enterprise" => Some("WPA3E"),
+        _ => None,
+    }
+}
+
+/// Runs `networksetup` with sudo, as changing known networks requires administrator rights
+///
+/// # Errors
+///
+/// Returns an error containing the output of `networksetup` if it fails.
+fn run_networksetup(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("sudo").arg("networksetup").args(args).output()?;
+    // networksetup reports some errors on stdout with exit code 0
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || stdout.contains("Error") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("networksetup failed: {} {}", stdout.trim(), stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Samples the current association and charts the signal strength
+///
+/// Each sample is printed as one line with a bar of the RSSI between -90 dBm and
+/// -30 dBm. Changes of the network, access point or channel are reported in between.
+///
+/// # Arguments
+///
+/// * `interval` - Time between samples
+/// * `count` - Number of samples after which a summary is printed; unlimited if `None`
+///
+/// # Errors
+///
+/// Returns an error if there is no Wi-Fi interface.
+fn monitor(interval: Duration, count: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let mut last: Option<(Option<String>, Option<String>, u32)> = None;
+    let mut taken: u64 = 0;
+    let mut samples: Vec<i64> = Vec::new();
+
+    println!(
+        "{:>7}  {:>8}  {:<BAR_WIDTH$}  {:>6}  {:>6}  {:>9}",
+        "TIME", "RSSI", "SIGNAL", "NOISE", "SNR", "TX RATE"
+    );
+    while count.is_none_or(|count| taken < count) {
+        if taken > 0 {
+            thread::sleep(interval);
+        }
+        taken += 1;
+        let elapsed = format!("{:.1}s", start.elapsed().as_secs_f64());
+
+        let Some(status) = link_status()? else {
+            if last.take().is_some() || taken == 1 {
+                println!("{elapsed:>7}  {}", "not connected to a Wi-Fi network".yellow());
+            }
+            continue;
+        };
+
+        let current = (status.ssid.clone(), status.bssid.clone(), status.channel);
+        if last.as_ref() != Some(&current) {
+            println!(
+                "{elapsed:>7}  {} {} via {} on channel {}{}",
+                "▶".cyan(),
+                status.ssid.as_deref().unwrap_or("(hidden network)").bold(),
+                status.bssid.as_deref().unwrap_or("unknown access point"),
+                status.channel,
+                status
+                    .band
+                    .map(|band| match status.channel_width {
+                        Some(width) => format!(" ({band}, {width} MHz)"),
+                        None => format!(" ({band})"),
+                    })
+                    .unwrap_or_default()
+            );
+            last = Some(current);
+        }
+
+        let noise = status.noise.map_or("-".to_string(), |noise| noise.to_string());
+        let snr = status
+            .noise
+            .map_or("-".to_string(), |noise| format!("{} dB", status.rssi - noise));
+        println!(
+            "{elapsed:>7}  {:>4} dBm  {}  {noise:>6}  {snr:>6}  {:>4.0} Mbps",
+            status.rssi,
+            signal_bar(status.rssi),
+            status.tx_rate
+        );
+        samples.push(status.rssi);
+    }
+
+    if let (Some(min), Some(max)) = (samples.iter().min(), samples.iter().max()) {
+        let average = samples.iter().sum::<i64>() as f64 / samples.len() as f64;
+        println!(
+            "RSSI min {min} dBm, avg {average:.1} dBm, max {max} dBm over {} connected sample(s)",
+            samples.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Renders an RSSI value as a colored bar
+fn signal_bar(rssi: i64) -> String {
+    let filled = ((rssi.clamp(-90, -30) + 90) as usize * BAR_WIDTH).div_ceil(60);
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+    match rssi {
+        -60.. => bar.green().to_string(),
+        -75..=-61 => bar.yellow().to_string(),
+        _ => bar.red().to_string(),
+    }
+}
+
+/// Returns the device name of the Wi-Fi hardware port, e.g. `en0`
+///
+/// # Errors
+///
+/// Returns an error if `networksetup` fails or there is no Wi-Fi hardware port.
+pub fn wifi_device() -> Result<String, Box<dyn Error>> {
+    let output = Command::new("networksetup").arg("-listallhardwareports").output()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    let mut lines = output.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == "Hardware Port: Wi-Fi" {
+            return lines
+                .next()
+                .and_then(|line| line.trim().strip_prefix("Device:"))
+                .map(|device| device.trim().to_string())
+                .ok_or_else(|| "Wi-Fi hardware port without device".into());
+        }
+    }
+    Err("No Wi-Fi hardware port found".into())
+}
+
+/// Reads the password of a Wi-Fi network from the keychain
+///
+/// Searches the keychains of the user first, then the system keychain, which may
+/// ask for an administrator password.
+fn keychain_password(ssid: &str) -> Option<String> {
+    let lookup = |keychain: Option<&str>| {
+        let mut command = Command::new("security");
+        command.args(["find-generic-password", "-D", KEYCHAIN_DESCRIPTION, "-a", ssid, "-w"]);
+        if let Some(keychain) = keychain {
+            command.arg(keychain);
+        }
+        let output = command.output().ok()?;
+        let password = String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches('\n')
+            .to_string();
+        (output.status.success() && !password.is_empty()).then_some(password)
+    };
+
+    lookup(None).or_else(|| lookup(Some(SYSTEM_KEYCHAIN)))
+}
+
+/// Joins a Wi-Fi network; requires CoreWLAN
+#[cfg(not(target_os = "macos"))]
+fn associate(_ssid: &str, _password: Option<&str>) -> Result<(), Box<dyn Error>> {
+    Err(COREWLAN_UNAVAILABLE.into())
+}
+
+/// Returns the known networks with their security types; requires CoreWLAN
+#[cfg(not(target_os = "macos"))]
+fn known_networks() -> Result<Vec<KnownNetwork>, Box<dyn Error>> {
+    Err(COREWLAN_UNAVAILABLE.into())
+}
+
+/// Returns the state of the current Wi-Fi association; requires CoreWLAN
+#[cfg(not(target_os = "macos"))]
+fn link_status() -> Result<Option<LinkStatus>, Box<dyn Error>> {
+    Err(COREWLAN_UNAVAILABLE.into())
+}
+
+/// Disconnects from the current Wi-Fi network; requires CoreWLAN
+#[cfg(not(target_os = "macos"))]
+fn disassociate() -> Result<Option<String>, Box<dyn Error>> {
+    Err(COREWLAN_UNAVAILABLE.into())
+}
+
+/// Scans for Wi-Fi networks and prints them
+///
+/// # Errors
+///
+/// Returns an error if neither CoreWLAN nor `airport` can scan.
+fn scan(args: WifiScanArgs) -> Result<(), Box<dyn Error>> {
+    let mut networks = scan_networks()?;
+    sort_networks(&mut networks, args.sort);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&networks)?);
+        return Ok(());
+    }
+
+    if networks.is_empty() {
+        println!("No Wi-Fi networks found");
+        return Ok(());
+    }
+
+    println!(
+        "{:<32} {:<17} {:>5} {:>7} {:<7} {:>6}  SECURITY",
+        "SSID", "BSSID", "RSSI", "CHANNEL", "BAND", "WIDTH"
+    );
+    for network in &networks {
+        let rssi = format!("{:>5}", network.rssi);
+        let rssi = match network.rssi {
+            -60.. => rssi.green(),
+            -75..=-61 => rssi.yellow(),
+            _ => rssi.red(),
+        };
+        println!(
+            "{:<32} {:<17} {rssi} {:>7} {:<7} {:>6}  {}",
+            network.ssid.as_deref().unwrap_or("(hidden)"),
+            network.bssid.as_deref().unwrap_or("-"),
+            network.channel,
+            network.band.unwrap_or("-"),
+            network
+                .channel_width
+                .map(|width| format!("{width} MHz"))
+                .unwrap_or_else(|| "-".to_string()),
+            network.security
+        );
+    }
+
+    if networks.iter().all(|network| network.ssid.is_none()) {
+        println!(
+            "{}",
+            "Network names are hidden: allow Location Services for your terminal app in System Settings".yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// Sorts scanned networks in the requested order
+///
+/// Ties are broken by signal strength, strongest first.
+fn sort_networks(networks: &mut [Network], order: ScanOrder) {
+    networks.sort_by(|a, b| {
+        let primary = match order {
+            ScanOrder::Signal => std::cmp::Ordering::Equal,
+            ScanOrder::Ssid => {
+                let name = |network: &Network| network.ssid.as_deref().map(str::to_lowercase);
+                // Hidden networks last
+                name(a).is_none().cmp(&name(b).is_none()).then(name(a).cmp(&name(b)))
+            }
+            ScanOrder::Channel => a.channel.cmp(&b.channel),
+        };
+        primary.then(b.rssi.cmp(&a.rssi))
+    });
+}
+
+/// Scans for Wi-Fi networks with CoreWLAN, falling back to `airport`
+///
+/// # Errors
+///
+/// Returns an error if no scan method works.
+fn scan_networks() -> Result<Vec<Network>, Box<dyn Error>> {
+    #[cfg(target_os = "macos")]
+    {
+        match corewlan::scan() {
+            Ok(networks) if networks.iter().any(|network| network.ssid.is_some()) => return Ok(networks),
+            // Names are redacted without Location Services access, airport may still reveal them
+            Ok(networks) => return Ok(airport_scan().unwrap_or(networks)),
+            Err(_) => {}
+        }
+    }
+
+    airport_scan()
+}
+
+/// Scans for Wi-Fi networks with the legacy `airport -s` command
+///
+/// # Errors
+///
+/// Returns an error if the command is not available or fails.
+fn airport_scan() -> Result<Vec<Network>, Box<dyn Error>> {
+    let output = Command::new(AIRPORT_PATH)
+        .arg("-s")
+        .output()
+        .map_err(|e| format!("Failed to run airport: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to scan for Wi-Fi networks: {}", stderr.trim()).into());
+    }
+
+    parse_airport_scan(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the output of `airport -s`
+///
+/// Each line holds the right-aligned SSID, BSSID, RSSI, channel (e.g. `36,+1` or
+/// `149,80`), HT flag, country code and security description.
+///
+/// # Errors
+///
+/// Returns an error if the output is not a scan result (e.g. a deprecation notice).
+fn parse_airport_scan(output: &str) -> Result<Vec<Network>, Box<dyn Error>> {
+    let bssid_regex = Regex::new(r"\s([0-9a-fA-F]{1,2}(?::[0-9a-fA-F]{1,2}){5})\s")?;
+
+    let mut lines = output.lines();
+    if !lines.next().is_some_and(|header| header.contains("BSSID")) {
+        return Err(format!("Unexpected airport output: {}", output.trim()).into());
+    }
+
+    let mut networks = Vec::new();
+    for line in lines {
+        let Some(bssid) = bssid_regex.captures(line).and_then(|captures| captures.get(1)) else {
+            continue;
+        };
+        let ssid = line[..bssid.start()].trim();
+        let mut fields = line[bssid.end()..].split_whitespace();
+        let (Some(rssi), Some(channel)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Ok(rssi) = rssi.parse() else {
+            continue;
+        };
+        let (channel, width) = parse_airport_channel(channel);
+        let security = fields.skip(2).collect::<Vec<_>>().join(" ");
+
+        networks.push(Network {
+            ssid: (!ssid.is_empty()).then(|| ssid.to_string()),
+            bssid: Some(bssid.as_str().to_string()),
+            rssi,
+            noise: None,
+            channel,
+            band: band_of_channel(channel),
+            channel_width: width,
+            security: if security == "NONE" {
+                "Open".to_string()
+            } else {
+                security
+            },
+        });
+    }
+
+    Ok(networks)
+}
+
+/// Parses an `airport` channel like `6`, `36,+1` (40 MHz) or `149,80` (80 MHz)
+///
+/// # Returns
+///
+/// The channel number and the channel width in MHz, if known.
+fn parse_airport_channel(channel: &str) -> (u32, Option<u32>) {
+    let (number, suffix) = channel.split_once(',').unwrap_or((channel, ""));
+    let width = match suffix {
+        "" => Some(20),
+        "+1" | "-1" => Some(40),
+        width => width.parse().ok(),
+    };
+    (number.parse().unwrap_or_default(), width)
+}
+
+/// Returns the frequency band of a channel number
+///
+/// Channels above 14 are assumed to be 5 GHz, as `airport` cannot report 6 GHz networks.
+fn band_of_channel(channel: u32) -> Option<&'static str> {
+    match channel {
+        1..=14 => Some("2.4 GHz"),
+        32..=177 => Some("5 GHz"),
+        _ => None,
+    }
+}
@@ -0,0 +1,294 @@
+//! CoreWLAN access through the Objective-C runtime
+//!
+//! CoreWLAN only offers an Objective-C API, whose classes are used with the
+//! message sending helpers of [`crate::objc`].
+
+use super::{KnownNetwork, LinkStatus, Network};
+use crate::objc::{
+    AutoreleasePool, Id, Sel, class, error_description, msg_send, ns_string, objects, selector, send_id, send_integer,
+    string,
+};
+use std::error::Error;
+use std::ptr;
+
+#[link(name = "CoreWLAN", kind = "framework")]
+unsafe extern "C" {}
+
+/// `CWErr` code of a timed out operation
+const TIMEOUT: isize = -3905;
+
+/// `CWErr` code of a failed authentication
+const AUTHENTICATION_FAILURE: isize = -3910;
+
+/// `CWErr` code of an invalid pairwise master key, i.e. a wrong password
+const INVALID_PMK: isize = -3924;
+
+/// Security types of `CWSecurity`, strongest first, with their display names
+const SECURITY_TYPES: &[(isize, &str)] = &[
+    (12, "WPA3 Enterprise"),
+    (9, "WPA2 Enterprise"),
+    (8, "WPA/WPA2 Enterprise"),
+    (7, "WPA Enterprise"),
+    (13, "WPA2/WPA3 Personal"),
+    (11, "WPA3 Personal"),
+    (4, "WPA2 Personal"),
+    (3, "WPA/WPA2 Personal"),
+    (2, "WPA Personal"),
+    (6, "Dynamic WEP"),
+    (1, "WEP"),
+    (15, "Enhanced Open Transition"),
+    (14, "Enhanced Open"),
+    (0, "Open"),
+];
+
+/// Scans for Wi-Fi networks with the default Wi-Fi interface
+///
+/// # Errors
+///
+/// Returns an error if CoreWLAN is not available, there is no Wi-Fi interface,
+/// or the scan fails (e.g. because Wi-Fi is turned off).
+pub fn scan() -> Result<Vec<Network>, Box<dyn Error>> {
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let interface = default_interface()?;
+        Ok(scan_objects(interface, ptr::null_mut())?
+            .into_iter()
+            .map(|object| network(object))
+            .collect())
+    }
+}
+
+/// Joins a Wi-Fi network with the default Wi-Fi interface
+///
+/// If several access points broadcast the network, the one with the strongest
+/// signal is joined.
+///
+/// # Arguments
+///
+/// * `ssid` - Name of the network
+/// * `password` - Password of the network; `None` for open networks
+///
+/// # Errors
+///
+/// Returns an error if the network is not in range or cannot be joined, with a
+/// hint if the authentication failed.
+pub fn associate(ssid: &str, password: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let interface = default_interface()?;
+        let target = scan_objects(interface, ns_string(ssid)?)?
+            .into_iter()
+            .max_by_key(|&object| send_integer(object, c"rssiValue"))
+            .ok_or_else(|| format!("Wi-Fi network '{ssid}' not found, see `rem wifi scan` for networks in range"))?;
+
+        let password = match password {
+            Some(password) => ns_string(password)?,
+            None => ptr::null_mut(),
+        };
+        let mut error: Id = ptr::null_mut();
+        let associate: unsafe extern "C" fn(Id, Sel, Id, Id, *mut Id) -> i8 = msg_send();
+        if associate(
+            interface,
+            selector(c"associateToNetwork:password:error:"),
+            target,
+            password,
+            &mut error,
+        ) == 0
+        {
+            let reason = match (send_integer(error, c"code"), password.is_null()) {
+                (AUTHENTICATION_FAILURE | INVALID_PMK | TIMEOUT, true) => {
+                    "the network requires a password, none is stored in the keychain (use --password)".to_string()
+                }
+                (AUTHENTICATION_FAILURE | INVALID_PMK, false) => {
+                    "authentication failed, check the password".to_string()
+                }
+                (TIMEOUT, false) => "timed out, the password may be wrong or the signal too weak".to_string(),
+                _ => error_description(error),
+            };
+            return Err(format!("Failed to join '{ssid}': {reason}").into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Disconnects the default Wi-Fi interface from its network
+///
+/// # Returns
+///
+/// The name of the network that was left, or `None` if no network was joined.
+///
+/// # Errors
+///
+/// Returns an error if there is no Wi-Fi interface.
+pub fn disassociate() -> Result<Option<String>, Box<dyn Error>> {
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let interface = default_interface()?;
+        let ssid = string(send_id(interface, c"ssid"));
+        let disassociate: unsafe extern "C" fn(Id, Sel) = msg_send();
+        disassociate(interface, selector(c"disassociate"));
+        Ok(ssid)
+    }
+}
+
+/// Returns the state of the current association of the default Wi-Fi interface
+///
+/// # Returns
+///
+/// The link state, or `None` if no network is joined.
+///
+/// # Errors
+///
+/// Returns an error if there is no Wi-Fi interface.
+pub fn link_status() -> Result<Option<LinkStatus>, Box<dyn Error>> {
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let interface = default_interface()?;
+        let rssi = send_integer(interface, c"rssiValue");
+        let channel = send_id(interface, c"wlanChannel");
+        if rssi == 0 || channel.is_null() {
+            return Ok(None);
+        }
+
+        let (channel, band, channel_width) = channel_properties(channel);
+        let noise = send_integer(interface, c"noiseMeasurement");
+        let transmit_rate: unsafe extern "C" fn(Id, Sel) -> f64 = msg_send();
+        Ok(Some(LinkStatus {
+            ssid: string(send_id(interface, c"ssid")),
+            bssid: string(send_id(interface, c"bssid")),
+            rssi: rssi as i64,
+            noise: (noise != 0).then_some(noise as i64),
+            tx_rate: transmit_rate(interface, selector(c"transmitRate")),
+            channel,
+            band,
+            channel_width,
+        }))
+    }
+}
+
+/// Returns the known networks of the default Wi-Fi interface in order of preference
+///
+/// # Errors
+///
+/// Returns an error if there is no Wi-Fi interface.
+pub fn known_networks() -> Result<Vec<KnownNetwork>, Box<dyn Error>> {
+    let _pool = AutoreleasePool::new();
+
+    unsafe {
+        let interface = default_interface()?;
+        let profiles = send_id(
+            send_id(send_id(interface, c"configuration"), c"networkProfiles"),
+            c"array",
+        );
+        Ok(objects(profiles)
+            .into_iter()
+            .filter_map(|profile| {
+                let security = send_integer(profile, c"security");
+                Some(KnownNetwork {
+                    ssid: string(send_id(profile, c"ssid"))?,
+                    security: SECURITY_TYPES
+                        .iter()
+                        .find(|(code, _)| *code == security)
+                        .map_or("Unknown", |(_, name)| name),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Scans for Wi-Fi networks, optionally restricted to a network name
+///
+/// # Returns
+///
+/// The found `CWNetwork` objects, valid until the enclosing autorelease pool is drained.
+///
+/// # Errors
+///
+/// Returns an error if the scan fails.
+unsafe fn scan_objects(interface: Id, name: Id) -> Result<Vec<Id>, Box<dyn Error>> {
+    unsafe {
+        let mut error: Id = ptr::null_mut();
+        let scan: unsafe extern "C" fn(Id, Sel, Id, *mut Id) -> Id = msg_send();
+        let networks = scan(interface, selector(c"scanForNetworksWithName:error:"), name, &mut error);
+        if networks.is_null() {
+            return Err(format!("Failed to scan for Wi-Fi networks: {}", error_description(error)).into());
+        }
+
+        Ok(objects(send_id(networks, c"allObjects")))
+    }
+}
+
+/// Returns the default Wi-Fi interface (`CWInterface`)
+///
+/// # Errors
+///
+/// Returns an error if CoreWLAN is not available or there is no Wi-Fi interface.
+unsafe fn default_interface() -> Result<Id, Box<dyn Error>> {
+    unsafe {
+        let class = class(c"CWWiFiClient");
+        if class.is_null() {
+            return Err("CoreWLAN is not available".into());
+        }
+        let client = send_id(class, c"sharedWiFiClient");
+        let interface = send_id(client, c"interface");
+        if interface.is_null() {
+            return Err("No Wi-Fi interface found".into());
+        }
+        Ok(interface)
+    }
+}
+
+/// Converts a `CWNetwork` into a [`Network`]
+unsafe fn network(object: Id) -> Network {
+    unsafe {
+        let channel = send_id(object, c"wlanChannel");
+        let (number, band, width) = if channel.is_null() {
+            (0, None, None)
+        } else {
+            channel_properties(channel)
+        };
+
+        let supports: unsafe extern "C" fn(Id, Sel, isize) -> i8 = msg_send();
+        let security = SECURITY_TYPES
+            .iter()
+            .find(|(code, _)| supports(object, selector(c"supportsSecurity:"), *code) != 0)
+            .map_or("Unknown", |(_, name)| name);
+
+        let noise = send_integer(object, c"noiseMeasurement");
+        Network {
+            ssid: string(send_id(object, c"ssid")),
+            bssid: string(send_id(object, c"bssid")),
+            rssi: send_integer(object, c"rssiValue") as i64,
+            noise: (noise != 0).then_some(noise as i64),
+            channel: number,
+            band,
+            channel_width: width,
+            security: security.to_string(),
+        }
+    }
+}
+
+/// Returns the number, band and width in MHz of a `CWChannel`
+unsafe fn channel_properties(channel: Id) -> (u32, Option<&'static str>, Option<u32>) {
+    unsafe {
+        let band = match send_integer(channel, c"channelBand") {
+            1 => Some("2.4 GHz"),
+            2 => Some("5 GHz"),
+            3 => Some("6 GHz"),
+            _ => None,
+        };
+        let width = match send_integer(channel, c"channelWidth") {
+            1 => Some(20),
+            2 => Some(40),
+            3 => Some(80),
+            4 => Some(160),
+            _ => None,
+        };
+        (send_integer(channel, c"channelNumber").max(0) as u32, band, width)
+    }
+}
@@ -0,0 +1,215 @@
+//! Wake-on-LAN
+//!
+//! Wakes up a host by broadcasting a magic packet over UDP: six `0xff` bytes
+//! followed by the MAC address of the host repeated 16 times. The network card of
+//! a sleeping host listens for this pattern and powers the host on. Broadcasts to
+//! `255.255.255.255` only reach the network of the primary interface, so hosts on
+//! other networks need the broadcast address of their network (e.g.
+//! `192.168.1.255`) and a router forwarding it.
+//!
+//! Hosts can be saved under an alias in the `[wol.hosts]` table of the rempower
+//! configuration file and woken by name.
+
+use crate::cli::{WolArgs, WolCommands};
+use crate::config::{Config, WolHost};
+use colored::Colorize;
+use std::error::Error;
+use std::net::{Ipv4Addr, UdpSocket};
+
+/// Number of magic packets sent, as single UDP datagrams may get lost
+const PACKET_COUNT: usize = 3;
+
+/// Performs Wake-on-LAN operations
+///
+/// # Arguments
+///
+/// * `args` - Wake-on-LAN arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the target is neither a MAC address nor a saved alias, the
+/// packet cannot be sent, or the configuration cannot be read or written.
+pub fn perform(args: WolArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Some(WolCommands::List) => list_hosts(),
+        Some(WolCommands::Remove { name }) => remove_host(&name),
+        None => match args.target {
+            Some(target) => wake(&target, args.broadcast, args.port, args.alias),
+            None => Err("Missing MAC address or alias".into()),
+        },
+    }
+}
+
+/// Sends magic packets to a host given by MAC address or alias
+///
+/// # Arguments
+///
+/// * `target` - MAC address or alias of the host
+/// * `broadcast` - Broadcast address overriding the one saved with the alias
+/// * `port` - UDP port of the packets
+/// * `alias` - Name to save the MAC address and broadcast address under
+///
+/// # Errors
+///
+/// Returns an error if the target is neither a MAC address nor a saved alias, an
+/// alias is given for a target that is no MAC address, or sending fails.
+fn wake(target: &str, broadcast: Option<Ipv4Addr>, port: u16, alias: Option<String>) -> Result<(), Box<dyn Error>> {
+    let (mac, saved_broadcast) = match parse_mac(target) {
+        Some(mac) => (mac, None),
+        None => {
+            if alias.is_some() {
+                return Err(format!("Invalid MAC address '{target}', --alias needs a MAC address").into());
+            }
+            let config = Config::load()?;
+            let host = config
+                .wol
+                .hosts
+                .get(target)
+                .ok_or_else(|| unknown_host(target, &config))?;
+            let mac = parse_mac(&host.mac)
+                .ok_or_else(|| format!("Invalid MAC address '{}' saved for '{target}'", host.mac))?;
+            (mac, host.broadcast)
+        }
+    };
+    let broadcast = broadcast.or(saved_broadcast).unwrap_or(Ipv4Addr::BROADCAST);
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    let packet = magic_packet(mac);
+    for _ in 0..PACKET_COUNT {
+        socket
+            .send_to(&packet, (broadcast, port))
+            .map_err(|e| format!("Failed to send the magic packet to {broadcast}:{port}: {e}"))?;
+    }
+    println!(
+        "{} Sent magic packet to {} via {broadcast}:{port}",
+        "✓".green(),
+        format_mac(mac).bold()
+    );
+
+    if let Some(name) = alias {
+        add_host(name, mac, broadcast)?;
+    }
+
+    Ok(())
+}
+
+/// Saves a host under an alias, replacing an existing host of the same name
+///
+/// The broadcast address is only saved if it differs from `255.255.255.255`.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved.
+fn add_host(name: String, mac: [u8; 6], broadcast: Ipv4Addr) -> Result<(), Box<dyn Error>> {
+    let host = WolHost {
+        mac: format_mac(mac),
+        broadcast: Some(broadcast).filter(|broadcast| *broadcast != Ipv4Addr::BROADCAST),
+    };
+
+    let mut config = Config::load()?;
+    let replaced = config.wol.hosts.insert(name.clone(), host).is_some();
+    config.save(&["wol", "hosts"])?;
+
+    let action = if replaced { "Updated" } else { "Added" };
+    println!("{} host '{name}'", action.green());
+
+    Ok(())
+}
+
+/// Prints all saved hosts with their MAC and broadcast addresses
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded.
+fn list_hosts() -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+
+    if config.wol.hosts.is_empty() {
+        println!("No hosts saved. Save one with 'rem wol <MAC> --alias <NAME>'");
+        return Ok(());
+    }
+
+    for (name, host) in &config.wol.hosts {
+        match host.broadcast {
+            Some(broadcast) => println!("{name:>30} : {} via {broadcast}", host.mac),
+            None => println!("{name:>30} : {}", host.mac),
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes a saved host
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved, or the host does not exist.
+fn remove_host(name: &str) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load()?;
+    if config.wol.hosts.remove(name).is_none() {
+        return Err(unknown_host(name, &config));
+    }
+    config.save(&["wol", "hosts"])?;
+
+    println!("{} host '{name}'", "Removed".green());
+
+    Ok(())
+}
+
+/// Builds an error for an unknown alias listing the saved aliases
+fn unknown_host(name: &str, config: &Config) -> Box<dyn Error> {
+    let available: Vec<&str> = config.wol.hosts.keys().map(String::as_str).collect();
+    if available.is_empty() {
+        format!("'{name}' is neither a MAC address nor a saved host (no hosts saved)").into()
+    } else {
+        format!(
+            "'{name}' is neither a MAC address nor a saved host (saved: {})",
+            available.join(", ")
+        )
+        .into()
+    }
+}
+
+/// Builds a magic packet: six `0xff` bytes followed by the MAC address 16 times
+fn magic_packet(mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0xff; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac);
+    }
+    packet
+}
+
+/// Parses a MAC address like `00:11:32:ab:cd:ef`, `00-11-32-AB-CD-EF` or `001132abcdef`
+///
+/// # Returns
+///
+/// `None` if the text is no MAC address.
+fn parse_mac(text: &str) -> Option<[u8; 6]> {
+    let digits: String = if text.contains([':', '-']) {
+        let parts: Vec<&str> = text.split([':', '-']).collect();
+        if parts.len() != 6 || parts.iter().any(|part| part.len() != 2) {
+            return None;
+        }
+        parts.concat()
+    } else {
+        text.to_string()
+    };
+    if digits.len() != 12 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut mac = [0; 6];
+    for (i, byte) in mac.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(mac)
+}
+
+/// Formats a MAC address as lowercase hex digits separated by colons
+fn format_mac(mac: [u8; 6]) -> String {
+    mac.iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
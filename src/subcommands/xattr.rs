@@ -0,0 +1,354 @@
+//! Extended attributes and quarantine
+//!
+//! Shows the extended attributes of a file with their values decoded where
+//! their format is known: the quarantine with the app that downloaded the
+//! file and the address it came from, the download addresses Safari records,
+//! Finder tags and the time the file was last opened. Other values are shown
+//! as text or in hexadecimal.
+//!
+//! Files downloaded from the internet get the `com.apple.quarantine`
+//! attribute, and Gatekeeper asks before opening them or refuses binaries
+//! that are not notarized. `strip-quarantine` removes it from files and
+//! everything in folders, e.g. an app or a downloaded tool.
+//!
+//! # System Commands Used
+//!
+//! - `sqlite3 -readonly -json` - Read where a quarantined file was downloaded from
+
+use crate::cli::{XattrArgs, XattrCommands};
+use crate::config;
+use crate::util::{format_bytes, format_time};
+use crate::xattr::{get_xattr, list_xattr, plist, remove_xattr, set_xattr};
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Extended attribute of quarantined files
+const QUARANTINE: &str = "com.apple.quarantine";
+
+/// Quarantine flag set once the user allowed the file to open
+const USER_APPROVED: u32 = 0x0040;
+
+/// Database of the downloads of quarantined files, below the home folder
+const QUARANTINE_EVENTS: &str = "Library/Preferences/com.apple.LaunchServices.QuarantineEventsV2";
+
+/// Bytes of a value shown in hexadecimal unless `--hex` is given
+const PREVIEW: usize = 64;
+
+/// Bytes in a line of hexadecimal
+const HEX_LINE: usize = 16;
+
+/// Executes an extended attribute operation
+///
+/// # Arguments
+///
+/// * `args` - Xattr arguments from the command line
+///
+/// # Errors
+///
+/// Returns an error if the attributes of a file cannot be read or changed.
+pub fn perform(args: XattrArgs) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        XattrCommands::Show { path, hex } => show(&path, hex),
+        XattrCommands::StripQuarantine { paths } => strip_quarantine(&paths),
+        XattrCommands::Copy {
+            source,
+            target,
+            no_quarantine,
+        } => copy(&source, &target, no_quarantine),
+    }
+}
+
+/// Prints the extended attributes of a file with their values
+///
+/// # Arguments
+///
+/// * `path` - File or folder
+/// * `hex` - Print all values in hexadecimal
+///
+/// # Errors
+///
+/// Returns an error if the attributes cannot be read.
+fn show(path: &Path, hex: bool) -> Result<(), Box<dyn Error>> {
+    let names = list_xattr(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    if names.is_empty() {
+        println!("{}", "No extended attributes".dimmed());
+        return Ok(());
+    }
+    for name in names {
+        // Attributes removed since they were listed are left out
+        let Some(value) = get_xattr(path, &name).map_err(|e| format!("Failed to read {name}: {e}"))? else {
+            continue;
+        };
+        println!("{}  {}", name.bold(), format_bytes(value.len() as f64).dimmed());
+        let lines = if hex {
+            hexadecimal(&value, value.len())
+        } else {
+            describe(&name, &value)
+        };
+        for line in lines {
+            println!("  {line}");
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the value of an attribute into lines to show
+///
+/// # Arguments
+///
+/// * `name` - Name of the attribute
+/// * `value` - Value of the attribute
+fn describe(name: &str, value: &[u8]) -> Vec<String> {
+    if name == QUARANTINE
+        && let Some(lines) = quarantine(&String::from_utf8_lossy(value))
+    {
+        return lines;
+    }
+    // The time the file was last opened, as `struct timespec`
+    if name == "com.apple.lastuseddate#PS" && value.len() == 16 {
+        let mut seconds = [0; 8];
+        seconds.copy_from_slice(&value[..8]);
+        return vec![format!(
+            "Last opened {}",
+            format_time(i64::from_le_bytes(seconds), true)
+        )];
+    }
+    if value.starts_with(b"bplist") {
+        return match plist::decode(value) {
+            // Tags have their color number after a line break
+            Ok(strings) => strings
+                .iter()
+                .map(|string| string.split('\n').next().unwrap_or_default().to_string())
+                .collect(),
+            Err(_) => vec!["Binary property list".dimmed().to_string()],
+        };
+    }
+    if let Ok(text) = std::str::from_utf8(value)
+        && !text.is_empty()
+        && !text.chars().any(|c| c.is_control() && c != '\n' && c != '\t')
+    {
+        return text.lines().map(str::to_string).collect();
+    }
+    hexadecimal(value, PREVIEW)
+}
+
+/// Decodes the quarantine, `flags;time;agent;event` with hexadecimal flags and time
+///
+/// # Arguments
+///
+/// * `value` - Value of the quarantine attribute
+///
+/// # Returns
+///
+/// The lines to show, or `None` if the value has another format.
+fn quarantine(value: &str) -> Option<Vec<String>> {
+    let mut fields = value.split(';');
+    let flags = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let time = i64::from_str_radix(fields.next()?, 16).ok()?;
+    let agent = fields
+        .next()
+        .filter(|agent| !agent.is_empty())
+        .unwrap_or("an unknown app");
+    let event = fields.next().unwrap_or_default();
+
+    let approval = if flags & USER_APPROVED != 0 {
+        "opening allowed".green()
+    } else {
+        "not opened yet".yellow()
+    };
+    let mut lines = vec![format!(
+        "Quarantined by {agent} on {}, {approval}",
+        format_time(time, true)
+    )];
+    if let Some((data, origin)) = download(event) {
+        lines.extend(data.map(|data| format!("Downloaded from {data}")));
+        lines.extend(origin.map(|origin| format!("Linked from {origin}")));
+    }
+    Some(lines)
+}
+
+/// Looks up the addresses of a download in the quarantine events of the user
+///
+/// # Arguments
+///
+/// * `event` - Identifier of the download in the quarantine attribute
+///
+/// # Returns
+///
+/// The address of the file and of the page linking to it, or `None` if the
+/// download is not recorded.
+fn download(event: &str) -> Option<(Option<String>, Option<String>)> {
+    // The identifier is a UUID, which keeps the query safe
+    if event.is_empty() || !event.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+        return None;
+    }
+    let database = config::home_dir().ok()?.join(QUARANTINE_EVENTS);
+    if !database.is_file() {
+        return None;
+    }
+    let output = Command::new("sqlite3")
+        .args(["-readonly", "-json"])
+        .arg(&database)
+        .arg(format!(
+            "SELECT LSQuarantineDataURLString AS data, LSQuarantineOriginURLString AS origin \
+             FROM LSQuarantineEvent WHERE LSQuarantineEventIdentifier = '{event}' COLLATE NOCASE"
+        ))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let row = rows.get(0)?;
+    let address = |key: &str| {
+        row.get(key)
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    Some((address("data"), address("origin")))
+}
+
+/// Formats bytes in hexadecimal, 16 per line
+///
+/// # Arguments
+///
+/// * `value` - Bytes to format
+/// * `limit` - Number of bytes to show; the rest is counted
+fn hexadecimal(value: &[u8], limit: usize) -> Vec<String> {
+    let mut lines: Vec<String> = value[..value.len().min(limit)]
+        .chunks(HEX_LINE)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    if value.len() > limit {
+        lines.push(format!("… {} more bytes", value.len() - limit).dimmed().to_string());
+    }
+    lines
+}
+
+/// Removes the quarantine of files and of everything in folders
+///
+/// # Arguments
+///
+/// * `paths` - Files, apps and folders
+///
+/// # Errors
+///
+/// Returns an error if the quarantine cannot be removed from a file.
+fn strip_quarantine(paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    let mut failed = 0;
+    for path in paths {
+        print!("Remove the quarantine of {}", path.display());
+        io::stdout().flush()?;
+        let mut stripped = 0;
+        let mut errors = Vec::new();
+        let walked = walk(path, &mut |file| match get_xattr(file, QUARANTINE) {
+            Ok(None) => {}
+            Ok(Some(_)) => match remove_xattr(file, QUARANTINE) {
+                Ok(()) => stripped += 1,
+                Err(e) => errors.push(format!("{}: {e}", file.display())),
+            },
+            Err(e) => errors.push(format!("{}: {e}", file.display())),
+        });
+        if let Err(e) = walked {
+            errors.push(format!("Failed to read {}: {e}", path.display()));
+        }
+
+        if let Some(error) = errors.first() {
+            failed += 1;
+            let count = if errors.len() == 1 {
+                String::new()
+            } else {
+                format!(" (and {} more)", errors.len() - 1)
+            };
+            println!("{}", format!(" {error}{count}").red());
+        } else if stripped == 0 {
+            println!("{}", " not quarantined".yellow());
+        } else {
+            let files = if stripped == 1 { "file" } else { "files" };
+            println!("{}", format!(" OK, {stripped} {files}").green());
+        }
+    }
+    if failed > 0 {
+        return Err(format!("Failed to remove the quarantine of {failed} of {} paths", paths.len()).into());
+    }
+    Ok(())
+}
+
+/// Calls a function for a path and everything below it if it is a folder
+///
+/// Symbolic links are visited, but not followed.
+///
+/// # Errors
+///
+/// Returns an error if the path or a folder below it cannot be read.
+fn walk(path: &Path, visit: &mut dyn FnMut(&Path)) -> io::Result<()> {
+    let metadata = path.symlink_metadata()?;
+    visit(path);
+    if metadata.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)?.flatten().map(|entry| entry.path()).collect();
+        entries.sort();
+        for entry in entries {
+            walk(&entry, visit)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the extended attributes of a file to another one
+///
+/// # Arguments
+///
+/// * `source` - File to copy the attributes of
+/// * `target` - File to copy the attributes to
+/// * `no_quarantine` - Leave out the quarantine
+///
+/// # Errors
+///
+/// Returns an error if the attributes cannot be read or an attribute cannot
+/// be written.
+fn copy(source: &Path, target: &Path, no_quarantine: bool) -> Result<(), Box<dyn Error>> {
+    target
+        .symlink_metadata()
+        .map_err(|e| format!("Failed to open {}: {e}", target.display()))?;
+    let names: Vec<String> = list_xattr(source)
+        .map_err(|e| format!("Failed to read {}: {e}", source.display()))?
+        .into_iter()
+        .filter(|name| !(no_quarantine && name == QUARANTINE))
+        .collect();
+    if names.is_empty() {
+        println!("No extended attributes to copy");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for name in &names {
+        print!("Copy {name}");
+        io::stdout().flush()?;
+        let result = get_xattr(source, name).and_then(|value| match value {
+            Some(value) => set_xattr(target, name, &value),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "removed since it was listed")),
+        });
+        match result {
+            Ok(()) => println!("{}", " OK".green()),
+            Err(e) => {
+                failed += 1;
+                println!("{}", format!(" {e}").red());
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("Failed to copy {failed} of {} attributes", names.len()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,319 @@
+//! Helpers shared by the commands
+//!
+//! Formatting of sizes, counts, durations and times, matching names given on
+//! the command line, asking for confirmation, running commands with sudo and
+//! catching Ctrl-C.
+//!
+//! # System Commands Used
+//!
+//! - `date` - Current local time of [`timestamp`]
+//! - `sudo` - Run commands requiring administrator rights
+
+use colored::Colorize;
+use std::error::Error;
+use std::ffi::{c_char, c_int};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::mem;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+unsafe extern "C" {
+    fn localtime_r(time: *const i64, result: *mut Tm) -> *mut Tm;
+    fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+}
+
+/// Broken-down local time (`struct tm`)
+#[repr(C)]
+struct Tm {
+    sec: i32,
+    min: i32,
+    hour: i32,
+    day: i32,
+    month: i32,
+    year: i32,
+    weekday: i32,
+    year_day: i32,
+    dst: i32,
+    offset: i64,
+    zone: *const c_char,
+}
+
+/// Signal sent by Ctrl-C
+const SIGINT: c_int = 2;
+
+/// Signal sent by `kill`
+const SIGTERM: c_int = 15;
+
+/// Set by the signal handler when the process is asked to stop
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Formats a number of bytes with decimal units, e.g. `1.5 MB`
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+
+    if bytes < 1000.0 {
+        return format!("{bytes:.0} B");
+    }
+    let mut value = bytes / 1000.0;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Formats a number of things, e.g. `1 file` or `3 files`
+///
+/// # Arguments
+///
+/// * `count` - Number of things
+/// * `noun` - Singular name of a thing, made plural with an `s`
+pub fn format_count(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// Formats a duration in minutes, e.g. `45 min`, `1 h` or `1 h 30 min`
+pub fn format_minutes(minutes: u64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes} min"),
+        (hours, 0) => format!("{hours} h"),
+        (hours, minutes) => format!("{hours} h {minutes} min"),
+    }
+}
+
+/// Formats a duration as `H:MM:SS`
+pub fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Formats a time in seconds since 1970 as local date and time
+///
+/// # Arguments
+///
+/// * `seconds` - Seconds since 1970
+/// * `with_seconds` - Include the seconds
+pub fn format_time(seconds: i64, with_seconds: bool) -> String {
+    let mut tm: Tm = unsafe { mem::zeroed() };
+    if unsafe { localtime_r(&seconds, &mut tm) }.is_null() {
+        return seconds.to_string();
+    }
+    let date = format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        tm.year + 1900,
+        tm.month + 1,
+        tm.day,
+        tm.hour,
+        tm.min
+    );
+    if with_seconds {
+        format!("{date}:{:02}", tm.sec)
+    } else {
+        date
+    }
+}
+
+/// Returns the current local time as `YYYY-MM-DD HH:MM:SS`
+///
+/// Falls back to the seconds since the Unix epoch if `date` cannot be run.
+pub fn timestamp() -> String {
+    Command::new("date")
+        .arg("+%Y-%m-%d %H:%M:%S")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs().to_string())
+                .unwrap_or_default()
+        })
+}
+
+/// Parses the date of a timestamp (`YYYY-MM-DD ...`) into days since the Unix epoch
+pub fn parse_date(timestamp: &str) -> Option<i64> {
+    let mut parts = timestamp.get(..10)?.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days from civil date, counting years from March so leap days come last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+/// Parses a time of the day given as `HH:MM`
+///
+/// # Errors
+///
+/// Returns an error if the time is malformed or out of range.
+pub fn parse_time(time: &str) -> Result<(u8, u8), Box<dyn Error>> {
+    let invalid = || format!("Invalid time '{time}', expected HH:MM");
+    let (hour, minute) = time.split_once(':').ok_or_else(invalid)?;
+    let hour: u8 = hour.parse().map_err(|_| invalid())?;
+    let minute: u8 = minute.parse().map_err(|_| invalid())?;
+    if hour > 23 || minute > 59 {
+        return Err(invalid().into());
+    }
+    Ok((hour, minute))
+}
+
+/// Returns the index of the name matching a query
+///
+/// The query is matched case-insensitively: exactly, as part of the name, word
+/// by word, and finally as letters in the order they appear in the name, which
+/// matches initials like `mbps`. The first of these that matches decides; it
+/// must match a single name.
+///
+/// # Arguments
+///
+/// * `names` - Names to choose from
+/// * `query` - Name or part of the name
+///
+/// # Errors
+///
+/// Returns an error listing the names if more than one name matches.
+pub fn match_name(names: &[&str], query: &str) -> Result<Option<usize>, Box<dyn Error>> {
+    let lowercase = query.to_lowercase();
+    let words: Vec<&str> = lowercase.split_whitespace().collect();
+    let letters: Vec<char> = lowercase.chars().filter(|c| !c.is_whitespace()).collect();
+    let matchers: [&dyn Fn(&str) -> bool; 4] = [
+        &|name| name == lowercase,
+        &|name| name.contains(&lowercase),
+        &|name| words.iter().all(|word| name.contains(word)),
+        &|name| {
+            let mut chars = name.chars();
+            letters.iter().all(|letter| chars.any(|c| c == *letter))
+        },
+    ];
+
+    let lowercase_names: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+    for matcher in matchers {
+        let matching: Vec<usize> = (0..names.len())
+            .filter(|&index| matcher(&lowercase_names[index]))
+            .collect();
+        match matching.as_slice() {
+            [] => continue,
+            [index] => return Ok(Some(*index)),
+            _ => {
+                let names: Vec<&str> = matching.iter().map(|&index| names[index]).collect();
+                return Err(format!("'{query}' matches {}", names.join(", ")).into());
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Asks a yes/no question on the terminal
+///
+/// Without a terminal nothing is assumed; the commands asking take `--yes`
+/// instead.
+///
+/// # Returns
+///
+/// True if the answer is yes.
+///
+/// # Errors
+///
+/// Returns an error if stdin is not a terminal or cannot be read.
+pub fn confirm(question: &str) -> Result<bool, Box<dyn Error>> {
+    if !io::stdin().is_terminal() {
+        return Err(format!("{question} Use --yes to answer yes without a terminal").into());
+    }
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Runs a command with sudo and checks that it succeeded
+///
+/// # Arguments
+///
+/// * `command` - Program and arguments to run
+///
+/// # Returns
+///
+/// The standard output of the command.
+///
+/// # Errors
+///
+/// Returns an error containing the command's stderr, or its stdout if stderr
+/// is empty, if it fails.
+pub fn run_sudo<S: AsRef<str>>(command: &[S]) -> Result<String, Box<dyn Error>> {
+    let command: Vec<&str> = command.iter().map(AsRef::as_ref).collect();
+    let output = Command::new("sudo")
+        .args(&command)
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {e}"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = if stderr.trim().is_empty() {
+            stdout.trim()
+        } else {
+            stderr.trim()
+        };
+        return Err(format!("Failed to run '{}': {message}", command.join(" ")).into());
+    }
+
+    Ok(stdout)
+}
+
+/// Prints a command that would be run with sudo in dry-run mode
+///
+/// Arguments containing whitespace or shell metacharacters are single-quoted,
+/// so the printed line can be copied into a shell as-is.
+pub fn print_dry_run(command: &[&str]) {
+    let quoted: Vec<String> = command.iter().map(|arg| shell_quote(arg)).collect();
+    println!("{} sudo {}", "[dry-run]".yellow(), quoted.join(" "));
+}
+
+/// Quotes a command line argument for POSIX shells if necessary
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Installs handlers recording Ctrl-C and `kill` instead of exiting
+///
+/// Afterwards, [`interrupted`] tells whether the process was asked to stop.
+pub fn catch_interrupts() {
+    unsafe {
+        signal(SIGINT, on_signal);
+        signal(SIGTERM, on_signal);
+    }
+}
+
+/// Returns true once Ctrl-C or `kill` was received after [`catch_interrupts`]
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Records that the process is asked to stop
+extern "C" fn on_signal(_signum: c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
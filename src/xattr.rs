@@ -0,0 +1,47 @@
+//! Extended attributes of files
+//!
+//! Reads and writes extended attributes with the system calls of macOS. Some
+//! of them, like Finder tags and the addresses a file was downloaded from,
+//! hold binary property lists, which [`plist`] encodes and decodes.
+
+pub mod plist;
+#[cfg(target_os = "macos")]
+mod syscalls;
+
+#[cfg(not(target_os = "macos"))]
+use std::io;
+#[cfg(not(target_os = "macos"))]
+use std::path::Path;
+
+#[cfg(target_os = "macos")]
+pub use syscalls::{get_xattr, list_xattr, remove_xattr, set_xattr};
+
+/// Lists the extended attributes of a file; requires macOS
+#[cfg(not(target_os = "macos"))]
+pub fn list_xattr(_path: &Path) -> io::Result<Vec<String>> {
+    Err(unsupported())
+}
+
+/// Reads an extended attribute; requires macOS
+#[cfg(not(target_os = "macos"))]
+pub fn get_xattr(_path: &Path, _name: &str) -> io::Result<Option<Vec<u8>>> {
+    Err(unsupported())
+}
+
+/// Writes an extended attribute; requires macOS
+#[cfg(not(target_os = "macos"))]
+pub fn set_xattr(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+    Err(unsupported())
+}
+
+/// Removes an extended attribute; requires macOS
+#[cfg(not(target_os = "macos"))]
+pub fn remove_xattr(_path: &Path, _name: &str) -> io::Result<()> {
+    Err(unsupported())
+}
+
+/// Error of the extended attribute functions on other systems
+#[cfg(not(target_os = "macos"))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "extended attributes require macOS")
+}
@@ -0,0 +1,276 @@
+//! Binary property lists with an array of strings
+//!
+//! Finder stores the tags of a file as a binary property list with an array
+//! of strings, and so does Safari with the addresses a file was downloaded
+//! from in `com.apple.metadata:kMDItemWhereFroms`. The format starts with
+//! `bplist00`, followed by the objects, a table with the offset of every object
+//! and a trailer with the sizes of the offsets and object references. Only
+//! arrays and strings are supported, which is all these attributes need.
+
+use std::error::Error;
+
+/// Magic number and version at the start of binary property lists
+const HEADER: &[u8] = b"bplist00";
+
+/// Size of the trailer at the end of binary property lists
+const TRAILER: usize = 32;
+
+/// Object type of arrays in the upper half of the marker byte
+const ARRAY: u8 = 0xA0;
+
+/// Object type of ASCII strings
+const ASCII: u8 = 0x50;
+
+/// Object type of UTF-16 strings
+const UTF16: u8 = 0x60;
+
+/// Object type of integers, which hold lengths of 15 and more
+const INTEGER: u8 = 0x10;
+
+/// Encodes strings as binary property list with an array
+///
+/// # Arguments
+///
+/// * `strings` - Strings of the array
+///
+/// # Returns
+///
+/// The property list.
+pub fn encode(strings: &[String]) -> Vec<u8> {
+    // The array is the first object, followed by the strings
+    let count = strings.len() + 1;
+    let reference_size = size_of_uint(count as u64 - 1);
+    let mut plist = HEADER.to_vec();
+    let mut offsets = vec![plist.len() as u64];
+    push_marker(&mut plist, ARRAY, strings.len());
+    for reference in 1..count {
+        push_uint(&mut plist, reference as u64, reference_size);
+    }
+    for string in strings {
+        offsets.push(plist.len() as u64);
+        if string.is_ascii() {
+            push_marker(&mut plist, ASCII, string.len());
+            plist.extend_from_slice(string.as_bytes());
+        } else {
+            let units: Vec<u16> = string.encode_utf16().collect();
+            push_marker(&mut plist, UTF16, units.len());
+            for unit in units {
+                plist.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+    }
+
+    let table = plist.len() as u64;
+    let offset_size = size_of_uint(offsets.last().copied().unwrap_or_default());
+    for offset in offsets {
+        push_uint(&mut plist, offset, offset_size);
+    }
+    plist.extend_from_slice(&[0; 6]);
+    plist.push(offset_size as u8);
+    plist.push(reference_size as u8);
+    plist.extend_from_slice(&(count as u64).to_be_bytes());
+    plist.extend_from_slice(&0u64.to_be_bytes());
+    plist.extend_from_slice(&table.to_be_bytes());
+    plist
+}
+
+/// Decodes the strings of a binary property list with an array
+///
+/// # Arguments
+///
+/// * `plist` - The property list
+///
+/// # Returns
+///
+/// The strings of the array.
+///
+/// # Errors
+///
+/// Returns an error if the data is no binary property list or holds
+/// anything but an array of strings.
+pub fn decode(plist: &[u8]) -> Result<Vec<String>, Box<dyn Error>> {
+    let invalid = || "The attribute is no valid property list";
+    if !plist.starts_with(HEADER) || plist.len() < HEADER.len() + TRAILER {
+        return Err(invalid().into());
+    }
+    let trailer = &plist[plist.len() - TRAILER..];
+    let offset_size = trailer[6] as usize;
+    let reference_size = trailer[7] as usize;
+    let count = uint(&trailer[8..16]).ok_or_else(invalid)?;
+    let top = uint(&trailer[16..24]).ok_or_else(invalid)?;
+    let table = usize::try_from(uint(&trailer[24..32]).ok_or_else(invalid)?)?;
+
+    let offset = |object: u64| -> Option<usize> {
+        if object >= count {
+            return None;
+        }
+        let start = table.checked_add(usize::try_from(object).ok()?.checked_mul(offset_size)?)?;
+        uint(plist.get(start..start.checked_add(offset_size)?)?).and_then(|offset| usize::try_from(offset).ok())
+    };
+    let start = offset(top).ok_or_else(invalid)?;
+    let (marker, length, mut position) = object(plist, start).ok_or_else(invalid)?;
+    if marker != ARRAY {
+        return Err(invalid().into());
+    }
+
+    // The length comes from the data, every reference takes at least a byte of it
+    let mut strings = Vec::with_capacity(length.min(plist.len()));
+    for _ in 0..length {
+        let end = position.checked_add(reference_size).ok_or_else(invalid)?;
+        let reference = uint(plist.get(position..end).ok_or_else(invalid)?).ok_or_else(invalid)?;
+        position = end;
+        let start = offset(reference).ok_or_else(invalid)?;
+        let (marker, length, start) = object(plist, start).ok_or_else(invalid)?;
+        let string = match marker {
+            ASCII => {
+                let end = start.checked_add(length).ok_or_else(invalid)?;
+                String::from_utf8(plist.get(start..end).ok_or_else(invalid)?.to_vec())?
+            }
+            UTF16 => {
+                let end = length.checked_mul(2).and_then(|size| start.checked_add(size));
+                let bytes = plist.get(start..end.ok_or_else(invalid)?).ok_or_else(invalid)?;
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                String::from_utf16(&units)?
+            }
+            _ => return Err(invalid().into()),
+        };
+        strings.push(string);
+    }
+    Ok(strings)
+}
+
+/// Reads the type and length of an object
+///
+/// # Returns
+///
+/// The object type, the length and the position of the contents, or `None`
+/// if the object is cut off.
+fn object(plist: &[u8], start: usize) -> Option<(u8, usize, usize)> {
+    let marker = *plist.get(start)?;
+    let length = (marker & 0x0F) as usize;
+    if length < 0x0F {
+        return Some((marker & 0xF0, length, start.checked_add(1)?));
+    }
+    // Longer lengths follow as integer object
+    let integer = *plist.get(start.checked_add(1)?)?;
+    if integer & 0xF0 != INTEGER {
+        return None;
+    }
+    let size = 1usize.checked_shl(u32::from(integer & 0x0F))?;
+    let contents = start.checked_add(2)?;
+    let end = contents.checked_add(size)?;
+    let length = uint(plist.get(contents..end)?)?;
+    Some((marker & 0xF0, usize::try_from(length).ok()?, end))
+}
+
+/// Reads a big-endian unsigned integer of up to 8 bytes
+fn uint(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    Some(bytes.iter().fold(0, |value, byte| value << 8 | u64::from(*byte)))
+}
+
+/// Returns the number of bytes needed for an unsigned integer
+fn size_of_uint(value: u64) -> usize {
+    match value {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFFFF_FFFF => 4,
+        _ => 8,
+    }
+}
+
+/// Appends a big-endian unsigned integer of a given size
+fn push_uint(plist: &mut Vec<u8>, value: u64, size: usize) {
+    plist.extend_from_slice(&value.to_be_bytes()[8 - size..]);
+}
+
+/// Appends the marker of an object with its length
+fn push_marker(plist: &mut Vec<u8>, kind: u8, length: usize) {
+    if length < 0x0F {
+        plist.push(kind | length as u8);
+        return;
+    }
+    let size = size_of_uint(length as u64);
+    plist.push(kind | 0x0F);
+    plist.push(INTEGER | size.trailing_zeros() as u8);
+    push_uint(plist, length as u64, size);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    /// Builds a property list claiming an array and object count of the given length
+    fn array_of_length(length: u64) -> Vec<u8> {
+        let mut plist = HEADER.to_vec();
+        plist.push(ARRAY | 0x0F);
+        plist.push(INTEGER | 3);
+        plist.extend_from_slice(&length.to_be_bytes());
+        let table = plist.len() as u64;
+        plist.push(HEADER.len() as u8);
+        plist.extend_from_slice(&[0; 6]);
+        plist.extend_from_slice(&[1, 1]);
+        plist.extend_from_slice(&length.to_be_bytes());
+        plist.extend_from_slice(&0u64.to_be_bytes());
+        plist.extend_from_slice(&table.to_be_bytes());
+        plist
+    }
+
+    #[test]
+    fn round_trip_keeps_the_strings() {
+        for values in [
+            strings(&[]),
+            strings(&["Red\n6", "Work"]),
+            strings(&["Wichtig \u{1F4CC}", "Ünïcödé"]),
+            strings(&["a string longer than fifteen characters", ""]),
+            (0..300).map(|index| format!("tag {index}")).collect(),
+        ] {
+            assert_eq!(decode(&encode(&values)).unwrap(), values);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_other_data() {
+        assert!(decode(b"").is_err());
+        assert!(decode(b"bplist00").is_err());
+        assert!(decode(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><plist></plist>").is_err());
+    }
+
+    #[test]
+    fn decode_rejects_truncated_data() {
+        let plist = encode(&strings(&["Red\n6", "a string longer than fifteen characters"]));
+        for length in 0..plist.len() {
+            assert!(decode(&plist[..length]).is_err(), "accepted {length} bytes");
+        }
+    }
+
+    #[test]
+    fn decode_rejects_huge_lengths() {
+        assert!(decode(&array_of_length(1 << 40)).is_err());
+        assert!(decode(&array_of_length(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_out_of_range_offsets() {
+        let mut plist = encode(&strings(&["Red"]));
+        let trailer = plist.len() - TRAILER;
+        // Offset size of 8 bytes and an offset table at the end of the address space
+        plist[trailer + 6] = 8;
+        plist[trailer + 24..].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(decode(&plist).is_err());
+
+        let mut plist = encode(&strings(&["Red"]));
+        // Top object beyond the object count
+        plist[trailer + 16..trailer + 24].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(decode(&plist).is_err());
+    }
+}
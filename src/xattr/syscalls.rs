@@ -0,0 +1,185 @@
+//! System calls for extended attributes
+//!
+//! Lists, reads, writes and removes extended attributes of files with the
+//! system calls of macOS, which take a position and options unlike the ones
+//! of Linux. Symbolic links are not followed, so their own attributes are used.
+
+use std::ffi::{CString, c_char, c_void};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+unsafe extern "C" {
+    fn getxattr(
+        path: *const c_char,
+        name: *const c_char,
+        value: *mut c_void,
+        size: usize,
+        position: u32,
+        options: i32,
+    ) -> isize;
+    fn setxattr(
+        path: *const c_char,
+        name: *const c_char,
+        value: *const c_void,
+        size: usize,
+        position: u32,
+        options: i32,
+    ) -> i32;
+    fn removexattr(path: *const c_char, name: *const c_char, options: i32) -> i32;
+    fn listxattr(path: *const c_char, names: *mut c_char, size: usize, options: i32) -> isize;
+}
+
+/// `XATTR_NOFOLLOW`
+const XATTR_NOFOLLOW: i32 = 0x0001;
+
+/// `ENOATTR`, returned if a file does not have the attribute
+const ENOATTR: i32 = 93;
+
+/// `ERANGE`, returned if the attribute does not fit into the buffer
+const ERANGE: i32 = 34;
+
+/// Lists the extended attributes of a file
+///
+/// # Arguments
+///
+/// * `path` - File to list the attributes of
+///
+/// # Returns
+///
+/// The names of the attributes.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn list_xattr(path: &Path) -> io::Result<Vec<String>> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    loop {
+        let size = unsafe { listxattr(path.as_ptr(), ptr::null_mut(), 0, XATTR_NOFOLLOW) };
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut names = vec![0u8; size as usize];
+        let read = unsafe { listxattr(path.as_ptr(), names.as_mut_ptr().cast(), names.len(), XATTR_NOFOLLOW) };
+        if read >= 0 {
+            // The names are separated by null bytes
+            return Ok(names[..read as usize]
+                .split(|byte| *byte == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .collect());
+        }
+        // Attributes were added since the size was read
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(ERANGE) {
+            return Err(error);
+        }
+    }
+}
+
+/// Reads an extended attribute
+///
+/// # Arguments
+///
+/// * `path` - File to read the attribute of
+/// * `name` - Name of the attribute
+///
+/// # Returns
+///
+/// The value of the attribute, or `None` if the file does not have it.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn get_xattr(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let name = CString::new(name)?;
+    loop {
+        let size = unsafe { getxattr(path.as_ptr(), name.as_ptr(), ptr::null_mut(), 0, 0, XATTR_NOFOLLOW) };
+        if size < 0 {
+            return missing(io::Error::last_os_error());
+        }
+        let mut value = vec![0u8; size as usize];
+        let read = unsafe {
+            getxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+                0,
+                XATTR_NOFOLLOW,
+            )
+        };
+        if read >= 0 {
+            value.truncate(read as usize);
+            return Ok(Some(value));
+        }
+        // The attribute grew since its size was read
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(ERANGE) {
+            return missing(error);
+        }
+    }
+}
+
+/// Maps the error of a missing attribute to `None`
+fn missing(error: io::Error) -> io::Result<Option<Vec<u8>>> {
+    if error.raw_os_error() == Some(ENOATTR) {
+        Ok(None)
+    } else {
+        Err(error)
+    }
+}
+
+/// Writes an extended attribute, replacing its value if the file has it
+///
+/// # Arguments
+///
+/// * `path` - File to write the attribute of
+/// * `name` - Name of the attribute
+/// * `value` - Value of the attribute
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn set_xattr(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let name = CString::new(name)?;
+    if unsafe {
+        setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+            XATTR_NOFOLLOW,
+        )
+    } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Removes an extended attribute; a file without it is left as it is
+///
+/// # Arguments
+///
+/// * `path` - File to remove the attribute from
+/// * `name` - Name of the attribute
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn remove_xattr(path: &Path, name: &str) -> io::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let name = CString::new(name)?;
+    if unsafe { removexattr(path.as_ptr(), name.as_ptr(), XATTR_NOFOLLOW) } != 0 {
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(ENOATTR) {
+            return Err(error);
+        }
+    }
+    Ok(())
+}